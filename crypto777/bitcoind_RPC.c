@@ -32,6 +32,8 @@ void LP_free(void *ptr);
 char *LP_clonestr(char *str);*/
 
 int32_t bitcoind_RPC_inittime;
+// set by the application to route a request through a proxy (socks5h://127.0.0.1:9050 for tor), debugstr is the coin symbol for coin rpc
+char *(*bitcoind_proxyfunc)(char *debugstr,char *url);
 
 #if LIQUIDITY_PROVIDER
 #include <curl/curl.h>
@@ -153,6 +155,18 @@ static void bitcoind_init()
     }
 }
 
+static void bitcoind_setproxy(CURL *curl_handle,char *debugstr,char *url)
+{
+    char *proxy = 0;
+    if ( bitcoind_proxyfunc != 0 )
+        proxy = (*bitcoind_proxyfunc)(debugstr,url);
+    if ( proxy != 0 && proxy[0] != 0 )
+        curl_easy_setopt(curl_handle,CURLOPT_PROXY,proxy);
+#ifdef KEEPALIVE
+    else curl_easy_setopt(curl_handle,CURLOPT_PROXY,(char *)0); // the reused handle could still have the last proxy
+#endif
+}
+
 char *bitcoind_RPC(char **retstrp,char *debugstr,char *url,char *userpass,char *command,char *params,int32_t timeout)
 {
 #ifdef KEEPALIVE
@@ -187,6 +201,7 @@ try_again:
   	curl_easy_setopt(curl_handle,CURLOPT_USERAGENT,"mozilla/4.0");//"Mozilla/4.0 (compatible; )");
     curl_easy_setopt(curl_handle,CURLOPT_HTTPHEADER,	headers);
     curl_easy_setopt(curl_handle,CURLOPT_URL,		url);
+    bitcoind_setproxy(curl_handle,debugstr,url);
     if ( (0) )
     {
         init_string(&s);
@@ -334,6 +349,7 @@ try_again:
     curl_easy_setopt(curl_handle,CURLOPT_USERAGENT,"mozilla/4.0");//"Mozilla/4.0 (compatible; )");
     curl_easy_setopt(curl_handle,CURLOPT_HTTPHEADER,	headers);
     curl_easy_setopt(curl_handle,CURLOPT_URL,		url);
+    bitcoind_setproxy(curl_handle,debugstr,url);
     if ( (0) )
     {
         init_string(&s);
@@ -539,6 +555,7 @@ void *curl_post(CURL **cHandlep,char *url,char *userpass,char *postfields,char *
 	curl_easy_setopt(cHandle,CURLOPT_SSL_VERIFYPEER,0);
 	//curl_easy_setopt(cHandle,CURLOPT_SSLVERSION,1);
 	curl_easy_setopt(cHandle,CURLOPT_URL,url);
+    bitcoind_setproxy(cHandle,"curl_post",url);
   	curl_easy_setopt(cHandle,CURLOPT_CONNECTTIMEOUT,10);
     if ( userpass != 0 && userpass[0] != 0 )
         curl_easy_setopt(cHandle,CURLOPT_USERPWD,userpass);
@@ -108,6 +108,126 @@ int32_t LP_RTmetrics_blacklisted(bits256 pubkey)
     return(LP_bits256_find(LP_RTmetrics.blacklist,LP_RTmetrics.numblacklist,pubkey));
 }
 
+struct LP_exposure { bits256 pubkey; int64_t kmdvalue; uint32_t timestamp; } LP_exposures[LP_MAXEXPOSURES];
+int32_t LP_numexposures,LP_exposure_maxswaps; int64_t LP_exposure_maxkmd; uint32_t LP_exposure_window = LP_EXPOSURE_WINDOW;
+
+int64_t LP_exposure_calc(int32_t *numswapsp,bits256 pubkey)
+{
+    int32_t i,n; int64_t total = 0; uint32_t now = (uint32_t)time(NULL);
+    *numswapsp = 0;
+    n = (LP_numexposures < LP_MAXEXPOSURES) ? LP_numexposures : LP_MAXEXPOSURES;
+    for (i=0; i<n; i++)
+    {
+        if ( LP_exposures[i].timestamp+LP_exposure_window >= now && bits256_cmp(LP_exposures[i].pubkey,pubkey) == 0 )
+        {
+            total += LP_exposures[i].kmdvalue;
+            (*numswapsp)++;
+        }
+    }
+    return(total);
+}
+
+void _LP_exposure_add(bits256 pubkey,int64_t kmdvalue,uint32_t timestamp)
+{
+    struct LP_exposure *ep;
+    ep = &LP_exposures[LP_numexposures % LP_MAXEXPOSURES];
+    ep->pubkey = pubkey;
+    ep->kmdvalue = kmdvalue;
+    ep->timestamp = timestamp;
+    LP_numexposures++;
+}
+
+void LP_exposure_add(bits256 pubkey,int64_t kmdvalue)
+{
+    FILE *fp; char fname[512]; struct LP_exposure E;
+    memset(&E,0,sizeof(E));
+    E.pubkey = pubkey;
+    E.kmdvalue = kmdvalue;
+    E.timestamp = (uint32_t)time(NULL);
+    portable_mutex_lock(&LP_exposuremutex);
+    _LP_exposure_add(E.pubkey,E.kmdvalue,E.timestamp);
+    sprintf(fname,"%s/exposures",GLOBAL_DBDIR), OS_compatible_path(fname);
+    if ( (fp= fopen(fname,"ab")) != 0 )
+    {
+        fwrite(&E,1,sizeof(E),fp);
+        fclose(fp);
+    }
+    portable_mutex_unlock(&LP_exposuremutex);
+}
+
+void LP_exposure_load()
+{
+    FILE *fp; char fname[512]; struct LP_exposure E; uint32_t now = (uint32_t)time(NULL);
+    sprintf(fname,"%s/exposures",GLOBAL_DBDIR), OS_compatible_path(fname);
+    portable_mutex_lock(&LP_exposuremutex);
+    if ( (fp= fopen(fname,"rb")) != 0 )
+    {
+        while ( fread(&E,1,sizeof(E),fp) == sizeof(E) )
+        {
+            if ( E.timestamp+LP_exposure_window >= now )
+                _LP_exposure_add(E.pubkey,E.kmdvalue,E.timestamp);
+        }
+        fclose(fp);
+    }
+    portable_mutex_unlock(&LP_exposuremutex);
+    printf("loaded %d counterparty exposures within %u seconds\n",LP_numexposures,LP_exposure_window);
+}
+
+int32_t LP_exposure_check(bits256 pubkey,int64_t kmdvalue)
+{
+    int32_t numswaps; int64_t total; char str[65];
+    if ( LP_exposure_maxkmd <= 0 && LP_exposure_maxswaps <= 0 )
+        return(0);
+    if ( LP_RTmetrics_whitelisted(pubkey) >= 0 )
+        return(0);
+    portable_mutex_lock(&LP_exposuremutex);
+    total = LP_exposure_calc(&numswaps,pubkey);
+    portable_mutex_unlock(&LP_exposuremutex);
+    if ( LP_exposure_maxswaps > 0 && numswaps >= LP_exposure_maxswaps )
+    {
+        printf("%s has %d swaps in the last %u seconds, limit %d\n",bits256_str(str,pubkey),numswaps,LP_exposure_window,LP_exposure_maxswaps);
+        return(-1);
+    }
+    if ( LP_exposure_maxkmd > 0 && total+kmdvalue > LP_exposure_maxkmd )
+    {
+        printf("%s exposure %.8f + %.8f KMD exceeds limit %.8f\n",bits256_str(str,pubkey),dstr(total),dstr(kmdvalue),dstr(LP_exposure_maxkmd));
+        return(-2);
+    }
+    return(0);
+}
+
+void LP_exposure_limits(cJSON *argjson)
+{
+    if ( jobj(argjson,"counterparty_maxkmd") != 0 )
+        LP_exposure_maxkmd = jdouble(argjson,"counterparty_maxkmd") * SATOSHIDEN;
+    if ( jobj(argjson,"counterparty_maxswaps") != 0 )
+        LP_exposure_maxswaps = jint(argjson,"counterparty_maxswaps");
+    if ( jobj(argjson,"counterparty_window") != 0 && juint(argjson,"counterparty_window") != 0 )
+        LP_exposure_window = juint(argjson,"counterparty_window");
+}
+
+char *LP_counterparty_limits(cJSON *argjson)
+{
+    bits256 pubkey; int32_t numswaps; int64_t total; cJSON *retjson;
+    LP_exposure_limits(argjson);
+    retjson = cJSON_CreateObject();
+    jaddstr(retjson,"result","success");
+    jaddnum(retjson,"maxkmd",dstr(LP_exposure_maxkmd));
+    jaddnum(retjson,"maxswaps",LP_exposure_maxswaps);
+    jaddnum(retjson,"window",LP_exposure_window);
+    pubkey = jbits256(argjson,"pubkey");
+    if ( bits256_nonz(pubkey) != 0 )
+    {
+        portable_mutex_lock(&LP_exposuremutex);
+        total = LP_exposure_calc(&numswaps,pubkey);
+        portable_mutex_unlock(&LP_exposuremutex);
+        jaddbits256(retjson,"pubkey",pubkey);
+        jaddnum(retjson,"exposure",dstr(total));
+        jaddnum(retjson,"numswaps",numswaps);
+    }
+    return(jprint(retjson,1));
+}
+
 void LP_RTmetrics_swapsinfo(char *refbase,char *refrel,cJSON *swaps,int32_t numswaps)
 {
     int32_t i; char *base,*rel,*retstr; cJSON *item,*swapjson; bits256 srcpub,destpub; uint64_t aliceid,basesatoshis,relsatoshis; uint32_t requestid,quoteid; double price;
@@ -0,0 +1,271 @@
+/******************************************************************************
+ * Copyright © 2014-2018 The SuperNET Developers.                             *
+ *                                                                            *
+ * See the AUTHORS, DEVELOPER-AGREEMENT and LICENSE files at                  *
+ * the top-level directory of this distribution for the individual copyright  *
+ * holder information and the developer policies on copyright and licensing.  *
+ *                                                                            *
+ * Unless otherwise agreed in a custom licensing agreement, no part of the    *
+ * SuperNET software, including this file may be copied, modified, propagated *
+ * or distributed except according to the terms contained in the LICENSE file *
+ *                                                                            *
+ * Removal or modification of this copyright notice is prohibited.            *
+ *                                                                            *
+ ******************************************************************************/
+//
+//  LP_amount.c
+//  marketmaker
+//
+//  swap amounts as decimal strings in coin units, so an 18 decimal token amount goes through the quote, the pubkeys
+//  negotiation and the swap without being cut to satoshis. they are only turned into integers of the coin's smallest
+//  unit where a transaction is built or checked: satoshis for utxo coins, wei in the etomic builders
+//
+
+// the arithmetic is on digit strings of amount * 10^LP_AMOUNT_DECIMALS, most significant first without leading zeros
+#define LP_UNITS_MAXDIGITS 96
+
+void LP_units_trim(char *units)
+{
+    int32_t i = 0;
+    while ( units[i] == '0' && units[i+1] != 0 )
+        i++;
+    if ( i > 0 )
+        memmove(units,units+i,strlen(units+i)+1);
+    if ( units[0] == 0 )
+        strcpy(units,"0");
+}
+
+int32_t LP_units_cmp(char *a,char *b)
+{
+    int32_t alen = (int32_t)strlen(a),blen = (int32_t)strlen(b);
+    if ( alen != blen )
+        return(alen < blen ? -1 : 1);
+    return(strcmp(a,b));
+}
+
+void LP_units_add(char *sum,char *a,char *b)
+{
+    char tmp[LP_UNITS_MAXDIGITS+1]; int32_t i = (int32_t)strlen(a)-1,j = (int32_t)strlen(b)-1,n = LP_UNITS_MAXDIGITS,carry = 0;
+    tmp[n] = 0;
+    while ( (i >= 0 || j >= 0 || carry != 0) && n > 0 )
+    {
+        carry += (i >= 0 ? a[i--] - '0' : 0) + (j >= 0 ? b[j--] - '0' : 0);
+        tmp[--n] = '0' + (carry % 10);
+        carry /= 10;
+    }
+    strcpy(sum,&tmp[n]);
+    LP_units_trim(sum);
+}
+
+// a - b for a >= b
+void LP_units_sub(char *diff,char *a,char *b)
+{
+    char tmp[LP_UNITS_MAXDIGITS+1]; int32_t i = (int32_t)strlen(a)-1,j = (int32_t)strlen(b)-1,n = LP_UNITS_MAXDIGITS,d,borrow = 0;
+    tmp[n] = 0;
+    while ( i >= 0 && n > 0 )
+    {
+        d = (a[i--] - '0') - borrow - (j >= 0 ? b[j--] - '0' : 0);
+        if ( (borrow= (d < 0)) != 0 )
+            d += 10;
+        tmp[--n] = '0' + d;
+    }
+    strcpy(diff,&tmp[n]);
+    LP_units_trim(diff);
+}
+
+void LP_units_mul(char *prod,char *a,uint32_t m)
+{
+    char tmp[LP_UNITS_MAXDIGITS+1]; int32_t i = (int32_t)strlen(a)-1,n = LP_UNITS_MAXDIGITS; uint64_t carry = 0;
+    tmp[n] = 0;
+    while ( (i >= 0 || carry != 0) && n > 0 )
+    {
+        carry += (uint64_t)(i >= 0 ? a[i--] - '0' : 0) * m;
+        tmp[--n] = '0' + (carry % 10);
+        carry /= 10;
+    }
+    strcpy(prod,&tmp[n]);
+    LP_units_trim(prod);
+}
+
+// truncating a / d, returns the remainder
+uint32_t LP_units_div(char *quot,char *a,uint32_t d)
+{
+    char tmp[LP_UNITS_MAXDIGITS+1]; int32_t i,n = 0; uint64_t rem = 0;
+    for (i=0; a[i]!=0 && n<LP_UNITS_MAXDIGITS; i++)
+    {
+        rem = rem*10 + (a[i] - '0');
+        tmp[n++] = '0' + (rem / d);
+        rem %= d;
+    }
+    tmp[n] = 0;
+    strcpy(quot,tmp);
+    LP_units_trim(quot);
+    return((uint32_t)rem);
+}
+
+// amountstr * 10^decimals, past that many decimals is truncated. -1 for anything but plain digits with an optional point
+int32_t LP_units_parse(char *units,char *amountstr,int32_t decimals)
+{
+    int32_t i,n = 0,dotflag = 0,frac = 0;
+    strcpy(units,"0");
+    if ( amountstr == 0 || strlen(amountstr) >= LP_AMOUNTSTRLEN || decimals < 0 || decimals > LP_AMOUNT_DECIMALS )
+        return(-1);
+    for (i=0; amountstr[i]!=0; i++)
+    {
+        if ( amountstr[i] == '.' && dotflag == 0 )
+            dotflag = 1;
+        else if ( amountstr[i] < '0' || amountstr[i] > '9' || (dotflag != 0 && ++frac > LP_AMOUNT_DECIMALS) )
+            return(-1);
+        else if ( dotflag == 0 || frac <= decimals )
+            units[n++] = amountstr[i];
+    }
+    if ( n == 0 )
+    {
+        strcpy(units,"0");
+        return(-1);
+    }
+    for (i=frac; i<decimals; i++)
+        units[n++] = '0';
+    units[n] = 0;
+    LP_units_trim(units);
+    return(0);
+}
+
+// units / 10^decimals without trailing zeros, the one spelling of an amount so two of them compare with strcmp
+int32_t LP_units_str(char *amountstr,char *units,int32_t decimals)
+{
+    int32_t i,len = (int32_t)strlen(units),n = 0;
+    strcpy(amountstr,"0");
+    if ( len > LP_AMOUNTSTRLEN - 3 )
+        return(-1);
+    if ( len <= decimals )
+        amountstr[n++] = '0';
+    else for (i=0; i<len-decimals; i++)
+        amountstr[n++] = units[i];
+    amountstr[n++] = '.';
+    for (i=len-decimals; i<len; i++)
+        amountstr[n++] = i < 0 ? '0' : units[i];
+    while ( amountstr[n-1] == '0' )
+        n--;
+    if ( amountstr[n-1] == '.' )
+        n--;
+    amountstr[n] = 0;
+    return(0);
+}
+
+int32_t LP_amount_canonical(char *dest,char *amountstr)
+{
+    char units[LP_UNITS_MAXDIGITS+1];
+    if ( LP_units_parse(units,amountstr,LP_AMOUNT_DECIMALS) < 0 )
+    {
+        strcpy(dest,"0");
+        return(-1);
+    }
+    return(LP_units_str(dest,units,LP_AMOUNT_DECIMALS));
+}
+
+// the 8 decimal satoshis of an amount, -1 if it isnt one or doesnt fit
+int64_t LP_amount_satoshis(char *amountstr)
+{
+    char units[LP_UNITS_MAXDIGITS+1];
+    if ( LP_units_parse(units,amountstr,8) < 0 || LP_units_cmp(units,"9223372036854775807") > 0 )
+        return(-1);
+    return((int64_t)strtoull(units,0,10));
+}
+
+void LP_amount_fromsatoshis(char *amountstr,int64_t satoshis)
+{
+    char units[32];
+    sprintf(units,"%lld",(long long)(satoshis < 0 ? 0 : satoshis));
+    LP_units_str(amountstr,units,8);
+}
+
+// dest is amountstr moved to exactly satoshis, keeping whatever amountstr has past the 8th decimal. the satoshis of a
+// quote get adjusted by utxo fitting, partial fills and txfees, its amount strings follow them this way
+int32_t LP_amount_align(char *dest,char *amountstr,int64_t satoshis)
+{
+    char units[LP_UNITS_MAXDIGITS+1],aligned[LP_UNITS_MAXDIGITS+1]; int32_t len;
+    if ( satoshis < 0 )
+    {
+        strcpy(dest,"0");
+        return(-1);
+    }
+    if ( LP_amount_satoshis(amountstr) < 0 )
+    {
+        LP_amount_fromsatoshis(dest,satoshis);
+        return(0);
+    }
+    LP_units_parse(units,amountstr,LP_AMOUNT_DECIMALS);
+    len = (int32_t)strlen(units);
+    sprintf(aligned,"%lld%010lld",(long long)satoshis,(long long)strtoull(len > LP_AMOUNT_DECIMALS-8 ? &units[len-(LP_AMOUNT_DECIMALS-8)] : units,0,10));
+    LP_units_trim(aligned);
+    return(LP_units_str(dest,aligned,LP_AMOUNT_DECIMALS));
+}
+
+// nonzero when amountstr has digits past satoshis, which only an etomic coin can send
+int32_t LP_amount_subsatoshi(char *amountstr)
+{
+    char satstr[LP_AMOUNTSTRLEN],canonical[LP_AMOUNTSTRLEN];
+    if ( LP_amount_canonical(canonical,amountstr) < 0 )
+        return(-1);
+    LP_amount_fromsatoshis(satstr,LP_amount_satoshis(canonical));
+    return(strcmp(satstr,canonical) != 0);
+}
+
+// each side derives its amounts from its own price representation, amounts within tolerance (or a satoshi) are the same trade
+int32_t LP_amount_agree(char *name,char *mine,char *other,double tolerance)
+{
+    char a[LP_UNITS_MAXDIGITS+1],b[LP_UNITS_MAXDIGITS+1],diff[LP_UNITS_MAXDIGITS+1],limit[LP_UNITS_MAXDIGITS+1];
+    if ( LP_units_parse(a,mine,LP_AMOUNT_DECIMALS) == 0 && LP_units_parse(b,other,LP_AMOUNT_DECIMALS) == 0 )
+    {
+        if ( LP_units_cmp(a,b) >= 0 )
+            LP_units_sub(diff,a,b);
+        else LP_units_sub(diff,b,a);
+        LP_units_mul(limit,a,(uint32_t)(tolerance * 1000000));
+        LP_units_div(limit,limit,1000000);
+        if ( LP_units_cmp(diff,"10000000000") <= 0 || LP_units_cmp(diff,limit) <= 0 )
+            return(0);
+    }
+    printf("%s amount %s vs counterparty %s outside dexfee tolerance %.4f\n",name,mine,other,tolerance);
+    return(-1);
+}
+
+// the wei an etomic builder sends or expects for a swap amount, its dexfee or the bob deposit, all on 18 decimals
+int32_t LP_amount_wei(char *weistr,char *amountstr,int32_t kind)
+{
+    char wei[LP_UNITS_MAXDIGITS+1],eighth[LP_UNITS_MAXDIGITS+1];
+    strcpy(weistr,"0");
+    if ( LP_units_parse(wei,amountstr,LP_AMOUNT_DECIMALS) < 0 || strcmp(wei,"0") == 0 )
+        return(-1);
+    if ( kind == LP_AMOUNT_DEXFEE )
+        LP_units_div(wei,wei,LP_dexfee_div);
+    else if ( kind == LP_AMOUNT_DEPOSIT )
+    {
+        LP_units_div(eighth,wei,8);
+        LP_units_add(wei,wei,eighth);
+    }
+    strcpy(weistr,wei);
+    return(0);
+}
+
+// a transaction's 0x hex value against the decimal wei it has to carry
+int32_t LP_amount_weiequal(char *valuehex,char *weistr)
+{
+    char value[LP_UNITS_MAXDIGITS+1],digit[16]; int32_t i,x;
+    if ( valuehex == 0 || weistr == 0 )
+        return(0);
+    if ( valuehex[0] == '0' && (valuehex[1] == 'x' || valuehex[1] == 'X') )
+        valuehex += 2;
+    if ( strlen(valuehex) > 64 )
+        return(0);
+    strcpy(value,"0");
+    for (i=0; valuehex[i]!=0; i++)
+    {
+        if ( (x= unhex(valuehex[i])) < 0 )
+            return(0);
+        LP_units_mul(value,value,16);
+        sprintf(digit,"%d",x);
+        LP_units_add(value,value,digit);
+    }
+    return(strcmp(value,weistr) == 0);
+}
@@ -0,0 +1,263 @@
+
+/******************************************************************************
+ * Copyright © 2014-2018 The SuperNET Developers.                             *
+ *                                                                            *
+ * See the AUTHORS, DEVELOPER-AGREEMENT and LICENSE files at                  *
+ * the top-level directory of this distribution for the individual copyright  *
+ * holder information and the developer policies on copyright and licensing.  *
+ *                                                                            *
+ * Unless otherwise agreed in a custom licensing agreement, no part of the    *
+ * SuperNET software, including this file may be copied, modified, propagated *
+ * or distributed except according to the terms contained in the LICENSE file *
+ *                                                                            *
+ * Removal or modification of this copyright notice is prohibited.            *
+ *                                                                            *
+ ******************************************************************************/
+//
+//  LP_backup.c
+//  marketmaker
+//
+//  export_swaps puts every swap in SWAPS/list with its tx files, the maker orders, GTC/orders and the swap utxo locks
+//  into one archive: {"version":N,"created":t,"files":{"SWAPS/1-2":"<hex>",...}} in a nacl box keyed from the
+//  passphrase, nonce first. import_swaps on a machine with the same passphrase puts it back like LP_legacy_import:
+//  swaps we already have are kept, unfinished ones go on the pending list so refunds and claims still happen, maker
+//  orders and GTC/orders are only taken when there are none and unexpired utxo locks are merged
+//
+
+bits256 LP_backup_privkey(bits256 *pubkeyp)
+{
+    uint8_t buf[32 + 16]; bits256 privkey;
+    memcpy(buf,G.LP_passhash.bytes,32);
+    memcpy(&buf[32],"swapbackup",10);
+    vcalc_sha256(0,privkey.bytes,buf,32 + 10);
+    privkey.bytes[0] &= 248, privkey.bytes[31] &= 127, privkey.bytes[31] |= 64;
+    *pubkeyp = curve25519(privkey,curve25519_basepoint9());
+    return(privkey);
+}
+
+int32_t LP_backup_addfile(cJSON *files,char *relname)
+{
+    char fname[512],*hexstr; uint8_t *data; long fsize;
+    sprintf(fname,"%s/%s",GLOBAL_DBDIR,relname), OS_compatible_path(fname);
+    if ( (data= OS_filestr(&fsize,fname)) == 0 )
+        return(0);
+    fsize--; // OS_filestr adds a 0
+    hexstr = malloc(fsize*2 + 1);
+    init_hexbytes_noT(hexstr,data,(int32_t)fsize);
+    jaddstr(files,relname,hexstr);
+    free(hexstr);
+    free(data);
+    return(1);
+}
+
+// 1 if relname was in the archive and is written, 0 if it wasnt there, -1 on error
+int32_t LP_backup_writefile(cJSON *files,char *relname)
+{
+    FILE *fp; char fname[512],tmpname[512],*hexstr; uint8_t *data; int32_t len;
+    if ( (hexstr= jstr(files,relname)) == 0 )
+        return(0);
+    if ( (len= (int32_t)strlen(hexstr)) == 0 || (len & 1) != 0 || is_hexstr(hexstr,0) != len )
+        return(-1);
+    len >>= 1;
+    data = malloc(len);
+    decode_hex(data,len,hexstr);
+    sprintf(fname,"%s/%s",GLOBAL_DBDIR,relname), OS_compatible_path(fname);
+    sprintf(tmpname,"%s.tmp",fname);
+    if ( (fp= fopen(tmpname,"wb")) == 0 || fwrite(data,1,len,fp) != len )
+    {
+        if ( fp != 0 )
+            fclose(fp);
+        free(data);
+        printf("swap import: cant write %s\n",tmpname);
+        return(-1);
+    }
+    fclose(fp);
+    free(data);
+    return(OS_renamefile(tmpname,fname) < 0 ? -1 : 1);
+}
+
+int32_t LP_backup_exists(char *relname)
+{
+    char fname[512],*fstr; long fsize;
+    sprintf(fname,"%s/%s",GLOBAL_DBDIR,relname), OS_compatible_path(fname);
+    if ( (fstr= OS_filestr(&fsize,fname)) == 0 )
+        return(0);
+    free(fstr);
+    return(1);
+}
+
+char *LP_export_swaps(char *filename)
+{
+    FILE *fp; char fname[512],tmpname[512],relname[512],*str; uint8_t *list,*sealed,*space; long fsize; uint32_t requestid,quoteid,now = (uint32_t)time(NULL); int32_t i,j,len,sealedlen,numswaps = 0; bits256 privkey,pubkey; cJSON *archive,*files,*retjson;
+    if ( bits256_nonz(G.LP_passhash) == 0 )
+        return(clonestr("{\"error\":\"no passphrase to key the backup\"}"));
+    files = cJSON_CreateObject();
+    sprintf(fname,"%s/SWAPS/list",GLOBAL_DBDIR), OS_compatible_path(fname);
+    if ( (list= OS_filestr(&fsize,fname)) != 0 )
+    {
+        for (i=0; i+sizeof(requestid)+sizeof(quoteid)<fsize; i+=sizeof(requestid)+sizeof(quoteid))
+        {
+            memcpy(&requestid,&list[i],sizeof(requestid));
+            memcpy(&quoteid,&list[i + sizeof(requestid)],sizeof(quoteid));
+            sprintf(relname,"SWAPS/%u-%u",requestid,quoteid);
+            if ( jobj(files,relname) != 0 || LP_backup_addfile(files,relname) == 0 )
+                continue;
+            numswaps++;
+            for (j=0; j<sizeof(txnames)/sizeof(*txnames); j++)
+            {
+                sprintf(relname,"SWAPS/%u-%u.%s",requestid,quoteid,txnames[j]);
+                LP_backup_addfile(files,relname);
+            }
+            sprintf(relname,"SWAPS/%u-%u.finished",requestid,quoteid);
+            LP_backup_addfile(files,relname);
+            sprintf(relname,"SWAPS/%u-%u.pubkeys",requestid,quoteid);
+            LP_backup_addfile(files,relname);
+        }
+        free(list);
+    }
+    LP_backup_addfile(files,"GTC/makerorders");
+    LP_backup_addfile(files,"GTC/orders");
+    LP_backup_addfile(files,"utxolocks");
+    archive = cJSON_CreateObject();
+    jaddnum(archive,"version",LP_SCHEMA_VERSION);
+    jaddnum(archive,"created",now);
+    jadd(archive,"files",files);
+    str = jprint(archive,1);
+    len = (int32_t)strlen(str) + 1;
+    sealed = malloc(crypto_box_NONCEBYTES + len + crypto_box_ZEROBYTES);
+    space = malloc(len + crypto_box_ZEROBYTES);
+    OS_randombytes(sealed,crypto_box_NONCEBYTES);
+    privkey = LP_backup_privkey(&pubkey);
+    sealedlen = crypto_box_NONCEBYTES + _SuperNET_cipher(sealed,&sealed[crypto_box_NONCEBYTES],(uint8_t *)str,len,pubkey,privkey,space);
+    free(space);
+    free(str);
+    if ( filename == 0 || filename[0] == 0 )
+    {
+        sprintf(fname,"%s/backups",GLOBAL_DBDIR), OS_ensure_directory(fname);
+        sprintf(fname,"%s/backups/swaps.%u.bak",GLOBAL_DBDIR,now);
+    } else safecopy(fname,filename,sizeof(fname));
+    OS_compatible_path(fname);
+    sprintf(tmpname,"%s.tmp",fname);
+    if ( (fp= fopen(tmpname,"wb")) == 0 || fwrite(sealed,1,sealedlen,fp) != sealedlen )
+    {
+        if ( fp != 0 )
+            fclose(fp);
+        free(sealed);
+        return(clonestr("{\"error\":\"cant write backup file\"}"));
+    }
+    fclose(fp);
+    free(sealed);
+    if ( OS_renamefile(tmpname,fname) < 0 )
+        return(clonestr("{\"error\":\"cant write backup file\"}"));
+    printf("exported %d swaps to %s\n",numswaps,fname);
+    retjson = cJSON_CreateObject();
+    jaddstr(retjson,"result","success");
+    jaddstr(retjson,"filename",fname);
+    jaddnum(retjson,"swaps",numswaps);
+    jaddnum(retjson,"bytes",sealedlen);
+    return(jprint(retjson,1));
+}
+
+// -1 on error, 0 if skipped, 1 imported and finished, 2 imported and still pending
+int32_t LP_backup_swap(cJSON *files,uint32_t requestid,uint32_t quoteid)
+{
+    char relname[512]; int32_t i,finished;
+    sprintf(relname,"SWAPS/%u-%u",requestid,quoteid);
+    if ( LP_backup_exists(relname) != 0 ) // already ours, never overwrite newer state with the backup
+        return(0);
+    for (i=0; i<sizeof(txnames)/sizeof(*txnames); i++)
+    {
+        sprintf(relname,"SWAPS/%u-%u.%s",requestid,quoteid,txnames[i]);
+        if ( LP_backup_writefile(files,relname) < 0 )
+            return(-1);
+    }
+    sprintf(relname,"SWAPS/%u-%u.finished",requestid,quoteid);
+    if ( (finished= LP_backup_writefile(files,relname)) < 0 )
+        return(-1);
+    sprintf(relname,"SWAPS/%u-%u.pubkeys",requestid,quoteid);
+    if ( LP_backup_writefile(files,relname) < 0 )
+        return(-1);
+    sprintf(relname,"SWAPS/%u-%u",requestid,quoteid); // main record last, a partial import can just be run again
+    if ( LP_backup_writefile(files,relname) <= 0 )
+        return(-1);
+    LP_swapsfp_update(requestid,quoteid);
+    if ( finished == 0 )
+    {
+        LP_pendswap_add(0,requestid,quoteid);
+        return(2);
+    }
+    return(1);
+}
+
+char *LP_import_swaps(char *filename)
+{
+    uint8_t *data,*space,*opened; char *hexstr,extra; long fsize; uint32_t requestid,quoteid; int32_t retval,len,numswaps = 0,numpending = 0,numerrs = 0,numlocks = 0,numorders = 0; bits256 privkey,pubkey; cJSON *archive,*files,*item,*retjson;
+    if ( filename == 0 || filename[0] == 0 )
+        return(clonestr("{\"error\":\"need filename\"}"));
+    if ( (data= OS_filestr(&fsize,filename)) == 0 )
+        return(clonestr("{\"error\":\"cant read backup file\"}"));
+    fsize--;
+    if ( fsize <= crypto_box_NONCEBYTES + crypto_box_ZEROBYTES )
+    {
+        free(data);
+        return(clonestr("{\"error\":\"not a swap backup\"}"));
+    }
+    space = malloc(fsize);
+    privkey = LP_backup_privkey(&pubkey);
+    archive = 0;
+    if ( (opened= _SuperNET_decipher(data,&data[crypto_box_NONCEBYTES],space,(int32_t)fsize - crypto_box_NONCEBYTES,pubkey,privkey)) != 0 )
+        archive = cJSON_Parse((char *)opened);
+    free(space);
+    free(data);
+    if ( archive == 0 )
+        return(clonestr("{\"error\":\"cant open backup, wrong passphrase or not a swap backup\"}"));
+    if ( juint(archive,"version") > LP_SCHEMA_VERSION )
+    {
+        free_json(archive);
+        return(clonestr("{\"error\":\"backup written by a newer marketmaker\"}"));
+    }
+    if ( (files= jobj(archive,"files")) != 0 )
+    {
+        for (item=files->child; item!=0; item=item->next)
+        {
+            if ( item->string == 0 || sscanf(item->string,"SWAPS/%u-%u%c",&requestid,&quoteid,&extra) != 2 )
+                continue;
+            if ( (retval= LP_backup_swap(files,requestid,quoteid)) < 0 )
+                numerrs++;
+            else if ( retval > 0 )
+            {
+                numswaps++;
+                if ( retval == 2 )
+                    numpending++;
+            }
+        }
+        if ( (hexstr= jstr(files,"utxolocks")) != 0 && (len= (int32_t)strlen(hexstr)) > 0 && (len & 1) == 0 && is_hexstr(hexstr,0) == len )
+        {
+            data = malloc(len >> 1);
+            decode_hex(data,len >> 1,hexstr);
+            numlocks = LP_utxolocks_merge(data,len >> 1,juint(archive,"version"));
+            free(data);
+        }
+        if ( LP_backup_exists("GTC/orders") == 0 )
+            LP_backup_writefile(files,"GTC/orders");
+        portable_mutex_lock(&LP_myordersmutex);
+        if ( LP_nummyorders == 0 && LP_backup_exists("GTC/makerorders") == 0 && LP_backup_writefile(files,"GTC/makerorders") > 0 )
+        {
+            item = cJSON_CreateObject();
+            LP_myorders_init(item);
+            free_json(item);
+            numorders = LP_nummyorders;
+        }
+        portable_mutex_unlock(&LP_myordersmutex);
+    }
+    free_json(archive);
+    printf("swap import from %s: %d swaps, %d pending refund or claim, %d errors, %d utxo locks, %d maker orders\n",filename,numswaps,numpending,numerrs,numlocks,numorders);
+    retjson = cJSON_CreateObject();
+    jaddstr(retjson,"result",numerrs == 0 ? "success" : "partial");
+    jaddnum(retjson,"swaps",numswaps);
+    jaddnum(retjson,"pending",numpending);
+    jaddnum(retjson,"errors",numerrs);
+    jaddnum(retjson,"utxolocks",numlocks);
+    jaddnum(retjson,"makerorders",numorders);
+    return(jprint(retjson,1));
+}
@@ -1979,6 +1979,18 @@ int32_t bitcoin_checklocktimeverify(uint8_t *script,int32_t n,uint32_t locktime)
     return(n);
 }
 
+// BIP68 relative lock in 512 second units, the type flag keeps the top byte nonzero so 3 bytes is a minimal push
+int32_t bitcoin_checksequenceverify(uint8_t *script,int32_t n,uint32_t sequence)
+{
+    script[n++] = 3;
+    script[n++] = sequence & 0xff, sequence >>= 8;
+    script[n++] = sequence & 0xff, sequence >>= 8;
+    script[n++] = sequence & 0xff;
+    script[n++] = SCRIPT_OP_CHECKSEQUENCEVERIFY;
+    script[n++] = SCRIPT_OP_DROP;
+    return(n);
+}
+
 int32_t bitcoin_timelockspend(uint8_t *script,int32_t n,uint8_t rmd160[20],uint32_t timestamp)
 {
     n = bitcoin_checklocktimeverify(script,n,timestamp);
@@ -2035,14 +2047,9 @@ int32_t bitcoin_p2shscript(uint8_t *script,int32_t n,const uint8_t *p2shscript,c
 
 char *bitcoind_passthrut(char *coinstr,char *serverport,char *userpass,char *method,char *params,int32_t timeout)
 {
-    /*struct iguana_info *coin; char *retstr;
-    if ( (coin= LP_coinfind(coinstr)) != 0 )
-    {
-        portable_mutex_lock(&coin->curl_mutex);
-        retstr = bitcoind_RPCnew(coin->curl_handle,0,coinstr,serverport,userpass,method,params,timeout);
-        portable_mutex_unlock(&coin->curl_mutex);
-        return(retstr);
-    }*/
+    struct iguana_info *coin;
+    if ( (coin= LP_coinfind(coinstr)) != 0 && coin->electrum == 0 && strcmp(coin->serverport,serverport) == 0 )
+        return(LP_rpcpool_request(coin,method,params,timeout));
     return(bitcoind_RPC(0,coinstr,serverport,userpass,method,params,timeout));
 }
 
@@ -2057,6 +2064,7 @@ char *bitcoind_passthru(char *coinstr,char *serverport,char *userpass,char *meth
 bits256 bits256_calcaddrhash(char *symbol,uint8_t *serialized,int32_t  len)
 {
     bits256 hash;
+    symbol = LP_chainsymbol(symbol);
     memset(hash.bytes,0,sizeof(hash));
     if ( strcmp(symbol,"GRS") != 0 )
     {
@@ -2075,9 +2083,83 @@ bits256 bits256_calcaddrhash(char *symbol,uint8_t *serialized,int32_t  len)
     return(hash);
 }
 
+// coins json "cashaddr_prefix" enables cashaddr, BCH has it by default. the prefix is optional in a cashaddr
+char *LP_cashaddr_prefix(char *symbol)
+{
+    struct iguana_info *coin;
+    if ( (coin= LP_coinsearch(symbol)) != 0 && coin->cashaddr[0] != 0 )
+        return(coin->cashaddr);
+    return(strcmp(symbol,"BCH") == 0 ? "bitcoincash" : 0);
+}
+
+// coins json "bech32_hrp" enables native segwit addresses, they are accepted as destinations, our own stay base58
+char *LP_bech32_hrp(char *symbol)
+{
+    struct iguana_info *coin;
+    if ( (coin= LP_coinsearch(symbol)) != 0 && coin->bech32hrp[0] != 0 )
+        return(coin->bech32hrp);
+    return(0);
+}
+
+int32_t LP_addrformat(char *symbol,char *coinaddr)
+{
+    char *prefix; int32_t n;
+    if ( coinaddr == 0 || coinaddr[0] == 0 )
+        return(LP_ADDRFORMAT_BASE58);
+    if ( (prefix= LP_bech32_hrp(symbol)) != 0 && strncasecmp(coinaddr,prefix,(n= (int32_t)strlen(prefix))) == 0 && coinaddr[n] == '1' )
+        return(LP_ADDRFORMAT_BECH32);
+    if ( (prefix= LP_cashaddr_prefix(symbol)) != 0 )
+    {
+        if ( strncasecmp(coinaddr,prefix,(n= (int32_t)strlen(prefix))) == 0 && coinaddr[n] == ':' )
+            return(LP_ADDRFORMAT_CASHADDR);
+        if ( strchr(coinaddr,':') == 0 && strchr("qpQP",coinaddr[0]) != 0 && strlen(coinaddr) == 42 )
+            return(LP_ADDRFORMAT_CASHADDR);
+    }
+    return(LP_ADDRFORMAT_BASE58);
+}
+
+// witness version and program of a bech32 address, 0 if it doesnt decode for this coin
+int32_t LP_segwit_decode(char *symbol,int32_t *witverp,uint8_t *prog,int32_t *proglenp,char *coinaddr)
+{
+    char *hrp;
+    *proglenp = 0;
+    if ( (hrp= LP_bech32_hrp(symbol)) == 0 || segwit_addr_decode(witverp,prog,proglenp,hrp,coinaddr) == 0 )
+        return(0);
+    return(*proglenp);
+}
+
+int32_t LP_cashaddr_decode(char *symbol,uint8_t *addrtypep,uint8_t rmd160[20],char *coinaddr)
+{
+    uint8_t data5[128],rmd21[64]; char prefixaddr[128],hrp[64],*prefix,*ptr; int32_t i,len,len5; struct iguana_info *coin;
+    if ( (prefix= LP_cashaddr_prefix(symbol)) == 0 || strlen(coinaddr) + strlen(prefix) + 2 > sizeof(prefixaddr) )
+        return(0);
+    if ( (ptr= strchr(coinaddr,':')) != 0 )
+        coinaddr = ptr + 1;
+    sprintf(prefixaddr,"%s:",prefix);
+    for (i=0; coinaddr[i]!=0; i++) // cashaddr is case insensitive, the checksum is over lowercase
+        prefixaddr[strlen(prefix)+1+i] = tolower(coinaddr[i]);
+    prefixaddr[strlen(prefix)+1+i] = 0;
+    if ( bech32_decode(hrp,data5,&len5,prefixaddr) == 0 )
+    {
+        printf("LP_cashaddr_decode bech32_decode error.(%s)\n",prefixaddr);
+        return(0);
+    }
+    len = 0;
+    if ( bech32_convert_bits(rmd21,&len,8,data5,len5,5,0) == 0 || len != 21 )
+    {
+        printf("LP_cashaddr_decode unsupported payload len.%d (%s)\n",len,prefixaddr);
+        return(0);
+    }
+    if ( (coin= LP_coinsearch(symbol)) != 0 )
+        *addrtypep = rmd21[0] == 0 ? coin->pubtype : coin->p2shtype;
+    else *addrtypep = rmd21[0] == 0 ? 0 : 5;
+    memcpy(rmd160,&rmd21[1],20);
+    return(20);
+}
+
 int32_t bitcoin_addr2rmd160(char *symbol,uint8_t taddr,uint8_t *addrtypep,uint8_t rmd160[20],char *coinaddr)
 {
-    bits256 hash; uint8_t *buf,_buf[26],data5[128],rmd21[21]; char prefixaddr[64],hrp[64]; int32_t len,len5,offset;
+    bits256 hash; uint8_t *buf,_buf[26],prog[40]; int32_t len,offset,witver,proglen; struct iguana_info *coin;
     *addrtypep = 0;
     memset(rmd160,0,20);
     if ( coinaddr == 0 || coinaddr[0] == 0 )
@@ -2087,25 +2169,18 @@ int32_t bitcoin_addr2rmd160(char *symbol,uint8_t taddr,uint8_t *addrtypep,uint8_
         decode_hex(rmd160,20,coinaddr+2); // not rmd160 hash but hopefully close enough;
         return(20);
     }
-    if ( strcmp(symbol,"BCH") == 0 )//&& strlen(coinaddr) == 42 )
+    switch ( LP_addrformat(symbol,coinaddr) )
     {
-        char *bchprefix = "bitcoincash:";
-        if ( strncmp(coinaddr,bchprefix,strlen(bchprefix)) != 0 )
-        {
-            strcpy(prefixaddr,bchprefix);
-            strcat(prefixaddr,coinaddr);
-        } else strcpy(prefixaddr,coinaddr);
-        if ( bech32_decode(hrp,data5,&len5,prefixaddr) == 0 )
-        {
-            printf("bitcoin_addr2rmd160 bech32_decode error.(%s)\n",prefixaddr);
+        case LP_ADDRFORMAT_CASHADDR:
+            return(LP_cashaddr_decode(symbol,addrtypep,rmd160,coinaddr));
+        case LP_ADDRFORMAT_BECH32: // only p2wpkh has an rmd160, scripts need LP_address_script
+            if ( LP_segwit_decode(symbol,&witver,prog,&proglen,coinaddr) == 20 && witver == 0 && (coin= LP_coinsearch(symbol)) != 0 )
+            {
+                *addrtypep = coin->pubtype;
+                memcpy(rmd160,prog,20);
+                return(20);
+            }
             return(0);
-        }
-        len = 0;
-        if ( bech32_convert_bits(rmd21,&len,8,data5,len5,5,0) == 0 )
-            printf("error converting data5\n");
-        *addrtypep = rmd21[0] == 0 ? 0 : 5;
-        memcpy(rmd160,&rmd21[1],20);
-        return(20);
     }
     offset = 1 + (taddr != 0);
     memset(rmd160,0,20);
@@ -2122,7 +2197,7 @@ int32_t bitcoin_addr2rmd160(char *symbol,uint8_t taddr,uint8_t *addrtypep,uint8_
             //printf("coinaddr.(%s) valid checksum addrtype.%02x\n",coinaddr,*addrtypep);
             return(20);
         }
-        else if ( (strcmp(symbol,"GRS") == 0 || strcmp(symbol,"SMART") == 0) && (buf[20+offset]&0xff) == hash.bytes[0] && (buf[21+offset]&0xff) == hash.bytes[1] && (buf[22+offset]&0xff) == hash.bytes[2] && (buf[23+offset]&0xff) == hash.bytes[3] )
+        else if ( (strcmp(LP_chainsymbol(symbol),"GRS") == 0 || strcmp(LP_chainsymbol(symbol),"SMART") == 0) && (buf[20+offset]&0xff) == hash.bytes[0] && (buf[21+offset]&0xff) == hash.bytes[1] && (buf[22+offset]&0xff) == hash.bytes[2] && (buf[23+offset]&0xff) == hash.bytes[3] )
             return(20);
         else if ( strcmp(symbol,"BTC") != 0 || *addrtypep == 0 || *addrtypep == 5 )
         {
@@ -2144,7 +2219,7 @@ char *bitcoin_address(char *symbol,char *coinaddr,uint8_t taddr,uint8_t addrtype
     if (strcmp(symbol, "ZECTEST") == 0 && addrtype == 186) {
         taddr = 28;
     }
-    int32_t offset,i,len5; char prefixed[64]; uint8_t data[64],data5[64],bigpubkey[65]; bits256 hash; struct iguana_info *coin;
+    int32_t offset,i,len5; char prefixed[64],*prefix; uint8_t data[64],data5[64],bigpubkey[65]; bits256 hash; struct iguana_info *coin;
 #ifndef NOTETOMIC
     if ( (coin= LP_coinfind(symbol)) != 0 && coin->etomic[0] != 0 )
     {
@@ -2184,14 +2259,14 @@ char *bitcoin_address(char *symbol,char *coinaddr,uint8_t taddr,uint8_t addrtype
         //printf(" rmd160\n");
     }
     else memcpy(data+offset,pubkey_or_rmd160,20);
-    if ( strcmp(symbol,"BCH") == 0 )
+    if ( (prefix= LP_cashaddr_prefix(symbol)) != 0 )
     {
         len5 = 0;
-        if ( addrtype == 0 )
+        if ( addrtype == ((coin= LP_coinsearch(symbol)) != 0 ? coin->pubtype : 0) )
             data[0] = (0 << 3);
         else data[0] = (1 << 3);
         bech32_convert_bits(data5,&len5,5,data,21,8,1);
-        if ( bech32_encode(prefixed,"bitcoincash",data5,len5) == 0 )
+        if ( bech32_encode(prefixed,prefix,data5,len5) == 0 )
             return(0);
         for (i=0; prefixed[i]!=0; i++)
             if ( prefixed[i] == ':' )
@@ -2207,7 +2282,7 @@ char *bitcoin_address(char *symbol,char *coinaddr,uint8_t taddr,uint8_t addrtype
         data[1] = addrtype;
     } else data[0] = addrtype;
     hash = bits256_calcaddrhash(symbol,data,20+offset);
-    if ( strcmp(symbol,"GRS") != 0 && strcmp(symbol,"SMART") != 0 )
+    if ( strcmp(LP_chainsymbol(symbol),"GRS") != 0 && strcmp(LP_chainsymbol(symbol),"SMART") != 0 )
     {
         for (i=0; i<4; i++)
             data[20+offset+i] = hash.bytes[31-i];
@@ -2234,22 +2309,46 @@ void bitcoin_priv2pub(void *ctx,char *symbol,uint8_t *pubkey33,char *coinaddr,bi
     bitcoin_address(symbol,coinaddr,taddr,addrtype,pubkey33,33);
 }
 
+// output script paying to coinaddr in any of the coins address formats, 0 if it isnt valid
+int32_t LP_address_script(uint8_t *script,char *symbol,uint8_t taddr,uint8_t pubtype,char *coinaddr)
+{
+    uint8_t addrtype,rmd160[20],prog[40]; int32_t witver,proglen;
+    if ( LP_addrformat(symbol,coinaddr) == LP_ADDRFORMAT_BECH32 )
+    {
+        if ( LP_segwit_decode(symbol,&witver,prog,&proglen,coinaddr) == 0 )
+            return(0);
+        script[0] = witver == 0 ? 0 : 0x50 + witver;
+        script[1] = proglen;
+        memcpy(&script[2],prog,proglen);
+        return(proglen + 2);
+    }
+    if ( bitcoin_addr2rmd160(symbol,taddr,&addrtype,rmd160,coinaddr) != 20 )
+        return(0);
+    if ( addrtype == pubtype )
+        return(bitcoin_standardspend(script,0,rmd160));
+    else return(bitcoin_p2shspend(script,0,rmd160));
+}
+
 int32_t bitcoin_validaddress(char *symbol,uint8_t taddr,uint8_t pubtype,uint8_t p2shtype,char *coinaddr)
 {
-    uint8_t rmd160[20],addrtype; char checkaddr[64],checkaddr2[64];
+    uint8_t rmd160[20],addrtype,script[64]; char checkaddr[64],checkaddr2[64]; int32_t format;
     if ( coinaddr == 0 || coinaddr[0] == 0 )
         return(-1);
-    else if ( bitcoin_addr2rmd160(symbol,taddr,&addrtype,rmd160,coinaddr) < 0 )
+    if ( (format= LP_addrformat(symbol,coinaddr)) == LP_ADDRFORMAT_BECH32 )
+        return(LP_address_script(script,symbol,taddr,pubtype,coinaddr) > 0 ? 0 : -1);
+    else if ( format == LP_ADDRFORMAT_CASHADDR && strchr(coinaddr,':') != 0 )
+        coinaddr = strchr(coinaddr,':') + 1;
+    if ( bitcoin_addr2rmd160(symbol,taddr,&addrtype,rmd160,coinaddr) < 0 )
     {
         printf("bitcoin_validaddress addr2rmd160 error\n");
         return(-1);
     }
     else if ( addrtype != pubtype && addrtype != p2shtype )
         return(-1);
-    else if ( bitcoin_address(symbol,checkaddr,addrtype,taddr,rmd160,sizeof(rmd160)) != checkaddr || strcmp(checkaddr,coinaddr) != 0 )
+    else if ( bitcoin_address(symbol,checkaddr,taddr,addrtype,rmd160,sizeof(rmd160)) != checkaddr || (format == LP_ADDRFORMAT_CASHADDR ? strcasecmp(checkaddr,coinaddr) : strcmp(checkaddr,coinaddr)) != 0 )
     {
         bitcoin_addr2rmd160(symbol,taddr,&addrtype,rmd160,coinaddr);
-        bitcoin_address(symbol,checkaddr2,addrtype,taddr,rmd160,sizeof(rmd160));
+        bitcoin_address(symbol,checkaddr2,taddr,addrtype,rmd160,sizeof(rmd160));
         printf("%s pubtype.%d taddr.%d bitcoin_validaddress checkaddr.%s != %s, checkaddr2.(%s)\n",symbol,pubtype,taddr,checkaddr,coinaddr,checkaddr2);
         return(-1);
     }
@@ -2272,7 +2371,7 @@ int32_t base58encode_checkbuf(char *symbol,uint8_t taddr,uint8_t addrtype,uint8_
     //for (i=0; i<32; i++)
     //    printf("%02x",hash.bytes[i]);
     //printf(" checkhash\n");
-    if ( strcmp(symbol,"GRS") != 0 && strcmp(symbol,"SMART") != 0 )
+    if ( strcmp(LP_chainsymbol(symbol),"GRS") != 0 && strcmp(LP_chainsymbol(symbol),"SMART") != 0 )
     {
         for (i=0; i<4; i++)
             data[data_len+i+offset] = hash.bytes[31-i];
@@ -2316,7 +2415,7 @@ int32_t bitcoin_wif2priv(char *symbol,uint8_t wiftaddr,uint8_t *addrtypep,bits25
             //printf("wifstr.(%s) valid len.%d\n",wifstr,len);
             return(32);
         }
-        else if ( (strcmp(symbol,"GRS") == 0 || strcmp(symbol,"SMART") == 0) && (ptr[len - 4]&0xff) == hash.bytes[0] && (ptr[len - 3]&0xff) == hash.bytes[1] &&(ptr[len - 2]&0xff) == hash.bytes[2] && (ptr[len - 1]&0xff) == hash.bytes[3] )
+        else if ( (strcmp(LP_chainsymbol(symbol),"GRS") == 0 || strcmp(LP_chainsymbol(symbol),"SMART") == 0) && (ptr[len - 4]&0xff) == hash.bytes[0] && (ptr[len - 3]&0xff) == hash.bytes[1] &&(ptr[len - 2]&0xff) == hash.bytes[2] && (ptr[len - 1]&0xff) == hash.bytes[3] )
             return(32);
         else if ( 0 ) // gets errors when len is 37
         {
@@ -2908,11 +3007,9 @@ cJSON *bitcoin_txoutput(cJSON *txobj,uint8_t *paymentscript,int32_t len,uint64_t
 
 int32_t bitcoin_txaddspend(char *symbol,uint8_t taddr,uint8_t pubtype,uint8_t p2shtype,cJSON *txobj,char *destaddress,uint64_t satoshis)
 {
-    uint8_t outputscript[128],addrtype,rmd160[20]; int32_t scriptlen;
-    if ( bitcoin_validaddress(symbol,taddr,pubtype,p2shtype,destaddress) == 0 && satoshis != 0 )
+    uint8_t outputscript[128]; int32_t scriptlen;
+    if ( bitcoin_validaddress(symbol,taddr,pubtype,p2shtype,destaddress) == 0 && satoshis != 0 && (scriptlen= LP_address_script(outputscript,symbol,taddr,pubtype,destaddress)) > 0 )
     {
-        bitcoin_addr2rmd160(symbol,taddr,&addrtype,rmd160,destaddress);
-        scriptlen = bitcoin_standardspend(outputscript,0,rmd160);
         bitcoin_txoutput(txobj,outputscript,scriptlen,satoshis);
         return(0);
     } else return(-1);
@@ -272,6 +272,51 @@ int32_t LP_merkleproof(struct iguana_info *coin,char *coinaddr,struct electrum_i
     return(SPV);
 }
 
+// merkle branch from the active server, merkle roots from the other ones so a single server cant fake inclusion
+int32_t LP_SPV_crosscheck(struct iguana_info *coin,bits256 txid,int32_t height)
+{
+    struct electrum_info *ep,*hdrep; struct LP_transaction *tx; cJSON *merkobj,*merkles,*hdrobj; bits256 roothash,merkleroot; int32_t m,numagree = 0,SPV = 0;
+    if ( (ep= coin->electrum) == 0 || height <= 0 )
+        return(0);
+    memset(roothash.bytes,0,sizeof(roothash));
+    if ( (merkobj= electrum_getmerkle(coin->symbol,ep,&merkobj,txid,height)) != 0 )
+    {
+        if ( (merkles= jarray(&m,merkobj,"merkle")) != 0 )
+            roothash = validate_merkle(jint(merkobj,"pos"),txid,merkles,m);
+        free_json(merkobj);
+    }
+    if ( bits256_nonz(roothash) == 0 )
+        return(0);
+    for (hdrep=ep->prev; hdrep!=0; hdrep=hdrep->prev)
+    {
+        if ( strcmp(hdrep->ipaddr,ep->ipaddr) == 0 )
+            continue;
+        memset(merkleroot.bytes,0,sizeof(merkleroot));
+        if ( (hdrobj= electrum_getheader(coin->symbol,hdrep,&hdrobj,height)) != 0 )
+        {
+            if ( jobj(hdrobj,"merkle_root") != 0 )
+                merkleroot = jbits256(hdrobj,"merkle_root");
+            free_json(hdrobj);
+        }
+        if ( bits256_nonz(merkleroot) == 0 )
+            continue;
+        if ( bits256_cmp(merkleroot,roothash) != 0 )
+        {
+            char str[65]; printf("SPV crosscheck mismatch %s %s ht.%d %s vs %s\n",coin->symbol,bits256_str(str,txid),height,ep->ipaddr,hdrep->ipaddr);
+            return(-1);
+        }
+        numagree++;
+    }
+    if ( numagree > 0 ) // the branch source plus at least one independent header source
+    {
+        SPV = height;
+        LP_SPV_store(coin,txid,height);
+        if ( (tx= LP_transactionfind(coin,txid)) != 0 )
+            tx->SPV = height;
+    }
+    return(SPV);
+}
+
 char *LP_unspents_filestr(char *symbol,char *addr)
 {
     char fname[1024]; long fsize;
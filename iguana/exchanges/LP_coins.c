@@ -22,6 +22,7 @@ char *portstrs[][3] = { { "BTC", "8332" }, { "KMD", "7771" } };
 
 int32_t LP_is_slowcoin(char *symbol)
 {
+    symbol = LP_chainsymbol(symbol);
     if ( strcmp(symbol,"BTC") == 0 )
         return(2);
     else if ( strcmp(symbol,"BCH") == 0 )
@@ -238,6 +239,8 @@ cJSON *LP_coinjson(struct iguana_info *coin,int32_t showwif)
         else jaddstr(item,"wif","error creating wif");
     }
     jadd(item,"installed",coin->userpass[0] == 0 ? jfalse() : jtrue());
+    if ( coin->testnet != 0 )
+        jaddstr(item,"testnet_of",coin->chain);
     if ( coin->userpass[0] != 0 )
     {
         jaddnum(item,"height",LP_getheight(&notarized,coin));
@@ -251,7 +254,7 @@ cJSON *LP_coinjson(struct iguana_info *coin,int32_t showwif)
     }
 #ifndef NOTETOMIC
     else if (coin->etomic[0] != 0) {
-        int error = 0;
+        int error = 0; char *contract;
         if (coin->inactive == 0) {
             balance = LP_etomic_get_balance(coin, coin->smartaddr, &error);
         } else {
@@ -259,6 +262,12 @@ cJSON *LP_coinjson(struct iguana_info *coin,int32_t showwif)
         }
         jaddnum(item,"height",-1);
         jaddnum(item,"balance",dstr(balance));
+        jaddstr(item,"alice_contract",LP_etomic_contract(coin->symbol,0));
+        jaddstr(item,"bob_contract",LP_etomic_contract(coin->symbol,1));
+        if ( (contract= LP_etomic_override(coin->symbol,0,1)) != 0 )
+            jaddstr(item,"fallback_alice_contract",contract);
+        if ( (contract= LP_etomic_override(coin->symbol,1,1)) != 0 )
+            jaddstr(item,"fallback_bob_contract",contract);
     }
 #endif
     else
@@ -284,6 +293,11 @@ cJSON *LP_coinjson(struct iguana_info *coin,int32_t showwif)
     jaddnum(item,"p2shtype",coin->p2shtype);
     jaddnum(item,"wiftype",coin->wiftype);
     jaddnum(item,"txfee",strcmp(coin->symbol,"BTC") != 0 ? coin->txfee : LP_txfeecalc(coin,0,0));
+    if ( coin->zeroconfmax != 0 )
+    {
+        jaddnum(item,"zeroconf_max_volume",dstr(coin->zeroconfmax));
+        jaddnum(item,"zeroconf_volume",dstr(coin->zeroconfvol));
+    }
     if ( strcmp(coin->symbol,"KMD") == 0 )
     {
         memset(zero.bytes,0,sizeof(zero));
@@ -361,6 +375,23 @@ struct iguana_info *LP_coinsearch(char *symbol)
     return(coin);
 }
 
+// a testnet/regtest variant trades under its own symbol but hashes addresses and estimates fees like its mainnet coin
+char *LP_chainsymbol(char *symbol)
+{
+    struct iguana_info *coin;
+    if ( symbol != 0 && (coin= LP_coinsearch(symbol)) != 0 && coin->chain[0] != 0 )
+        return(coin->chain);
+    return(symbol);
+}
+
+int32_t LP_testnet_mismatch(char *base,char *rel)
+{
+    struct iguana_info *basecoin,*relcoin;
+    if ( (basecoin= LP_coinsearch(base)) != 0 && (relcoin= LP_coinsearch(rel)) != 0 && basecoin->testnet != relcoin->testnet )
+        return(1);
+    return(0);
+}
+
 struct iguana_info *LP_coinadd(struct iguana_info *cdata)
 {
     struct iguana_info *coin = calloc(1,sizeof(*coin));
@@ -439,6 +470,7 @@ uint16_t LP_coininit(struct iguana_info *coin,char *symbol,char *name,char *asse
     }
     coin->curl_handle = curl_easy_init();
     portable_mutex_init(&coin->curl_mutex);
+    LP_rpcpool_init(coin);
     coin->decimals = decimals;
     if ( strcmp(symbol,"PART") == 0 ) {
         coin->txversion = 160;
@@ -452,6 +484,20 @@ uint16_t LP_coininit(struct iguana_info *coin,char *symbol,char *name,char *asse
     return(port);
 }
 
+// smallest amount that can be swapped on this coin without the payment being rejected as dust
+uint64_t LP_min_tradevol(struct iguana_info *coin)
+{
+    uint64_t minvol = LP_MIN_TRADEVOL;
+    if ( coin != 0 )
+    {
+        if ( coin->txfee * LP_MINSIZE_TXFEEMULT > minvol )
+            minvol = coin->txfee * LP_MINSIZE_TXFEEMULT;
+        if ( coin->mintradevol > minvol )
+            minvol = coin->mintradevol;
+    }
+    return(minvol);
+}
+
 int32_t LP_isdisabled(char *base,char *rel)
 {
     struct iguana_info *coin;
@@ -513,6 +559,205 @@ struct iguana_info *LP_coinfind(char *symbol)
     return(coin);
 }
 
+void LP_coinconf_error(cJSON *errors,int32_t i,char *symbol,char *field,char *problem)
+{
+    char errstr[512];
+    snprintf(errstr,sizeof(errstr),"coins[%d]%s%s%s.%s: %s",i,symbol != 0 ? "(" : "",symbol != 0 ? symbol : "",symbol != 0 ? ")" : "",field,problem);
+    jaddistr(errors,errstr);
+}
+
+int32_t LP_coinconf_range(cJSON *errors,int32_t i,char *symbol,cJSON *item,char *field,double minval,double maxval,int32_t required)
+{
+    cJSON *obj; double val; char problem[128];
+    if ( (obj= jobj(item,field)) == 0 )
+    {
+        if ( required != 0 )
+        {
+            LP_coinconf_error(errors,i,symbol,field,"required field missing");
+            return(-1);
+        }
+        return(0);
+    }
+    if ( is_cJSON_Number(obj) == 0 )
+    {
+        LP_coinconf_error(errors,i,symbol,field,"must be a number");
+        return(-1);
+    }
+    if ( (val= obj->valuedouble) < minval || val > maxval || (minval >= 0. && val != (double)(int64_t)val) )
+    {
+        sprintf(problem,"%.8g out of range, expected integer %.0f..%.0f",val,minval,maxval);
+        LP_coinconf_error(errors,i,symbol,field,problem);
+        return(-1);
+    }
+    return(0);
+}
+
+// returns a copy of coins with the testnet/regtest variants appended, the caller frees it
+// {"coin":"BTC",...,"testnet":{"rpcport":18332,"pubtype":111,"p2shtype":196,"wiftype":239}} adds BTC-TEST next to BTC,
+// "regtest" the same as BTC-REGTEST. fields given override the mainnet entry, "coin" overrides the generated symbol
+cJSON *LP_coins_variants(cJSON *coins)
+{
+    static char *kinds[][2] = { { "testnet", "-TEST" }, { "regtest", "-REGTEST" } };
+    cJSON *expanded,*item,*variant,*override,*child; int32_t i,k,n; char *symbol,vsymbol[64];
+    expanded = cJSON_CreateArray();
+    n = cJSON_GetArraySize(coins);
+    for (i=0; i<n; i++)
+    {
+        item = jitem(coins,i);
+        jaddi(expanded,jduplicate(item));
+        if ( (item->type & 0xff) != cJSON_Object || (symbol= jstr(item,"coin")) == 0 )
+            continue;
+        for (k=0; k<sizeof(kinds)/sizeof(*kinds); k++)
+        {
+            if ( (override= jobj(item,kinds[k][0])) == 0 || (override->type & 0xff) != cJSON_Object )
+                continue;
+            variant = jduplicate(item);
+            jdelete(variant,"testnet");
+            jdelete(variant,"regtest");
+            for (child=override->child; child!=0; child=child->next)
+            {
+                if ( child->string == 0 )
+                    continue;
+                jdelete(variant,child->string);
+                jadd(variant,child->string,jduplicate(child));
+            }
+            if ( jstr(override,"coin") == 0 )
+            {
+                snprintf(vsymbol,sizeof(vsymbol),"%s%s",symbol,kinds[k][1]);
+                jdelete(variant,"coin");
+                jaddstr(variant,"coin",vsymbol);
+            }
+            jaddstr(variant,"testnet_of",symbol);
+            jaddi(expanded,variant);
+        }
+    }
+    return(expanded);
+}
+
+int32_t LP_coinconf_evm(cJSON *errors,int32_t i,char *symbol,cJSON *item)
+{
+    char *fields[] = { "alice_contract", "bob_contract", "fallback_alice_contract", "fallback_bob_contract" }; char *str; int32_t j,numerrs = 0;
+    for (j=0; j<sizeof(fields)/sizeof(*fields); j++)
+    {
+        if ( jobj(item,fields[j]) != 0 && ((str= jstr(item,fields[j])) == 0 || strlen(str) != 42 || str[0] != '0' || str[1] != 'x' || is_hexstr(str+2,0) != 40) )
+            LP_coinconf_error(errors,i,symbol,fields[j],"must be a 0x prefixed 20 byte contract address"), numerrs++;
+    }
+    if ( jobj(item,"rpc_url") != 0 && ((str= jstr(item,"rpc_url")) == 0 || strlen(str) >= sizeof(((struct iguana_info *)0)->rpcurl) || strncmp(str,"http",4) != 0) )
+        LP_coinconf_error(errors,i,symbol,"rpc_url","must be an http(s) url of an EVM node"), numerrs++;
+    return(numerrs);
+}
+
+int32_t LP_coinconf_hrp(char *str)
+{
+    int32_t i;
+    if ( str == 0 || str[0] == 0 || strlen(str) >= sizeof(((struct iguana_info *)0)->bech32hrp) )
+        return(-1);
+    for (i=0; str[i]!=0; i++)
+        if ( str[i] < 33 || str[i] > 126 || (str[i] >= 'A' && str[i] <= 'Z') || str[i] == ':' || str[i] == '1' )
+            return(-1);
+    return(0);
+}
+
+// checks one entry of the coins array, returns number of problems found, all of which are added to errors
+int32_t LP_coinconf_validate(cJSON *errors,cJSON *coins,int32_t i)
+{
+    cJSON *item,*prev; char *symbol,*etomic,*str,proxy[128]; int32_t j,len,numerrs = 0;
+    item = jitem(coins,i);
+    if ( item == 0 || (item->type & 0xff) != cJSON_Object )
+    {
+        LP_coinconf_error(errors,i,0,"","entry must be an object");
+        return(1);
+    }
+    if ( (symbol= jstr(item,"coin")) == 0 || (len= (int32_t)strlen(symbol)) == 0 )
+    {
+        LP_coinconf_error(errors,i,0,"coin","required string field missing");
+        return(1);
+    }
+    if ( len >= 16 )
+        LP_coinconf_error(errors,i,symbol,"coin","symbol must be shorter than 16 characters"), numerrs++;
+    for (j=0; j<len; j++)
+        if ( isalnum((int32_t)symbol[j]) == 0 && symbol[j] != '-' && symbol[j] != '_' )
+            break;
+    if ( j != len )
+        LP_coinconf_error(errors,i,symbol,"coin","symbol must be alphanumeric"), numerrs++;
+    for (j=0; j<i; j++)
+    {
+        if ( (prev= jitem(coins,j)) != 0 && (str= jstr(prev,"coin")) != 0 && strcmp(str,symbol) == 0 )
+        {
+            LP_coinconf_error(errors,i,symbol,"coin","duplicate of an earlier entry");
+            numerrs++;
+            break;
+        }
+    }
+    if ( jobj(item,"name") != 0 && jstr(item,"name") == 0 )
+        LP_coinconf_error(errors,i,symbol,"name","must be a string"), numerrs++;
+    if ( jobj(item,"etomic") != 0 )
+    {
+        if ( (etomic= jstr(item,"etomic")) == 0 || strlen(etomic) != 42 || etomic[0] != '0' || etomic[1] != 'x' || is_hexstr(etomic+2,0) != 40 )
+            LP_coinconf_error(errors,i,symbol,"etomic","must be a 0x prefixed 20 byte contract address"), numerrs++;
+        numerrs += (LP_coinconf_range(errors,i,symbol,item,"decimals",0,18,0) < 0);
+    }
+    else
+    {
+        numerrs += (LP_coinconf_range(errors,i,symbol,item,"rpcport",1,65535,1) < 0);
+        numerrs += (LP_coinconf_range(errors,i,symbol,item,"pubtype",0,255,0) < 0);
+        numerrs += (LP_coinconf_range(errors,i,symbol,item,"p2shtype",0,255,0) < 0);
+        numerrs += (LP_coinconf_range(errors,i,symbol,item,"wiftype",0,255,0) < 0);
+        numerrs += (LP_coinconf_range(errors,i,symbol,item,"taddr",0,255,0) < 0);
+        numerrs += (LP_coinconf_range(errors,i,symbol,item,"wiftaddr",0,255,0) < 0);
+        numerrs += (LP_coinconf_range(errors,i,symbol,item,"decimals",0,18,0) < 0);
+        if ( jobj(item,"pubtype") != 0 && jobj(item,"p2shtype") != 0 && juint(item,"pubtype") == juint(item,"p2shtype") )
+            LP_coinconf_error(errors,i,symbol,"p2shtype","must differ from pubtype"), numerrs++;
+        if ( jobj(item,"asset") != 0 && jstr(item,"asset") == 0 )
+            LP_coinconf_error(errors,i,symbol,"asset","must be a string"), numerrs++;
+    }
+    if ( jobj(item,"txfee") != 0 && jstr(item,"txfee") == 0 )
+        numerrs += (LP_coinconf_range(errors,i,symbol,item,"txfee",0,SATOSHIDEN,0) < 0);
+    numerrs += (LP_coinconf_range(errors,i,symbol,item,"txversion",0,0x7fffffff,0) < 0);
+    numerrs += (LP_coinconf_range(errors,i,symbol,item,"locktime",0,0xffffffff,0) < 0);
+    numerrs += (LP_coinconf_range(errors,i,symbol,item,"rbf",0,1,0) < 0);
+    numerrs += (LP_coinconf_range(errors,i,symbol,item,"uniform_spends",0,1,0) < 0);
+    numerrs += (LP_coinconf_range(errors,i,symbol,item,"burn_fee",0,1,0) < 0);
+    numerrs += (LP_coinconf_range(errors,i,symbol,item,"csv_refunds",0,1,0) < 0);
+    numerrs += (LP_coinconf_range(errors,i,symbol,item,"bip44",0,0x7fffffff,0) < 0);
+    numerrs += (LP_coinconf_range(errors,i,symbol,item,"hd_account",0,0x7fffffff,0) < 0);
+    numerrs += (LP_coinconf_range(errors,i,symbol,item,"feebump_delay",0,0xffffffff,0) < 0);
+    numerrs += (LP_coinconf_range(errors,i,symbol,item,"signer_timeout",0,86400,0) < 0);
+    if ( jobj(item,"signer") != 0 && (jstr(item,"signer") == 0 || strlen(jstr(item,"signer")) >= sizeof(((struct iguana_info *)0)->signerurl)) )
+        LP_coinconf_error(errors,i,symbol,"signer","must be a signer url string"), numerrs++;
+    if ( jobj(item,"signer") != 0 && (jstr(item,"signer_pubkey") == 0 || strlen(jstr(item,"signer_pubkey")) != 66 || is_hexstr(jstr(item,"signer_pubkey"),0) != 66) )
+        LP_coinconf_error(errors,i,symbol,"signer_pubkey","must be the 33 byte compressed pubkey of the signer in hex"), numerrs++;
+    if ( jobj(item,"proxy") != 0 && (jstr(item,"proxy") == 0 || LP_proxy_normalize(proxy,sizeof(proxy),jstr(item,"proxy")) < 0) )
+        LP_coinconf_error(errors,i,symbol,"proxy","must be host:port, socks5h://host:port or none"), numerrs++;
+    if ( jobj(item,"bech32_hrp") != 0 && LP_coinconf_hrp(jstr(item,"bech32_hrp")) < 0 )
+        LP_coinconf_error(errors,i,symbol,"bech32_hrp","must be a lowercase prefix like bc or ltc"), numerrs++;
+    if ( jobj(item,"cashaddr_prefix") != 0 && LP_coinconf_hrp(jstr(item,"cashaddr_prefix")) < 0 )
+        LP_coinconf_error(errors,i,symbol,"cashaddr_prefix","must be a lowercase prefix like bitcoincash"), numerrs++;
+    if ( jobj(item,"min_trading_vol") != 0 && (is_cJSON_Number(jobj(item,"min_trading_vol")) == 0 || jdouble(item,"min_trading_vol") < 0.) )
+        LP_coinconf_error(errors,i,symbol,"min_trading_vol","must be a non-negative number"), numerrs++;
+    numerrs += LP_coinconf_evm(errors,i,symbol,item);
+    return(numerrs);
+}
+
+// EVM coins: rpc_url is the node this coin's calls go to (a token without one uses the node of ETH), the contracts are
+// where new swap payments go and the fallback contracts are only used to spend or refund payments an older version made
+void LP_coin_evmconfig(struct iguana_info *coin,cJSON *item)
+{
+    char *str;
+    if ( (str= jstr(item,"rpc_url")) != 0 )
+        safecopy(coin->rpcurl,str,sizeof(coin->rpcurl));
+    if ( (str= jstr(item,"alice_contract")) != 0 && strlen(str) == 42 )
+        safecopy(coin->alicecontract,str,sizeof(coin->alicecontract));
+    if ( (str= jstr(item,"bob_contract")) != 0 && strlen(str) == 42 )
+        safecopy(coin->bobcontract,str,sizeof(coin->bobcontract));
+    if ( (str= jstr(item,"fallback_alice_contract")) != 0 && strlen(str) == 42 )
+        safecopy(coin->fallback_alicecontract,str,sizeof(coin->fallback_alicecontract));
+    if ( (str= jstr(item,"fallback_bob_contract")) != 0 && strlen(str) == 42 )
+        safecopy(coin->fallback_bobcontract,str,sizeof(coin->fallback_bobcontract));
+    if ( coin->rpcurl[0] != 0 )
+        printf("%s EVM node %s\n",coin->symbol,coin->rpcurl);
+}
+
 // "coins":[{"coin":"<assetchain>", "rpcport":pppp}, {"coin":"LTC", "name":"litecoin", "rpcport":9332, "pubtype":48, "p2shtype":5, "wiftype":176, "txfee":100000 }]
 // {"coin":"HUSH", "name":"hush", "rpcport":8822, "taddr":28, "pubtype":184, "p2shtype":189, "wiftype":128, "txfee":10000 }
 
@@ -548,6 +793,41 @@ struct iguana_info *LP_coincreate(cJSON *item)
             coin = LP_coinadd(&cdata);
             coin->inactive = (uint32_t)time(NULL);
         } else coin = LP_coinadd(&cdata);
+        coin->blocktime = juint(item,"blocktime");
+        coin->maxinflight = jdouble(item,"max_volume_in_flight") * SATOSHIDEN;
+        coin->zeroconfmax = jdouble(item,"zeroconf_max_volume") * SATOSHIDEN;
+        coin->mintradevol = jdouble(item,"min_trading_vol") * SATOSHIDEN;
+        coin->rbf = (jint(item,"rbf") != 0);
+        coin->uniformspends = (jint(item,"uniform_spends") != 0);
+        coin->burnfee = (jint(item,"burn_fee") != 0);
+        coin->csvrefunds = (jint(item,"csv_refunds") != 0);
+        if ( (coin->hdwallet= (jobj(item,"bip44") != 0)) != 0 )
+        {
+            coin->bip44 = juint(item,"bip44");
+            coin->hdaccount = juint(item,"hd_account");
+        }
+        coin->feebumpdelay = juint(item,"feebump_delay");
+        if ( jstr(item,"testnet_of") != 0 )
+        {
+            safecopy(coin->chain,jstr(item,"testnet_of"),sizeof(coin->chain));
+            coin->testnet = 1;
+        }
+        if ( jstr(item,"signer") != 0 && jstr(item,"signer_pubkey") != 0 && is_hexstr(jstr(item,"signer_pubkey"),0) == 66 )
+        {
+            safecopy(coin->signerurl,jstr(item,"signer"),sizeof(coin->signerurl));
+            decode_hex(coin->signerpub33,33,jstr(item,"signer_pubkey"));
+            coin->signertimeout = juint(item,"signer_timeout");
+        }
+        if ( jstr(item,"proxy") != 0 && LP_proxy_normalize(coin->proxy,sizeof(coin->proxy),jstr(item,"proxy")) < 0 )
+            coin->proxy[0] = 0;
+        if ( LP_coinconf_hrp(jstr(item,"bech32_hrp")) == 0 )
+            safecopy(coin->bech32hrp,jstr(item,"bech32_hrp"),sizeof(coin->bech32hrp));
+        if ( LP_coinconf_hrp(jstr(item,"cashaddr_prefix")) == 0 )
+            safecopy(coin->cashaddr,jstr(item,"cashaddr_prefix"),sizeof(coin->cashaddr));
+        if ( (coin->locktime= juint(item,"locktime")) != 0 && coin->locktime < LP_MIN_LOCKTIME )
+            coin->locktime = LP_MIN_LOCKTIME;
+        if ( jstr(item,"etomic") != 0 )
+            LP_coin_evmconfig(coin,item);
     } else if ( symbol != 0 && jobj(item,"rpcport") == 0 )
         printf("SKIP %s, missing rpcport field in coins array\n",symbol);
     if ( coin != 0 && item != 0 )
@@ -102,14 +102,29 @@ char *stats_JSON(void *ctx,int32_t fastflag,char *myipaddr,int32_t pubsock,cJSON
      else if ( strcmp(method,"help") == 0 )
          return(clonestr("{\"result\":\" \
 available localhost RPC commands: \n \
-setprice(base, rel, price, broadcast=1)\n\
-autoprice(base, rel, fixed, minprice, maxprice, margin, refbase, refrel, factor, offset)*\n\
+setprice(base, rel, price, broadcast=1, volume=0, payfirst=taker)\n\
+setprice(base, rel, price_autoupdate=1, feed=<first>, spread=0, broadcast=1, volume=0, payfirst=taker)\n\
+pricefeeds(base=\"\", rel=\"\")\n\
+my_orders()\n\
+start_simple_market_maker_bot(cfg=[{base, rel, feed, spread, balance_percent, min_volume=0}, ...], refresh=30)\n\
+stop_simple_market_maker_bot()\n\
+simple_market_maker_bot_status()\n\
+multihop_buy(base, rel, relvolume, maxprice=0, via=\"\", slippage=0.01, timeout=30)\n\
+multihop_status(uuid=\"\")\n\
+multihop_cancel(uuid)\n\
+autoprice(base, rel, fixed, minprice, maxprice, margin, refbase, refrel, factor, offset, maxage)*\n\
 goal(coin=*, val=<autocalc>)\n\
 myprice(base, rel)\n\
-enable(coin)\n\
+enable(coin, tx_history=0)\n\
+setlocktime(coin=\"\", base=\"\", rel=\"\", locktime=0)\n\
+enable_token(coin, etomic, decimals=0, swap_contract=\"\", alice_contract=\"\", bob_contract=\"\", fallback_alice_contract=\"\", fallback_bob_contract=\"\")\n\
+deploy_swap_contract(url, chainid, type=alice|bob, bytecode, gas=3000000) # returns a jobid\n\
+deploy_swap_contract_status(jobid)\n\
 disable(coin)\n\
+disable_coin(coin, force=0) # refused while swaps or maker orders use coin, force cancels the orders. closes its rpc and electrum connections\n\
 notarizations(coin)\n\
 statsdisp(starttime=0, endtime=0, gui="", pubkey="", base="", rel="")\n\
+stats_swaps(from=0, to=now, base=\"\", rel=\"\", limit=1000)\n\
 ticker(base="", rel="")\n\
 tradesarray(base, rel, starttime=<now>-timescale*1024, endtime=<now>, timescale=60) -> [timestamp, high, low, open, close, relvolume, basevolume, aveprice, numtrades]\n\
 pricearray(base, rel, starttime=0, endtime=0, timescale=60) -> [timestamp, avebid, aveask, highbid, lowask]\n\
@@ -117,18 +132,34 @@ getrawtransaction(coin, txid)\n\
 inventory(coin, reset=0, [passphrase=])\n\
 lastnonce()\n\
 cancel(uuid)\n\
-buy(base, rel, price, relvolume, timeout=10, duration=3600, nonce)\n\
-sell(base, rel, price, basevolume, timeout=10, duration=3600, nonce)\n\
+cancel_order(uuid)\n\
+cancel_all_orders(coin=\"\", base=\"\", rel=\"\", all=0)\n\
+export_analytics()\n\
+abort_swap(uuid)\n\
+swaplog(uuid, lines=50)\n\
+support_bundle(uuid)\n\
+metrics()\n\
+tuning(coin=\"\", reset=0)\n\
+proxy_check(coin=\"\", url=\"https://check.torproject.org/api/ip\")\n\
+counterparty_limits(counterparty_maxkmd=, counterparty_maxswaps=, counterparty_window=, pubkey=)\n\
+buy(base, rel, price, relvolume, timeout=10, duration=3600, nonce, internal=0, requirecaps=0, payfirst=taker)\n\
+sell(base, rel, price, basevolume, timeout=10, duration=3600, nonce, internal=0, requirecaps=0, payfirst=taker)\n\
 withdraw(coin, outputs[], broadcast=0)\n\
+withdraw(coin, to, amount|max=1, txfee=<auto>, broadcast=0)\n\
+my_balance(coin)\n\
+maintenance(coin, enable=1)\n\
 eth_withdraw(coin, to, amount, gas, gas_price, broadcast=0)\n\
 txblast(coin, utxotxid, utxovout, utxovalue, txfee, passphrase, outputs[], broadcast=0)\n\
 sendrawtransaction(coin, signedtx)\n\
+send_raw_transaction(coin, tx_hex)\n\
 swapstatus(pending=0, fast=0)\n\
 swapstatus(coin, limit=10)\n\
 swapstatus(base, rel, limit=10)\n\
 swapstatus(requestid, quoteid, pending=0, fast=0)\n\
 recentswaps(limit=3)\n\
 kickstart(requestid, quoteid)\n\
+swap_receipt(requestid, quoteid)\n\
+verify_swap_receipt(receipt)\n\
 notarizations(coin)\n\
 public API:\n \
 getcoins()\n\
@@ -138,13 +169,27 @@ getpeers()\n\
 passphrase(passphrase, gui, netid=0, seednode="")\n\
 listunspent(coin, address)\n\
 setconfirms(coin, numconfirms, maxconfirms=6)\n\
+zeroconf(coin, max_volume) # 0 confirms for swaps while the coins zero conf volume stays under max_volume, 0 disables\n\
 trust(pubkey, trust) # positive to trust, 0 for normal, negative to blacklist\n\
+ban_pubkey(pubkey, reason=\"\")\n\
+unban_pubkey(pubkey)\n\
+list_banned_pubkeys()\n\
+reputation(pubkey=\"\") # takers we deposited to, completed and abandoned swaps\n\
+set_policy(policy={allow:[\"BASE/REL\", ...], deny:[...], maxsize:{\"BASE/REL\":vol}, pubkeys:[...]})\n\
+get_policy()\n\
+export_swaps(filename=\"\") # swaps, maker orders and utxo locks encrypted with a key from the passphrase, default DB/backups\n\
+import_swaps(filename) # needs the same passphrase, swaps already here are kept\n\
 balance(coin, address)\n\
+get_new_address(coin, change=0)\n\
+hd_addresses(coin)\n\
+hd_scan(coin, gap_limit=20)\n\
+coldwallet(coin, address, threshold, keep, confirms)\n\
 balances(address)\n\
 fundvalue(address="", holdings=[], divisor=0)\n\
-orderbook(base, rel, duration=3600)\n\
+orderbook(base, rel, duration=3600, bucket=0) # bucket > 0 adds bidbuckets and askbuckets aggregated to that price step\n\
 getprices()\n\
 inuse()\n\
+utxolocks()\n\
 movecoinbases(coin)\n\
 getmyprice(base, rel)\n\
 getprice(base, rel)\n\
@@ -153,11 +198,14 @@ getprice(base, rel)\n\
 //deletemessages(firsti=0, num=100)\n\
 secretaddresses(prefix='secretaddress', passphrase, num=10, pubtype=60, taddr=0)\n\
 gen64addrs(passphrase, taddr=0, pubtype=60)\n\
-electrum(coin, ipaddr, port)\n\
+electrum(coin, ipaddr, port, tx_history=0)\n\
+electrum(coin, servers=[{ipaddr, port}, ...], tx_history=0)\n\
+electrum_health(coin)\n\
+rpc_health(coin)\n\
 snapshot(coin, height)\n\
 snapshot_balance(coin, height, addresses[])\n\
 dividends(coin, height, <args>)\n\
-stop()\n\
+stop(graceful=0, deadline=600)\n\
 bot_list()\n\
 bot_statuslist()\n\
 bot_buy(base, rel, maxprice, relvolume) -> botid\n\
@@ -178,12 +226,16 @@ getfee(coin)\n\
 mpnet(onoff)\n\
 sleep(seconds=60)\n\
 listtransactions(coin, address, count=10, skip=0)\n\
+my_tx_history(coin, limit=10, from_id=\"\")\n\
 jpg(srcfile, destfile, power2=7, password, data="", required, ind=0)\n\
 version\n\
 \"}"));
     if ( strcmp(method,"version") == 0 ) {
         retjson = cJSON_CreateObject();
         jaddstr(retjson,"result",MM_VERSION);
+        jaddnum(retjson,"schema",LP_schema_version);
+        if ( LP_schema_dropped != 0 )
+            jaddnum(retjson,"schema_dropped",LP_schema_dropped);
         return(jprint(retjson,1));
     }
 
@@ -332,10 +384,68 @@ version\n\
             LP_deletemessages(jint(argjson,"firsti"),jint(argjson,"num"));
             return(clonestr("{\"result\":\"success\"}"));
         }*/
-        else if ( strcmp(method,"cancel") == 0 )
+        else if ( strcmp(method,"cancel") == 0 || strcmp(method,"cancel_order") == 0 )
         {
             return(LP_cancel_order(jstr(argjson,"uuid")));
         }
+        else if ( strcmp(method,"cancel_all_orders") == 0 )
+        {
+            return(LP_cancel_all_orders(ctx,argjson));
+        }
+        else if ( strcmp(method,"export_analytics") == 0 )
+        {
+            return(LP_export_analytics());
+        }
+        else if ( strcmp(method,"abort_swap") == 0 )
+        {
+            return(LP_abort_swap(jstr(argjson,"uuid")));
+        }
+        else if ( strcmp(method,"setlocktime") == 0 )
+        {
+            return(LP_setlocktime(jstr(argjson,"base"),jstr(argjson,"rel"),jstr(argjson,"coin"),juint(argjson,"locktime")));
+        }
+        else if ( strcmp(method,"enable_token") == 0 )
+        {
+            return(LP_enable_token(argjson));
+        }
+        else if ( strcmp(method,"deploy_swap_contract") == 0 )
+        {
+            return(LP_deploy_swap_contract(argjson));
+        }
+        else if ( strcmp(method,"deploy_swap_contract_status") == 0 )
+        {
+            return(LP_deploy_swap_contract_status(juint(argjson,"jobid")));
+        }
+        else if ( strcmp(method,"counterparty_limits") == 0 )
+        {
+            return(LP_counterparty_limits(argjson));
+        }
+        else if ( strcmp(method,"swaplog") == 0 )
+        {
+            return(LP_swaplog_tail(jstr(argjson,"uuid"),jint(argjson,"lines")));
+        }
+        else if ( strcmp(method,"metrics") == 0 )
+        {
+            return(LP_metrics());
+        }
+        else if ( strcmp(method,"tuning") == 0 )
+        {
+            return(LP_tuning(jstr(argjson,"coin"),jint(argjson,"reset")));
+        }
+        else if ( strcmp(method,"proxy_check") == 0 )
+        {
+            return(LP_proxy_check(jstr(argjson,"coin"),jstr(argjson,"url")));
+        }
+#ifdef LP_MOCKCLOCK
+        else if ( strcmp(method,"mockclock") == 0 )
+        {
+            return(LP_mockclock(argjson));
+        }
+#endif
+        else if ( strcmp(method,"support_bundle") == 0 )
+        {
+            return(LP_support_bundle(jstr(argjson,"uuid")));
+        }
         else if ( strcmp(method,"recentswaps") == 0 )
         {
             return(LP_recent_swaps(jint(argjson,"limit"),0));
@@ -343,6 +453,8 @@ version\n\
         else if ( strcmp(method,"stop") == 0 )
         {
             printf("DEBUG stop\n");
+            if ( jint(argjson,"graceful") != 0 )
+                return(LP_shutdown(ctx,jint(argjson,"deadline")));
             LP_STOP_RECEIVED = 1;
             return(clonestr("{\"result\":\"success\"}"));
         }
@@ -423,6 +535,10 @@ version\n\
         {
             return(jprint(LP_statslog_disp(juint(argjson,"starttime"),juint(argjson,"endtime"),jstr(argjson,"gui"),jbits256(argjson,"pubkey"),jstr(argjson,"base"),jstr(argjson,"rel")),1));
         }
+        else if ( strcmp(method,"stats_swaps") == 0 )
+        {
+            return(LP_stats_swaps(juint(argjson,"from"),juint(argjson,"to"),jstr(argjson,"base"),jstr(argjson,"rel"),jint(argjson,"limit")));
+        }
         else if ( strcmp(method,"ticker") == 0 )
         {
             return(LP_ticker(jstr(argjson,"base"),jstr(argjson,"rel")));
@@ -448,6 +564,15 @@ version\n\
                 return(LP_kickstart(requestid,quoteid));
             else return(clonestr("{\"error\":\"kickstart needs requestid and quoteid\"}"));
         }
+        else if ( strcmp(method,"swap_receipt") == 0 )
+        {
+            uint32_t requestid,quoteid;
+            if ( (requestid= juint(argjson,"requestid")) != 0 && (quoteid= juint(argjson,"quoteid")) != 0 )
+                return(LP_swap_receipt(requestid,quoteid));
+            else return(clonestr("{\"error\":\"swap_receipt needs requestid and quoteid\"}"));
+        }
+        else if ( strcmp(method,"verify_swap_receipt") == 0 )
+            return(LP_swap_receipt_verify(ctx,argjson));
         else if ( strcmp(method,"swapstatus") == 0 )
         {
             uint32_t requestid,quoteid;
@@ -478,6 +603,24 @@ version\n\
         }
         else if ( strcmp(method,"inuse") == 0 )
             return(jprint(LP_inuse_json(),1));
+        else if ( strcmp(method,"utxolocks") == 0 )
+            return(jprint(LP_utxolocks_json(),1));
+        else if ( strcmp(method,"pricefeeds") == 0 )
+            return(LP_pricefeeds_json(base,rel));
+        else if ( strcmp(method,"my_orders") == 0 )
+            return(LP_myorders_json());
+        else if ( strcmp(method,"start_simple_market_maker_bot") == 0 )
+            return(LP_mmbot_start(ctx,argjson));
+        else if ( strcmp(method,"stop_simple_market_maker_bot") == 0 )
+            return(LP_mmbot_stop(ctx));
+        else if ( strcmp(method,"simple_market_maker_bot_status") == 0 )
+            return(LP_mmbot_status());
+        else if ( strcmp(method,"multihop_buy") == 0 )
+            return(LP_multihop_buy(ctx,argjson));
+        else if ( strcmp(method,"multihop_status") == 0 )
+            return(LP_multihop_status(jstr(argjson,"uuid")));
+        else if ( strcmp(method,"multihop_cancel") == 0 )
+            return(LP_multihop_cancel(jstr(argjson,"uuid")));
 #ifndef NOTETOMIC
         else if ( strcmp(method,"eth_gas_price") == 0 )
         {
@@ -536,17 +679,35 @@ version\n\
                 return(jprint(retjson,1));
             }
             else if ( strcmp(method,"orderbook") == 0 )
-                return(LP_orderbook(base,rel,jint(argjson,"duration")));
+                return(LP_orderbook(base,rel,jint(argjson,"duration"),jdouble(argjson,"bucket")));
             if ( IAMLP == 0 && LP_isdisabled(base,rel) != 0 )
                 return(clonestr("{\"error\":\"at least one of coins disabled\"}"));
             price = jdouble(argjson,"price");
+            if ( jstr(argjson,"payfirst") != 0 && strcmp(jstr(argjson,"payfirst"),"taker") != 0 && strcmp(jstr(argjson,"payfirst"),"maker") != 0 )
+                return(clonestr("{\"error\":\"payfirst has to be maker or taker\"}"));
+            if ( strcmp(method,"setprice") == 0 && price != 0. && LP_testnet_mismatch(base,rel) != 0 )
+                return(clonestr("{\"error\":\"cant price a testnet coin against a mainnet coin\"}"));
             if ( strcmp(method,"setprice") == 0 )
             {
+                if ( price != 0. && jobj(argjson,"volume") != 0 && LP_myvolume_check(base,jdouble(argjson,"volume")) < 0 )
+                    return(clonestr("{\"error\":\"invalid volume, must be 0 or at least min_trading_vol\"}"));
+                if ( jstr(argjson,"payfirst") != 0 )
+                    LP_makerfirst_set(base,rel,LP_payfirst_caps(argjson) != 0);
+                if ( jint(argjson,"price_autoupdate") != 0 )
+                {
+                    if ( (retstr= LP_pricefeed_peg(ctx,myipaddr,LP_mypubsock,base,rel,argjson)) != 0 && strncmp(retstr,"{\"error",7) != 0 && LP_myprice(1,&bid,&ask,base,rel) > SMALLVAL )
+                        LP_myorder_update(base,rel,ask,argjson);
+                    return(retstr);
+                }
+                LP_pricefeed_unpeg(base,rel);
                 if ( LP_mypriceset(1,&changed,base,rel,price) < 0 )
                     return(clonestr("{\"error\":\"couldnt set price\"}"));
+                if ( price != 0. && jobj(argjson,"volume") != 0 && LP_myvolume_set(base,rel,jdouble(argjson,"volume") * SATOSHIDEN) < 0 )
+                    return(clonestr("{\"error\":\"invalid volume\"}"));
+                LP_myorder_update(base,rel,price,argjson);
                 //else if ( LP_mypriceset(1,&changed,rel,base,1./price) < 0 )
                 //    return(clonestr("{\"error\":\"couldnt set price\"}"));
-                else if ( price == 0. || jobj(argjson,"broadcast") == 0 || jint(argjson,"broadcast") != 0 )
+                if ( price == 0. || jobj(argjson,"broadcast") == 0 || jint(argjson,"broadcast") != 0 )
                     return(LP_pricepings(ctx,myipaddr,LP_mypubsock,base,rel,price * LP_profitratio));
                 else return(clonestr("{\"result\":\"success\"}"));
             }
@@ -564,30 +725,30 @@ version\n\
             }
             else if ( strcmp(method,"buy") == 0 )
             {
-                int32_t fomo = 0; double vol;
+                int32_t fomo = 0; double vol; char *volstr = 0;
                 if ( jobj(argjson,"fomo") != 0 )
                 {
                     fomo = 1;
                     price = 1.;
                     vol = jdouble(argjson,"fomo");
-                } else vol = jdouble(argjson,"relvolume");
+                } else vol = jdouble(argjson,"relvolume"), volstr = jstr(argjson,"relvolume");
                 if ( price > SMALLVAL )
                 {
-                    return(LP_autobuy(ctx,fomo,myipaddr,pubsock,base,rel,price,vol,jint(argjson,"timeout"),jint(argjson,"duration"),jstr(argjson,"gui"),juint(argjson,"nonce"),jbits256(argjson,"destpubkey"),0,jstr(argjson,"uuid"),jint(argjson,"fill"),jint(argjson,"gtc")));
+                    return(LP_autobuy(ctx,fomo,myipaddr,pubsock,base,rel,price,vol,volstr,jint(argjson,"timeout"),jint(argjson,"duration"),jstr(argjson,"gui"),juint(argjson,"nonce"),jbits256(argjson,"destpubkey"),0,jstr(argjson,"uuid"),jint(argjson,"fill"),jint(argjson,"gtc"),jint(argjson,"internal"),juint(argjson,"requirecaps") | LP_payfirst_caps(argjson)));
                 } else return(clonestr("{\"error\":\"no price set\"}"));
             }
             else if ( strcmp(method,"sell") == 0 )
             {
-                int32_t fomo = 0; double vol;
+                int32_t fomo = 0; double vol; char *volstr = 0;
                 if ( jobj(argjson,"dump") != 0 )
                 {
                     fomo = 1;
                     price = 1.;
                     vol = jdouble(argjson,"dump");
-                } else vol = jdouble(argjson,"basevolume");
+                } else vol = jdouble(argjson,"basevolume"), volstr = jstr(argjson,"basevolume");
                 if ( price > SMALLVAL )
                 {
-                    return(LP_autobuy(ctx,fomo,myipaddr,pubsock,rel,base,1./price,vol,jint(argjson,"timeout"),jint(argjson,"duration"),jstr(argjson,"gui"),juint(argjson,"nonce"),jbits256(argjson,"destpubkey"),0,jstr(argjson,"uuid"),jint(argjson,"fill"),jint(argjson,"gtc")));
+                    return(LP_autobuy(ctx,fomo,myipaddr,pubsock,rel,base,1./price,vol,volstr,jint(argjson,"timeout"),jint(argjson,"duration"),jstr(argjson,"gui"),juint(argjson,"nonce"),jbits256(argjson,"destpubkey"),0,jstr(argjson,"uuid"),jint(argjson,"fill"),jint(argjson,"gtc"),jint(argjson,"internal"),juint(argjson,"requirecaps") | LP_payfirst_caps(argjson)));
                 } else return(clonestr("{\"error\":\"no price set\"}"));
             }
         }
@@ -630,6 +791,7 @@ version\n\
                         }
 
                         if (ptr->decimals == 0 && strcmp(coin, "ETH") != 0) {
+                            LP_etomic_select(coin);
                             ptr->decimals = getErc20DecimalsZeroOnError(ptr->etomic);
                             if (ptr->decimals == 0) {
                                 return(clonestr("{\"error\":\"Could not get token decimals or token has zero decimals which is not supported!\"}"));
@@ -643,6 +805,7 @@ version\n\
                     {
                         cJSON *array;
                         ptr->inactive = 0;
+                        LP_rpcpool_restore(ptr);
 #ifndef NOTETOMIC
                         if (ptr->etomic[0] != 0 && OS_thread_create(malloc(sizeof(pthread_t)),NULL,(void *)LP_etomic_txhistory_loop,(void *)ptr) != 0 )
                         {
@@ -651,6 +814,20 @@ version\n\
                             return(clonestr("{\"error\":\"couldnt launch tx history thread\"}"));
                         }
 #endif
+                        if ( ptr->etomic[0] == 0 && ptr->cache_history == 0 && (juint(argjson,"tx_history") != 0 || juint(argjson,"cache_history") != 0) )
+                        {
+                            ptr->cache_history = 1;
+                            ptr->txhistory_synced = 0;
+                            if ( ptr->txhistory_running == 0 ) // a disable/enable within its sleep leaves the old loop running
+                            {
+                                ptr->txhistory_running = (uint32_t)time(NULL);
+                                if ( OS_thread_create(malloc(sizeof(pthread_t)),NULL,(void *)LP_native_txhistory_loop,(void *)ptr) != 0 )
+                                {
+                                    ptr->txhistory_running = 0;
+                                    printf("error launching LP_native_txhistory_loop %s\n",ptr->symbol);
+                                }
+                            }
+                        }
                         LP_unspents_load(coin,ptr->smartaddr);
                         if ( strcmp(ptr->symbol,"KMD") == 0 )
                             LP_importaddress("KMD",BOTS_BONDADDRESS);
@@ -666,11 +843,14 @@ version\n\
                 if ( (ptr= LP_coinsearch(coin)) != 0 )
                 {
                     ptr->inactive = (uint32_t)time(NULL);
+                    ptr->cache_history = 0;
                     cJSON *array = cJSON_CreateArray();
                     jaddi(array,LP_coinjson(ptr,0));
                     return(jprint(array,1));
                 } else return(clonestr("{\"error\":\"couldnt find coin\"}"));
             }
+            else if ( strcmp(method,"disable_coin") == 0 )
+                return(LP_disable_coin(ctx,coin,jint(argjson,"force")));
             else if ( strcmp(method,"listunspent") == 0 )
             {
                 if ( (ptr= LP_coinsearch(coin)) != 0 )
@@ -693,12 +873,44 @@ version\n\
                     return(clonestr("{\"error\":\"no address specified\"}"));
                 } else return(clonestr("{\"error\":\"cant find coind\"}"));
             }
+            else if ( strcmp(method,"coldwallet") == 0 )
+            {
+                if ( (ptr= LP_coinsearch(coin)) != 0 )
+                    return(LP_coldwallet(ptr,argjson));
+                else return(clonestr("{\"error\":\"cant find coind\"}"));
+            }
+            else if ( strcmp(method,"maintenance") == 0 )
+                return(LP_maintenance(coin,jobj(argjson,"enable") == 0 || jint(argjson,"enable") != 0));
+            else if ( strcmp(method,"my_balance") == 0 )
+            {
+                if ( (ptr= LP_coinsearch(coin)) != 0 )
+                    return(jprint(LP_my_balance(ptr),1));
+                else return(clonestr("{\"error\":\"cant find coind\"}"));
+            }
             else if ( strcmp(method,"balance") == 0 )
             {
                 if ( (ptr= LP_coinsearch(coin)) != 0 )
                     return(jprint(LP_address_balance(ptr,jstr(argjson,"address"),1),1));
                 else return(clonestr("{\"error\":\"cant find coind\"}"));
             }
+            else if ( strcmp(method,"get_new_address") == 0 )
+            {
+                if ( (ptr= LP_coinsearch(coin)) != 0 )
+                    return(LP_get_new_address(ptr,jint(argjson,"change")));
+                else return(clonestr("{\"error\":\"cant find coind\"}"));
+            }
+            else if ( strcmp(method,"hd_addresses") == 0 )
+            {
+                if ( (ptr= LP_coinsearch(coin)) != 0 )
+                    return(LP_hd_addresses(ptr));
+                else return(clonestr("{\"error\":\"cant find coind\"}"));
+            }
+            else if ( strcmp(method,"hd_scan") == 0 )
+            {
+                if ( (ptr= LP_coinsearch(coin)) != 0 )
+                    return(LP_hd_scan(ptr,jint(argjson,"gap_limit")));
+                else return(clonestr("{\"error\":\"cant find coind\"}"));
+            }
             else if ( strcmp(method,"getfee") == 0 )
             {
                 uint64_t txfee;
@@ -716,15 +928,45 @@ version\n\
             {
                 if ( (ptr= LP_coinsearch(coin)) != 0 )
                 {
+                    cJSON *array,*item; int32_t i,n;
                     ptr->inactive = 0;
-                    ptr->cache_history = juint(argjson, "cache_history");
+                    ptr->cache_history = (juint(argjson,"tx_history") != 0 || juint(argjson,"cache_history") != 0);
+                    ptr->txhistory_synced = 0;
+                    if ( (array= jarray(&n,argjson,"servers")) != 0 && n > 0 )
+                    {
+                        retjson = cJSON_CreateArray();
+                        for (i=0; i<n; i++)
+                        {
+                            item = jitem(array,i);
+                            jaddi(retjson,LP_electrumserver(ptr,jstr(item,"ipaddr"),juint(item,"port")));
+                        }
+                        return(jprint(retjson,1));
+                    }
                     return(jprint(LP_electrumserver(ptr,jstr(argjson,"ipaddr"),juint(argjson,"port")),1));
                 } else return(clonestr("{\"error\":\"cant find coind\"}"));
             }
+            else if ( strcmp(method,"electrum_health") == 0 )
+            {
+                if ( (ptr= LP_coinsearch(coin)) != 0 )
+                    return(jprint(LP_electrum_health(ptr),1));
+                else return(clonestr("{\"error\":\"cant find coind\"}"));
+            }
+            else if ( strcmp(method,"rpc_health") == 0 )
+            {
+                if ( (ptr= LP_coinsearch(coin)) != 0 )
+                    return(jprint(LP_rpc_health(ptr),1));
+                else return(clonestr("{\"error\":\"cant find coind\"}"));
+            }
             else if ( strcmp(method,"sendrawtransaction") == 0 )
             {
                 return(LP_sendrawtransaction(coin,jstr(argjson,"signedtx"),jint(argjson,"needjson")));
             }
+            else if ( strcmp(method,"send_raw_transaction") == 0 )
+            {
+                if ( jstr(argjson,"tx_hex") == 0 && jstr(argjson,"signedtx") == 0 )
+                    return(clonestr("{\"error\":\"send_raw_transaction needs tx_hex\"}"));
+                return(LP_sendrawtransaction(coin,jstr(argjson,"tx_hex") != 0 ? jstr(argjson,"tx_hex") : jstr(argjson,"signedtx"),1));
+            }
             else if ( strcmp(method,"convaddress") == 0 )
             {
                 return(LP_convaddress(coin,jstr(argjson,"address"),jstr(argjson,"destcoin")));
@@ -741,6 +983,12 @@ version\n\
             {
                 return(LP_unlockedspend(ctx,coin,jbits256(argjson,"txid")));
             }
+            else if ( strcmp(method,"my_tx_history") == 0 )
+            {
+                if ( (ptr= LP_coinsearch(coin)) != 0 )
+                    return(jprint(LP_my_tx_history(ptr,jint(argjson,"limit"),jstr(argjson,"from_id")),1));
+                else return(clonestr("{\"error\":\"cant find coind\"}"));
+            }
             // cJSON *LP_listtransactions(char *symbol,char *coinaddr,int32_t count,int32_t skip)
             else if ( strcmp(method,"listtransactions") == 0 )
             {
@@ -765,7 +1013,9 @@ version\n\
             {
                 if ( (ptr= LP_coinsearch(coin)) != 0 )
                 {
-                    if ( jobj(argjson,"outputs") == 0 && jstr(argjson,"opreturn") == 0 )
+                    if ( jobj(argjson,"outputs") == 0 && jstr(argjson,"to") != 0 )
+                        return(LP_withdraw_to(ptr,argjson));
+                    else if ( jobj(argjson,"outputs") == 0 && jstr(argjson,"opreturn") == 0 )
                         return(clonestr("{\"error\":\"withdraw needs to have outputs\"}"));
                     else if ( ptr->etomic[0] != 0 )
                         return(clonestr("{\"error\":\"use eth_withdraw for ETH/ERC20\"}"));
@@ -797,6 +1047,24 @@ version\n\
                     return(clonestr("{\"result\":\"success\"}"));
                 } else return(clonestr("{\"error\":\"cant find coind\"}"));
             }
+            else if ( strcmp(method,"zeroconf") == 0 )
+            {
+                double maxvolume = jdouble(argjson,"max_volume");
+                if ( maxvolume < 0. )
+                    return(clonestr("{\"error\":\"illegal max_volume\"}"));
+                if ( (ptr= LP_coinsearch(coin)) != 0 )
+                {
+                    if ( ptr->etomic[0] != 0 && maxvolume > 0. )
+                        return(clonestr("{\"error\":\"zeroconf needs a utxo coin\"}"));
+                    ptr->zeroconfmax = maxvolume * SATOSHIDEN;
+                    retjson = cJSON_CreateObject();
+                    jaddstr(retjson,"result","success");
+                    jaddstr(retjson,"coin",ptr->symbol);
+                    jaddnum(retjson,"zeroconf_max_volume",dstr(ptr->zeroconfmax));
+                    jaddnum(retjson,"zeroconf_volume",dstr(ptr->zeroconfvol));
+                    return(jprint(retjson,1));
+                } else return(clonestr("{\"error\":\"cant find coind\"}"));
+            }
             else if ( strcmp(method,"snapshot") == 0 )
             {
                 if ( (ptr= LP_coinsearch(coin)) != 0 )
@@ -868,6 +1136,22 @@ version\n\
             return(LP_pubkey_trustset(jbits256(argjson,"pubkey"),jint(argjson,"trust")));
         else if ( strcmp(method,"trusted") == 0 )
             return(LP_pubkey_trusted());
+        else if ( strcmp(method,"ban_pubkey") == 0 )
+            return(LP_ban_pubkey(jbits256(argjson,"pubkey"),jstr(argjson,"reason")));
+        else if ( strcmp(method,"unban_pubkey") == 0 )
+            return(LP_unban_pubkey(jbits256(argjson,"pubkey")));
+        else if ( strcmp(method,"list_banned_pubkeys") == 0 )
+            return(LP_banned_json());
+        else if ( strcmp(method,"reputation") == 0 )
+            return(LP_reputation_json(jbits256(argjson,"pubkey")));
+        else if ( strcmp(method,"set_policy") == 0 )
+            return(LP_policy_set(jobj(argjson,"policy"),1));
+        else if ( strcmp(method,"get_policy") == 0 )
+            return(LP_policy_json());
+        else if ( strcmp(method,"export_swaps") == 0 )
+            return(LP_export_swaps(jstr(argjson,"filename")));
+        else if ( strcmp(method,"import_swaps") == 0 )
+            return(LP_import_swaps(jstr(argjson,"filename")));
     } // end of protected localhost commands
     if ( IAMLP == 0 )
     {
@@ -890,6 +1174,10 @@ version\n\
         {
             return(jprint(LP_tradesarray(base,rel,juint(argjson,"starttime"),juint(argjson,"endtime"),jint(argjson,"timescale")),1));
         }
+        else if ( strcmp(method,"stats_swaps") == 0 )
+        {
+            return(LP_stats_swaps(juint(argjson,"from"),juint(argjson,"to"),base,rel,jint(argjson,"limit")));
+        }
         else if ( strcmp(method,"getdPoW") == 0 )
         {
             if ( (ptr= LP_coinfind(jstr(argjson,"coin"))) != 0 )
@@ -907,6 +1195,10 @@ version\n\
     }
     else if ( strcmp(method,"postprice") == 0 )
         return(LP_postprice_recv(argjson));
+    else if ( strcmp(method,"swap_aborted") == 0 )
+        return(LP_swapaborted_recv(argjson));
+    else if ( strcmp(method,"swapmsg") == 0 )
+        return(LP_swapmsg_recv(argjson));
     else if ( strcmp(method,"uitem") == 0 )
         return(LP_uitem_recv(argjson));
     else if ( strcmp(method,"dPoW") == 0 )
@@ -26,28 +26,114 @@
 #define BOB_DEPOSIT_SENT 1
 #define BOB_PAYMENT_SENT 1
 
-int32_t LP_etomic_wait_for_confirmation(char *txId)
+int32_t LP_etomic_wait_for_confirmation(char *symbol,char *txId)
 {
+    LP_etomic_select(symbol);
     return(waitForConfirmation(txId));
 }
 
+// contract override for this coin, a token without its own uses the one configured for the ETH network it lives on
+char *LP_etomic_override(char *symbol,int32_t bobflag,int32_t fallbackflag)
+{
+    struct iguana_info *coin; char *contract; int32_t iter;
+    for (iter=0; iter<2; iter++)
+    {
+        if ( iter == 1 && (strcmp(symbol,"ETH") == 0 || strcmp(symbol,"ETOMIC") == 0) )
+            break;
+        if ( (coin= LP_coinfind(iter == 0 ? symbol : "ETH")) == 0 )
+            continue;
+        if ( fallbackflag == 0 )
+            contract = bobflag == 0 ? coin->alicecontract : coin->bobcontract;
+        else contract = bobflag == 0 ? coin->fallback_alicecontract : coin->fallback_bobcontract;
+        if ( contract[0] != 0 )
+            return(contract);
+    }
+    return(0);
+}
+
+// EVM node of this coin, a token without its own goes through the node of the ETH network it lives on
+char *LP_etomic_rpcurl(char *symbol)
+{
+    char *symbols[] = { symbol, "ETH", "ETOMIC" }; struct iguana_info *coin; int32_t i;
+    for (i=0; i<sizeof(symbols)/sizeof(*symbols); i++)
+        if ( (coin= LP_coinfind(symbols[i])) != 0 && coin->rpcurl[0] != 0 )
+            return(coin->rpcurl);
+    return(0);
+}
+
+// the etomic calls this thread makes until its next select go to the node of symbol
+void LP_etomic_select(char *symbol)
+{
+    selectEthRpcUrl(LP_etomic_rpcurl(symbol));
+}
+
+// swap contract new payments of this coin go to
+char *LP_etomic_contract(char *symbol,int32_t bobflag)
+{
+    char *contract;
+    if ( (contract= LP_etomic_override(symbol,bobflag,0)) != 0 )
+        return(contract);
+    return(bobflag == 0 ? ETOMIC_ALICECONTRACT : ETOMIC_BOBCONTRACT);
+}
+
+// a contract the counterparty proposes for its payments has to be one this node would use or still spends from
+int32_t LP_etomic_contractknown(char *symbol,int32_t bobflag,char *contract)
+{
+    char *fallback;
+    if ( compareAddresses(contract,LP_etomic_contract(symbol,bobflag)) != 0 )
+        return(1);
+    if ( (fallback= LP_etomic_override(symbol,bobflag,1)) != 0 && compareAddresses(contract,fallback) != 0 )
+        return(1);
+    return(0);
+}
+
+// contract an existing payment is locked in, the one negotiated for the swap. swaps saved before contracts were
+// negotiated only use the fallback (an older contract version) for payments that were sent there
+char *LP_etomic_paymentcontract(char *symbol,int32_t bobflag,char *negotiated,char *txId)
+{
+    char *fallback; EthTxData data;
+    if ( negotiated != 0 && negotiated[0] != 0 )
+        return(negotiated);
+    if ( (fallback= LP_etomic_override(symbol,bobflag,1)) != 0 )
+    {
+        data = getEthTxData(txId);
+        if ( data.exists != 0 && compareAddresses(data.to,fallback) != 0 )
+        {
+            printf("%s payment %s is in fallback contract %s\n",symbol,txId,fallback);
+            return(fallback);
+        }
+    }
+    return(LP_etomic_contract(symbol,bobflag));
+}
+
+// the wei a builder sends or checks for a swap amount, its dexfee or the bob deposit. a swap remembered before
+// it carried decimal amounts only has its realsat to go by
+void LP_etomic_wei(char *wei,char *amountstr,uint64_t realsat,int32_t kind)
+{
+    if ( amountstr[0] != 0 && LP_amount_wei(wei,amountstr,kind) == 0 )
+        return;
+    satoshisToWei(wei,kind == LP_AMOUNT_DEXFEE ? LP_DEXFEE(realsat) : (kind == LP_AMOUNT_DEPOSIT ? LP_DEPOSITSATOSHIS(realsat) : realsat));
+}
+
 char *LP_etomicalice_send_fee(struct basilisk_swap *swap)
 {
+    LP_etomic_select(swap->I.alicestr);
     char amount[100], secretKey[70];
-    satoshisToWei(amount, LP_DEXFEE(swap->I.alicerealsat));
+    LP_etomic_wei(amount, swap->I.aliceamount, swap->I.alicerealsat, LP_AMOUNT_DEXFEE);
     swap->myfee.I.eth_amount = LP_DEXFEE(swap->I.alicerealsat);
     uint8arrayToHex(secretKey, swap->persistent_privkey.bytes, 32);
     if (strcmp(swap->I.alicestr,"ETH") == 0 ) {
-        return(sendEth(INSTANTDEX_ETHADDR, amount, secretKey, 1, 0, 0, 1));
+        return(sendEth(LP_dexfee_ethaddr, amount, secretKey, 1, 0, 0, 1));
     } else {
         struct iguana_info *alicecoin = LP_coinfind(swap->I.alicestr);
 
-        return(sendErc20(swap->I.alicetomic, INSTANTDEX_ETHADDR, amount, secretKey, 1, 0, 0, 1, alicecoin->decimals));
+        return(sendErc20(swap->I.alicetomic, LP_dexfee_ethaddr, amount, secretKey, 1, 0, 0, 1, alicecoin->decimals));
     }
 }
 
 uint8_t LP_etomic_verify_alice_fee(struct basilisk_swap *swap)
 {
+    LP_etomic_select(swap->I.alicestr);
     if (waitForConfirmation(swap->otherfee.I.ethTxid) < 0) {
         printf("Alice fee tx %s does not exist", swap->otherfee.I.ethTxid);
         return(0);
@@ -59,13 +145,14 @@ uint8_t LP_etomic_verify_alice_fee(struct basilisk_swap *swap)
     }
 
     if ( strcmp(swap->I.alicestr,"ETH") == 0 ) {
-        if (compareAddresses(data.to, INSTANTDEX_ETHADDR) == 0 && compareAddresses(data.to, INSTANTDEX_OLD_ETHADDR) == 0) {
+        if (compareAddresses(data.to, LP_dexfee_ethaddr) == 0 && compareAddresses(data.to, INSTANTDEX_OLD_ETHADDR) == 0) {
             printf("Alice fee %s was sent to wrong address %s\n", swap->otherfee.I.ethTxid, data.to);
             return(0);
         }
-        uint64_t txValue = weiToSatoshi(data.valueHex, 18);
-        if (txValue != LP_DEXFEE(swap->I.alicerealsat)) {
-            printf("Alice fee %s amount %" PRIu64 " is not equal to expected %" PRId64 "\n", swap->otherfee.I.ethTxid, txValue, LP_DEXFEE(swap->I.alicerealsat));
+        char weiAmount[100];
+        LP_etomic_wei(weiAmount, swap->I.aliceamount, swap->I.alicerealsat, LP_AMOUNT_DEXFEE);
+        if (LP_amount_weiequal(data.valueHex, weiAmount) == 0) {
+            printf("Alice fee %s amount %s is not equal to expected %s wei\n", swap->otherfee.I.ethTxid, data.valueHex, weiAmount);
             return(0);
         }
         return(1);
@@ -76,9 +163,9 @@ uint8_t LP_etomic_verify_alice_fee(struct basilisk_swap *swap)
             printf("Alice ERC20 fee %s token address %s is not equal to expected %s\n", swap->otherfee.I.ethTxid, data.to, swap->I.alicetomic);
             return(0);
         }
-        char weiAmount[70];
-        satoshisToWei(weiAmount, LP_DEXFEE(swap->I.alicerealsat));
-        uint8_t verify_new = verifyAliceErc20FeeData(swap->I.alicetomic, INSTANTDEX_ETHADDR, weiAmount, data.input, alicecoin->decimals);
+        char weiAmount[100];
+        LP_etomic_wei(weiAmount, swap->I.aliceamount, swap->I.alicerealsat, LP_AMOUNT_DEXFEE);
+        uint8_t verify_new = verifyAliceErc20FeeData(swap->I.alicetomic, LP_dexfee_ethaddr, weiAmount, data.input, alicecoin->decimals);
         uint8_t verify_old = verifyAliceErc20FeeData(swap->I.alicetomic, INSTANTDEX_OLD_ETHADDR, weiAmount, data.input, alicecoin->decimals);
         return((uint8_t)(verify_new || verify_old));
     }
@@ -86,7 +173,9 @@ uint8_t LP_etomic_verify_alice_fee(struct basilisk_swap *swap)
 
 char *LP_etomicalice_send_payment(struct basilisk_swap *swap)
 {
+    LP_etomic_select(swap->I.alicestr);
     AliceSendsEthPaymentInput input; AliceSendsErc20PaymentInput input20; BasicTxData txData;
+    char *contract = swap->I.alicecontract;
     swap->alicepayment.I.eth_amount = swap->I.alicerealsat;
     // set input and txData fields from the swap data structure
     memset(&txData,0,sizeof(txData));
@@ -99,8 +188,8 @@ char *LP_etomicalice_send_payment(struct basilisk_swap *swap)
         uint8arrayToHex(input.dealId, swap->alicepayment.I.actualtxid.bytes, 32);
 
         strcpy(txData.from, swap->I.etomicdest);
-        strcpy(txData.to, ETOMIC_ALICECONTRACT);
-        satoshisToWei(txData.amount, swap->I.alicerealsat);
+        strcpy(txData.to, contract);
+        LP_etomic_wei(txData.amount, swap->I.aliceamount, swap->I.alicerealsat, LP_AMOUNT_VALUE);
         uint8arrayToHex(txData.secretKey, swap->persistent_privkey.bytes, 32);
 
         return(aliceSendsEthPayment(input,txData));
@@ -115,21 +204,21 @@ char *LP_etomicalice_send_payment(struct basilisk_swap *swap)
         uint8arrayToHex(input20.aliceHash, swap->I.secretAm, 20);
         uint8arrayToHex(input20.dealId, swap->alicepayment.I.actualtxid.bytes, 32);
         strcpy(input20.tokenAddress, swap->I.alicetomic);
-        satoshisToWei(input20.amount, swap->I.alicerealsat);
+        LP_etomic_wei(input20.amount, swap->I.aliceamount, swap->I.alicerealsat, LP_AMOUNT_VALUE);
         input20.decimals = alicecoin->decimals;
 
         strcpy(txData.from, swap->I.etomicdest);
-        strcpy(txData.to, ETOMIC_ALICECONTRACT);
+        strcpy(txData.to, contract);
         strcpy(txData.amount, "0");
         uint8arrayToHex(txData.secretKey, swap->persistent_privkey.bytes, 32);
 
-        uint64_t allowance = getErc20Allowance(swap->I.etomicdest, ETOMIC_ALICECONTRACT, swap->I.alicetomic, alicecoin->decimals);
+        uint64_t allowance = getErc20Allowance(swap->I.etomicdest, contract, swap->I.alicetomic, alicecoin->decimals);
         if (allowance < swap->I.alicerealsat) {
             printf("Alice token allowance is too low, setting new allowance\n");
             ApproveErc20Input approveErc20Input;
             strcpy(approveErc20Input.tokenAddress, swap->I.alicetomic);
             strcpy(approveErc20Input.owner, swap->I.etomicdest);
-            strcpy(approveErc20Input.spender, ETOMIC_ALICECONTRACT);
+            strcpy(approveErc20Input.spender, contract);
 
             char *tokenBalance = getErc20BalanceHexWei(swap->I.etomicdest, swap->I.alicetomic);
             strcpy(approveErc20Input.amount, tokenBalance);
@@ -137,7 +226,7 @@ char *LP_etomicalice_send_payment(struct basilisk_swap *swap)
             strcpy(approveErc20Input.secret, txData.secretKey);
 
             char *allowTxId = approveErc20(approveErc20Input);
-            LP_etomic_wait_for_confirmation(allowTxId);
+            LP_etomic_wait_for_confirmation(swap->I.alicestr,allowTxId);
             free(allowTxId);
         }
 
@@ -147,12 +236,13 @@ char *LP_etomicalice_send_payment(struct basilisk_swap *swap)
 
 uint8_t LP_etomic_verify_alice_payment(struct basilisk_swap *swap, char *txId)
 {
+    LP_etomic_select(swap->I.alicestr);
     if (waitForConfirmation(txId) < 0) {
         printf("Alice payment %s does not exist\n", txId);
         return(0);
     }
     EthTxData data = getEthTxData(txId);
-    if (compareAddresses(data.to, ETOMIC_ALICECONTRACT) == 0) {
+    if (compareAddresses(data.to, swap->I.alicecontract) == 0) {
         printf("Alice payment %s was sent to wrong address %s\n", txId, data.to);
         return(0);
     }
@@ -162,9 +252,10 @@ uint8_t LP_etomic_verify_alice_payment(struct basilisk_swap *swap, char *txId)
     }
     AliceSendsEthPaymentInput input; AliceSendsErc20PaymentInput input20;
     if ( strcmp(swap->I.alicestr,"ETH") == 0 ) {
-        uint64_t paymentAmount = weiToSatoshi(data.valueHex, 18);
-        if (paymentAmount != swap->I.alicerealsat) {
-            printf("Alice payment amount %" PRIu64 " does not match expected %" PRIu64 "\n", paymentAmount, swap->I.alicerealsat);
+        char weiAmount[100];
+        LP_etomic_wei(weiAmount, swap->I.aliceamount, swap->I.alicerealsat, LP_AMOUNT_VALUE);
+        if (LP_amount_weiequal(data.valueHex, weiAmount) == 0) {
+            printf("Alice payment amount %s does not match expected %s wei\n", data.valueHex, weiAmount);
             return(0);
         }
         memset(&input,0,sizeof(input));
@@ -183,7 +274,7 @@ uint8_t LP_etomic_verify_alice_payment(struct basilisk_swap *swap, char *txId)
         uint8arrayToHex(input20.aliceHash, swap->I.secretAm, 20);
         uint8arrayToHex(input20.dealId, swap->alicepayment.I.actualtxid.bytes, 32);
         strcpy(input20.tokenAddress, swap->I.alicetomic);
-        satoshisToWei(input20.amount, swap->I.alicerealsat);
+        LP_etomic_wei(input20.amount, swap->I.aliceamount, swap->I.alicerealsat, LP_AMOUNT_VALUE);
         input20.decimals = alicecoin->decimals;
 
         return(verifyAliceErc20PaymentData(input20, data.input));
@@ -192,6 +283,7 @@ uint8_t LP_etomic_verify_alice_payment(struct basilisk_swap *swap, char *txId)
 
 char *LP_etomicalice_reclaims_payment(struct LP_swap_remember *swap)
 {
+    LP_etomic_select(swap->dest);
     if (waitForConfirmation(swap->eth_tx_ids[BASILISK_ALICEPAYMENT]) < 0) {
         printf("Alice ETH payment %s is not found, can't reclaim\n", swap->eth_tx_ids[BASILISK_ALICEPAYMENT]);
         return NULL;
@@ -201,6 +293,7 @@ char *LP_etomicalice_reclaims_payment(struct LP_swap_remember *swap)
         printf("Alice payment receipt status failed, can't reclaim\n");
         return NULL;
     }
+    char *contract = LP_etomic_paymentcontract(swap->dest,0,swap->alicecontract,swap->eth_tx_ids[BASILISK_ALICEPAYMENT]);
     AliceReclaimsAlicePaymentInput input;
     BasicTxData txData;
     memset(&txData,0,sizeof(txData));
@@ -213,11 +306,11 @@ char *LP_etomicalice_reclaims_payment(struct LP_swap_remember *swap)
     privkey = LP_privkey(ecoin->symbol, ecoin->smartaddr, ecoin->taddr);
 
     uint8arrayToHex(input.dealId, swap->txids[BASILISK_ALICEPAYMENT].bytes, 32);
-    if (alicePaymentStatus(input.dealId + 2) != ALICE_PAYMENT_SENT) {
+    if (alicePaymentStatus(contract, input.dealId + 2) != ALICE_PAYMENT_SENT) {
         printf("Alice payment smart contract status check failed, can't spend\n");
         return NULL;
     }
-    satoshisToWei(input.amount, swap->alicerealsat);
+    LP_etomic_wei(input.amount, swap->aliceamount, swap->alicerealsat, LP_AMOUNT_VALUE);
 
     if (swap->alicetomic[0] != 0) {
         strcpy(input.tokenAddress, swap->alicetomic);
@@ -236,7 +329,7 @@ char *LP_etomicalice_reclaims_payment(struct LP_swap_remember *swap)
     input.decimals = alice_coin->decimals;
 
     strcpy(txData.from, swap->etomicdest);
-    strcpy(txData.to, ETOMIC_ALICECONTRACT);
+    strcpy(txData.to, contract);
     strcpy(txData.amount, "0");
     uint8arrayToHex(txData.secretKey, privkey.bytes, 32);
     return aliceReclaimsAlicePayment(input, txData);
@@ -244,6 +337,7 @@ char *LP_etomicalice_reclaims_payment(struct LP_swap_remember *swap)
 
 char *LP_etomicbob_spends_alice_payment(struct LP_swap_remember *swap)
 {
+    LP_etomic_select(swap->dest);
     if (waitForConfirmation(swap->eth_tx_ids[BASILISK_ALICEPAYMENT]) < 0) {
         printf("Alice ETH payment %s is not found, can't spend\n", swap->eth_tx_ids[BASILISK_ALICEPAYMENT]);
         return NULL;
@@ -253,6 +347,7 @@ char *LP_etomicbob_spends_alice_payment(struct LP_swap_remember *swap)
         printf("Alice payment receipt status failed, can't spend\n");
         return NULL;
     }
+    char *contract = LP_etomic_paymentcontract(swap->dest,0,swap->alicecontract,swap->eth_tx_ids[BASILISK_ALICEPAYMENT]);
     BobSpendsAlicePaymentInput input;
     BasicTxData txData;
 
@@ -265,12 +360,12 @@ char *LP_etomicbob_spends_alice_payment(struct LP_swap_remember *swap)
     privkey = LP_privkey(ecoin->symbol, ecoin->smartaddr, ecoin->taddr);
 
     uint8arrayToHex(input.dealId, swap->txids[BASILISK_ALICEPAYMENT].bytes, 32);
-    if (alicePaymentStatus(input.dealId + 2) != ALICE_PAYMENT_SENT) {
+    if (alicePaymentStatus(contract, input.dealId + 2) != ALICE_PAYMENT_SENT) {
         printf("Alice payment smart contract status check failed, can't spend\n");
         return NULL;
     }
 
-    satoshisToWei(input.amount, swap->alicerealsat);
+    LP_etomic_wei(input.amount, swap->aliceamount, swap->alicerealsat, LP_AMOUNT_VALUE);
 
     if (swap->alicetomic[0] != 0) {
         strcpy(input.tokenAddress, swap->alicetomic);
@@ -289,7 +384,7 @@ char *LP_etomicbob_spends_alice_payment(struct LP_swap_remember *swap)
     input.decimals = alice_coin->decimals;
 
     strcpy(txData.from, swap->etomicsrc);
-    strcpy(txData.to, ETOMIC_ALICECONTRACT);
+    strcpy(txData.to, contract);
     strcpy(txData.amount, "0");
     uint8arrayToHex(txData.secretKey, privkey.bytes, 32);
     return bobSpendsAlicePayment(input, txData);
@@ -297,9 +392,11 @@ char *LP_etomicbob_spends_alice_payment(struct LP_swap_remember *swap)
 
 char *LP_etomicbob_sends_deposit(struct basilisk_swap *swap)
 {
+    LP_etomic_select(swap->I.bobstr);
     BobSendsEthDepositInput input;
     BobSendsErc20DepositInput input20;
     BasicTxData txData;
+    char *contract = swap->I.bobcontract;
     memset(&txData,0,sizeof(txData));
     memset(&input,0,sizeof(input));
     memset(&input20,0,sizeof(input20));
@@ -310,8 +407,8 @@ char *LP_etomicbob_sends_deposit(struct basilisk_swap *swap)
         input.lockTime = swap->bobdeposit.I.locktime;
 
         strcpy(txData.from, swap->I.etomicsrc);
-        strcpy(txData.to, ETOMIC_BOBCONTRACT);
-        satoshisToWei(txData.amount, LP_DEPOSITSATOSHIS(swap->I.bobrealsat));
+        strcpy(txData.to, contract);
+        LP_etomic_wei(txData.amount, swap->I.bobamount, swap->I.bobrealsat, LP_AMOUNT_DEPOSIT);
         uint8arrayToHex(txData.secretKey, swap->persistent_privkey.bytes, 32);
         return bobSendsEthDeposit(input, txData);
     } else {
@@ -320,23 +417,23 @@ char *LP_etomicbob_sends_deposit(struct basilisk_swap *swap)
         uint8arrayToHex(input20.depositId, swap->bobdeposit.I.actualtxid.bytes, 32);
         strcpy(input20.aliceAddress, swap->I.etomicdest);
         uint8arrayToHex(input20.bobHash, swap->I.secretBn, 20);
-        satoshisToWei(input20.amount, LP_DEPOSITSATOSHIS(swap->I.bobrealsat));
+        LP_etomic_wei(input20.amount, swap->I.bobamount, swap->I.bobrealsat, LP_AMOUNT_DEPOSIT);
         strcpy(input20.tokenAddress, swap->I.bobtomic);
         input20.lockTime = swap->bobdeposit.I.locktime;
         input20.decimals = bobcoin->decimals;
 
         strcpy(txData.from, swap->I.etomicsrc);
-        strcpy(txData.to, ETOMIC_BOBCONTRACT);
+        strcpy(txData.to, contract);
         strcpy(txData.amount, "0");
         uint8arrayToHex(txData.secretKey, swap->persistent_privkey.bytes, 32);
 
-        uint64_t allowance = getErc20Allowance(swap->I.etomicsrc, ETOMIC_BOBCONTRACT, swap->I.bobtomic, bobcoin->decimals);
+        uint64_t allowance = getErc20Allowance(swap->I.etomicsrc, contract, swap->I.bobtomic, bobcoin->decimals);
         if (allowance < LP_DEPOSITSATOSHIS(swap->I.bobrealsat)) {
             printf("Bob token allowance is too low, setting new allowance\n");
             ApproveErc20Input approveErc20Input;
             strcpy(approveErc20Input.tokenAddress, swap->I.bobtomic);
             strcpy(approveErc20Input.owner, swap->I.etomicsrc);
-            strcpy(approveErc20Input.spender, ETOMIC_BOBCONTRACT);
+            strcpy(approveErc20Input.spender, contract);
 
             char *tokenBalance = getErc20BalanceHexWei(swap->I.etomicsrc, swap->I.bobtomic);
             strcpy(approveErc20Input.amount, tokenBalance);
@@ -344,7 +441,7 @@ char *LP_etomicbob_sends_deposit(struct basilisk_swap *swap)
             strcpy(approveErc20Input.secret, txData.secretKey);
 
             char *allowTxId = approveErc20(approveErc20Input);
-            LP_etomic_wait_for_confirmation(allowTxId);
+            LP_etomic_wait_for_confirmation(swap->I.bobstr,allowTxId);
             free(allowTxId);
         }
 
@@ -354,12 +451,13 @@ char *LP_etomicbob_sends_deposit(struct basilisk_swap *swap)
 
 uint8_t LP_etomic_verify_bob_deposit(struct basilisk_swap *swap, char *txId)
 {
+    LP_etomic_select(swap->I.bobstr);
     if (waitForConfirmation(txId) < 0) {
         printf("Bob deposit txid %s does not exist\n", txId);
         return(0);
     }
     EthTxData data = getEthTxData(txId);
-    if (compareAddresses(data.to, ETOMIC_BOBCONTRACT) == 0) {
+    if (compareAddresses(data.to, swap->I.bobcontract) == 0) {
         printf("Bob deposit txid %s was sent to wrong address %s\n", txId, data.to);
         return(0);
     }
@@ -372,9 +470,10 @@ uint8_t LP_etomic_verify_bob_deposit(struct basilisk_swap *swap, char *txId)
     memset(&input,0,sizeof(input));
     memset(&input20,0,sizeof(input20));
     if ( strcmp(swap->I.bobstr,"ETH") == 0 ) {
-        uint64_t depositAmount = weiToSatoshi(data.valueHex, 18);
-        if (depositAmount != LP_DEPOSITSATOSHIS(swap->I.bobrealsat)) {
-            printf("Bob deposit %s amount %" PRIu64 " != expected %" PRIu64 "\n", txId, depositAmount, LP_DEPOSITSATOSHIS(swap->I.bobrealsat));
+        char weiAmount[100];
+        LP_etomic_wei(weiAmount, swap->I.bobamount, swap->I.bobrealsat, LP_AMOUNT_DEPOSIT);
+        if (LP_amount_weiequal(data.valueHex, weiAmount) == 0) {
+            printf("Bob deposit %s amount %s != expected %s wei\n", txId, data.valueHex, weiAmount);
             return(0);
         }
         uint8arrayToHex(input.depositId, swap->bobdeposit.I.actualtxid.bytes, 32);
@@ -389,7 +488,7 @@ uint8_t LP_etomic_verify_bob_deposit(struct basilisk_swap *swap, char *txId)
         uint8arrayToHex(input20.depositId, swap->bobdeposit.I.actualtxid.bytes, 32);
         strcpy(input20.aliceAddress, swap->I.etomicdest);
         uint8arrayToHex(input20.bobHash, swap->I.secretBn, 20);
-        satoshisToWei(input20.amount, LP_DEPOSITSATOSHIS(swap->I.bobrealsat));
+        LP_etomic_wei(input20.amount, swap->I.bobamount, swap->I.bobrealsat, LP_AMOUNT_DEPOSIT);
         strcpy(input20.tokenAddress, swap->I.bobtomic);
         input20.lockTime = swap->bobdeposit.I.locktime;
         input20.decimals = bobcoin->decimals;
@@ -400,10 +499,12 @@ uint8_t LP_etomic_verify_bob_deposit(struct basilisk_swap *swap, char *txId)
 
 char *LP_etomicbob_refunds_deposit(struct LP_swap_remember *swap)
 {
+    LP_etomic_select(swap->src);
     if (waitForConfirmation(swap->eth_tx_ids[BASILISK_BOBDEPOSIT]) < 0) {
         printf("Bob deposit %s is not found, can't refund\n", swap->eth_tx_ids[BASILISK_BOBDEPOSIT]);
         return NULL;
     }
+    char *contract = LP_etomic_paymentcontract(swap->src,1,swap->bobcontract,swap->eth_tx_ids[BASILISK_BOBDEPOSIT]);
     BobRefundsDepositInput input;
     BasicTxData txData;
     memset(&txData,0,sizeof(txData));
@@ -421,7 +522,7 @@ char *LP_etomicbob_refunds_deposit(struct LP_swap_remember *swap)
         return NULL;
     }
     uint8arrayToHex(input.depositId, swap->txids[BASILISK_BOBDEPOSIT].bytes, 32);
-    if (bobDepositStatus(input.depositId + 2) != BOB_DEPOSIT_SENT) {
+    if (bobDepositStatus(contract, input.depositId + 2) != BOB_DEPOSIT_SENT) {
         printf("Bob deposit smart contract status check failed, can't claim\n");
         return NULL;
     }
@@ -440,11 +541,11 @@ char *LP_etomicbob_refunds_deposit(struct LP_swap_remember *swap)
     } else {
         strcpy(input.tokenAddress, "0x0000000000000000000000000000000000000000");
     }
-    satoshisToWei(input.amount, LP_DEPOSITSATOSHIS(swap->bobrealsat));
+    LP_etomic_wei(input.amount, swap->bobamount, swap->bobrealsat, LP_AMOUNT_DEPOSIT);
     input.decimals = bobcoin->decimals;
 
     strcpy(txData.from, swap->etomicsrc);
-    strcpy(txData.to, ETOMIC_BOBCONTRACT);
+    strcpy(txData.to, contract);
     strcpy(txData.amount, "0");
     uint8arrayToHex(txData.secretKey, privkey.bytes, 32);
     return bobRefundsDeposit(input, txData);
@@ -452,9 +553,11 @@ char *LP_etomicbob_refunds_deposit(struct LP_swap_remember *swap)
 
 char *LP_etomicbob_sends_payment(struct basilisk_swap *swap)
 {
+    LP_etomic_select(swap->I.bobstr);
     BobSendsEthPaymentInput input;
     BobSendsErc20PaymentInput input20;
     BasicTxData txData;
+    char *contract = swap->I.bobcontract;
     memset(&txData,0,sizeof(txData));
     memset(&input,0,sizeof(input));
     memset(&input20,0,sizeof(input20));
@@ -466,8 +569,8 @@ char *LP_etomicbob_sends_payment(struct basilisk_swap *swap)
         input.lockTime = swap->bobpayment.I.locktime;
 
         strcpy(txData.from, swap->I.etomicsrc);
-        strcpy(txData.to, ETOMIC_BOBCONTRACT);
-        satoshisToWei(txData.amount, swap->I.bobrealsat);
+        strcpy(txData.to, contract);
+        LP_etomic_wei(txData.amount, swap->I.bobamount, swap->I.bobrealsat, LP_AMOUNT_VALUE);
         uint8arrayToHex(txData.secretKey, swap->persistent_privkey.bytes, 32);
         return bobSendsEthPayment(input, txData);
     } else {
@@ -476,23 +579,23 @@ char *LP_etomicbob_sends_payment(struct basilisk_swap *swap)
         uint8arrayToHex(input20.paymentId, swap->bobpayment.I.actualtxid.bytes, 32);
         strcpy(input20.aliceAddress, swap->I.etomicdest);
         uint8arrayToHex(input20.aliceHash, swap->I.secretAm, 20);
-        satoshisToWei(input20.amount, swap->I.bobrealsat);
+        LP_etomic_wei(input20.amount, swap->I.bobamount, swap->I.bobrealsat, LP_AMOUNT_VALUE);
         strcpy(input20.tokenAddress, swap->I.bobtomic);
         input20.lockTime = swap->bobpayment.I.locktime;
         input20.decimals = bobcoin->decimals;
 
         strcpy(txData.from, swap->I.etomicsrc);
-        strcpy(txData.to, ETOMIC_BOBCONTRACT);
+        strcpy(txData.to, contract);
         strcpy(txData.amount, "0");
         uint8arrayToHex(txData.secretKey, swap->persistent_privkey.bytes, 32);
 
-        uint64_t allowance = getErc20Allowance(swap->I.etomicsrc, ETOMIC_BOBCONTRACT, swap->I.bobtomic, bobcoin->decimals);
+        uint64_t allowance = getErc20Allowance(swap->I.etomicsrc, contract, swap->I.bobtomic, bobcoin->decimals);
         if (allowance < swap->I.bobrealsat) {
             printf("Bob token allowance is too low, setting new allowance\n");
             ApproveErc20Input approveErc20Input;
             strcpy(approveErc20Input.tokenAddress, swap->I.bobtomic);
             strcpy(approveErc20Input.owner, swap->I.etomicsrc);
-            strcpy(approveErc20Input.spender, ETOMIC_BOBCONTRACT);
+            strcpy(approveErc20Input.spender, contract);
 
             char *tokenBalance = getErc20BalanceHexWei(swap->I.etomicsrc, swap->I.bobtomic);
             strcpy(approveErc20Input.amount, tokenBalance);
@@ -500,7 +603,7 @@ char *LP_etomicbob_sends_payment(struct basilisk_swap *swap)
             strcpy(approveErc20Input.secret, txData.secretKey);
 
             char *allowTxId = approveErc20(approveErc20Input);
-            LP_etomic_wait_for_confirmation(allowTxId);
+            LP_etomic_wait_for_confirmation(swap->I.bobstr,allowTxId);
             free(allowTxId);
         }
 
@@ -510,12 +613,13 @@ char *LP_etomicbob_sends_payment(struct basilisk_swap *swap)
 
 uint8_t LP_etomic_verify_bob_payment(struct basilisk_swap *swap, char *txId)
 {
+    LP_etomic_select(swap->I.bobstr);
     if (waitForConfirmation(txId) < 0) {
         printf("Bob payment %s is not found\n", txId);
         return 0;
     }
     EthTxData data = getEthTxData(txId);
-    if (compareAddresses(data.to, ETOMIC_BOBCONTRACT) == 0) {
+    if (compareAddresses(data.to, swap->I.bobcontract) == 0) {
         printf("Bob payment %s was sent to wrong address %s\n", txId, data.to);
     }
     if (compareAddresses(data.from, swap->I.etomicsrc) == 0) {
@@ -526,9 +630,10 @@ uint8_t LP_etomic_verify_bob_payment(struct basilisk_swap *swap, char *txId)
     memset(&input,0,sizeof(input));
     memset(&input20,0,sizeof(input20));
     if ( strcmp(swap->I.bobstr,"ETH") == 0 ) {
-        uint64_t paymentAmount = weiToSatoshi(data.valueHex, 18);
-        if (paymentAmount != swap->I.bobrealsat) {
-            printf("Bob payment %s amount %" PRIu64 " != expected %" PRIu64 "\n", txId, paymentAmount, swap->I.bobrealsat);
+        char weiAmount[100];
+        LP_etomic_wei(weiAmount, swap->I.bobamount, swap->I.bobrealsat, LP_AMOUNT_VALUE);
+        if (LP_amount_weiequal(data.valueHex, weiAmount) == 0) {
+            printf("Bob payment %s amount %s != expected %s wei\n", txId, data.valueHex, weiAmount);
             return(0);
         }
         uint8arrayToHex(input.paymentId, swap->bobpayment.I.actualtxid.bytes, 32);
@@ -543,7 +648,7 @@ uint8_t LP_etomic_verify_bob_payment(struct basilisk_swap *swap, char *txId)
         uint8arrayToHex(input20.paymentId, swap->bobpayment.I.actualtxid.bytes, 32);
         strcpy(input20.aliceAddress, swap->I.etomicdest);
         uint8arrayToHex(input20.aliceHash, swap->I.secretAm, 20);
-        satoshisToWei(input20.amount, swap->I.bobrealsat);
+        LP_etomic_wei(input20.amount, swap->I.bobamount, swap->I.bobrealsat, LP_AMOUNT_VALUE);
         strcpy(input20.tokenAddress, swap->I.bobtomic);
         input20.lockTime = swap->bobpayment.I.locktime;
         input20.decimals = bobcoin->decimals;
@@ -554,10 +659,12 @@ uint8_t LP_etomic_verify_bob_payment(struct basilisk_swap *swap, char *txId)
 
 char *LP_etomicbob_reclaims_payment(struct LP_swap_remember *swap)
 {
+    LP_etomic_select(swap->src);
     if (waitForConfirmation(swap->eth_tx_ids[BASILISK_BOBPAYMENT]) < 0) {
         printf("Bob payment %s is not found, can't reclaim\n", swap->eth_tx_ids[BASILISK_BOBPAYMENT]);
         return NULL;
     }
+    char *contract = LP_etomic_paymentcontract(swap->src,1,swap->bobcontract,swap->eth_tx_ids[BASILISK_BOBPAYMENT]);
     BobReclaimsBobPaymentInput input;
     BasicTxData txData;
     memset(&txData,0,sizeof(txData));
@@ -575,7 +682,7 @@ char *LP_etomicbob_reclaims_payment(struct LP_swap_remember *swap)
         return NULL;
     }
     uint8arrayToHex(input.paymentId, swap->txids[BASILISK_BOBPAYMENT].bytes, 32);
-    if (bobPaymentStatus(input.paymentId + 2) != BOB_PAYMENT_SENT) {
+    if (bobPaymentStatus(contract, input.paymentId + 2) != BOB_PAYMENT_SENT) {
         printf("Bob payment smart contract status check failed, can't spend\n");
         return NULL;
     }
@@ -587,11 +694,11 @@ char *LP_etomicbob_reclaims_payment(struct LP_swap_remember *swap)
     } else {
         strcpy(input.tokenAddress, "0x0000000000000000000000000000000000000000");
     }
-    satoshisToWei(input.amount, swap->bobrealsat);
+    LP_etomic_wei(input.amount, swap->bobamount, swap->bobrealsat, LP_AMOUNT_VALUE);
     input.decimals = bobcoin->decimals;
 
     strcpy(txData.from, swap->etomicsrc);
-    strcpy(txData.to, ETOMIC_BOBCONTRACT);
+    strcpy(txData.to, contract);
     strcpy(txData.amount, "0");
     uint8arrayToHex(txData.secretKey, privkey.bytes, 32);
     return bobReclaimsBobPayment(input, txData);
@@ -599,10 +706,12 @@ char *LP_etomicbob_reclaims_payment(struct LP_swap_remember *swap)
 
 char *LP_etomicalice_spends_bob_payment(struct LP_swap_remember *swap)
 {
+    LP_etomic_select(swap->src);
     if (waitForConfirmation(swap->eth_tx_ids[BASILISK_BOBPAYMENT]) < 0) {
         printf("Bob payment %s is not found, can't spend\n", swap->eth_tx_ids[BASILISK_BOBPAYMENT]);
         return NULL;
     }
+    char *contract = LP_etomic_paymentcontract(swap->src,1,swap->bobcontract,swap->eth_tx_ids[BASILISK_BOBPAYMENT]);
     AliceSpendsBobPaymentInput input;
     BasicTxData txData;
 
@@ -620,11 +729,11 @@ char *LP_etomicalice_spends_bob_payment(struct LP_swap_remember *swap)
     privkey = LP_privkey(ecoin->symbol, ecoin->smartaddr, ecoin->taddr);
 
     uint8arrayToHex(input.paymentId, swap->txids[BASILISK_BOBPAYMENT].bytes, 32);
-    if (bobPaymentStatus(input.paymentId + 2) != BOB_PAYMENT_SENT) {
+    if (bobPaymentStatus(contract, input.paymentId + 2) != BOB_PAYMENT_SENT) {
         printf("Bob payment smart contract status check failed, can't spend\n");
         return NULL;
     }
-    satoshisToWei(input.amount, swap->bobrealsat);
+    LP_etomic_wei(input.amount, swap->bobamount, swap->bobrealsat, LP_AMOUNT_VALUE);
 
     if (swap->bobtomic[0] != 0) {
         strcpy(input.tokenAddress, swap->bobtomic);
@@ -642,7 +751,7 @@ char *LP_etomicalice_spends_bob_payment(struct LP_swap_remember *swap)
     input.decimals = bobcoin->decimals;
 
     strcpy(txData.from, swap->etomicdest);
-    strcpy(txData.to, ETOMIC_BOBCONTRACT);
+    strcpy(txData.to, contract);
     strcpy(txData.amount, "0");
     uint8arrayToHex(txData.secretKey, privkey.bytes, 32);
     return aliceSpendsBobPayment(input, txData);
@@ -650,10 +759,12 @@ char *LP_etomicalice_spends_bob_payment(struct LP_swap_remember *swap)
 
 char *LP_etomicalice_claims_bob_deposit(struct LP_swap_remember *swap)
 {
+    LP_etomic_select(swap->src);
     if (waitForConfirmation(swap->eth_tx_ids[BASILISK_BOBDEPOSIT]) < 0) {
         printf("Bob deposit %s is not found, can't claim\n", swap->eth_tx_ids[BASILISK_BOBDEPOSIT]);
         return NULL;
     }
+    char *contract = LP_etomic_paymentcontract(swap->src,1,swap->bobcontract,swap->eth_tx_ids[BASILISK_BOBDEPOSIT]);
     AliceClaimsBobDepositInput input;
     BasicTxData txData;
 
@@ -672,12 +783,12 @@ char *LP_etomicalice_claims_bob_deposit(struct LP_swap_remember *swap)
     privkey = LP_privkey(ecoin->symbol, ecoin->smartaddr, ecoin->taddr);
 
     uint8arrayToHex(input.depositId, swap->txids[BASILISK_BOBDEPOSIT].bytes, 32);
-    if (bobDepositStatus(input.depositId + 2) != BOB_DEPOSIT_SENT) {
+    if (bobDepositStatus(contract, input.depositId + 2) != BOB_DEPOSIT_SENT) {
         printf("Bob deposit smart contract status check failed, can't claim\n");
         return NULL;
     }
 
-    satoshisToWei(input.amount, LP_DEPOSITSATOSHIS(swap->bobrealsat));
+    LP_etomic_wei(input.amount, swap->bobamount, swap->bobrealsat, LP_AMOUNT_DEPOSIT);
 
     if (swap->bobtomic[0] != 0) {
         strcpy(input.tokenAddress, swap->bobtomic);
@@ -690,7 +801,7 @@ char *LP_etomicalice_claims_bob_deposit(struct LP_swap_remember *swap)
     input.decimals = bobcoin->decimals;
 
     strcpy(txData.from, swap->etomicdest);
-    strcpy(txData.to, ETOMIC_BOBCONTRACT);
+    strcpy(txData.to, contract);
     strcpy(txData.amount, "0");
     uint8arrayToHex(txData.secretKey, privkey.bytes, 32);
     return aliceClaimsBobDeposit(input, txData);
@@ -766,6 +877,7 @@ uint8_t LP_etomic_is_empty_tx_id(char *txId)
 
 uint64_t LP_etomic_get_balance(struct iguana_info *coin, char *coinaddr, int *error)
 {
+    LP_etomic_select(coin->symbol);
     if (coin->etomic[0] == 0) {
         printf("Trying to get etomic balance for non-etomic coin %s!", coin->symbol);
         return 0;
@@ -826,7 +938,7 @@ void LP_etomic_txhistory_loop(void *_coin)
         printf("Calling ETOMIC tx history loop for non-ETOMIC coin %s\n", coin->symbol);
         return;
     }
-
+    LP_etomic_select(coin->symbol);
     while (coin != NULL && coin->inactive == 0) {
         coin->height = (int32_t) getEthBlockNumber();
         char *result;
@@ -876,6 +988,59 @@ void LP_etomic_txhistory_loop(void *_coin)
         portable_mutex_lock(&coin->tx_history_mutex);
         DL_SORT(coin->tx_history, ptr);
         portable_mutex_unlock(&coin->tx_history_mutex);
+        if (coin->txhistory_synced == 0) {
+            coin->txhistory_synced = (uint32_t)time(NULL);
+        }
         sleep(30);
     }
 }
+
+cJSON *LP_etomic_deploy(char *url,uint64_t chainid,char *canonical,char *bytecode,int64_t gas,bits256 privkey)
+{
+    char secretKey[70],*txId,*contractAddr = 0,*refcode = 0,*newcode = 0; uint64_t gasPrice; int32_t i; cJSON *retjson = cJSON_CreateObject();
+    if ( getChainIdUrl(url) != chainid )
+    {
+        jaddstr(retjson,"error","node chain id doesnt match chainid");
+        return(retjson);
+    }
+    if ( (refcode= getCodeUrl(ETOMIC_URL,canonical)) == 0 || strlen(refcode) <= 2 )
+    {
+        jaddstr(retjson,"error","couldnt fetch canonical swap contract code");
+        if ( refcode != 0 )
+            free(refcode);
+        return(retjson);
+    }
+    if ( (gasPrice= getGasPriceUrl(url)) == 0 )
+        gasPrice = (uint64_t)DEFAULT_GAS_PRICE * 1000000000;
+    uint8arrayToHex(secretKey,privkey.bytes,32);
+    if ( (txId= deployContract(url,bytecode,secretKey,gas,gasPrice)) == 0 )
+    {
+        jaddstr(retjson,"error","contract creation tx was not accepted");
+        free(refcode);
+        return(retjson);
+    }
+    jaddstr(retjson,"txid",txId);
+    for (i=0; i<40; i++)
+    {
+        if ( (contractAddr= getContractAddressUrl(url,txId)) != 0 )
+            break;
+        printf("waiting for swap contract deployment %s\n",txId);
+        sleep(15);
+    }
+    free(txId);
+    if ( contractAddr == 0 )
+    {
+        jaddstr(retjson,"error","contract creation tx not confirmed");
+        free(refcode);
+        return(retjson);
+    }
+    jaddstr(retjson,"contract",contractAddr);
+    if ( (newcode= getCodeUrl(url,contractAddr)) == 0 || strcasecmp(newcode,refcode) != 0 )
+        jaddstr(retjson,"error","deployed bytecode doesnt match the canonical swap contract");
+    else jaddstr(retjson,"result","success");
+    if ( newcode != 0 )
+        free(newcode);
+    free(contractAddr);
+    free(refcode);
+    return(retjson);
+}
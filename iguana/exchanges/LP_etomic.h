@@ -9,7 +9,19 @@
 #include <inttypes.h>
 #include "LP_include.h"
 
-int32_t LP_etomic_wait_for_confirmation(char *txId);
+int32_t LP_etomic_wait_for_confirmation(char *symbol,char *txId);
+
+char *LP_etomic_rpcurl(char *symbol);
+
+void LP_etomic_select(char *symbol);
+
+char *LP_etomic_override(char *symbol,int32_t bobflag,int32_t fallbackflag);
+
+char *LP_etomic_contract(char *symbol,int32_t bobflag);
+
+int32_t LP_etomic_contractknown(char *symbol,int32_t bobflag,char *contract);
+
+char *LP_etomic_paymentcontract(char *symbol,int32_t bobflag,char *negotiated,char *txId);
 
 char *LP_etomicalice_send_fee(struct basilisk_swap *swap);
 
@@ -55,4 +67,6 @@ void LP_etomic_pubkeystr_to_addr(char *pubkey, char *output);
 
 void LP_etomic_txhistory_loop(void *_coin);
 
+cJSON *LP_etomic_deploy(char *url,uint64_t chainid,char *canonical,char *bytecode,int64_t gas,bits256 privkey);
+
 #endif //SUPERNET_LP_ETOMIC_H
@@ -0,0 +1,334 @@
+
+/******************************************************************************
+ * Copyright © 2014-2018 The SuperNET Developers.                             *
+ *                                                                            *
+ * See the AUTHORS, DEVELOPER-AGREEMENT and LICENSE files at                  *
+ * the top-level directory of this distribution for the individual copyright  *
+ * holder information and the developer policies on copyright and licensing.  *
+ *                                                                            *
+ * Unless otherwise agreed in a custom licensing agreement, no part of the    *
+ * SuperNET software, including this file may be copied, modified, propagated *
+ * or distributed except according to the terms contained in the LICENSE file *
+ *                                                                            *
+ * Removal or modification of this copyright notice is prohibited.            *
+ *                                                                            *
+ ******************************************************************************/
+//
+//  LP_hdwallet.c
+//  marketmaker
+//
+//  BIP32 keys per coin along m/44'/bip44'/hd_account'/chain/index, seeded from the passphrase the way BIP39 seeds a mnemonic
+//
+
+#define LP_HD_HARDENED 0x80000000
+#define LP_HD_GAPLIMIT 20
+#define LP_HD_MAXGAPLIMIT 100
+#define LP_HD_MAXADDRS 256
+
+void hmac_sha512str(const unsigned char *key,unsigned int key_size,const unsigned char *message,unsigned int message_len,unsigned char *mac,unsigned mac_size);
+
+uint8_t LP_hdseed[64]; int32_t LP_hdseedvalid;
+portable_mutex_t LP_hdmutex;
+
+void LP_pbkdf2_sha512(uint8_t out[64],uint8_t *password,int32_t passlen,char *salt,int32_t iters)
+{
+    uint8_t U[64],prev[64],buf[256]; int32_t i,j,saltlen;
+    saltlen = (int32_t)strlen(salt);
+    memcpy(buf,salt,saltlen);
+    buf[saltlen] = buf[saltlen+1] = buf[saltlen+2] = 0, buf[saltlen+3] = 1;
+    hmac_sha512str(password,passlen,buf,saltlen+4,U,64);
+    memcpy(out,U,64);
+    for (i=1; i<iters; i++)
+    {
+        memcpy(prev,U,64);
+        hmac_sha512str(password,passlen,prev,64,U,64);
+        for (j=0; j<64; j++)
+            out[j] ^= U[j];
+    }
+}
+
+void LP_hdwallet_init(char *passphrase)
+{
+    portable_mutex_lock(&LP_hdmutex);
+    memset(LP_hdseed,0,sizeof(LP_hdseed));
+    if ( (LP_hdseedvalid= (passphrase != 0 && passphrase[0] != 0)) != 0 )
+        LP_pbkdf2_sha512(LP_hdseed,(uint8_t *)passphrase,(int32_t)strlen(passphrase),"mnemonic",2048);
+    portable_mutex_unlock(&LP_hdmutex);
+}
+
+int32_t LP_bip32_child(void *ctx,bits256 *privkeyp,bits256 *chaincodep,uint32_t index)
+{
+    uint8_t data[37],I[64]; int32_t len = 0;
+    if ( (index & LP_HD_HARDENED) != 0 )
+    {
+        data[len++] = 0;
+        memcpy(&data[len],privkeyp->bytes,32), len += 32;
+    }
+    else
+    {
+        bitcoin_pubkey33(ctx,data,*privkeyp);
+        len += 33;
+    }
+    data[len++] = (index >> 24) & 0xff;
+    data[len++] = (index >> 16) & 0xff;
+    data[len++] = (index >> 8) & 0xff;
+    data[len++] = index & 0xff;
+    hmac_sha512str(chaincodep->bytes,32,data,len,I,64);
+    if ( secp256k1_ec_privkey_tweak_add(ctx,privkeyp->bytes,I) == 0 )
+        return(-1);
+    memcpy(chaincodep->bytes,&I[32],32);
+    return(0);
+}
+
+int32_t LP_hdkey(void *ctx,bits256 *privkeyp,uint8_t *pubkey33,char *coinaddr,struct iguana_info *coin,uint32_t chain,uint32_t index)
+{
+    bits256 privkey,chaincode; uint8_t I[64]; uint32_t path[5]; int32_t i;
+    if ( LP_hdseedvalid == 0 || coin->hdwallet == 0 || index >= LP_HD_HARDENED )
+        return(-1);
+    hmac_sha512str((uint8_t *)"Bitcoin seed",12,LP_hdseed,sizeof(LP_hdseed),I,64);
+    memcpy(privkey.bytes,I,32);
+    memcpy(chaincode.bytes,&I[32],32);
+    path[0] = 44 | LP_HD_HARDENED;
+    path[1] = coin->bip44 | LP_HD_HARDENED;
+    path[2] = coin->hdaccount | LP_HD_HARDENED;
+    path[3] = chain;
+    path[4] = index;
+    for (i=0; i<5; i++)
+        if ( LP_bip32_child(ctx,&privkey,&chaincode,path[i]) < 0 )
+            return(-1);
+    bitcoin_priv2pub(ctx,coin->symbol,pubkey33,coinaddr,privkey,coin->taddr,coin->pubtype);
+    *privkeyp = privkey;
+    return(0);
+}
+
+char *LP_hdpath(char *pathstr,struct iguana_info *coin,uint32_t chain,uint32_t index)
+{
+    sprintf(pathstr,"m/44'/%u'/%u'/%u/%u",coin->bip44,coin->hdaccount,chain,index);
+    return(pathstr);
+}
+
+struct LP_hdaddress *_LP_hdaddress_add(void *ctx,struct iguana_info *coin,uint32_t chain,uint32_t index)
+{
+    struct LP_hdaddress *hp; bits256 privkey; uint8_t pubkey33[33],rmd160[20],addrtype; char coinaddr[64]; int32_t i;
+    for (i=0; i<coin->numhdaddrs; i++)
+        if ( coin->hdaddrs[i].chain == chain && coin->hdaddrs[i].index == index )
+            return(&coin->hdaddrs[i]);
+    if ( coin->numhdaddrs >= LP_HD_MAXADDRS || LP_hdkey(ctx,&privkey,pubkey33,coinaddr,coin,chain,index) < 0 )
+        return(0);
+    bitcoin_addr2rmd160(coin->symbol,coin->taddr,&addrtype,rmd160,coinaddr);
+    if ( LP_privkeyadd(privkey,rmd160) < 0 )
+        return(0);
+    coin->hdaddrs = realloc(coin->hdaddrs,sizeof(*coin->hdaddrs) * (coin->numhdaddrs + 1));
+    hp = &coin->hdaddrs[coin->numhdaddrs++];
+    memset(hp,0,sizeof(*hp));
+    safecopy(hp->coinaddr,coinaddr,sizeof(hp->coinaddr));
+    memcpy(hp->pubkey33,pubkey33,33);
+    hp->chain = chain;
+    hp->index = index;
+    LP_address(coin,coinaddr);
+    if ( coin->electrum == 0 && coin->inactive == 0 && coin->userpass[0] != 0 )
+        LP_importaddress(coin->symbol,coinaddr);
+    return(hp);
+}
+
+// an HD coin trades from m/44'/bip44'/hd_account'/0/0, so swaps select utxos and sign with the derived key
+void LP_hdwallet_coininit(void *ctx,struct iguana_info *coin)
+{
+    struct LP_hdaddress *hp;
+    if ( coin->hdwallet == 0 || coin->etomic[0] != 0 )
+        return;
+    portable_mutex_lock(&LP_hdmutex);
+    if ( coin->hdaddrs != 0 )
+        free(coin->hdaddrs), coin->hdaddrs = 0;
+    coin->numhdaddrs = 0;
+    memset(coin->hdnext,0,sizeof(coin->hdnext));
+    if ( (hp= _LP_hdaddress_add(ctx,coin,0,0)) != 0 )
+    {
+        coin->hdnext[0] = 1;
+        memcpy(coin->pubkey33,hp->pubkey33,33);
+        strcpy(coin->smartaddr,hp->coinaddr);
+        printf("%s HD address %s m/44'/%u'/%u'/0/0\n",coin->symbol,coin->smartaddr,coin->bip44,coin->hdaccount);
+    } else printf("%s HD derivation failed, staying on %s\n",coin->symbol,coin->smartaddr);
+    portable_mutex_unlock(&LP_hdmutex);
+}
+
+// fresh receive address for the output a swap claims from the other side, so repeated trades dont all land on one address.
+// it is only pending until the swap completes, hdnext moves past it then, so failed swaps dont open a gap a rescan cant cross
+int32_t LP_hd_swapaddress(struct iguana_info *coin,uint8_t *pubkey33,char *pathstr)
+{
+    static void *ctx; struct LP_hdaddress *hp; uint32_t index; int32_t retval = -1;
+    if ( coin->hdwallet == 0 || LP_hdseedvalid == 0 || coin->etomic[0] != 0 || coin->signerurl[0] != 0 )
+        return(-1);
+    if ( ctx == 0 )
+        ctx = bitcoin_ctx();
+    portable_mutex_lock(&LP_hdmutex);
+    for (index=coin->hdnext[0]; (hp= _LP_hdaddress_add(ctx,coin,0,index)) != 0; index++)
+    {
+        if ( hp->used == 0 && hp->pending == 0 )
+        {
+            hp->pending = 1;
+            memcpy(pubkey33,hp->pubkey33,33);
+            LP_hdpath(pathstr,coin,hp->chain,hp->index);
+            retval = 0;
+            break;
+        }
+    }
+    portable_mutex_unlock(&LP_hdmutex);
+    return(retval);
+}
+
+int32_t LP_hd_pathparse(struct iguana_info *coin,char *pathstr,uint32_t *chainp,uint32_t *indexp)
+{
+    uint32_t bip44,account;
+    if ( sscanf(pathstr,"m/44'/%u'/%u'/%u/%u",&bip44,&account,chainp,indexp) != 4 || bip44 != coin->bip44 || account != coin->hdaccount || *chainp > 1 )
+    {
+        printf("%s swap claim path %s is not on this wallet's account\n",coin->symbol,pathstr);
+        return(-1);
+    }
+    return(0);
+}
+
+// a completed swap claimed to its address, a failed one gives it back to the next swap
+void LP_hd_swapaddress_done(struct iguana_info *coin,char *pathstr,int32_t used)
+{
+    static void *ctx; struct LP_hdaddress *hp; uint32_t chain,index;
+    if ( coin == 0 || coin->hdwallet == 0 || LP_hdseedvalid == 0 || pathstr == 0 || pathstr[0] == 0 || LP_hd_pathparse(coin,pathstr,&chain,&index) < 0 )
+        return;
+    if ( ctx == 0 )
+        ctx = bitcoin_ctx();
+    portable_mutex_lock(&LP_hdmutex);
+    if ( (hp= _LP_hdaddress_add(ctx,coin,chain,index)) != 0 )
+    {
+        hp->pending = 0;
+        if ( used != 0 )
+        {
+            hp->used++;
+            if ( index >= coin->hdnext[chain] )
+                coin->hdnext[chain] = index + 1;
+        }
+    }
+    portable_mutex_unlock(&LP_hdmutex);
+}
+
+// swap records carry the path of their claim address, so the key is back in the wallet before the swap is resumed
+void LP_hd_swapaddress_restore(struct iguana_info *coin,char *pathstr)
+{
+    static void *ctx; struct LP_hdaddress *hp; uint32_t chain,index;
+    if ( coin->hdwallet == 0 || LP_hdseedvalid == 0 || pathstr == 0 || LP_hd_pathparse(coin,pathstr,&chain,&index) < 0 )
+        return;
+    if ( ctx == 0 )
+        ctx = bitcoin_ctx();
+    portable_mutex_lock(&LP_hdmutex);
+    if ( (hp= _LP_hdaddress_add(ctx,coin,chain,index)) != 0 && hp->used == 0 )
+        hp->pending = 1;
+    portable_mutex_unlock(&LP_hdmutex);
+}
+
+cJSON *LP_hdaddress_json(struct iguana_info *coin,struct LP_hdaddress *hp)
+{
+    cJSON *item; char pathstr[64];
+    item = cJSON_CreateObject();
+    jaddstr(item,"address",hp->coinaddr);
+    jaddstr(item,"path",LP_hdpath(pathstr,coin,hp->chain,hp->index));
+    jaddnum(item,"chain",hp->chain);
+    jaddnum(item,"index",hp->index);
+    if ( hp->used != 0 )
+        jaddnum(item,"used",hp->used);
+    else if ( hp->pending != 0 )
+        jaddnum(item,"pending",1);
+    return(item);
+}
+
+char *LP_get_new_address(struct iguana_info *coin,int32_t change)
+{
+    static void *ctx; struct LP_hdaddress *hp; uint32_t index; cJSON *retjson = 0;
+    if ( coin->hdwallet == 0 || LP_hdseedvalid == 0 )
+        return(clonestr("{\"error\":\"coin is not configured for HD derivation, set bip44 in its config\"}"));
+    if ( ctx == 0 )
+        ctx = bitcoin_ctx();
+    change = (change != 0);
+    portable_mutex_lock(&LP_hdmutex);
+    for (index=coin->hdnext[change]; (hp= _LP_hdaddress_add(ctx,coin,change,index)) != 0; index++)
+    {
+        if ( hp->pending == 0 )
+        {
+            coin->hdnext[change] = index + 1;
+            retjson = LP_hdaddress_json(coin,hp);
+            break;
+        }
+    }
+    portable_mutex_unlock(&LP_hdmutex);
+    if ( retjson == 0 )
+        return(clonestr("{\"error\":\"cant derive another address\"}"));
+    jaddstr(retjson,"result","success");
+    jaddstr(retjson,"coin",coin->symbol);
+    return(jprint(retjson,1));
+}
+
+char *LP_hd_addresses(struct iguana_info *coin)
+{
+    cJSON *retjson,*array; int32_t i;
+    if ( coin->hdwallet == 0 || LP_hdseedvalid == 0 )
+        return(clonestr("{\"error\":\"coin is not configured for HD derivation, set bip44 in its config\"}"));
+    array = cJSON_CreateArray();
+    portable_mutex_lock(&LP_hdmutex);
+    for (i=0; i<coin->numhdaddrs; i++)
+        jaddi(array,LP_hdaddress_json(coin,&coin->hdaddrs[i]));
+    portable_mutex_unlock(&LP_hdmutex);
+    retjson = cJSON_CreateObject();
+    jaddstr(retjson,"result","success");
+    jaddstr(retjson,"coin",coin->symbol);
+    jaddnum(retjson,"bip44",coin->bip44);
+    jaddnum(retjson,"account",coin->hdaccount);
+    jaddnum(retjson,"next_receive",coin->hdnext[0]);
+    jaddnum(retjson,"next_change",coin->hdnext[1]);
+    jadd(retjson,"addresses",array);
+    return(jprint(retjson,1));
+}
+
+// walks both chains until gap_limit consecutive addresses have no history, native coins only see addresses their wallet already watches
+char *LP_hd_scan(struct iguana_info *coin,int32_t gaplimit)
+{
+    static void *ctx; struct LP_hdaddress *hp; cJSON *history; uint32_t chain,index; int32_t gap,n; char coinaddr[64];
+    if ( coin->hdwallet == 0 || LP_hdseedvalid == 0 )
+        return(clonestr("{\"error\":\"coin is not configured for HD derivation, set bip44 in its config\"}"));
+    if ( coin->inactive != 0 )
+        return(clonestr("{\"error\":\"coin is not active\"}"));
+    if ( gaplimit <= 0 )
+        gaplimit = LP_HD_GAPLIMIT;
+    else if ( gaplimit > LP_HD_MAXGAPLIMIT )
+        gaplimit = LP_HD_MAXGAPLIMIT;
+    if ( ctx == 0 )
+        ctx = bitcoin_ctx();
+    for (chain=0; chain<2; chain++)
+    {
+        for (index=gap=0; gap<gaplimit; index++)
+        {
+            // hdaddrs is realloced as it grows, so only the copied address is used outside the mutex
+            portable_mutex_lock(&LP_hdmutex);
+            if ( (hp= _LP_hdaddress_add(ctx,coin,chain,index)) != 0 )
+                safecopy(coinaddr,hp->coinaddr,sizeof(coinaddr));
+            portable_mutex_unlock(&LP_hdmutex);
+            if ( hp == 0 )
+                break;
+            n = 0;
+            if ( (history= LP_listreceivedbyaddress(coin->symbol,coinaddr)) != 0 )
+            {
+                if ( is_cJSON_Array(history) != 0 )
+                    n = cJSON_GetArraySize(history);
+                free_json(history);
+            }
+            if ( n > 0 )
+            {
+                portable_mutex_lock(&LP_hdmutex);
+                if ( (hp= _LP_hdaddress_add(ctx,coin,chain,index)) != 0 )
+                    hp->used = n;
+                if ( index >= coin->hdnext[chain] )
+                    coin->hdnext[chain] = index + 1;
+                portable_mutex_unlock(&LP_hdmutex);
+                gap = 0;
+            } else gap++;
+        }
+    }
+    return(LP_hd_addresses(coin));
+}
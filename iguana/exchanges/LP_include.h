@@ -33,6 +33,10 @@
 
 void dontprintf(char *formatstr,...) {}
 //void fdontprintf(FILE *fp,char *formatstr,...) {}
+#elif !defined(__cplusplus)
+#define printf(...) LP_logf(__FILE__,3,__VA_ARGS__) // level and sinks per module, swap threads tag lines with role and uuid, see LP_log.c
+
+int LP_logf(const char *srcfile,int32_t level,const char *formatstr,...);
 #endif
 
 #define LP_MAJOR_VERSION "0"
@@ -43,6 +47,12 @@ void dontprintf(char *formatstr,...) {}
 
 #define LP_DONT_IMPORTPRIVKEY
 
+#ifdef _MSC_VER
+#define LP_THREADLOCAL __declspec(thread)
+#else
+#define LP_THREADLOCAL __thread
+#endif
+
 #ifdef FROM_JS
 #include <emscripten.h>
 #define sleep(x) emscripten_usleep((x) * 1000000)
@@ -62,8 +72,16 @@ void emscripten_usleep(int32_t x); // returns immediate, no sense for sleeping
 #define LP_HTTP_TIMEOUT 10 // 1 is too small due to edge cases of time(NULL)
 #define LP_AUTOTRADE_TIMEOUT 30
 #define LP_RESERVETIME (LP_AUTOTRADE_TIMEOUT * 3)
+#define LP_LOCKTIME_TOLERANCE 300
+#define LP_DEXFEE_TOLERANCE 0.005 // fraction the counterparty's amounts and dexfee may differ by from ours
+#define LP_DEXFEE_MAXTOLERANCE 0.05
+#define LP_MAXEXPOSURES 4096
+#define LP_EXPOSURE_WINDOW (3600 * 24)
+#define LP_COLDSWEEP_INTERVAL 600
+#define LP_AUTOPRICE_MAXAGE 600 // reject connects if autoprice feed hasnt updated within this many seconds
 #define ELECTRUM_TIMEOUT 13
 #define LP_ELECTRUM_KEEPALIVE 60
+#define LP_TXHISTORY_PAGE 100
 #define LP_ELECTRUM_MAXERRORS 777
 #define LP_MEMPOOL_TIMEINCR 10
 #define LP_SCREENWIDTH 1024
@@ -87,6 +105,7 @@ void emscripten_usleep(int32_t x); // returns immediate, no sense for sleeping
 #define LP_ENCRYPTED_MAXSIZE (16384 + 2 + crypto_box_NONCEBYTES + crypto_box_ZEROBYTES)
 
 #define LP_MAXPUBKEY_ERRORS 10
+#define LP_PRICE_SIGVERSION 1 // price signatures that also cover the advertised liquidity
 #define PSOCK_KEEPALIVE 3600
 #define MAINLOOP_PERSEC 100
 #define MAX_PSOCK_PORT 60000
@@ -99,17 +118,64 @@ void emscripten_usleep(int32_t x); // returns immediate, no sense for sleeping
 #define LP_PEERGOOD_ERRORDECAY 0.9
 
 #define LP_SWAPSTEP_TIMEOUT 30
+#define LP_SWAPPHASES 4 // negotiation, fee, payment, spend
+#define LP_SWAPCHANNELS 2 // pair, cmdchannel
+#define LP_SWAPRELAY_SILENCE 120 // counterparty silent this long and its pair socket may be gone, copies go over the cmdchannel too
+#define LP_SWAPMSG_HASHES 64
+#define LP_MIN_SWAPSTEP_TIMEOUT 15
+#define LP_PUBKEYS_TIMEOUT 120
+#define LP_PUBKEYS_VERSION 3 // bump whenever the pubkeys payload layout changes
+#define LP_AMOUNTSTRLEN 48 // decimal amount in coin units, up to LP_AMOUNT_DECIMALS past the point
+#define LP_AMOUNT_DECIMALS 18
+#define LP_AMOUNT_VALUE 0
+#define LP_AMOUNT_DEXFEE 1
+#define LP_AMOUNT_DEPOSIT 2
+#define LP_MIN_PUBKEYS_TIMEOUT 60
+#define LP_MIN_TXWAIT_TIMEOUT 600
+#define LP_CONFIRMPOLL_MIN 5
+#define LP_CONFIRMPOLL_MAX 120
+#define LP_CONFIRM_TIMEOUT_FACTOR 3 // confirmation waits give up after this many times the expected block interval
+#define LP_FEEBUMP_DELAY 1800
+#define LP_FEEBUMP_MAX 3
+#define LP_RBF_SEQUENCE 0xfffffffd
+#define LP_CLAIMTX_SIZE 400
+#define LP_CLAIMMARGIN_MULT 3
+#define LP_CLAIMFEE_MAXMULT 4
 #define LP_MIN_TXFEE 1000
+#define LP_MIN_TRADEVOL (LP_MIN_TXFEE * LP_MINSIZE_TXFEEMULT)
 #define LP_MINVOL 100
 #define LP_MINCLIENTVOL 1000
 #define LP_MINSIZE_TXFEEMULT 10
 #define LP_REQUIRED_TXFEE 0.75
 
-#define LP_DEXFEE(destsatoshis) ((destsatoshis) / INSTANTDEX_INSURANCEDIV)
+#define LP_DEXFEE(destsatoshis) ((destsatoshis) / LP_dexfee_div)
 #define LP_DEPOSITSATOSHIS(satoshis) ((satoshis) + (satoshis >> 3))
 
 #define INSTANTDEX_DECKSIZE 1000
 #define INSTANTDEX_LOCKTIME (3600*2 + 300*2)
+#define LP_MIN_LOCKTIME 3600
+#define LP_SCHEMA_VERSION 2 // bump together with a new entry in LP_migrations
+#define LP_PROTOCOL_VERSION 2 // advertised in the top byte of the order caps
+#define LP_SWAPSEQ_PROTOCOL 2 // swap messages carry uuid, both identities and a sequence number
+
+// order capability bits, maker advertises them in postprice and reserved, taker can require them with "requirecaps"
+#define LP_CAP_SEGWIT 1
+#define LP_CAP_ZEROCONF 2
+// 4 is reserved for watchtower support
+#define LP_CAP_UNIFORMSPENDS 8
+#define LP_CAP_BURNFEE 0x10
+#define LP_CAP_CSV 0x20
+#define LP_CAP_MAKERFIRST 0x40
+#define LP_CSV_TYPEFLAG (1 << 22)
+#define LP_CAP_FLAGS 0xffffff
+#define LP_CAP_PROTOCOL(caps) ((caps) >> 24)
+#define LP_COUNTDOWN_INTERVAL 60
+#define LP_ADDRFORMAT_BASE58 0
+#define LP_ADDRFORMAT_CASHADDR 1
+#define LP_ADDRFORMAT_BECH32 2
+#define LP_MAXUTXOLOCKS 1024
+#define LP_MAXSWAPCHANGES 256
+#define LP_MAXPAIRLOCKTIMES 64
 #define INSTANTDEX_INSURANCEDIV 777
 #define INSTANTDEX_PUBKEY "03bc2c7ba671bae4a6fc835244c9762b41647b9827d4780a89a949b984a8ddcc06"
 #define INSTANTDEX_ETHADDR "0xd8997941dd1346e9231118d5685d866294f59e5b"
@@ -255,17 +321,19 @@ struct basilisk_rawtx
     char vinstr[8192],p2shaddr[64];
     cJSON *vins;
     bits256 utxotxid; int32_t utxovout;
+    uint32_t senttime; int32_t numbumps;
     uint8_t txbytes[16384],spendscript[512],redeemscript[1024],extraspace[4096],pubkey33[33];
 };
 
 struct basilisk_swapinfo
 {
     struct basilisk_request req;
-    char bobstr[128],alicestr[128],bobtomic[64],alicetomic[64],etomicsrc[65],etomicdest[65];
+    char bobstr[128],alicestr[128],bobtomic[64],alicetomic[64],etomicsrc[65],etomicdest[65],alicecontract[64],bobcontract[64];
     bits256 myhash,otherhash,orderhash;
     uint32_t statebits,otherstatebits,started,expiration,finished,dead,reftime,putduration,callduration;
-    int32_t bobconfirms,aliceconfirms,iambob,reclaimed,bobspent,alicespent,pad,aliceistrusted,bobistrusted,otheristrusted,otherstrust,alicemaxconfirms,bobmaxconfirms;
-    int64_t alicesatoshis,bobsatoshis,bobinsurance,aliceinsurance,Atxfee,Btxfee,alicerealsat,bobrealsat;
+    int32_t bobconfirms,aliceconfirms,iambob,reclaimed,bobspent,alicespent,internal,aliceistrusted,bobistrusted,otheristrusted,otherstrust,alicemaxconfirms,bobmaxconfirms,uniformspends,burnfee,csvrefunds,makerfirst,feeconfirm;
+    int64_t alicesatoshis,bobsatoshis,bobinsurance,aliceinsurance,Atxfee,Btxfee,alicerealsat,bobrealsat,Aclaimfee,Bclaimfee;
+    char aliceamount[LP_AMOUNTSTRLEN],bobamount[LP_AMOUNTSTRLEN];
     
     bits256 myprivs[2],mypubs[2],otherpubs[2],pubA0,pubA1,pubB0,pubB1,privAm,pubAm,privBn,pubBn;
     uint32_t crcs_mypub[2],crcs_mychoosei[2],crcs_myprivs[2],crcs_mypriv[2];
@@ -296,14 +364,15 @@ static char *txnames[] = { "myfee", "otherfee", "bobdeposit", "alicepayment", "b
 
 struct LP_swap_remember
 {
-    bits256 pubA0,pubB0,pubB1,privAm,privBn,paymentspent,Apaymentspent,depositspent,myprivs[2],txids[sizeof(txnames)/sizeof(*txnames)];
-    uint64_t Atxfee,Btxfee,srcamount,destamount,aliceid,alicerealsat,bobrealsat;
+    bits256 pubA0,pubB0,pubB1,privAm,privBn,paymentspent,Apaymentspent,depositspent,Apaymentblock,myprivs[2],txids[sizeof(txnames)/sizeof(*txnames)];
+    uint64_t Atxfee,Btxfee,Aclaimfee,Bclaimfee,srcamount,destamount,aliceid,alicerealsat,bobrealsat;
     int64_t values[sizeof(txnames)/sizeof(*txnames)];
-    uint32_t finishtime,tradeid,requestid,quoteid,plocktime,dlocktime,expiration,state,otherstate;
-    int32_t iambob,finishedflag,origfinishedflag,Predeemlen,Dredeemlen,sentflags[sizeof(txnames)/sizeof(*txnames)];
-    uint8_t secretAm[20],secretAm256[32],secretBn[20],secretBn256[32],Predeemscript[1024],Dredeemscript[1024],pubkey33[33],other33[33];
-    char uuidstr[65],Agui[65],Bgui[65],gui[65],src[65],dest[65],bobtomic[128],alicetomic[128],etomicsrc[65],etomicdest[65],destaddr[64],Adestaddr[64],Sdestaddr[64],alicepaymentaddr[64],bobpaymentaddr[64],bobdepositaddr[64],alicecoin[65],bobcoin[65],*txbytes[sizeof(txnames)/sizeof(*txnames)];
-    char eth_tx_ids[sizeof(txnames)/sizeof(*txnames)][75];
+    uint32_t finishtime,tradeid,requestid,quoteid,plocktime,dlocktime,expiration,state,otherstate,lockduration,loopended;
+    double looptotal;
+    int32_t iambob,finishedflag,origfinishedflag,uniformspends,csvrefunds,makerfirst,Apaymentheight,Predeemlen,Dredeemlen,Aredeemlen,sentflags[sizeof(txnames)/sizeof(*txnames)];
+    uint8_t secretAm[20],secretAm256[32],secretBn[20],secretBn256[32],Predeemscript[1024],Dredeemscript[1024],Aredeemscript[1024],pubkey33[33],other33[33],claim33[33];
+    char claimpath[64],uuidstr[65],Agui[65],Bgui[65],gui[65],src[65],dest[65],bobtomic[128],alicetomic[128],etomicsrc[65],etomicdest[65],alicecontract[64],bobcontract[64],destaddr[64],Adestaddr[64],Sdestaddr[64],alicepaymentaddr[64],bobpaymentaddr[64],bobdepositaddr[64],alicecoin[65],bobcoin[65],*txbytes[sizeof(txnames)/sizeof(*txnames)];
+    char eth_tx_ids[sizeof(txnames)/sizeof(*txnames)][75],aliceamount[LP_AMOUNTSTRLEN],bobamount[LP_AMOUNTSTRLEN];
     int64_t eth_values[sizeof(txnames)/sizeof(*txnames)];
 };
 
@@ -333,6 +402,15 @@ struct LP_tx_history_item
     uint32_t vout,blockindex,blocktime,time;
 };
 
+struct LP_hdaddress { char coinaddr[64]; uint8_t pubkey33[33]; uint32_t chain,index,used,pending; };
+
+#define LP_RPCPOOL_SIZE 4
+struct LP_rpcpool
+{
+    void *handles[LP_RPCPOOL_SIZE]; uint8_t inuse[LP_RPCPOOL_SIZE];
+    uint32_t lastok,downsince,lastprobe,numrequests,numretries,numfailures,numreconnects,numoverflow;
+};
+
 struct iguana_info
 {
     UT_hash_handle hh;
@@ -341,19 +419,23 @@ struct iguana_info
     struct LP_address *addresses;
     uint64_t txfee,do_autofill_merge;
     int32_t numutxos,notarized,longestchain,firstrefht,firstscanht,lastscanht,height; uint16_t busport,did_addrutxo_reset;
-    uint32_t txversion,dPoWtime,lastautosplit,lastresetutxo,loadedcache,electrumlist,lastunspent,importedprivkey,lastpushtime,lastutxosync,addr_listunspent_requested,lastutxos,updaterate,counter,inactive,lastmempool,lastgetinfo,ratetime,heighttime,lastmonitor,obooktime;
-    uint8_t pubtype,p2shtype,isPoS,wiftype,wiftaddr,taddr,noimportprivkey_flag,userconfirms,isassetchain,maxconfirms,cache_history;
-    char symbol[128],smartaddr[64],userpass[1024],serverport[128],instantdex_address[64],estimatefeestr[32],getinfostr[32],etomic[64],validateaddress[64];
+    uint32_t txversion,dPoWtime,lastautosplit,lastresetutxo,loadedcache,electrumlist,lastunspent,importedprivkey,lastpushtime,lastutxosync,addr_listunspent_requested,lastutxos,updaterate,counter,inactive,lastmempool,lastgetinfo,ratetime,heighttime,lastmonitor,obooktime,locktime,blocktime,feebumpdelay,bip44,hdaccount,hdnext[2],signertimeout,maintenance,txhistory_synced,txhistory_running;
+    uint64_t maxinflight,inflight,mintradevol,zeroconfmax,zeroconfvol;
+    uint8_t pubtype,p2shtype,isPoS,wiftype,wiftaddr,taddr,noimportprivkey_flag,userconfirms,isassetchain,maxconfirms,cache_history,rbf,uniformspends,hdwallet,burnfee,notestaccept,testnet,csvrefunds;
+    char symbol[128],smartaddr[64],userpass[1024],serverport[128],instantdex_address[64],estimatefeestr[32],getinfostr[32],etomic[64],validateaddress[64],signerurl[128],chain[16],proxy[128],bech32hrp[16],cashaddr[16];
     struct LP_tx_history_item *tx_history;
     // portfolio
     double price_kmd,force,perc,goal,goalperc,relvolume,rate;
     void *electrum; void *ctx;
     uint64_t maxamount,kmd_equiv,balanceA,balanceB,valuesumA,valuesumB,fillsatoshis;
-    uint8_t pubkey33[33],zcash,decimals;
+    uint8_t pubkey33[33],signerpub33[33],zcash,decimals;
     int32_t privkeydepth,bobfillheight;
-    void *curl_handle; portable_mutex_t curl_mutex;
+    void *curl_handle; portable_mutex_t curl_mutex; struct LP_rpcpool rpcpool;
     bits256 cachedtxid,notarizationtxid; uint8_t *cachedtxiddata; int32_t cachedtxidlen;
     bits256 cachedmerkle,notarizedhash; int32_t cachedmerkleheight;
+    char coldaddr[64]; uint64_t coldthreshold,coldkeep; int32_t coldconfirms; uint32_t lastcoldsweep;
+    struct LP_hdaddress *hdaddrs; int32_t numhdaddrs;
+    char rpcurl[128],alicecontract[64],bobcontract[64],fallback_alicecontract[64],fallback_bobcontract[64]; // EVM overrides
 };
 
 struct _LP_utxoinfo { bits256 txid; uint64_t value; int32_t height; uint32_t vout:30,suppress:1,pad:1; };
@@ -418,9 +500,10 @@ struct LP_quoteinfo
     double maxprice;
     int64_t othercredits;
     uint64_t satoshis,txfee,destsatoshis,desttxfee,aliceid;
-    uint32_t timestamp,quotetime,tradeid,gtc,fill,mpnet;
+    uint32_t timestamp,quotetime,tradeid,gtc,fill,mpnet,internal,locktime,caps,reqcaps;
     int32_t vout,vout2,destvout,feevout,pair;
     char srccoin[65],coinaddr[64],destcoin[65],destaddr[64],gui[64],etomicsrc[65],etomicdest[65],uuidstr[65];
+    char srcamountstr[LP_AMOUNTSTRLEN],destamountstr[LP_AMOUNTSTRLEN]; // satoshis and destsatoshis with every decimal the coin has
 };
 
 struct LP_endpoint { int32_t pair; char ipaddr[64]; uint16_t port; };
@@ -431,9 +514,16 @@ struct basilisk_swap
     struct LP_endpoint N;
     void (*balancingtrade)(struct basilisk_swap *swap,int32_t iambob);
     int32_t subsock,pushsock,connected,aliceunconf,depositunconf,paymentunconf;
-    uint32_t lasttime,aborted,tradeid,received;
+    uint32_t lasttime,aborted,tradeid,received,identityerr,locktimeerr,timedout,swapsteptimeout,txwaittimeout,feeerr,macerr,sendseq,recvseq;
+    double startmillis,laststatemillis,phasemillis[LP_SWAPPHASES]; int32_t phase;
+    uint32_t delivered[LP_SWAPCHANNELS],duplicates; bits256 msghashes[LP_SWAPMSG_HASHES]; int32_t msghashind;
+    char statename[32]; uint32_t statetime,watchdog,sending,shutdown;
+    int64_t myclaimfees[2],myclaimmargins[2],claimfees[2],claimmargins[2]; // [0] alice coin, [1] bob coin
+    int64_t zeroconfvol[2]; // [0] alice coin, [1] bob coin, counted against zeroconf_max_volume
     FILE *fp;
-    bits256 persistent_privkey,persistent_pubkey;
+    bits256 persistent_privkey,persistent_pubkey,Apaymentblock;
+    int32_t Apaymentheight;
+    uint8_t claimpub33[33]; char claimpath[64];
     struct basilisk_swapinfo I;
     struct basilisk_rawtx bobdeposit,bobpayment,alicepayment,myfee,otherfee,aliceclaim,alicespend,bobreclaim,bobspend,bobrefund,alicereclaim;
     bits256 privkeys[INSTANTDEX_DECKSIZE];
@@ -447,7 +537,7 @@ struct LP_pubkey_quote
 {
     struct LP_pubkey_quote *next,*prev;
     float price;
-    uint32_t maxutxo,aveutxo;
+    uint32_t maxutxo,aveutxo,timestamp,caps;
     uint8_t baseind,relind,numutxos,scale;
 };
 
@@ -461,6 +551,7 @@ struct LP_swapstats
     uint64_t aliceid;
     int32_t bobneeds_dPoW,aliceneeds_dPoW;
     uint32_t ind,methodind,finished,expired,lasttime,dPoWfinished;
+    uint8_t counted;
     char alicegui[65],bobgui[65];
 };
 
@@ -474,8 +565,8 @@ struct LP_pubkey_info
     struct LP_pubkey_quote *quotes;
     struct LP_pubswap *bobswaps,*aliceswaps;
     int64_t dynamictrust,unconfcredits;
-    uint32_t timestamp,numerrors,lasttime,slowresponse;
-    int32_t istrusted,pairsock;
+    uint32_t timestamp,numerrors,lasttime,slowresponse,lastfailure,backoffuntil,banned,nofees,lastnofee;
+    int32_t istrusted,pairsock,swapscompleted,swapsfailed;
     uint8_t rmd160[20],sig[65],pubsecp[33],siglen;
 };
 
@@ -487,6 +578,8 @@ struct electrum_info
     int32_t bufsize,sock,*heightp,numerrors;
     struct iguana_info *coin;
     uint32_t stratumid,lasttime,keepalive,pending,*heighttimep;
+    uint32_t numrequests,numtimeouts,numerrs,numdisagree,lastfailover;
+    double avelatency;
     char ipaddr[64],symbol[66];
     uint16_t port;
     uint8_t buf[];
@@ -499,7 +592,7 @@ struct LP_trade
     uint64_t aliceid;
     int64_t besttrust,bestunconfcredits;
     double bestprice;
-    uint32_t negotiationdone,bestresponse,connectsent,firsttime,lasttime,firstprocessed,lastprocessed,newtime,cancelled;
+    uint32_t negotiationdone,bestresponse,connectsent,firsttime,lasttime,firstprocessed,lastprocessed,newtime,cancelled,queued;
     char pairstr[64],funcid,iambob;
     struct LP_quoteinfo Qs[4],Q;
 };
@@ -532,12 +625,15 @@ int64_t LP_listunspent_parseitem(struct iguana_info *coin,bits256 *txidp,int32_t
 void LP_unspents_cache(char *symbol,char *addr,char *arraystr,int32_t updatedflag);
 uint16_t LP_psock_get(char *connectaddr,char *publicaddr,int32_t ispaired,int32_t cmdchannel,char *ipaddr);
 void LP_failedmsg(uint32_t requestid,uint32_t quoteid,double val,char *uuidstr);
+void LP_failedmsg_category(uint32_t requestid,uint32_t quoteid,double val,char *uuidstr,char *category);
+int32_t LP_swapabort_sigadd(cJSON *item,uint32_t timestamp,bits256 priv,uint8_t *pubsecp,bits256 pubkey,char *uuidstr);
 //void LP_utxo_clientpublish(struct LP_utxoinfo *utxo);
 //int32_t LP_coinbus(uint16_t coin_busport);
 int32_t LP_nanomsg_recvs(void *ctx);
 int32_t LP_numconfirms(char *symbol,char *coinaddr,bits256 txid,int32_t vout,int32_t mempool);
 void LP_aliceid(uint32_t tradeid,uint64_t aliceid,char *event,uint32_t requestid,uint32_t quoteid);
 void LP_autoprices_update(char *method,char *base,double basevol,char *rel,double relvol);
+uint32_t LP_autoprice_stale(char *base,char *rel);
 cJSON *LP_cache_transaction(struct iguana_info *coin,bits256 txid,uint8_t *serialized,int32_t len);
 uint64_t LP_balance(uint64_t *valuep,int32_t iambob,char *symbol,char *coinaddr);
 cJSON *LP_transaction_fromdata(struct iguana_info *coin,bits256 txid,uint8_t *serialized,int32_t len);
@@ -545,6 +641,7 @@ uint64_t LP_RTsmartbalance(struct iguana_info *coin);
 int32_t LP_getheight(int32_t *notarizedp,struct iguana_info *coin);
 int32_t LP_reserved_msg(int32_t priority,char *base,char *rel,bits256 pubkey,char *msg);
 struct iguana_info *LP_coinfind(char *symbol);
+struct iguana_info *LP_coinsearch(char *symbol);
 int32_t LP_crc32find(int32_t *duplicatep,int32_t ind,uint32_t crc32);
 char *LP_pricepings(void *ctx,char *myipaddr,int32_t pubsock,char *base,char *rel,double price);
 int32_t LP_merkleproof(struct iguana_info *coin,char *coinaddr,struct electrum_info *ep,bits256 txid,int32_t height);
@@ -602,6 +699,13 @@ int32_t LP_autoref_clear(char *base,char *rel);
 int32_t bitcoin_addr2rmd160(char *symbol,uint8_t taddr,uint8_t *addrtypep,uint8_t rmd160[20],char *coinaddr);
 struct LP_pubkey_info *LP_pubkeyadd(bits256 pubkey);
 uint32_t LP_atomic_locktime(char *base,char *rel);
+void LP_swaplog(struct basilisk_swap *swap,char *statename,char *msg);
+double LP_swap_elapsed(struct basilisk_swap *swap);
+char *LP_swaperr_kind(int32_t err,char *category,char **sidep);
+extern uint32_t LP_dexfee_div;
+extern int32_t LP_txwait_timeout;
+extern char LP_dexfee_rmd160[41],LP_dexfee_ethaddr[43];
+extern LP_THREADLOCAL struct basilisk_swap *LP_threadswap;
 struct LP_pubkey_info *LP_pubkeyfind(bits256 pubkey);
 char *issue_LP_psock(char *destip,uint16_t destport,int32_t ispaired,int32_t cmdchannel);
 char *LP_unspents_filestr(char *symbol,char *addr);
@@ -627,8 +731,57 @@ int32_t bitcoin_priv2wif(char *symbol,uint8_t wiftaddr,char *wifstr,bits256 priv
 int bech32_convert_bits(uint8_t *out,int32_t *outlen,int outbits,const uint8_t *in,int32_t inlen,int inbits,int pad);
 int bech32_decode(char *hrp,uint8_t *data,int32_t *data_len,const char *input);
 int bech32_encode(char *output,const char *hrp,const uint8_t *data,int32_t data_len);
+int segwit_addr_encode(char *output,const char *hrp,int ver,const uint8_t *prog,int32_t prog_len);
+int segwit_addr_decode(int *ver,uint8_t *prog,int32_t *prog_len,const char *hrp,const char *addr);
+int32_t LP_addrformat(char *symbol,char *coinaddr);
+int32_t LP_address_script(uint8_t *script,char *symbol,uint8_t taddr,uint8_t pubtype,char *coinaddr);
 void HashGroestl(void * buf, const void * pbegin, int len);
 bits256 LP_privkey(char *symbol,char *coinaddr,uint8_t taddr);
+void LP_hdwallet_init(char *passphrase);
+void LP_hdwallet_coininit(void *ctx,struct iguana_info *coin);
+int32_t LP_hd_swapaddress(struct iguana_info *coin,uint8_t *pubkey33,char *pathstr);
+void LP_hd_swapaddress_restore(struct iguana_info *coin,char *pathstr);
+void LP_hd_swapaddress_done(struct iguana_info *coin,char *pathstr,int32_t used);
+int32_t LP_signer_sign(void *ctx,char *symbol,uint8_t *sig,uint8_t *pubkey33,bits256 sighash,bits256 privkey,uint8_t *script,int32_t scriptlen);
+void LP_signer_coininit(struct iguana_info *coin);
+int32_t LP_swap_reorgcheck(char *symbol,char *coinaddr,bits256 txid,int32_t height,bits256 blockhash);
 cJSON *address_history_cached(struct iguana_info *coin);
 int history_item_cmp(struct LP_tx_history_item *item1, struct LP_tx_history_item *item2);
+double LP_pricefeed_price(char *feed,char *base,char *rel);
+uint32_t LP_pricefeed_maxage(char *feed);
+void LP_myorder_match(struct basilisk_swap *swap,char *event);
+void LP_mmbot_swapdone(char *bobstr,char *alicestr);
+void LP_multihop_match(struct basilisk_swap *swap,char *event);
+char *LP_eth_withdraw(struct iguana_info *coin,cJSON *argjson);
+char *LP_chainsymbol(char *symbol);
+bits256 LP_swap_privkey(struct iguana_info *coin,char *coinaddr);
+void LP_pubkey_nofee(bits256 pubkey);
+void LP_pubkey_validationfail(struct LP_pubkey_info *pubp,char *what);
+uint32_t basilisk_csvscript(uint8_t *redeemscript,int32_t redeemlen);
+char *LP_abort_swap(char *uuidstr);
+void LP_rpcpool_init(struct iguana_info *coin);
+char *LP_rpcpool_request(struct iguana_info *coin,char *method,char *params,int32_t timeout);
+cJSON *LP_electrum_request(char *symbol,struct electrum_info *ep,cJSON **retjsonp,char *method,char *params,int32_t timeout);
+int32_t LP_reputation_feeconfirm(bits256 pubkey);
+void LP_reputation_update(bits256 pubkey,int32_t completed);
+int32_t LP_swapabort_coin(char *symbol);
+void LP_rpcpool_restore(struct iguana_info *coin);
+char *LP_disable_coin(void *ctx,char *symbol,int32_t force);
+int32_t LP_swaprelay_send(struct basilisk_swap *swap,uint8_t *sealed,int32_t sealedlen);
+int32_t LP_swapsealed_valid(struct basilisk_swap *swap,uint8_t *sealed,int32_t sealedlen);
+uint8_t *LP_swapmsg_next(struct basilisk_swap *swap,int32_t *datalenp,int32_t *channelp);
+int32_t LP_swapmsg_dup(struct basilisk_swap *swap,uint8_t *data,int32_t datalen);
+char *LP_export_swaps(char *filename);
+char *LP_import_swaps(char *filename);
+void LP_swapstats_count(struct LP_swapstats *sp);
+int32_t LP_caps_sigadd(cJSON *item,struct LP_quoteinfo *qp);
+void LP_myorder_reserve(struct LP_quoteinfo *qp,int64_t taken,int64_t remaining,int32_t filled);
+void LP_myorder_feedstale(char *base,char *rel,int32_t staleflag);
+void LP_myorder_update(char *base,char *rel,double price,cJSON *argjson);
+int32_t LP_pricefeed_unpeg(char *base,char *rel);
+int32_t LP_makerfirst(char *base,char *rel);
+int32_t LP_pubkeys_signer(void *ctx,uint8_t *pub33,uint32_t *requestidp,uint32_t *quoteidp,int64_t *alicesatoshisp,int64_t *bobsatoshisp,uint8_t *data,int32_t datalen);
+int64_t LP_amount_satoshis(char *amountstr);
+int32_t LP_amount_wei(char *weistr,char *amountstr,int32_t kind);
+int32_t LP_amount_weiequal(char *valuehex,char *weistr);
 #endif
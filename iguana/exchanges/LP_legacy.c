@@ -0,0 +1,126 @@
+
+/******************************************************************************
+ * Copyright © 2014-2018 The SuperNET Developers.                             *
+ *                                                                            *
+ * See the AUTHORS, DEVELOPER-AGREEMENT and LICENSE files at                  *
+ * the top-level directory of this distribution for the individual copyright  *
+ * holder information and the developer policies on copyright and licensing.  *
+ *                                                                            *
+ * Unless otherwise agreed in a custom licensing agreement, no part of the    *
+ * SuperNET software, including this file may be copied, modified, propagated *
+ * or distributed except according to the terms contained in the LICENSE file *
+ *                                                                            *
+ * Removal or modification of this copyright notice is prohibited.            *
+ *                                                                            *
+ ******************************************************************************/
+//
+//  LP_legacy.c
+//  marketmaker
+//
+//  startup import of a legacy marketmaker DB folder, "legacy_dbdir" in the startup json. every swap in its SWAPS/list
+//  that is not already in our DB is copied over with its tx files, stamped like LP_schema_migrate_v1 would and appended
+//  to our SWAPS/list. unfinished ones go on the pending list so LP_swapsloop runs the refunds and claims still possible.
+//  GTC/orders is only taken when we have none. the legacy folder itself is never written to
+//
+
+int32_t LP_legacy_copy(char *srcdir,char *relname,int32_t stampflag)
+{
+    FILE *fp; char src[512],dest[512],tmpname[512],*fstr; long fsize;
+    sprintf(src,"%s/%s",srcdir,relname), OS_compatible_path(src);
+    sprintf(dest,"%s/%s",GLOBAL_DBDIR,relname), OS_compatible_path(dest);
+    if ( (fstr= OS_filestr(&fsize,src)) == 0 )
+        return(0);
+    sprintf(tmpname,"%s.tmp",dest);
+    if ( (fp= fopen(tmpname,"wb")) == 0 || fwrite(fstr,1,fsize,fp) != fsize )
+    {
+        if ( fp != 0 )
+            fclose(fp);
+        free(fstr);
+        printf("legacy import: cant write %s\n",tmpname);
+        return(-1);
+    }
+    fclose(fp);
+    free(fstr);
+    if ( OS_renamefile(tmpname,dest) < 0 )
+        return(-1);
+    if ( stampflag != 0 && LP_schema_stamp(dest,1) < 0 )
+        return(-1);
+    return(1);
+}
+
+// -1 on error, 0 if skipped, 1 imported and finished, 2 imported and still pending
+int32_t LP_legacy_swap(char *srcdir,uint32_t requestid,uint32_t quoteid)
+{
+    char relname[512],fname[512],*fstr; long fsize; cJSON *item; int32_t i,finished,parsed = 0; uint32_t version = 0;
+    sprintf(fname,"%s/SWAPS/%u-%u",GLOBAL_DBDIR,requestid,quoteid), OS_compatible_path(fname);
+    if ( (fstr= OS_filestr(&fsize,fname)) != 0 ) // already ours, never overwrite newer state with the legacy copy
+    {
+        free(fstr);
+        return(0);
+    }
+    sprintf(fname,"%s/SWAPS/%u-%u",srcdir,requestid,quoteid), OS_compatible_path(fname);
+    if ( (fstr= OS_filestr(&fsize,fname)) == 0 )
+        return(0);
+    if ( (item= cJSON_Parse(fstr)) != 0 )
+    {
+        version = juint(item,"version");
+        free_json(item);
+        parsed = 1;
+    }
+    free(fstr);
+    if ( parsed == 0 || version > LP_SCHEMA_VERSION )
+    {
+        printf("legacy import: skip %u-%u, %s\n",requestid,quoteid,parsed == 0 ? "unparseable record" : "written by a newer marketmaker");
+        return(0);
+    }
+    for (i=0; i<sizeof(txnames)/sizeof(*txnames); i++)
+    {
+        sprintf(relname,"SWAPS/%u-%u.%s",requestid,quoteid,txnames[i]);
+        if ( LP_legacy_copy(srcdir,relname,1) < 0 )
+            return(-1);
+    }
+    sprintf(relname,"SWAPS/%u-%u.finished",requestid,quoteid);
+    if ( (finished= LP_legacy_copy(srcdir,relname,1)) < 0 )
+        return(-1);
+    sprintf(relname,"SWAPS/%u-%u",requestid,quoteid); // main record last, a partial copy gets retried on the next start
+    if ( LP_legacy_copy(srcdir,relname,1) <= 0 )
+        return(-1);
+    LP_swapsfp_update(requestid,quoteid);
+    if ( finished == 0 )
+    {
+        LP_pendswap_add(0,requestid,quoteid);
+        return(2);
+    }
+    return(1);
+}
+
+void LP_legacy_import(char *srcdir)
+{
+    FILE *fp; char fname[512],*fstr; long fsize; uint32_t requestid,quoteid; int32_t retval,numswaps = 0,numpending = 0,numerrs = 0;
+    if ( srcdir == 0 || srcdir[0] == 0 || strcmp(srcdir,GLOBAL_DBDIR) == 0 )
+        return;
+    sprintf(fname,"%s/SWAPS/list",srcdir), OS_compatible_path(fname);
+    if ( (fp= fopen(fname,"rb")) == 0 )
+    {
+        printf("legacy import: no %s\n",fname);
+        return;
+    }
+    while ( fread(&requestid,1,sizeof(requestid),fp) == sizeof(requestid) && fread(&quoteid,1,sizeof(quoteid),fp) == sizeof(quoteid) )
+    {
+        if ( (retval= LP_legacy_swap(srcdir,requestid,quoteid)) < 0 )
+            numerrs++;
+        else if ( retval > 0 )
+        {
+            numswaps++;
+            if ( retval == 2 )
+                numpending++;
+        }
+    }
+    fclose(fp);
+    sprintf(fname,"%s/GTC/orders",GLOBAL_DBDIR), OS_compatible_path(fname);
+    if ( (fstr= OS_filestr(&fsize,fname)) != 0 )
+        free(fstr);
+    else if ( LP_legacy_copy(srcdir,"GTC/orders",0) > 0 )
+        printf("legacy import: GTC orders taken from %s\n",srcdir);
+    printf("legacy import from %s: %d swaps, %d pending refund or claim, %d errors\n",srcdir,numswaps,numpending,numerrs);
+}
@@ -0,0 +1,289 @@
+
+/******************************************************************************
+ * Copyright © 2014-2018 The SuperNET Developers.                             *
+ *                                                                            *
+ * See the AUTHORS, DEVELOPER-AGREEMENT and LICENSE files at                  *
+ * the top-level directory of this distribution for the individual copyright  *
+ * holder information and the developer policies on copyright and licensing.  *
+ *                                                                            *
+ * Unless otherwise agreed in a custom licensing agreement, no part of the    *
+ * SuperNET software, including this file may be copied, modified, propagated *
+ * or distributed except according to the terms contained in the LICENSE file *
+ *                                                                            *
+ * Removal or modification of this copyright notice is prohibited.            *
+ *                                                                            *
+ ******************************************************************************/
+//
+//  LP_log.c
+//  marketmaker
+//
+//  every printf goes through LP_logf with the source file as its module, so "log_levels":{"ordermatch":"warn"} in the
+//  startup json quiets one module while "log_level" sets the rest. printf has no level of its own, lines that start
+//  with error or warning are taken at that level and everything else is info. "log_file" adds a file sink that rotates
+//  at "log_maxsize" bytes or every "log_rotate_hours", keeping "log_keep" old files. with "log_json":1 the file sink,
+//  or stdout when there is no file, gets one json object per line and swap state changes come out as SWAP_STATUS records
+//
+
+#define LP_LOG_ERROR 1
+#define LP_LOG_WARN 2
+#define LP_LOG_INFO 3
+#define LP_LOG_DEBUG 4
+#define LP_LOG_MAXMODULES 64
+#define LP_LOG_KEEP 5
+
+struct LP_logmodule { char module[32]; int32_t level; } LP_logmodules[LP_LOG_MAXMODULES];
+int32_t LP_numlogmodules,LP_loglevel = LP_LOG_INFO,LP_log_json,LP_log_keep = LP_LOG_KEEP;
+char LP_logfname[512]; FILE *LP_logfp; int64_t LP_log_maxsize; uint32_t LP_log_rotatesecs,LP_log_opened;
+static char *LP_loglevelnames[] = { "none", "error", "warn", "info", "debug" };
+
+static LP_THREADLOCAL char LP_swaplogtag[96],LP_logline[4096];
+static LP_THREADLOCAL int32_t LP_swaplog_midline,LP_loglinelen,LP_loglinelevel;
+LP_THREADLOCAL struct basilisk_swap *LP_threadswap;
+
+void LP_swaplogtag_set(struct basilisk_swap *swap)
+{
+    LP_threadswap = swap;
+    if ( swap == 0 )
+        LP_swaplogtag[0] = 0;
+    else snprintf(LP_swaplogtag,sizeof(LP_swaplogtag),"[%s %s] ",swap->I.iambob != 0 ? "maker" : "taker",swap->uuidstr);
+    LP_swaplog_midline = 0;
+    LP_loglinelen = 0;
+}
+
+int32_t LP_loglevel_parse(char *levelstr)
+{
+    int32_t i;
+    if ( levelstr != 0 )
+    {
+        for (i=0; i<sizeof(LP_loglevelnames)/sizeof(*LP_loglevelnames); i++)
+            if ( strcmp(levelstr,LP_loglevelnames[i]) == 0 )
+                return(i);
+    }
+    return(-1);
+}
+
+// LP_ordermatch.c -> ordermatch, anything else keeps its basename without the extension
+char *LP_log_module(char *module,int32_t size,const char *srcfile)
+{
+    const char *str; int32_t len;
+    if ( (str= strrchr(srcfile,'/')) != 0 || (str= strrchr(srcfile,'\\')) != 0 )
+        str++;
+    else str = srcfile;
+    if ( strncmp(str,"LP_",3) == 0 )
+        str += 3;
+    if ( (len= (int32_t)strlen(str)) > 2 && strcmp(str+len-2,".c") == 0 )
+        len -= 2;
+    if ( len >= size )
+        len = size - 1;
+    memcpy(module,str,len);
+    module[len] = 0;
+    return(module);
+}
+
+int32_t LP_log_threshold(char *module)
+{
+    int32_t i;
+    for (i=0; i<LP_numlogmodules; i++)
+        if ( strcmp(LP_logmodules[i].module,module) == 0 )
+            return(LP_logmodules[i].level);
+    return(LP_loglevel);
+}
+
+int32_t LP_log_guesslevel(char *str)
+{
+    while ( *str == '\n' || *str == ' ' )
+        str++;
+    if ( strncasecmp(str,"error",5) == 0 )
+        return(LP_LOG_ERROR);
+    else if ( strncasecmp(str,"warning",7) == 0 )
+        return(LP_LOG_WARN);
+    return(LP_LOG_INFO);
+}
+
+void LP_log_rotate()
+{
+    char src[600],dest[600]; int32_t i;
+    fclose(LP_logfp);
+    sprintf(dest,"%s.%d",LP_logfname,LP_log_keep);
+    OS_portable_removefile(dest);
+    for (i=LP_log_keep-1; i>0; i--)
+    {
+        sprintf(src,"%s.%d",LP_logfname,i);
+        sprintf(dest,"%s.%d",LP_logfname,i+1);
+        rename(src,dest);
+    }
+    sprintf(dest,"%s.1",LP_logfname);
+    if ( rename(LP_logfname,dest) != 0 )
+        fprintf(stderr,"log: cant rotate %s\n",LP_logfname);
+    if ( (LP_logfp= fopen(LP_logfname,"a")) == 0 )
+        fprintf(stderr,"log: cant reopen %s, file logging stopped\n",LP_logfname);
+    LP_log_opened = (uint32_t)time(NULL);
+}
+
+// a whole line for the file or json sink, never call printf from here
+void LP_log_emit(char *str)
+{
+    FILE *fp;
+    if ( LP_logfp == 0 )
+    {
+        if ( LP_log_json != 0 && LP_logfname[0] == 0 )
+        {
+            fputs(str,stdout);
+            fflush(stdout);
+        }
+        return;
+    }
+    portable_mutex_lock(&LP_logmutex);
+    if ( (fp= LP_logfp) != 0 )
+    {
+        fputs(str,fp);
+        fflush(fp);
+        if ( (LP_log_maxsize > 0 && ftell(fp) >= LP_log_maxsize) || (LP_log_rotatesecs != 0 && time(NULL) >= LP_log_opened + LP_log_rotatesecs) )
+            LP_log_rotate();
+    }
+    portable_mutex_unlock(&LP_logmutex);
+}
+
+void LP_log_record(char *module,int32_t level,char *line,struct basilisk_swap *swap,char *status)
+{
+    char tstr[64],*str,*buf; uint64_t millis; cJSON *item; int32_t len;
+    millis = LP_utcmillis();
+    if ( LP_log_json != 0 )
+    {
+        item = cJSON_CreateObject();
+        jaddnum(item,"ts",millis);
+        jaddstr(item,"level",LP_loglevelnames[level]);
+        jaddstr(item,"module",module);
+        if ( swap != 0 )
+        {
+            jaddstr(item,"uuid",swap->uuidstr);
+            jaddstr(item,"role",swap->I.iambob != 0 ? "maker" : "taker");
+            jaddnum(item,"requestid",swap->I.req.requestid);
+            jaddnum(item,"quoteid",swap->I.req.quoteid);
+        }
+        if ( status != 0 )
+        {
+            jaddstr(item,"tag","SWAP_STATUS");
+            jaddstr(item,"status",status);
+            jaddstr(item,"base",swap->I.bobstr);
+            jaddstr(item,"rel",swap->I.alicestr);
+            jaddnum(item,"elapsed_ms",(int64_t)LP_swap_elapsed(swap));
+        }
+        jaddstr(item,"msg",line);
+        str = jprint(item,1);
+        len = (int32_t)strlen(str);
+        buf = malloc(len + 2);
+        memcpy(buf,str,len), buf[len] = '\n', buf[len+1] = 0;
+        free(str);
+    }
+    else
+    {
+        if ( LP_logfp == 0 )
+            return;
+        len = (int32_t)strlen(line) + 256;
+        buf = malloc(len);
+        utc_str(tstr,(uint32_t)(millis / 1000));
+        if ( status != 0 )
+            snprintf(buf,len,"%s.%03d %s %s SWAP_STATUS %s %s %u-%u %s: %s\n",tstr,(int32_t)(millis % 1000),LP_loglevelnames[level],module,swap->I.iambob != 0 ? "maker" : "taker",swap->uuidstr,swap->I.req.requestid,swap->I.req.quoteid,status,line);
+        else snprintf(buf,len,"%s.%03d %s %s %s%s\n",tstr,(int32_t)(millis % 1000),LP_loglevelnames[level],module,swap != 0 ? LP_swaplogtag : "",line);
+    }
+    LP_log_emit(buf);
+    free(buf);
+}
+
+void LP_log_swapstatus(struct basilisk_swap *swap,char *statename,char *msg)
+{
+    if ( (LP_logfp != 0 || LP_log_json != 0) && LP_log_threshold("swap") >= LP_LOG_INFO )
+        LP_log_record("swap",LP_LOG_INFO,msg,swap,statename);
+}
+
+// collects the pieces of a line printed with several printf calls, the first piece sets its level
+void LP_log_line(char *module,int32_t level,char *str,int32_t len)
+{
+    int32_t i;
+    for (i=0; i<len; i++)
+    {
+        if ( LP_loglinelen == 0 )
+            LP_loglinelevel = level;
+        if ( str[i] == '\n' || LP_loglinelen == sizeof(LP_logline)-1 )
+        {
+            LP_logline[LP_loglinelen] = 0;
+            if ( LP_loglinelen > 0 )
+                LP_log_record(module,LP_loglinelevel,LP_logline,LP_threadswap,0);
+            LP_loglinelen = 0;
+            if ( str[i] == '\n' )
+                continue;
+        }
+        LP_logline[LP_loglinelen++] = str[i];
+    }
+}
+
+int LP_logf(const char *srcfile,int32_t level,const char *formatstr,...)
+{
+    va_list args; char buf[8192],*str = buf,module[32]; int len;
+    va_start(args,formatstr);
+    len = vsnprintf(buf,sizeof(buf),formatstr,args);
+    va_end(args);
+    if ( len >= (int)sizeof(buf) )
+    {
+        str = malloc(len + 1);
+        va_start(args,formatstr);
+        vsnprintf(str,len + 1,formatstr,args);
+        va_end(args);
+    }
+    if ( len > 0 )
+    {
+        LP_log_module(module,sizeof(module),srcfile);
+        if ( level == LP_LOG_INFO && LP_loglinelen == 0 && LP_swaplog_midline == 0 )
+            level = LP_log_guesslevel(str);
+        if ( level <= LP_log_threshold(module) )
+        {
+            if ( LP_log_json == 0 || LP_logfname[0] != 0 )
+            {
+                if ( LP_swaplogtag[0] != 0 && LP_swaplog_midline == 0 )
+                    fputs(LP_swaplogtag,stdout);
+                fputs(str,stdout);
+                LP_swaplog_midline = (str[len-1] != '\n');
+            }
+            if ( LP_logfp != 0 || LP_log_json != 0 )
+                LP_log_line(module,level,str,len);
+        }
+    }
+    if ( str != buf )
+        free(str);
+    return(len);
+}
+
+void LP_log_init(cJSON *argjson)
+{
+    cJSON *levels,*item; int32_t level; char *fname;
+    if ( (level= LP_loglevel_parse(jstr(argjson,"log_level"))) >= 0 )
+        LP_loglevel = level;
+    if ( (levels= jobj(argjson,"log_levels")) != 0 && is_cJSON_Object(levels) != 0 )
+    {
+        for (item=levels->child; item!=0 && LP_numlogmodules<LP_LOG_MAXMODULES; item=item->next)
+        {
+            if ( item->string == 0 || (level= LP_loglevel_parse(item->valuestring)) < 0 )
+            {
+                printf("log_levels %s needs one of none, error, warn, info, debug\n",item->string != 0 ? item->string : "");
+                continue;
+            }
+            safecopy(LP_logmodules[LP_numlogmodules].module,item->string,sizeof(LP_logmodules[LP_numlogmodules].module));
+            LP_logmodules[LP_numlogmodules++].level = level;
+        }
+    }
+    LP_log_json = (jint(argjson,"log_json") != 0);
+    LP_log_maxsize = j64bits(argjson,"log_maxsize");
+    LP_log_rotatesecs = juint(argjson,"log_rotate_hours") * 3600;
+    if ( jobj(argjson,"log_keep") != 0 && (LP_log_keep= jint(argjson,"log_keep")) < 1 )
+        LP_log_keep = 1;
+    if ( (fname= jstr(argjson,"log_file")) != 0 && fname[0] != 0 )
+    {
+        safecopy(LP_logfname,fname,sizeof(LP_logfname));
+        if ( (LP_logfp= fopen(LP_logfname,"a")) == 0 )
+            printf("log: cant open %s, logging to stdout only\n",LP_logfname);
+        else LP_log_opened = (uint32_t)time(NULL);
+    }
+    if ( LP_logfp != 0 || LP_log_json != 0 || LP_numlogmodules != 0 || LP_loglevel != LP_LOG_INFO )
+        printf("log level %s, %d module overrides, %s%s\n",LP_loglevelnames[LP_loglevel],LP_numlogmodules,LP_logfname[0] != 0 ? LP_logfname : "stdout",LP_log_json != 0 ? " as json" : "");
+}
@@ -0,0 +1,252 @@
+
+/******************************************************************************
+ * Copyright © 2014-2018 The SuperNET Developers.                             *
+ *                                                                            *
+ * See the AUTHORS, DEVELOPER-AGREEMENT and LICENSE files at                  *
+ * the top-level directory of this distribution for the individual copyright  *
+ * holder information and the developer policies on copyright and licensing.  *
+ *                                                                            *
+ * Unless otherwise agreed in a custom licensing agreement, no part of the    *
+ * SuperNET software, including this file may be copied, modified, propagated *
+ * or distributed except according to the terms contained in the LICENSE file *
+ *                                                                            *
+ * Removal or modification of this copyright notice is prohibited.            *
+ *                                                                            *
+ ******************************************************************************/
+//
+//  LP_metrics.c
+//  marketmaker
+//
+//  operator counters and histograms, served as prometheus text at GET /metrics and as json via the metrics rpc
+//
+
+#define LP_MAXMETRICPAIRS 256
+#define LP_MAXMETRICSTATES 64
+#define LP_METRICBUCKETS 8
+
+struct LP_pairmetrics { char base[16],rel[16]; uint64_t started,completed,failed,refunded; } LP_pairmetrics[LP_MAXMETRICPAIRS];
+struct LP_statemetrics { char name[32]; uint64_t buckets[LP_METRICBUCKETS],count; double sum; } LP_statemetrics[LP_MAXMETRICSTATES];
+double LP_metricbounds[LP_METRICBUCKETS-1] = { 1., 5., 15., 60., 300., 900., 3600. }; // seconds, last bucket is +Inf
+struct LP_phasemetrics { uint64_t count,failedin; double sum; } LP_phasemetrics[2][LP_SWAPPHASES]; // [0] completed, [1] failed
+char *LP_swapphases[LP_SWAPPHASES] = { "negotiation", "fee", "payment", "spend" };
+char *LP_swapchannels[LP_SWAPCHANNELS] = { "pair", "cmdchannel" };
+int32_t LP_numpairmetrics,LP_numstatemetrics;
+uint64_t LP_p2p_retries,LP_p2p_dropped;
+portable_mutex_t LP_metricsmutex;
+
+struct LP_pairmetrics *_LP_pairmetrics(char *base,char *rel)
+{
+    int32_t i; struct LP_pairmetrics *pm;
+    for (i=0; i<LP_numpairmetrics; i++)
+    {
+        pm = &LP_pairmetrics[i];
+        if ( strcmp(pm->base,base) == 0 && strcmp(pm->rel,rel) == 0 )
+            return(pm);
+    }
+    if ( LP_numpairmetrics >= LP_MAXMETRICPAIRS )
+        return(0);
+    pm = &LP_pairmetrics[LP_numpairmetrics++];
+    safecopy(pm->base,base,sizeof(pm->base));
+    safecopy(pm->rel,rel,sizeof(pm->rel));
+    return(pm);
+}
+
+void LP_metrics_swap(char *base,char *rel,char *event)
+{
+    struct LP_pairmetrics *pm;
+    if ( base == 0 || rel == 0 || base[0] == 0 || rel[0] == 0 )
+        return;
+    portable_mutex_lock(&LP_metricsmutex);
+    if ( (pm= _LP_pairmetrics(base,rel)) != 0 )
+    {
+        if ( strcmp(event,"started") == 0 )
+            pm->started++;
+        else if ( strcmp(event,"completed") == 0 )
+            pm->completed++;
+        else if ( strcmp(event,"failed") == 0 )
+            pm->failed++;
+        else if ( strcmp(event,"refunded") == 0 )
+            pm->refunded++;
+    }
+    portable_mutex_unlock(&LP_metricsmutex);
+}
+
+void LP_metrics_state(char *statename,double millis)
+{
+    int32_t i,j; struct LP_statemetrics *sm = 0;
+    if ( statename == 0 || statename[0] == 0 || millis < 0. )
+        return;
+    portable_mutex_lock(&LP_metricsmutex);
+    for (i=0; i<LP_numstatemetrics; i++)
+        if ( strcmp(LP_statemetrics[i].name,statename) == 0 )
+        {
+            sm = &LP_statemetrics[i];
+            break;
+        }
+    if ( sm == 0 && LP_numstatemetrics < LP_MAXMETRICSTATES )
+    {
+        sm = &LP_statemetrics[LP_numstatemetrics++];
+        safecopy(sm->name,statename,sizeof(sm->name));
+    }
+    if ( sm != 0 )
+    {
+        for (j=0; j<LP_METRICBUCKETS-1; j++)
+            if ( millis/1000. <= LP_metricbounds[j] )
+                break;
+        sm->buckets[j]++;
+        sm->count++;
+        sm->sum += millis/1000.;
+    }
+    portable_mutex_unlock(&LP_metricsmutex);
+}
+
+// per phase totals of finished swaps, failedin counts the last phase a failed swap had reached
+void LP_metrics_timings(double *phasemillis,int32_t phase,int32_t failed)
+{
+    int32_t i; struct LP_phasemetrics *ph;
+    portable_mutex_lock(&LP_metricsmutex);
+    for (i=0; i<LP_SWAPPHASES; i++)
+    {
+        ph = &LP_phasemetrics[failed != 0][i];
+        if ( phasemillis[i] > 0. )
+        {
+            ph->count++;
+            ph->sum += phasemillis[i]/1000.;
+        }
+        if ( failed != 0 && i == phase )
+            ph->failedin++;
+    }
+    portable_mutex_unlock(&LP_metricsmutex);
+}
+
+cJSON *_LP_metrics_timings()
+{
+    int32_t i,j; struct LP_phasemetrics *ph; cJSON *retjson,*item,*array;
+    retjson = cJSON_CreateObject();
+    for (j=0; j<2; j++)
+    {
+        array = cJSON_CreateArray();
+        for (i=0; i<LP_SWAPPHASES; i++)
+        {
+            ph = &LP_phasemetrics[j][i];
+            item = cJSON_CreateObject();
+            jaddstr(item,"phase",LP_swapphases[i]);
+            jaddnum(item,"count",ph->count);
+            jaddnum(item,"sum",ph->sum);
+            jaddnum(item,"avg",ph->count != 0 ? ph->sum / ph->count : 0.);
+            if ( j != 0 )
+                jaddnum(item,"failedin",ph->failedin);
+            jaddi(array,item);
+        }
+        jadd(retjson,j == 0 ? "completed" : "failed",array);
+    }
+    return(retjson);
+}
+
+cJSON *LP_metrics_timings_json()
+{
+    cJSON *retjson;
+    portable_mutex_lock(&LP_metricsmutex);
+    retjson = _LP_metrics_timings();
+    portable_mutex_unlock(&LP_metricsmutex);
+    return(retjson);
+}
+
+void LP_metrics_p2p(int32_t dropped)
+{
+    if ( dropped != 0 )
+        LP_p2p_dropped++;
+    else LP_p2p_retries++;
+}
+
+char *LP_metrics_prometheus()
+{
+    int32_t i,j,len = 0,size = 65536; uint64_t cumulative; char *buf; struct LP_pairmetrics *pm; struct LP_statemetrics *sm;
+    buf = calloc(1,size);
+    portable_mutex_lock(&LP_metricsmutex);
+    len += snprintf(&buf[len],size-len,"# HELP mm_swaps_total swaps by pair and outcome\n# TYPE mm_swaps_total counter\n");
+    for (i=0; i<LP_numpairmetrics && len < size-512; i++)
+    {
+        pm = &LP_pairmetrics[i];
+        len += snprintf(&buf[len],size-len,"mm_swaps_total{base=\"%s\",rel=\"%s\",event=\"started\"} %llu\n",pm->base,pm->rel,(long long)pm->started);
+        len += snprintf(&buf[len],size-len,"mm_swaps_total{base=\"%s\",rel=\"%s\",event=\"completed\"} %llu\n",pm->base,pm->rel,(long long)pm->completed);
+        len += snprintf(&buf[len],size-len,"mm_swaps_total{base=\"%s\",rel=\"%s\",event=\"failed\"} %llu\n",pm->base,pm->rel,(long long)pm->failed);
+        len += snprintf(&buf[len],size-len,"mm_swaps_total{base=\"%s\",rel=\"%s\",event=\"refunded\"} %llu\n",pm->base,pm->rel,(long long)pm->refunded);
+    }
+    len += snprintf(&buf[len],size-len,"# HELP mm_swap_state_seconds time spent reaching each swap state\n# TYPE mm_swap_state_seconds histogram\n");
+    for (i=0; i<LP_numstatemetrics && len < size-1024; i++)
+    {
+        sm = &LP_statemetrics[i];
+        for (j=0,cumulative=0; j<LP_METRICBUCKETS-1; j++)
+        {
+            cumulative += sm->buckets[j];
+            len += snprintf(&buf[len],size-len,"mm_swap_state_seconds_bucket{state=\"%s\",le=\"%g\"} %llu\n",sm->name,LP_metricbounds[j],(long long)cumulative);
+        }
+        len += snprintf(&buf[len],size-len,"mm_swap_state_seconds_bucket{state=\"%s\",le=\"+Inf\"} %llu\n",sm->name,(long long)sm->count);
+        len += snprintf(&buf[len],size-len,"mm_swap_state_seconds_sum{state=\"%s\"} %.3f\n",sm->name,sm->sum);
+        len += snprintf(&buf[len],size-len,"mm_swap_state_seconds_count{state=\"%s\"} %llu\n",sm->name,(long long)sm->count);
+    }
+    len += snprintf(&buf[len],size-len,"# HELP mm_swap_phase_seconds wall clock time finished swaps spent in each phase\n# TYPE mm_swap_phase_seconds summary\n");
+    for (j=0; j<2; j++)
+        for (i=0; i<LP_SWAPPHASES && len < size-1024; i++)
+        {
+            len += snprintf(&buf[len],size-len,"mm_swap_phase_seconds_sum{phase=\"%s\",outcome=\"%s\"} %.3f\n",LP_swapphases[i],j == 0 ? "completed" : "failed",LP_phasemetrics[j][i].sum);
+            len += snprintf(&buf[len],size-len,"mm_swap_phase_seconds_count{phase=\"%s\",outcome=\"%s\"} %llu\n",LP_swapphases[i],j == 0 ? "completed" : "failed",(long long)LP_phasemetrics[j][i].count);
+        }
+    len += snprintf(&buf[len],size-len,"# HELP mm_swap_failed_in_total failed swaps by the last phase they reached\n# TYPE mm_swap_failed_in_total counter\n");
+    for (i=0; i<LP_SWAPPHASES && len < size-1024; i++)
+        len += snprintf(&buf[len],size-len,"mm_swap_failed_in_total{phase=\"%s\"} %llu\n",LP_swapphases[i],(long long)LP_phasemetrics[1][i].failedin);
+    len += snprintf(&buf[len],size-len,"# HELP mm_p2p_send_retries_total queued p2p sends that had to wait for the socket\n# TYPE mm_p2p_send_retries_total counter\nmm_p2p_send_retries_total %llu\n",(long long)LP_p2p_retries);
+    len += snprintf(&buf[len],size-len,"# HELP mm_p2p_send_dropped_total queued p2p sends given up on\n# TYPE mm_p2p_send_dropped_total counter\nmm_p2p_send_dropped_total %llu\n",(long long)LP_p2p_dropped);
+    portable_mutex_unlock(&LP_metricsmutex);
+    return(buf);
+}
+
+char *LP_metrics()
+{
+    int32_t i,j; struct LP_pairmetrics *pm; struct LP_statemetrics *sm; cJSON *retjson,*array,*item,*buckets,*bucket;
+    retjson = cJSON_CreateObject();
+    portable_mutex_lock(&LP_metricsmutex);
+    array = cJSON_CreateArray();
+    for (i=0; i<LP_numpairmetrics; i++)
+    {
+        pm = &LP_pairmetrics[i];
+        item = cJSON_CreateObject();
+        jaddstr(item,"base",pm->base);
+        jaddstr(item,"rel",pm->rel);
+        jaddnum(item,"started",pm->started);
+        jaddnum(item,"completed",pm->completed);
+        jaddnum(item,"failed",pm->failed);
+        jaddnum(item,"refunded",pm->refunded);
+        jaddi(array,item);
+    }
+    jadd(retjson,"swaps",array);
+    array = cJSON_CreateArray();
+    for (i=0; i<LP_numstatemetrics; i++)
+    {
+        sm = &LP_statemetrics[i];
+        item = cJSON_CreateObject();
+        jaddstr(item,"state",sm->name);
+        jaddnum(item,"count",sm->count);
+        jaddnum(item,"sum",sm->sum);
+        buckets = cJSON_CreateArray();
+        for (j=0; j<LP_METRICBUCKETS; j++)
+        {
+            bucket = cJSON_CreateObject();
+            if ( j < LP_METRICBUCKETS-1 )
+                jaddnum(bucket,"le",LP_metricbounds[j]);
+            else jaddstr(bucket,"le","+Inf");
+            jaddnum(bucket,"count",sm->buckets[j]);
+            jaddi(buckets,bucket);
+        }
+        jadd(item,"buckets",buckets);
+        jaddi(array,item);
+    }
+    jadd(retjson,"states",array);
+    jadd(retjson,"timings",_LP_metrics_timings());
+    jaddnum(retjson,"p2p_send_retries",LP_p2p_retries);
+    jaddnum(retjson,"p2p_send_dropped",LP_p2p_dropped);
+    portable_mutex_unlock(&LP_metricsmutex);
+    jaddstr(retjson,"result","success");
+    return(jprint(retjson,1));
+}
@@ -0,0 +1,253 @@
+
+/******************************************************************************
+ * Copyright © 2014-2018 The SuperNET Developers.                             *
+ *                                                                            *
+ * See the AUTHORS, DEVELOPER-AGREEMENT and LICENSE files at                  *
+ * the top-level directory of this distribution for the individual copyright  *
+ * holder information and the developer policies on copyright and licensing.  *
+ *                                                                            *
+ * Unless otherwise agreed in a custom licensing agreement, no part of the    *
+ * SuperNET software, including this file may be copied, modified, propagated *
+ * or distributed except according to the terms contained in the LICENSE file *
+ *                                                                            *
+ * Removal or modification of this copyright notice is prohibited.            *
+ *                                                                            *
+ ******************************************************************************/
+//
+//  LP_mmbot.c
+//  marketmaker
+//
+//  simple market maker: start_simple_market_maker_bot with
+//  {"cfg":[{"base":"KMD","rel":"BTC","feed":"idx","spread":0.02,"balance_percent":50,"min_volume":1}],"refresh":30}
+//  keeps one maker order per pair at the feed price plus spread for balance_percent of the base balance. the order is
+//  cancelled while the feed has no price or the volume drops below min_volume, and repriced right after a swap on
+//  either coin completes. bot orders are not persisted, a restart leaves them cancelled until the bot is started again
+//
+
+#define LP_MMBOT_MAXPAIRS 32
+#define LP_MMBOT_REFRESH 30
+#define LP_MMBOT_MINMOVE 0.002 // reprice only when the ask moved more than this fraction
+
+struct LP_mmbotpair
+{
+    char base[16],rel[16],feed[32],status[16];
+    double spread,balancepct,ask;
+    int64_t minvolume,volume;
+    uint32_t lastupdate;
+    uint8_t kick;
+} LP_mmbotpairs[LP_MMBOT_MAXPAIRS];
+int32_t LP_mmbot_numpairs,LP_mmbot_refresh = LP_MMBOT_REFRESH; uint32_t LP_mmbot_running,LP_mmbot_generation;
+portable_mutex_t LP_mmbotmutex,LP_mmbot_iomutex; // LP_mmbotmutex only for copying pairs in and out, LP_mmbot_iomutex orders the balance and price pings
+
+int64_t LP_mmbot_balance(struct iguana_info *coin)
+{
+    if ( coin->electrum != 0 )
+        return(LP_unspents_load(coin->symbol,coin->smartaddr));
+    else return(LP_RTsmartbalance(coin));
+}
+
+void LP_mmbot_cancel(void *ctx,struct LP_mmbotpair *bp,char *status)
+{
+    int32_t changed; char *retstr;
+    if ( bp->ask != 0. )
+    {
+        LP_mypriceset(1,&changed,bp->base,bp->rel,0.);
+        if ( (retstr= LP_pricepings(ctx,LP_myipaddr,LP_mypubsock,bp->base,bp->rel,0.)) != 0 )
+            free(retstr);
+        printf("mmbot %s/%s cancelled: %s\n",bp->base,bp->rel,status);
+    }
+    bp->ask = 0.;
+    bp->volume = 0;
+    safecopy(bp->status,status,sizeof(bp->status));
+}
+
+void LP_mmbot_update(void *ctx,struct LP_mmbotpair *bp)
+{
+    struct iguana_info *basecoin,*relcoin; double price,ask; int64_t volume; int32_t changed; char *retstr;
+    bp->lastupdate = (uint32_t)time(NULL);
+    bp->kick = 0;
+    if ( (basecoin= LP_coinfind(bp->base)) == 0 || (relcoin= LP_coinfind(bp->rel)) == 0 || basecoin->inactive != 0 || relcoin->inactive != 0 )
+    {
+        LP_mmbot_cancel(ctx,bp,"waiting_coins");
+        return;
+    }
+    if ( (price= LP_pricefeed_price(bp->feed,bp->base,bp->rel)) < SMALLVAL )
+    {
+        LP_mmbot_cancel(ctx,bp,"no_price");
+        return;
+    }
+    volume = (LP_mmbot_balance(basecoin) * bp->balancepct) / 100.;
+    if ( volume <= 0 || volume < bp->minvolume || volume < basecoin->mintradevol )
+    {
+        LP_mmbot_cancel(ctx,bp,"low_balance");
+        return;
+    }
+    ask = price * (1. + bp->spread);
+    if ( bp->ask != 0. && fabs(ask - bp->ask) < bp->ask * LP_MMBOT_MINMOVE && volume == bp->volume )
+        return;
+    if ( LP_mypriceset(1,&changed,bp->base,bp->rel,ask) < 0 || LP_myvolume_set(bp->base,bp->rel,volume) < 0 )
+    {
+        LP_mmbot_cancel(ctx,bp,"setprice_failed");
+        return;
+    }
+    if ( (retstr= LP_pricepings(ctx,LP_myipaddr,LP_mypubsock,bp->base,bp->rel,ask * LP_profitratio)) != 0 )
+        free(retstr);
+    bp->ask = ask;
+    bp->volume = volume;
+    strcpy(bp->status,"active");
+}
+
+void LP_mmbot_loop(void *ctx)
+{
+    int32_t i,due; uint32_t now,generation = LP_mmbot_generation; struct LP_mmbotpair pair;
+    while ( LP_STOP_RECEIVED == 0 && LP_mmbot_running != 0 && LP_mmbot_generation == generation )
+    {
+        now = (uint32_t)time(NULL);
+        for (i=0; i<LP_MMBOT_MAXPAIRS; i++)
+        {
+            portable_mutex_lock(&LP_mmbot_iomutex);
+            portable_mutex_lock(&LP_mmbotmutex);
+            if ( i >= LP_mmbot_numpairs || LP_mmbot_running == 0 || LP_mmbot_generation != generation )
+            {
+                portable_mutex_unlock(&LP_mmbotmutex);
+                portable_mutex_unlock(&LP_mmbot_iomutex);
+                break;
+            }
+            pair = LP_mmbotpairs[i];
+            if ( (due= (pair.kick != 0 || now >= pair.lastupdate + LP_mmbot_refresh)) != 0 )
+                LP_mmbotpairs[i].kick = 0;
+            portable_mutex_unlock(&LP_mmbotmutex);
+            if ( due != 0 )
+            {
+                LP_mmbot_update(ctx,&pair);
+                portable_mutex_lock(&LP_mmbotmutex);
+                pair.kick = LP_mmbotpairs[i].kick; // a swap finishing meanwhile still gets its reprice
+                LP_mmbotpairs[i] = pair;
+                portable_mutex_unlock(&LP_mmbotmutex);
+            }
+            portable_mutex_unlock(&LP_mmbot_iomutex);
+        }
+        sleep(1);
+    }
+}
+
+// balances on both coins changed, reprice now instead of at the next refresh
+void LP_mmbot_swapdone(char *bobstr,char *alicestr)
+{
+    int32_t i;
+    if ( LP_mmbot_running == 0 )
+        return;
+    portable_mutex_lock(&LP_mmbotmutex);
+    for (i=0; i<LP_mmbot_numpairs; i++)
+        if ( strcmp(LP_mmbotpairs[i].base,bobstr) == 0 || strcmp(LP_mmbotpairs[i].rel,bobstr) == 0 || strcmp(LP_mmbotpairs[i].base,alicestr) == 0 || strcmp(LP_mmbotpairs[i].rel,alicestr) == 0 )
+            LP_mmbotpairs[i].kick = 1;
+    portable_mutex_unlock(&LP_mmbotmutex);
+}
+
+char *LP_mmbot_stop(void *ctx)
+{
+    int32_t i,n; struct LP_mmbotpair pairs[LP_MMBOT_MAXPAIRS];
+    portable_mutex_lock(&LP_mmbot_iomutex);
+    portable_mutex_lock(&LP_mmbotmutex);
+    if ( LP_mmbot_running == 0 )
+    {
+        portable_mutex_unlock(&LP_mmbotmutex);
+        portable_mutex_unlock(&LP_mmbot_iomutex);
+        return(clonestr("{\"error\":\"simple market maker bot not running\"}"));
+    }
+    LP_mmbot_running = 0;
+    n = LP_mmbot_numpairs;
+    memcpy(pairs,LP_mmbotpairs,sizeof(pairs));
+    portable_mutex_unlock(&LP_mmbotmutex);
+    for (i=0; i<n; i++)
+        LP_mmbot_cancel(ctx,&pairs[i],"stopped");
+    portable_mutex_lock(&LP_mmbotmutex);
+    memcpy(LP_mmbotpairs,pairs,sizeof(pairs));
+    portable_mutex_unlock(&LP_mmbotmutex);
+    portable_mutex_unlock(&LP_mmbot_iomutex);
+    printf("mmbot stopped, %d pairs cancelled\n",n);
+    return(clonestr("{\"result\":\"success\"}"));
+}
+
+char *LP_mmbot_start(void *ctx,cJSON *argjson)
+{
+    cJSON *array,*item; int32_t i,n; char *base,*rel,*feed; double pct; struct LP_mmbotpair pairs[LP_MMBOT_MAXPAIRS],*bp;
+    if ( (array= jarray(&n,argjson,"cfg")) == 0 || n <= 0 )
+        return(clonestr("{\"error\":\"need cfg array of pairs\"}"));
+    if ( n > LP_MMBOT_MAXPAIRS )
+        return(clonestr("{\"error\":\"too many pairs\"}"));
+    memset(pairs,0,sizeof(pairs));
+    for (i=0; i<n; i++)
+    {
+        item = jitem(array,i);
+        bp = &pairs[i];
+        if ( (base= jstr(item,"base")) == 0 || (rel= jstr(item,"rel")) == 0 || strlen(base) >= sizeof(bp->base) || strlen(rel) >= sizeof(bp->rel) || strcmp(base,rel) == 0 )
+            return(clonestr("{\"error\":\"each pair needs a distinct base and rel\"}"));
+        if ( LP_testnet_mismatch(base,rel) != 0 )
+            return(clonestr("{\"error\":\"cant price a testnet coin against a mainnet coin\"}"));
+        if ( (feed= jstr(item,"feed")) == 0 && LP_numpricefeeds > 0 )
+            feed = LP_pricefeeds[0].name;
+        if ( feed == 0 || LP_pricefeed_find(feed) == 0 )
+            return(clonestr("{\"error\":\"pair needs a configured pricefeed\"}"));
+        if ( (bp->spread= jdouble(item,"spread")) <= -1. )
+            return(clonestr("{\"error\":\"spread must be more than -1\"}"));
+        if ( jobj(item,"balance_percent") == 0 || is_cJSON_Number(jobj(item,"balance_percent")) == 0 || (pct= jdouble(item,"balance_percent")) <= 0. || pct > 100. || pct != pct )
+            return(clonestr("{\"error\":\"balance_percent must be a number in (0, 100]\"}"));
+        strcpy(bp->base,base);
+        strcpy(bp->rel,rel);
+        safecopy(bp->feed,feed,sizeof(bp->feed));
+        bp->balancepct = pct;
+        bp->minvolume = jdouble(item,"min_volume") * SATOSHIDEN;
+        bp->kick = 1;
+        strcpy(bp->status,"starting");
+    }
+    if ( LP_mmbot_running != 0 )
+        free(LP_mmbot_stop(ctx));
+    portable_mutex_lock(&LP_mmbotmutex);
+    memcpy(LP_mmbotpairs,pairs,sizeof(pairs));
+    LP_mmbot_numpairs = n;
+    if ( (LP_mmbot_refresh= juint(argjson,"refresh")) < 10 )
+        LP_mmbot_refresh = LP_MMBOT_REFRESH;
+    LP_mmbot_running = (uint32_t)time(NULL);
+    LP_mmbot_generation++;
+    portable_mutex_unlock(&LP_mmbotmutex);
+    for (i=0; i<n; i++)
+        LP_pricefeed_unpeg(pairs[i].base,pairs[i].rel); // the bot owns these pairs now
+    if ( OS_thread_create(malloc(sizeof(pthread_t)),NULL,(void *)LP_mmbot_loop,ctx) != 0 )
+    {
+        LP_mmbot_running = 0;
+        return(clonestr("{\"error\":\"couldnt launch simple market maker bot\"}"));
+    }
+    printf("mmbot started with %d pairs, refresh %ds\n",n,LP_mmbot_refresh);
+    return(clonestr("{\"result\":\"success\"}"));
+}
+
+char *LP_mmbot_status()
+{
+    int32_t i; cJSON *retjson,*array,*item; struct LP_mmbotpair *bp;
+    retjson = cJSON_CreateObject();
+    array = cJSON_CreateArray();
+    portable_mutex_lock(&LP_mmbotmutex);
+    for (i=0; i<LP_mmbot_numpairs; i++)
+    {
+        bp = &LP_mmbotpairs[i];
+        item = cJSON_CreateObject();
+        jaddstr(item,"base",bp->base);
+        jaddstr(item,"rel",bp->rel);
+        jaddstr(item,"feed",bp->feed);
+        jaddnum(item,"spread",bp->spread);
+        jaddnum(item,"balance_percent",bp->balancepct);
+        jaddnum(item,"min_volume",dstr(bp->minvolume));
+        jaddnum(item,"ask",bp->ask);
+        jaddnum(item,"volume",dstr(bp->volume));
+        jaddstr(item,"status",bp->status);
+        jaddnum(item,"lastupdate",bp->lastupdate);
+        jaddi(array,item);
+    }
+    portable_mutex_unlock(&LP_mmbotmutex);
+    jaddstr(retjson,"result","success");
+    jaddstr(retjson,"state",LP_mmbot_running != 0 ? "running" : "stopped");
+    jaddnum(retjson,"refresh",LP_mmbot_refresh);
+    jadd(retjson,"pairs",array);
+    return(jprint(retjson,1));
+}
@@ -99,7 +99,14 @@ void LP_mpnet_send(int32_t localcopy,char *msg,int32_t sendflag,char *otheraddr)
         if ( (fp= fopen(fname,"rb+")) == 0 )
             fp = fopen(fname,"wb+");
         else fseek(fp,0,SEEK_END);
-        fprintf(fp,"%s\n",msg);
+        if ( (argjson= cJSON_Parse(msg)) != 0 )
+        {
+            if ( jobj(argjson,"version") == 0 )
+                jaddnum(argjson,"version",LP_SCHEMA_VERSION);
+            hexstr = jprint(argjson,1);
+            fprintf(fp,"%s\n",hexstr);
+            free(hexstr);
+        } else fprintf(fp,"%s\n",msg);
         fclose(fp);
     }
     if ( G.mpnet != 0 && sendflag != 0 && (coin= LP_coinfind("CHIPS")) != 0 && coin->inactive == 0 )
@@ -0,0 +1,461 @@
+
+/******************************************************************************
+ * Copyright © 2014-2018 The SuperNET Developers.                             *
+ *                                                                            *
+ * See the AUTHORS, DEVELOPER-AGREEMENT and LICENSE files at                  *
+ * the top-level directory of this distribution for the individual copyright  *
+ * holder information and the developer policies on copyright and licensing.  *
+ *                                                                            *
+ * Unless otherwise agreed in a custom licensing agreement, no part of the    *
+ * SuperNET software, including this file may be copied, modified, propagated *
+ * or distributed except according to the terms contained in the LICENSE file *
+ *                                                                            *
+ * Removal or modification of this copyright notice is prohibited.            *
+ *                                                                            *
+ ******************************************************************************/
+//
+//  LP_multihop.c
+//  marketmaker
+//
+//  multihop_buy base/rel through an intermediate coin when there is no direct liquidity: leg1 buys via with rel, leg2
+//  buys base with whatever leg1 actually delivered. each leg is a normal taker swap with its own uuid derived from the
+//  route uuid, leg2 is only ordered after leg1 completed and the via balance is there. a failed or cancelled route
+//  leaves the funds in the coin of the last completed leg, nothing is unwound. routes are not persisted
+//
+
+#define LP_MAXMULTIHOPS 64
+#define LP_MULTIHOP_SLIPPAGE 0.01
+#define LP_MULTIHOP_TIMEOUT 30
+#define LP_MULTIHOP_STARTWAIT 60 // extra seconds past the order timeout for the swap thread to start
+
+struct LP_multihop
+{
+    char uuidstr[65],base[16],via[16],rel[16],status[32],leguuids[2][65],legstatus[2][16];
+    double prices[2],maxprice,slippage,volumes[2];
+    int64_t received;
+    uint32_t created,updated,ordered[2],requestids[2],quoteids[2];
+    int32_t leg,timeout,err;
+    uint8_t cancelled;
+} LP_multihops[LP_MAXMULTIHOPS];
+int32_t LP_nummultihops; uint32_t LP_multihop_running;
+portable_mutex_t LP_multihopmutex;
+
+// best ask for base/rel from the local orderbook, *maxvolp is how much base it offers
+double LP_multihop_bestask(char *base,char *rel,double *maxvolp)
+{
+    char *retstr; cJSON *retjson,*asks,*item; int32_t i,numasks; double price,bestprice = 0.;
+    *maxvolp = 0.;
+    if ( (retstr= LP_orderbook(base,rel,0,0.)) != 0 )
+    {
+        if ( (retjson= cJSON_Parse(retstr)) != 0 )
+        {
+            if ( (asks= jarray(&numasks,retjson,"asks")) != 0 )
+            {
+                for (i=0; i<numasks; i++)
+                {
+                    item = jitem(asks,i);
+                    if ( (price= jdouble(item,"price")) > SMALLVAL && (bestprice == 0. || price < bestprice) )
+                    {
+                        bestprice = price;
+                        *maxvolp = jdouble(item,"maxvolume");
+                    }
+                }
+            }
+            free_json(retjson);
+        }
+        free(retstr);
+    }
+    return(bestprice);
+}
+
+// cheapest base/via/rel route that can take relvolume at the best asks of both legs
+double LP_multihop_plan(char *via,char *base,char *rel,char *onlyvia,double relvolume,double *prices)
+{
+    struct iguana_info *coin,*tmp; double p1,p2,vol1,vol2,bestprice = 0.;
+    via[0] = 0;
+    HASH_ITER(hh,LP_coins,coin,tmp)
+    {
+        if ( coin->inactive != 0 || strcmp(coin->symbol,base) == 0 || strcmp(coin->symbol,rel) == 0 )
+            continue;
+        if ( onlyvia != 0 && onlyvia[0] != 0 && strcmp(coin->symbol,onlyvia) != 0 )
+            continue;
+        if ( (p1= LP_multihop_bestask(coin->symbol,rel,&vol1)) < SMALLVAL || vol1 * p1 < relvolume )
+            continue;
+        if ( (p2= LP_multihop_bestask(base,coin->symbol,&vol2)) < SMALLVAL || vol2 * p2 < relvolume / p1 )
+            continue;
+        if ( bestprice == 0. || p1 * p2 < bestprice )
+        {
+            bestprice = p1 * p2;
+            prices[0] = p1;
+            prices[1] = p2;
+            safecopy(via,coin->symbol,16);
+        }
+    }
+    return(bestprice);
+}
+
+struct LP_multihop *_LP_multihop_find(char *uuidstr)
+{
+    int32_t i;
+    for (i=0; i<LP_nummultihops; i++)
+        if ( strcmp(LP_multihops[i].uuidstr,uuidstr) == 0 )
+            return(&LP_multihops[i]);
+    return(0);
+}
+
+struct LP_multihop *_LP_multihop_leg(char *uuidstr,int32_t *legp)
+{
+    int32_t i,leg;
+    for (i=0; i<LP_nummultihops; i++)
+        for (leg=0; leg<2; leg++)
+            if ( strcmp(LP_multihops[i].leguuids[leg],uuidstr) == 0 )
+            {
+                *legp = leg;
+                return(&LP_multihops[i]);
+            }
+    return(0);
+}
+
+void _LP_multihop_status(struct LP_multihop *mp,char *status,int32_t err)
+{
+    safecopy(mp->status,status,sizeof(mp->status));
+    mp->err = err;
+    mp->updated = (uint32_t)time(NULL);
+    printf("multihop %s %s/%s/%s: %s\n",mp->uuidstr,mp->base,mp->via,mp->rel,status);
+}
+
+// route is done whichever way it ended
+int32_t _LP_multihop_done(struct LP_multihop *mp)
+{
+    return(strcmp(mp->status,"completed") == 0 || strcmp(mp->status,"failed") == 0 || strcmp(mp->status,"cancelled") == 0);
+}
+
+int32_t LP_multihop_order(void *ctx,char *uuidstr,int32_t leg)
+{
+    struct LP_multihop *mp,M; char *retstr,*base,*rel; cJSON *retjson; int32_t retval = -1; bits256 zero;
+    portable_mutex_lock(&LP_multihopmutex);
+    if ( (mp= _LP_multihop_find(uuidstr)) != 0 )
+        M = *mp;
+    portable_mutex_unlock(&LP_multihopmutex);
+    if ( mp == 0 )
+        return(-1);
+    base = leg == 0 ? M.via : M.base;
+    rel = leg == 0 ? M.rel : M.via;
+    memset(zero.bytes,0,sizeof(zero));
+    if ( (retstr= LP_autobuy(ctx,0,LP_myipaddr,LP_mypubsock,base,rel,M.prices[leg] * (1. + M.slippage),M.volumes[leg],0,M.timeout,0,"multihop",0,zero,0,M.leguuids[leg],0,0,0,0)) != 0 )
+    {
+        if ( (retjson= cJSON_Parse(retstr)) != 0 )
+        {
+            if ( jobj(retjson,"error") == 0 )
+                retval = 0;
+            free_json(retjson);
+        }
+        if ( retval < 0 )
+            printf("multihop %s leg%d order failed: %s\n",uuidstr,leg+1,retstr);
+        free(retstr);
+    }
+    portable_mutex_lock(&LP_multihopmutex);
+    if ( (mp= _LP_multihop_find(uuidstr)) != 0 )
+    {
+        mp->leg = leg;
+        if ( retval == 0 )
+        {
+            mp->ordered[leg] = (uint32_t)time(NULL);
+            if ( mp->legstatus[leg][0] == 0 || strcmp(mp->legstatus[leg],"sizing") == 0 ) // the swap can start before autobuy returns
+            {
+                strcpy(mp->legstatus[leg],"ordered");
+                _LP_multihop_status(mp,leg == 0 ? "leg1_ordered" : "leg2_ordered",0);
+            }
+        }
+        else
+        {
+            strcpy(mp->legstatus[leg],"failed");
+            _LP_multihop_status(mp,"failed",-1);
+        }
+    }
+    portable_mutex_unlock(&LP_multihopmutex);
+    return(retval);
+}
+
+// leg1 delivered, size leg2 from what actually arrived and reprice it against the route limit
+int32_t LP_multihop_leg2(char *uuidstr)
+{
+    struct LP_multihop *mp,M; struct iguana_info *viacoin; int64_t balance,available = 0; double p2 = 0.,maxvol; int32_t err = 0;
+    portable_mutex_lock(&LP_multihopmutex);
+    if ( (mp= _LP_multihop_find(uuidstr)) != 0 )
+        M = *mp;
+    portable_mutex_unlock(&LP_multihopmutex);
+    if ( mp == 0 )
+        return(-1);
+    if ( (viacoin= LP_coinfind(M.via)) == 0 || viacoin->inactive != 0 )
+        err = -2;
+    else
+    {
+        balance = LP_mmbot_balance(viacoin);
+        if ( (available= M.received) <= 0 || available > balance - 2*viacoin->txfee )
+            available = balance - 2*viacoin->txfee;
+        if ( available <= 0 || available < viacoin->mintradevol )
+        {
+            printf("multihop %s: %s balance %.8f too low for leg2\n",uuidstr,M.via,dstr(balance));
+            err = -3;
+        }
+        else if ( (p2= LP_multihop_bestask(M.base,M.via,&maxvol)) < SMALLVAL || (M.maxprice > SMALLVAL && M.prices[0] * p2 > M.maxprice * (1. + M.slippage)) )
+            err = -4;
+    }
+    portable_mutex_lock(&LP_multihopmutex);
+    if ( (mp= _LP_multihop_find(uuidstr)) != 0 )
+    {
+        if ( err != 0 )
+        {
+            strcpy(mp->legstatus[1],"failed");
+            _LP_multihop_status(mp,"failed",err);
+        }
+        else
+        {
+            mp->prices[1] = p2;
+            mp->volumes[1] = dstr(available);
+        }
+    }
+    portable_mutex_unlock(&LP_multihopmutex);
+    return(mp != 0 && err == 0 ? 0 : -1);
+}
+
+void LP_multihop_loop(void *ctx)
+{
+    struct LP_multihop *mp; int32_t i,n,leg; char uuids[LP_MAXMULTIHOPS][65]; uint32_t now;
+    while ( LP_STOP_RECEIVED == 0 )
+    {
+        n = 0;
+        now = (uint32_t)time(NULL);
+        portable_mutex_lock(&LP_multihopmutex);
+        for (i=0; i<LP_nummultihops; i++)
+        {
+            mp = &LP_multihops[i];
+            if ( _LP_multihop_done(mp) != 0 )
+                continue;
+            leg = mp->leg;
+            if ( strcmp(mp->legstatus[leg],"ordered") == 0 && now > mp->ordered[leg] + mp->timeout + LP_MULTIHOP_STARTWAIT )
+            {
+                strcpy(mp->legstatus[leg],"unmatched");
+                _LP_multihop_status(mp,"failed",-5);
+            }
+            else if ( leg == 0 && strcmp(mp->legstatus[0],"completed") == 0 && mp->legstatus[1][0] == 0 )
+            {
+                if ( mp->cancelled != 0 )
+                    _LP_multihop_status(mp,"cancelled",0);
+                else
+                {
+                    strcpy(mp->legstatus[1],"sizing");
+                    safecopy(uuids[n++],mp->uuidstr,sizeof(uuids[0]));
+                }
+            }
+        }
+        portable_mutex_unlock(&LP_multihopmutex);
+        for (i=0; i<n; i++)
+            if ( LP_multihop_leg2(uuids[i]) == 0 )
+                LP_multihop_order(ctx,uuids[i],1);
+        sleep(1);
+    }
+    LP_multihop_running = 0;
+}
+
+// called from the taker swap thread on started, completed and failed
+void LP_multihop_match(struct basilisk_swap *swap,char *event)
+{
+    struct LP_multihop *mp; int32_t leg;
+    if ( swap == 0 || swap->I.iambob != 0 || LP_nummultihops == 0 )
+        return;
+    portable_mutex_lock(&LP_multihopmutex);
+    if ( (mp= _LP_multihop_leg(swap->uuidstr,&leg)) != 0 && _LP_multihop_done(mp) == 0 )
+    {
+        mp->requestids[leg] = swap->I.req.requestid;
+        mp->quoteids[leg] = swap->I.req.quoteid;
+        safecopy(mp->legstatus[leg],event,sizeof(mp->legstatus[leg]));
+        if ( strcmp(event,"started") == 0 )
+            _LP_multihop_status(mp,leg == 0 ? "leg1_swapping" : "leg2_swapping",0);
+        else if ( strcmp(event,"failed") == 0 )
+            _LP_multihop_status(mp,"failed",-6);
+        else if ( leg == 0 )
+        {
+            mp->received = swap->I.bobsatoshis - swap->I.Btxfee;
+            _LP_multihop_status(mp,"leg1_completed",0);
+        }
+        else _LP_multihop_status(mp,"completed",0);
+    }
+    portable_mutex_unlock(&LP_multihopmutex);
+}
+
+cJSON *_LP_multihop_json(struct LP_multihop *mp)
+{
+    int32_t leg; cJSON *item,*legs,*legitem;
+    item = cJSON_CreateObject();
+    jaddstr(item,"uuid",mp->uuidstr);
+    jaddstr(item,"base",mp->base);
+    jaddstr(item,"via",mp->via);
+    jaddstr(item,"rel",mp->rel);
+    jaddstr(item,"status",mp->status);
+    if ( mp->err != 0 )
+        jaddnum(item,"error",mp->err);
+    jaddnum(item,"price",mp->prices[0] * mp->prices[1]);
+    jaddnum(item,"maxprice",mp->maxprice);
+    jaddnum(item,"relvolume",mp->volumes[0]);
+    if ( _LP_multihop_done(mp) != 0 && strcmp(mp->status,"completed") != 0 )
+        jaddstr(item,"funds_in",strcmp(mp->legstatus[0],"completed") == 0 && strcmp(mp->legstatus[1],"completed") != 0 ? mp->via : mp->rel);
+    legs = cJSON_CreateArray();
+    for (leg=0; leg<2; leg++)
+    {
+        legitem = cJSON_CreateObject();
+        jaddstr(legitem,"uuid",mp->leguuids[leg]);
+        jaddstr(legitem,"base",leg == 0 ? mp->via : mp->base);
+        jaddstr(legitem,"rel",leg == 0 ? mp->rel : mp->via);
+        jaddnum(legitem,"price",mp->prices[leg]);
+        jaddnum(legitem,"relvolume",mp->volumes[leg]);
+        jaddstr(legitem,"status",mp->legstatus[leg][0] != 0 ? mp->legstatus[leg] : "waiting");
+        if ( mp->requestids[leg] != 0 )
+        {
+            jaddnum(legitem,"requestid",mp->requestids[leg]);
+            jaddnum(legitem,"quoteid",mp->quoteids[leg]);
+        }
+        jaddi(legs,legitem);
+    }
+    jadd(item,"legs",legs);
+    jaddnum(item,"created",mp->created);
+    jaddnum(item,"updated",mp->updated);
+    return(item);
+}
+
+char *LP_multihop_status(char *uuidstr)
+{
+    struct LP_multihop *mp; cJSON *retjson,*array; int32_t i;
+    retjson = cJSON_CreateObject();
+    portable_mutex_lock(&LP_multihopmutex);
+    if ( uuidstr != 0 && uuidstr[0] != 0 )
+    {
+        if ( (mp= _LP_multihop_find(uuidstr)) == 0 )
+        {
+            portable_mutex_unlock(&LP_multihopmutex);
+            free_json(retjson);
+            return(clonestr("{\"error\":\"route not found\"}"));
+        }
+        jaddstr(retjson,"result","success");
+        jadd(retjson,"route",_LP_multihop_json(mp));
+    }
+    else
+    {
+        array = cJSON_CreateArray();
+        for (i=0; i<LP_nummultihops; i++)
+            jaddi(array,_LP_multihop_json(&LP_multihops[i]));
+        jaddstr(retjson,"result","success");
+        jadd(retjson,"routes",array);
+    }
+    portable_mutex_unlock(&LP_multihopmutex);
+    return(jprint(retjson,1));
+}
+
+char *LP_multihop_buy(void *ctx,cJSON *argjson)
+{
+    struct LP_multihop *mp; char *base,*rel,via[16],uuidstr[65]; double relvolume,maxprice,price,prices[2]; uint8_t uuidhash[256]; bits256 hash; uint64_t millis; int32_t i,leg,len = 0; cJSON *retjson;
+    if ( (base= jstr(argjson,"base")) == 0 || (rel= jstr(argjson,"rel")) == 0 || strcmp(base,rel) == 0 )
+        return(clonestr("{\"error\":\"need distinct base and rel\"}"));
+    if ( (relvolume= jdouble(argjson,"relvolume")) <= SMALLVAL )
+        return(clonestr("{\"error\":\"need relvolume\"}"));
+    if ( LP_shutdown_started != 0 )
+        return(clonestr("{\"error\":\"shutting down\"}"));
+    maxprice = jdouble(argjson,"maxprice");
+    if ( (price= LP_multihop_plan(via,base,rel,jstr(argjson,"via"),relvolume,prices)) < SMALLVAL )
+        return(clonestr("{\"error\":\"no route with enough liquidity on both legs\"}"));
+    if ( maxprice > SMALLVAL && price > maxprice )
+    {
+        retjson = cJSON_CreateObject();
+        jaddstr(retjson,"error","best route is above maxprice");
+        jaddstr(retjson,"via",via);
+        jaddnum(retjson,"price",price);
+        return(jprint(retjson,1));
+    }
+    memcpy(uuidhash,&G.LP_mypub25519,sizeof(bits256)), len += sizeof(bits256);
+    millis = OS_milliseconds();
+    memcpy(&uuidhash[len],&millis,sizeof(millis)), len += sizeof(millis);
+    memcpy(&uuidhash[len],base,(int32_t)strlen(base)), len += (int32_t)strlen(base);
+    memcpy(&uuidhash[len],via,(int32_t)strlen(via)), len += (int32_t)strlen(via);
+    memcpy(&uuidhash[len],rel,(int32_t)strlen(rel)), len += (int32_t)strlen(rel);
+    vcalc_sha256(0,hash.bytes,uuidhash,len);
+    bits256_str(uuidstr,hash);
+    portable_mutex_lock(&LP_multihopmutex);
+    if ( LP_nummultihops == LP_MAXMULTIHOPS ) // recycle the oldest finished route
+    {
+        for (i=0; i<LP_nummultihops; i++)
+            if ( _LP_multihop_done(&LP_multihops[i]) != 0 )
+                break;
+        if ( i == LP_nummultihops )
+        {
+            portable_mutex_unlock(&LP_multihopmutex);
+            return(clonestr("{\"error\":\"too many routes in progress\"}"));
+        }
+        LP_multihops[i] = LP_multihops[--LP_nummultihops];
+    }
+    mp = &LP_multihops[LP_nummultihops++];
+    memset(mp,0,sizeof(*mp));
+    strcpy(mp->uuidstr,uuidstr);
+    safecopy(mp->base,base,sizeof(mp->base));
+    safecopy(mp->via,via,sizeof(mp->via));
+    safecopy(mp->rel,rel,sizeof(mp->rel));
+    for (leg=0; leg<2; leg++)
+    {
+        memcpy(uuidhash,hash.bytes,sizeof(hash));
+        uuidhash[sizeof(hash)] = leg;
+        vcalc_sha256(0,hash.bytes,uuidhash,sizeof(hash)+1);
+        bits256_str(mp->leguuids[leg],hash);
+    }
+    memcpy(mp->prices,prices,sizeof(mp->prices));
+    mp->volumes[0] = relvolume;
+    mp->maxprice = maxprice;
+    if ( (mp->slippage= jdouble(argjson,"slippage")) <= 0. || mp->slippage >= 1. )
+        mp->slippage = LP_MULTIHOP_SLIPPAGE;
+    if ( (mp->timeout= jint(argjson,"timeout")) <= 0 )
+        mp->timeout = LP_MULTIHOP_TIMEOUT;
+    mp->created = (uint32_t)time(NULL);
+    _LP_multihop_status(mp,"planned",0);
+    if ( LP_multihop_running == 0 )
+    {
+        LP_multihop_running = (uint32_t)time(NULL);
+        if ( OS_thread_create(malloc(sizeof(pthread_t)),NULL,(void *)LP_multihop_loop,ctx) != 0 )
+        {
+            LP_multihop_running = 0;
+            _LP_multihop_status(mp,"failed",-7);
+            portable_mutex_unlock(&LP_multihopmutex);
+            return(clonestr("{\"error\":\"couldnt launch multihop loop\"}"));
+        }
+    }
+    portable_mutex_unlock(&LP_multihopmutex);
+    LP_multihop_order(ctx,uuidstr,0);
+    return(LP_multihop_status(uuidstr));
+}
+
+char *LP_multihop_cancel(char *uuidstr)
+{
+    struct LP_multihop *mp; char *retstr = 0;
+    if ( uuidstr == 0 )
+        return(clonestr("{\"error\":\"need uuid\"}"));
+    portable_mutex_lock(&LP_multihopmutex);
+    if ( (mp= _LP_multihop_find(uuidstr)) == 0 )
+        retstr = clonestr("{\"error\":\"route not found\"}");
+    else if ( _LP_multihop_done(mp) != 0 )
+        retstr = clonestr("{\"error\":\"route already finished\"}");
+    else if ( strcmp(mp->legstatus[mp->leg],"ordered") == 0 ) // not matched yet, drop the pending order
+    {
+        if ( strcmp(LP_Alicequery.uuidstr,mp->leguuids[mp->leg]) == 0 )
+            LP_alicequery_clear();
+        strcpy(mp->legstatus[mp->leg],"cancelled");
+        _LP_multihop_status(mp,"cancelled",0);
+    }
+    else if ( mp->leg == 0 ) // leg1 swap is running, dont start leg2 once it completes
+    {
+        mp->cancelled = 1;
+        retstr = clonestr("{\"result\":\"success\",\"status\":\"leg1 in progress, leg2 will not be ordered\"}");
+    }
+    else retstr = clonestr("{\"error\":\"leg2 swap in progress, cant cancel\"}");
+    portable_mutex_unlock(&LP_multihopmutex);
+    if ( retstr == 0 )
+        retstr = LP_multihop_status(uuidstr);
+    return(retstr);
+}
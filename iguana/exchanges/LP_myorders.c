@@ -0,0 +1,453 @@
+
+/******************************************************************************
+ * Copyright © 2014-2018 The SuperNET Developers.                             *
+ *                                                                            *
+ * See the AUTHORS, DEVELOPER-AGREEMENT and LICENSE files at                  *
+ * the top-level directory of this distribution for the individual copyright  *
+ * holder information and the developer policies on copyright and licensing.  *
+ *                                                                            *
+ * Unless otherwise agreed in a custom licensing agreement, no part of the    *
+ * SuperNET software, including this file may be copied, modified, propagated *
+ * or distributed except according to the terms contained in the LICENSE file *
+ *                                                                            *
+ * Removal or modification of this copyright notice is prohibited.            *
+ *                                                                            *
+ ******************************************************************************/
+//
+//  LP_myorders.c
+//  marketmaker
+//
+//  maker orders placed with setprice are kept in DB/GTC/makerorders so a restart doesnt pull liquidity. on startup they
+//  wait until both coins are enabled and the base coin still has a balance, then get set and broadcast again.
+//  "persist_orders":0 at startup goes back to exit being a cancel all
+//
+
+#define LP_MAXMYORDERS 256
+#define LP_MYORDER_MAXMATCHES 64
+#define LP_MYORDER_RETRY 60
+
+struct LP_myorder
+{
+    char base[16],rel[16],feed[32],status[16];
+    double price,spread;
+    int64_t volume;
+    uint32_t created,updated,lastcheck;
+    uint8_t pegged,pending;
+    cJSON *matches;
+} LP_myorders[LP_MAXMYORDERS];
+int32_t LP_nummyorders,LP_persist_orders = 1;
+portable_mutex_t LP_myordersmutex;
+
+char *LP_myorders_fname(char *fname)
+{
+    sprintf(fname,"%s/GTC/makerorders",GLOBAL_DBDIR), OS_compatible_path(fname);
+    return(fname);
+}
+
+struct LP_myorder *_LP_myorder_find(char *base,char *rel)
+{
+    int32_t i;
+    for (i=0; i<LP_nummyorders; i++)
+        if ( strcmp(LP_myorders[i].base,base) == 0 && strcmp(LP_myorders[i].rel,rel) == 0 )
+            return(&LP_myorders[i]);
+    return(0);
+}
+
+cJSON *_LP_myorder_json(struct LP_myorder *op)
+{
+    cJSON *item = cJSON_CreateObject();
+    jaddstr(item,"base",op->base);
+    jaddstr(item,"rel",op->rel);
+    jaddnum(item,"price",op->price);
+    jaddnum(item,"volume",dstr(op->volume));
+    if ( op->pegged != 0 )
+    {
+        jaddnum(item,"price_autoupdate",1);
+        jaddstr(item,"feed",op->feed);
+        jaddnum(item,"spread",op->spread);
+    }
+    jaddnum(item,"created",op->created);
+    jaddnum(item,"updated",op->updated);
+    jadd(item,"matches",op->matches != 0 ? jduplicate(op->matches) : cJSON_CreateArray());
+    return(item);
+}
+
+int32_t _LP_myorders_save()
+{
+    FILE *fp; char fname[512],tmpname[512],*str; int32_t i; cJSON *item,*array;
+    if ( LP_persist_orders == 0 )
+        return(0);
+    LP_myorders_fname(fname);
+    sprintf(tmpname,"%s.tmp",fname);
+    if ( (fp= fopen(tmpname,"wb")) == 0 )
+        return(-1);
+    array = cJSON_CreateArray();
+    for (i=0; i<LP_nummyorders; i++)
+    {
+        item = _LP_myorder_json(&LP_myorders[i]);
+        if ( strcmp(LP_myorders[i].status,"filled") == 0 )
+            jaddnum(item,"filled",1);
+        jaddnum(item,"version",LP_SCHEMA_VERSION);
+        jaddi(array,item);
+    }
+    str = jprint(array,1);
+    fprintf(fp,"%s\n",str);
+    free(str);
+    fclose(fp);
+    return(OS_renamefile(tmpname,fname));
+}
+
+void _LP_myorder_remove(struct LP_myorder *op)
+{
+    int32_t i = (int32_t)(op - LP_myorders);
+    if ( op->matches != 0 )
+        free_json(op->matches);
+    if ( i != --LP_nummyorders )
+        LP_myorders[i] = LP_myorders[LP_nummyorders];
+    memset(&LP_myorders[LP_nummyorders],0,sizeof(LP_myorders[LP_nummyorders]));
+}
+
+cJSON *_LP_myorder_matchfind(struct LP_myorder *op,uint32_t requestid,uint32_t quoteid)
+{
+    int32_t i,n; cJSON *item;
+    n = cJSON_GetArraySize(op->matches);
+    for (i=0; i<n; i++)
+    {
+        item = jitem(op->matches,i);
+        if ( juint(item,"requestid") == requestid && juint(item,"quoteid") == quoteid )
+            return(item);
+    }
+    return(0);
+}
+
+cJSON *_LP_myorder_matchadd(struct LP_myorder *op,char *uuidstr,uint32_t requestid,uint32_t quoteid,int64_t basesatoshis,int64_t relsatoshis,char *event)
+{
+    cJSON *item = cJSON_CreateObject();
+    jaddstr(item,"uuid",uuidstr);
+    jaddnum(item,"requestid",requestid);
+    jaddnum(item,"quoteid",quoteid);
+    jaddnum(item,"basevol",dstr(basesatoshis));
+    jaddnum(item,"relvol",dstr(relsatoshis));
+    jaddnum(item,"started",time(NULL));
+    jaddstr(item,"status",event);
+    jaddi(op->matches,item);
+    while ( cJSON_GetArraySize(op->matches) > LP_MYORDER_MAXMATCHES )
+        cJSON_DeleteItemFromArray(op->matches,0);
+    return(item);
+}
+
+int64_t _LP_myorder_reserved(struct LP_myorder *op)
+{
+    int32_t i,n; int64_t reserved = 0;
+    n = cJSON_GetArraySize(op->matches);
+    for (i=0; i<n; i++)
+        reserved += jdouble(jitem(op->matches,i),"reserved") * SATOSHIDEN;
+    return(reserved);
+}
+
+// called after a setprice rpc went through, price 0 is a cancel
+void LP_myorder_update(char *base,char *rel,double price,cJSON *argjson)
+{
+    struct LP_myorder *op; int32_t i; uint32_t now = (uint32_t)time(NULL);
+    portable_mutex_lock(&LP_myordersmutex);
+    if ( (op= _LP_myorder_find(base,rel)) != 0 && price == 0. )
+        _LP_myorder_remove(op);
+    else if ( price != 0. )
+    {
+        if ( op == 0 && LP_nummyorders < LP_MAXMYORDERS )
+        {
+            op = &LP_myorders[LP_nummyorders++];
+            memset(op,0,sizeof(*op));
+            safecopy(op->base,base,sizeof(op->base));
+            safecopy(op->rel,rel,sizeof(op->rel));
+            op->created = now;
+            op->matches = cJSON_CreateArray();
+        }
+        if ( op != 0 )
+        {
+            op->price = price;
+            if ( jobj(argjson,"volume") != 0 )
+            {
+                // a new size replaces whatever running swaps had reserved out of the old one
+                op->volume = jdouble(argjson,"volume") * SATOSHIDEN;
+                for (i=0; i<cJSON_GetArraySize(op->matches); i++)
+                    jdelete(jitem(op->matches,i),"reserved");
+            }
+            if ( (op->pegged= (jint(argjson,"price_autoupdate") != 0)) != 0 )
+            {
+                safecopy(op->feed,jstr(argjson,"feed") != 0 ? jstr(argjson,"feed") : "",sizeof(op->feed));
+                op->spread = jdouble(argjson,"spread");
+            }
+            else
+            {
+                op->feed[0] = 0;
+                op->spread = 0.;
+            }
+            op->pending = 0;
+            strcpy(op->status,"active");
+            op->updated = now;
+        } else printf("LP_myorders full, %s/%s wont survive a restart\n",base,rel);
+    }
+    if ( _LP_myorders_save() < 0 )
+        printf("couldnt save maker orders\n");
+    portable_mutex_unlock(&LP_myordersmutex);
+}
+
+void LP_myorders_init(cJSON *argjson)
+{
+    char fname[512],*fstr,*base,*rel; long fsize; int32_t i,n; cJSON *array,*item,*matches; struct LP_myorder *op;
+    if ( jobj(argjson,"persist_orders") != 0 )
+        LP_persist_orders = jint(argjson,"persist_orders");
+    if ( LP_persist_orders == 0 || (fstr= OS_filestr(&fsize,LP_myorders_fname(fname))) == 0 )
+        return;
+    if ( (array= cJSON_Parse(fstr)) != 0 )
+    {
+        n = cJSON_GetArraySize(array);
+        for (i=0; i<n && LP_nummyorders<LP_MAXMYORDERS; i++)
+        {
+            item = jitem(array,i);
+            if ( (base= jstr(item,"base")) == 0 || (rel= jstr(item,"rel")) == 0 || jdouble(item,"price") <= SMALLVAL )
+                continue;
+            op = &LP_myorders[LP_nummyorders++];
+            memset(op,0,sizeof(*op));
+            safecopy(op->base,base,sizeof(op->base));
+            safecopy(op->rel,rel,sizeof(op->rel));
+            op->price = jdouble(item,"price");
+            op->volume = jdouble(item,"volume") * SATOSHIDEN;
+            if ( (op->pegged= (jint(item,"price_autoupdate") != 0)) != 0 )
+            {
+                safecopy(op->feed,jstr(item,"feed") != 0 ? jstr(item,"feed") : "",sizeof(op->feed));
+                op->spread = jdouble(item,"spread");
+            }
+            op->created = juint(item,"created");
+            op->updated = juint(item,"updated");
+            op->matches = (matches= jarray(0,item,"matches")) != 0 ? jduplicate(matches) : cJSON_CreateArray();
+            if ( jint(item,"filled") != 0 )
+                strcpy(op->status,"filled");
+            else
+            {
+                op->pending = 1;
+                strcpy(op->status,"restoring");
+            }
+        }
+        free_json(array);
+        printf("%d maker orders to restore from %s\n",LP_nummyorders,fname);
+    } else printf("unparseable %s, maker orders not restored\n",fname);
+    free(fstr);
+}
+
+int32_t LP_myorder_restore(void *ctx,struct LP_myorder *op)
+{
+    struct iguana_info *basecoin,*relcoin; cJSON *argjson; char *retstr; int64_t balance; int32_t changed;
+    if ( (basecoin= LP_coinfind(op->base)) == 0 || (relcoin= LP_coinfind(op->rel)) == 0 || basecoin->inactive != 0 || relcoin->inactive != 0 )
+    {
+        strcpy(op->status,"waiting_coins");
+        return(0);
+    }
+    if ( basecoin->electrum != 0 )
+        balance = LP_unspents_load(basecoin->symbol,basecoin->smartaddr);
+    else balance = LP_RTsmartbalance(basecoin);
+    if ( balance <= 0 )
+    {
+        strcpy(op->status,"no_balance");
+        return(0);
+    }
+    if ( op->volume > balance )
+        printf("maker order %s/%s volume %.8f more than %.8f balance, restored anyway\n",op->base,op->rel,dstr(op->volume),dstr(balance));
+    if ( op->pegged != 0 )
+    {
+        argjson = cJSON_CreateObject();
+        jaddnum(argjson,"price_autoupdate",1);
+        if ( op->feed[0] != 0 )
+            jaddstr(argjson,"feed",op->feed);
+        jaddnum(argjson,"spread",op->spread);
+        if ( op->volume != 0 )
+            jaddnum(argjson,"volume",dstr(op->volume));
+        retstr = LP_pricefeed_peg(ctx,LP_myipaddr,LP_mypubsock,op->base,op->rel,argjson);
+        free_json(argjson);
+        if ( retstr != 0 && strncmp(retstr,"{\"error",7) == 0 )
+        {
+            printf("maker order %s/%s peg not restored %s\n",op->base,op->rel,retstr);
+            free(retstr);
+            strcpy(op->status,"waiting_feed");
+            return(0);
+        }
+    }
+    else
+    {
+        if ( LP_mypriceset(1,&changed,op->base,op->rel,op->price) < 0 )
+            return(-1);
+        if ( op->volume != 0 )
+            LP_myvolume_set(op->base,op->rel,op->volume);
+        retstr = LP_pricepings(ctx,LP_myipaddr,LP_mypubsock,op->base,op->rel,op->price * LP_profitratio);
+    }
+    if ( retstr != 0 )
+        free(retstr);
+    printf("restored maker order %s/%s %.8f vol %.8f\n",op->base,op->rel,op->price,dstr(op->volume));
+    strcpy(op->status,"active");
+    op->pending = 0;
+    return(1);
+}
+
+// balance checks and pings go out on a copy, the mutex is only held to pick the order and to store the outcome
+void LP_myorders_restore(void *ctx)
+{
+    int32_t i,retval; struct LP_myorder *op,O; uint32_t now = (uint32_t)time(NULL);
+    for (i=0; ; i++)
+    {
+        portable_mutex_lock(&LP_myordersmutex);
+        for (; i<LP_nummyorders; i++)
+            if ( LP_myorders[i].pending != 0 && now >= LP_myorders[i].lastcheck + LP_MYORDER_RETRY )
+                break;
+        if ( i >= LP_nummyorders )
+        {
+            portable_mutex_unlock(&LP_myordersmutex);
+            break;
+        }
+        LP_myorders[i].lastcheck = now;
+        O = LP_myorders[i];
+        O.matches = 0;
+        portable_mutex_unlock(&LP_myordersmutex);
+        retval = LP_myorder_restore(ctx,&O);
+        portable_mutex_lock(&LP_myordersmutex);
+        if ( (op= _LP_myorder_find(O.base,O.rel)) != 0 && op->pending != 0 )
+        {
+            if ( retval < 0 )
+            {
+                printf("maker order %s/%s cant be restored, dropped\n",op->base,op->rel);
+                _LP_myorder_remove(op);
+                _LP_myorders_save();
+                i--;
+            }
+            else
+            {
+                strcpy(op->status,O.status);
+                op->pending = O.pending;
+            }
+        }
+        portable_mutex_unlock(&LP_myordersmutex);
+    }
+}
+
+// LP_myvolume_filled took the swap amount out of a sized order at connect, keep it reserved until the swap is done
+void LP_myorder_reserve(struct LP_quoteinfo *qp,int64_t taken,int64_t remaining,int32_t filled)
+{
+    struct LP_myorder *op; cJSON *item;
+    portable_mutex_lock(&LP_myordersmutex);
+    if ( (op= _LP_myorder_find(qp->srccoin,qp->destcoin)) != 0 && op->matches != 0 )
+    {
+        if ( (item= _LP_myorder_matchfind(op,qp->R.requestid,qp->R.quoteid)) == 0 )
+            item = _LP_myorder_matchadd(op,qp->uuidstr,qp->R.requestid,qp->R.quoteid,qp->satoshis,qp->destsatoshis,"reserved");
+        jdelete(item,"reserved");
+        jaddnum(item,"reserved",dstr(taken));
+        op->volume = remaining;
+        if ( filled != 0 )
+            strcpy(op->status,"filled");
+        _LP_myorders_save();
+    }
+    portable_mutex_unlock(&LP_myordersmutex);
+}
+
+// a failed swap puts its reserved volume back, a filled order that was pulled goes back on the book. returns the price to ping
+double _LP_myorder_release(struct LP_myorder *op,int64_t reserved)
+{
+    int64_t volume; int32_t changed; double bid,ask;
+    if ( strcmp(op->status,"filled") == 0 )
+    {
+        volume = op->volume + reserved;
+        if ( LP_mypriceset(1,&changed,op->base,op->rel,op->price) < 0 )
+            return(0.);
+        strcpy(op->status,"active");
+    } else volume = LP_myvolume(op->base,op->rel) + reserved;
+    op->volume = volume;
+    LP_myvolume_set(op->base,op->rel,volume);
+    printf("%s/%s swap failed, %.8f back in the order\n",op->base,op->rel,dstr(reserved));
+    return(LP_myprice(1,&bid,&ask,op->base,op->rel));
+}
+
+// a pegged order whose feed went stale keeps its entry while the ask is pulled, its volume goes back with the price
+void LP_myorder_feedstale(char *base,char *rel,int32_t staleflag)
+{
+    struct LP_myorder *op;
+    portable_mutex_lock(&LP_myordersmutex);
+    if ( (op= _LP_myorder_find(base,rel)) != 0 && op->pending == 0 && op->pegged != 0 )
+    {
+        if ( staleflag != 0 )
+            strcpy(op->status,"feed_stale");
+        else if ( strcmp(op->status,"feed_stale") == 0 )
+        {
+            strcpy(op->status,"active");
+            if ( op->volume != 0 )
+                LP_myvolume_set(base,rel,op->volume);
+        }
+    }
+    portable_mutex_unlock(&LP_myordersmutex);
+}
+
+void LP_myorder_match(struct basilisk_swap *swap,char *event)
+{
+    struct LP_myorder *op; cJSON *item; int64_t reserved; double pingprice = 0.; char *retstr;
+    if ( swap == 0 || swap->I.iambob == 0 )
+        return;
+    portable_mutex_lock(&LP_myordersmutex);
+    if ( (op= _LP_myorder_find(swap->I.bobstr,swap->I.alicestr)) != 0 && op->matches != 0 )
+    {
+        if ( (item= _LP_myorder_matchfind(op,swap->I.req.requestid,swap->I.req.quoteid)) == 0 )
+            _LP_myorder_matchadd(op,swap->uuidstr,swap->I.req.requestid,swap->I.req.quoteid,swap->I.bobsatoshis,swap->I.alicesatoshis,event);
+        else
+        {
+            jdelete(item,"status");
+            jaddstr(item,"status",event);
+            if ( strcmp(event,"started") != 0 )
+            {
+                jaddnum(item,"finished",time(NULL));
+                if ( (reserved= jdouble(item,"reserved") * SATOSHIDEN) > 0 && strcmp(event,"failed") == 0 )
+                    pingprice = _LP_myorder_release(op,reserved);
+                jdelete(item,"reserved");
+            }
+        }
+        if ( strcmp(op->status,"filled") == 0 && _LP_myorder_reserved(op) == 0 )
+        {
+            printf("maker order %s/%s completely filled\n",op->base,op->rel);
+            _LP_myorder_remove(op);
+        }
+        _LP_myorders_save();
+    }
+    portable_mutex_unlock(&LP_myordersmutex);
+    if ( pingprice > SMALLVAL && (retstr= LP_pricepings(swap->ctx,LP_myipaddr,LP_mypubsock,swap->I.bobstr,swap->I.alicestr,pingprice)) != 0 )
+        free(retstr);
+}
+
+char *LP_myorders_json()
+{
+    int32_t i,changed = 0; double bid,ask; cJSON *retjson,*array,*item; struct LP_myorder *op;
+    retjson = cJSON_CreateObject();
+    array = cJSON_CreateArray();
+    portable_mutex_lock(&LP_myordersmutex);
+    for (i=0; i<LP_nummyorders; i++)
+    {
+        op = &LP_myorders[i];
+        if ( op->pending == 0 && strcmp(op->status,"active") == 0 && LP_myprice(1,&bid,&ask,op->base,op->rel) < SMALLVAL )
+        {
+            // cancelled behind our back, e.g. setprice 0 through autoprice
+            _LP_myorder_remove(op);
+            changed++;
+            i--;
+            continue;
+        }
+        item = _LP_myorder_json(op);
+        jaddstr(item,"status",op->status);
+        if ( op->pending == 0 && strcmp(op->status,"active") == 0 )
+            jaddnum(item,"ask",ask);
+        else if ( strcmp(op->status,"filled") == 0 )
+            jaddnum(item,"reserved",dstr(_LP_myorder_reserved(op)));
+        jaddi(array,item);
+    }
+    if ( changed != 0 )
+        _LP_myorders_save();
+    portable_mutex_unlock(&LP_myordersmutex);
+    jadd(retjson,"orders",array);
+    jaddnum(retjson,"persist",LP_persist_orders);
+    jaddstr(retjson,"result","success");
+    return(jprint(retjson,1));
+}
@@ -30,6 +30,7 @@
 // there is an issue about waiting for notarization for a swap that never starts (expiration ok)
 
 #include <stdio.h>
+#include <stdarg.h>
 #ifndef MM_VERSION
 #define MM_VERSION "UNKNOWN"
 #endif
@@ -93,7 +94,7 @@ void LP_millistats_update(struct LP_millistats *mp)
 #include "LP_etomic.h"
 #endif
 
-portable_mutex_t LP_peermutex,LP_UTXOmutex,LP_utxomutex,LP_commandmutex,LP_cachemutex,LP_swaplistmutex,LP_forwardmutex,LP_pubkeymutex,LP_networkmutex,LP_psockmutex,LP_coinmutex,LP_messagemutex,LP_portfoliomutex,LP_electrummutex,LP_butxomutex,LP_reservedmutex,LP_nanorecvsmutex,LP_tradebotsmutex,LP_gcmutex,LP_inusemutex,LP_cJSONmutex,LP_logmutex,LP_statslogmutex,LP_tradesmutex,LP_commandQmutex,LP_blockinit_mutex,LP_pendswap_mutex,LP_listmutex,LP_gtcmutex;
+portable_mutex_t LP_peermutex,LP_UTXOmutex,LP_utxomutex,LP_commandmutex,LP_cachemutex,LP_swaplistmutex,LP_forwardmutex,LP_pubkeymutex,LP_networkmutex,LP_psockmutex,LP_coinmutex,LP_messagemutex,LP_portfoliomutex,LP_electrummutex,LP_butxomutex,LP_reservedmutex,LP_nanorecvsmutex,LP_tradebotsmutex,LP_gcmutex,LP_inusemutex,LP_cJSONmutex,LP_logmutex,LP_statslogmutex,LP_tradesmutex,LP_commandQmutex,LP_blockinit_mutex,LP_pendswap_mutex,LP_listmutex,LP_gtcmutex,LP_swapabortmutex,LP_exposuremutex,LP_tokenmutex,LP_inflightmutex,LP_countdownmutex,LP_utxolockmutex,LP_maintqmutex;
 int32_t LP_canbind;
 char *Broadcaststr,*Reserved_msgs[2][1000];
 int32_t num_Reserved_msgs[2],max_Reserved_msgs[2];
@@ -112,21 +113,21 @@ int32_t LP_STOP_RECEIVED,LP_numactive_LP;//,LP_mybussock = -1;
 int32_t LP_mypubsock = -1,IPC_ENDPOINT = -1;
 int32_t LP_cmdcount,LP_mypullsock = -1;
 int32_t LP_numfinished,LP_showwif,IAMLP = 0;
-double LP_profitratio = 1.;
+double LP_profitratio = 1.,LP_pricetolerance = 0.002;
 
 struct LP_privkey { bits256 privkey; uint8_t rmd160[20]; };
 
 struct LP_globals
 {
     //struct LP_utxoinfo  *LP_utxoinfos[2],*LP_utxoinfos2[2];
-    bits256 LP_mypub25519,LP_privkey,LP_mypriv25519,LP_passhash;
+    bits256 LP_mypub25519,LP_privkey,LP_mypriv25519,LP_passhash,LP_internalsecret;
     uint64_t LP_skipstatus[10000], LP_required_etomic_balance;
     uint16_t netid;
     uint8_t LP_myrmd160[20],LP_pubsecp[33];
     uint32_t LP_sessionid,counter,mpnet;
     int32_t LP_IAMLP,LP_pendingswaps,USERPASS_COUNTER,LP_numprivkeys,initializing,waiting,LP_numskips;
     char seednode[64],USERPASS[65],USERPASS_WIFSTR[64],LP_myrmd160str[41],gui[65],LP_NXTaddr[64];
-    struct LP_privkey LP_privkeys[100];
+    struct LP_privkey LP_privkeys[1024];
 } G;
 
 uint32_t LP_rand()
@@ -139,6 +140,54 @@ uint32_t LP_rand()
     return(retval);
 }
 
+// wall clock UTC in milliseconds, for correlating events across peers
+uint64_t LP_utcmillis()
+{
+    struct timeval tv;
+    gettimeofday(&tv,NULL);
+    return((uint64_t)tv.tv_sec * 1000 + tv.tv_usec / 1000);
+}
+
+// swap deadlines read the clock through LP_now() and LP_monotonic_millis(). builds with -DLP_MOCKCLOCK expose the
+// mockclock rpc, which pushes both forward so tests can reach locktime expiry, refunds and step timeouts without waiting
+int64_t LP_mockclock_offset;
+
+uint32_t LP_now()
+{
+    return((uint32_t)(time(NULL) + LP_mockclock_offset));
+}
+
+// monotonic milliseconds, unaffected by clock adjustments, only meaningful as a difference
+double LP_monotonic_millis()
+{
+#ifdef _WIN32
+    return((double)GetTickCount64() + LP_mockclock_offset*1000.);
+#else
+    struct timespec ts;
+    if ( clock_gettime(CLOCK_MONOTONIC,&ts) != 0 )
+        return(OS_milliseconds() + LP_mockclock_offset*1000.);
+    return((double)ts.tv_sec * 1000. + (double)ts.tv_nsec / 1000000. + LP_mockclock_offset*1000.);
+#endif
+}
+
+#ifdef LP_MOCKCLOCK
+char *LP_mockclock(cJSON *argjson)
+{
+    cJSON *retjson;
+    if ( jint(argjson,"reset") != 0 )
+        LP_mockclock_offset = 0;
+    else if ( jint(argjson,"advance") < 0 )
+        return(clonestr("{\"error\":\"mock clock only moves forward\"}"));
+    else LP_mockclock_offset += jint(argjson,"advance");
+    retjson = cJSON_CreateObject();
+    jaddstr(retjson,"result","success");
+    jaddnum(retjson,"offset",LP_mockclock_offset);
+    jaddnum(retjson,"now",LP_now());
+    return(jprint(retjson,1));
+}
+#endif
+
+#include "LP_log.c"
 #include "LP_network.c"
 
 char *activecoins[] = { "BTC", "KMD" };
@@ -175,6 +224,7 @@ char *blocktrail_listtransactions(char *symbol,char *coinaddr,int32_t num,int32_
 
 #include "LP_mmjson.c"
 #include "LP_socket.c"
+#include "LP_rpcpool.c"
 #include "LP_secp.c"
 #include "LP_bitcoin.c"
 #include "LP_coins.c"
@@ -183,21 +233,36 @@ char *blocktrail_listtransactions(char *symbol,char *coinaddr,int32_t num,int32_
 #include "LP_NXT.c"
 #include "LP_cache.c"
 #include "LP_RTmetrics.c"
+#include "LP_metrics.c"
 #include "LP_utxo.c"
 #include "LP_prices.c"
 #include "LP_scan.c"
+#include "LP_amount.c"
+#include "LP_swapops.c"
 #include "LP_transaction.c"
 #include "LP_stats.c"
 #include "LP_remember.c"
+#include "LP_schema.c"
 #include "LP_instantdex.c"
+#include "LP_tuning.c"
 #include "LP_swap.c"
+#include "LP_legacy.c"
 #include "LP_peers.c"
+#include "LP_policy.c"
 #include "LP_privkey.c"
+#include "LP_hdwallet.c"
+#include "LP_signer.c"
 #include "LP_forwarding.c"
 #include "LP_signatures.c"
 #include "LP_ordermatch.c"
 #include "LP_tradebots.c"
 #include "LP_portfolio.c"
+#include "LP_pricefeed.c"
+#include "LP_myorders.c"
+#include "LP_backup.c"
+#include "LP_mmbot.c"
+#include "LP_multihop.c"
+#include "LP_tokens.c"
 #include "LP_messages.c"
 #include "LP_commands.c"
 
@@ -943,7 +1008,8 @@ void emc2addrs()
 
 void LP_initcoins(void *ctx,int32_t pubsock,cJSON *coins)
 {
-    int32_t i,n,notarized; cJSON *item; char *symbol,*etomic; struct iguana_info *coin;
+    int32_t i,n,notarized; cJSON *item,*errors; char *symbol,*etomic; struct iguana_info *coin; uint8_t *invalid = 0;
+    coins = LP_coins_variants(coins);
     for (i=0; i<sizeof(activecoins)/sizeof(*activecoins); i++)
     {
         printf("%s, ",activecoins[i]);
@@ -968,10 +1034,21 @@ void LP_initcoins(void *ctx,int32_t pubsock,cJSON *coins)
     }
     if ( (n= cJSON_GetArraySize(coins)) > 0 )
     {
+        errors = cJSON_CreateArray();
+        invalid = calloc(n,sizeof(*invalid));
+        for (i=0; i<n; i++)
+            invalid[i] = (LP_coinconf_validate(errors,coins,i) != 0);
+        if ( cJSON_GetArraySize(errors) > 0 )
+        {
+            printf("\ncoins config has %d problems, skipping those coins:\n",cJSON_GetArraySize(errors));
+            for (i=0; i<cJSON_GetArraySize(errors); i++)
+                printf("  %s\n",jstri(errors,i));
+        }
+        free_json(errors);
         for (i=0; i<n; i++)
         {
             item = jitem(coins,i);
-            if ( (symbol= jstr(item,"coin")) != 0 )
+            if ( invalid[i] == 0 && (symbol= jstr(item,"coin")) != 0 )
             {
                 printf("%s.%d ",jstr(item,"coin"),LP_numpriceinfos);
                 LP_coincreate(item);
@@ -1021,7 +1098,9 @@ void LP_initcoins(void *ctx,int32_t pubsock,cJSON *coins)
             item = jitem(coins,i);
             printf("\"%s\", ",jstr(item,"coin"));
         }
+        free(invalid);
     }
+    free_json(coins);
     printf("privkey updates\n");
 }
 
@@ -1125,6 +1204,90 @@ void LP_pendswap_add(uint32_t expiration,uint32_t requestid,uint32_t quoteid)
     portable_mutex_unlock(&LP_pendswap_mutex);
 }
 
+// finished swaps still waiting on a refund or claim that use symbol, from their DB/SWAPS record
+int32_t LP_pendswaps_coin(char *symbol)
+{
+    struct LP_pendswap *sp; char fname[512],*fstr; long fsize; cJSON *item; int32_t n = 0;
+    portable_mutex_lock(&LP_pendswap_mutex);
+    DL_FOREACH(LP_pendingswaps,sp)
+    {
+        if ( sp->finished != 0 )
+            continue;
+        sprintf(fname,"%s/SWAPS/%u-%u",GLOBAL_DBDIR,sp->requestid,sp->quoteid), OS_compatible_path(fname);
+        if ( (fstr= OS_filestr(&fsize,fname)) != 0 )
+        {
+            if ( (item= cJSON_Parse(fstr)) != 0 )
+            {
+                if ( (jstr(item,"src") != 0 && strcmp(jstr(item,"src"),symbol) == 0) || (jstr(item,"dest") != 0 && strcmp(jstr(item,"dest"),symbol) == 0) )
+                    n++;
+                free_json(item);
+            }
+            free(fstr);
+        }
+    }
+    portable_mutex_unlock(&LP_pendswap_mutex);
+    return(n);
+}
+
+// takes symbol out of the active set. running swaps, pending refunds or claims and our maker orders on it make this
+// fail unless force is set, then the orders are cancelled. electrum connections and idle rpc handles are closed
+char *LP_disable_coin(void *ctx,char *symbol,int32_t force)
+{
+    struct iguana_info *coin; cJSON *retjson,*orders,*item; char *base,*rel,*retstr; double bid,ask; int32_t i,baseid,relid,changed,numswaps,numpending,numelectrums = 0;
+    if ( (coin= LP_coinsearch(symbol)) == 0 )
+        return(clonestr("{\"error\":\"couldnt find coin\"}"));
+    numswaps = LP_swapabort_coin(symbol);
+    numpending = LP_pendswaps_coin(symbol);
+    orders = cJSON_CreateArray();
+    for (baseid=0; baseid<LP_numpriceinfos; baseid++)
+    {
+        base = LP_priceinfos[baseid].symbol;
+        for (relid=0; relid<LP_numpriceinfos; relid++)
+        {
+            rel = LP_priceinfos[relid].symbol;
+            if ( (strcmp(base,symbol) == 0 || strcmp(rel,symbol) == 0) && LP_myprice(1,&bid,&ask,base,rel) > SMALLVAL )
+            {
+                item = cJSON_CreateObject();
+                jaddstr(item,"base",base);
+                jaddstr(item,"rel",rel);
+                jaddi(orders,item);
+            }
+        }
+    }
+    retjson = cJSON_CreateObject();
+    jaddstr(retjson,"coin",symbol);
+    jaddnum(retjson,"swaps",numswaps);
+    jaddnum(retjson,"pending",numpending);
+    if ( force == 0 && (numswaps > 0 || numpending > 0 || cJSON_GetArraySize(orders) > 0) )
+    {
+        jaddstr(retjson,"error","coin in use, cancel the orders and let the swaps finish or set force");
+        jadd(retjson,"orders",orders);
+        return(jprint(retjson,1));
+    }
+    coin->inactive = (uint32_t)time(NULL);
+    coin->cache_history = 0;
+    for (i=0; i<cJSON_GetArraySize(orders); i++)
+    {
+        item = jitem(orders,i);
+        base = jstr(item,"base");
+        rel = jstr(item,"rel");
+        LP_pricefeed_unpeg(base,rel);
+        LP_mypriceset(1,&changed,base,rel,0.);
+        LP_myorder_update(base,rel,0.,0);
+        if ( (retstr= LP_pricepings(ctx,LP_myipaddr,LP_mypubsock,base,rel,0.)) != 0 )
+            free(retstr);
+    }
+    if ( coin->electrum != 0 )
+        numelectrums = LP_electrum_release(coin);
+    LP_rpcpool_release(coin);
+    if ( force != 0 && numswaps + numpending > 0 )
+        printf("%s force disabled with %d swaps and %d pending refunds or claims\n",symbol,numswaps,numpending);
+    jaddstr(retjson,"result","success");
+    jadd(retjson,"cancelled",orders);
+    jaddnum(retjson,"electrums",numelectrums);
+    return(jprint(retjson,1));
+}
+
 void LP_swapsloop(void *ctx)
 {
     char *retstr; cJSON *retjson; uint32_t requestid,quoteid; int32_t i,nonz; struct LP_pendswap *sp,*tmp;
@@ -1147,6 +1310,8 @@ void LP_swapsloop(void *ctx)
             continue;
         }
         LP_millistats_update(&LP_swapsloop_stats);
+        LP_countdowns_send();
+        LP_swap_watchdog();
         nonz = 0;
         DL_FOREACH_SAFE(LP_pendingswaps,sp,tmp)
         {
@@ -1155,6 +1320,7 @@ void LP_swapsloop(void *ctx)
                 nonz++;
                 if ( (sp->finished= LP_swapwait(0,sp->requestid,sp->quoteid,-1,0)) != 0 )
                 {
+                    LP_countdown_remove(sp->requestid,sp->quoteid);
                 }
                 sleep(3);
             }
@@ -1169,6 +1335,8 @@ void LP_swapsloop(void *ctx)
             }
         } else sleep(10);
         LP_gtc_iteration(ctx,LP_myipaddr,LP_mypubsock);
+        LP_myorders_restore(ctx);
+        LP_tuning_flush();
     }
 }
 
@@ -1285,6 +1453,7 @@ void queue_loop(void *ctx)
                     }
                     else
                     {
+                        LP_metrics_p2p(ptr->notready > 100);
                         if ( ptr->notready++ > 100 )
                         {
                             flag = 1;
@@ -1427,6 +1596,7 @@ int32_t LP_reserved_msg(int32_t priority,char *base,char *rel,bits256 pubkey,cha
                 else
                 {
                     printf("sent %d bytes to cmdchannel.%d\n",sentbytes,pubp->pairsock);
+                    free(msg);
                     return(sentbytes);
                 }
             }
@@ -1465,6 +1635,8 @@ void LPinit(uint16_t myport,uint16_t mypullport,uint16_t mypubport,uint16_t mybu
     }
     LP_showwif = juint(argjson,"wif");
     printf("showwif.%d version: %s %u\n",LP_showwif,MM_VERSION,calc_crc32(0,MM_VERSION,(int32_t)strlen(MM_VERSION)));
+    LP_schema_init();
+    LP_proxy_init(argjson);
     if ( passphrase == 0 || passphrase[0] == 0 )
     {
         printf("jeezy says we cant use the nullstring as passphrase and I agree\n");
@@ -1517,6 +1689,7 @@ void LPinit(uint16_t myport,uint16_t mypullport,uint16_t mypubport,uint16_t mybu
     portable_mutex_init(&LP_gcmutex);
     portable_mutex_init(&LP_forwardmutex);
     portable_mutex_init(&LP_inusemutex);
+    portable_mutex_init(&LP_metricsmutex);
     portable_mutex_init(&LP_psockmutex);
     portable_mutex_init(&LP_coinmutex);
     portable_mutex_init(&LP_pubkeymutex);
@@ -1530,12 +1703,31 @@ void LPinit(uint16_t myport,uint16_t mypullport,uint16_t mypubport,uint16_t mybu
     portable_mutex_init(&LP_cJSONmutex);
     portable_mutex_init(&LP_logmutex);
     portable_mutex_init(&LP_statslogmutex);
+    LP_log_init(argjson);
     portable_mutex_init(&LP_tradesmutex);
     portable_mutex_init(&LP_commandQmutex);
     portable_mutex_init(&LP_blockinit_mutex);
     portable_mutex_init(&LP_pendswap_mutex);
+    portable_mutex_init(&LP_swapabortmutex);
+    portable_mutex_init(&LP_exposuremutex);
+    portable_mutex_init(&LP_tokenmutex);
+    portable_mutex_init(&LP_banmutex);
+    portable_mutex_init(&LP_reputationmutex);
+    portable_mutex_init(&LP_policymutex);
+    portable_mutex_init(&LP_inflightmutex);
+    portable_mutex_init(&LP_countdownmutex);
+    portable_mutex_init(&LP_refundfailmutex);
+    portable_mutex_init(&LP_utxolockmutex);
+    portable_mutex_init(&LP_maintqmutex);
+    portable_mutex_init(&LP_pricefeedmutex);
+    portable_mutex_init(&LP_myordersmutex);
+    portable_mutex_init(&LP_mmbotmutex);
+    portable_mutex_init(&LP_mmbot_iomutex);
+    portable_mutex_init(&LP_multihopmutex);
+    portable_mutex_init(&LP_tuningmutex);
     portable_mutex_init(&LP_listmutex);
     portable_mutex_init(&LP_gtcmutex);
+    portable_mutex_init(&LP_hdmutex);
     myipaddr = clonestr("127.0.0.1");
 #ifndef _WIN32
 #ifndef FROM_JS
@@ -1610,6 +1802,44 @@ void LPinit(uint16_t myport,uint16_t mypullport,uint16_t mypubport,uint16_t mybu
     //LP_mybussock = LP_coinbus(mybusport);
     printf("got %s, initpeers. LP_mypubsock.%d pullsock.%d RPC_port.%u mypullport.%d mypubport.%d\n",myipaddr,LP_mypubsock,LP_mypullsock,RPC_port,mypullport,mypubport);
     LP_passphrase_init(passphrase,jstr(argjson,"gui"),juint(argjson,"netid"),jstr(argjson,"seednode"));
+    LP_exposure_limits(argjson);
+    LP_pricefeeds_init(argjson);
+    LP_myorders_init(argjson);
+    LP_tuning_init(argjson);
+    LP_swaptimeouts_set(argjson);
+    if ( LP_feeschedule_set(argjson,juint(argjson,"netid")) < 0 )
+    {
+        printf("invalid dexfee schedule\n");
+        exit(-1);
+    }
+    LP_max_concurrent_swaps = jint(argjson,"max_concurrent_swaps");
+    if ( jobj(argjson,"rpc_deadline") != 0 && (LP_rpc_deadline= jint(argjson,"rpc_deadline")) < 0 )
+        LP_rpc_deadline = 0;
+    LP_swapchange_allow = jint(argjson,"allow_unconfirmed_swapchange");
+    if ( (LP_fee_confirm_below= jint(argjson,"fee_confirm_below")) < 0 )
+        LP_fee_confirm_below = 0;
+    if ( jobj(argjson,"swap_relay") != 0 )
+        LP_swaprelay = jint(argjson,"swap_relay");
+    if ( jobj(argjson,"price_tolerance") != 0 && ((LP_pricetolerance= jdouble(argjson,"price_tolerance")) < 0. || LP_pricetolerance > 0.05) )
+    {
+        printf("price_tolerance %.4f must be between 0 and 0.05\n",LP_pricetolerance);
+        exit(-1);
+    }
+    LP_export_interval = juint(argjson,"export_interval");
+    if ( jstr(argjson,"screening_url") != 0 )
+        safecopy(LP_screening_url,jstr(argjson,"screening_url"),sizeof(LP_screening_url));
+    if ( juint(argjson,"screening_timeout") != 0 )
+        LP_screening_timeout = juint(argjson,"screening_timeout");
+    if ( jstr(argjson,"internal_secret") != 0 )
+        vcalc_sha256(0,G.LP_internalsecret.bytes,(uint8_t *)jstr(argjson,"internal_secret"),(int32_t)strlen(jstr(argjson,"internal_secret")));
+    LP_exposure_load();
+    LP_tokens_load();
+    LP_banned_load();
+    LP_reputation_load();
+    LP_policy_init(argjson);
+    LP_utxolocks_load();
+    LP_legacy_import(jstr(argjson,"legacy_dbdir"));
+    LP_shutdown_resume();
 #ifndef FROM_JS
     if ( OS_thread_create(malloc(sizeof(pthread_t)),NULL,(void *)LP_psockloop,(void *)myipaddr) != 0 )
     {
@@ -398,6 +398,100 @@ void LP_broadcast_message(int32_t pubsock,char *base,char *rel,bits256 destpub25
         free(msgstr);
 }
     
+// swap messages travel inside a nacl box between the ordermatched curve25519 identities, so they are both private and authenticated
+bits256 LP_swap_otherpub25519(struct basilisk_swap *swap)
+{
+    return(swap->I.iambob != 0 ? swap->I.req.desthash : swap->I.req.srchash);
+}
+
+int32_t LP_swapseal(struct basilisk_swap *swap,uint8_t *sealed,uint8_t *data,int32_t datalen)
+{
+    uint8_t *space; int32_t len;
+    space = malloc(datalen + crypto_box_ZEROBYTES);
+    OS_randombytes(sealed,crypto_box_NONCEBYTES);
+    len = _SuperNET_cipher(sealed,&sealed[crypto_box_NONCEBYTES],data,datalen,LP_swap_otherpub25519(swap),G.LP_mypriv25519,space);
+    free(space);
+    return(crypto_box_NONCEBYTES + len);
+}
+
+// inside the box every message starts with sha256(uuid), sender and recipient identity and the senders sequence number,
+// so a payload captured from another session between the same identities, or replayed within this one, doesnt open
+#define LP_SWAPHDR_SIZE (32*3 + sizeof(uint32_t))
+
+int32_t LP_swaphdr(struct basilisk_swap *swap,uint8_t *hdr,bits256 sender,bits256 recipient,uint32_t seq)
+{
+    int32_t len = 0;
+    vcalc_sha256(0,&hdr[len],(uint8_t *)swap->uuidstr,(int32_t)strlen(swap->uuidstr)), len += 32;
+    memcpy(&hdr[len],sender.bytes,32), len += 32;
+    memcpy(&hdr[len],recipient.bytes,32), len += 32;
+    len += iguana_rwnum(1,&hdr[len],sizeof(seq),&seq);
+    return(len);
+}
+
+uint8_t *LP_swapopen(struct basilisk_swap *swap,int32_t *openlenp,uint8_t *space,uint8_t *sealed,int32_t sealedlen)
+{
+    uint8_t *opened = 0,hdr[LP_SWAPHDR_SIZE]; uint32_t seq; int32_t openlen;
+    *openlenp = 0;
+    if ( sealedlen > crypto_box_NONCEBYTES+crypto_box_ZEROBYTES+LP_SWAPHDR_SIZE && (opened= _SuperNET_decipher(sealed,&sealed[crypto_box_NONCEBYTES],space,sealedlen - crypto_box_NONCEBYTES,LP_swap_otherpub25519(swap),G.LP_mypriv25519)) != 0 )
+    {
+        openlen = sealedlen - crypto_box_NONCEBYTES - crypto_box_ZEROBYTES;
+        iguana_rwnum(0,&opened[32*3],sizeof(seq),&seq);
+        LP_swaphdr(swap,hdr,LP_swap_otherpub25519(swap),G.LP_mypub25519,seq);
+        if ( memcmp(hdr,opened,32*3) != 0 )
+        {
+            printf("swap message for another session or identity, dropped\n");
+            return(0);
+        }
+        if ( seq <= swap->recvseq )
+        {
+            printf("swap message seq.%u not after %u, replay dropped\n",seq,swap->recvseq);
+            return(0);
+        }
+        swap->recvseq = seq;
+        *openlenp = openlen - LP_SWAPHDR_SIZE;
+        opened += LP_SWAPHDR_SIZE;
+    }
+    return(opened);
+}
+
+// LP_swapopen's header check without advancing recvseq, for relayed messages before they are queued for the swap thread
+int32_t LP_swapsealed_valid(struct basilisk_swap *swap,uint8_t *sealed,int32_t sealedlen)
+{
+    uint8_t *space,*opened,hdr[LP_SWAPHDR_SIZE]; int32_t retval = 0;
+    if ( sealedlen <= crypto_box_NONCEBYTES+crypto_box_ZEROBYTES+LP_SWAPHDR_SIZE )
+        return(0);
+    space = malloc(sealedlen);
+    if ( (opened= _SuperNET_decipher(sealed,&sealed[crypto_box_NONCEBYTES],space,sealedlen - crypto_box_NONCEBYTES,LP_swap_otherpub25519(swap),G.LP_mypriv25519)) != 0 )
+    {
+        LP_swaphdr(swap,hdr,LP_swap_otherpub25519(swap),G.LP_mypub25519,0);
+        retval = (memcmp(hdr,opened,32*3) == 0);
+    }
+    free(space);
+    return(retval);
+}
+
+// sealed bytes go out on the pair socket, a copy through LP_swaprelay_send only when that failed or the counterparty has
+// been silent for LP_SWAPRELAY_SILENCE. the receiver dedupes by their hash. sock -1 only relays
+int32_t LP_swapsealsend(struct basilisk_swap *swap,int32_t sock,uint8_t *data,int32_t datalen)
+{
+    uint8_t *sealed,*buf; int32_t len,sealedlen,sentbytes = -1;
+    buf = malloc(LP_SWAPHDR_SIZE + datalen);
+    len = LP_swaphdr(swap,buf,G.LP_mypub25519,LP_swap_otherpub25519(swap),++swap->sendseq);
+    memcpy(&buf[len],data,datalen), len += datalen;
+    sealed = malloc(len + crypto_box_NONCEBYTES + crypto_box_ZEROBYTES);
+    sealedlen = LP_swapseal(swap,sealed,buf,len);
+    if ( sock >= 0 && (sentbytes= nn_send(sock,sealed,sealedlen,0)) == sealedlen )
+        sentbytes = datalen;
+    if ( sentbytes != datalen || LP_now() > (swap->received != 0 ? swap->received : swap->I.started) + LP_SWAPRELAY_SILENCE )
+    {
+        if ( LP_swaprelay_send(swap,sealed,sealedlen) > 0 )
+            sentbytes = datalen;
+    }
+    free(sealed);
+    free(buf);
+    return(sentbytes);
+}
+
 uint32_t LP_swapsend(int32_t pairsock,struct basilisk_swap *swap,uint32_t msgbits,uint8_t *data,int32_t datalen,uint32_t nextbits,uint32_t crcs[2])
 {
     uint8_t *buf; int32_t sentbytes,offset=0,i;
@@ -410,7 +504,7 @@ uint32_t LP_swapsend(int32_t pairsock,struct basilisk_swap *swap,uint32_t msgbit
     offset += iguana_rwnum(1,&buf[offset],sizeof(msgbits),&msgbits);
     if ( datalen > 0 )
         memcpy(&buf[offset],data,datalen), offset += datalen;
-    if ( (sentbytes= nn_send(pairsock,buf,offset,0)) != offset )
+    if ( (sentbytes= LP_swapsealsend(swap,pairsock,buf,offset)) != offset )
     {
         printf("sentbytes.%d vs offset.%d\n",sentbytes,offset);
         if ( sentbytes < 0 )
@@ -33,17 +33,131 @@ uint32_t Alice_expiration,Bob_expiration;
 struct { uint64_t aliceid; double bestprice; uint32_t starttime,counter; } Bob_competition[512];
 
 
-void LP_failedmsg(uint32_t requestid,uint32_t quoteid,double val,char *uuidstr)
+void LP_failedmsg_category(uint32_t requestid,uint32_t quoteid,double val,char *uuidstr,char *category)
 {
-    char *msg; cJSON *retjson;
+    char *msg,*side; cJSON *retjson;
     if ( IPC_ENDPOINT >= 0 )
     {
         retjson = cJSON_CreateObject();
         jaddstr(retjson,"method","failed");
         jaddstr(retjson,"uuid",uuidstr);
         jaddnum(retjson,"error",val);
+        jaddstr(retjson,"kind",LP_swaperr_kind((int32_t)val,category,&side));
+        if ( side != 0 )
+            jaddstr(retjson,"side",side);
+        if ( category != 0 )
+        {
+            jaddstr(retjson,"category",category);
+            jaddstr(retjson,"state",strcmp(category,"timeout") == 0 ? "timedout" : "failed");
+        }
         jaddnum(retjson,"requestid",requestid);
         jaddnum(retjson,"quoteid",quoteid);
+        jaddnum(retjson,"timestamp_ms",LP_utcmillis());
+        msg = jprint(retjson,1);
+        LP_queuecommand(0,msg,IPC_ENDPOINT,-1,0);
+        free(msg);
+    }
+}
+
+void LP_failedmsg(uint32_t requestid,uint32_t quoteid,double val,char *uuidstr)
+{
+    LP_failedmsg_category(requestid,quoteid,val,uuidstr,0);
+}
+
+void LP_queuedmsg(struct LP_quoteinfo *qp,char *reason)
+{
+    char *msg; cJSON *retjson;
+    if ( IPC_ENDPOINT >= 0 )
+    {
+        retjson = cJSON_CreateObject();
+        jaddstr(retjson,"method","queued");
+        jaddstr(retjson,"uuid",qp->uuidstr);
+        jaddstr(retjson,"base",qp->srccoin);
+        jaddstr(retjson,"rel",qp->destcoin);
+        jaddnum(retjson,"basevol",dstr(qp->satoshis));
+        jaddstr(retjson,"reason",reason);
+        msg = jprint(retjson,1);
+        LP_queuecommand(0,msg,IPC_ENDPOINT,-1,0);
+        free(msg);
+    }
+}
+
+char LP_screening_url[512]; int32_t LP_screening_timeout = 10;
+
+// optional operator supplied screening service, must answer {"result":"accept"} for the match to proceed
+int32_t LP_screening_check(struct LP_quoteinfo *qp,int32_t iambob)
+{
+    char *paramstr,*retstr; int32_t retval = -1; cJSON *params,*item,*array,*addr,*retjson;
+    if ( LP_screening_url[0] == 0 )
+        return(0);
+    item = cJSON_CreateObject();
+    jaddstr(item,"uuid",qp->uuidstr);
+    jaddstr(item,"side",iambob != 0 ? "maker" : "taker");
+    jaddbits256(item,"pubkey",iambob != 0 ? qp->desthash : qp->srchash);
+    array = cJSON_CreateArray();
+    addr = cJSON_CreateObject();
+    jaddstr(addr,"coin",qp->srccoin);
+    jaddstr(addr,"address",qp->coinaddr);
+    jaddstr(addr,"owner",iambob != 0 ? "self" : "counterparty");
+    jaddi(array,addr);
+    addr = cJSON_CreateObject();
+    jaddstr(addr,"coin",qp->destcoin);
+    jaddstr(addr,"address",qp->destaddr);
+    jaddstr(addr,"owner",iambob != 0 ? "counterparty" : "self");
+    jaddi(array,addr);
+    jadd(item,"addresses",array);
+    params = cJSON_CreateArray();
+    jaddi(params,item);
+    paramstr = jprint(params,1);
+    if ( (retstr= bitcoind_RPC(0,"screening",LP_screening_url,"","screen",paramstr,LP_screening_timeout)) != 0 )
+    {
+        if ( strcmp(retstr,"accept") == 0 )
+            retval = 0;
+        else if ( (retjson= cJSON_Parse(retstr)) != 0 )
+        {
+            if ( is_cJSON_True(jobj(retjson,"accept")) != 0 )
+                retval = 0;
+            free_json(retjson);
+        }
+        free(retstr);
+    }
+    free(paramstr);
+    if ( retval < 0 )
+        printf("screening declined uuid.%s\n",qp->uuidstr);
+    return(retval);
+}
+
+void LP_declinedmsg(struct LP_quoteinfo *qp)
+{
+    char *msg; cJSON *retjson;
+    if ( IPC_ENDPOINT >= 0 )
+    {
+        retjson = cJSON_CreateObject();
+        jaddstr(retjson,"method","declined");
+        jaddstr(retjson,"uuid",qp->uuidstr);
+        jaddstr(retjson,"base",qp->srccoin);
+        jaddstr(retjson,"rel",qp->destcoin);
+        jaddstr(retjson,"reason","match declined");
+        msg = jprint(retjson,1);
+        LP_queuecommand(0,msg,IPC_ENDPOINT,-1,0);
+        free(msg);
+    }
+}
+
+void LP_stalepricemsg(struct LP_quoteinfo *qp,uint32_t age)
+{
+    char *msg; cJSON *retjson;
+    printf("reject %s/%s connect uuid.%s, autoprice stale for %u seconds\n",qp->srccoin,qp->destcoin,qp->uuidstr,age);
+    if ( IPC_ENDPOINT >= 0 )
+    {
+        retjson = cJSON_CreateObject();
+        jaddstr(retjson,"method","stale_price");
+        jaddstr(retjson,"uuid",qp->uuidstr);
+        jaddstr(retjson,"base",qp->srccoin);
+        jaddstr(retjson,"rel",qp->destcoin);
+        jaddnum(retjson,"age",age);
+        jaddnum(retjson,"requestid",qp->R.requestid);
+        jaddnum(retjson,"quoteid",qp->R.quoteid);
         msg = jprint(retjson,1);
         LP_queuecommand(0,msg,IPC_ENDPOINT,-1,0);
         free(msg);
@@ -92,7 +206,7 @@ uint64_t LP_txfeecalc(struct iguana_info *coin,uint64_t txfee,int32_t txlen)
 {
     if ( coin != 0 )
     {
-        if ( strcmp(coin->symbol,"BTC") == 0 )
+        if ( strcmp(LP_chainsymbol(coin->symbol),"BTC") == 0 )
         {
             if ( txlen == 0 )
                 txlen = LP_AVETXSIZE;
@@ -132,6 +246,12 @@ double LP_quote_validate(struct LP_utxoinfo *autxo,struct LP_utxoinfo *butxo,str
     double qprice=0.; char str[65],srccoin[65],destcoin[65],bobtomic[64],alicetomic[64]; cJSON *txout; uint64_t txfee,desttxfee,srcvalue=0,srcvalue2=0,destvalue=0,destvalue2=0;
     LP_etomicsymbol(srccoin,bobtomic,qp->srccoin);
     LP_etomicsymbol(destcoin,alicetomic,qp->destcoin);
+    // only an etomic side can move less than a satoshi, a utxo coin gets its amount in satoshis or not at all
+    if ( (bobtomic[0] == 0 && LP_amount_subsatoshi(qp->srcamountstr) != 0) || (alicetomic[0] == 0 && LP_amount_subsatoshi(qp->destamountstr) != 0) )
+    {
+        printf("quote amounts %s %s / %s %s past satoshis on a utxo coin\n",qp->srcamountstr,qp->srccoin,qp->destamountstr,qp->destcoin);
+        return(-15);
+    }
   //printf(">>>>>>> quote satoshis.(%.8f %.8f) %s %.8f -> %s %.8f\n",dstr(qp->satoshis),dstr(qp->destsatoshis),qp->srccoin,dstr(qp->satoshis),qp->destcoin,dstr(qp->destsatoshis));
     if ( butxo != 0 )
     {
@@ -502,7 +622,7 @@ int32_t LP_connectstartbob(void *ctx,int32_t pubsock,char *base,char *rel,double
         LP_failedmsg(qp->R.requestid,qp->R.quoteid,-3000,qp->uuidstr);
         return(-1);
     }
-    privkey = LP_privkey(coin->symbol,coin->smartaddr,coin->taddr);
+    privkey = LP_swap_privkey(coin,coin->smartaddr);
     if ( coin->etomic[0] != 0 )
     {
         if ( (ecoin= LP_coinfind("ETOMIC")) != 0 )
@@ -512,6 +632,7 @@ int32_t LP_connectstartbob(void *ctx,int32_t pubsock,char *base,char *rel,double
     {
         LP_requestinit(&qp->R,qp->srchash,qp->desthash,base,qp->satoshis-qp->txfee,rel,qp->destsatoshis-qp->desttxfee,qp->timestamp,qp->quotetime,DEXselector,qp->fill,qp->gtc);
         dtrust = LP_dynamictrust(qp->othercredits,qp->desthash,LP_kmdvalue(qp->destcoin,qp->destsatoshis));
+        qp->locktime = LP_atomic_locktime(qp->srccoin,qp->destcoin); // bob decides, alice mismatch is caught in the pubkeys exchange
         if ( (swap= LP_swapinit(1,0,privkey,&qp->R,qp,dtrust > 0)) == 0 )
         {
             printf("cant initialize swap\n");
@@ -521,6 +642,7 @@ int32_t LP_connectstartbob(void *ctx,int32_t pubsock,char *base,char *rel,double
         if ( (pair= LP_nanobind(ctx,pairstr)) >= 0 )
         {
             swap->N.pair = pair;
+            LP_makerswap_add(swap,1);
             if ( OS_thread_create(malloc(sizeof(pthread_t)),NULL,(void *)LP_bobloop,(void *)swap) == 0 )
             {
                 reqjson = LP_quotejson(qp);
@@ -566,6 +688,8 @@ int32_t LP_connectstartbob(void *ctx,int32_t pubsock,char *base,char *rel,double
             }
             else
             {
+                LP_makerswap_add(swap,-1);
+                LP_zeroconf_release(swap);
                 LP_failedmsg(qp->R.requestid,qp->R.quoteid,-3002,qp->uuidstr);
                 printf("error launching swaploop\n");
             }
@@ -623,7 +747,9 @@ void LP_gtc_iteration(void *ctx,char *myipaddr,int32_t mypubsock)
             {
                 gtc->pending = qp->timestamp = (uint32_t)time(NULL);
                 LP_query(ctx,myipaddr,mypubsock,"request",qp);
+                portable_mutex_lock(&LP_gtcmutex);
                 LP_Alicequery = *qp, LP_Alicemaxprice = gtc->Q.maxprice, Alice_expiration = qp->timestamp + 2*LP_AUTOTRADE_TIMEOUT, LP_Alicedestpubkey = qp->srchash;
+                portable_mutex_unlock(&LP_gtcmutex);
                 char str[65]; printf("LP_gtc fill.%d gtc.%d %s/%s %.8f vol %.8f dest.(%s) maxprice %.8f etomicdest.(%s) uuid.%s fill.%d gtc.%d\n",qp->fill,qp->gtc,qp->srccoin,qp->destcoin,dstr(qp->satoshis),dstr(qp->destsatoshis),bits256_str(str,LP_Alicedestpubkey),gtc->Q.maxprice,qp->etomicdest,qp->uuidstr,qp->fill,qp->gtc);
                 break;
             }
@@ -661,7 +787,9 @@ char *LP_trade(void *ctx,char *myipaddr,int32_t mypubsock,struct LP_quoteinfo *q
     }
     {
         LP_query(ctx,myipaddr,mypubsock,"request",qp);
+        portable_mutex_lock(&LP_gtcmutex);
         LP_Alicequery = *qp, LP_Alicemaxprice = qp->maxprice, Alice_expiration = qp->timestamp + timeout, LP_Alicedestpubkey = qp->srchash;
+        portable_mutex_unlock(&LP_gtcmutex);
     }
     if ( qp->gtc == 0 )
     {
@@ -684,7 +812,7 @@ int32_t LP_quotecmp(int32_t strictflag,struct LP_quoteinfo *qp,struct LP_quotein
             return(-1);
         } else printf("dont reject quote from destpubkey\n");
     }
-    if ( bits256_cmp(qp->desthash,qp2->desthash) == 0 && strcmp(qp->srccoin,qp2->srccoin) == 0 && strcmp(qp->destcoin,qp2->destcoin) == 0 && bits256_cmp(qp->desttxid,qp2->desttxid) == 0 && qp->destvout == qp2->destvout && bits256_cmp(qp->feetxid,qp2->feetxid) == 0 && qp->feevout == qp2->feevout && qp->destsatoshis == qp2->destsatoshis && qp->txfee >= qp2->txfee && qp->desttxfee == qp2->desttxfee )
+    if ( bits256_cmp(qp->desthash,qp2->desthash) == 0 && strcmp(qp->srccoin,qp2->srccoin) == 0 && strcmp(qp->destcoin,qp2->destcoin) == 0 && bits256_cmp(qp->desttxid,qp2->desttxid) == 0 && qp->destvout == qp2->destvout && bits256_cmp(qp->feetxid,qp2->feetxid) == 0 && qp->feevout == qp2->feevout && qp->destsatoshis == qp2->destsatoshis && strcmp(qp->destamountstr,qp2->destamountstr) == 0 && qp->txfee >= qp2->txfee && qp->desttxfee == qp2->desttxfee )
     {
         if ( strictflag == 0 || (qp->aliceid == qp2->aliceid && qp->R.requestid == qp2->R.requestid && qp->R.quoteid == qp2->R.quoteid && qp->vout == qp2->vout && qp->vout2 == qp2->vout2 && qp->satoshis == qp2->satoshis && bits256_cmp(qp->txid,qp2->txid) == 0 && bits256_cmp(qp->txid2,qp2->txid2) == 0 && bits256_cmp(qp->srchash,qp2->srchash) == 0) )
             return(0);
@@ -695,10 +823,12 @@ int32_t LP_quotecmp(int32_t strictflag,struct LP_quoteinfo *qp,struct LP_quotein
 
 void LP_alicequery_clear()
 {
+    portable_mutex_lock(&LP_gtcmutex);
     memset(&LP_Alicequery,0,sizeof(LP_Alicequery));
     memset(&LP_Alicedestpubkey,0,sizeof(LP_Alicedestpubkey));
     LP_Alicemaxprice = 0.;
     Alice_expiration = 0;
+    portable_mutex_unlock(&LP_gtcmutex);
 }
 
 int32_t LP_alice_eligible(uint32_t quotetime)
@@ -721,6 +851,7 @@ char *LP_cancel_order(char *uuidstr)
         if ( uuidstr[0] == 'G' )
         {
             struct LP_gtcorder *gtc,*tmp;
+            portable_mutex_lock(&LP_gtcmutex);
             DL_FOREACH_SAFE(GTCorders,gtc,tmp)
             {
                 if ( strcmp(gtc->Q.uuidstr,uuidstr) == 0 )
@@ -740,10 +871,15 @@ char *LP_cancel_order(char *uuidstr)
                         jaddstr(retjson,"status","uuid already canceled");
                         LP_failedmsg(gtc->Q.R.requestid,gtc->Q.R.quoteid,-9996,gtc->Q.uuidstr);
                     }
+                    portable_mutex_unlock(&LP_gtcmutex);
+                    return(jprint(retjson,1));
                 }
             }
+            portable_mutex_unlock(&LP_gtcmutex);
             return(clonestr("{\"error\":\"gtc uuid not found\"}"));
         }
+        else if ( LP_swap_makerpaid(uuidstr) != 0 )
+            return(clonestr("{\"error\":\"maker payment already sent, cant cancel\"}"));
         else
         {
             num = LP_trades_canceluuid(uuidstr);
@@ -771,6 +907,12 @@ char *LP_connectedalice(struct LP_quoteinfo *qp,char *pairstr) // alice
         LP_failedmsg(qp->R.requestid,qp->R.quoteid,-4000,qp->uuidstr);
         return(clonestr("{\"result\",\"update stats\"}"));
     }
+    if ( LP_shutdown_started != 0 )
+    {
+        LP_aliceid(qp->tradeid,qp->aliceid,"error12",0,0);
+        LP_failedmsg(qp->R.requestid,qp->R.quoteid,-4009,qp->uuidstr);
+        return(clonestr("{\"error\":\"shutting down\"}"));
+    }
     printf("CONNECTED mpnet.%d fill.%d gtc.%d numpending.%d tradeid.%u requestid.%u quoteid.%u pairstr.%s\n",qp->mpnet,qp->fill,qp->gtc,G.LP_pendingswaps,qp->tradeid,qp->R.requestid,qp->R.quoteid,pairstr!=0?pairstr:"");
     LP_requestinit(&qp->R,qp->srchash,qp->desthash,qp->srccoin,qp->satoshis-qp->txfee,qp->destcoin,qp->destsatoshis-qp->desttxfee,qp->timestamp,qp->quotetime,DEXselector,qp->fill,qp->gtc);
     //printf("calculated requestid.%u quoteid.%u\n",qp->R.requestid,qp->R.quoteid);
@@ -814,7 +956,7 @@ char *LP_connectedalice(struct LP_quoteinfo *qp,char *pairstr) // alice
         LP_failedmsg(qp->R.requestid,qp->R.quoteid,-4003,qp->uuidstr);
         return(clonestr("{\"error\":\"cant get alicecoin\"}"));
     }
-    qp->privkey = LP_privkey(coin->symbol,qp->destaddr,coin->taddr);
+    qp->privkey = LP_swap_privkey(coin,qp->destaddr);
     if ( bits256_nonz(qp->privkey) != 0 )//&& qp->quotetime >= qp->timestamp-3 )
     {
         retjson = cJSON_CreateObject();
@@ -865,6 +1007,7 @@ char *LP_connectedalice(struct LP_quoteinfo *qp,char *pairstr) // alice
             {
                 LP_aliceid(qp->tradeid,qp->aliceid,"error9",qp->R.requestid,qp->R.quoteid);
                 jaddstr(retjson,"error","couldnt aliceloop");
+                LP_zeroconf_release(swap);
                 LP_failedmsg(qp->R.requestid,qp->R.quoteid,-4006,qp->uuidstr);
             }
         }
@@ -1031,9 +1174,9 @@ double LP_trades_pricevalidate(struct LP_quoteinfo *qp,struct iguana_info *coin,
         printf("quote %s/%s validate error %.0f\n",qp->srccoin,qp->destcoin,qprice);
         return(-3);
     }
-    if ( qprice < (price - 0.00000001) * 0.998)
+    if ( qprice < (price - 0.00000001) * (1. - LP_pricetolerance) )
     {
-        printf(" quote price %.8f (%llu/%llu %.8f) too low vs %.8f for %s/%s price %.8f %.8f\n",qprice,(long long)qp->destsatoshis,(long long)(qp->satoshis-qp->txfee),(double)qp->destsatoshis/(qp->satoshis-qp->txfee),price,qp->srccoin,qp->destcoin,price,(price - 0.00000001) * 0.998);
+        printf(" quote price %.8f (%llu/%llu %.8f) too low vs %.8f for %s/%s price %.8f %.8f\n",qprice,(long long)qp->destsatoshis,(long long)(qp->satoshis-qp->txfee),(double)qp->destsatoshis/(qp->satoshis-qp->txfee),price,qp->srccoin,qp->destcoin,price,(price - 0.00000001) * (1. - LP_pricetolerance));
         return(-77);
     }
     return(qprice);
@@ -1042,7 +1185,7 @@ double LP_trades_pricevalidate(struct LP_quoteinfo *qp,struct iguana_info *coin,
 struct LP_quoteinfo *LP_trades_gotrequest(void *ctx,struct LP_quoteinfo *qp,struct LP_quoteinfo *newqp,char *pairstr)
 {
     int32_t voliters=10,priceiters=33;
-    double price=0.,p=0.,qprice,myprice,bestprice,range,bid,ask; uint64_t satoshis; struct iguana_info *coin,*othercoin; struct LP_utxoinfo A,B,*autxo,*butxo; cJSON *reqjson,*retjson; char str[65],*retstr,*txidstr,*hexstr; struct LP_address_utxo *utxos[4096]; int32_t i,j,notarized,r,num,counter,max = (int32_t)(sizeof(utxos)/sizeof(*utxos));
+    double price=0.,p=0.,qprice,myprice,bestprice,range,bid,ask; uint64_t satoshis; int64_t remaining,needed,available,locked; struct iguana_info *coin,*othercoin; struct LP_utxoinfo A,B,*autxo,*butxo; cJSON *reqjson,*retjson; char str[65],*retstr,*txidstr,*hexstr; struct LP_address_utxo *utxos[4096]; int32_t i,j,notarized,r,num,counter,max = (int32_t)(sizeof(utxos)/sizeof(*utxos));
     *newqp = *qp;
     qp = newqp;
 printf("bob %s received REQUEST.(%s) mpnet.%d fill.%d gtc.%d\n",bits256_str(str,G.LP_mypub25519),qp->uuidstr+32,qp->mpnet,qp->fill,qp->gtc);
@@ -1080,6 +1223,17 @@ printf("bob %s received REQUEST.(%s) mpnet.%d fill.%d gtc.%d\n",bits256_str(str,
         }
         strcpy(butxo->coinaddr,qp->coinaddr);
         qp->srchash = G.LP_mypub25519;
+        qp->caps = LP_caps(coin,othercoin);
+        if ( LP_caps_ok(qp->caps,qp->reqcaps) == 0 )
+        {
+            printf("%s/%s ignore as caps %x dont meet required %x\n",qp->srccoin,qp->destcoin,qp->caps,qp->reqcaps);
+            return(0);
+        }
+        if ( LP_CAP_PROTOCOL(qp->reqcaps) < LP_SWAPSEQ_PROTOCOL )
+        {
+            printf("%s/%s ignore request from a taker without sequenced swap messages\n",qp->srccoin,qp->destcoin);
+            return(0);
+        }
         memset(&qp->txid,0,sizeof(qp->txid));
         memset(&qp->txid2,0,sizeof(qp->txid2));
         qp->vout = qp->vout2 = -1;
@@ -1100,11 +1254,38 @@ printf("bob %s received REQUEST.(%s) mpnet.%d fill.%d gtc.%d\n",bits256_str(str,
         return(0);
     }
     //LP_RTmetrics_update(qp->srccoin,qp->destcoin);
-    if ( LP_RTmetrics_blacklisted(qp->desthash) >= 0 )
+    if ( LP_RTmetrics_blacklisted(qp->desthash) >= 0 || LP_pubkey_banned(qp->desthash) != 0 )
     {
         printf("request from blacklisted %s, ignore\n",bits256_str(str,qp->desthash));
         return(0);
     }
+    if ( LP_policy_check(qp,1) < 0 )
+        return(0);
+    if ( LP_testnet_mismatch(qp->srccoin,qp->destcoin) != 0 )
+        return(0);
+    if ( LP_exposure_check(qp->desthash,LP_kmdvalue(qp->srccoin,qp->satoshis)) < 0 )
+        return(0);
+    if ( LP_swap_fundscheck(coin,qp->satoshis,1,qp->R.requestid,qp->R.quoteid,qp->desthash,&needed,&available,&locked) != 0 )
+        return(0);
+    qp->locktime = LP_atomic_locktime(qp->srccoin,qp->destcoin);
+    if ( (remaining= LP_myvolume(qp->srccoin,qp->destcoin)) > 0 )
+    {
+        if ( remaining < LP_min_tradevol(coin) )
+        {
+            printf("%s/%s only %.8f left on the order, below min_trading_vol\n",qp->srccoin,qp->destcoin,dstr(remaining));
+            return(0);
+        }
+        if ( qp->destsatoshis > remaining * myprice )
+        {
+            if ( qp->fill != 0 )
+            {
+                printf("%s/%s fill request for %.8f but only %.8f left on the order\n",qp->srccoin,qp->destcoin,dstr(qp->destsatoshis),dstr(remaining * myprice));
+                return(0);
+            }
+            printf("%s/%s partial fill, relvol %.8f -> %.8f with %.8f left on the order\n",qp->srccoin,qp->destcoin,dstr(qp->destsatoshis),dstr(remaining * myprice),dstr(remaining));
+            qp->destsatoshis = remaining * myprice;
+        }
+    }
     //printf("LP_address_utxo_reset.%s\n",coin->symbol);
     //LP_address_utxo_reset(coin);
     //printf("done LP_address_utxo_reset.%s\n",coin->symbol);
@@ -1201,6 +1382,11 @@ printf("bob %s received REQUEST.(%s) mpnet.%d fill.%d gtc.%d\n",bits256_str(str,
         }
     }
     printf("%s/%s i.%d j.%d qprice %.8f myprice %.8f price %.8f [%.8f]\n",qp->srccoin,qp->destcoin,i,j,qprice,myprice,price,p);
+    if ( remaining > 0 && qp->satoshis - qp->txfee > remaining )
+    {
+        printf("%s/%s matched %.8f but only %.8f left on the order\n",qp->srccoin,qp->destcoin,dstr(qp->satoshis - qp->txfee),dstr(remaining));
+        return(0);
+    }
     if ( butxo != 0 && bits256_nonz(qp->txid) != 0 && bits256_nonz(qp->txid2) != 0 && LP_allocated(qp->txid,qp->vout) == 0 && LP_allocated(qp->txid2,qp->vout2) == 0 )
     {
         //printf("found unallocated txids\n");
@@ -1237,6 +1423,12 @@ struct LP_quoteinfo *LP_trades_gotreserved(void *ctx,struct LP_quoteinfo *qp,str
     char str[65]; printf("alice %s received RESERVED.(%s) %.8f mpnet.%d fill.%d gtc.%d\n",bits256_str(str,G.LP_mypub25519),qp->uuidstr+32,(double)qp->destsatoshis/(qp->satoshis+1),qp->mpnet,qp->fill,qp->gtc);
     *newqp = *qp;
     qp = newqp;
+    if ( LP_policy_check(qp,0) < 0 || LP_screening_check(qp,0) < 0 )
+    {
+        LP_declinedmsg(qp);
+        LP_failedmsg(qp->R.requestid,qp->R.quoteid,-4009,qp->uuidstr);
+        return(0);
+    }
     if ( (qprice= LP_trades_alicevalidate(ctx,qp)) > 0. )
     {
         //printf("got qprice %.8f\n",qprice);
@@ -1248,21 +1440,80 @@ struct LP_quoteinfo *LP_trades_gotreserved(void *ctx,struct LP_quoteinfo *qp,str
     return(0);
 }
 
+// a sized order shrinks by what each swap takes and is re-broadcast with the remainder, or pulled once too little is left.
+// the taken amount stays reserved in the maker order until the swap finishes, a failed swap gives it back
+void LP_myvolume_filled(void *ctx,struct LP_quoteinfo *qp)
+{
+    int64_t remaining,taken; double bid,ask,price; int32_t changed; struct iguana_info *coin; char *retstr;
+    if ( (remaining= LP_myvolume(qp->srccoin,qp->destcoin)) <= 0 || (coin= LP_coinfind(qp->srccoin)) == 0 )
+        return;
+    taken = qp->satoshis - qp->txfee;
+    if ( (remaining -= taken) < LP_min_tradevol(coin) )
+    {
+        printf("%s/%s order filled\n",qp->srccoin,qp->destcoin);
+        LP_mypriceset(1,&changed,qp->srccoin,qp->destcoin,0.);
+        LP_myorder_reserve(qp,taken,remaining > 0 ? remaining : 0,1);
+        price = 0.;
+    }
+    else
+    {
+        LP_myvolume_set(qp->srccoin,qp->destcoin,remaining);
+        LP_myorder_reserve(qp,taken,remaining,0);
+        if ( (price= LP_myprice(1,&bid,&ask,qp->srccoin,qp->destcoin)) <= SMALLVAL )
+            return;
+        printf("%s/%s order partially filled, %.8f left\n",qp->srccoin,qp->destcoin,dstr(remaining));
+    }
+    if ( (retstr= LP_pricepings(ctx,LP_myipaddr,LP_mypubsock,qp->srccoin,qp->destcoin,price)) != 0 )
+        free(retstr);
+}
+
 struct LP_quoteinfo *LP_trades_gotconnect(void *ctx,struct LP_quoteinfo *qp,struct LP_quoteinfo *newqp,char *pairstr)
 {
-    double myprice,qprice,bid,ask; struct iguana_info *coin;
+    double myprice,qprice,bid,ask; int64_t remaining,needed,available,locked; uint32_t age; struct iguana_info *coin;
     *newqp = *qp;
     qp = newqp;
     if ( (coin= LP_coinfind(qp->srccoin)) == 0 )
        return(0);
     if ( (myprice= LP_trades_bobprice(&bid,&ask,qp)) == 0. )
         return(0);
+    if ( (age= LP_autoprice_stale(qp->srccoin,qp->destcoin)) != 0 )
+    {
+        LP_stalepricemsg(qp,age);
+        LP_failedmsg(qp->R.requestid,qp->R.quoteid,-3005,qp->uuidstr);
+        return(0);
+    }
     if ( (qprice= LP_trades_pricevalidate(qp,coin,myprice)) < 0. )
         return(0);
+    if ( (remaining= LP_myvolume(qp->srccoin,qp->destcoin)) > 0 && qp->satoshis - qp->txfee > remaining )
+    {
+        printf("connect for %.8f %s but only %.8f left on the order\n",dstr(qp->satoshis - qp->txfee),qp->srccoin,dstr(remaining));
+        LP_failedmsg(qp->R.requestid,qp->R.quoteid,-3009,qp->uuidstr);
+        return(0);
+    }
+    if ( LP_exposure_check(qp->desthash,LP_kmdvalue(qp->srccoin,qp->satoshis)) < 0 )
+    {
+        LP_failedmsg(qp->R.requestid,qp->R.quoteid,-3006,qp->uuidstr);
+        return(0);
+    }
+    if ( LP_swap_fundscheck(coin,qp->satoshis,1,qp->R.requestid,qp->R.quoteid,qp->desthash,&needed,&available,&locked) != 0 )
+    {
+        LP_failedmsg(qp->R.requestid,qp->R.quoteid,-3011,qp->uuidstr);
+        return(0);
+    }
+    if ( LP_policy_check(qp,1) < 0 || LP_screening_check(qp,1) < 0 )
+    {
+        LP_declinedmsg(qp);
+        LP_failedmsg(qp->R.requestid,qp->R.quoteid,-3008,qp->uuidstr);
+        return(0);
+    }
     if ( LP_reservation_check(qp->txid,qp->vout,qp->desthash) == 0 && LP_reservation_check(qp->txid2,qp->vout2,qp->desthash) == 0  )
     {
         char str[65]; printf("bob %s received CONNECT.(%s)\n",bits256_str(str,G.LP_mypub25519),qp->uuidstr+32);
-        LP_connectstartbob(ctx,LP_mypubsock,qp->srccoin,qp->destcoin,qprice,qp);
+        if ( LP_connectstartbob(ctx,LP_mypubsock,qp->srccoin,qp->destcoin,qprice,qp) == 0 )
+        {
+            LP_exposure_add(qp->desthash,LP_kmdvalue(qp->srccoin,qp->satoshis));
+            LP_myvolume_filled(ctx,qp);
+        }
         return(qp);
     }
     else
@@ -1273,6 +1524,30 @@ struct LP_quoteinfo *LP_trades_gotconnect(void *ctx,struct LP_quoteinfo *qp,stru
     return(0);
 }
 
+void LP_trades_bobconnect(void *ctx,struct LP_trade *tp,uint32_t now)
+{
+    char *reason;
+    if ( (reason= LP_makercapacity_check(&tp->Q)) != 0 && strcmp(reason,"maintenance") == 0 )
+    {
+        LP_failedmsg(tp->Q.R.requestid,tp->Q.R.quoteid,-3010,tp->Q.uuidstr);
+        tp->negotiationdone = now;
+        return;
+    }
+    else if ( reason != 0 )
+    {
+        if ( tp->queued == 0 )
+        {
+            printf("queue CONNECT.(%s) %s\n",tp->Q.uuidstr+32,reason);
+            tp->queued = now;
+            LP_queuedmsg(&tp->Q,reason);
+        }
+        return;
+    }
+    tp->negotiationdone = now;
+    tp->queued = 0;
+    LP_trades_gotconnect(ctx,&tp->Q,&tp->Qs[LP_CONNECT],tp->pairstr);
+}
+
 struct LP_quoteinfo *LP_trades_gotconnected(void *ctx,struct LP_quoteinfo *qp,struct LP_quoteinfo *newqp,char *pairstr)
 {
     char *retstr; int32_t changed; double val;
@@ -1331,6 +1606,7 @@ int32_t LP_trades_bestpricecheck(void *ctx,struct LP_trade *tp)
 int32_t LP_trades_canceluuid(char *uuidstr)
 {
     int32_t num = 0; struct LP_trade *qtp,*tp,*tmp;
+    portable_mutex_lock(&LP_tradesmutex);
     HASH_ITER(hh,LP_trades,tp,tmp)
     {
         if ( strcmp(tp->Q.uuidstr,uuidstr) == 0 )
@@ -1347,11 +1623,310 @@ int32_t LP_trades_canceluuid(char *uuidstr)
             num++;
         }
     }
+    portable_mutex_unlock(&LP_tradesmutex);
     if ( num > 0 )
         fprintf(stderr,"uuid.%s %d cancelled\n",uuidstr,num);
     return(num);
 }
 
+int32_t LP_cancel_matches(char *coin,char *base,char *rel,char *src,char *dest)
+{
+    if ( coin != 0 && coin[0] != 0 )
+        return(strcmp(coin,src) == 0 || strcmp(coin,dest) == 0);
+    else if ( base != 0 && base[0] != 0 && rel != 0 && rel[0] != 0 )
+        return(strcmp(base,src) == 0 && strcmp(rel,dest) == 0);
+    return(1);
+}
+
+char *LP_cancel_all_orders(void *ctx,cJSON *argjson)
+{
+    struct LP_priceinfo *basepp,*relpp; struct LP_gtcorder *gtc,*tmpgtc; struct LP_trade *tp,*tmp; int32_t i,j,n,changed,numtrades = 0; char *coin,*base,*rel,*retstr; cJSON *retjson,*array,*item,*inprogress,*uuids;
+    coin = jstr(argjson,"coin");
+    base = jstr(argjson,"base");
+    rel = jstr(argjson,"rel");
+    if ( (coin == 0 || coin[0] == 0) && (base == 0 || rel == 0 || base[0] == 0 || rel[0] == 0) && jint(argjson,"all") == 0 )
+        return(clonestr("{\"error\":\"need coin, base and rel or all\"}"));
+    array = cJSON_CreateArray();
+    for (i=0; i<LP_numpriceinfos; i++)
+    {
+        basepp = &LP_priceinfos[i];
+        for (j=0; j<LP_numpriceinfos; j++)
+        {
+            relpp = &LP_priceinfos[j];
+            if ( basepp->myprices[1][j] > SMALLVAL && LP_cancel_matches(coin,base,rel,basepp->symbol,relpp->symbol) != 0 )
+            {
+                LP_pricefeed_unpeg(basepp->symbol,relpp->symbol);
+                LP_mypriceset(1,&changed,basepp->symbol,relpp->symbol,0.);
+                LP_myorder_update(basepp->symbol,relpp->symbol,0.,0);
+                if ( (retstr= LP_pricepings(ctx,LP_myipaddr,LP_mypubsock,basepp->symbol,relpp->symbol,0.)) != 0 )
+                    free(retstr);
+                item = cJSON_CreateObject();
+                jaddstr(item,"base",basepp->symbol);
+                jaddstr(item,"rel",relpp->symbol);
+                jaddi(array,item);
+            }
+        }
+    }
+    portable_mutex_lock(&LP_gtcmutex);
+    DL_FOREACH_SAFE(GTCorders,gtc,tmpgtc)
+    {
+        if ( gtc->cancelled == 0 && LP_cancel_matches(coin,base,rel,gtc->Q.srccoin,gtc->Q.destcoin) != 0 )
+        {
+            gtc->cancelled = (uint32_t)time(NULL);
+            LP_failedmsg(gtc->Q.R.requestid,gtc->Q.R.quoteid,-9997,gtc->Q.uuidstr);
+            item = cJSON_CreateObject();
+            jaddstr(item,"uuid",gtc->Q.uuidstr);
+            jaddi(array,item);
+        }
+    }
+    portable_mutex_unlock(&LP_gtcmutex);
+    if ( LP_Alicequery.uuidstr[0] != 0 && LP_cancel_matches(coin,base,rel,LP_Alicequery.srccoin,LP_Alicequery.destcoin) != 0 )
+    {
+        item = cJSON_CreateObject();
+        jaddstr(item,"uuid",LP_Alicequery.uuidstr);
+        jaddi(array,item);
+        LP_failedmsg(LP_Alicequery.R.requestid,LP_Alicequery.R.quoteid,-9998,LP_Alicequery.uuidstr);
+        LP_alicequery_clear();
+    }
+    inprogress = cJSON_CreateArray();
+    uuids = cJSON_CreateArray();
+    portable_mutex_lock(&LP_tradesmutex);
+    HASH_ITER(hh,LP_trades,tp,tmp)
+    {
+        if ( tp->cancelled == 0 && LP_cancel_matches(coin,base,rel,tp->Q.srccoin,tp->Q.destcoin) != 0 )
+        {
+            if ( LP_swap_makerpaid(tp->Q.uuidstr) != 0 )
+                jaddistr(inprogress,tp->Q.uuidstr);
+            else jaddistr(uuids,tp->Q.uuidstr);
+        }
+    }
+    portable_mutex_unlock(&LP_tradesmutex);
+    n = cJSON_GetArraySize(uuids);
+    for (i=0; i<n; i++)
+        numtrades += LP_trades_canceluuid(jstri(uuids,i));
+    free_json(uuids);
+    retjson = cJSON_CreateObject();
+    jaddstr(retjson,"result","success");
+    jadd(retjson,"cancelled",array);
+    jaddnum(retjson,"numtrades",numtrades);
+    jadd(retjson,"inprogress",inprogress);
+    return(jprint(retjson,1));
+}
+
+uint32_t LP_export_interval,LP_lastexport;
+
+// drop anything that could be used to spend or impersonate before it leaves the node
+void LP_export_sanitize(cJSON *json)
+{
+    cJSON *item,*next;
+    if ( json == 0 )
+        return;
+    for (item=json->child; item!=0; item=next)
+    {
+        next = item->next;
+        if ( item->string != 0 && (strstr(item->string,"secret") != 0 || strstr(item->string,"priv") != 0 || strstr(item->string,"passphrase") != 0 || strstr(item->string,"wif") != 0 || strstr(item->string,"userpass") != 0) )
+            jdelete(json,item->string);
+        else LP_export_sanitize(item);
+    }
+}
+
+int32_t LP_export_jsonl(char *name,cJSON *array)
+{
+    FILE *fp; char fname[512],tmpname[512],*str; cJSON *item; int32_t i,n = 0;
+    sprintf(fname,"%s/EXPORT/%s.jsonl",GLOBAL_DBDIR,name), OS_compatible_path(fname);
+    sprintf(tmpname,"%s.tmp",fname);
+    if ( (fp= fopen(tmpname,"wb")) == 0 )
+        return(-1);
+    for (i=0; i<cJSON_GetArraySize(array); i++)
+    {
+        if ( (item= jitem(array,i)) != 0 )
+        {
+            LP_export_sanitize(item);
+            str = jprint(item,0);
+            fprintf(fp,"%s\n",str);
+            free(str);
+            n++;
+        }
+    }
+    fclose(fp);
+    OS_portable_removefile(fname);
+    if ( OS_renamefile(tmpname,fname) < 0 )
+        return(-1);
+#ifndef _WIN32
+    chmod(fname,0444);
+#endif
+    return(n);
+}
+
+char *LP_export_analytics()
+{
+    struct LP_gtcorder *gtc,*tmp; struct LP_quoteinfo Q; char *liststr; int32_t i,numswaps = -1,numorders; cJSON *retjson,*listjson,*array,*item;
+    if ( (liststr= basilisk_swaplist(1,0,0,0,0)) != 0 )
+    {
+        if ( (listjson= cJSON_Parse(liststr)) != 0 )
+        {
+            if ( (array= jarray(0,listjson,"swaps")) != 0 )
+                numswaps = LP_export_jsonl("swaps",array);
+            free_json(listjson);
+        }
+        free(liststr);
+    }
+    if ( (liststr= LP_myprices(1)) != 0 )
+    {
+        if ( (array= cJSON_Parse(liststr)) != 0 )
+        {
+            for (i=0; i<cJSON_GetArraySize(array); i++)
+                jaddstr(jitem(array,i),"type","maker");
+        } else array = cJSON_CreateArray();
+        free(liststr);
+    } else array = cJSON_CreateArray();
+    // LP_gtc_iteration frees cancelled gtc orders from the swaps loop, the taker query is swapped out under the same lock
+    portable_mutex_lock(&LP_gtcmutex);
+    DL_FOREACH_SAFE(GTCorders,gtc,tmp)
+    {
+        item = cJSON_CreateObject();
+        jaddstr(item,"type","gtc");
+        jaddstr(item,"uuid",gtc->Q.uuidstr);
+        jaddstr(item,"base",gtc->Q.srccoin);
+        jaddstr(item,"rel",gtc->Q.destcoin);
+        jaddnum(item,"basevol",dstr(gtc->Q.satoshis));
+        jaddnum(item,"relvol",dstr(gtc->Q.destsatoshis));
+        jaddnum(item,"timestamp",gtc->Q.timestamp);
+        jaddnum(item,"cancelled",gtc->cancelled);
+        jaddi(array,item);
+    }
+    Q = LP_Alicequery;
+    portable_mutex_unlock(&LP_gtcmutex);
+    if ( Q.uuidstr[0] != 0 )
+    {
+        item = cJSON_CreateObject();
+        jaddstr(item,"type","taker");
+        jaddstr(item,"uuid",Q.uuidstr);
+        jaddstr(item,"base",Q.srccoin);
+        jaddstr(item,"rel",Q.destcoin);
+        jaddnum(item,"basevol",dstr(Q.satoshis));
+        jaddnum(item,"relvol",dstr(Q.destsatoshis));
+        jaddnum(item,"timestamp",Q.timestamp);
+        jaddi(array,item);
+    }
+    numorders = LP_export_jsonl("orders",array);
+    free_json(array);
+    LP_lastexport = (uint32_t)time(NULL);
+    retjson = cJSON_CreateObject();
+    if ( numswaps < 0 || numorders < 0 )
+        jaddstr(retjson,"error","couldnt write export");
+    else jaddstr(retjson,"result","success");
+    jaddnum(retjson,"swaps",numswaps);
+    jaddnum(retjson,"orders",numorders);
+    jaddnum(retjson,"timestamp",LP_lastexport);
+    return(jprint(retjson,1));
+}
+
+void LP_export_poll()
+{
+    char *retstr;
+    if ( LP_export_interval != 0 && time(NULL) > LP_lastexport + LP_export_interval )
+    {
+        if ( (retstr= LP_export_analytics()) != 0 )
+            free(retstr);
+    }
+}
+
+cJSON *LP_support_coinjson(char *symbol)
+{
+    struct iguana_info *coin; cJSON *item = cJSON_CreateObject();
+    jaddstr(item,"coin",symbol);
+    if ( (coin= LP_coinfind(symbol)) != 0 )
+    {
+        jaddstr(item,"mode",coin->electrum != 0 ? "electrum" : (coin->etomic[0] != 0 ? "etomic" : "native"));
+        jaddnum(item,"txfee",dstr(coin->txfee));
+        jaddnum(item,"min_trading_vol",dstr(LP_min_tradevol(coin)));
+        jaddnum(item,"confirmations",coin->userconfirms);
+        jaddnum(item,"locktime",coin->locktime);
+        jaddnum(item,"rbf",coin->rbf);
+        jaddnum(item,"height",coin->height);
+        jaddnum(item,"inactive",coin->inactive);
+    } else jaddstr(item,"error","coin not found");
+    return(item);
+}
+
+// everything a bug report for one swap needs, with secrets stripped, also written to DB/EXPORT/support-<uuid>.json
+char *LP_support_bundle(char *uuidstr)
+{
+    FILE *fp; char fname[512],line[8192],*retstr,*ptr; uint32_t requestid=0,quoteid=0; cJSON *retjson,*swapjson=0,*logjson,*log,*trace,*config,*coins,*item;
+    if ( LP_swaplog_uuidvalid(uuidstr) == 0 )
+        return(clonestr("{\"error\":\"invalid uuid\"}"));
+    retjson = cJSON_CreateObject();
+    jaddstr(retjson,"uuid",uuidstr);
+    log = 0;
+    if ( (retstr= LP_swaplog_tail(uuidstr,1000)) != 0 )
+    {
+        if ( (logjson= cJSON_Parse(retstr)) != 0 )
+        {
+            if ( (log= jobj(logjson,"log")) != 0 )
+                log = jduplicate(log);
+            free_json(logjson);
+        }
+        free(retstr);
+    }
+    if ( log == 0 )
+        log = cJSON_CreateArray();
+    if ( cJSON_GetArraySize(log) > 0 && jstri(log,0) != 0 ) // "utc.ms +elapsed bob|alice requestid-quoteid state: msg"
+    {
+        if ( (ptr= strstr(jstri(log,0)," bob ")) != 0 || (ptr= strstr(jstri(log,0)," alice ")) != 0 )
+            sscanf(strchr(ptr+1,' ')+1,"%u-%u",&requestid,&quoteid);
+    }
+    trace = cJSON_CreateArray();
+    sprintf(fname,"%s/SWAPS/%s.trace",GLOBAL_DBDIR,uuidstr), OS_compatible_path(fname);
+    if ( (fp= fopen(fname,"rb")) != 0 )
+    {
+        while ( fgets(line,sizeof(line),fp) != 0 )
+        {
+            if ( (item= cJSON_Parse(line)) != 0 )
+                jaddi(trace,item);
+        }
+        fclose(fp);
+    }
+    if ( requestid != 0 && quoteid != 0 && (retstr= basilisk_swapentry(1,requestid,quoteid,0)) != 0 )
+    {
+        swapjson = cJSON_Parse(retstr);
+        free(retstr);
+    }
+    config = cJSON_CreateObject();
+    jaddstr(config,"version",MM_VERSION);
+    jaddnum(config,"swapstep_timeout",LP_swapstep_timeout);
+    jaddnum(config,"txwait_timeout",LP_txwait_timeout);
+    jaddbits256(config,"feeschedule",LP_feeschedule_hash);
+    jaddnum(config,"max_concurrent_swaps",LP_max_concurrent_swaps);
+    coins = cJSON_CreateArray();
+    if ( swapjson != 0 && jstr(swapjson,"bob") != 0 && jstr(swapjson,"alice") != 0 )
+    {
+        jaddi(coins,LP_support_coinjson(jstr(swapjson,"bob")));
+        jaddi(coins,LP_support_coinjson(jstr(swapjson,"alice")));
+    }
+    jadd(config,"coins",coins);
+    jaddnum(retjson,"requestid",requestid);
+    jaddnum(retjson,"quoteid",quoteid);
+    jaddnum(retjson,"timestamp",time(NULL));
+    if ( swapjson != 0 )
+        jadd(retjson,"swap",swapjson);
+    jadd(retjson,"events",log);
+    jadd(retjson,"trace",trace);
+    jadd(retjson,"config",config);
+    LP_export_sanitize(retjson);
+    retstr = jprint(retjson,0);
+    sprintf(fname,"%s/EXPORT/support-%s.json",GLOBAL_DBDIR,uuidstr), OS_compatible_path(fname);
+    if ( (fp= fopen(fname,"wb")) != 0 )
+    {
+        fprintf(fp,"%s\n",retstr);
+        fclose(fp);
+    }
+    free(retstr);
+    jaddstr(retjson,"result","success");
+    jaddstr(retjson,"filename",fname);
+    return(jprint(retjson,1));
+}
+
 void LP_tradesloop(void *ctx)
 {
     struct LP_trade *qtp,*tp,*tmp; struct LP_quoteinfo *qp,Q; uint32_t now; int32_t timeout,funcid,flag,nonz; struct iguana_info *coin; struct LP_pubkey_info *pubp;
@@ -1373,6 +1948,16 @@ void LP_tradesloop(void *ctx)
             if ( (coin= LP_coinfind(tp->Q.destcoin)) != 0 && coin->electrum != 0 )
                 timeout += LP_AUTOTRADE_TIMEOUT * .5;
             now = (uint32_t)time(NULL);
+            if ( tp->iambob != 0 && tp->queued != 0 )
+            {
+                if ( now > tp->queued+timeout )
+                {
+                    LP_failedmsg(tp->Q.R.requestid,tp->Q.R.quoteid,-3007,tp->Q.uuidstr);
+                    tp->cancelled = now;
+                }
+                else LP_trades_bobconnect(ctx,tp,now);
+                continue;
+            }
             if ( now > tp->lastprocessed )
             {
                 if ( tp->iambob == 0 )
@@ -1459,11 +2044,7 @@ void LP_tradesloop(void *ctx)
                     nonz++;
                     tp->firstprocessed = tp->lastprocessed = (uint32_t)time(NULL);
                     if ( funcid == LP_CONNECT && tp->negotiationdone == 0 ) // bob all done
-                    {
-                        tp->negotiationdone = now;
-                        //printf("bob sets negotiationdone.%u\n",now);
-                        LP_trades_gotconnect(ctx,&tp->Q,&tp->Qs[LP_CONNECT],tp->pairstr);
-                    }
+                        LP_trades_bobconnect(ctx,tp,now);
                 }
                 continue;
             }
@@ -1501,9 +2082,7 @@ void LP_tradesloop(void *ctx)
                 else if ( funcid == LP_CONNECT && tp->negotiationdone == 0 ) // bob all done
                 {
                     flag = 1;
-                    tp->negotiationdone = now;
-                    //printf("bob sets negotiationdone.%u\n",now);
-                    LP_trades_gotconnect(ctx,&tp->Q,&tp->Qs[LP_CONNECT],tp->pairstr);
+                    LP_trades_bobconnect(ctx,tp,now);
                 }
             }
             if ( flag != 0 )
@@ -1574,6 +2153,16 @@ int32_t LP_tradecommand(int32_t from_mpnet,void *ctx,char *myipaddr,int32_t pubs
             {
                 if ( LP_Alicemaxprice == 0. )
                     return(retval);
+                if ( LP_caps_ok(Q.caps,LP_Alicequery.reqcaps) == 0 )
+                {
+                    printf("got reserved response with caps %x, need %x\n",Q.caps,LP_Alicequery.reqcaps);
+                    return(retval);
+                }
+                if ( (LP_Alicequery.reqcaps & LP_CAP_FLAGS) != 0 && LP_caps_sigcheck(argjson,&Q) != 0 )
+                {
+                    printf("got reserved response with unsigned caps %x from %s\n",Q.caps,bits256_str(str,Q.srchash));
+                    return(retval);
+                }
                 if ( bits256_nonz(LP_Alicedestpubkey) != 0 )
                 {
                     if (bits256_cmp(LP_Alicedestpubkey,Q.srchash) != 0 )
@@ -1678,17 +2267,28 @@ printf("CONNECTED.(%s)\n",jprint(argjson,0));
     return(retval);
 }
 
-char *LP_autobuy(void *ctx,int32_t fomoflag,char *myipaddr,int32_t mypubsock,char *base,char *rel,double maxprice,double relvolume,int32_t timeout,int32_t duration,char *gui,uint32_t nonce,bits256 destpubkey,uint32_t tradeid,char *uuidstr,int32_t fillflag,int32_t gtcflag)
+char *LP_autobuy(void *ctx,int32_t fomoflag,char *myipaddr,int32_t mypubsock,char *base,char *rel,double maxprice,double relvolume,char *relvolumestr,int32_t timeout,int32_t duration,char *gui,uint32_t nonce,bits256 destpubkey,uint32_t tradeid,char *uuidstr,int32_t fillflag,int32_t gtcflag,int32_t internalflag,uint32_t reqcaps)
 {
-    uint64_t desttxfee,txfee,balance; uint32_t lastnonce; int64_t bestsatoshis=0,destsatoshis; struct iguana_info *basecoin,*relcoin; struct LP_utxoinfo *autxo,B,A; struct LP_quoteinfo Q; bits256 pubkeys[100]; struct LP_address_utxo *utxos[4096]; int32_t num=0,maxiters=100,i,max=(int32_t)(sizeof(utxos)/sizeof(*utxos)); char _uuidstr[65];
+    uint64_t desttxfee,txfee,balance; uint32_t lastnonce; int64_t needed,available,locked,bestsatoshis=0,destsatoshis,relclaimfee,baseclaimfee; struct iguana_info *basecoin,*relcoin; struct LP_utxoinfo *autxo,B,A; struct LP_quoteinfo Q; bits256 pubkeys[100],zero; struct LP_address_utxo *utxos[4096]; int32_t num=0,maxiters=100,i,max=(int32_t)(sizeof(utxos)/sizeof(*utxos)); char _uuidstr[65],*retstr;
     basecoin = LP_coinfind(base);
     relcoin = LP_coinfind(rel);
     if ( gui == 0 )
         gui = "nogui";
     if ( basecoin == 0 || basecoin->inactive != 0 || relcoin == 0 || relcoin->inactive != 0 )
         return(clonestr("{\"error\":\"base or rel not found or inactive\"}"));
+    if ( basecoin->maintenance != 0 || relcoin->maintenance != 0 )
+        return(clonestr("{\"error\":\"base or rel in maintenance\"}"));
+    if ( LP_shutdown_started != 0 )
+        return(clonestr("{\"error\":\"shutting down\"}"));
+    if ( basecoin->testnet != relcoin->testnet )
+        return(clonestr("{\"error\":\"cant swap a testnet coin against a mainnet coin\"}"));
     if ( LP_aliceonly(base) > 0 )
         return(clonestr("{\"error\":\"GAME can only be alice coin\"}"));
+    if ( internalflag != 0 && (bits256_nonz(G.LP_internalsecret) == 0 || bits256_nonz(destpubkey) == 0 || gtcflag != 0) )
+        return(clonestr("{\"error\":\"internal transfer needs internal_secret and destpubkey, and cant be gtc\"}"));
+    // relvolume as a decimal string keeps the decimals an etomic rel has past satoshis
+    if ( relvolumestr != 0 && LP_amount_satoshis(relvolumestr) < 0 )
+        return(clonestr("{\"error\":\"invalid relvolume amount\"}"));
     printf("LP_autobuy %s/%s price %.8f vol %.8f nonce %u\n",base,rel,maxprice,relvolume,nonce);
     if ( (lastnonce= LP_lastnonce) != 0 && nonce <= lastnonce )
     {
@@ -1754,6 +2354,37 @@ char *LP_autobuy(void *ctx,int32_t fomoflag,char *myipaddr,int32_t mypubsock,cha
     }
     if ( maxprice <= 0. || relvolume <= 0. || LP_priceinfofind(base) == 0 || LP_priceinfofind(rel) == 0 )
         return(clonestr("{\"error\":\"invalid parameter\"}"));
+    if ( relvolume*SATOSHIDEN < LP_min_tradevol(relcoin) || (relvolume/maxprice)*SATOSHIDEN < LP_min_tradevol(basecoin) )
+    {
+        cJSON *retjson = cJSON_CreateObject();
+        jaddstr(retjson,"error","volume below min_trading_vol");
+        jaddnum(retjson,"base_min_trading_vol",dstr(LP_min_tradevol(basecoin)));
+        jaddnum(retjson,"rel_min_trading_vol",dstr(LP_min_tradevol(relcoin)));
+        return(jprint(retjson,1));
+    }
+    relclaimfee = LP_claimfee(relcoin,relcoin->txfee);
+    baseclaimfee = LP_claimfee(basecoin,basecoin->txfee);
+    if ( relvolume*SATOSHIDEN - relclaimfee < LP_claimmargin(relcoin,relclaimfee) || (relvolume/maxprice)*SATOSHIDEN - baseclaimfee < LP_claimmargin(basecoin,baseclaimfee) )
+    {
+        cJSON *retjson = cJSON_CreateObject();
+        jaddstr(retjson,"error","volume too small to cover the claim fee");
+        jaddnum(retjson,"base_claimfee",dstr(baseclaimfee));
+        jaddnum(retjson,"base_claim_margin",dstr(LP_claimmargin(basecoin,baseclaimfee)));
+        jaddnum(retjson,"rel_claimfee",dstr(relclaimfee));
+        jaddnum(retjson,"rel_claim_margin",dstr(LP_claimmargin(relcoin,relclaimfee)));
+        return(jprint(retjson,1));
+    }
+    memset(zero.bytes,0,sizeof(zero));
+    if ( LP_swap_fundscheck(relcoin,relvolume*SATOSHIDEN,0,0,0,zero,&needed,&available,&locked) != 0 )
+    {
+        cJSON *retjson = cJSON_CreateObject();
+        jaddstr(retjson,"error","insufficient funds for the swap");
+        jaddstr(retjson,"coin",rel);
+        jaddnum(retjson,"needed",dstr(needed));
+        jaddnum(retjson,"available",dstr(available));
+        jaddnum(retjson,"locked_by_swaps",dstr(locked));
+        return(jprint(retjson,1));
+    }
     if ( strcmp("BTC",rel) == 0 )
         maxprice *= 1.01;
     else maxprice *= 1.001;
@@ -1800,8 +2431,12 @@ char *LP_autobuy(void *ctx,int32_t fomoflag,char *myipaddr,int32_t mypubsock,cha
     strcpy(B.coin,base);
     if ( LP_quoteinfoinit(&Q,&B,rel,maxprice,bestsatoshis,destsatoshis) < 0 )
         return(clonestr("{\"error\":\"cant set ordermatch quote\"}"));
+    if ( relvolumestr != 0 && relcoin->etomic[0] != 0 )
+        LP_amount_align(Q.destamountstr,relvolumestr,Q.destsatoshis);
     if ( LP_quotedestinfo(&Q,autxo->payment.txid,autxo->payment.vout,autxo->fee.txid,autxo->fee.vout,G.LP_mypub25519,autxo->coinaddr) < 0 )
         return(clonestr("{\"error\":\"cant set ordermatch quote info\"}"));
+    if ( (retstr= LP_policy_order(base,rel,Q.satoshis,Q.destsatoshis)) != 0 )
+        return(retstr);
     if ( relcoin->etomic[0] != 0 || basecoin->etomic[0] != 0 )
     {
         struct iguana_info *coin;
@@ -1823,6 +2458,10 @@ char *LP_autobuy(void *ctx,int32_t fomoflag,char *myipaddr,int32_t mypubsock,cha
     Q.mpnet = G.mpnet;
     Q.fill = fillflag;
     Q.gtc = gtcflag;
+    Q.internal = internalflag != 0;
+    if ( LP_CAP_PROTOCOL(reqcaps) < LP_SWAPSEQ_PROTOCOL ) // older makers would drop our sequenced swap messages
+        reqcaps = (reqcaps & LP_CAP_FLAGS) | ((uint32_t)LP_SWAPSEQ_PROTOCOL << 24);
+    Q.reqcaps = reqcaps;
     LP_mypriceset(0,&changed,rel,base,1. / maxprice);
     LP_mypriceset(0,&changed,base,rel,0.);
     if ( uuidstr == 0 || uuidstr[0] == 0 )
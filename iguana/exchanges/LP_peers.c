@@ -347,3 +347,294 @@ uint16_t LP_rarestpeer(char *destip)
     return(rarest != 0 ? rarest->port : RPC_port);
 }
 
+
+// peers that keep failing validation get exponentially longer timeouts, banned peers are ignored until unbanned. only
+// messages the peer provably sent count, a bad signature could come from anyone naming that pubkey.
+// bans persist in banned.json, the backoff is in memory only and resets when a peer stays clean for a while
+
+#define LP_PEER_BACKOFF_ERRORS 3 // failures tolerated before backing off
+#define LP_PEER_BACKOFF_BASE 10
+#define LP_PEER_BACKOFF_MAX 3600
+#define LP_PEER_FAILURE_DECAY 3600 // no failures for this long and the count starts over
+#define LP_PEER_NOFEE_BAN 3 // swaps a taker may start without ever sending the dexfee
+
+cJSON *LP_banned;
+portable_mutex_t LP_banmutex;
+
+void LP_pubkey_validationfail(struct LP_pubkey_info *pubp,char *what)
+{
+    uint32_t now,delay; char str[65];
+    if ( pubp == 0 )
+        return;
+    now = (uint32_t)time(NULL);
+    if ( pubp->lastfailure != 0 && now > pubp->lastfailure + LP_PEER_FAILURE_DECAY )
+        pubp->numerrors = 0;
+    pubp->numerrors++;
+    pubp->lastfailure = now;
+    if ( pubp->numerrors >= LP_PEER_BACKOFF_ERRORS )
+    {
+        delay = LP_PEER_BACKOFF_BASE << MIN(pubp->numerrors - LP_PEER_BACKOFF_ERRORS,16);
+        if ( delay > LP_PEER_BACKOFF_MAX )
+            delay = LP_PEER_BACKOFF_MAX;
+        pubp->backoffuntil = now + delay;
+        printf("%s failed %s validation %u times, ignored for %us\n",bits256_str(str,pubp->pubkey),what,pubp->numerrors,delay);
+    }
+}
+
+int32_t LP_pubkey_ignored(struct LP_pubkey_info *pubp)
+{
+    if ( pubp == 0 )
+        return(0);
+    else if ( pubp->banned != 0 )
+        return(1);
+    return(time(NULL) < pubp->backoffuntil);
+}
+
+int32_t LP_pubkey_banned(bits256 pubkey)
+{
+    struct LP_pubkey_info *pubp;
+    return((pubp= LP_pubkeyfind(pubkey)) != 0 && pubp->banned != 0);
+}
+
+void LP_banned_save()
+{
+    char fname[512],*str; FILE *fp;
+    sprintf(fname,"%s/banned.json",GLOBAL_DBDIR), OS_compatible_path(fname);
+    if ( LP_banned != 0 && (fp= fopen(fname,"wb")) != 0 )
+    {
+        str = jprint(LP_banned,0);
+        fwrite(str,1,strlen(str),fp);
+        fclose(fp);
+        free(str);
+    }
+}
+
+int32_t _LP_banned_find(bits256 pubkey)
+{
+    int32_t i,n;
+    if ( LP_banned != 0 && (n= cJSON_GetArraySize(LP_banned)) > 0 )
+    {
+        for (i=0; i<n; i++)
+            if ( bits256_cmp(jbits256(jitem(LP_banned,i),"pubkey"),pubkey) == 0 )
+                return(i);
+    }
+    return(-1);
+}
+
+char *LP_ban_pubkey(bits256 pubkey,char *reason)
+{
+    struct LP_pubkey_info *pubp; cJSON *item; char str[65];
+    if ( bits256_nonz(pubkey) == 0 || bits256_cmp(pubkey,G.LP_mypub25519) == 0 )
+        return(clonestr("{\"error\":\"cant ban a null pubkey or yourself\"}"));
+    if ( (pubp= LP_pubkeyadd(pubkey)) == 0 )
+        return(clonestr("{\"error\":\"couldnt add pubkey\"}"));
+    portable_mutex_lock(&LP_banmutex);
+    pubp->banned = (uint32_t)time(NULL);
+    if ( LP_banned == 0 )
+        LP_banned = cJSON_CreateArray();
+    if ( _LP_banned_find(pubkey) < 0 )
+    {
+        item = cJSON_CreateObject();
+        jaddbits256(item,"pubkey",pubkey);
+        jaddstr(item,"reason",reason != 0 && reason[0] != 0 ? reason : "manual");
+        jaddnum(item,"timestamp",pubp->banned);
+        jaddi(LP_banned,item);
+        LP_banned_save();
+    }
+    portable_mutex_unlock(&LP_banmutex);
+    printf("banned %s: %s\n",bits256_str(str,pubkey),reason != 0 ? reason : "manual");
+    return(clonestr("{\"result\":\"success\"}"));
+}
+
+char *LP_unban_pubkey(bits256 pubkey)
+{
+    struct LP_pubkey_info *pubp; int32_t i;
+    portable_mutex_lock(&LP_banmutex);
+    if ( (i= _LP_banned_find(pubkey)) < 0 )
+    {
+        portable_mutex_unlock(&LP_banmutex);
+        return(clonestr("{\"error\":\"pubkey is not banned\"}"));
+    }
+    cJSON_DeleteItemFromArray(LP_banned,i);
+    LP_banned_save();
+    portable_mutex_unlock(&LP_banmutex);
+    if ( (pubp= LP_pubkeyfind(pubkey)) != 0 )
+    {
+        pubp->banned = pubp->backoffuntil = pubp->nofees = 0;
+        pubp->numerrors = 0;
+    }
+    return(clonestr("{\"result\":\"success\"}"));
+}
+
+char *LP_banned_json()
+{
+    cJSON *retjson = cJSON_CreateObject();
+    jaddstr(retjson,"result","success");
+    portable_mutex_lock(&LP_banmutex);
+    jadd(retjson,"banned",LP_banned != 0 ? cJSON_Duplicate(LP_banned,1) : cJSON_CreateArray());
+    portable_mutex_unlock(&LP_banmutex);
+    return(jprint(retjson,1));
+}
+
+void LP_banned_load()
+{
+    char fname[512],*fstr; long fsize; cJSON *array; struct LP_pubkey_info *pubp; int32_t i,n;
+    sprintf(fname,"%s/banned.json",GLOBAL_DBDIR), OS_compatible_path(fname);
+    if ( (fstr= OS_filestr(&fsize,fname)) != 0 )
+    {
+        if ( (array= cJSON_Parse(fstr)) != 0 && is_cJSON_Array(array) != 0 )
+        {
+            n = cJSON_GetArraySize(array);
+            for (i=0; i<n; i++)
+                if ( (pubp= LP_pubkeyadd(jbits256(jitem(array,i),"pubkey"))) != 0 )
+                    pubp->banned = juint(jitem(array,i),"timestamp");
+            portable_mutex_lock(&LP_banmutex);
+            if ( LP_banned != 0 )
+                free_json(LP_banned);
+            LP_banned = array;
+            portable_mutex_unlock(&LP_banmutex);
+            printf("loaded %d banned pubkeys from %s\n",n,fname);
+        } else if ( array != 0 )
+            free_json(array);
+        free(fstr);
+    }
+}
+
+// the taker started a swap and never paid the dexfee: our utxos sat reserved for nothing
+void LP_pubkey_nofee(bits256 pubkey)
+{
+    struct LP_pubkey_info *pubp; uint32_t now = (uint32_t)time(NULL); char *retstr;
+    if ( (pubp= LP_pubkeyadd(pubkey)) == 0 || pubp->banned != 0 )
+        return;
+    if ( pubp->lastnofee != 0 && now > pubp->lastnofee + LP_PEER_FAILURE_DECAY*24 )
+        pubp->nofees = 0;
+    pubp->lastnofee = now;
+    if ( ++pubp->nofees >= LP_PEER_NOFEE_BAN && (retstr= LP_ban_pubkey(pubkey,"started swaps without sending the taker fee")) != 0 )
+        free(retstr);
+}
+
+// takers we sent a deposit to, by pubkey in reputation.json. the score is completed swaps minus
+// LP_REPUTATION_ABANDON_WEIGHT for each one where the taker let us deposit and never paid. with "fee_confirm_below"
+// in the startup json, takers scoring below it must have their dexfee confirmed before our deposit goes out
+
+#define LP_REPUTATION_ABANDON_WEIGHT 3
+
+cJSON *LP_reputation;
+portable_mutex_t LP_reputationmutex;
+int32_t LP_fee_confirm_below;
+
+void _LP_reputation_save()
+{
+    char fname[512],*str; FILE *fp;
+    sprintf(fname,"%s/reputation.json",GLOBAL_DBDIR), OS_compatible_path(fname);
+    if ( LP_reputation != 0 && (fp= fopen(fname,"wb")) != 0 )
+    {
+        str = jprint(LP_reputation,0);
+        fwrite(str,1,strlen(str),fp);
+        fclose(fp);
+        free(str);
+    }
+}
+
+int32_t _LP_reputation_score(cJSON *item)
+{
+    if ( item == 0 )
+        return(0);
+    return(jint(item,"completed") - LP_REPUTATION_ABANDON_WEIGHT*jint(item,"abandoned"));
+}
+
+int32_t LP_reputation_score(bits256 pubkey)
+{
+    char str[65]; int32_t score;
+    portable_mutex_lock(&LP_reputationmutex);
+    score = _LP_reputation_score(LP_reputation != 0 ? jobj(LP_reputation,bits256_str(str,pubkey)) : 0);
+    portable_mutex_unlock(&LP_reputationmutex);
+    return(score);
+}
+
+void LP_reputation_update(bits256 pubkey,int32_t completed)
+{
+    char str[65]; cJSON *item;
+    if ( bits256_nonz(pubkey) == 0 )
+        return;
+    bits256_str(str,pubkey);
+    portable_mutex_lock(&LP_reputationmutex);
+    if ( LP_reputation == 0 )
+        LP_reputation = cJSON_CreateObject();
+    if ( (item= jobj(LP_reputation,str)) == 0 )
+    {
+        item = cJSON_CreateObject();
+        jaddnum(item,"completed",0);
+        jaddnum(item,"abandoned",0);
+        jaddnum(item,"timestamp",0);
+        jadd(LP_reputation,str,item);
+    }
+    if ( completed != 0 )
+        cJSON_ReplaceItemInObject(item,"completed",cJSON_CreateNumber(jint(item,"completed") + 1));
+    else cJSON_ReplaceItemInObject(item,"abandoned",cJSON_CreateNumber(jint(item,"abandoned") + 1));
+    cJSON_ReplaceItemInObject(item,"timestamp",cJSON_CreateNumber((uint32_t)time(NULL)));
+    _LP_reputation_save();
+    portable_mutex_unlock(&LP_reputationmutex);
+    if ( completed == 0 )
+        printf("taker %s abandoned a swap after our deposit, reputation %d\n",str,LP_reputation_score(pubkey));
+}
+
+// 1 if this taker has to wait for a confirmed dexfee before we deposit
+int32_t LP_reputation_feeconfirm(bits256 pubkey)
+{
+    if ( LP_fee_confirm_below <= 0 )
+        return(0);
+    return(LP_reputation_score(pubkey) < LP_fee_confirm_below);
+}
+
+char *LP_reputation_json(bits256 pubkey)
+{
+    char str[65]; cJSON *retjson,*item,*array,*entry;
+    retjson = cJSON_CreateObject();
+    jaddstr(retjson,"result","success");
+    jaddnum(retjson,"fee_confirm_below",LP_fee_confirm_below);
+    portable_mutex_lock(&LP_reputationmutex);
+    if ( bits256_nonz(pubkey) != 0 )
+    {
+        jaddbits256(retjson,"pubkey",pubkey);
+        item = LP_reputation != 0 ? jobj(LP_reputation,bits256_str(str,pubkey)) : 0;
+        jaddnum(retjson,"completed",item != 0 ? jint(item,"completed") : 0);
+        jaddnum(retjson,"abandoned",item != 0 ? jint(item,"abandoned") : 0);
+        jaddnum(retjson,"score",_LP_reputation_score(item));
+        jaddnum(retjson,"feeconfirm",LP_fee_confirm_below > 0 && _LP_reputation_score(item) < LP_fee_confirm_below);
+    }
+    else
+    {
+        array = cJSON_CreateArray();
+        for (item=(LP_reputation != 0 ? LP_reputation->child : 0); item!=0; item=item->next)
+        {
+            entry = cJSON_Duplicate(item,1);
+            jaddstr(entry,"pubkey",item->string);
+            jaddnum(entry,"score",_LP_reputation_score(item));
+            jaddi(array,entry);
+        }
+        jadd(retjson,"takers",array);
+    }
+    portable_mutex_unlock(&LP_reputationmutex);
+    return(jprint(retjson,1));
+}
+
+void LP_reputation_load()
+{
+    char fname[512],*fstr; long fsize; cJSON *obj;
+    sprintf(fname,"%s/reputation.json",GLOBAL_DBDIR), OS_compatible_path(fname);
+    if ( (fstr= OS_filestr(&fsize,fname)) != 0 )
+    {
+        if ( (obj= cJSON_Parse(fstr)) != 0 && is_cJSON_Object(obj) != 0 )
+        {
+            portable_mutex_lock(&LP_reputationmutex);
+            if ( LP_reputation != 0 )
+                free_json(LP_reputation);
+            LP_reputation = obj;
+            portable_mutex_unlock(&LP_reputationmutex);
+            printf("loaded %d taker reputations from %s\n",cJSON_GetArraySize(obj),fname);
+        } else if ( obj != 0 )
+            free_json(obj);
+        free(fstr);
+    }
+}
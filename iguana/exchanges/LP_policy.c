@@ -0,0 +1,234 @@
+
+/******************************************************************************
+ * Copyright © 2014-2018 The SuperNET Developers.                             *
+ *                                                                            *
+ * See the AUTHORS, DEVELOPER-AGREEMENT and LICENSE files at                  *
+ * the top-level directory of this distribution for the individual copyright  *
+ * holder information and the developer policies on copyright and licensing.  *
+ *                                                                            *
+ * Unless otherwise agreed in a custom licensing agreement, no part of the    *
+ * SuperNET software, including this file may be copied, modified, propagated *
+ * or distributed except according to the terms contained in the LICENSE file *
+ *                                                                            *
+ * Removal or modification of this copyright notice is prohibited.            *
+ *                                                                            *
+ ******************************************************************************/
+//
+//  LP_policy.c
+//  marketmaker
+//
+//  operator trading policy, checked before ordering, when a request comes in and again before a swap is accepted:
+//  {"allow":["KMD/BTC","KMD/*"],"deny":["DOGE/*"],"maxsize":{"KMD/BTC":1000},"pubkeys":["<hex>", ...]}
+//  pairs match in either direction and * matches any coin. a non empty allow list means only those pairs trade, deny
+//  always wins, maxsize is in units of the first coin of the pair and a non empty pubkeys list is a counterpart
+//  whitelist. "policy" in the startup json wins over DB/policy.json, which holds the last set_policy
+//
+
+cJSON *LP_policy;
+portable_mutex_t LP_policymutex;
+
+int32_t LP_policy_pairsplit(char *pair,char *a,char *b)
+{
+    char *slash;
+    if ( pair == 0 || (slash= strchr(pair,'/')) == 0 || slash == pair || slash[1] == 0 || slash-pair >= 16 || strlen(slash+1) >= 16 )
+        return(-1);
+    memcpy(a,pair,slash-pair), a[slash-pair] = 0;
+    strcpy(b,slash+1);
+    return(0);
+}
+
+int32_t LP_policy_pairmatch(char *pair,char *base,char *rel,int32_t *reversedp)
+{
+    char a[16],b[16];
+    *reversedp = 0;
+    if ( LP_policy_pairsplit(pair,a,b) < 0 )
+        return(0);
+    if ( (strcmp(a,"*") == 0 || strcmp(a,base) == 0) && (strcmp(b,"*") == 0 || strcmp(b,rel) == 0) )
+        return(1);
+    if ( (strcmp(a,"*") == 0 || strcmp(a,rel) == 0) && (strcmp(b,"*") == 0 || strcmp(b,base) == 0) )
+    {
+        *reversedp = 1;
+        return(1);
+    }
+    return(0);
+}
+
+int32_t LP_policy_listmatch(cJSON *array,char *base,char *rel)
+{
+    int32_t i,n,reversed;
+    if ( array != 0 && (n= cJSON_GetArraySize(array)) > 0 )
+    {
+        for (i=0; i<n; i++)
+            if ( LP_policy_pairmatch(jstri(array,i),base,rel,&reversed) != 0 )
+                return(1);
+    }
+    return(0);
+}
+
+// 0 if base/rel for basesatoshis/relsatoshis with this counterpart is allowed, otherwise reason is filled in
+int32_t _LP_policy_check(char *reason,char *base,char *rel,int64_t basesatoshis,int64_t relsatoshis,bits256 *pubkeyp)
+{
+    cJSON *array,*maxsizes,*item; int32_t i,n,reversed; char str[65];
+    if ( LP_policy == 0 )
+        return(0);
+    if ( LP_policy_listmatch(jarray(&n,LP_policy,"deny"),base,rel) != 0 )
+    {
+        sprintf(reason,"%s/%s denied",base,rel);
+        return(-1);
+    }
+    if ( (array= jarray(&n,LP_policy,"allow")) != 0 && n > 0 && LP_policy_listmatch(array,base,rel) == 0 )
+    {
+        sprintf(reason,"%s/%s not in allow list",base,rel);
+        return(-1);
+    }
+    if ( (maxsizes= jobj(LP_policy,"maxsize")) != 0 )
+    {
+        for (item=maxsizes->child; item!=0; item=item->next)
+        {
+            if ( LP_policy_pairmatch(item->string,base,rel,&reversed) != 0 && (reversed == 0 ? basesatoshis : relsatoshis) > item->valuedouble * SATOSHIDEN + 0.5 )
+            {
+                sprintf(reason,"%s above maxsize %.8f",item->string,item->valuedouble);
+                return(-1);
+            }
+        }
+    }
+    if ( pubkeyp != 0 && (array= jarray(&n,LP_policy,"pubkeys")) != 0 && n > 0 )
+    {
+        bits256_str(str,*pubkeyp);
+        for (i=0; i<n; i++)
+            if ( jstri(array,i) != 0 && strcasecmp(jstri(array,i),str) == 0 )
+                break;
+        if ( i == n )
+        {
+            sprintf(reason,"counterpart %s not whitelisted",str);
+            return(-1);
+        }
+    }
+    return(0);
+}
+
+int32_t LP_policy_check(struct LP_quoteinfo *qp,int32_t iambob)
+{
+    char reason[256]; int32_t retval;
+    portable_mutex_lock(&LP_policymutex);
+    retval = _LP_policy_check(reason,qp->srccoin,qp->destcoin,qp->satoshis,qp->destsatoshis,iambob != 0 ? &qp->desthash : &qp->srchash);
+    portable_mutex_unlock(&LP_policymutex);
+    if ( retval < 0 )
+        printf("policy declined %s uuid.%s: %s\n",iambob != 0 ? "maker" : "taker",qp->uuidstr,reason);
+    return(retval);
+}
+
+// the counterpart isnt known yet when a taker orders, only pairs and size are checked then
+char *LP_policy_order(char *base,char *rel,int64_t basesatoshis,int64_t relsatoshis)
+{
+    char reason[256]; int32_t retval; cJSON *retjson;
+    portable_mutex_lock(&LP_policymutex);
+    retval = _LP_policy_check(reason,base,rel,basesatoshis,relsatoshis,0);
+    portable_mutex_unlock(&LP_policymutex);
+    if ( retval == 0 )
+        return(0);
+    retjson = cJSON_CreateObject();
+    jaddstr(retjson,"error","trading policy");
+    jaddstr(retjson,"reason",reason);
+    return(jprint(retjson,1));
+}
+
+// 0 if the policy json is well formed, otherwise a clonestr error
+char *LP_policy_validate(cJSON *policy)
+{
+    char *fields[] = { "allow", "deny", "pubkeys" },a[16],b[16]; cJSON *array,*item; int32_t i,j,n;
+    if ( is_cJSON_Object(policy) == 0 )
+        return(clonestr("{\"error\":\"policy must be an object\"}"));
+    for (j=0; j<sizeof(fields)/sizeof(*fields); j++)
+    {
+        if ( (item= jobj(policy,fields[j])) == 0 )
+            continue;
+        if ( (array= jarray(&n,policy,fields[j])) == 0 )
+            return(clonestr("{\"error\":\"allow, deny and pubkeys must be arrays\"}"));
+        for (i=0; i<n; i++)
+        {
+            if ( jstri(array,i) == 0 )
+                return(clonestr("{\"error\":\"allow, deny and pubkeys entries must be strings\"}"));
+            if ( j < 2 && LP_policy_pairsplit(jstri(array,i),a,b) < 0 )
+                return(clonestr("{\"error\":\"pairs must be BASE/REL\"}"));
+            if ( j == 2 && (strlen(jstri(array,i)) != 64 || is_hexstr(jstri(array,i),64) <= 0) )
+                return(clonestr("{\"error\":\"pubkeys must be 64 hex chars\"}"));
+        }
+    }
+    if ( (item= jobj(policy,"maxsize")) != 0 )
+    {
+        if ( is_cJSON_Object(item) == 0 )
+            return(clonestr("{\"error\":\"maxsize must be an object of pair: volume\"}"));
+        for (item=item->child; item!=0; item=item->next)
+            if ( LP_policy_pairsplit(item->string,a,b) < 0 || item->valuedouble <= 0. )
+                return(clonestr("{\"error\":\"maxsize needs BASE/REL keys with positive volumes\"}"));
+    }
+    return(0);
+}
+
+void LP_policy_save()
+{
+    char fname[512],*str; FILE *fp;
+    sprintf(fname,"%s/policy.json",GLOBAL_DBDIR), OS_compatible_path(fname);
+    if ( (fp= fopen(fname,"wb")) != 0 )
+    {
+        str = jprint(LP_policy != 0 ? LP_policy : cJSON_CreateObject(),LP_policy == 0);
+        fprintf(fp,"%s\n",str);
+        fclose(fp);
+        free(str);
+    }
+}
+
+char *LP_policy_set(cJSON *policy,int32_t saveflag)
+{
+    char *errstr;
+    if ( (errstr= LP_policy_validate(policy)) != 0 )
+        return(errstr);
+    portable_mutex_lock(&LP_policymutex);
+    if ( LP_policy != 0 )
+        free_json(LP_policy);
+    LP_policy = cJSON_Duplicate(policy,1);
+    if ( saveflag != 0 )
+        LP_policy_save();
+    portable_mutex_unlock(&LP_policymutex);
+    return(clonestr("{\"result\":\"success\"}"));
+}
+
+char *LP_policy_json()
+{
+    cJSON *retjson = cJSON_CreateObject();
+    jaddstr(retjson,"result","success");
+    portable_mutex_lock(&LP_policymutex);
+    jadd(retjson,"policy",LP_policy != 0 ? cJSON_Duplicate(LP_policy,1) : cJSON_CreateObject());
+    portable_mutex_unlock(&LP_policymutex);
+    return(jprint(retjson,1));
+}
+
+void LP_policy_init(cJSON *argjson)
+{
+    char fname[512],*fstr,*retstr; long fsize; cJSON *policy;
+    if ( (policy= jobj(argjson,"policy")) != 0 )
+    {
+        if ( (retstr= LP_policy_set(policy,1)) != 0 && strstr(retstr,"error") != 0 )
+        {
+            printf("invalid policy: %s\n",retstr);
+            exit(-1);
+        }
+        free(retstr);
+        return;
+    }
+    sprintf(fname,"%s/policy.json",GLOBAL_DBDIR), OS_compatible_path(fname);
+    if ( (fstr= OS_filestr(&fsize,fname)) != 0 )
+    {
+        if ( (policy= cJSON_Parse(fstr)) != 0 )
+        {
+            if ( (retstr= LP_policy_set(policy,0)) != 0 )
+            {
+                printf("loaded trading policy from %s: %s\n",fname,retstr);
+                free(retstr);
+            }
+            free_json(policy);
+        }
+        free(fstr);
+    }
+}
@@ -22,13 +22,14 @@ struct LP_portfoliotrade { double metric; char buycoin[65],sellcoin[65]; };
 
 struct LP_autoprice_ref
 {
-    char refbase[65],refrel[65],base[65],rel[65],fundbid[16],fundask[16],usdpeg;
+    char refbase[65],refrel[65],base[65],rel[65],fundbid[16],fundask[16],usdpeg,feedstale;
     double buymargin,sellmargin,factor,offset,lastbid,lastask;
     cJSON *fundvalue;
-    uint32_t count;
+    uint32_t count,lastupdate;
 } LP_autorefs[1024];
 
 int32_t LP_autoprices,num_LP_autorefs;
+uint32_t LP_autoprice_maxage = LP_AUTOPRICE_MAXAGE;
 char LP_portfolio_base[128],LP_portfolio_rel[128];
 double LP_portfolio_relvolume;
 
@@ -278,6 +279,8 @@ void LP_autopriceset(int32_t ind,void *ctx,int32_t dir,struct LP_priceinfo *base
     oppomargin = basepp->buymargins[relpp->ind];
     if ( (fixedprice= basepp->fixedprices[relpp->ind]) > SMALLVAL )
     {
+        if ( ind >= 0 )
+            LP_autorefs[ind].lastupdate = (uint32_t)time(NULL);
         LP_mypriceset(1,&changed,relpp->symbol,basepp->symbol,fixedprice);
         //printf("autoprice FIXED %s/%s <- %.8f\n",basepp->symbol,relpp->symbol,fixedprice);
         LP_pricepings(ctx,LP_myipaddr,LP_mypubsock,relpp->symbol,basepp->symbol,fixedprice);
@@ -314,6 +317,7 @@ void LP_autopriceset(int32_t ind,void *ctx,int32_t dir,struct LP_priceinfo *base
                         LP_autorefs[ind].lastask = newprice;
                     else LP_autorefs[ind].lastask = (LP_autorefs[ind].lastask * 0.99) + (0.01 *newprice);
                     newprice = LP_autorefs[ind].lastask;
+                    LP_autorefs[ind].lastupdate = (uint32_t)time(NULL);
                     //printf("autopriceset %s/%s <- %.8f %.8f (%.8f %.8f)\n",basepp->symbol,relpp->symbol,price,newprice,LP_autorefs[ind].lastbid,LP_autorefs[ind].lastask);
                 }
                 LP_mypriceset(1,&changed,relpp->symbol,basepp->symbol,newprice);
@@ -474,6 +478,24 @@ int32_t LP_autoref_clear(char *base,char *rel)
     return(-1);
 }
 
+uint32_t LP_autoprice_stale(char *base,char *rel)
+{
+    int32_t i; uint32_t now = (uint32_t)time(NULL);
+    if ( LP_autoprice_maxage == 0 )
+        return(0);
+    for (i=0; i<num_LP_autorefs; i++)
+    {
+        if ( (strcmp(rel,LP_autorefs[i].rel) == 0 && strcmp(base,LP_autorefs[i].base) == 0) ||
+            (strcmp(base,LP_autorefs[i].rel) == 0 && strcmp(rel,LP_autorefs[i].base) == 0) )
+        {
+            if ( LP_autorefs[i].lastupdate == 0 || now > LP_autorefs[i].lastupdate + LP_autoprice_maxage )
+                return(now - LP_autorefs[i].lastupdate);
+            else return(0);
+        }
+    }
+    return(0);
+}
+
 void LP_autoprice_iter(void *ctx,struct LP_priceinfo *btcpp)
 {
     static cJSON *tickerjson; static uint32_t lasttime;
@@ -590,6 +612,7 @@ void LP_autoprice_iter(void *ctx,struct LP_priceinfo *btcpp)
                         LP_pricepings(ctx,LP_myipaddr,LP_mypubsock,rel,base,bidprice);
                         LP_mypriceset(1,&changed,base,rel,askprice);
                         LP_pricepings(ctx,LP_myipaddr,LP_mypubsock,base,rel,askprice);
+                        LP_autorefs[i].lastupdate = (uint32_t)time(NULL);
                         //printf("price %.8f -> %.8f %.8f\n",price,bidprice,askprice);
                     }
                     LP_autorefs[i].count++;
@@ -640,8 +663,38 @@ void LP_autoprice_iter(void *ctx,struct LP_priceinfo *btcpp)
                 newprice = LP_autorefs[i].lastask;
                 LP_mypriceset(1,&changed,base,rel,newprice);
                 LP_pricepings(ctx,LP_myipaddr,LP_mypubsock,base,rel,newprice);
+                LP_autorefs[i].lastupdate = (uint32_t)time(NULL);
             } //else printf("null return from CMC\n");
         }
+        else if ( strcmp(LP_autorefs[i].refrel,"pricefeed") == 0 )
+        {
+            // a pegged ask is pulled once its feed is stale past maxage and comes back with the feed, the peg itself stays
+            if ( (price= LP_pricefeed_price(LP_autorefs[i].refbase,base,rel)) > SMALLVAL )
+            {
+                if ( LP_autorefs[i].feedstale != 0 )
+                {
+                    printf("pricefeed %s is back, %s/%s pegged ask restored\n",LP_autorefs[i].refbase,base,rel);
+                    LP_autorefs[i].feedstale = 0;
+                    LP_myorder_feedstale(base,rel,0);
+                }
+                newprice = price * (1. + sellmargin);
+                LP_autorefs[i].lastask = newprice;
+                LP_mypriceset(1,&changed,base,rel,newprice);
+                if ( (retstr= LP_pricepings(ctx,LP_myipaddr,LP_mypubsock,base,rel,newprice)) != 0 )
+                    free(retstr);
+                LP_autorefs[i].lastupdate = (uint32_t)time(NULL);
+                LP_autorefs[i].count++;
+            }
+            else if ( LP_autorefs[i].feedstale == 0 && time(NULL) > LP_autorefs[i].lastupdate + LP_pricefeed_maxage(LP_autorefs[i].refbase) )
+            {
+                printf("pricefeed %s stale for %d seconds, %s/%s pegged ask pulled until it updates\n",LP_autorefs[i].refbase,(int32_t)(time(NULL) - LP_autorefs[i].lastupdate),base,rel);
+                LP_autorefs[i].feedstale = 1;
+                LP_myorder_feedstale(base,rel,1);
+                LP_mypriceset(1,&changed,base,rel,0.);
+                if ( (retstr= LP_pricepings(ctx,LP_myipaddr,LP_mypubsock,base,rel,0.)) != 0 )
+                    free(retstr);
+            }
+        }
         else
         {
             basepp = LP_priceinfofind(base);
@@ -708,6 +761,8 @@ int32_t LP_autoprice(void *ctx,char *base,char *rel,cJSON *argjson)
         offset = jdouble(argjson,"offset");
         factor = jdouble(argjson,"factor");
         fixedprice = jdouble(argjson,"fixed");
+        if ( jobj(argjson,"maxage") != 0 )
+            LP_autoprice_maxage = juint(argjson,"maxage");
         basepp->fixedprices[relpp->ind] = fixedprice;
         basepp->minprices[relpp->ind] = minprice;
         if ( jobj(argjson,"maxprice") != 0 )
@@ -806,7 +861,7 @@ int32_t LP_portfolio_trade(void *ctx,uint32_t *requestidp,uint32_t *quoteidp,str
             //if ( LP_utxo_bestfit(sell->symbol,SATOSHIDEN * relvolume) != 0 )
             {
                 memset(zero.bytes,0,sizeof(zero));
-                if ( (retstr2= LP_autobuy(ctx,0,"127.0.0.1",-1,buy->symbol,sell->symbol,maxprice,relvolume,60,24*3600,gui,LP_lastnonce+1,zero,1,0,0,0)) != 0 )
+                if ( (retstr2= LP_autobuy(ctx,0,"127.0.0.1",-1,buy->symbol,sell->symbol,maxprice,relvolume,0,60,24*3600,gui,LP_lastnonce+1,zero,1,0,0,0,0,0)) != 0 )
                 {
                     if ( (retjson2= cJSON_Parse(retstr2)) != 0 )
                     {
@@ -896,6 +951,103 @@ int32_t LP_portfolio_order(struct LP_portfoliotrade *trades,int32_t max,cJSON *a
     return(n);
 }
 
+int32_t LP_coldsweep_confirms(struct iguana_info *coin,cJSON *item)
+{
+    int32_t height;
+    if ( jobj(item,"confirmations") != 0 )
+        return(jint(item,"confirmations"));
+    else if ( (height= jint(item,"height")) > 0 && coin->height >= height )
+        return(coin->height - height + 1);
+    return(0);
+}
+
+uint64_t LP_coldsweep_balance(struct iguana_info *coin,int32_t minconfirms)
+{
+    cJSON *array,*item; bits256 zero; int32_t i,n; uint64_t valuesum = 0;
+    memset(zero.bytes,0,sizeof(zero));
+    if ( (array= LP_listunspent(coin->symbol,coin->smartaddr,zero,zero)) != 0 )
+    {
+        if ( is_cJSON_Array(array) != 0 && (n= cJSON_GetArraySize(array)) > 0 )
+        {
+            for (i=0; i<n; i++)
+            {
+                item = jitem(array,i);
+                if ( LP_coldsweep_confirms(coin,item) >= minconfirms )
+                    valuesum += LP_value_extract(item,0,zero);
+            }
+        }
+        free_json(array);
+    }
+    return(valuesum);
+}
+
+void LP_coldsweep(struct iguana_info *coin)
+{
+    uint64_t balance,amount; char *retstr; cJSON *argjson,*outputs,*item;
+    if ( coin->coldaddr[0] == 0 || coin->inactive != 0 || coin->etomic[0] != 0 || time(NULL) < coin->lastcoldsweep+LP_COLDSWEEP_INTERVAL )
+        return;
+    coin->lastcoldsweep = (uint32_t)time(NULL);
+    if ( G.LP_pendingswaps != 0 )
+        return;
+    balance = LP_coldsweep_balance(coin,coin->coldconfirms);
+    if ( balance <= coin->coldkeep + 2*coin->txfee )
+        return;
+    amount = balance - coin->coldkeep - 2*coin->txfee;
+    if ( amount < coin->coldthreshold )
+        return;
+    argjson = cJSON_CreateObject();
+    outputs = cJSON_CreateArray();
+    item = cJSON_CreateObject();
+    jaddnum(item,coin->coldaddr,dstr(amount));
+    jaddi(outputs,item);
+    jadd(argjson,"outputs",outputs);
+    jaddnum(argjson,"broadcast",1);
+    printf("coldsweep %.8f %s to %s, balance %.8f keep %.8f\n",dstr(amount),coin->symbol,coin->coldaddr,dstr(balance),dstr(coin->coldkeep));
+    if ( (retstr= LP_withdraw(coin,argjson)) != 0 )
+    {
+        printf("coldsweep %s -> (%s)\n",coin->symbol,retstr);
+        free(retstr);
+    }
+    free_json(argjson);
+}
+
+void LP_coldsweeps()
+{
+    struct iguana_info *coin,*tmp;
+    HASH_ITER(hh,LP_coins,coin,tmp)
+    {
+        LP_coldsweep(coin);
+    }
+}
+
+char *LP_coldwallet(struct iguana_info *coin,cJSON *argjson)
+{
+    char *coldaddr; cJSON *retjson;
+    if ( coin->etomic[0] != 0 )
+        return(clonestr("{\"error\":\"coldwallet sweeps not supported for ETH/ERC20\"}"));
+    if ( (coldaddr= jstr(argjson,"address")) != 0 )
+    {
+        if ( coldaddr[0] != 0 && LP_address_isvalid(coin->symbol,coldaddr) <= 0 )
+            return(clonestr("{\"error\":\"invalid cold address\"}"));
+        safecopy(coin->coldaddr,coldaddr,sizeof(coin->coldaddr));
+    }
+    if ( jobj(argjson,"threshold") != 0 )
+        coin->coldthreshold = jdouble(argjson,"threshold") * SATOSHIDEN;
+    if ( jobj(argjson,"keep") != 0 )
+        coin->coldkeep = jdouble(argjson,"keep") * SATOSHIDEN;
+    if ( jobj(argjson,"confirms") != 0 )
+        coin->coldconfirms = jint(argjson,"confirms");
+    coin->lastcoldsweep = 0;
+    retjson = cJSON_CreateObject();
+    jaddstr(retjson,"result","success");
+    jaddstr(retjson,"coin",coin->symbol);
+    jaddstr(retjson,"address",coin->coldaddr);
+    jaddnum(retjson,"threshold",dstr(coin->coldthreshold));
+    jaddnum(retjson,"keep",dstr(coin->coldkeep));
+    jaddnum(retjson,"confirms",coin->coldconfirms);
+    return(jprint(retjson,1));
+}
+
 void prices_loop(void *ctx)
 {
     char *retstr; cJSON *retjson,*array; char *buycoin,*sellcoin; struct iguana_info *buy,*sell; uint32_t requestid,quoteid; int32_t i,n,m; struct LP_portfoliotrade trades[256]; struct LP_priceinfo *btcpp;
@@ -920,6 +1072,9 @@ void prices_loop(void *ctx)
         }
         if ( LP_autoprices != 0 )
             LP_autoprice_iter(ctx,btcpp);
+        LP_coldsweeps();
+        LP_myprices_rebroadcast(ctx);
+        LP_export_poll();
         if ( (retstr= LP_portfolio()) != 0 )
         {
             if ( (retjson= cJSON_Parse(retstr)) != 0 )
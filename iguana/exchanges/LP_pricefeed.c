@@ -0,0 +1,306 @@
+
+/******************************************************************************
+ * Copyright © 2014-2018 The SuperNET Developers.                             *
+ *                                                                            *
+ * See the AUTHORS, DEVELOPER-AGREEMENT and LICENSE files at                  *
+ * the top-level directory of this distribution for the individual copyright  *
+ * holder information and the developer policies on copyright and licensing.  *
+ *                                                                            *
+ * Unless otherwise agreed in a custom licensing agreement, no part of the    *
+ * SuperNET software, including this file may be copied, modified, propagated *
+ * or distributed except according to the terms contained in the LICENSE file *
+ *                                                                            *
+ * Removal or modification of this copyright notice is prohibited.            *
+ *                                                                            *
+ ******************************************************************************/
+//
+//  LP_pricefeed.c
+//  marketmaker
+//
+//  external price index providers from the "pricefeeds" startup array, e.g.
+//  {"name":"idx","url":"https://example.com/price?base={base}&rel={rel}","field":"price","pubkey":"02..","maxage":300,"cachetime":30}
+//  setprice with "price_autoupdate":1 pegs the ask to a feed plus "spread" and the autoprice loop keeps it there
+//
+
+#define LP_MAXPRICEFEEDS 8
+#define LP_MAXFEEDQUOTES 256
+#define LP_PRICEFEED_CACHETIME 30
+#define LP_PRICEFEED_MAXAGE 300
+
+struct LP_pricefeed { char name[32],url[256],field[32]; uint8_t pubkey33[33],signedflag; uint32_t cachetime,maxage; } LP_pricefeeds[LP_MAXPRICEFEEDS];
+struct LP_feedquote { char feed[32],base[16],rel[16]; double price; uint32_t fetched,timestamp; } LP_feedquotes[LP_MAXFEEDQUOTES];
+int32_t LP_numpricefeeds,LP_numfeedquotes;
+portable_mutex_t LP_pricefeedmutex;
+
+void LP_pricefeeds_init(cJSON *argjson)
+{
+    cJSON *array,*item; int32_t i,n; char *name,*url,*field,*pubstr; struct LP_pricefeed *pf;
+    if ( (array= jarray(&n,argjson,"pricefeeds")) == 0 )
+        return;
+    for (i=0; i<n && LP_numpricefeeds<LP_MAXPRICEFEEDS; i++)
+    {
+        item = jitem(array,i);
+        if ( (name= jstr(item,"name")) == 0 || (url= jstr(item,"url")) == 0 || strlen(name) >= sizeof(pf->name) || strlen(url) >= sizeof(pf->url) )
+        {
+            printf("pricefeed.%d needs a name and url, skipped\n",i);
+            continue;
+        }
+        pf = &LP_pricefeeds[LP_numpricefeeds];
+        memset(pf,0,sizeof(*pf));
+        strcpy(pf->name,name);
+        strcpy(pf->url,url);
+        if ( (field= jstr(item,"field")) == 0 )
+            field = "price";
+        safecopy(pf->field,field,sizeof(pf->field));
+        if ( (pubstr= jstr(item,"pubkey")) != 0 )
+        {
+            if ( strlen(pubstr) != 66 || is_hexstr(pubstr,0) != 66 )
+            {
+                printf("pricefeed %s pubkey must be 33 bytes of hex, skipped\n",name);
+                continue;
+            }
+            decode_hex(pf->pubkey33,33,pubstr);
+            pf->signedflag = 1;
+        }
+        if ( (pf->cachetime= juint(item,"cachetime")) == 0 )
+            pf->cachetime = LP_PRICEFEED_CACHETIME;
+        if ( (pf->maxage= juint(item,"maxage")) == 0 )
+            pf->maxage = LP_PRICEFEED_MAXAGE;
+        printf("pricefeed %s %s%s cache %us maxage %us\n",pf->name,pf->url,pf->signedflag != 0 ? " signed" : "",pf->cachetime,pf->maxage);
+        LP_numpricefeeds++;
+    }
+}
+
+struct LP_pricefeed *LP_pricefeed_find(char *feed)
+{
+    int32_t i;
+    if ( LP_numpricefeeds == 0 )
+        return(0);
+    if ( feed == 0 || feed[0] == 0 )
+        return(&LP_pricefeeds[0]);
+    for (i=0; i<LP_numpricefeeds; i++)
+        if ( strcmp(LP_pricefeeds[i].name,feed) == 0 )
+            return(&LP_pricefeeds[i]);
+    return(0);
+}
+
+void LP_pricefeed_url(char *url,int32_t size,char *fmt,char *base,char *rel)
+{
+    int32_t len = 0; char *src = fmt;
+    while ( *src != 0 && len < size-1 )
+    {
+        if ( strncmp(src,"{base}",6) == 0 )
+            len += snprintf(&url[len],size-len,"%s",base), src += 6;
+        else if ( strncmp(src,"{rel}",5) == 0 )
+            len += snprintf(&url[len],size-len,"%s",rel), src += 5;
+        else url[len++] = *src++;
+    }
+    if ( len > size-1 )
+        len = size-1;
+    url[len] = 0;
+}
+
+// signed providers sign sha256("BASE/REL:price:timestamp") with price printed to 8 decimals
+int32_t LP_pricefeed_verify(struct LP_pricefeed *pf,char *base,char *rel,double price,uint32_t timestamp,char *sigstr)
+{
+    char msg[128]; uint8_t sig[128]; int32_t len,siglen; bits256 hash;
+    if ( sigstr == 0 || (len= is_hexstr(sigstr,0)) <= 16 || len > 144 || (len & 1) != 0 )
+        return(-1);
+    siglen = len >> 1;
+    decode_hex(sig,siglen,sigstr);
+    snprintf(msg,sizeof(msg),"%s/%s:%.8f:%u",base,rel,price,timestamp);
+    vcalc_sha256(0,hash.bytes,(uint8_t *)msg,(int32_t)strlen(msg));
+    return(bitcoin_verify(bitcoin_ctx(),sig,siglen,hash,pf->pubkey33,33));
+}
+
+double LP_pricefeed_fetch(struct LP_pricefeed *pf,char *base,char *rel,uint32_t *timestampp)
+{
+    char url[512],*retstr; cJSON *retjson; double price = 0.; uint32_t timestamp,now = (uint32_t)time(NULL);
+    *timestampp = 0;
+    LP_pricefeed_url(url,sizeof(url),pf->url,base,rel);
+    if ( (retstr= issue_curlt(url,LP_HTTP_TIMEOUT)) == 0 )
+        return(0.);
+    if ( (retjson= cJSON_Parse(retstr)) != 0 )
+    {
+        price = jdouble(retjson,pf->field);
+        if ( (timestamp= juint(retjson,"timestamp")) == 0 )
+            timestamp = now;
+        if ( pf->signedflag != 0 )
+        {
+            if ( juint(retjson,"timestamp") == 0 || LP_pricefeed_verify(pf,base,rel,price,timestamp,jstr(retjson,"sig")) < 0 )
+            {
+                printf("pricefeed %s %s/%s signature doesnt verify, ignored\n",pf->name,base,rel);
+                price = 0.;
+            }
+        }
+        if ( price > SMALLVAL && (timestamp > now+60 || now > timestamp+pf->maxage) )
+        {
+            printf("pricefeed %s %s/%s timestamp %u is %d seconds off, ignored\n",pf->name,base,rel,timestamp,(int32_t)(now - timestamp));
+            price = 0.;
+        }
+        *timestampp = timestamp;
+        free_json(retjson);
+    }
+    free(retstr);
+    return(price > SMALLVAL ? price : 0.);
+}
+
+// cached for cachetime, a quote that cant be refreshed is served until it is maxage old and then dropped
+double LP_pricefeed_price(char *feed,char *base,char *rel)
+{
+    struct LP_pricefeed *pf; struct LP_feedquote *fq = 0; int32_t i; double price; uint32_t timestamp,now = (uint32_t)time(NULL);
+    if ( (pf= LP_pricefeed_find(feed)) == 0 )
+        return(0.);
+    portable_mutex_lock(&LP_pricefeedmutex);
+    for (i=0; i<LP_numfeedquotes; i++)
+        if ( strcmp(LP_feedquotes[i].feed,pf->name) == 0 && strcmp(LP_feedquotes[i].base,base) == 0 && strcmp(LP_feedquotes[i].rel,rel) == 0 )
+        {
+            fq = &LP_feedquotes[i];
+            break;
+        }
+    if ( fq != 0 && now < fq->fetched+pf->cachetime )
+    {
+        price = fq->price;
+        portable_mutex_unlock(&LP_pricefeedmutex);
+        return(price);
+    }
+    portable_mutex_unlock(&LP_pricefeedmutex);
+    price = LP_pricefeed_fetch(pf,base,rel,&timestamp);
+    portable_mutex_lock(&LP_pricefeedmutex);
+    if ( fq == 0 && LP_numfeedquotes < LP_MAXFEEDQUOTES )
+    {
+        fq = &LP_feedquotes[LP_numfeedquotes++];
+        safecopy(fq->feed,pf->name,sizeof(fq->feed));
+        safecopy(fq->base,base,sizeof(fq->base));
+        safecopy(fq->rel,rel,sizeof(fq->rel));
+    }
+    if ( fq != 0 )
+    {
+        if ( price > SMALLVAL )
+        {
+            fq->price = price;
+            fq->timestamp = timestamp;
+        }
+        else if ( fq->timestamp != 0 && now > fq->timestamp+pf->maxage )
+            fq->price = 0.;
+        fq->fetched = now;
+        price = fq->price;
+    }
+    portable_mutex_unlock(&LP_pricefeedmutex);
+    return(price);
+}
+
+uint32_t LP_pricefeed_maxage(char *feed)
+{
+    struct LP_pricefeed *pf;
+    if ( (pf= LP_pricefeed_find(feed)) == 0 )
+        return(LP_PRICEFEED_MAXAGE);
+    return(pf->maxage);
+}
+
+int32_t LP_pricefeed_unpeg(char *base,char *rel)
+{
+    int32_t i;
+    for (i=0; i<num_LP_autorefs; i++)
+    {
+        if ( strcmp(LP_autorefs[i].refrel,"pricefeed") == 0 && strcmp(base,LP_autorefs[i].base) == 0 && strcmp(rel,LP_autorefs[i].rel) == 0 )
+        {
+            memset(&LP_autorefs[i],0,sizeof(LP_autorefs[i]));
+            return(i);
+        }
+    }
+    return(-1);
+}
+
+// the peg is an autoprice ref with refrel "pricefeed" and the feed name as refbase, LP_autoprice_iter moves the ask
+char *LP_pricefeed_peg(void *ctx,char *myipaddr,int32_t pubsock,char *base,char *rel,cJSON *argjson)
+{
+    struct LP_pricefeed *pf; int32_t i,changed,ind = -1; double price,spread; cJSON *retjson;
+    if ( (pf= LP_pricefeed_find(jstr(argjson,"feed"))) == 0 )
+        return(clonestr("{\"error\":\"no such pricefeed\"}"));
+    if ( (spread= jdouble(argjson,"spread")) <= -1. )
+        return(clonestr("{\"error\":\"spread must be more than -1\"}"));
+    if ( (price= LP_pricefeed_price(pf->name,base,rel)) < SMALLVAL )
+        return(clonestr("{\"error\":\"pricefeed has no price for pair\"}"));
+    price *= (1. + spread);
+    if ( jobj(argjson,"volume") != 0 && LP_myvolume_check(base,jdouble(argjson,"volume")) < 0 )
+        return(clonestr("{\"error\":\"invalid volume\"}"));
+    if ( LP_mypriceset(1,&changed,base,rel,price) < 0 )
+        return(clonestr("{\"error\":\"couldnt set price\"}"));
+    if ( jobj(argjson,"volume") != 0 && LP_myvolume_set(base,rel,jdouble(argjson,"volume") * SATOSHIDEN) < 0 )
+        return(clonestr("{\"error\":\"invalid volume\"}"));
+    for (i=0; i<num_LP_autorefs; i++)
+    {
+        if ( strcmp(base,LP_autorefs[i].base) == 0 && strcmp(rel,LP_autorefs[i].rel) == 0 )
+        {
+            ind = i;
+            break;
+        }
+        else if ( ind < 0 && LP_autorefs[i].base[0] == 0 )
+            ind = i;
+    }
+    if ( ind < 0 )
+    {
+        if ( num_LP_autorefs >= sizeof(LP_autorefs)/sizeof(*LP_autorefs) )
+            return(clonestr("{\"error\":\"too many autoprice pairs\"}"));
+        ind = num_LP_autorefs++;
+    }
+    if ( LP_autorefs[ind].fundvalue != 0 )
+        free_json(LP_autorefs[ind].fundvalue);
+    memset(&LP_autorefs[ind],0,sizeof(LP_autorefs[ind]));
+    safecopy(LP_autorefs[ind].base,base,sizeof(LP_autorefs[ind].base));
+    safecopy(LP_autorefs[ind].rel,rel,sizeof(LP_autorefs[ind].rel));
+    safecopy(LP_autorefs[ind].refbase,pf->name,sizeof(LP_autorefs[ind].refbase));
+    strcpy(LP_autorefs[ind].refrel,"pricefeed");
+    LP_autorefs[ind].sellmargin = spread;
+    LP_autorefs[ind].lastask = price;
+    LP_autorefs[ind].lastupdate = (uint32_t)time(NULL);
+    LP_autoprices++;
+    printf("%d %s/%s pegged to pricefeed %s spread %.4f ask %.8f\n",ind,base,rel,pf->name,spread,price);
+    if ( jobj(argjson,"broadcast") == 0 || jint(argjson,"broadcast") != 0 )
+        return(LP_pricepings(ctx,myipaddr,pubsock,base,rel,price));
+    retjson = cJSON_CreateObject();
+    jaddstr(retjson,"result","success");
+    jaddstr(retjson,"feed",pf->name);
+    jaddnum(retjson,"price",price);
+    return(jprint(retjson,1));
+}
+
+char *LP_pricefeeds_json(char *base,char *rel)
+{
+    int32_t i; cJSON *retjson,*array,*item; struct LP_pricefeed *pf;
+    retjson = cJSON_CreateObject();
+    array = cJSON_CreateArray();
+    for (i=0; i<LP_numpricefeeds; i++)
+    {
+        pf = &LP_pricefeeds[i];
+        item = cJSON_CreateObject();
+        jaddstr(item,"name",pf->name);
+        jaddstr(item,"url",pf->url);
+        jaddstr(item,"field",pf->field);
+        jadd(item,"signed",pf->signedflag != 0 ? cJSON_CreateTrue() : cJSON_CreateFalse());
+        jaddnum(item,"cachetime",pf->cachetime);
+        jaddnum(item,"maxage",pf->maxage);
+        if ( base != 0 && rel != 0 && base[0] != 0 && rel[0] != 0 )
+            jaddnum(item,"price",LP_pricefeed_price(pf->name,base,rel));
+        jaddi(array,item);
+    }
+    jadd(retjson,"pricefeeds",array);
+    array = cJSON_CreateArray();
+    for (i=0; i<num_LP_autorefs; i++)
+    {
+        if ( strcmp(LP_autorefs[i].refrel,"pricefeed") != 0 )
+            continue;
+        item = cJSON_CreateObject();
+        jaddstr(item,"base",LP_autorefs[i].base);
+        jaddstr(item,"rel",LP_autorefs[i].rel);
+        jaddstr(item,"feed",LP_autorefs[i].refbase);
+        jaddnum(item,"spread",LP_autorefs[i].sellmargin);
+        jaddnum(item,"ask",LP_autorefs[i].lastask);
+        jaddnum(item,"lastupdate",LP_autorefs[i].lastupdate);
+        jaddi(array,item);
+    }
+    jadd(retjson,"pegs",array);
+    jaddstr(retjson,"result","success");
+    return(jprint(retjson,1));
+}
@@ -23,8 +23,8 @@ struct LP_orderbookentry
     bits256 pubkey;
     double price;
     int64_t avesatoshis,maxsatoshis,depth,dynamictrust;
-    uint32_t timestamp;
-    int32_t numutxos;
+    uint32_t timestamp,caps;
+    int32_t numutxos,completed,failed;
     char coinaddr[64];
 };
 
@@ -42,6 +42,10 @@ struct LP_priceinfo
     double sellmargins[LP_MAXPRICEINFOS];
     double offsets[LP_MAXPRICEINFOS];
     double factors[LP_MAXPRICEINFOS];
+    double pingprices[LP_MAXPRICEINFOS]; // last signed price broadcast
+    int64_t volumes[LP_MAXPRICEINFOS]; // base satoshis left on a sized order, 0 is bounded only by utxos
+    uint32_t pingtimes[LP_MAXPRICEINFOS];
+    uint8_t makerfirst[LP_MAXPRICEINFOS]; // setprice payfirst maker, offered as LP_CAP_MAKERFIRST
 } LP_priceinfos[LP_MAXPRICEINFOS];
 int32_t LP_numpriceinfos;
 
@@ -67,10 +71,11 @@ void LP_priceinfos_clear()
         memset(pp->sellmargins,0,sizeof(pp->sellmargins));
         memset(pp->offsets,0,sizeof(pp->offsets));
         memset(pp->factors,0,sizeof(pp->factors));
+        memset(pp->volumes,0,sizeof(pp->volumes));
     }
 }
 
-float LP_pubkey_price(int32_t *numutxosp,int64_t *avesatoshisp,int64_t *maxsatoshisp,struct LP_pubkey_info *pubp,uint32_t baseind,uint32_t relind)
+float _LP_pubkey_price(int32_t *numutxosp,int64_t *avesatoshisp,int64_t *maxsatoshisp,struct LP_pubkey_info *pubp,uint32_t baseind,uint32_t relind)
 {
     struct LP_pubkey_quote *pq,*tmp; int32_t scale; int64_t scale64;
     *numutxosp = 0;
@@ -79,6 +84,8 @@ float LP_pubkey_price(int32_t *numutxosp,int64_t *avesatoshisp,int64_t *maxsatos
     {
         if ( baseind == pq->baseind && relind == pq->relind )
         {
+            if ( pq->timestamp != 0 && pq->timestamp < (uint32_t)time(NULL) - LP_ORDERBOOK_DURATION && bits256_cmp(pubp->pubkey,G.LP_mypub25519) != 0 )
+                return(0.);
             if ( (scale= pq->scale) == 0 )
                 pq->scale = scale = 6;
             scale64 = 1;
@@ -96,9 +103,19 @@ float LP_pubkey_price(int32_t *numutxosp,int64_t *avesatoshisp,int64_t *maxsatos
     return(0);
 }
 
+float LP_pubkey_price(int32_t *numutxosp,int64_t *avesatoshisp,int64_t *maxsatoshisp,struct LP_pubkey_info *pubp,uint32_t baseind,uint32_t relind)
+{
+    float price;
+    portable_mutex_lock(&LP_pubkeymutex);
+    price = _LP_pubkey_price(numutxosp,avesatoshisp,maxsatoshisp,pubp,baseind,relind);
+    portable_mutex_unlock(&LP_pubkeymutex);
+    return(price);
+}
+
 void LP_pubkey_update(struct LP_pubkey_info *pubp,uint32_t baseind,uint32_t relind,float price,int64_t balance,char *utxocoin,int32_t numutxos,int64_t minutxo,int64_t maxutxo)
 {
     struct LP_pubkey_quote *pq,*tmp; int64_t aveutxo,scale64,ave64,max64; int32_t scale;
+    portable_mutex_lock(&LP_pubkeymutex);
     DL_FOREACH_SAFE(pubp->quotes,pq,tmp)
     {
         if ( baseind == pq->baseind && relind == pq->relind )
@@ -111,11 +128,12 @@ void LP_pubkey_update(struct LP_pubkey_info *pubp,uint32_t baseind,uint32_t reli
         pq->baseind = baseind;
         pq->relind = relind;
         pq->scale = 6; // millions of SATOSHIS, ie. 0.01
-        DL_APPEND(pubp->quotes,pq); // already serialized as only path is via stats_JSON()
+        DL_APPEND(pubp->quotes,pq);
         //printf("create pubp quotes %d/%d\n",baseind,relind);
     }
 //printf("%d/%d price %.8f balance %.8f %s num.%d min %.8f max %.8f\n",baseind,relind,price,dstr(balance),utxocoin,numutxos,dstr(minutxo),dstr(maxutxo));
     pq->price = price;
+    pq->timestamp = (uint32_t)time(NULL);
     if ( utxocoin != 0 && utxocoin[0] != 0 )
     {
         if ( (scale= pq->scale) == 0 )
@@ -141,10 +159,86 @@ void LP_pubkey_update(struct LP_pubkey_info *pubp,uint32_t baseind,uint32_t reli
         {
             printf("price %.8f base.%s rel.%s utxocoin.%s balance %.8f numutxos.%u %u scale64 = %llu, ave %llu, ave32 %u (%llu) max32 %u (%llu)\n",price,LP_priceinfos[baseind].symbol,LP_priceinfos[relind].symbol,utxocoin,dstr(balance),numutxos,pq->numutxos,(long long)scale64,(long long)aveutxo,pq->aveutxo,(long long)pq->aveutxo * scale64,pq->maxutxo,(long long)pq->maxutxo * scale64);
             int64_t avesatoshis,maxsatoshis;
-            price = LP_pubkey_price(&numutxos,&avesatoshis,&maxsatoshis,pubp,baseind,relind);
+            price = _LP_pubkey_price(&numutxos,&avesatoshis,&maxsatoshis,pubp,baseind,relind);
             printf("checkprice %.8f numutxos.%d ave %.8f max %.8f\n",price,numutxos,dstr(avesatoshis),dstr(maxsatoshis));
         }
     }
+    portable_mutex_unlock(&LP_pubkeymutex);
+}
+
+void LP_pubkey_quote_remove(struct LP_pubkey_info *pubp,uint32_t baseind,uint32_t relind)
+{
+    struct LP_pubkey_quote *pq,*tmp;
+    portable_mutex_lock(&LP_pubkeymutex); // readers walk the quotes from other threads
+    DL_FOREACH_SAFE(pubp->quotes,pq,tmp)
+    {
+        if ( baseind == pq->baseind && relind == pq->relind )
+        {
+            DL_DELETE(pubp->quotes,pq);
+            free(pq);
+            break;
+        }
+    }
+    portable_mutex_unlock(&LP_pubkeymutex);
+}
+
+uint32_t LP_caps(struct iguana_info *basecoin,struct iguana_info *relcoin)
+{
+    uint32_t caps = 0; struct iguana_info *kmd; struct LP_address *ap;
+    if ( basecoin != 0 && basecoin->bech32hrp[0] != 0 )
+        caps |= LP_CAP_SEGWIT;
+    if ( basecoin != 0 && basecoin->uniformspends != 0 )
+        caps |= LP_CAP_UNIFORMSPENDS;
+    if ( basecoin != 0 && basecoin->csvrefunds != 0 )
+        caps |= LP_CAP_CSV;
+    if ( relcoin != 0 && relcoin->burnfee != 0 )
+        caps |= LP_CAP_BURNFEE;
+    if ( basecoin != 0 && relcoin != 0 && basecoin->etomic[0] == 0 && relcoin->etomic[0] == 0 && LP_makerfirst(basecoin->symbol,relcoin->symbol) != 0 )
+        caps |= LP_CAP_MAKERFIRST;
+    if ( (kmd= LP_coinfind("KMD")) != 0 && (ap= LP_address(kmd,kmd->smartaddr)) != 0 && ap->instantdex_credits != 0 )
+        caps |= LP_CAP_ZEROCONF;
+    return(caps | ((uint32_t)LP_PROTOCOL_VERSION << 24));
+}
+
+// every required flag has to be present and the protocol version has to be at least the required one
+int32_t LP_caps_ok(uint32_t caps,uint32_t reqcaps)
+{
+    if ( (caps & reqcaps & LP_CAP_FLAGS) != (reqcaps & LP_CAP_FLAGS) )
+        return(0);
+    if ( LP_CAP_PROTOCOL(caps) < LP_CAP_PROTOCOL(reqcaps) )
+        return(0);
+    return(1);
+}
+
+void LP_pubkey_capsset(struct LP_pubkey_info *pubp,uint32_t baseind,uint32_t relind,uint32_t caps)
+{
+    struct LP_pubkey_quote *pq,*tmp;
+    portable_mutex_lock(&LP_pubkeymutex);
+    DL_FOREACH_SAFE(pubp->quotes,pq,tmp)
+    {
+        if ( baseind == pq->baseind && relind == pq->relind )
+        {
+            pq->caps = caps;
+            break;
+        }
+    }
+    portable_mutex_unlock(&LP_pubkeymutex);
+}
+
+uint32_t LP_pubkey_caps(struct LP_pubkey_info *pubp,uint32_t baseind,uint32_t relind)
+{
+    struct LP_pubkey_quote *pq,*tmp; uint32_t caps = 0;
+    portable_mutex_lock(&LP_pubkeymutex);
+    DL_FOREACH_SAFE(pubp->quotes,pq,tmp)
+    {
+        if ( baseind == pq->baseind && relind == pq->relind )
+        {
+            caps = pq->caps;
+            break;
+        }
+    }
+    portable_mutex_unlock(&LP_pubkeymutex);
+    return(caps);
 }
 
 struct LP_priceinfo *LP_priceinfo(int32_t ind)
@@ -510,6 +604,88 @@ char *LP_myprices(int32_t iambob)
     return(jprint(array,1));
 }
 
+void LP_pingprice_set(char *base,char *rel,double price)
+{
+    struct LP_priceinfo *basepp; int32_t relid;
+    if ( (basepp= LP_priceinfoptr(&relid,base,rel)) != 0 )
+    {
+        basepp->pingprices[relid] = price;
+        basepp->pingtimes[relid] = (uint32_t)time(NULL);
+    }
+}
+
+void LP_myprices_rebroadcast(void *ctx)
+{
+    struct LP_priceinfo *basepp,*relpp; int32_t i,j; char *retstr; uint32_t now = (uint32_t)time(NULL);
+    for (i=0; i<LP_numpriceinfos; i++)
+    {
+        basepp = &LP_priceinfos[i];
+        for (j=0; j<LP_numpriceinfos; j++)
+        {
+            relpp = &LP_priceinfos[j];
+            if ( basepp->myprices[1][j] > SMALLVAL && basepp->pingprices[j] > SMALLVAL && now > basepp->pingtimes[j] + LP_ORDERBOOK_DURATION/2 )
+            {
+                if ( (retstr= LP_pricepings(ctx,LP_myipaddr,LP_mypubsock,basepp->symbol,relpp->symbol,basepp->pingprices[j])) != 0 )
+                    free(retstr);
+            }
+        }
+    }
+}
+
+int64_t LP_myvolume(char *base,char *rel)
+{
+    struct LP_priceinfo *basepp,*relpp;
+    if ( (basepp= LP_priceinfofind(base)) != 0 && (relpp= LP_priceinfofind(rel)) != 0 )
+        return(basepp->volumes[relpp->ind]);
+    return(0);
+}
+
+// volume 0 is an unsized order, anything else has to be at least one tradeable swap
+int32_t LP_myvolume_check(char *base,double volume)
+{
+    struct iguana_info *coin;
+    if ( isfinite(volume) == 0 || volume < 0. )
+        return(-1);
+    if ( volume != 0. && ((coin= LP_coinfind(base)) == 0 || volume * SATOSHIDEN < LP_min_tradevol(coin)) )
+        return(-1);
+    return(0);
+}
+
+int32_t LP_myvolume_set(char *base,char *rel,int64_t satoshis)
+{
+    struct LP_priceinfo *basepp,*relpp;
+    if ( satoshis < 0 || (basepp= LP_priceinfofind(base)) == 0 || (relpp= LP_priceinfofind(rel)) == 0 )
+        return(-1);
+    basepp->volumes[relpp->ind] = satoshis;
+    return(0);
+}
+
+int32_t LP_makerfirst(char *base,char *rel)
+{
+    struct LP_priceinfo *basepp,*relpp;
+    if ( (basepp= LP_priceinfofind(base)) != 0 && (relpp= LP_priceinfofind(rel)) != 0 )
+        return(basepp->makerfirst[relpp->ind]);
+    return(0);
+}
+
+int32_t LP_makerfirst_set(char *base,char *rel,int32_t flag)
+{
+    struct LP_priceinfo *basepp,*relpp;
+    if ( (basepp= LP_priceinfofind(base)) == 0 || (relpp= LP_priceinfofind(rel)) == 0 )
+        return(-1);
+    basepp->makerfirst[relpp->ind] = (flag != 0);
+    return(0);
+}
+
+// "payfirst":"maker" on buy/sell requires a maker that offers to pay first
+uint32_t LP_payfirst_caps(cJSON *argjson)
+{
+    char *payfirst;
+    if ( (payfirst= jstr(argjson,"payfirst")) != 0 && strcmp(payfirst,"maker") == 0 )
+        return(LP_CAP_MAKERFIRST);
+    return(0);
+}
+
 int32_t LP_mypriceset(int32_t iambob,int32_t *changedp,char *base,char *rel,double price)
 {
     struct LP_priceinfo *basepp=0,*relpp=0; struct LP_pubkey_info *pubp; double minprice,maxprice,margin,buymargin,sellmargin;
@@ -535,6 +711,7 @@ int32_t LP_mypriceset(int32_t iambob,int32_t *changedp,char *base,char *rel,doub
                 relpp->offsets[basepp->ind] = 0.;
                 relpp->factors[basepp->ind] = 0.;
                 LP_autoref_clear(base,rel);
+                basepp->volumes[relpp->ind] = 0;
                 margin = 0.;
             }
             else if ( (minprice= basepp->minprices[relpp->ind]) > SMALLVAL && price < minprice )
@@ -765,6 +942,12 @@ cJSON *LP_orderbookjson(char *symbol,struct LP_orderbookentry *op)
         jaddbits256(item,"pubkey",op->pubkey);
         jaddnum(item,"age",time(NULL)-op->timestamp);
         jaddnum(item,"zcredits",dstr(op->dynamictrust));
+        jaddnum(item,"swaps_completed",op->completed);
+        jaddnum(item,"swaps_failed",op->failed);
+        if ( op->completed + op->failed > 0 )
+            jaddnum(item,"success_rate",(double)op->completed / (op->completed + op->failed));
+        if ( op->caps != 0 )
+            jaddnum(item,"caps",op->caps);
     }
     return(item);
 }
@@ -845,6 +1028,8 @@ int32_t LP_orderbook_utxoentries(uint32_t now,int32_t polarity,char *base,char *
             }
             if ( (op= LP_orderbookentry(coinaddr,base,rel,polarity > 0 ? price : 1./price,n,avesatoshis,maxsatoshis,pubp->pubkey,pubp->timestamp,balance,pubp->dynamictrust)) != 0 )
             {
+                op->caps = LP_pubkey_caps(pubp,baseid,relid);
+                op->completed = pubp->swapscompleted, op->failed = pubp->swapsfailed;
                 *arrayp = realloc(*arrayp,sizeof(*(*arrayp)) * (num+1));
                 (*arrayp)[num++] = op;
             }
@@ -854,9 +1039,47 @@ int32_t LP_orderbook_utxoentries(uint32_t now,int32_t polarity,char *base,char *
     return(num);
 }
 
-char *LP_orderbook(char *base,char *rel,int32_t duration)
+// ops are in book order with their own depth, not yet accumulated. asks round up and bids round down to the bucket
+// so a bucket price is never better than the orders in it. volumes are in the units of the orders
+cJSON *LP_orderbook_buckets(struct LP_orderbookentry **ops,int32_t num,double bucket,int32_t polarity)
+{
+    cJSON *array,*item = 0; int32_t i,numorders = 0; int64_t volume = 0,maxsatoshis = 0; double price,bucketprice = 0.;
+    array = cJSON_CreateArray();
+    for (i=0; i<=num; i++)
+    {
+        if ( i < num )
+        {
+            price = ops[i]->price / bucket;
+            price = bucket * (polarity > 0 ? ceil(price - SMALLVAL) : floor(price + SMALLVAL));
+        } else price = 0.;
+        if ( i == num || (numorders > 0 && fabs(price - bucketprice) > SMALLVAL) )
+        {
+            if ( numorders > 0 )
+            {
+                item = cJSON_CreateObject();
+                jaddnum(item,"price",bucketprice);
+                jaddnum(item,"volume",dstr(volume));
+                jaddnum(item,"maxvolume",dstr(maxsatoshis));
+                jaddnum(item,"numorders",numorders);
+                jaddi(array,item);
+            }
+            volume = maxsatoshis = numorders = 0;
+        }
+        if ( i < num )
+        {
+            bucketprice = price;
+            volume += ops[i]->depth;
+            if ( ops[i]->maxsatoshis > maxsatoshis )
+                maxsatoshis = ops[i]->maxsatoshis;
+            numorders++;
+        }
+    }
+    return(array);
+}
+
+char *LP_orderbook(char *base,char *rel,int32_t duration,double bucket)
 {
-    uint32_t now,i; int64_t depth,askdepth=0,biddepth=0; struct LP_priceinfo *basepp=0,*relpp=0; struct LP_orderbookentry **bids = 0,**asks = 0; cJSON *retjson,*array; struct iguana_info *basecoin,*relcoin; int32_t n,numbids=0,numasks=0,cachenumbids,cachenumasks,baseid,relid,suppress_prefetch=0;
+    uint32_t now,i; int64_t depth,askdepth=0,biddepth=0; struct LP_priceinfo *basepp=0,*relpp=0; struct LP_orderbookentry **bids = 0,**asks = 0; cJSON *retjson,*array,*bidbuckets=0,*askbuckets=0; struct iguana_info *basecoin,*relcoin; int32_t n,numbids=0,numasks=0,cachenumbids,cachenumasks,baseid,relid,suppress_prefetch=0;
     basecoin = LP_coinfind(base);
     relcoin = LP_coinfind(rel);
     if ( basecoin == 0 || relcoin == 0 )
@@ -882,8 +1105,16 @@ char *LP_orderbook(char *base,char *rel,int32_t duration)
     retjson = cJSON_CreateObject();
     array = cJSON_CreateArray();
     if ( numbids > 1 )
-    {
         qsort(bids,numbids,sizeof(*bids),_revcmp_orderbook);
+    if ( numasks > 1 )
+        qsort(asks,numasks,sizeof(*asks),_cmp_orderbook);
+    if ( bucket > SMALLVAL )
+    {
+        bidbuckets = LP_orderbook_buckets(bids,numbids,bucket,-1);
+        askbuckets = LP_orderbook_buckets(asks,numasks,bucket,1);
+    }
+    if ( numbids > 1 )
+    {
         depth = 0;
         for (i=0; i<numbids; i++)
         {
@@ -893,7 +1124,6 @@ char *LP_orderbook(char *base,char *rel,int32_t duration)
     }
     if ( numasks > 1 )
     {
-        qsort(asks,numasks,sizeof(*asks),_cmp_orderbook);
         depth = 0;
         for (i=0; i<numasks; i++)
         {
@@ -962,6 +1192,12 @@ char *LP_orderbook(char *base,char *rel,int32_t duration)
     jadd(retjson,"asks",array);
     jaddnum(retjson,"numasks",numasks);
     jaddnum(retjson,"askdepth",dstr(askdepth));
+    if ( bidbuckets != 0 && askbuckets != 0 )
+    {
+        jaddnum(retjson,"bucket",bucket);
+        jadd(retjson,"bidbuckets",bidbuckets);
+        jadd(retjson,"askbuckets",askbuckets);
+    }
     jaddstr(retjson,"base",base);
     jaddstr(retjson,"rel",rel);
     jaddnum(retjson,"timestamp",now);
@@ -977,7 +1213,7 @@ double LP_fomoprice(char *base,char *rel,double *relvolumep)
 {
     char *retstr; cJSON *retjson,*asks,*item; int32_t i,numasks; double maxvol=0.,relvolume,biggest,price,fomoprice = 0.;
     relvolume = *relvolumep;
-    if ( (retstr= LP_orderbook(base,rel,0)) != 0 )
+    if ( (retstr= LP_orderbook(base,rel,0,0.)) != 0 )
     {
         if ( (retjson= cJSON_Parse(retstr)) != 0 )
         {
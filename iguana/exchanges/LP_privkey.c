@@ -682,7 +682,8 @@ char *verusblocks()
 void LP_privkey_updates(void *ctx,int32_t pubsock,char *passphrase)
 {
     struct iguana_info *coin,*tmp; bits256 pubkey,privkey; uint8_t pubkey33[33]; int32_t initonly;
-    initonly = (passphrase != 0);
+    if ( (initonly= (passphrase != 0)) != 0 )
+        LP_hdwallet_init(passphrase);
     memset(privkey.bytes,0,sizeof(privkey));
     memset(pubkey.bytes,0,sizeof(pubkey));
 	//printf("Total coins: %d\n", HASH_COUNT(LP_coins));
@@ -698,6 +699,8 @@ void LP_privkey_updates(void *ctx,int32_t pubsock,char *passphrase)
             {
                 privkey = LP_privkeycalc(ctx,pubkey33,&pubkey,coin,passphrase,"");
             }
+            LP_hdwallet_coininit(ctx,coin);
+            LP_signer_coininit(coin);
         }
         //printf("i.%d of %d\n",i,LP_numcoins);
         else if ( IAMLP == 0 || coin->inactive == 0 )
@@ -62,7 +62,7 @@ void basilisk_dontforget(struct basilisk_swap *swap,struct basilisk_rawtx *rawtx
     memset(zeroes,0,sizeof(zeroes));
     if ( alicecoin != 0 && bobcoin != 0 && rawtx != 0 && (fp= fopen(fname,"wb")) != 0 )
     {
-        fprintf(fp,"{\"name\":\"%s\",\"coin\":\"%s\"",rawtx->name,rawtx->symbol);
+        fprintf(fp,"{\"name\":\"%s\",\"coin\":\"%s\",\"version\":%d",rawtx->name,rawtx->symbol,LP_SCHEMA_VERSION);
         if ( rawtx->I.datalen > 0 )
         {
             fprintf(fp,",\"tx\":\"");
@@ -109,9 +109,15 @@ void basilisk_dontforget(struct basilisk_swap *swap,struct basilisk_rawtx *rawtx
         if (swap->alicepayment.I.ethTxid[0] != 0) {
             fprintf(fp,",\"alicePaymentEthTx\":\"%s\"", swap->alicepayment.I.ethTxid);
         }
+        if ( swap->I.alicecontract[0] != 0 )
+            fprintf(fp,",\"alicecontract\":\"%s\"",swap->I.alicecontract);
+        if ( swap->I.bobcontract[0] != 0 )
+            fprintf(fp,",\"bobcontract\":\"%s\"",swap->I.bobcontract);
 
         fprintf(fp,",\"aliceRealSat\":\"%" PRId64 "\"", swap->I.alicerealsat);
         fprintf(fp,",\"bobRealSat\":\"%" PRId64 "\"", swap->I.bobrealsat);
+        fprintf(fp,",\"aliceAmount\":\"%s\"",swap->I.aliceamount);
+        fprintf(fp,",\"bobAmount\":\"%s\"",swap->I.bobamount);
 #endif
         fprintf(fp,",\"alicecoin\":\"%s\"",swap->I.alicestr);
         if ( swap->I.alicetomic[0] != 0 )
@@ -122,7 +128,16 @@ void basilisk_dontforget(struct basilisk_swap *swap,struct basilisk_rawtx *rawtx
         fprintf(fp,",\"amount\":%.8f",dstr(rawtx->I.amount));
         if ( bits256_nonz(triggertxid) != 0 )
             fprintf(fp,",\"trigger\":\"%s\"",bits256_str(str,triggertxid));
-        if ( bits256_nonz(swap->I.pubAm) != 0 && bits256_nonz(swap->I.pubBn) != 0 )
+        if ( swap->I.makerfirst != 0 )
+        {
+            if ( swap->alicepayment.I.redeemlen > 0 )
+            {
+                bitcoin_address(alicecoin->symbol,coinaddr,alicecoin->taddr,alicecoin->p2shtype,swap->alicepayment.redeemscript,swap->alicepayment.I.redeemlen);
+                LP_importaddress(swap->I.alicestr,coinaddr);
+                fprintf(fp,",\"Apayment\":\"%s\"",coinaddr);
+            }
+        }
+        else if ( bits256_nonz(swap->I.pubAm) != 0 && bits256_nonz(swap->I.pubBn) != 0 )
         {
             basilisk_alicescript(alicecoin->symbol,redeemscript,&len,script,0,coinaddr,alicecoin->taddr,alicecoin->p2shtype,swap->I.pubAm,swap->I.pubBn);
             LP_importaddress(swap->I.alicestr,coinaddr);
@@ -146,12 +161,31 @@ void basilisk_dontforget(struct basilisk_swap *swap,struct basilisk_rawtx *rawtx
     sprintf(fname,"%s/SWAPS/%u-%u",GLOBAL_DBDIR,swap->I.req.requestid,swap->I.req.quoteid), OS_compatible_path(fname);
     if ( (fp= fopen(fname,"wb")) != 0 )
     {
-        fprintf(fp,"{\"tradeid\":%u,\"aliceid\":\"%llu\",\"src\":\"%s\",\"srcamount\":%.8f,\"dest\":\"%s\",\"destamount\":%.8f,\"requestid\":%u,\"quoteid\":%u,\"iambob\":%d,\"state\":%u,\"otherstate\":%u,\"expiration\":%u,\"dlocktime\":%u,\"plocktime\":%u,\"Atxfee\":%llu,\"Btxfee\":%llu",swap->tradeid,(long long)swap->aliceid,swap->I.req.src,dstr(swap->I.req.srcamount),swap->I.req.dest,dstr(swap->I.req.destamount),swap->I.req.requestid,swap->I.req.quoteid,swap->I.iambob,swap->I.statebits,swap->I.otherstatebits,swap->I.expiration,swap->bobdeposit.I.locktime,swap->bobpayment.I.locktime,(long long)swap->I.Atxfee,(long long)swap->I.Btxfee);
+        fprintf(fp,"{\"tradeid\":%u,\"aliceid\":\"%llu\",\"src\":\"%s\",\"srcamount\":%.8f,\"dest\":\"%s\",\"destamount\":%.8f,\"requestid\":%u,\"quoteid\":%u,\"iambob\":%d,\"state\":%u,\"otherstate\":%u,\"expiration\":%u,\"dlocktime\":%u,\"plocktime\":%u,\"Atxfee\":%llu,\"Btxfee\":%llu,\"Aclaimfee\":%llu,\"Bclaimfee\":%llu",swap->tradeid,(long long)swap->aliceid,swap->I.req.src,dstr(swap->I.req.srcamount),swap->I.req.dest,dstr(swap->I.req.destamount),swap->I.req.requestid,swap->I.req.quoteid,swap->I.iambob,swap->I.statebits,swap->I.otherstatebits,swap->I.expiration,swap->bobdeposit.I.locktime,swap->bobpayment.I.locktime,(long long)swap->I.Atxfee,(long long)swap->I.Btxfee,(long long)swap->I.Aclaimfee,(long long)swap->I.Bclaimfee);
         if ( swap->I.iambob == 0 )
             fprintf(fp,",\"Agui\":\"%s\"",G.gui);
         else fprintf(fp,",\"Bgui\":\"%s\"",G.gui);
         fprintf(fp,",\"gui\":\"%s\"",G.gui);
         fprintf(fp,",\"uuid\":\"%s\"",swap->uuidstr);
+        fprintf(fp,",\"version\":%d",LP_SCHEMA_VERSION);
+        fprintf(fp,",\"lockduration\":%u",swap->I.putduration);
+        if ( swap->startmillis != 0. )
+        {
+            fprintf(fp,",\"timings\":{");
+            for (i=0; i<LP_SWAPPHASES; i++)
+                fprintf(fp,"\"%s\":%.0f,",LP_swapphases[i],swap->phasemillis[i]);
+            fprintf(fp,"\"phase\":\"%s\",\"total\":%.0f,\"ended\":%u}",LP_swapphases[swap->phase],LP_monotonic_millis() - swap->startmillis,LP_now());
+        }
+        fprintf(fp,",\"channels\":{");
+        for (i=0; i<LP_SWAPCHANNELS; i++)
+            fprintf(fp,"\"%s\":%u,",LP_swapchannels[i],swap->delivered[i]);
+        fprintf(fp,"\"duplicates\":%u}",swap->duplicates);
+        if ( swap->I.uniformspends != 0 )
+            fprintf(fp,",\"uniformspends\":%d",swap->I.uniformspends);
+        if ( swap->I.csvrefunds != 0 )
+            fprintf(fp,",\"csvrefunds\":%d",swap->I.csvrefunds);
+        if ( swap->I.makerfirst != 0 )
+            fprintf(fp,",\"makerfirst\":%d",swap->I.makerfirst);
         if ( memcmp(zeroes,swap->I.secretAm,20) != 0 )
         {
             init_hexbytes_noT(secretAmstr,swap->I.secretAm,20);
@@ -202,6 +236,19 @@ void basilisk_dontforget(struct basilisk_swap *swap,struct basilisk_rawtx *rawtx
         
         if ( bits256_nonz(swap->alicepayment.I.actualtxid) != 0 )
             fprintf(fp,",\"Apayment\":\"%s\"",bits256_str(str,swap->alicepayment.I.actualtxid));
+        if ( swap->Apaymentheight > 0 )
+        {
+            fprintf(fp,",\"Apaymentheight\":%d",swap->Apaymentheight);
+            if ( bits256_nonz(swap->Apaymentblock) != 0 )
+                fprintf(fp,",\"Apaymentblock\":\"%s\"",bits256_str(str,swap->Apaymentblock));
+        }
+        if ( swap->claimpath[0] != 0 )
+        {
+            fprintf(fp,",\"claimpath\":\"%s\",\"claim33\":\"",swap->claimpath);
+            for (i=0; i<33; i++)
+                fprintf(fp,"%02x",swap->claimpub33[i]);
+            fprintf(fp,"\"");
+        }
         if ( bits256_nonz(swap->bobspend.I.actualtxid) != 0 )
             fprintf(fp,",\"Bspend\":\"%s\"",bits256_str(str,swap->bobspend.I.actualtxid));
         if ( bits256_nonz(swap->alicereclaim.I.actualtxid) != 0 )
@@ -309,6 +356,8 @@ void basilisk_dontforget_update(struct basilisk_swap *swap,struct basilisk_rawtx
                 else jaddstr(reqjson,"update","main");
                 jaddnum(reqjson,"requestid",swap->I.req.requestid);
                 jaddnum(reqjson,"quoteid",swap->I.req.quoteid);
+                jaddnum(reqjson,"timestamp_ms",LP_utcmillis());
+                jaddnum(reqjson,"elapsed_ms",(int64_t)LP_swap_elapsed(swap));
                 outstr = jprint(reqjson,1);
                 LP_queuecommand(0,outstr,IPC_ENDPOINT,-1,0);
                 free(outstr);
@@ -493,7 +542,7 @@ int32_t basilisk_isbobcoin(int32_t iambob,int32_t ind)
 
 int32_t basilisk_swap_isfinished(uint32_t requestid,uint32_t quoteid,uint32_t expiration,int32_t iambob,bits256 *txids,int32_t *sentflags,bits256 paymentspent,bits256 Apaymentspent,bits256 depositspent,uint32_t lockduration)
 {
-    int32_t i,n = 0; uint32_t now = (uint32_t)time(NULL);
+    int32_t i,n = 0; uint32_t now = LP_now();
     if ( bits256_nonz(paymentspent) != 0 && bits256_nonz(Apaymentspent) != 0 && bits256_nonz(depositspent) != 0 )
         return(1);
     else if ( sentflags[BASILISK_BOBPAYMENT] == 0 && bits256_nonz(txids[BASILISK_BOBPAYMENT]) == 0 && bits256_nonz(Apaymentspent) != 0 && bits256_nonz(depositspent) != 0 )
@@ -670,9 +719,250 @@ void LP_totals_update(int32_t iambob,char *alicecoin,char *bobcoin,int64_t *KMDt
     }
 }
 
+void LP_swap_errorsave(uint32_t requestid,uint32_t quoteid,int32_t err,char *category)
+{
+    char fname[512],*str,*side; FILE *fp; cJSON *item;
+    sprintf(fname,"%s/SWAPS/%u-%u.error",GLOBAL_DBDIR,requestid,quoteid), OS_compatible_path(fname);
+    if ( (fp= fopen(fname,"wb")) != 0 )
+    {
+        item = cJSON_CreateObject();
+        jaddnum(item,"error",err);
+        jaddstr(item,"kind",LP_swaperr_kind(err,category,&side));
+        if ( side != 0 )
+            jaddstr(item,"side",side);
+        jaddstr(item,"category",category);
+        jaddstr(item,"state",strcmp(category,"timeout") == 0 ? "timedout" : (strcmp(category,"shutdown") == 0 ? "resumable" : "failed"));
+        jaddnum(item,"timestamp",time(NULL));
+        str = jprint(item,1);
+        fwrite(str,1,strlen(str),fp);
+        fclose(fp);
+        free(str);
+    }
+}
+
+cJSON *LP_swap_errorload(uint32_t requestid,uint32_t quoteid)
+{
+    char fname[512],*fstr; long fsize; cJSON *item = 0;
+    sprintf(fname,"%s/SWAPS/%u-%u.error",GLOBAL_DBDIR,requestid,quoteid), OS_compatible_path(fname);
+    if ( (fstr= OS_filestr(&fsize,fname)) != 0 )
+    {
+        item = cJSON_Parse(fstr);
+        free(fstr);
+    }
+    return(item);
+}
+
+void LP_receipt_txid(cJSON *item,char *field,struct LP_swap_remember *rswap,int32_t ind)
+{
+#ifndef NOTETOMIC
+    if ( LP_etomic_is_empty_tx_id(rswap->eth_tx_ids[ind]) == 0 )
+    {
+        jaddstr(item,field,rswap->eth_tx_ids[ind]);
+        return;
+    }
+#endif
+    if ( bits256_nonz(rswap->txids[ind]) != 0 )
+        jaddbits256(item,field,rswap->txids[ind]);
+}
+
+// the key that negotiated the swap, the receipt has to be signed by the pubkey the counterparty saw
+int32_t LP_receipt_privkey(void *ctx,bits256 *privkeyp,struct LP_swap_remember *rswap)
+{
+    struct iguana_info *coin; uint8_t pub33[33]; bits256 privkey; int32_t iter;
+    for (iter=0; iter<2; iter++)
+    {
+        if ( iter == 0 )
+            privkey = G.LP_privkey;
+        else if ( (coin= LP_coinfind(rswap->iambob != 0 ? rswap->src : rswap->dest)) != 0 )
+            privkey = LP_swap_privkey(coin,coin->smartaddr);
+        else break;
+        if ( bits256_nonz(privkey) == 0 )
+            continue;
+        bitcoin_pubkey33(ctx,pub33,privkey);
+        if ( memcmp(pub33,rswap->pubkey33,33) == 0 )
+        {
+            *privkeyp = privkey;
+            return(0);
+        }
+    }
+    return(-1);
+}
+
+// once our side has spent the counterparty's payment the swap is settled from our point of view, sign a receipt of
+// what was agreed and what hit the chains. the signature covers payload byte for byte: sha256(payload) recovers to
+// signer, which is the persistent pubkey exchanged in the swap. what makes it evidence against the counterparty is
+// the pubkeys message it signed with its own persistent key, carried along in the payload
+void LP_swap_receiptsave(void *ctx,struct LP_swap_remember *rswap)
+{
+    char fname[512],tmpname[512],hexstr[67],sigstr[131],*payload,*str,*otherpubkeys; FILE *fp; long fsize; uint8_t sig[65]; bits256 privkey,hash; cJSON *receipt,*maker,*taker,*txids,*retjson; int32_t spendind;
+    spendind = rswap->iambob != 0 ? BASILISK_BOBSPEND : BASILISK_ALICESPEND;
+    if ( rswap->sentflags[spendind] == 0 || rswap->uuidstr[0] == 0 )
+        return;
+    sprintf(fname,"%s/SWAPS/%u-%u.receipt",GLOBAL_DBDIR,rswap->requestid,rswap->quoteid), OS_compatible_path(fname);
+    if ( OS_filesize(fname) > 0 ) // every basilisk_remember pass gets here, only the first one writes
+        return;
+    if ( LP_receipt_privkey(ctx,&privkey,rswap) < 0 )
+    {
+        printf("swap %u-%u: no key for persistent pubkey, cant sign receipt\n",rswap->requestid,rswap->quoteid);
+        return;
+    }
+    receipt = cJSON_CreateObject();
+    jaddstr(receipt,"uuid",rswap->uuidstr);
+    jaddnum(receipt,"requestid",rswap->requestid);
+    jaddnum(receipt,"quoteid",rswap->quoteid);
+    jaddstr(receipt,"signer_role",rswap->iambob != 0 ? "maker" : "taker");
+    maker = cJSON_CreateObject();
+    jaddstr(maker,"coin",rswap->src);
+    init_hexbytes_noT(hexstr,rswap->iambob != 0 ? rswap->pubkey33 : rswap->other33,33);
+    jaddstr(maker,"pubkey",hexstr);
+    jaddnum(maker,"amount",dstr(rswap->srcamount));
+    jadd(receipt,"maker",maker);
+    taker = cJSON_CreateObject();
+    jaddstr(taker,"coin",rswap->dest);
+    init_hexbytes_noT(hexstr,rswap->iambob != 0 ? rswap->other33 : rswap->pubkey33,33);
+    jaddstr(taker,"pubkey",hexstr);
+    jaddnum(taker,"amount",dstr(rswap->destamount));
+    jadd(receipt,"taker",taker);
+    txids = cJSON_CreateObject();
+    LP_receipt_txid(txids,"takerfee",rswap,rswap->iambob != 0 ? BASILISK_OTHERFEE : BASILISK_MYFEE);
+    LP_receipt_txid(txids,"makerdeposit",rswap,BASILISK_BOBDEPOSIT);
+    LP_receipt_txid(txids,"takerpayment",rswap,BASILISK_ALICEPAYMENT);
+    LP_receipt_txid(txids,"makerpayment",rswap,BASILISK_BOBPAYMENT);
+    LP_receipt_txid(txids,rswap->iambob != 0 ? "makerspend" : "takerspend",rswap,spendind);
+    jadd(receipt,"txids",txids);
+    init_hexbytes_noT(hexstr,rswap->secretAm,20);
+    jaddstr(receipt,"secret_hash",hexstr);
+    init_hexbytes_noT(hexstr,rswap->secretBn,20);
+    jaddstr(receipt,"deposit_secret_hash",hexstr);
+    sprintf(fname,"%s/SWAPS/%u-%u.pubkeys",GLOBAL_DBDIR,rswap->requestid,rswap->quoteid), OS_compatible_path(fname);
+    if ( (otherpubkeys= OS_filestr(&fsize,fname)) != 0 )
+    {
+        if ( (str= strchr(otherpubkeys,'\n')) != 0 )
+            *str = 0;
+        jaddstr(receipt,"counterparty_pubkeys",otherpubkeys);
+        free(otherpubkeys);
+    }
+    jaddnum(receipt,"timestamp",time(NULL));
+    sprintf(fname,"%s/SWAPS/%u-%u.receipt",GLOBAL_DBDIR,rswap->requestid,rswap->quoteid), OS_compatible_path(fname);
+    payload = jprint(receipt,1);
+    vcalc_sha256(0,hash.bytes,(uint8_t *)payload,(int32_t)strlen(payload));
+    if ( bitcoin_sign(ctx,"receipt",sig,hash,privkey,1) != 65 )
+    {
+        printf("swap %u-%u: error signing receipt\n",rswap->requestid,rswap->quoteid);
+        free(payload);
+        return;
+    }
+    retjson = cJSON_CreateObject();
+    jaddstr(retjson,"payload",payload);
+    init_hexbytes_noT(hexstr,rswap->pubkey33,33);
+    jaddstr(retjson,"signer",hexstr);
+    init_hexbytes_noT(sigstr,sig,65);
+    jaddstr(retjson,"signature",sigstr);
+    free(payload);
+    sprintf(tmpname,"%s.tmp",fname); // renamed into place whole, so the existence check above never sees half a receipt
+    if ( (fp= fopen(tmpname,"wb")) != 0 )
+    {
+        str = jprint(retjson,0);
+        fprintf(fp,"%s\n",str);
+        fclose(fp);
+        free(str);
+        if ( OS_renamefile(tmpname,fname) == 0 )
+            printf("swap %u-%u: signed completion receipt\n",rswap->requestid,rswap->quoteid);
+    }
+    free_json(retjson);
+}
+
+cJSON *LP_swap_receiptload(uint32_t requestid,uint32_t quoteid)
+{
+    char fname[512],*fstr; long fsize; cJSON *item = 0;
+    sprintf(fname,"%s/SWAPS/%u-%u.receipt",GLOBAL_DBDIR,requestid,quoteid), OS_compatible_path(fname);
+    if ( (fstr= OS_filestr(&fsize,fname)) != 0 )
+    {
+        item = cJSON_Parse(fstr);
+        free(fstr);
+    }
+    return(item);
+}
+
+char *LP_swap_receipt(uint32_t requestid,uint32_t quoteid)
+{
+    cJSON *retjson,*receipt;
+    if ( (receipt= LP_swap_receiptload(requestid,quoteid)) == 0 )
+        return(clonestr("{\"error\":\"no receipt for this swap, receipts are signed once our side spends the other payment\"}"));
+    retjson = cJSON_CreateObject();
+    jaddstr(retjson,"result","success");
+    jadd(retjson,"receipt",receipt);
+    return(jprint(retjson,1));
+}
+
+// the counterparty's own pubkeys message has to be signed by the other party named in the payload for this very swap
+int32_t LP_receipt_counterparty(void *ctx,cJSON *retjson,cJSON *payjson,char *role)
+{
+    char *hexstr,*pubstr,pubkeystr[67]; uint8_t *data,pub33[33]; uint32_t requestid,quoteid; int64_t alicesatoshis,bobsatoshis; int32_t datalen,retval = -1;
+    if ( (hexstr= jstr(payjson,"counterparty_pubkeys")) == 0 )
+        return(0);
+    if ( (datalen= (int32_t)strlen(hexstr)/2) <= 0 || is_hexstr(hexstr,0) != datalen*2 )
+        return(-1);
+    data = malloc(datalen);
+    decode_hex(data,datalen,hexstr);
+    if ( LP_pubkeys_signer(ctx,pub33,&requestid,&quoteid,&alicesatoshis,&bobsatoshis,data,datalen) == 0 && requestid == juint(payjson,"requestid") && quoteid == juint(payjson,"quoteid") )
+    {
+        init_hexbytes_noT(pubkeystr,pub33,33);
+        if ( (pubstr= jstr(jobj(payjson,strcmp(role,"maker") == 0 ? "taker" : "maker"),"pubkey")) != 0 && strcmp(pubstr,pubkeystr) == 0 )
+        {
+            jaddstr(retjson,"counterparty",pubkeystr);
+            jaddnum(retjson,"counterparty_makeramount",dstr(bobsatoshis));
+            jaddnum(retjson,"counterparty_takeramount",dstr(alicesatoshis));
+            retval = 1;
+        }
+    }
+    free(data);
+    return(retval);
+}
+
+// anyone can check a receipt: the signature has to recover to the signer, the signer has to be the party in the
+// payload it claims to be and a counterparty pubkeys message has to be signed by the other party for this swap.
+// whether the txids are on chain is left to the verifier
+char *LP_swap_receipt_verify(void *ctx,cJSON *argjson)
+{
+    char *payload,*signer,*sigstr,*role,*pubstr; uint8_t sig[65],pub33[33],signer33[33]; bits256 hash; int32_t countersigned; cJSON *receipt,*payjson,*retjson;
+    if ( (receipt= jobj(argjson,"receipt")) == 0 )
+        receipt = argjson;
+    if ( (payload= jstr(receipt,"payload")) == 0 || (signer= jstr(receipt,"signer")) == 0 || (sigstr= jstr(receipt,"signature")) == 0 )
+        return(clonestr("{\"error\":\"receipt needs payload, signer and signature\"}"));
+    if ( strlen(signer) != 66 || is_hexstr(signer,0) != 66 || strlen(sigstr) != 130 || is_hexstr(sigstr,0) != 130 )
+        return(clonestr("{\"error\":\"signer must be a 33 byte pubkey and signature 65 bytes, both hex\"}"));
+    decode_hex(signer33,33,signer);
+    decode_hex(sig,65,sigstr);
+    vcalc_sha256(0,hash.bytes,(uint8_t *)payload,(int32_t)strlen(payload));
+    memset(pub33,0,sizeof(pub33));
+    if ( bitcoin_recoververify(ctx,"receipt",sig,hash,pub33,0) != 0 || memcmp(pub33,signer33,33) != 0 )
+        return(clonestr("{\"error\":\"signature does not match signer\"}"));
+    if ( (payjson= cJSON_Parse(payload)) == 0 )
+        return(clonestr("{\"error\":\"payload is not json\"}"));
+    if ( (role= jstr(payjson,"signer_role")) == 0 || (strcmp(role,"maker") != 0 && strcmp(role,"taker") != 0) || (pubstr= jstr(jobj(payjson,role),"pubkey")) == 0 || strcmp(pubstr,signer) != 0 )
+    {
+        free_json(payjson);
+        return(clonestr("{\"error\":\"signer is not the party named in the receipt\"}"));
+    }
+    retjson = cJSON_CreateObject();
+    if ( (countersigned= LP_receipt_counterparty(ctx,retjson,payjson,role)) < 0 )
+    {
+        free_json(retjson);
+        free_json(payjson);
+        return(clonestr("{\"error\":\"counterparty pubkeys message is not signed by the other party for this swap\"}"));
+    }
+    jaddstr(retjson,"result","success");
+    jaddstr(retjson,"signer",signer);
+    jaddstr(retjson,"signer_role",role);
+    jaddnum(retjson,"countersigned",countersigned);
+    jadd(retjson,"receipt",payjson);
+    return(jprint(retjson,1));
+}
+
 cJSON *LP_swap_json(struct LP_swap_remember *rswap)
 {
-    cJSON *item,*array; int32_t i;
+    cJSON *item,*array,*errjson,*receipt; int32_t i;
     item = cJSON_CreateObject();
     if ( LP_swap_endcritical < LP_swap_critical )
     {
@@ -694,6 +984,8 @@ cJSON *LP_swap_json(struct LP_swap_remember *rswap)
         jaddstr(item,"bobtomic",rswap->bobtomic);
     if ( rswap->etomicsrc[0] != 0 )
         jaddstr(item,"etomicsrc",rswap->etomicsrc);
+    if ( rswap->bobcontract[0] != 0 )
+        jaddstr(item,"bobcontract",rswap->bobcontract);
     jaddnum(item,"srcamount",dstr(rswap->srcamount));
     jaddnum(item,"bobtxfee",dstr(rswap->Btxfee));
     jaddstr(item,"alice",rswap->dest);
@@ -701,6 +993,8 @@ cJSON *LP_swap_json(struct LP_swap_remember *rswap)
         jaddstr(item,"alicetomic",rswap->alicetomic);
     if ( rswap->etomicdest[0] != 0 )
         jaddstr(item,"etomicdest",rswap->etomicdest);
+    if ( rswap->alicecontract[0] != 0 )
+        jaddstr(item,"alicecontract",rswap->alicecontract);
     jaddnum(item,"destamount",dstr(rswap->destamount));
     jaddnum(item,"alicetxfee",dstr(rswap->Atxfee));
     jadd64bits(item,"aliceid",rswap->aliceid);
@@ -742,6 +1036,22 @@ cJSON *LP_swap_json(struct LP_swap_remember *rswap)
         jaddnum(item,"finishtime",rswap->finishtime);
     }
     else jaddstr(item,"status","pending");
+    if ( (errjson= LP_swap_errorload(rswap->requestid,rswap->quoteid)) != 0 )
+    {
+        if ( jstr(errjson,"state") != 0 && jstr(errjson,"category") != 0 )
+        {
+            jaddstr(item,"state",jstr(errjson,"state"));
+            jaddnum(item,"error",jint(errjson,"error"));
+            jaddstr(item,"errorcategory",jstr(errjson,"category"));
+            if ( jstr(errjson,"kind") != 0 )
+                jaddstr(item,"errorkind",jstr(errjson,"kind"));
+            if ( jstr(errjson,"side") != 0 )
+                jaddstr(item,"errorside",jstr(errjson,"side"));
+        }
+        free_json(errjson);
+    }
+    if ( (receipt= LP_swap_receiptload(rswap->requestid,rswap->quoteid)) != 0 )
+        jadd(item,"receipt",receipt);
     jaddbits256(item,"bobdeposit",rswap->txids[BASILISK_BOBDEPOSIT]);
     jaddbits256(item,"alicepayment",rswap->txids[BASILISK_ALICEPAYMENT]);
     jaddbits256(item,"bobpayment",rswap->txids[BASILISK_BOBPAYMENT]);
@@ -754,7 +1064,7 @@ cJSON *LP_swap_json(struct LP_swap_remember *rswap)
 
 int32_t LP_rswap_init(struct LP_swap_remember *rswap,uint32_t requestid,uint32_t quoteid,int32_t forceflag)
 {
-    char fname[1024],*fstr,*secretstr,*srcstr,*deststr,*dest33,*txname; long fsize; cJSON *item,*txobj,*array; bits256 privkey; struct iguana_info *coin; uint32_t r,q; int32_t i,j,n; uint8_t other33[33]; uint32_t lockduration;
+    char fname[1024],*fstr,*secretstr,*srcstr,*deststr,*dest33,*txname; long fsize; cJSON *item,*txobj,*array,*timings; bits256 privkey; struct iguana_info *coin; uint32_t r,q; int32_t i,j,n; uint8_t other33[33]; uint32_t lockduration;
     memset(rswap,0,sizeof(*rswap));
     rswap->requestid = requestid;
     rswap->quoteid = quoteid;
@@ -764,6 +1074,8 @@ int32_t LP_rswap_init(struct LP_swap_remember *rswap,uint32_t requestid,uint32_t
         if ( (item= cJSON_Parse(fstr)) != 0 )
         {
             rswap->iambob = jint(item,"iambob");
+            if ( juint(item,"version") > LP_SCHEMA_VERSION )
+                printf("swap %u-%u was written by a newer marketmaker (version %u > %u)\n",requestid,quoteid,juint(item,"version"),LP_SCHEMA_VERSION);
             safecopy(rswap->uuidstr,jstr(item,"uuid"),sizeof(rswap->uuidstr));
             safecopy(rswap->Bgui,jstr(item,"Bgui"),sizeof(rswap->Bgui));
             safecopy(rswap->Agui,jstr(item,"Agui"),sizeof(rswap->Agui));
@@ -811,15 +1123,35 @@ int32_t LP_rswap_init(struct LP_swap_remember *rswap,uint32_t requestid,uint32_t
             }
             if ( (rswap->plocktime= juint(item,"plocktime")) == 0 )
                 rswap->plocktime = LP_extract(requestid,quoteid,fname,"plocktime");
+            rswap->lockduration = juint(item,"lockduration");
+            if ( (timings= jobj(item,"timings")) != 0 )
+            {
+                rswap->loopended = juint(timings,"ended");
+                rswap->looptotal = jdouble(timings,"total");
+            }
+            rswap->uniformspends = juint(item,"uniformspends");
+            rswap->csvrefunds = juint(item,"csvrefunds");
+            rswap->makerfirst = juint(item,"makerfirst");
             if ( (rswap->dlocktime= juint(item,"dlocktime")) == 0 )
                 rswap->dlocktime = LP_extract(requestid,quoteid,fname,"dlocktime");
             r = juint(item,"requestid");
             q = juint(item,"quoteid");
             rswap->Atxfee = j64bits(item,"Atxfee");
             rswap->Btxfee = j64bits(item,"Btxfee");
+            rswap->Aclaimfee = j64bits(item,"Aclaimfee");
+            rswap->Bclaimfee = j64bits(item,"Bclaimfee");
             rswap->pubA0 = jbits256(item,"pubA0");
             rswap->pubB0 = jbits256(item,"pubB0");
             rswap->pubB1 = jbits256(item,"pubB1");
+            rswap->Apaymentheight = jint(item,"Apaymentheight");
+            rswap->Apaymentblock = jbits256(item,"Apaymentblock");
+            if ( (dest33= jstr(item,"claim33")) != 0 && strlen(dest33) == 66 && jstr(item,"claimpath") != 0 )
+            {
+                decode_hex(rswap->claim33,33,dest33);
+                safecopy(rswap->claimpath,jstr(item,"claimpath"),sizeof(rswap->claimpath));
+                if ( (coin= LP_coinfind(rswap->iambob != 0 ? rswap->dest : rswap->src)) != 0 )
+                    LP_hd_swapaddress_restore(coin,rswap->claimpath);
+            }
             privkey = jbits256(item,"myprivs0");
             if ( bits256_nonz(privkey) != 0 )
                 rswap->myprivs[0] = privkey;
@@ -888,7 +1220,8 @@ int32_t LP_rswap_init(struct LP_swap_remember *rswap,uint32_t requestid,uint32_t
             }
             free_json(txobj);
         }
-        lockduration = LP_atomic_locktime(rswap->bobcoin,rswap->alicecoin);
+        if ( (lockduration= rswap->lockduration) == 0 )
+            lockduration = LP_atomic_locktime(rswap->bobcoin,rswap->alicecoin);
         rswap->origfinishedflag = basilisk_swap_isfinished(requestid,quoteid,rswap->expiration,rswap->iambob,rswap->txids,rswap->sentflags,rswap->paymentspent,rswap->Apaymentspent,rswap->depositspent,lockduration);
         rswap->finishedflag = rswap->origfinishedflag;
         if ( forceflag != 0 )
@@ -971,6 +1304,11 @@ int32_t LP_swap_load(struct LP_swap_remember *rswap,int32_t forceflag)
 
                 rswap->bobrealsat = jint(txobj, "bobRealSat");
                 rswap->alicerealsat = jint(txobj, "aliceRealSat");
+                // a swap saved before decimal amounts falls back to its realsat in the etomic builders
+                if ( jstr(txobj,"aliceAmount") == 0 || LP_amount_canonical(rswap->aliceamount,jstr(txobj,"aliceAmount")) < 0 )
+                    rswap->aliceamount[0] = 0;
+                if ( jstr(txobj,"bobAmount") == 0 || LP_amount_canonical(rswap->bobamount,jstr(txobj,"bobAmount")) < 0 )
+                    rswap->bobamount[0] = 0;
 
                 if (jstr(txobj,"aliceFeeEthTx") != 0) {
                     if (rswap->iambob == 0) {
@@ -1005,6 +1343,11 @@ int32_t LP_swap_load(struct LP_swap_remember *rswap,int32_t forceflag)
                     strcpy(rswap->alicetomic, jstr(txobj,"alicetomic"));
                 }
 
+                if ( jstr(txobj,"alicecontract") != 0 )
+                    safecopy(rswap->alicecontract,jstr(txobj,"alicecontract"),sizeof(rswap->alicecontract));
+                if ( jstr(txobj,"bobcontract") != 0 )
+                    safecopy(rswap->bobcontract,jstr(txobj,"bobcontract"),sizeof(rswap->bobcontract));
+
                 rswap->txids[i] = txid;
                 if ( jstr(txobj,"Apayment") != 0 )
                     safecopy(rswap->alicepaymentaddr,jstr(txobj,"Apayment"),sizeof(rswap->alicepaymentaddr));
@@ -1028,6 +1371,11 @@ int32_t LP_swap_load(struct LP_swap_remember *rswap,int32_t forceflag)
                     rswap->Dredeemlen >>= 1;
                     decode_hex(rswap->Dredeemscript,rswap->Dredeemlen,rstr);
                 }
+                else if ( strcmp(txnames[i],"alicepayment") == 0 && (rstr= jstr(txobj,"redeem")) != 0 && (rswap->Aredeemlen= is_hexstr(rstr,0)) > 0 )
+                {
+                    rswap->Aredeemlen >>= 1;
+                    decode_hex(rswap->Aredeemscript,rswap->Aredeemlen,rstr);
+                }
                 rswap->values[i] = value = LP_value_extract(txobj,1,txid);
                 if ( (symbol= jstr(txobj,"src")) != 0 )
                 {
@@ -1095,9 +1443,54 @@ int32_t LP_swap_load(struct LP_swap_remember *rswap,int32_t forceflag)
     return(rswap->finishedflag);
 }
 
-void LP_txbytes_update(char *name,char *symbol,char *txbytes,bits256 *txidp,bits256 *ptr,int32_t *flagp)
+// the spends and refunds go out from here once the swap loop has ended, so their states are logged here and not by LP_swaplog
+void LP_rswap_swaplog(struct LP_swap_remember *rswap,char *statename,char *msg)
+{
+    FILE *fp; char fname[512],tstr[64]; uint64_t millis; double elapsed = 0.;
+    millis = LP_utcmillis();
+    if ( rswap->loopended != 0 )
+    {
+        elapsed = (double)millis - rswap->loopended*1000.;
+        LP_metrics_state(statename,elapsed > 0. ? elapsed : 0.);
+        elapsed += rswap->looptotal;
+    }
+    if ( rswap->uuidstr[0] == 0 )
+        return;
+    sprintf(fname,"%s/SWAPS/%s.log",GLOBAL_DBDIR,rswap->uuidstr), OS_compatible_path(fname);
+    if ( (fp= fopen(fname,"a")) != 0 )
+    {
+        fprintf(fp,"%s.%03d +%.3f %s %u-%u %s: %s\n",utc_str(tstr,(uint32_t)(millis / 1000)),(int32_t)(millis % 1000),elapsed/1000.,rswap->iambob != 0 ? "bob" : "alice",rswap->requestid,rswap->quoteid,statename,msg);
+        fclose(fp);
+    }
+}
+
+// the spend phase runs from the end of the swap loop until LP_swapsloop sees the swap finished. failed swaps were already
+// counted in the phase they failed in, their refunds only add to the failed spend totals
+void LP_rswap_spendtimings(struct LP_swap_remember *rswap)
 {
-    bits256 zero;
+    double phasemillis[LP_SWAPPHASES]; int32_t completed;
+    if ( rswap->loopended == 0 || rswap->finishtime < rswap->loopended )
+        return;
+    if ( rswap->iambob != 0 )
+    {
+        if ( rswap->sentflags[BASILISK_BOBSPEND] == 0 && rswap->sentflags[BASILISK_BOBRECLAIM] == 0 && rswap->sentflags[BASILISK_BOBREFUND] == 0 )
+            return;
+        completed = rswap->sentflags[BASILISK_BOBSPEND];
+    }
+    else
+    {
+        if ( rswap->sentflags[BASILISK_ALICESPEND] == 0 && rswap->sentflags[BASILISK_ALICECLAIM] == 0 && rswap->sentflags[BASILISK_ALICERECLAIM] == 0 )
+            return;
+        completed = rswap->sentflags[BASILISK_ALICESPEND];
+    }
+    memset(phasemillis,0,sizeof(phasemillis));
+    phasemillis[LP_SWAPPHASES-1] = (rswap->finishtime - rswap->loopended) * 1000.;
+    LP_metrics_timings(phasemillis,-1,completed == 0);
+}
+
+void LP_txbytes_update(struct LP_swap_remember *rswap,char *name,char *symbol,char *txbytes,bits256 *txidp,bits256 *ptr,int32_t *flagp)
+{
+    bits256 zero; char str[65];
     memset(zero.bytes,0,sizeof(zero));
     if ( txbytes != 0 )
     {
@@ -1107,10 +1500,61 @@ void LP_txbytes_update(char *name,char *symbol,char *txbytes,bits256 *txidp,bits
             *flagp = 1;
             if ( ptr != 0 )
                 *ptr = *txidp;
+            LP_rswap_swaplog(rswap,name,bits256_str(str,*txidp));
         }
     }
 }
 
+#define LP_REFUNDFAIL_INTERVAL 3600
+
+struct LP_refundfail { struct LP_refundfail *next,*prev; uint32_t requestid,quoteid,lastsent; } *LP_refundfails;
+portable_mutex_t LP_refundfailmutex;
+
+// basilisk_remember runs on every LP_swapsloop pass, a refund that keeps failing to broadcast is reported once an hour
+void LP_refundfail_msg(struct LP_swap_remember *rswap,int32_t err)
+{
+    struct LP_refundfail *rp; uint32_t now = (uint32_t)time(NULL); int32_t sendflag = 0;
+    portable_mutex_lock(&LP_refundfailmutex);
+    DL_FOREACH(LP_refundfails,rp)
+        if ( rp->requestid == rswap->requestid && rp->quoteid == rswap->quoteid )
+            break;
+    if ( rp == 0 && (rp= calloc(1,sizeof(*rp))) != 0 )
+    {
+        rp->requestid = rswap->requestid;
+        rp->quoteid = rswap->quoteid;
+        DL_APPEND(LP_refundfails,rp);
+    }
+    if ( rp != 0 && now >= rp->lastsent + LP_REFUNDFAIL_INTERVAL )
+    {
+        rp->lastsent = now;
+        sendflag = 1;
+    }
+    portable_mutex_unlock(&LP_refundfailmutex);
+    if ( sendflag != 0 )
+        LP_failedmsg_category(rswap->requestid,rswap->quoteid,err,rswap->uuidstr,"broadcast");
+}
+
+// matches basilisk_bobscript: putduration relative to the bobpayment confirming, the bobdeposit stays on its absolute locktime
+uint32_t LP_rswap_csvduration(struct LP_swap_remember *rswap,int32_t depositflag)
+{
+    if ( rswap->csvrefunds == 0 || rswap->lockduration == 0 || depositflag != 0 )
+        return(0);
+    else return(rswap->lockduration);
+}
+
+// a csv bobpayment unlocks relative to the block that confirmed it, plocktime (swap start + putduration) says nothing about that
+int32_t LP_rswap_reclaimready(struct LP_swap_remember *rswap,struct iguana_info *coin)
+{
+    int32_t height; uint32_t duration,heighttime;
+    if ( rswap->makerfirst != 0 )
+        return(1);
+    else if ( (duration= LP_rswap_csvduration(rswap,0)) == 0 )
+        return(LP_now() > rswap->plocktime+777);
+    else if ( (height= LP_txheight(coin,rswap->txids[BASILISK_BOBPAYMENT])) <= 0 || (heighttime= LP_heighttime(coin->symbol,height)) == 0 )
+        return(0);
+    return(LP_now() > heighttime + (LP_swapops_csvsequence(duration) & 0xffff)*512 + 777);
+}
+
 int32_t LP_rswap_checktx(struct LP_swap_remember *rswap,char *symbol,int32_t txi)
 {
     int32_t ht; struct iguana_info *coin; //char str[65];
@@ -1138,7 +1582,9 @@ int32_t LP_spends_set(struct LP_swap_remember *rswap)
             rswap->paymentspent = rswap->txids[BASILISK_ALICESPEND];
         else rswap->paymentspent = rswap->txids[BASILISK_BOBRECLAIM];
     } else numspent++;
-    if ( bits256_nonz(rswap->depositspent) == 0 )
+    if ( rswap->makerfirst != 0 && bits256_nonz(rswap->txids[BASILISK_BOBDEPOSIT]) == 0 ) // maker pays first has no bobdeposit
+        numspent++;
+    else if ( bits256_nonz(rswap->depositspent) == 0 )
     {
         if ( bits256_nonz(rswap->txids[BASILISK_BOBREFUND]) != 0 )
             rswap->depositspent = rswap->txids[BASILISK_BOBREFUND];
@@ -1153,10 +1599,79 @@ int32_t LP_spends_set(struct LP_swap_remember *rswap)
     return(numspent);
 }
 
+// maker pays first: bobspend reveals privBn byte reversed, anything else spending alicepayment wont match secretBn
+bits256 LP_rswap_makerfirst_privBn(struct LP_swap_remember *rswap)
+{
+    bits256 privBn,spendtxid; uint8_t secretBn[20];
+    memset(privBn.bytes,0,sizeof(privBn));
+    if ( bits256_nonz(rswap->privBn) != 0 )
+        return(rswap->privBn);
+    spendtxid = rswap->txids[BASILISK_BOBSPEND];
+    if ( bits256_nonz(spendtxid) == 0 )
+        spendtxid = rswap->Apaymentspent;
+    if ( bits256_nonz(spendtxid) != 0 )
+    {
+        privBn = basilisk_swap_privbob_extract(rswap->alicecoin,spendtxid,0,1);
+        revcalc_rmd160_sha256(secretBn,privBn);
+        if ( memcmp(secretBn,rswap->secretBn,sizeof(secretBn)) != 0 )
+            memset(privBn.bytes,0,sizeof(privBn));
+    }
+    return(privBn);
+}
+
+int32_t LP_rswap_makerfirstscript(struct LP_swap_remember *rswap,int32_t takerflag,uint8_t *redeemscript)
+{
+    if ( takerflag != 0 && rswap->Aredeemlen > 0 )
+    {
+        memcpy(redeemscript,rswap->Aredeemscript,rswap->Aredeemlen);
+        return(rswap->Aredeemlen);
+    }
+    else if ( takerflag == 0 && rswap->Predeemlen > 0 )
+    {
+        memcpy(redeemscript,rswap->Predeemscript,rswap->Predeemlen);
+        return(rswap->Predeemlen);
+    }
+    else if ( takerflag == 0 && rswap->plocktime != 0 )
+        return(basilisk_swap_makerfirstscript(0,0,redeemscript,rswap->plocktime,rswap->pubA0,rswap->pubB0,rswap->pubB1,rswap->privBn,rswap->secretBn));
+    return(-1);
+}
+
+// alicespend of bobpayment or bobspend of alicepayment, both through the secretBn path
+char *LP_rswap_makerfirst_spend(void *ctx,struct LP_swap_remember *rswap,struct iguana_info *coin,int32_t takerflag,uint8_t *claim33)
+{
+    uint8_t redeemscript[1024],userdata[1024]; int32_t i,len,redeemlen,txi; bits256 revBn,signedtxid;
+    txi = (takerflag != 0) ? BASILISK_ALICEPAYMENT : BASILISK_BOBPAYMENT;
+    if ( bits256_nonz(rswap->txids[txi]) == 0 || bits256_nonz(rswap->privBn) == 0 || (redeemlen= LP_rswap_makerfirstscript(rswap,takerflag,redeemscript)) <= 0 )
+        return(0);
+    for (i=0; i<32; i++)
+        revBn.bytes[i] = rswap->privBn.bytes[31-i];
+    len = basilisk_swapuserdata(userdata,revBn,0,rswap->myprivs[0],redeemscript,redeemlen);
+    if ( takerflag != 0 )
+        return(basilisk_swap_bobtxspend(&signedtxid,rswap->Aclaimfee,"bobspend",coin->symbol,coin->wiftaddr,coin->taddr,coin->pubtype,coin->p2shtype,coin->isPoS,coin->wiftype,ctx,rswap->myprivs[0],0,redeemscript,redeemlen,userdata,len,rswap->txids[txi],0,0,claim33,1,0,&rswap->values[BASILISK_BOBSPEND],0,0,rswap->alicepaymentaddr,1,coin->zcash));
+    else return(basilisk_swap_bobtxspend(&signedtxid,rswap->Bclaimfee,"alicespend",coin->symbol,coin->wiftaddr,coin->taddr,coin->pubtype,coin->p2shtype,coin->isPoS,coin->wiftype,ctx,rswap->myprivs[0],0,redeemscript,redeemlen,userdata,len,rswap->txids[txi],0,0,claim33,1,0,&rswap->values[BASILISK_ALICESPEND],0,0,rswap->bobpaymentaddr,1,coin->zcash));
+}
+
+// alicereclaim or bobreclaim through the CLTV path, nothing to sign until the chain passes the script locktime
+char *LP_rswap_makerfirst_reclaim(void *ctx,struct LP_swap_remember *rswap,struct iguana_info *coin,int32_t takerflag)
+{
+    static bits256 zero;
+    uint8_t redeemscript[1024],userdata[1024]; int32_t len,redeemlen,txi; uint32_t locktime,claimtime; bits256 privkey,signedtxid;
+    txi = (takerflag != 0) ? BASILISK_ALICEPAYMENT : BASILISK_BOBPAYMENT;
+    if ( bits256_nonz(rswap->txids[txi]) == 0 || (redeemlen= LP_rswap_makerfirstscript(rswap,takerflag,redeemscript)) <= 0 )
+        return(0);
+    if ( (locktime= basilisk_cltvscript(redeemscript,redeemlen)) == 0 || LP_now() <= locktime || (claimtime= LP_claimtime(coin,locktime+1)) == 0 )
+        return(0);
+    privkey = rswap->myprivs[(takerflag != 0) ? 0 : 1];
+    len = basilisk_swapuserdata(userdata,zero,1,privkey,redeemscript,redeemlen);
+    if ( takerflag != 0 )
+        return(basilisk_swap_bobtxspend(&signedtxid,rswap->Aclaimfee,"alicereclaim",coin->symbol,coin->wiftaddr,coin->taddr,coin->pubtype,coin->p2shtype,coin->isPoS,coin->wiftype,ctx,privkey,0,redeemscript,redeemlen,userdata,len,rswap->txids[txi],0,0,rswap->pubkey33,0,claimtime,&rswap->values[BASILISK_ALICERECLAIM],0,0,rswap->alicepaymentaddr,1,coin->zcash));
+    else return(basilisk_swap_bobtxspend(&signedtxid,rswap->Bclaimfee,"bobreclaim",coin->symbol,coin->wiftaddr,coin->taddr,coin->pubtype,coin->p2shtype,coin->isPoS,coin->wiftype,ctx,privkey,0,redeemscript,redeemlen,userdata,len,rswap->txids[txi],0,0,rswap->pubkey33,0,claimtime,&rswap->values[BASILISK_BOBRECLAIM],0,0,rswap->bobpaymentaddr,1,coin->zcash));
+}
+
 cJSON *basilisk_remember(int32_t fastflag,int64_t *KMDtotals,int64_t *BTCtotals,uint32_t requestid,uint32_t quoteid,int32_t forceflag,int32_t pendingonly)
 {
     static void *ctx;
-    struct LP_swap_remember rswap; int32_t i,j,flag,numspent,len,secretstart,redeemlen; char str[65],*srcAdest,*srcBdest,*destAdest,*destBdest,otheraddr[64],*fstr,fname[512],bobtomic[128],alicetomic[128],bobstr[65],alicestr[65]; cJSON *item,*txoutobj,*retjson; bits256 rev,revAm,signedtxid,zero,deadtxid; uint32_t claimtime,lockduration; struct iguana_info *bob=0,*alice=0; uint8_t redeemscript[1024],userdata[1024]; long fsize;
+    struct LP_swap_remember rswap; int32_t i,j,flag,sentflag,numspent,len,secretstart,redeemlen; char str[65],*srcAdest,*srcBdest,*destAdest,*destBdest,otheraddr[64],*fstr,fname[512],bobtomic[128],alicetomic[128],bobstr[65],alicestr[65]; cJSON *item,*txoutobj,*retjson; bits256 rev,revAm,signedtxid,zero,deadtxid; uint32_t claimtime,lockduration; struct iguana_info *bob=0,*alice=0; uint8_t redeemscript[1024],userdata[1024],*claim33; long fsize;
     sprintf(fname,"%s/SWAPS/%u-%u.finished",GLOBAL_DBDIR,requestid,quoteid), OS_compatible_path(fname);
     if ( (fstr= OS_filestr(&fsize,fname)) != 0 )
     {
@@ -1179,13 +1694,14 @@ cJSON *basilisk_remember(int32_t fastflag,int64_t *KMDtotals,int64_t *BTCtotals,
     LP_swap_load(&rswap,forceflag);
     memset(zero.bytes,0,sizeof(zero));
     otheraddr[0] = 0;
-    claimtime = (uint32_t)time(NULL) - 777;
+    claimtime = LP_now() - 777;
     srcAdest = srcBdest = destAdest = destBdest = 0;
     alice = LP_coinfind(rswap.alicecoin);
     bob = LP_coinfind(rswap.bobcoin);
     LP_etomicsymbol(bobstr,bobtomic,rswap.src);
     LP_etomicsymbol(alicestr,alicetomic,rswap.dest);
-    lockduration = LP_atomic_locktime(rswap.bobcoin,rswap.alicecoin);
+    if ( (lockduration= rswap.lockduration) == 0 )
+        lockduration = LP_atomic_locktime(rswap.bobcoin,rswap.alicecoin);
     if ( rswap.bobcoin[0] == 0 || rswap.alicecoin[0] == 0 || strcmp(rswap.bobcoin,bobstr) != 0 || strcmp(rswap.alicecoin,alicestr) != 0 )
     {
         //printf("legacy r%u-q%u DB SWAPS.(%u %u) %llu files BOB.(%s) Alice.(%s) src.(%s) dest.(%s)\n",requestid,quoteid,rswap.requestid,rswap.quoteid,(long long)rswap.aliceid,rswap.bobcoin,rswap.alicecoin,rswap.src,rswap.dest);
@@ -1204,6 +1720,12 @@ cJSON *basilisk_remember(int32_t fastflag,int64_t *KMDtotals,int64_t *BTCtotals,
     }
     rswap.Atxfee = LP_txfeecalc(alice,rswap.Atxfee,0);
     rswap.Btxfee = LP_txfeecalc(bob,rswap.Btxfee,0);
+    // the spends of the htlc outputs pay the negotiated claim fees, older swap files only have the txfees
+    if ( rswap.Aclaimfee < rswap.Atxfee )
+        rswap.Aclaimfee = rswap.Atxfee;
+    if ( rswap.Bclaimfee < rswap.Btxfee )
+        rswap.Bclaimfee = rswap.Btxfee;
+    claim33 = rswap.claimpath[0] != 0 ? rswap.claim33 : rswap.pubkey33;
     if ( rswap.iambob == 0 )
     {
         if ( alice != 0 )
@@ -1240,7 +1762,7 @@ cJSON *basilisk_remember(int32_t fastflag,int64_t *KMDtotals,int64_t *BTCtotals,
         }
         if ( (bob= LP_coinfind(rswap.bobcoin)) != 0 )
         {
-            bitcoin_address(bob->symbol,rswap.Sdestaddr,bob->taddr,bob->pubtype,rswap.pubkey33,33);
+            bitcoin_address(bob->symbol,rswap.Sdestaddr,bob->taddr,bob->pubtype,claim33,33);
             srcAdest = rswap.Sdestaddr;
         }
         srcBdest = rswap.destaddr;
@@ -1281,7 +1803,7 @@ cJSON *basilisk_remember(int32_t fastflag,int64_t *KMDtotals,int64_t *BTCtotals,
         }
         if ( (alice= LP_coinfind(rswap.alicecoin)) != 0 )
         {
-            bitcoin_address(alice->symbol,rswap.Sdestaddr,alice->taddr,alice->pubtype,rswap.pubkey33,33);
+            bitcoin_address(alice->symbol,rswap.Sdestaddr,alice->taddr,alice->pubtype,claim33,33);
             destBdest = rswap.Sdestaddr;
         }
         destAdest = rswap.Adestaddr;
@@ -1328,13 +1850,18 @@ cJSON *basilisk_remember(int32_t fastflag,int64_t *KMDtotals,int64_t *BTCtotals,
                     }
                     if ( flag == 0 )
                     {
-                        if ( bits256_nonz(rswap.txids[BASILISK_BOBPAYMENT]) != 0 )
+                        if ( rswap.makerfirst != 0 )
+                        {
+                            rswap.privBn = LP_rswap_makerfirst_privBn(&rswap);
+                            rswap.txbytes[BASILISK_ALICESPEND] = LP_rswap_makerfirst_spend(ctx,&rswap,bob,0,claim33);
+                        }
+                        else if ( bits256_nonz(rswap.txids[BASILISK_BOBPAYMENT]) != 0 )
                         {
                             // alicespend
                             memset(rev.bytes,0,sizeof(rev));
                             for (j=0; j<32; j++)
                                 rev.bytes[j] = rswap.privAm.bytes[31 - j];
-                            redeemlen = basilisk_swap_bobredeemscript(0,&secretstart,redeemscript,rswap.plocktime,rswap.pubA0,rswap.pubB0,rswap.pubB1,rev,rswap.privBn,rswap.secretAm,rswap.secretAm256,rswap.secretBn,rswap.secretBn256);
+                            redeemlen = basilisk_swap_bobredeemscript(0,rswap.uniformspends,&secretstart,redeemscript,rswap.plocktime,LP_rswap_csvduration(&rswap,0),rswap.pubA0,rswap.pubB0,rswap.pubB1,rev,rswap.privBn,rswap.secretAm,rswap.secretAm256,rswap.secretBn,rswap.secretBn256);
                             if ( rswap.Predeemlen != 0 )
                             {
                                 if ( rswap.Predeemlen != redeemlen || memcmp(redeemscript,rswap.Predeemscript,redeemlen) != 0 )
@@ -1351,7 +1878,7 @@ cJSON *basilisk_remember(int32_t fastflag,int64_t *KMDtotals,int64_t *BTCtotals,
                                 printf(" secretAm, privAm %s alicespend len.%d redeemlen.%d\n",bits256_str(str,rswap.privAm),len,redeemlen);
                             }
                             claimtime = LP_claimtime(bob,rswap.plocktime - 777);
-                            if ( (rswap.txbytes[BASILISK_ALICESPEND]= basilisk_swap_bobtxspend(&signedtxid,rswap.Btxfee,"alicespend",rswap.bobcoin,bob->wiftaddr,bob->taddr,bob->pubtype,bob->p2shtype,bob->isPoS,bob->wiftype,ctx,rswap.myprivs[0],0,redeemscript,redeemlen,userdata,len,rswap.txids[BASILISK_BOBPAYMENT],0,0,rswap.pubkey33,1,claimtime,&rswap.values[BASILISK_ALICESPEND],0,0,rswap.bobpaymentaddr,1,bob->zcash)) != 0 )
+                            if ( (rswap.txbytes[BASILISK_ALICESPEND]= basilisk_swap_bobtxspend(&signedtxid,rswap.Bclaimfee,"alicespend",rswap.bobcoin,bob->wiftaddr,bob->taddr,bob->pubtype,bob->p2shtype,bob->isPoS,bob->wiftype,ctx,rswap.myprivs[0],0,redeemscript,redeemlen,userdata,len,rswap.txids[BASILISK_BOBPAYMENT],0,0,claim33,1,claimtime,&rswap.values[BASILISK_ALICESPEND],0,0,rswap.bobpaymentaddr,1,bob->zcash)) != 0 )
                             {
                                 //printf("alicespend.(%s)\n",rswap.txbytes[BASILISK_ALICESPEND]);
 #ifndef NOTETOMIC
@@ -1369,13 +1896,13 @@ cJSON *basilisk_remember(int32_t fastflag,int64_t *KMDtotals,int64_t *BTCtotals,
 #endif
                             }
                         }
-                        LP_txbytes_update("alicespend",rswap.bobcoin,rswap.txbytes[BASILISK_ALICESPEND],&rswap.txids[BASILISK_ALICESPEND],&rswap.paymentspent,&rswap.sentflags[BASILISK_ALICESPEND]);
+                        LP_txbytes_update(&rswap,"alicespend",rswap.bobcoin,rswap.txbytes[BASILISK_ALICESPEND],&rswap.txids[BASILISK_ALICESPEND],&rswap.paymentspent,&rswap.sentflags[BASILISK_ALICESPEND]);
                     }
                 }
             }
             if ( rswap.sentflags[BASILISK_ALICECLAIM] == 0 && (rswap.sentflags[BASILISK_BOBDEPOSIT] != 0 || bits256_nonz(rswap.txids[BASILISK_BOBDEPOSIT]) != 0) && bits256_nonz(rswap.depositspent) == 0 )
             {
-                if ( time(NULL) > rswap.dlocktime+777 )
+                if ( LP_now() > rswap.dlocktime+777 )
                 {
                     flag = 0;
                     if ( bob->electrum == 0 )
@@ -1389,7 +1916,7 @@ cJSON *basilisk_remember(int32_t fastflag,int64_t *KMDtotals,int64_t *BTCtotals,
                         if ( rswap.Dredeemlen != 0 )
                             redeemlen = rswap.Dredeemlen, memcpy(redeemscript,rswap.Dredeemscript,rswap.Dredeemlen);
                         else
-                            redeemlen = basilisk_swap_bobredeemscript(1,&secretstart,redeemscript,rswap.dlocktime,rswap.pubA0,rswap.pubB0,rswap.pubB1,rswap.privAm,zero,rswap.secretAm,rswap.secretAm256,rswap.secretBn,rswap.secretBn256);
+                            redeemlen = basilisk_swap_bobredeemscript(1,rswap.uniformspends,&secretstart,redeemscript,rswap.dlocktime,LP_rswap_csvduration(&rswap,1),rswap.pubA0,rswap.pubB0,rswap.pubB1,rswap.privAm,zero,rswap.secretAm,rswap.secretAm256,rswap.secretBn,rswap.secretBn256);
                         /*if ( rswap.Dredeemlen != 0 )
                         {
                             if ( rswap.Dredeemlen != redeemlen || memcmp(redeemscript,rswap.Dredeemscript,redeemlen) != 0 )
@@ -1402,7 +1929,7 @@ cJSON *basilisk_remember(int32_t fastflag,int64_t *KMDtotals,int64_t *BTCtotals,
                                 revAm.bytes[i] = rswap.privAm.bytes[31-i];
                             len = basilisk_swapuserdata(userdata,revAm,1,rswap.myprivs[0],redeemscript,redeemlen);
                             claimtime = LP_claimtime(bob,rswap.dlocktime);
-                            if ( (rswap.txbytes[BASILISK_ALICECLAIM]= basilisk_swap_bobtxspend(&signedtxid,rswap.Btxfee,"aliceclaim",rswap.bobcoin,bob->wiftaddr,bob->taddr,bob->pubtype,bob->p2shtype,bob->isPoS,bob->wiftype,ctx,rswap.myprivs[0],0,redeemscript,redeemlen,userdata,len,rswap.txids[BASILISK_BOBDEPOSIT],0,0,rswap.pubkey33,0,claimtime,&rswap.values[BASILISK_ALICECLAIM],0,0,rswap.bobdepositaddr,1,bob->zcash)) != 0 )
+                            if ( (rswap.txbytes[BASILISK_ALICECLAIM]= basilisk_swap_bobtxspend(&signedtxid,rswap.Bclaimfee,"aliceclaim",rswap.bobcoin,bob->wiftaddr,bob->taddr,bob->pubtype,bob->p2shtype,bob->isPoS,bob->wiftype,ctx,rswap.myprivs[0],0,redeemscript,redeemlen,userdata,len,rswap.txids[BASILISK_BOBDEPOSIT],0,0,claim33,0,claimtime,&rswap.values[BASILISK_ALICECLAIM],0,0,rswap.bobdepositaddr,1,bob->zcash)) != 0 )
                             {
                                 //printf("dlocktime.%u claimtime.%u aliceclaim.(%s)\n",rswap.dlocktime,claimtime,rswap.txbytes[BASILISK_ALICECLAIM]);
 #ifndef NOTETOMIC
@@ -1420,7 +1947,7 @@ cJSON *basilisk_remember(int32_t fastflag,int64_t *KMDtotals,int64_t *BTCtotals,
 #endif
                             }
                         }
-                        LP_txbytes_update("aliceclaim",rswap.bobcoin,rswap.txbytes[BASILISK_ALICECLAIM],&rswap.txids[BASILISK_ALICECLAIM],&rswap.depositspent,&rswap.sentflags[BASILISK_ALICECLAIM]);
+                        LP_txbytes_update(&rswap,"aliceclaim",rswap.bobcoin,rswap.txbytes[BASILISK_ALICECLAIM],&rswap.txids[BASILISK_ALICECLAIM],&rswap.depositspent,&rswap.sentflags[BASILISK_ALICECLAIM]);
                     }
                 } //else printf("now %u before expiration %u\n",(uint32_t)time(NULL),rswap.expiration);
             }
@@ -1435,10 +1962,12 @@ cJSON *basilisk_remember(int32_t fastflag,int64_t *KMDtotals,int64_t *BTCtotals,
                 }
                 if ( flag == 0 )
                 {
-                    rswap.privBn = basilisk_swap_privBn_extract(&rswap.txids[BASILISK_BOBREFUND],rswap.bobcoin,rswap.txids[BASILISK_BOBDEPOSIT],rswap.privBn);
-                    if ( bits256_nonz(rswap.txids[BASILISK_ALICEPAYMENT]) != 0 && bits256_nonz(rswap.privAm) != 0 && bits256_nonz(rswap.privBn) != 0 )
+                    if ( rswap.makerfirst != 0 )
+                        rswap.txbytes[BASILISK_ALICERECLAIM] = LP_rswap_makerfirst_reclaim(ctx,&rswap,alice,1);
+                    else rswap.privBn = basilisk_swap_privBn_extract(&rswap.txids[BASILISK_BOBREFUND],rswap.bobcoin,rswap.txids[BASILISK_BOBDEPOSIT],rswap.privBn);
+                    if ( rswap.makerfirst == 0 && bits256_nonz(rswap.txids[BASILISK_ALICEPAYMENT]) != 0 && bits256_nonz(rswap.privAm) != 0 && bits256_nonz(rswap.privBn) != 0 )
                     {
-                        if ( (rswap.txbytes[BASILISK_ALICERECLAIM]= basilisk_swap_Aspend("alicereclaim",rswap.alicecoin,rswap.Atxfee,alice->wiftaddr,alice->taddr,alice->pubtype,alice->p2shtype,alice->isPoS,alice->wiftype,ctx,rswap.privAm,rswap.privBn,rswap.txids[BASILISK_ALICEPAYMENT],0,rswap.pubkey33,rswap.expiration,&rswap.values[BASILISK_ALICERECLAIM],rswap.alicepaymentaddr,alice->zcash)) != 0 ) {
+                        if ( (rswap.txbytes[BASILISK_ALICERECLAIM]= basilisk_swap_Aspend("alicereclaim",rswap.alicecoin,rswap.Aclaimfee,alice->wiftaddr,alice->taddr,alice->pubtype,alice->p2shtype,alice->isPoS,alice->wiftype,ctx,rswap.privAm,rswap.privBn,rswap.txids[BASILISK_ALICEPAYMENT],0,rswap.pubkey33,rswap.expiration,&rswap.values[BASILISK_ALICERECLAIM],rswap.alicepaymentaddr,alice->zcash)) != 0 ) {
                             printf("alicereclaim.(%s)\n", rswap.txbytes[BASILISK_ALICERECLAIM]);
 #ifndef NOTETOMIC
                             if ( rswap.alicetomic[0] != 0 )
@@ -1455,7 +1984,12 @@ cJSON *basilisk_remember(int32_t fastflag,int64_t *KMDtotals,int64_t *BTCtotals,
 #endif
                         }
                     }
-                    LP_txbytes_update("alicereclaim",rswap.alicecoin,rswap.txbytes[BASILISK_ALICERECLAIM],&rswap.txids[BASILISK_ALICERECLAIM],&rswap.Apaymentspent,&rswap.sentflags[BASILISK_ALICERECLAIM]);
+                    sentflag = rswap.sentflags[BASILISK_ALICERECLAIM];
+                    LP_txbytes_update(&rswap,"alicereclaim",rswap.alicecoin,rswap.txbytes[BASILISK_ALICERECLAIM],&rswap.txids[BASILISK_ALICERECLAIM],&rswap.Apaymentspent,&rswap.sentflags[BASILISK_ALICERECLAIM]);
+                    if ( sentflag == 0 && rswap.sentflags[BASILISK_ALICERECLAIM] != 0 )
+                        LP_metrics_swap(rswap.bobcoin,rswap.alicecoin,"refunded");
+                    if ( rswap.txbytes[BASILISK_ALICERECLAIM] != 0 && rswap.sentflags[BASILISK_ALICERECLAIM] == 0 )
+                        LP_refundfail_msg(&rswap,-6001);
                 }
             }
         }
@@ -1464,10 +1998,18 @@ cJSON *basilisk_remember(int32_t fastflag,int64_t *KMDtotals,int64_t *BTCtotals,
             if ( rswap.sentflags[BASILISK_BOBSPEND] == 0 && bits256_nonz(rswap.Apaymentspent) == 0 )
             {
                 //printf("try to bobspend aspend.%s have privAm.%d aspent.%d\n",bits256_str(str,rswap.txids[BASILISK_ALICESPEND]),bits256_nonz(rswap.privAm),rswap.sentflags[BASILISK_ALICESPEND]);
-                if ( rswap.sentflags[BASILISK_ALICESPEND] != 0 || bits256_nonz(rswap.paymentspent) != 0 || bits256_nonz(rswap.privAm) != 0 || bits256_nonz(rswap.depositspent) != 0 )
+                if ( (rswap.makerfirst != 0 && bits256_nonz(rswap.txids[BASILISK_ALICEPAYMENT]) != 0) || rswap.sentflags[BASILISK_ALICESPEND] != 0 || bits256_nonz(rswap.paymentspent) != 0 || bits256_nonz(rswap.privAm) != 0 || bits256_nonz(rswap.depositspent) != 0 )
                 {
                     flag = 0;
-                    if ( alice->electrum == 0 )
+                    if ( rswap.Apaymentheight > 0 && LP_swap_reorgcheck(rswap.alicecoin,rswap.alicepaymentaddr,rswap.txids[BASILISK_ALICEPAYMENT],rswap.Apaymentheight,rswap.Apaymentblock) <= 0 )
+                    {
+                        // bobpayment is already out, so all bob can do is wait for alicepayment to be mined again
+                        printf("%u-%u alicepayment %s confirmed at ht.%d is no longer in the chain, holding bobspend\n",rswap.requestid,rswap.quoteid,bits256_str(str,rswap.txids[BASILISK_ALICEPAYMENT]),rswap.Apaymentheight);
+                        if ( LP_now() > rswap.plocktime )
+                            LP_failedmsg_category(rswap.requestid,rswap.quoteid,-2016,rswap.uuidstr,"reorg");
+                        flag = -1;
+                    }
+                    else if ( alice->electrum == 0 )
                     {
                         if ( (txoutobj= LP_gettxout(rswap.alicecoin,rswap.alicepaymentaddr,rswap.txids[BASILISK_ALICEPAYMENT],0)) != 0 )
                             free_json(txoutobj), flag = 0;
@@ -1476,7 +2018,9 @@ cJSON *basilisk_remember(int32_t fastflag,int64_t *KMDtotals,int64_t *BTCtotals,
                     //printf("flag.%d apayment.%s\n",flag,bits256_str(str,rswap.paymentspent));
                     if ( flag == 0 )
                     {
-                        if ( bits256_nonz(rswap.privAm) == 0 )
+                        if ( rswap.makerfirst != 0 )
+                            rswap.txbytes[BASILISK_BOBSPEND] = LP_rswap_makerfirst_spend(ctx,&rswap,alice,1,claim33);
+                        else if ( bits256_nonz(rswap.privAm) == 0 )
                         {
                             rswap.privAm = basilisk_swap_privbob_extract(rswap.bobcoin,rswap.paymentspent,0,1);
                             if ( bits256_nonz(rswap.privAm) == 0 && bits256_nonz(rswap.depositspent) != 0 )
@@ -1485,9 +2029,9 @@ cJSON *basilisk_remember(int32_t fastflag,int64_t *KMDtotals,int64_t *BTCtotals,
                                 //printf("try to bobspend aspend.%s have privAm.%d\n",bits256_str(str,rswap.depositspent),bits256_nonz(rswap.privAm));
                             }
                         }
-                        if ( bits256_nonz(rswap.privAm) != 0 && bits256_nonz(rswap.privBn) != 0 )
+                        if ( rswap.makerfirst == 0 && bits256_nonz(rswap.privAm) != 0 && bits256_nonz(rswap.privBn) != 0 )
                         {
-                            if ( (rswap.txbytes[BASILISK_BOBSPEND]= basilisk_swap_Aspend("bobspend",rswap.alicecoin,rswap.Atxfee,alice->wiftaddr,alice->taddr,alice->pubtype,alice->p2shtype,alice->isPoS,alice->wiftype,ctx,rswap.privAm,rswap.privBn,rswap.txids[BASILISK_ALICEPAYMENT],0,rswap.pubkey33,rswap.expiration,&rswap.values[BASILISK_BOBSPEND],rswap.alicepaymentaddr,alice->zcash)) != 0 )
+                            if ( (rswap.txbytes[BASILISK_BOBSPEND]= basilisk_swap_Aspend("bobspend",rswap.alicecoin,rswap.Aclaimfee,alice->wiftaddr,alice->taddr,alice->pubtype,alice->p2shtype,alice->isPoS,alice->wiftype,ctx,rswap.privAm,rswap.privBn,rswap.txids[BASILISK_ALICEPAYMENT],0,claim33,rswap.expiration,&rswap.values[BASILISK_BOBSPEND],rswap.alicepaymentaddr,alice->zcash)) != 0 )
                             {
 #ifndef NOTETOMIC
                                 if ( rswap.alicetomic[0] != 0 )
@@ -1505,7 +2049,7 @@ cJSON *basilisk_remember(int32_t fastflag,int64_t *KMDtotals,int64_t *BTCtotals,
                                 //printf("bobspend.(%s)\n",rswap.txbytes[BASILISK_BOBSPEND]);
                             }
                         }
-                        LP_txbytes_update("bobspend",rswap.alicecoin,rswap.txbytes[BASILISK_BOBSPEND],&rswap.txids[BASILISK_BOBSPEND],&rswap.Apaymentspent,&rswap.sentflags[BASILISK_BOBSPEND]);
+                        LP_txbytes_update(&rswap,"bobspend",rswap.alicecoin,rswap.txbytes[BASILISK_BOBSPEND],&rswap.txids[BASILISK_BOBSPEND],&rswap.Apaymentspent,&rswap.sentflags[BASILISK_BOBSPEND]);
                     }
                 }
             }
@@ -1518,15 +2062,17 @@ cJSON *basilisk_remember(int32_t fastflag,int64_t *KMDtotals,int64_t *BTCtotals,
                         free_json(txoutobj), flag = 0;
                     else flag = -1, rswap.paymentspent = deadtxid;
                 }
-                if ( flag == 0 && time(NULL) > rswap.plocktime+777 )
+                if ( flag == 0 && LP_rswap_reclaimready(&rswap,bob) != 0 )
                 {
                     // bobreclaim
-                    redeemlen = basilisk_swap_bobredeemscript(0,&secretstart,redeemscript,rswap.plocktime,rswap.pubA0,rswap.pubB0,rswap.pubB1,zero,rswap.privBn,rswap.secretAm,rswap.secretAm256,rswap.secretBn,rswap.secretBn256);
+                    if ( rswap.makerfirst != 0 )
+                        rswap.txbytes[BASILISK_BOBRECLAIM] = LP_rswap_makerfirst_reclaim(ctx,&rswap,bob,0), redeemlen = 0;
+                    else redeemlen = basilisk_swap_bobredeemscript(0,rswap.uniformspends,&secretstart,redeemscript,rswap.plocktime,LP_rswap_csvduration(&rswap,0),rswap.pubA0,rswap.pubB0,rswap.pubB1,zero,rswap.privBn,rswap.secretAm,rswap.secretAm256,rswap.secretBn,rswap.secretBn256);
                     if ( redeemlen > 0 )
                     {
                         len = basilisk_swapuserdata(userdata,zero,1,rswap.myprivs[1],redeemscript,redeemlen);
                         claimtime = LP_claimtime(bob,rswap.plocktime - 777);
-                        if ( (rswap.txbytes[BASILISK_BOBRECLAIM]= basilisk_swap_bobtxspend(&signedtxid,rswap.Btxfee,"bobreclaim",rswap.bobcoin,bob->wiftaddr,bob->taddr,bob->pubtype,bob->p2shtype,bob->isPoS,bob->wiftype,ctx,rswap.myprivs[1],0,redeemscript,redeemlen,userdata,len,rswap.txids[BASILISK_BOBPAYMENT],0,0,rswap.pubkey33,0,claimtime,&rswap.values[BASILISK_BOBRECLAIM],0,0,rswap.bobpaymentaddr,1,bob->zcash)) != 0 )
+                        if ( (rswap.txbytes[BASILISK_BOBRECLAIM]= basilisk_swap_bobtxspend(&signedtxid,rswap.Bclaimfee,"bobreclaim",rswap.bobcoin,bob->wiftaddr,bob->taddr,bob->pubtype,bob->p2shtype,bob->isPoS,bob->wiftype,ctx,rswap.myprivs[1],0,redeemscript,redeemlen,userdata,len,rswap.txids[BASILISK_BOBPAYMENT],0,0,rswap.pubkey33,0,claimtime,&rswap.values[BASILISK_BOBRECLAIM],0,0,rswap.bobpaymentaddr,1,bob->zcash)) != 0 )
                         {
 #ifndef NOTETOMIC
                             if ( rswap.bobtomic[0] != 0 )
@@ -1547,7 +2093,12 @@ cJSON *basilisk_remember(int32_t fastflag,int64_t *KMDtotals,int64_t *BTCtotals,
                             //printf(" secretAm, myprivs[1].(%s) bobreclaim.(%s)\n",bits256_str(str,rswap.myprivs[1]),rswap.txbytes[BASILISK_BOBRECLAIM]);
                         }
                     }
-                    LP_txbytes_update("bobreclaim",rswap.bobcoin,rswap.txbytes[BASILISK_BOBRECLAIM],&rswap.txids[BASILISK_BOBRECLAIM],&rswap.paymentspent,&rswap.sentflags[BASILISK_BOBRECLAIM]);
+                    sentflag = rswap.sentflags[BASILISK_BOBRECLAIM];
+                    LP_txbytes_update(&rswap,"bobreclaim",rswap.bobcoin,rswap.txbytes[BASILISK_BOBRECLAIM],&rswap.txids[BASILISK_BOBRECLAIM],&rswap.paymentspent,&rswap.sentflags[BASILISK_BOBRECLAIM]);
+                    if ( sentflag == 0 && rswap.sentflags[BASILISK_BOBRECLAIM] != 0 )
+                        LP_metrics_swap(rswap.bobcoin,rswap.alicecoin,"refunded");
+                    if ( rswap.txbytes[BASILISK_BOBRECLAIM] != 0 && rswap.sentflags[BASILISK_BOBRECLAIM] == 0 )
+                        LP_refundfail_msg(&rswap,-6000);
                 }
                 else if ( flag == 0 )
                 {
@@ -1567,10 +2118,10 @@ cJSON *basilisk_remember(int32_t fastflag,int64_t *KMDtotals,int64_t *BTCtotals,
                 //printf("lockduration.%d plocktime.%u lag.%d\n",lockduration,rswap.plocktime,(int32_t)(time(NULL) - (rswap.plocktime-lockduration+1800)));
                 if ( flag == 0 && (
                                    bits256_nonz(rswap.Apaymentspent) != 0 ||
-                                   time(NULL) > rswap.dlocktime-777 ||
-                                   (bits256_nonz(rswap.txids[BASILISK_ALICEPAYMENT]) == 0 && time(NULL) > rswap.plocktime-777) ||
-                                   (bits256_nonz(rswap.txids[BASILISK_BOBPAYMENT]) != 0 && rswap.sentflags[BASILISK_BOBPAYMENT] == 0 && time(NULL) > rswap.plocktime-lockduration+1800) || // failed bobpayment
-                                   (bits256_nonz(rswap.txids[BASILISK_BOBPAYMENT]) == 0 && time(NULL) > rswap.dlocktime-3*lockduration/2)
+                                   LP_now() > rswap.dlocktime-777 ||
+                                   (bits256_nonz(rswap.txids[BASILISK_ALICEPAYMENT]) == 0 && LP_now() > rswap.plocktime-777) ||
+                                   (bits256_nonz(rswap.txids[BASILISK_BOBPAYMENT]) != 0 && rswap.sentflags[BASILISK_BOBPAYMENT] == 0 && LP_now() > rswap.plocktime-lockduration+1800) || // failed bobpayment
+                                   (bits256_nonz(rswap.txids[BASILISK_BOBPAYMENT]) == 0 && LP_now() > rswap.dlocktime-3*lockduration/2)
                                    ) )
                 {
                     //printf("do the refund! paymentspent.%s now.%u vs expiration.%u\n",bits256_str(str,rswap.paymentspent),(uint32_t)time(NULL),rswap.expiration);
@@ -1578,10 +2129,10 @@ cJSON *basilisk_remember(int32_t fastflag,int64_t *KMDtotals,int64_t *BTCtotals,
                     {
                         revcalc_rmd160_sha256(rswap.secretBn,rswap.privBn);
                         vcalc_sha256(0,rswap.secretBn256,rswap.privBn.bytes,sizeof(rswap.privBn));
-                        redeemlen = basilisk_swap_bobredeemscript(1,&secretstart,redeemscript,rswap.dlocktime,rswap.pubA0,rswap.pubB0,rswap.pubB1,rswap.privAm,rswap.privBn,rswap.secretAm,rswap.secretAm256,rswap.secretBn,rswap.secretBn256);
+                        redeemlen = basilisk_swap_bobredeemscript(1,rswap.uniformspends,&secretstart,redeemscript,rswap.dlocktime,LP_rswap_csvduration(&rswap,1),rswap.pubA0,rswap.pubB0,rswap.pubB1,rswap.privAm,rswap.privBn,rswap.secretAm,rswap.secretAm256,rswap.secretBn,rswap.secretBn256);
                         len = basilisk_swapuserdata(userdata,rswap.privBn,0,rswap.myprivs[0],redeemscript,redeemlen);
                         claimtime = LP_claimtime(bob,rswap.plocktime - 777);
-                        if ( (rswap.txbytes[BASILISK_BOBREFUND]= basilisk_swap_bobtxspend(&signedtxid,rswap.Btxfee,"bobrefund",rswap.bobcoin,bob->wiftaddr,bob->taddr,bob->pubtype,bob->p2shtype,bob->isPoS,bob->wiftype,ctx,rswap.myprivs[0],0,redeemscript,redeemlen,userdata,len,rswap.txids[BASILISK_BOBDEPOSIT],0,0,rswap.pubkey33,1,claimtime,&rswap.values[BASILISK_BOBREFUND],0,0,rswap.bobdepositaddr,1,bob->zcash)) != 0 )
+                        if ( (rswap.txbytes[BASILISK_BOBREFUND]= basilisk_swap_bobtxspend(&signedtxid,rswap.Bclaimfee,"bobrefund",rswap.bobcoin,bob->wiftaddr,bob->taddr,bob->pubtype,bob->p2shtype,bob->isPoS,bob->wiftype,ctx,rswap.myprivs[0],0,redeemscript,redeemlen,userdata,len,rswap.txids[BASILISK_BOBDEPOSIT],0,0,rswap.pubkey33,1,claimtime,&rswap.values[BASILISK_BOBREFUND],0,0,rswap.bobdepositaddr,1,bob->zcash)) != 0 )
                         {
 #ifndef NOTETOMIC
                             if ( rswap.bobtomic[0] != 0 )
@@ -1599,10 +2150,12 @@ cJSON *basilisk_remember(int32_t fastflag,int64_t *KMDtotals,int64_t *BTCtotals,
                             //printf("pubB1.(%s) bobrefund.(%s)\n",bits256_str(str,rswap.pubB1),rswap.txbytes[BASILISK_BOBREFUND]);
                         }
                     }
-                    LP_txbytes_update("bobrefund",rswap.bobcoin,rswap.txbytes[BASILISK_BOBREFUND],&rswap.txids[BASILISK_BOBREFUND],&rswap.depositspent,&rswap.sentflags[BASILISK_BOBREFUND]);
+                    LP_txbytes_update(&rswap,"bobrefund",rswap.bobcoin,rswap.txbytes[BASILISK_BOBREFUND],&rswap.txids[BASILISK_BOBREFUND],&rswap.depositspent,&rswap.sentflags[BASILISK_BOBREFUND]);
+                    if ( rswap.txbytes[BASILISK_BOBREFUND] != 0 && rswap.sentflags[BASILISK_BOBREFUND] == 0 )
+                        LP_refundfail_msg(&rswap,-6000);
                 }
                 else if ( 0 && flag == 0 )
-                    printf("bobrefund's time %u vs expiration %u\n",(uint32_t)time(NULL),rswap.expiration);
+                    printf("bobrefund's time %u vs expiration %u\n",LP_now(),rswap.expiration);
             }
         }
         portable_mutex_unlock(&LP_swaplistmutex);
@@ -1624,6 +2177,7 @@ cJSON *basilisk_remember(int32_t fastflag,int64_t *KMDtotals,int64_t *BTCtotals,
         printf("depositspent.(%s) alice.%d bob.%d %s %.8f\n",bits256_str(str,rswap.depositspent),rswap.sentflags[BASILISK_ALICECLAIM],rswap.sentflags[BASILISK_BOBREFUND],rswap.bobcoin,dstr(rswap.values[BASILISK_BOBDEPOSIT]));
     }
     LP_totals_update(rswap.iambob,rswap.alicecoin,rswap.bobcoin,KMDtotals,BTCtotals,rswap.sentflags,rswap.values);
+    LP_swap_receiptsave(ctx,&rswap);
     if ( (numspent= LP_spends_set(&rswap)) == 3 )
         rswap.finishedflag = 1;
     else rswap.finishedflag = basilisk_swap_isfinished(requestid,quoteid,rswap.expiration,rswap.iambob,rswap.txids,rswap.sentflags,rswap.paymentspent,rswap.Apaymentspent,rswap.depositspent,lockduration);
@@ -1634,6 +2188,8 @@ cJSON *basilisk_remember(int32_t fastflag,int64_t *KMDtotals,int64_t *BTCtotals,
         printf("SWAP %u-%u finished LP_numfinished.%d !\n",requestid,quoteid,LP_numfinished);
         if ( rswap.finishtime == 0 )
             rswap.finishtime = (uint32_t)time(NULL);
+        LP_rswap_swaplog(&rswap,"spent","swap finished");
+        LP_rswap_spendtimings(&rswap);
         if ( rswap.tradeid != 0 )
             LP_tradebot_finished(rswap.tradeid,rswap.requestid,rswap.quoteid);
         sprintf(fname,"%s/SWAPS/%u-%u.finished",GLOBAL_DBDIR,rswap.requestid,rswap.quoteid), OS_compatible_path(fname);
@@ -1642,6 +2198,7 @@ cJSON *basilisk_remember(int32_t fastflag,int64_t *KMDtotals,int64_t *BTCtotals,
         {
             jaddstr(item,"method","tradestatus");
             jaddnum(item,"finishtime",rswap.finishtime);
+            jaddnum(item,"version",LP_SCHEMA_VERSION);
             if ( jobj(item,"gui") == 0 )
                 jaddstr(item,"gui",G.gui);
             //jaddbits256(item,"srchash",rswap.Q.srchash);
@@ -1875,13 +2432,13 @@ char *LP_recent_swaps(int32_t limit,char *uuidstr)
         }
     }
     jadd(retjson,"netamounts",array);
-    if ( time(NULL) < Alice_expiration )
+    if ( LP_now() < Alice_expiration )
     {
         item = cJSON_CreateObject();
         if ( uuidstr != 0 )
             jaddstr(item,"uuid",uuidstr);
         jaddnum(item,"expiration",Alice_expiration);
-        jaddnum(item,"timeleft",Alice_expiration-time(NULL));
+        jaddnum(item,"timeleft",Alice_expiration-LP_now());
         jaddnum(item,"tradeid",LP_Alicequery.tradeid);
         jaddnum(item,"requestid",LP_Alicequery.R.requestid);
         jaddnum(item,"quoteid",LP_Alicequery.R.quoteid);
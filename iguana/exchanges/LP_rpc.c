@@ -320,7 +320,7 @@ cJSON *LP_gettxout(char *symbol,char *coinaddr,bits256 txid,int32_t vout)
 
 cJSON *LP_validateaddress(char *symbol,char *address)
 {
-    char buf[512],coinaddr[64],checkaddr[64],script[128]; int32_t i; uint8_t rmd160[20],addrtype; cJSON *retjson; struct iguana_info *coin;
+    char buf[512],coinaddr[64],script[128]; int32_t i,spklen; uint8_t rmd160[20],addrtype,spk[64]; cJSON *retjson; struct iguana_info *coin;
     if ( symbol == 0 || symbol[0] == 0 )
         return(cJSON_Parse("{\"error\":\"null symbol\"}"));
     coin = LP_coinfind(symbol);
@@ -330,14 +330,29 @@ cJSON *LP_validateaddress(char *symbol,char *address)
     {
         retjson = cJSON_CreateObject();
         jaddstr(retjson,"address",address);
+        if ( LP_addrformat(symbol,address) == LP_ADDRFORMAT_BECH32 )
+        {
+            if ( (spklen= LP_address_script(spk,symbol,coin->taddr,coin->pubtype,address)) <= 0 )
+            {
+                jadd(retjson,"isvalid",cJSON_CreateFalse());
+                return(retjson);
+            }
+            jadd(retjson,"isvalid",cJSON_CreateTrue());
+            init_hexbytes_noT(script,spk,spklen);
+            jaddstr(retjson,"scriptPubKey",script);
+            jadd(retjson,"ismine",cJSON_CreateFalse());
+            jadd(retjson,"iswatchonly",cJSON_CreateTrue());
+            jadd(retjson,"isscript",spklen == 34 ? cJSON_CreateTrue() : cJSON_CreateFalse());
+            jadd(retjson,"iswitness",cJSON_CreateTrue());
+            return(retjson);
+        }
         bitcoin_addr2rmd160(symbol,coin->taddr,&addrtype,rmd160,address);
-        bitcoin_address(symbol,checkaddr,coin->taddr,addrtype,rmd160,20);
         if ( addrtype != coin->pubtype && addrtype != coin->p2shtype )
         {
             jadd(retjson,"isvalid",cJSON_CreateFalse());
             return(retjson);
         }
-        jadd(retjson,"isvalid",strcmp(address,checkaddr)==0 ? cJSON_CreateTrue() : cJSON_CreateFalse());
+        jadd(retjson,"isvalid",bitcoin_validaddress(symbol,coin->taddr,coin->pubtype,coin->p2shtype,address) == 0 ? cJSON_CreateTrue() : cJSON_CreateFalse());
         if ( addrtype == coin->pubtype )
         {
             strcpy(script,"76a914");
@@ -563,6 +578,138 @@ cJSON *LP_listtransactions(char *symbol,char *coinaddr,int32_t count,int32_t ski
     }
 }
 
+// folds one listtransactions entry into coin->tx_history, a send to several outputs shows up as one entry per output
+// so amounts accumulate while item->vout (reused as the pass id) matches. returns 1 if the txid was already known and confirmed
+int32_t LP_native_txhistory_item(struct iguana_info *coin,cJSON *entry,uint32_t passid)
+{
+    struct LP_tx_history_item *iter,*item = 0; char *txid,*category,*addr,*blockhash; int32_t confirmations,found = 0;
+    if ( (txid= jstr(entry,"txid")) == 0 || strlen(txid) >= sizeof(item->txid) || (category= jstr(entry,"category")) == 0 )
+        return(0);
+    if ( strcmp(category,"send") != 0 && (strcmp(category,"receive") != 0 || (addr= jstr(entry,"address")) == 0 || strcmp(addr,coin->smartaddr) != 0) )
+        return(0);
+    confirmations = jint(entry,"confirmations");
+    portable_mutex_lock(&coin->tx_history_mutex);
+    DL_FOREACH(coin->tx_history, iter)
+    {
+        if ( strcmp(iter->txid,txid) == 0 )
+        {
+            item = iter;
+            found = 1;
+            break;
+        }
+    }
+    if ( item != 0 && item->blockindex > 0 && item->vout != passid )
+    {
+        portable_mutex_unlock(&coin->tx_history_mutex);
+        return(1);
+    }
+    if ( item == 0 )
+    {
+        item = calloc(1,sizeof(*item));
+        strcpy(item->txid,txid);
+        strcpy(item->category,category);
+    }
+    if ( item->vout != passid )
+        item->amount = 0.;
+    else if ( strcmp(item->category,category) != 0 ) // change back to ourselves, the send side is what matters
+        strcpy(item->category,"send");
+    item->vout = passid;
+    item->amount += jdouble(entry,"amount");
+    if ( confirmations > 0 && (blockhash= jstr(entry,"blockhash")) != 0 && strlen(blockhash) < sizeof(item->blockhash) )
+    {
+        strcpy(item->blockhash,blockhash);
+        item->blockindex = coin->height - confirmations + 1;
+        item->blocktime = juint(entry,"blocktime");
+    }
+    if ( (item->time= juint(entry,"time")) == 0 )
+        item->time = (uint32_t)time(NULL);
+    if ( found == 0 )
+        DL_APPEND(coin->tx_history,item);
+    portable_mutex_unlock(&coin->tx_history_mutex);
+    return(0);
+}
+
+// native counterpart of LP_electrum_txhistory_loop, pages backwards through listtransactions until it reaches confirmed history it already has
+void LP_native_txhistory_loop(void *_coin)
+{
+    struct iguana_info *coin = _coin; char buf[128]; cJSON *array; int32_t i,n,notarized,skip,known; uint32_t passid = 0;
+    int (*ptr)(struct LP_tx_history_item*, struct LP_tx_history_item*) = &history_item_cmp;
+    while ( coin != 0 && coin->electrum == 0 && coin->inactive == 0 && coin->cache_history != 0 )
+    {
+        passid++;
+        coin->height = LP_getheight(&notarized,coin);
+        for (skip=known=0; known==0; skip+=LP_TXHISTORY_PAGE)
+        {
+            sprintf(buf,"[\"*\", %d, %d, true]",LP_TXHISTORY_PAGE,skip);
+            if ( (array= bitcoin_json(coin,"listtransactions",buf)) == 0 || is_cJSON_Array(array) == 0 )
+            {
+                if ( array != 0 )
+                    free_json(array);
+                break;
+            }
+            n = cJSON_GetArraySize(array);
+            for (i=n-1; i>=0; i--)
+                known += LP_native_txhistory_item(coin,jitem(array,i),passid);
+            free_json(array);
+            if ( n < LP_TXHISTORY_PAGE )
+            {
+                if ( coin->txhistory_synced == 0 )
+                    coin->txhistory_synced = (uint32_t)time(NULL);
+                break;
+            }
+        }
+        portable_mutex_lock(&coin->tx_history_mutex);
+        DL_SORT(coin->tx_history, ptr);
+        portable_mutex_unlock(&coin->tx_history_mutex);
+        sleep(30);
+    }
+    if ( coin != 0 )
+        coin->txhistory_running = 0;
+}
+
+// newest first, from_id is the last txid of the previous page
+cJSON *LP_my_tx_history(struct iguana_info *coin,int32_t limit,char *fromid)
+{
+    struct LP_tx_history_item *item; cJSON *retjson,*array; int32_t skipped = 0,total = 0,started;
+    if ( coin->cache_history == 0 && coin->etomic[0] == 0 )
+        return(cJSON_Parse("{\"error\":\"tx_history indexer not enabled for coin, pass tx_history:1 to enable or electrum\"}"));
+    if ( limit <= 0 )
+        limit = 10;
+    started = (fromid == 0 || fromid[0] == 0);
+    array = cJSON_CreateArray();
+    portable_mutex_lock(&coin->tx_history_mutex);
+    DL_FOREACH(coin->tx_history, item)
+    {
+        total++;
+        if ( started == 0 )
+        {
+            skipped++;
+            if ( strcmp(item->txid,fromid) == 0 )
+                started = 1;
+        }
+        else if ( cJSON_GetArraySize(array) < limit )
+            jaddi(array,tx_history_to_json(item,coin));
+    }
+    portable_mutex_unlock(&coin->tx_history_mutex);
+    if ( started == 0 )
+    {
+        free_json(array);
+        return(cJSON_Parse("{\"error\":\"from_id not found in tx history\"}"));
+    }
+    retjson = cJSON_CreateObject();
+    jaddstr(retjson,"result","success");
+    jaddstr(retjson,"coin",coin->symbol);
+    jadd(retjson,"transactions",array);
+    if ( fromid != 0 && fromid[0] != 0 )
+        jaddstr(retjson,"from_id",fromid);
+    jaddnum(retjson,"limit",limit);
+    jaddnum(retjson,"skipped",skipped);
+    jaddnum(retjson,"total",total);
+    jaddnum(retjson,"current_block",coin->height);
+    jaddstr(retjson,"sync_status",coin->txhistory_synced != 0 ? "finished" : "in_progress");
+    return(retjson);
+}
+
 int64_t LP_listunspent_parseitem(struct iguana_info *coin,bits256 *txidp,int32_t *voutp,int32_t *heightp,cJSON *item)
 {
     int64_t satoshis = 0;
@@ -812,6 +959,43 @@ double LP_getestimatedrate(struct iguana_info *coin)
     return(rate);
 }
 
+#define LP_MAXMAINTQUEUE 256
+
+// broadcasts outside of swap threads while a coin is in maintenance, sent once maintenance ends
+struct LP_maintqueued { char symbol[16],*signedtx; uint32_t queued; } LP_maintqueue[LP_MAXMAINTQUEUE];
+int32_t LP_nummaintqueue;
+
+int32_t LP_maintenance_queue(char *symbol,char *signedtx)
+{
+    int32_t i,retval = -1;
+    portable_mutex_lock(&LP_maintqmutex);
+    for (i=0; i<LP_nummaintqueue; i++)
+        if ( strcmp(LP_maintqueue[i].signedtx,signedtx) == 0 )
+            break;
+    if ( i < LP_nummaintqueue )
+        retval = i;
+    else if ( LP_nummaintqueue < LP_MAXMAINTQUEUE )
+    {
+        safecopy(LP_maintqueue[LP_nummaintqueue].symbol,symbol,sizeof(LP_maintqueue[LP_nummaintqueue].symbol));
+        LP_maintqueue[LP_nummaintqueue].signedtx = clonestr(signedtx);
+        LP_maintqueue[LP_nummaintqueue].queued = (uint32_t)time(NULL);
+        retval = LP_nummaintqueue++;
+    }
+    portable_mutex_unlock(&LP_maintqmutex);
+    return(retval);
+}
+
+int32_t LP_maintenance_numqueued(char *symbol)
+{
+    int32_t i,n = 0;
+    portable_mutex_lock(&LP_maintqmutex);
+    for (i=0; i<LP_nummaintqueue; i++)
+        if ( strcmp(LP_maintqueue[i].symbol,symbol) == 0 )
+            n++;
+    portable_mutex_unlock(&LP_maintqmutex);
+    return(n);
+}
+
 char *LP_sendrawtransaction(char *symbol,char *signedtx,int32_t needjson)
 {
     cJSON *array,*errobj; char *paramstr,*tmpstr,*retstr=0; int32_t n,alreadyflag = 0; cJSON *retjson; struct iguana_info *coin;
@@ -826,6 +1010,13 @@ char *LP_sendrawtransaction(char *symbol,char *signedtx,int32_t needjson)
         printf("LP_sendrawtransaction null coin\n");
         return(clonestr("{\"error\":\"invalid coin\"}"));
     }
+    if ( coin->maintenance != 0 && LP_threadswap == 0 )
+    {
+        if ( LP_maintenance_queue(symbol,signedtx) < 0 )
+            return(clonestr("{\"error\":\"coin in maintenance and queue full\"}"));
+        printf("%s in maintenance, broadcast queued\n",symbol);
+        return(clonestr("{\"error\":\"coin in maintenance, queued\"}"));
+    }
     if ( coin->electrum == 0 )
     {
         array = cJSON_CreateArray();
@@ -876,6 +1067,107 @@ char *LP_sendrawtransaction(char *symbol,char *signedtx,int32_t needjson)
     return(retstr);
 }
 
+int32_t LP_maintenance_flush(char *symbol)
+{
+    int32_t i,n = 0; char *signedtx,*retstr;
+    while ( 1 )
+    {
+        signedtx = 0;
+        portable_mutex_lock(&LP_maintqmutex);
+        for (i=0; i<LP_nummaintqueue; i++)
+        {
+            if ( strcmp(LP_maintqueue[i].symbol,symbol) == 0 )
+            {
+                signedtx = LP_maintqueue[i].signedtx;
+                LP_maintqueue[i] = LP_maintqueue[--LP_nummaintqueue];
+                memset(&LP_maintqueue[LP_nummaintqueue],0,sizeof(LP_maintqueue[LP_nummaintqueue]));
+                break;
+            }
+        }
+        portable_mutex_unlock(&LP_maintqmutex);
+        if ( signedtx == 0 )
+            break;
+        if ( (retstr= LP_sendrawtransaction(symbol,signedtx,0)) != 0 )
+        {
+            printf("%s maintenance over, sent queued tx -> %s\n",symbol,retstr);
+            free(retstr);
+        }
+        free(signedtx);
+        n++;
+    }
+    return(n);
+}
+
+char *LP_maintenance(char *symbol,int32_t enable)
+{
+    struct iguana_info *coin; cJSON *retjson; int32_t flushed = 0;
+    if ( (coin= LP_coinfind(symbol)) == 0 )
+        return(clonestr("{\"error\":\"cant find coind\"}"));
+    if ( enable != 0 )
+    {
+        if ( coin->maintenance == 0 )
+            coin->maintenance = (uint32_t)time(NULL);
+        printf("%s in maintenance: new swaps refused, swaps pause before funding, broadcasts queued\n",symbol);
+    }
+    else if ( coin->maintenance != 0 )
+    {
+        coin->maintenance = 0;
+        flushed = LP_maintenance_flush(symbol);
+        printf("%s maintenance over, %d queued broadcasts sent\n",symbol,flushed);
+    }
+    retjson = cJSON_CreateObject();
+    jaddstr(retjson,"result","success");
+    jaddstr(retjson,"coin",symbol);
+    jadd(retjson,"maintenance",coin->maintenance != 0 ? jtrue() : jfalse());
+    if ( coin->maintenance != 0 )
+        jaddnum(retjson,"since",coin->maintenance);
+    jaddnum(retjson,"queued",LP_maintenance_numqueued(symbol));
+    jaddnum(retjson,"sent",flushed);
+    return(jprint(retjson,1));
+}
+
+// 1 if the node would take signedtx into its mempool, 0 if it wouldnt (reason filled in), -1 when the coin cant tell us
+int32_t LP_testmempoolaccept(char *symbol,char *signedtx,char *reason,int32_t reasonsize)
+{
+    cJSON *array,*params,*retjson,*item; char *paramstr,*retstr,*str; struct iguana_info *coin; int32_t retval = -1;
+    reason[0] = 0;
+    if ( symbol == 0 || signedtx == 0 || signedtx[0] == 0 || (coin= LP_coinfind(symbol)) == 0 )
+        return(-1);
+    if ( coin->electrum != 0 || coin->etomic[0] != 0 || coin->notestaccept != 0 )
+        return(-1);
+    array = cJSON_CreateArray();
+    jaddistr(array,signedtx);
+    params = cJSON_CreateArray();
+    jaddi(params,array);
+    paramstr = jprint(params,1);
+    if ( (retstr= bitcoind_passthru(symbol,coin->serverport,coin->userpass,"testmempoolaccept",paramstr)) != 0 )
+    {
+        if ( (retjson= cJSON_Parse(retstr)) != 0 )
+        {
+            if ( is_cJSON_Array(retjson) != 0 && (item= jitem(retjson,0)) != 0 && jobj(item,"allowed") != 0 )
+            {
+                if ( is_cJSON_True(jobj(item,"allowed")) != 0 )
+                    retval = 1;
+                else
+                {
+                    retval = 0;
+                    if ( (str= jstr(item,"reject-reason")) != 0 )
+                        safecopy(reason,str,reasonsize);
+                }
+            }
+            else if ( jobj(retjson,"error") != 0 && strstr(retstr,"Method not found") != 0 )
+            {
+                printf("%s daemon has no testmempoolaccept, broadcasting swap txs unchecked\n",symbol);
+                coin->notestaccept = 1;
+            }
+            free_json(retjson);
+        }
+        free(retstr);
+    }
+    free(paramstr);
+    return(retval);
+}
+
 char *LP_signrawtx(char *symbol,bits256 *signedtxidp,int32_t *completedp,cJSON *vins,char *rawtx,cJSON *privkeys,struct vin_info *V)
 {
     cJSON *array,*json,*retjson; int32_t len; uint8_t *data; char str[65],*paramstr,*retstr,*hexstr,*signedtx=0; struct iguana_msgtx msgtx; struct iguana_info *coin;
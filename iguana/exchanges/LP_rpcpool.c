@@ -0,0 +1,230 @@
+
+/******************************************************************************
+ * Copyright © 2014-2018 The SuperNET Developers.                             *
+ *                                                                            *
+ * See the AUTHORS, DEVELOPER-AGREEMENT and LICENSE files at                  *
+ * the top-level directory of this distribution for the individual copyright  *
+ * holder information and the developer policies on copyright and licensing.  *
+ *                                                                            *
+ * Unless otherwise agreed in a custom licensing agreement, no part of the    *
+ * SuperNET software, including this file may be copied, modified, propagated *
+ * or distributed except according to the terms contained in the LICENSE file *
+ *                                                                            *
+ * Removal or modification of this copyright notice is prohibited.            *
+ *                                                                            *
+ ******************************************************************************/
+//
+//  LP_rpcpool.c
+//  marketmaker
+//
+//  coin daemon connections. native coins get LP_RPCPOOL_SIZE keepalive curl handles, a handle whose request failed at
+//  the transport level is replaced before it is used again. a failed request is retried with jittered backoff until
+//  "rpc_deadline" (startup json, seconds) so a daemon restart or a short electrum outage doesnt surface as a failed
+//  swap step. once a coin is marked down further requests get one attempt each, at most every LP_RPCPOOL_PROBE seconds
+//  they get the full deadline again to find out if it is back
+//
+
+#define LP_RPC_DEADLINE 30
+#define LP_RPCPOOL_PROBE 10
+#define LP_RPC_BACKOFF_MILLIS 250
+#define LP_RPC_BACKOFF_MAXSHIFT 4
+
+void *curl_easy_init();
+void curl_easy_cleanup(void *curl_handle);
+extern int32_t bitcoind_RPC_inittime;
+
+int32_t LP_rpc_deadline = LP_RPC_DEADLINE;
+
+void LP_rpcpool_init(struct iguana_info *coin)
+{
+    int32_t i;
+    memset(&coin->rpcpool,0,sizeof(coin->rpcpool));
+    for (i=0; i<LP_RPCPOOL_SIZE; i++)
+        coin->rpcpool.handles[i] = curl_easy_init();
+}
+
+// idle handles are closed now, busy ones when their request returns. LP_rpcpool_restore reopens them on enable
+void LP_rpcpool_release(struct iguana_info *coin)
+{
+    struct LP_rpcpool *pool = &coin->rpcpool; int32_t i;
+    portable_mutex_lock(&coin->curl_mutex);
+    for (i=0; i<LP_RPCPOOL_SIZE; i++)
+        if ( pool->inuse[i] == 0 && pool->handles[i] != 0 )
+        {
+            curl_easy_cleanup(pool->handles[i]);
+            pool->handles[i] = 0;
+        }
+    portable_mutex_unlock(&coin->curl_mutex);
+}
+
+void LP_rpcpool_restore(struct iguana_info *coin)
+{
+    struct LP_rpcpool *pool = &coin->rpcpool; int32_t i;
+    portable_mutex_lock(&coin->curl_mutex);
+    for (i=0; i<LP_RPCPOOL_SIZE; i++)
+        if ( pool->inuse[i] == 0 && pool->handles[i] == 0 )
+            pool->handles[i] = curl_easy_init();
+    portable_mutex_unlock(&coin->curl_mutex);
+}
+
+// sleeps base << attempt, half of it random so reconnecting callers dont hit the daemon in lockstep. 0 if past deadline
+int32_t LP_rpc_backoff(int32_t attempt,uint32_t deadline)
+{
+    int32_t millis; uint32_t now = (uint32_t)time(NULL);
+    if ( now >= deadline )
+        return(0);
+    millis = LP_RPC_BACKOFF_MILLIS << (attempt < LP_RPC_BACKOFF_MAXSHIFT ? attempt : LP_RPC_BACKOFF_MAXSHIFT);
+    millis = millis/2 + (rand() % (millis/2 + 1));
+    if ( millis > (deadline - now) * 1000 )
+        millis = (deadline - now) * 1000;
+    usleep(millis * 1000);
+    return(1);
+}
+
+// a down coin gets a single attempt per request, except for one full probe every LP_RPCPOOL_PROBE seconds
+int32_t LP_rpcpool_fastfail(struct LP_rpcpool *pool)
+{
+    uint32_t now = (uint32_t)time(NULL);
+    if ( bitcoind_RPC_inittime != 0 )
+        return(1);
+    if ( pool->downsince == 0 || now >= pool->lastprobe + LP_RPCPOOL_PROBE )
+    {
+        pool->lastprobe = now;
+        return(0);
+    }
+    return(1);
+}
+
+void LP_rpcpool_result(struct iguana_info *coin,int32_t okflag)
+{
+    struct LP_rpcpool *pool = &coin->rpcpool; uint32_t now = (uint32_t)time(NULL);
+    if ( okflag != 0 )
+    {
+        if ( pool->downsince != 0 )
+            printf("%s rpc back after %ds\n",coin->symbol,now - pool->downsince);
+        pool->downsince = 0;
+        pool->lastok = now;
+    }
+    else
+    {
+        pool->numfailures++;
+        if ( pool->downsince == 0 )
+        {
+            pool->downsince = now;
+            printf("%s rpc down, retrying for %ds\n",coin->symbol,LP_rpc_deadline);
+        }
+    }
+}
+
+char *LP_rpcpool_request(struct iguana_info *coin,char *method,char *params,int32_t timeout)
+{
+    struct LP_rpcpool *pool = &coin->rpcpool; void *handle; char *retstr; int32_t i,slot,fastfail,attempt = 0; uint32_t deadline;
+    deadline = (uint32_t)time(NULL) + LP_rpc_deadline;
+    fastfail = LP_rpcpool_fastfail(pool);
+    pool->numrequests++;
+    while ( 1 )
+    {
+        slot = -1;
+        handle = 0;
+        portable_mutex_lock(&coin->curl_mutex);
+        for (i=0; i<LP_RPCPOOL_SIZE; i++)
+            if ( pool->inuse[i] == 0 && pool->handles[i] != 0 )
+            {
+                pool->inuse[i] = 1;
+                handle = pool->handles[i];
+                slot = i;
+                break;
+            }
+        portable_mutex_unlock(&coin->curl_mutex);
+        if ( slot < 0 ) // all busy, a one shot handle is cheaper than waiting behind a slow call
+            pool->numoverflow++;
+        retstr = bitcoind_RPCnew(handle,0,coin->symbol,coin->serverport,coin->userpass,method,params,timeout);
+        if ( slot >= 0 )
+        {
+            if ( coin->inactive != 0 ) // disabled while this request ran
+            {
+                curl_easy_cleanup(handle);
+                handle = 0;
+            }
+            else if ( retstr == 0 ) // dont reuse a connection the daemon may have dropped
+            {
+                curl_easy_cleanup(handle);
+                handle = curl_easy_init();
+                pool->numreconnects++;
+            }
+            portable_mutex_lock(&coin->curl_mutex);
+            pool->handles[slot] = handle;
+            pool->inuse[slot] = 0;
+            portable_mutex_unlock(&coin->curl_mutex);
+        }
+        LP_rpcpool_result(coin,retstr != 0);
+        if ( retstr != 0 || fastfail != 0 || LP_rpc_backoff(attempt++,deadline) == 0 )
+            break;
+        pool->numretries++;
+    }
+    return(retstr);
+}
+
+// electrum requests that timed out on every server are retried until the deadline, errors returned by a server are not
+cJSON *LP_electrum_request(char *symbol,struct electrum_info *ep,cJSON **retjsonp,char *method,char *params,int32_t timeout)
+{
+    struct iguana_info *coin; struct electrum_info *ptr; cJSON *retjson; int32_t connected,fastfail = 1,attempt = 0; uint32_t deadline;
+    deadline = (uint32_t)time(NULL) + LP_rpc_deadline;
+    if ( (coin= LP_coinfind(symbol)) != 0 )
+    {
+        fastfail = LP_rpcpool_fastfail(&coin->rpcpool);
+        coin->rpcpool.numrequests++;
+    }
+    while ( 1 )
+    {
+        retjson = electrum_submit_servers(symbol,ep,retjsonp,method,params,timeout);
+        if ( retjson != 0 && jstr(retjson,"error") != 0 && strcmp(jstr(retjson,"error"),"timeout") == 0 )
+        {
+            if ( coin != 0 )
+                LP_rpcpool_result(coin,0);
+        }
+        else
+        {
+            if ( coin != 0 && retjson != 0 )
+                LP_rpcpool_result(coin,1);
+            return(retjson);
+        }
+        for (connected=0,ptr=(ep != 0 ? ep : electrum_server(symbol,0)); ptr!=0; ptr=ptr->prev)
+            if ( ptr->sock >= 0 )
+                connected++;
+        if ( fastfail != 0 || connected == 0 || LP_rpc_backoff(attempt++,deadline) == 0 )
+            return(retjson);
+        free_json(retjson);
+        *retjsonp = 0;
+        if ( coin != 0 )
+            coin->rpcpool.numretries++;
+    }
+}
+
+cJSON *LP_rpc_health(struct iguana_info *coin)
+{
+    struct LP_rpcpool *pool = &coin->rpcpool; cJSON *retjson; int32_t i,inuse = 0;
+    if ( coin->electrum != 0 )
+        retjson = LP_electrum_health(coin);
+    else
+    {
+        retjson = cJSON_CreateObject();
+        jaddstr(retjson,"coin",coin->symbol);
+        jaddstr(retjson,"result","success");
+        for (i=0; i<LP_RPCPOOL_SIZE; i++)
+            inuse += pool->inuse[i];
+        jaddnum(retjson,"connections",LP_RPCPOOL_SIZE);
+        jaddnum(retjson,"inuse",inuse);
+        jaddnum(retjson,"reconnects",pool->numreconnects);
+        jaddnum(retjson,"overflow",pool->numoverflow);
+    }
+    jaddstr(retjson,"status",pool->downsince != 0 ? "down" : "up");
+    if ( pool->downsince != 0 )
+        jaddnum(retjson,"downsince",pool->downsince);
+    jaddnum(retjson,"lastok",pool->lastok);
+    jaddnum(retjson,"requests",pool->numrequests);
+    jaddnum(retjson,"retries",pool->numretries);
+    jaddnum(retjson,"failures",pool->numfailures);
+    jaddnum(retjson,"deadline",LP_rpc_deadline);
+    return(retjson);
+}
@@ -0,0 +1,207 @@
+
+/******************************************************************************
+ * Copyright © 2014-2018 The SuperNET Developers.                             *
+ *                                                                            *
+ * See the AUTHORS, DEVELOPER-AGREEMENT and LICENSE files at                  *
+ * the top-level directory of this distribution for the individual copyright  *
+ * holder information and the developer policies on copyright and licensing.  *
+ *                                                                            *
+ * Unless otherwise agreed in a custom licensing agreement, no part of the    *
+ * SuperNET software, including this file may be copied, modified, propagated *
+ * or distributed except according to the terms contained in the LICENSE file *
+ *                                                                            *
+ * Removal or modification of this copyright notice is prohibited.            *
+ *                                                                            *
+ ******************************************************************************/
+//
+//  LP_schema.c
+//  marketmaker
+//
+//  DB/SCHEMA records the on-disk format, json records carry their own "version" and the migrations below run at startup
+//  to bring an older DB up to LP_SCHEMA_VERSION before anything reads it. binary files (UNSPENTS caches) have no room
+//  for a stamp, so a change to their layout needs a migration that converts or drops them
+//
+
+uint32_t LP_schema_version; int32_t LP_schema_dropped;
+
+char *LP_schema_fname(char *fname)
+{
+    sprintf(fname,"%s/SCHEMA",GLOBAL_DBDIR), OS_compatible_path(fname);
+    return(fname);
+}
+
+int32_t LP_schema_save(uint32_t version)
+{
+    FILE *fp; char fname[512],tmpname[512];
+    LP_schema_fname(fname);
+    sprintf(tmpname,"%s.tmp",fname);
+    if ( (fp= fopen(tmpname,"wb")) == 0 )
+        return(-1);
+    fprintf(fp,"{\"version\":%u,\"mmversion\":\"%s\",\"updated\":%u}\n",version,MM_VERSION,(uint32_t)time(NULL));
+    fclose(fp);
+    if ( OS_renamefile(tmpname,fname) < 0 )
+        return(-1);
+    LP_schema_version = version;
+    return(0);
+}
+
+// adds "version" to a json record written before records were stamped, leaves anything already stamped alone
+int32_t LP_schema_stamp(char *fname,uint32_t version)
+{
+    FILE *fp; char *fstr,*str,tmpname[512]; long fsize; cJSON *item; int32_t retval = 0;
+    if ( (fstr= OS_filestr(&fsize,fname)) == 0 )
+        return(0);
+    if ( (item= cJSON_Parse(fstr)) != 0 )
+    {
+        if ( jobj(item,"version") == 0 )
+        {
+            jaddnum(item,"version",version);
+            sprintf(tmpname,"%s.tmp",fname);
+            if ( (fp= fopen(tmpname,"wb")) != 0 )
+            {
+                str = jprint(item,0);
+                fprintf(fp,"%s\n",str);
+                free(str);
+                fclose(fp);
+                if ( OS_renamefile(tmpname,fname) == 0 )
+                    retval = 1;
+                else retval = -1;
+            } else retval = -1;
+        }
+        free_json(item);
+    } else printf("schema: unparseable record %s left as is\n",fname);
+    free(fstr);
+    return(retval);
+}
+
+// returns the number of records it had to drop, -1 on error
+int32_t LP_schema_migrate_v1()
+{
+    FILE *fp,*outfp; char fname[512],tmpname[512],line[8192],*str; uint32_t requestid,quoteid; int32_t i,n = 0,numswaps = 0,numfiles = 0,numdropped = 0; cJSON *argjson;
+    sprintf(fname,"%s/SWAPS/list",GLOBAL_DBDIR), OS_compatible_path(fname);
+    if ( (fp= fopen(fname,"rb")) != 0 )
+    {
+        while ( fread(&requestid,1,sizeof(requestid),fp) == sizeof(requestid) && fread(&quoteid,1,sizeof(quoteid),fp) == sizeof(quoteid) )
+        {
+            sprintf(fname,"%s/SWAPS/%u-%u",GLOBAL_DBDIR,requestid,quoteid), OS_compatible_path(fname);
+            if ( (n= LP_schema_stamp(fname,1)) < 0 )
+                break;
+            numfiles += n;
+            sprintf(fname,"%s/SWAPS/%u-%u.finished",GLOBAL_DBDIR,requestid,quoteid), OS_compatible_path(fname);
+            if ( (n= LP_schema_stamp(fname,1)) < 0 )
+                break;
+            numfiles += n;
+            for (i=0; i<sizeof(txnames)/sizeof(*txnames); i++)
+            {
+                sprintf(fname,"%s/SWAPS/%u-%u.%s",GLOBAL_DBDIR,requestid,quoteid,txnames[i]), OS_compatible_path(fname);
+                if ( (n= LP_schema_stamp(fname,1)) < 0 )
+                    break;
+                numfiles += n;
+            }
+            if ( n < 0 )
+                break;
+            numswaps++;
+        }
+        fclose(fp);
+        if ( n < 0 )
+        {
+            printf("schema v1: cant rewrite %s\n",fname);
+            return(-1);
+        }
+    }
+    sprintf(fname,"%s/GTC/orders",GLOBAL_DBDIR), OS_compatible_path(fname);
+    if ( (fp= fopen(fname,"rb")) != 0 )
+    {
+        sprintf(tmpname,"%s.tmp",fname);
+        if ( (outfp= fopen(tmpname,"wb")) == 0 )
+        {
+            fclose(fp);
+            return(-1);
+        }
+        while ( fgets(line,sizeof(line),fp) > 0 )
+        {
+            if ( (argjson= cJSON_Parse(line)) != 0 )
+            {
+                if ( jobj(argjson,"version") == 0 )
+                    jaddnum(argjson,"version",1);
+                str = jprint(argjson,1);
+                fprintf(outfp,"%s\n",str);
+                free(str);
+                numfiles++;
+            }
+            else
+            {
+                printf("schema v1: dropping unparseable order from %s: %s%s",fname,line,strchr(line,'\n') == 0 ? "\n" : "");
+                numdropped++;
+            }
+        }
+        fclose(fp);
+        fclose(outfp);
+        if ( OS_renamefile(tmpname,fname) < 0 )
+            return(-1);
+    }
+    printf("schema v1: stamped %d records across %d swaps, dropped %d\n",numfiles,numswaps,numdropped);
+    return(numdropped);
+}
+
+struct LP_migration { uint32_t version; char *name; int32_t (*migrate)(); } LP_migrations[] =
+{
+    { 1, "stamp swap and order records with their format version", LP_schema_migrate_v1 },
+    { 2, "convert the binary utxo lock table to versioned json", LP_utxolocks_migrate },
+};
+
+void LP_schema_init()
+{
+    char fname[512],listname[512],*fstr; long fsize; cJSON *item; int32_t i,numdropped; uint32_t version = 0;
+    LP_schema_fname(fname);
+    if ( (fstr= OS_filestr(&fsize,fname)) != 0 )
+    {
+        if ( (item= cJSON_Parse(fstr)) != 0 )
+        {
+            version = juint(item,"version");
+            free_json(item);
+        }
+        free(fstr);
+    }
+    else
+    {
+        sprintf(listname,"%s/SWAPS/list",GLOBAL_DBDIR), OS_compatible_path(listname);
+        if ( (fstr= OS_filestr(&fsize,listname)) == 0 )
+        {
+            sprintf(listname,"%s/GTC/orders",GLOBAL_DBDIR), OS_compatible_path(listname);
+            fstr = OS_filestr(&fsize,listname);
+        }
+        if ( fstr == 0 ) // fresh DB, nothing to migrate
+        {
+            if ( LP_schema_save(LP_SCHEMA_VERSION) < 0 )
+                printf("schema: cant write %s\n",fname);
+            return;
+        }
+        free(fstr);
+    }
+    if ( version > LP_SCHEMA_VERSION )
+    {
+        printf("%s is schema version %u, this marketmaker only understands up to %u. refusing to touch it, upgrade or point at another userhome\n",GLOBAL_DBDIR,version,LP_SCHEMA_VERSION);
+        exit(-1);
+    }
+    LP_schema_version = version;
+    for (i=0; i<sizeof(LP_migrations)/sizeof(*LP_migrations); i++)
+    {
+        if ( LP_migrations[i].version <= version )
+            continue;
+        printf("schema migration %u -> %u: %s\n",version,LP_migrations[i].version,LP_migrations[i].name);
+        if ( (numdropped= (*LP_migrations[i].migrate)()) < 0 || LP_schema_save(LP_migrations[i].version) < 0 )
+        {
+            printf("schema migration to %u failed, DB left at %u. fix the error above and restart\n",LP_migrations[i].version,version);
+            exit(-1);
+        }
+        if ( numdropped > 0 )
+        {
+            printf("schema migration to %u dropped %d records\n",LP_migrations[i].version,numdropped);
+            LP_schema_dropped += numdropped;
+        }
+        version = LP_migrations[i].version;
+    }
+    if ( version != LP_SCHEMA_VERSION && LP_schema_save(LP_SCHEMA_VERSION) < 0 )
+        printf("schema: cant write %s\n",fname);
+}
@@ -39,9 +39,20 @@ struct basilisk_request *LP_requestinit(struct basilisk_request *rp,bits256 srch
     return(rp);
 }
 
+bits256 LP_internal_proof(struct LP_quoteinfo *qp)
+{
+    uint8_t buf[sizeof(bits256)*2 + sizeof(qp->uuidstr)]; bits256 proof;
+    memset(buf,0,sizeof(buf));
+    memcpy(buf,G.LP_internalsecret.bytes,sizeof(bits256));
+    memcpy(&buf[sizeof(bits256)],qp->desthash.bytes,sizeof(bits256));
+    memcpy(&buf[sizeof(bits256)*2],qp->uuidstr,sizeof(qp->uuidstr));
+    vcalc_sha256(0,proof.bytes,buf,sizeof(buf));
+    return(proof);
+}
+
 cJSON *LP_quotejson(struct LP_quoteinfo *qp)
 {
-    double price; char etomic[64],activesymbol[65]; cJSON *retjson = cJSON_CreateObject();
+    double price; char etomic[64],activesymbol[65],amountstr[LP_AMOUNTSTRLEN]; cJSON *retjson = cJSON_CreateObject();
     if ( jobj(retjson,"gui") == 0 )
         jaddstr(retjson,"gui",qp->gui[0] != 0 ? qp->gui : LP_gui);
     jaddstr(retjson,"uuid",qp->uuidstr);
@@ -53,6 +64,21 @@ cJSON *LP_quotejson(struct LP_quoteinfo *qp)
         jaddnum(retjson,"gtc",qp->gtc);
     if ( qp->fill != 0 )
         jaddnum(retjson,"fill",qp->fill);
+    if ( qp->locktime != 0 )
+        jaddnum(retjson,"locktime",qp->locktime);
+    if ( qp->caps != 0 )
+    {
+        jaddnum(retjson,"caps",qp->caps);
+        if ( bits256_cmp(qp->srchash,G.LP_mypub25519) == 0 )
+            LP_caps_sigadd(retjson,qp);
+    }
+    if ( qp->reqcaps != 0 )
+        jaddnum(retjson,"requirecaps",qp->reqcaps);
+    if ( qp->internal != 0 )
+    {
+        jaddnum(retjson,"internal",qp->internal);
+        jaddbits256(retjson,"internalproof",LP_internal_proof(qp));
+    }
     jadd64bits(retjson,"aliceid",qp->aliceid);
     jaddnum(retjson,"tradeid",qp->tradeid);
     jaddstr(retjson,"base",qp->srccoin);
@@ -101,9 +127,13 @@ cJSON *LP_quotejson(struct LP_quoteinfo *qp)
     }
     if ( qp->desttxfee != 0 )
         jadd64bits(retjson,"desttxfee",qp->desttxfee);
+    if ( qp->satoshis != 0 && LP_amount_align(amountstr,qp->srcamountstr,qp->satoshis) == 0 )
+        jaddstr(retjson,"srcamountstr",amountstr);
     if ( qp->destsatoshis != 0 )
     {
         jadd64bits(retjson,"destsatoshis",qp->destsatoshis);
+        if ( LP_amount_align(amountstr,qp->destamountstr,qp->destsatoshis) == 0 )
+            jaddstr(retjson,"destamountstr",amountstr);
         if ( qp->satoshis != 0 )
         {
             price = (double)qp->destsatoshis / (qp->satoshis - qp->txfee);
@@ -117,6 +147,18 @@ cJSON *LP_quotejson(struct LP_quoteinfo *qp)
     return(retjson);
 }
 
+int32_t LP_quote_amountparse(char *amountstr,char *jsonstr,uint64_t satoshis)
+{
+    if ( jsonstr == 0 )
+    {
+        LP_amount_fromsatoshis(amountstr,satoshis);
+        return(0);
+    }
+    if ( LP_amount_canonical(amountstr,jsonstr) < 0 || LP_amount_satoshis(amountstr) != satoshis )
+        return(-1);
+    return(0);
+}
+
 int32_t LP_quoteparse(struct LP_quoteinfo *qp,cJSON *argjson)
 {
     uint32_t rid,qid; char etomic[64],activesymbol[65],*etomicstr;
@@ -174,8 +216,25 @@ int32_t LP_quoteparse(struct LP_quoteinfo *qp,cJSON *argjson)
     }
     qp->destsatoshis = j64bits(argjson,"destsatoshis");
     qp->desttxfee = j64bits(argjson,"desttxfee");
+    // a peer without decimal amounts sends satoshis only, one that has them must send both sides at those satoshis
+    if ( LP_quote_amountparse(qp->srcamountstr,jstr(argjson,"srcamountstr"),qp->satoshis) < 0 || LP_quote_amountparse(qp->destamountstr,jstr(argjson,"destamountstr"),qp->destsatoshis) < 0 )
+    {
+        printf("quote uuid.%s amounts (%s %s) dont match satoshis %.8f %.8f\n",qp->uuidstr,jstr(argjson,"srcamountstr"),jstr(argjson,"destamountstr"),dstr(qp->satoshis),dstr(qp->destsatoshis));
+        return(-1);
+    }
     qp->R.requestid = juint(argjson,"requestid");
     qp->R.quoteid = juint(argjson,"quoteid");
+    qp->locktime = juint(argjson,"locktime");
+    qp->caps = juint(argjson,"caps");
+    qp->reqcaps = juint(argjson,"requirecaps");
+    if ( (qp->internal= juint(argjson,"internal")) != 0 )
+    {
+        if ( bits256_nonz(G.LP_internalsecret) == 0 || bits256_cmp(jbits256(argjson,"internalproof"),LP_internal_proof(qp)) != 0 )
+        {
+            printf("invalid internal transfer proof for uuid.%s\n",qp->uuidstr);
+            return(-1);
+        }
+    }
     if ( qp->R.requestid == 0 )
     {
         rid = basilisk_requestid(&qp->R);
@@ -209,6 +268,8 @@ int32_t LP_quoteinfoinit(struct LP_quoteinfo *qp,struct LP_utxoinfo *utxo,char *
     LP_txfees(&qp->txfee,&qp->desttxfee,utxo->coin,qp->destcoin);
     qp->satoshis = satoshis;//(destsatoshis / price) + 0.49;
     qp->destsatoshis = destsatoshis;
+    LP_amount_fromsatoshis(qp->srcamountstr,satoshis);
+    LP_amount_fromsatoshis(qp->destamountstr,destsatoshis);
     /*if ( qp->txfee >= qp->satoshis || qp->txfee >= utxo->deposit.value || utxo->deposit.value < LP_DEPOSITSATOSHIS(qp->satoshis) ) //utxo->iambob == 0 ||
     {
         printf("quoteinit error.(%d %d %d %d) %.8f vs %.8f\n",utxo->iambob == 0,qp->txfee >= qp->satoshis,qp->txfee >= utxo->deposit.value,utxo->deposit.value < LP_DEPOSITSATOSHIS(qp->satoshis),dstr(utxo->deposit.value),dstr(LP_DEPOSITSATOSHIS(qp->satoshis)));
@@ -283,9 +344,63 @@ int32_t LP_bitcoinsig_add(cJSON *item,bits256 priv,uint8_t *pubsecp,bits256 sigh
     return(-1);
 }
 
-bits256 LP_price_sighash(uint32_t timestamp,uint8_t *pubsecp,bits256 pubkey,char *base,char *rel,uint64_t price64)
+bits256 LP_caps_sighash(struct LP_quoteinfo *qp)
+{
+    uint8_t buf[sizeof(bits256)*2 + sizeof(qp->aliceid) + sizeof(qp->caps) + sizeof(qp->quotetime)]; bits256 sighash; int32_t len = 0;
+    memcpy(&buf[len],qp->srchash.bytes,sizeof(qp->srchash)), len += sizeof(qp->srchash);
+    memcpy(&buf[len],qp->desthash.bytes,sizeof(qp->desthash)), len += sizeof(qp->desthash);
+    memcpy(&buf[len],&qp->aliceid,sizeof(qp->aliceid)), len += sizeof(qp->aliceid);
+    memcpy(&buf[len],&qp->caps,sizeof(qp->caps)), len += sizeof(qp->caps);
+    memcpy(&buf[len],&qp->quotetime,sizeof(qp->quotetime)), len += sizeof(qp->quotetime);
+    vcalc_sha256(0,sighash.bytes,buf,len);
+    return(sighash);
+}
+
+// the maker signs the caps it answers a request with, so a relay cant upgrade them to satisfy requirecaps
+int32_t LP_caps_sigadd(cJSON *item,struct LP_quoteinfo *qp)
 {
-    uint8_t buf[sizeof(pubkey) + 33 + sizeof(uint64_t)*3 + sizeof(timestamp)]; uint64_t basebits,relbits; bits256 sighash;
+    cJSON *sigjson; int32_t siglen;
+    sigjson = cJSON_CreateObject();
+    if ( (siglen= LP_bitcoinsig_add(sigjson,G.LP_privkey,G.LP_pubsecp,LP_caps_sighash(qp))) > 0 )
+        jaddstr(item,"capssig",jstr(sigjson,"sig"));
+    free_json(sigjson);
+    return(siglen);
+}
+
+int32_t LP_caps_sigcheck(cJSON *argjson,struct LP_quoteinfo *qp)
+{
+    static void *ctx; uint8_t pub33[33],sig[65]; char *sigstr; struct LP_pubkey_info *pubp;
+    if ( ctx == 0 )
+        ctx = bitcoin_ctx();
+    if ( (pubp= LP_pubkeyfind(qp->srchash)) == 0 || pubp->pubsecp[0] == 0 || (sigstr= jstr(argjson,"capssig")) == 0 || strlen(sigstr) != 65*2 )
+        return(-1);
+    decode_hex(sig,65,sigstr);
+    if ( bitcoin_recoververify(ctx,"caps",sig,LP_caps_sighash(qp),pub33,0) != 0 || memcmp(pub33,pubp->pubsecp,33) != 0 )
+        return(-1);
+    return(0);
+}
+
+bits256 LP_price_liquidityhash(cJSON *item)
+{
+    uint8_t buf[sizeof(uint64_t)*6 + sizeof(uint32_t)]; uint64_t utxobits; int64_t vals[5]; uint32_t caps; int32_t len; bits256 liqhash; char *utxocoin;
+    utxobits = (utxocoin= jstr(item,"utxocoin")) != 0 ? stringbits(utxocoin) : 0;
+    vals[0] = jint(item,"n");
+    vals[1] = jdouble(item,"bal") * SATOSHIDEN + 0.5;
+    vals[2] = jdouble(item,"min") * SATOSHIDEN + 0.5;
+    vals[3] = jdouble(item,"max") * SATOSHIDEN + 0.5;
+    vals[4] = jdouble(item,"credits") * SATOSHIDEN + 0.5;
+    memcpy(buf,&utxobits,sizeof(utxobits));
+    memcpy(&buf[sizeof(utxobits)],vals,sizeof(vals));
+    len = (int32_t)(sizeof(utxobits) + sizeof(vals));
+    caps = juint(item,"caps");
+    memcpy(&buf[len],&caps,sizeof(caps)), len += sizeof(caps);
+    vcalc_sha256(0,liqhash.bytes,buf,len);
+    return(liqhash);
+}
+
+bits256 LP_price_sighash(uint32_t timestamp,uint8_t *pubsecp,bits256 pubkey,char *base,char *rel,uint64_t price64,bits256 *liqhashp)
+{
+    uint8_t buf[sizeof(pubkey) + 33 + sizeof(uint64_t)*3 + sizeof(timestamp) + sizeof(*liqhashp)]; int32_t len; uint64_t basebits,relbits; bits256 sighash;
     basebits = stringbits(base);
     relbits = stringbits(rel);
     memcpy(buf,pubkey.bytes,sizeof(pubkey));
@@ -294,7 +409,10 @@ bits256 LP_price_sighash(uint32_t timestamp,uint8_t *pubsecp,bits256 pubkey,char
     memcpy(&buf[sizeof(pubkey)+33+sizeof(price64)],&basebits,sizeof(basebits));
     memcpy(&buf[sizeof(pubkey)+33+sizeof(price64)+sizeof(basebits)],&relbits,sizeof(relbits));
     memcpy(&buf[sizeof(pubkey)+33+sizeof(price64)+sizeof(basebits)+sizeof(relbits)],&timestamp,sizeof(timestamp));
-    vcalc_sha256(0,sighash.bytes,buf,sizeof(buf));
+    len = (int32_t)(sizeof(pubkey)+33+sizeof(price64)+sizeof(basebits)+sizeof(relbits)+sizeof(timestamp));
+    if ( liqhashp != 0 ) // LP_PRICE_SIGVERSION 1, legacy signatures stop at the timestamp
+        memcpy(&buf[len],liqhashp->bytes,sizeof(*liqhashp)), len += sizeof(*liqhashp);
+    vcalc_sha256(0,sighash.bytes,buf,len);
     return(sighash);
 }
 
@@ -326,7 +444,7 @@ int32_t LP_utxos_sigcheck(uint32_t timestamp,char *sigstr,char *pubsecpstr,bits2
     if ( ctx == 0 )
         ctx = bitcoin_ctx();
     pubp = LP_pubkeyfind(pubkey);
-    if ( (pubp == 0 || pubp->numerrors < LP_MAXPUBKEY_ERRORS) && sigstr != 0 && pubsecpstr != 0 && strlen(sigstr) == 65*2 && strlen(pubsecpstr) == 33 *2 )
+    if ( LP_pubkey_ignored(pubp) == 0 && sigstr != 0 && pubsecpstr != 0 && strlen(sigstr) == 65*2 && strlen(pubsecpstr) == 33 *2 )
     {
         decode_hex(sig,65,sigstr);
         decode_hex(pubsecp,33,pubsecpstr);
@@ -336,12 +454,7 @@ int32_t LP_utxos_sigcheck(uint32_t timestamp,char *sigstr,char *pubsecpstr,bits2
         {
             static uint32_t counter;
             if ( counter++ <= LP_MAXPUBKEY_ERRORS )
-            {
-                if ( pubp != 0 )
-                    pubp->numerrors++;
-                if ( pubp != 0 && pubp->numerrors > LP_MAXPUBKEY_ERRORS/2 )
-                    printf("LP_utxos_sigcheck failure.%d, probably from %s with older version\n",pubp!=0?pubp->numerrors:-1,bits256_str(str,pubkey));
-            }
+                printf("LP_utxos_sigcheck failure, probably from %s with older version\n",bits256_str(str,pubkey));
             retval = -1;
         } else retval = 0;
     }
@@ -403,22 +516,30 @@ return;
     }
 }
 
-int32_t LP_price_sigcheck(uint32_t timestamp,char *sigstr,char *pubsecpstr,bits256 pubkey,char *base,char *rel,uint64_t price64)
+int32_t LP_price_sigcheck(uint32_t timestamp,char *sigstr,char *pubsecpstr,bits256 pubkey,char *base,char *rel,uint64_t price64,bits256 *liqhashp)
 {
     static void *ctx; int32_t retval=-1; uint8_t pub33[33],pubsecp[33],sig[65]; bits256 sighash; struct LP_pubkey_info *pubp;
     if ( ctx == 0 )
         ctx = bitcoin_ctx();
     pubp = LP_pubkeyfind(pubkey);
-    if ( (pubp == 0 || pubp->numerrors < LP_MAXPUBKEY_ERRORS) && sigstr != 0 && pubsecpstr != 0 && strlen(sigstr) == 65*2 && strlen(pubsecpstr) == 33 *2 )
+    if ( pubp == 0 || pubp->pubsecp[0] == 0 )
+    {
+        // until the signed notify binds a pubsecp to this pubkey there is no advertised maker to check against
+        return(-1);
+    }
+    if ( LP_pubkey_ignored(pubp) == 0 && sigstr != 0 && pubsecpstr != 0 && strlen(sigstr) == 65*2 && strlen(pubsecpstr) == 33 *2 )
     {
         decode_hex(sig,65,sigstr);
         decode_hex(pubsecp,33,pubsecpstr);
-        sighash = LP_price_sighash(timestamp,pubsecp,pubkey,base,rel,price64);
+        if ( memcmp(pubp->pubsecp,pubsecp,33) != 0 )
+        {
+            printf("LP_price_sigcheck pubsecp doesnt match advertised maker\n");
+            return(-1);
+        }
+        sighash = LP_price_sighash(timestamp,pubsecp,pubkey,base,rel,price64,liqhashp);
         retval = bitcoin_recoververify(ctx,"price",sig,sighash,pub33,0);
         if ( memcmp(pub33,pubsecp,33) != 0 || retval != 0 )
         {
-            if ( pubp != 0 )
-                pubp->numerrors++;
             printf("LP_price_sigcheck failure\n");
             retval = -1;
         }
@@ -431,16 +552,112 @@ int32_t LP_price_sigcheck(uint32_t timestamp,char *sigstr,char *pubsecpstr,bits2
     return(retval);
 }
 
+int32_t LP_price_argsigcheck(cJSON *argjson,bits256 pubkey,char *base,char *rel,uint64_t price64)
+{
+    bits256 liqhash;
+    if ( juint(argjson,"sigversion") >= LP_PRICE_SIGVERSION )
+    {
+        liqhash = LP_price_liquidityhash(argjson);
+        return(LP_price_sigcheck(juint(argjson,"timestamp"),jstr(argjson,"liqsig"),jstr(argjson,"pubsecp"),pubkey,base,rel,price64,&liqhash));
+    }
+    // older nodes only sign the price itself, accepted until they have all upgraded
+    return(LP_price_sigcheck(juint(argjson,"timestamp"),jstr(argjson,"sig"),jstr(argjson,"pubsecp"),pubkey,base,rel,price64,0));
+}
+
 int32_t LP_price_sigadd(cJSON *item,uint32_t timestamp,bits256 priv,uint8_t *pubsecp,bits256 pubkey,char *base,char *rel,uint64_t price64)
+{
+    bits256 sighash,liqhash; cJSON *sigjson; int32_t siglen;
+    sighash = LP_price_sighash(timestamp,pubsecp,pubkey,base,rel,price64,0);
+    if ( LP_bitcoinsig_add(item,priv,pubsecp,sighash) < 0 )
+        return(-1);
+    liqhash = LP_price_liquidityhash(item);
+    sighash = LP_price_sighash(timestamp,pubsecp,pubkey,base,rel,price64,&liqhash);
+    sigjson = cJSON_CreateObject();
+    if ( (siglen= LP_bitcoinsig_add(sigjson,priv,pubsecp,sighash)) > 0 )
+    {
+        jaddstr(item,"liqsig",jstr(sigjson,"sig"));
+        jaddnum(item,"sigversion",LP_PRICE_SIGVERSION);
+    }
+    free_json(sigjson);
+    return(siglen);
+}
+
+bits256 LP_swapabort_sighash(uint32_t timestamp,uint8_t *pubsecp,bits256 pubkey,char *uuidstr)
+{
+    uint8_t buf[sizeof(pubkey) + 33 + 64 + sizeof(timestamp)]; bits256 sighash;
+    memset(buf,0,sizeof(buf));
+    memcpy(buf,pubkey.bytes,sizeof(pubkey));
+    memcpy(&buf[sizeof(pubkey)],pubsecp,33);
+    strncpy((char *)&buf[sizeof(pubkey)+33],uuidstr,64);
+    memcpy(&buf[sizeof(pubkey)+33+64],&timestamp,sizeof(timestamp));
+    vcalc_sha256(0,sighash.bytes,buf,sizeof(buf));
+    return(sighash);
+}
+
+int32_t LP_swapabort_sigadd(cJSON *item,uint32_t timestamp,bits256 priv,uint8_t *pubsecp,bits256 pubkey,char *uuidstr)
 {
     bits256 sighash;
-    sighash = LP_price_sighash(timestamp,pubsecp,pubkey,base,rel,price64);
+    sighash = LP_swapabort_sighash(timestamp,pubsecp,pubkey,uuidstr);
     return(LP_bitcoinsig_add(item,priv,pubsecp,sighash));
 }
 
+int32_t LP_swapabort_sigcheck(uint32_t timestamp,char *sigstr,char *pubsecpstr,bits256 pubkey,char *uuidstr)
+{
+    static void *ctx; int32_t retval=-1; uint8_t pub33[33],pubsecp[33],sig[65]; bits256 sighash; struct LP_pubkey_info *pubp;
+    if ( ctx == 0 )
+        ctx = bitcoin_ctx();
+    pubp = LP_pubkeyfind(pubkey);
+    if ( LP_pubkey_ignored(pubp) == 0 && sigstr != 0 && pubsecpstr != 0 && strlen(sigstr) == 65*2 && strlen(pubsecpstr) == 33 *2 )
+    {
+        decode_hex(sig,65,sigstr);
+        decode_hex(pubsecp,33,pubsecpstr);
+        if ( pubp != 0 && pubp->pubsecp[0] != 0 && memcmp(pubp->pubsecp,pubsecp,33) != 0 )
+        {
+            printf("LP_swapabort_sigcheck pubsecp mismatch\n");
+            return(-1);
+        }
+        sighash = LP_swapabort_sighash(timestamp,pubsecp,pubkey,uuidstr);
+        retval = bitcoin_recoververify(ctx,"swapabort",sig,sighash,pub33,0);
+        if ( memcmp(pub33,pubsecp,33) != 0 || retval != 0 )
+        {
+            printf("LP_swapabort_sigcheck failure\n");
+            retval = -1;
+        } else retval = 0;
+    }
+    return(retval);
+}
+
+char *LP_swapaborted_recv(cJSON *argjson)
+{
+    bits256 pubkey; char *uuidstr,*argstr; uint32_t timestamp;
+    pubkey = jbits256(argjson,"pubkey");
+    if ( (uuidstr= jstr(argjson,"uuid")) != 0 && bits256_nonz(pubkey) != 0 )
+    {
+        if ( bits256_cmp(pubkey,G.LP_mypub25519) == 0 )
+            return(clonestr("{\"result\":\"success\"}"));
+        // same 60 second window LP_reserved_msg applies, a captured abort cant be replayed into a later swap
+        timestamp = juint(argjson,"timestamp");
+        if ( timestamp == 0 || time(NULL) > timestamp+60 || timestamp > time(NULL)+60 )
+            return(clonestr("{\"error\":\"stale swap_aborted\"}"));
+        if ( LP_swapabort_sigcheck(timestamp,jstr(argjson,"sig"),jstr(argjson,"pubsecp"),pubkey,uuidstr) != 0 )
+            return(clonestr("{\"error\":\"sig failure\"}"));
+        if ( LP_swapabort_set(uuidstr,pubkey) == 0 )
+        {
+            printf("counterparty aborted swap uuid.%s\n",uuidstr);
+            if ( IPC_ENDPOINT >= 0 && (argstr= jprint(argjson,0)) != 0 )
+            {
+                LP_queuecommand(0,argstr,IPC_ENDPOINT,-1,0);
+                free(argstr);
+            }
+        }
+        return(clonestr("{\"result\":\"success\"}"));
+    }
+    return(clonestr("{\"error\":\"missing fields in swap_aborted\"}"));
+}
+
 char *LP_pricepings(void *ctx,char *myipaddr,int32_t pubsock,char *base,char *rel,double price)
 {
-    struct iguana_info *basecoin,*relcoin,*kmd; struct LP_address *ap; char pubsecpstr[67]; uint32_t numutxos,timestamp; uint64_t price64,median,minsize,maxsize; bits256 zero; cJSON *reqjson;
+    struct iguana_info *basecoin,*relcoin,*kmd; struct LP_address *ap; char pubsecpstr[67]; uint32_t numutxos,timestamp; uint64_t price64,median,minsize,maxsize,balance; int64_t remaining; bits256 zero; cJSON *reqjson;
     reqjson = cJSON_CreateObject();
     if ( (basecoin= LP_coinfind(base)) != 0 && (relcoin= LP_coinfind(rel)) != 0 )//&& basecoin->electrum == 0 )//&& relcoin->electrum == 0 )
     {
@@ -472,28 +689,58 @@ char *LP_pricepings(void *ctx,char *myipaddr,int32_t pubsock,char *base,char *re
         if ( (numutxos= LP_address_minmax(1,&median,&minsize,&maxsize,basecoin,basecoin->smartaddr)) != 0 )
         {
             //printf("send %s numutxos.%d median %.8f min %.8f max %.8f\n",base,numutxos,dstr(median),dstr(minsize),dstr(maxsize));
+            balance = median * numutxos;
+            if ( (remaining= LP_myvolume(base,rel)) > 0 ) // sized order, advertise what is left rather than the whole wallet
+            {
+                balance = MIN(balance,remaining);
+                maxsize = MIN(maxsize,remaining);
+                minsize = MIN(minsize,maxsize);
+            }
             jaddstr(reqjson,"utxocoin",base);
             jaddnum(reqjson,"n",numutxos);
-            jaddnum(reqjson,"bal",dstr(median) * numutxos);
+            jaddnum(reqjson,"bal",dstr(balance));
             jaddnum(reqjson,"min",dstr(minsize));
             jaddnum(reqjson,"max",dstr(maxsize));
         }
+        jaddnum(reqjson,"caps",LP_caps(basecoin,relcoin));
         LP_price_sigadd(reqjson,timestamp,G.LP_privkey,G.LP_pubsecp,G.LP_mypub25519,base,rel,price64);
         LP_reserved_msg(0,base,rel,zero,jprint(reqjson,1));
+        LP_pingprice_set(base,rel,price);
         return(clonestr("{\"result\":\"success\"}"));
     } else return(clonestr("{\"error\":\"electrum node cant post bob asks\"}"));
 }
 
 char *LP_postprice_recv(cJSON *argjson)
 {
-    bits256 pubkey; double price; uint8_t pubkey33[33]; char *base,*rel,*argstr,coinaddr[64];
+    bits256 pubkey; double price; uint64_t price64; uint8_t pubkey33[33]; char *base,*rel,*argstr,coinaddr[64]; struct LP_pubkey_info *pubp; struct LP_priceinfo *basepp; int32_t relid; uint32_t timestamp;
     //printf("PRICE POSTED.(%s)\n",jprint(argjson,0));
+    if ( (base= jstr(argjson,"base")) != 0 && (rel= jstr(argjson,"rel")) != 0 && jdouble(argjson,"price") == 0. && j64bits(argjson,"price64") == 0 )
+    {
+        // a signed zero price cancels the maker's order for this pair
+        pubkey = jbits256(argjson,"pubkey");
+        timestamp = juint(argjson,"timestamp");
+        if ( timestamp == 0 || time(NULL) > timestamp+LP_ORDERBOOK_DURATION || timestamp > time(NULL)+60 )
+            return(clonestr("{\"error\":\"stale cancel\"}"));
+        if ( bits256_nonz(pubkey) == 0 || LP_price_argsigcheck(argjson,pubkey,base,rel,0) != 0 )
+            return(clonestr("{\"error\":\"sig failure\"}"));
+        if ( (pubp= LP_pubkeyfind(pubkey)) != 0 && (basepp= LP_priceinfoptr(&relid,base,rel)) != 0 )
+            LP_pubkey_quote_remove(pubp,basepp->ind,relid);
+        if ( IPC_ENDPOINT >= 0 && (argstr= jprint(argjson,0)) != 0 )
+        {
+            LP_queuecommand(0,argstr,IPC_ENDPOINT,-1,0);
+            free(argstr);
+        }
+        return(clonestr("{\"result\":\"success\",\"status\":\"cancelled\"}"));
+    }
     if ( (base= jstr(argjson,"base")) != 0 && (rel= jstr(argjson,"rel")) != 0 && (price= jdouble(argjson,"price")) > SMALLVAL )
     {
         pubkey = jbits256(argjson,"pubkey");
+        price64 = j64bits(argjson,"price64");
+        if ( fabs(price * SATOSHIDEN - (double)price64) > 1. )
+            return(clonestr("{\"error\":\"price doesnt match signed price64\"}"));
         if ( bits256_nonz(pubkey) != 0 )
         {
-            if ( LP_price_sigcheck(juint(argjson,"timestamp"),jstr(argjson,"sig"),jstr(argjson,"pubsecp"),pubkey,base,rel,j64bits(argjson,"price64")) == 0 )
+            if ( LP_price_argsigcheck(argjson,pubkey,base,rel,price64) == 0 )
             {
                 if ( IPC_ENDPOINT >= 0 )
                 {
@@ -505,6 +752,8 @@ char *LP_postprice_recv(cJSON *argjson)
                 }
                 //printf("call pricefeed update\n");
                 LP_pricefeedupdate(pubkey,base,rel,price,jstr(argjson,"utxocoin"),jint(argjson,"n"),jdouble(argjson,"bal")*SATOSHIDEN,jdouble(argjson,"min")*SATOSHIDEN,jdouble(argjson,"max")*SATOSHIDEN,jdouble(argjson,"credits")*SATOSHIDEN);
+                if ( (pubp= LP_pubkeyfind(pubkey)) != 0 && (basepp= LP_priceinfoptr(&relid,base,rel)) != 0 )
+                    LP_pubkey_capsset(pubp,basepp->ind,relid,juint(argjson,"caps"));
                 return(clonestr("{\"result\":\"success\"}"));
             }
             else
@@ -546,6 +795,8 @@ int32_t LP_pubkey_sigadd(cJSON *item,uint32_t timestamp,bits256 priv,bits256 pub
 int32_t LP_pubkey_sigcheck(struct LP_pubkey_info *pubp,cJSON *item)
 {
     int32_t i,len,siglen,retval=-1; uint8_t rmd160[20],checkrmd160[20],pubsecp[33],sig[65],zeroes[20]; char *sigstr,*hexstr,*pubsecpstr;
+    if ( LP_pubkey_ignored(pubp) != 0 )
+        return(-1);
     if ( (hexstr= jstr(item,"rmd160")) != 0 && strlen(hexstr) == 2*sizeof(rmd160) )
     {
         decode_hex(rmd160,sizeof(rmd160),hexstr);
@@ -578,7 +829,7 @@ int32_t LP_pubkey_sigcheck(struct LP_pubkey_info *pubp,cJSON *item)
                                 }
                                 pubp->timestamp = juint(item,"timestamp");
                                 retval = 0;
-                            } else pubp->numerrors++;
+                            }
                         }
                     }
                     else if ( 0 )
@@ -0,0 +1,147 @@
+
+/******************************************************************************
+ * Copyright © 2014-2018 The SuperNET Developers.                             *
+ *                                                                            *
+ * See the AUTHORS, DEVELOPER-AGREEMENT and LICENSE files at                  *
+ * the top-level directory of this distribution for the individual copyright  *
+ * holder information and the developer policies on copyright and licensing.  *
+ *                                                                            *
+ * Unless otherwise agreed in a custom licensing agreement, no part of the    *
+ * SuperNET software, including this file may be copied, modified, propagated *
+ * or distributed except according to the terms contained in the LICENSE file *
+ *                                                                            *
+ * Removal or modification of this copyright notice is prohibited.            *
+ *                                                                            *
+ ******************************************************************************/
+//
+//  LP_signer.c
+//  marketmaker
+//
+//  transaction signers: the local in-process key, or an out of process signer (hardware wallet bridge, signing service)
+//  reached over json-rpc for coins configured with "signer" and "signer_pubkey"
+//
+
+#define LP_SIGNER_TIMEOUT 300
+#define LP_SIGNER_POLLINTERVAL 3
+
+struct LP_signer
+{
+    char *name;
+    int32_t (*sign)(void *ctx,struct iguana_info *coin,char *symbol,uint8_t *sig,uint8_t *pubkey33,bits256 sighash,bits256 privkey);
+};
+
+int32_t LP_signer_local(void *ctx,struct iguana_info *coin,char *symbol,uint8_t *sig,uint8_t *pubkey33,bits256 sighash,bits256 privkey)
+{
+    int32_t siglen;
+    if ( bits256_nonz(privkey) == 0 || (siglen= bitcoin_sign(ctx,symbol,sig,sighash,privkey,0)) <= 0 )
+        return(0);
+    bitcoin_pubkey33(ctx,pubkey33,privkey);
+    return(siglen);
+}
+
+// {"method":"signhash","params":[{"coin":"KMD","pubkey":"02..","sighash":"..","uuid":".."}]}
+// result is {"signature":"<DER hex>"} once approved, {"pending":true} while the device waits on the user, or an error to decline
+int32_t LP_signer_remote(void *ctx,struct iguana_info *coin,char *symbol,uint8_t *sig,uint8_t *pubkey33,bits256 sighash,bits256 privkey)
+{
+    struct basilisk_swap *swap = LP_threadswap; cJSON *params,*item,*retjson; char *paramstr,*retstr,*sigstr,pubstr[67],str[65],msg[512]; uint32_t starttime,timeout; int32_t len,declined = 0,siglen = 0;
+    if ( (timeout= coin->signertimeout) == 0 )
+        timeout = LP_SIGNER_TIMEOUT;
+    init_hexbytes_noT(pubstr,coin->signerpub33,33);
+    item = cJSON_CreateObject();
+    jaddstr(item,"coin",coin->symbol);
+    jaddstr(item,"pubkey",pubstr);
+    jaddbits256(item,"sighash",sighash);
+    if ( swap != 0 )
+        jaddstr(item,"uuid",swap->uuidstr);
+    params = cJSON_CreateArray();
+    jaddi(params,item);
+    paramstr = jprint(params,1);
+    snprintf(msg,sizeof(msg),"%s sighash %s from %s via %s",coin->symbol,bits256_str(str,sighash),coin->smartaddr,coin->signerurl);
+    LP_swaplog(swap,"WaitingForSignature",msg);
+    printf("waiting up to %us for external signature: %s\n",timeout,msg);
+    starttime = (uint32_t)time(NULL);
+    while ( siglen == 0 && declined == 0 && time(NULL) < starttime + timeout )
+    {
+        if ( (retstr= bitcoind_RPC(0,"signer",coin->signerurl,"","signhash",paramstr,LP_SIGNER_POLLINTERVAL * 10)) != 0 )
+        {
+            if ( (retjson= cJSON_Parse(retstr)) != 0 )
+            {
+                if ( (sigstr= jstr(retjson,"signature")) != 0 && (len= is_hexstr(sigstr,0)) > 16 && len <= 144 && (len & 1) == 0 )
+                {
+                    siglen = len >> 1;
+                    decode_hex(sig,siglen,sigstr);
+                }
+                else if ( is_cJSON_True(jobj(retjson,"pending")) == 0 )
+                    declined = 1;
+                free_json(retjson);
+            } else declined = 1;
+            if ( declined != 0 )
+                printf("%s signer declined (%s)\n",coin->symbol,retstr);
+            free(retstr);
+        }
+        if ( siglen == 0 && declined == 0 )
+        {
+            if ( swap != 0 && swap->aborted != 0 )
+                break;
+            sleep(LP_SIGNER_POLLINTERVAL);
+        }
+    }
+    free(paramstr);
+    if ( siglen > 0 && bitcoin_verify(ctx,sig,siglen,sighash,coin->signerpub33,33) < 0 )
+    {
+        printf("%s signer returned a signature that doesnt verify against %s\n",coin->symbol,pubstr);
+        siglen = 0;
+    }
+    if ( siglen > 0 )
+        memcpy(pubkey33,coin->signerpub33,33);
+    LP_swaplog(swap,siglen > 0 ? "SignatureReceived" : (declined != 0 ? "SignatureDeclined" : "SignatureTimeout"),msg);
+    return(siglen);
+}
+
+struct LP_signer LP_localsigner = { "local", LP_signer_local }, LP_remotesigner = { "remote", LP_signer_remote };
+
+// a vin is routed to the external signer when it spends to the signer pubkey, whatever privkey the caller had at hand
+struct LP_signer *LP_signer_select(struct iguana_info *coin,uint8_t *script,int32_t scriptlen)
+{
+    uint8_t rmd160[20],spendscript[64]; int32_t spendlen;
+    if ( coin != 0 && coin->signerurl[0] != 0 && script != 0 )
+    {
+        calc_rmd160_sha256(rmd160,coin->signerpub33,33);
+        spendlen = bitcoin_standardspend(spendscript,0,rmd160);
+        if ( scriptlen == spendlen && memcmp(script,spendscript,spendlen) == 0 )
+            return(&LP_remotesigner);
+        if ( scriptlen == 35 && script[0] == 33 && script[34] == SCRIPT_OP_CHECKSIG && memcmp(script+1,coin->signerpub33,33) == 0 )
+            return(&LP_remotesigner);
+    }
+    return(&LP_localsigner);
+}
+
+int32_t LP_signer_sign(void *ctx,char *symbol,uint8_t *sig,uint8_t *pubkey33,bits256 sighash,bits256 privkey,uint8_t *script,int32_t scriptlen)
+{
+    struct iguana_info *coin; struct LP_signer *signer;
+    coin = LP_coinfind(symbol);
+    signer = LP_signer_select(coin,script,scriptlen);
+    return((*signer->sign)(ctx,coin,symbol,sig,pubkey33,sighash,privkey));
+}
+
+// no hot key for the coin: trade from the signer address, swap session keys are still derived from the passphrase
+void LP_signer_coininit(struct iguana_info *coin)
+{
+    if ( coin->signerurl[0] == 0 || coin->etomic[0] != 0 )
+        return;
+    memcpy(coin->pubkey33,coin->signerpub33,33);
+    bitcoin_address(coin->symbol,coin->smartaddr,coin->taddr,coin->pubtype,coin->signerpub33,33);
+    LP_address(coin,coin->smartaddr);
+    if ( coin->electrum == 0 && coin->inactive == 0 && coin->userpass[0] != 0 )
+        LP_importaddress(coin->symbol,coin->smartaddr);
+    printf("%s trades from %s, signed by %s\n",coin->symbol,coin->smartaddr,coin->signerurl);
+}
+
+bits256 LP_swap_privkey(struct iguana_info *coin,char *coinaddr)
+{
+    bits256 privkey;
+    privkey = LP_privkey(coin->symbol,coinaddr,coin->taddr);
+    if ( bits256_nonz(privkey) == 0 && coin->signerurl[0] != 0 && strcmp(coinaddr,coin->smartaddr) == 0 )
+        privkey = G.LP_privkey;
+    return(privkey);
+}
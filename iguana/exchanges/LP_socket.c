@@ -325,6 +325,209 @@ int32_t LP_socketrecv(int32_t sock,uint8_t *recvbuf,int32_t maxlen)
     return(recvlen);
 }
 
+// "proxy":"127.0.0.1:9050" at startup or per coin routes coin rpc, electrum and http through socks5, names resolved by the proxy.
+// a coin can opt out with "proxy":"none". nanomsg has no proxy support, p2p connects stay direct
+char LP_proxy[128];
+extern char *(*bitcoind_proxyfunc)(char *debugstr,char *url);
+
+int32_t LP_proxy_normalize(char *dest,int32_t maxlen,char *proxy)
+{
+    char *hostport;
+    if ( strcmp(proxy,"none") == 0 )
+        hostport = 0;
+    else if ( strncmp(proxy,"socks5h://",10) == 0 )
+        hostport = proxy + 10;
+    else if ( strncmp(proxy,"socks5://",9) == 0 )
+        hostport = proxy + 9;
+    else if ( strstr(proxy,"://") == 0 )
+        hostport = proxy;
+    else return(-1);
+    if ( hostport == 0 )
+        safecopy(dest,"none",maxlen);
+    else if ( strchr(hostport,':') == 0 || strlen(hostport) + 10 >= maxlen )
+        return(-1);
+    else snprintf(dest,maxlen,"socks5h://%s",hostport);
+    return(0);
+}
+
+// host part of [scheme://][user:pass@]host[:port][/path], 127.0.0.0/8 as four numbers, localhost or ::1
+int32_t LP_url_isloopback(char *url)
+{
+    char host[256],*ptr; int32_t i,len,octet,numoctets = 0;
+    ptr = (ptr= strstr(url,"://")) != 0 ? ptr + 3 : url;
+    for (len=0; ptr[len]!=0 && ptr[len]!='/' && ptr[len]!='?' && ptr[len]!='#'; len++)
+        ;
+    for (i=len-1; i>=0; i--)
+        if ( ptr[i] == '@' )
+        {
+            ptr += i + 1, len -= i + 1;
+            break;
+        }
+    if ( len <= 0 || len >= sizeof(host) )
+        return(0);
+    memcpy(host,ptr,len), host[len] = 0;
+    if ( host[0] == '[' )
+        return(strncmp(host,"[::1]",5) == 0 && (host[5] == 0 || host[5] == ':'));
+    if ( (ptr= strchr(host,':')) != 0 )
+        *ptr = 0;
+    if ( strcmp(host,"localhost") == 0 )
+        return(1);
+    for (ptr=host; *ptr!=0; )
+    {
+        for (octet=i=0; ptr[i]>='0' && ptr[i]<='9' && i<3; i++)
+            octet = octet*10 + (ptr[i] - '0');
+        if ( i == 0 || octet > 255 || (numoctets == 0 && octet != 127) )
+            return(0);
+        numoctets++, ptr += i;
+        if ( *ptr == '.' && numoctets < 4 && ptr[1] != 0 )
+            ptr++;
+        else if ( *ptr != 0 )
+            return(0);
+    }
+    return(numoctets == 4);
+}
+
+char *LP_proxyfor(char *symbol)
+{
+    struct iguana_info *coin;
+    if ( symbol != 0 && (coin= LP_coinfind(symbol)) != 0 && coin->proxy[0] != 0 )
+        return(strcmp(coin->proxy,"none") == 0 ? 0 : coin->proxy);
+    return(LP_proxy[0] != 0 ? LP_proxy : 0);
+}
+
+// tor wont connect back to localhost, so a local daemon or signer is always reached directly
+char *LP_bitcoind_proxy(char *debugstr,char *url)
+{
+    if ( url == 0 || LP_url_isloopback(url) != 0 )
+        return(0);
+    return(LP_proxyfor(debugstr));
+}
+
+void LP_proxy_init(cJSON *argjson)
+{
+    if ( jstr(argjson,"proxy") != 0 && LP_proxy_normalize(LP_proxy,sizeof(LP_proxy),jstr(argjson,"proxy")) < 0 )
+    {
+        printf("invalid proxy (%s), expected host:port or socks5h://host:port\n",jstr(argjson,"proxy"));
+        exit(-1);
+    }
+    if ( strcmp(LP_proxy,"none") == 0 )
+        LP_proxy[0] = 0;
+    bitcoind_proxyfunc = LP_bitcoind_proxy;
+    if ( LP_proxy[0] != 0 )
+        printf("coin and http connections via %s, p2p stays direct\n",LP_proxy);
+}
+
+int32_t LP_recvall(int32_t sock,uint8_t *buf,int32_t len)
+{
+    int32_t n,recvlen = 0;
+    while ( recvlen < len )
+    {
+        if ( (n= LP_socketrecv(sock,&buf[recvlen],len - recvlen)) <= 0 )
+            return(-1);
+        recvlen += n;
+    }
+    return(recvlen);
+}
+
+// RFC 1928 connect with the destination passed as a hostname (ATYP 3) so no local dns lookup happens
+int32_t LP_socks5_connect(char *proxy,char *hostname,uint16_t port)
+{
+    uint8_t buf[512]; char proxyhost[128],host[256]; uint16_t proxyport; int32_t sock,len,n = 0;
+    if ( (proxyport= parse_ipaddr(proxyhost,proxy + strlen("socks5h://"))) == 0 )
+        return(-1);
+    safecopy(host,hostname,sizeof(host));
+    if ( strchr(host,':') != 0 && (port= parse_ipaddr(host,hostname)) == 0 )
+        return(-1);
+    if ( (len= (int32_t)strlen(host)) == 0 || len > 255 )
+        return(-1);
+    if ( (sock= LP_socket(0,proxyhost,proxyport)) < 0 )
+    {
+        printf("cant reach proxy %s\n",proxy);
+        return(-1);
+    }
+    buf[0] = 5, buf[1] = 1, buf[2] = 0; // version 5, one method, no auth
+    if ( LP_socketsend(sock,buf,3) != 3 || LP_recvall(sock,buf,2) != 2 || buf[0] != 5 || buf[1] != 0 )
+    {
+        printf("proxy %s refused the socks5 greeting\n",proxy);
+        closesocket(sock);
+        return(-1);
+    }
+    buf[n++] = 5, buf[n++] = 1, buf[n++] = 0, buf[n++] = 3, buf[n++] = len;
+    memcpy(&buf[n],host,len), n += len;
+    buf[n++] = (port >> 8), buf[n++] = (port & 0xff);
+    if ( LP_socketsend(sock,buf,n) != n || LP_recvall(sock,buf,4) != 4 || buf[0] != 5 || buf[1] != 0 )
+    {
+        printf("proxy %s couldnt connect to %s:%u\n",proxy,host,port);
+        closesocket(sock);
+        return(-1);
+    }
+    if ( buf[3] == 1 )
+        n = 4 + 2;
+    else if ( buf[3] == 4 )
+        n = 16 + 2;
+    else if ( buf[3] == 3 && LP_recvall(sock,buf,1) == 1 )
+        n = buf[0] + 2;
+    else n = -1;
+    if ( n < 0 || LP_recvall(sock,buf,n) != n )
+    {
+        closesocket(sock);
+        return(-1);
+    }
+    return(sock);
+}
+
+int32_t LP_coinsocket(char *symbol,char *hostname,uint16_t port)
+{
+    char *proxy;
+    if ( (proxy= LP_proxyfor(symbol)) != 0 && LP_url_isloopback(hostname) == 0 )
+        return(LP_socks5_connect(proxy,hostname,port));
+    return(LP_socket(0,hostname,port));
+}
+
+char *LP_proxy_check(char *symbol,char *url)
+{
+    cJSON *retjson,*checkjson; char *retstr,*proxy; struct iguana_info *coin,*tmp;
+    if ( url == 0 || url[0] == 0 )
+        url = "https://check.torproject.org/api/ip";
+    proxy = LP_proxyfor(symbol);
+    retjson = cJSON_CreateObject();
+    jaddstr(retjson,"proxy",LP_proxy[0] != 0 ? LP_proxy : "none");
+    jaddstr(retjson,"p2p","direct");
+    if ( symbol != 0 && symbol[0] != 0 )
+    {
+        jaddstr(retjson,"coin",symbol);
+        jaddstr(retjson,"coin_proxy",proxy != 0 ? proxy : "none");
+    }
+    else
+    {
+        cJSON *array = cJSON_CreateArray();
+        HASH_ITER(hh,LP_coins,coin,tmp)
+        {
+            if ( coin->proxy[0] != 0 )
+            {
+                cJSON *item = cJSON_CreateObject();
+                jaddstr(item,"coin",coin->symbol);
+                jaddstr(item,"proxy",coin->proxy);
+                jaddi(array,item);
+            }
+        }
+        jadd(retjson,"coin_proxies",array);
+    }
+    jaddstr(retjson,"url",url);
+    if ( (retstr= bitcoind_RPC(0,symbol != 0 && symbol[0] != 0 ? symbol : "curl",url,0,0,0,LP_HTTP_TIMEOUT)) != 0 )
+    {
+        if ( (checkjson= cJSON_Parse(retstr)) != 0 )
+        {
+            jadd(retjson,"check",checkjson);
+            if ( jobj(checkjson,"IsTor") != 0 )
+                jadd(retjson,"tor",is_cJSON_True(jobj(checkjson,"IsTor")) != 0 ? cJSON_CreateTrue() : cJSON_CreateFalse());
+        } else jaddstr(retjson,"check",retstr);
+        free(retstr);
+        jaddstr(retjson,"result",proxy != 0 ? "success" : "no proxy in use");
+    } else jaddstr(retjson,"error","check url unreachable through the proxy");
+    return(jprint(retjson,1));
+}
+
 struct electrum_info *Electrums[8192];
 int32_t Num_electrums;
 
@@ -475,8 +678,10 @@ void electrum_initial_requests(struct electrum_info *ep)
 
 int32_t electrum_kickstart(struct electrum_info *ep)
 {
+    if ( ep->sock < 0 ) // released, let LP_dedicatedloop exit
+        return(-1);
     closesocket(ep->sock);//, ep->sock = -1;
-    if ( (ep->sock= LP_socket(0,ep->ipaddr,ep->port)) < 0 )
+    if ( (ep->sock= LP_coinsocket(ep->symbol,ep->ipaddr,ep->port)) < 0 )
     {
         printf("error RE-connecting to %s:%u\n",ep->ipaddr,ep->port);
         return(-1);
@@ -491,40 +696,83 @@ int32_t electrum_kickstart(struct electrum_info *ep)
     return(0);
 }
 
+// closes every electrum connection of coin, their LP_dedicatedloop threads see sock -1 and drop them. returns how many
+int32_t LP_electrum_release(struct iguana_info *coin)
+{
+    struct electrum_info *ep,*prev; int32_t sock,n = 0;
+    ep = coin->electrum;
+    coin->electrum = 0;
+    while ( ep != 0 )
+    {
+        if ( (sock= ep->sock) >= 0 )
+        {
+            ep->sock = -1;
+            closesocket(sock);
+            n++;
+        }
+        printf("release %s electrum %s:%u\n",coin->symbol,ep->ipaddr,ep->port);
+        prev = ep->prev;
+        ep->prev = 0;
+        ep = prev;
+    }
+    return(n);
+}
+
 int32_t zeroval();
 
-cJSON *electrum_submit(char *symbol,struct electrum_info *ep,cJSON **retjsonp,char *method,char *params,int32_t timeout)
+cJSON *electrum_submit_one(char *symbol,struct electrum_info *ep,cJSON **retjsonp,char *method,char *params,int32_t timeout)
 {
     // queue id and string and callback
-    char stratumreq[16384]; uint32_t expiration; struct stritem *sitem;
+    char stratumreq[16384]; uint32_t expiration; double startmillis,elapsed; struct stritem *sitem;
+    if ( strcmp(ep->symbol,symbol) != 0 )
+    {
+        printf("electrum_submit ep.%p %s %s:%u called for [%s]???\n",ep,ep->symbol,ep->ipaddr,ep->port,symbol);
+    }
+    *retjsonp = 0;
+    sprintf(stratumreq,"{ \"jsonrpc\":\"2.0\", \"id\": %u, \"method\":\"%s\", \"params\": %s }\n",ep->stratumid,method,params);
+//printf("timeout.%d exp.%d %s %s",timeout,(int32_t)(expiration-time(NULL)),symbol,stratumreq);
+    memset(ep->buf,0,ep->bufsize);
+    startmillis = OS_milliseconds();
+    ep->numrequests++;
+    sitem = electrum_sitem(ep,stratumreq,timeout,retjsonp);
+    portable_mutex_lock(&ep->mutex); // this helps performance!
+    expiration = (uint32_t)time(NULL) + timeout + 1;
+    while ( *retjsonp == 0 && time(NULL) <= expiration )
+        usleep(15000);
+    portable_mutex_unlock(&ep->mutex);
+    if ( *retjsonp == 0 || jobj(*retjsonp,"error") != 0 )
+    {
+        if ( *retjsonp == 0 )
+            ep->numtimeouts++;
+        else ep->numerrs++;
+        if ( ++ep->numerrors >= LP_ELECTRUM_MAXERRORS )
+        {
+            // electrum_kickstart(ep); seems to hurt more than help
+        }
+    }
+    else
+    {
+        if ( ep->numerrors > 0 )
+            ep->numerrors--;
+        elapsed = OS_milliseconds() - startmillis;
+        ep->avelatency = ep->avelatency == 0. ? elapsed : (ep->avelatency * 0.9 + elapsed * 0.1);
+    }
+    return(*retjsonp);
+}
+
+// tries the servers for this coin in turn and returns the first good answer, failing over on timeouts and errors
+cJSON *electrum_submit_servers(char *symbol,struct electrum_info *ep,cJSON **retjsonp,char *method,char *params,int32_t timeout)
+{
     if ( ep == 0 )
         ep = electrum_server(symbol,0);
+    if ( retjsonp == 0 )
+        return(0);
     while ( ep != 0 )
     {
-        if ( strcmp(ep->symbol,symbol) != 0 )
-        {
-            printf("electrum_submit ep.%p %s %s:%u called for [%s]???\n",ep,ep->symbol,ep->ipaddr,ep->port,symbol);
-        }
-        if ( ep != 0 && ep->sock >= 0 && retjsonp != 0 )
+        if ( ep->sock >= 0 )
         {
-            *retjsonp = 0;
-            sprintf(stratumreq,"{ \"jsonrpc\":\"2.0\", \"id\": %u, \"method\":\"%s\", \"params\": %s }\n",ep->stratumid,method,params);
-//printf("timeout.%d exp.%d %s %s",timeout,(int32_t)(expiration-time(NULL)),symbol,stratumreq);
-            memset(ep->buf,0,ep->bufsize);
-            sitem = electrum_sitem(ep,stratumreq,timeout,retjsonp);
-            portable_mutex_lock(&ep->mutex); // this helps performance!
-            expiration = (uint32_t)time(NULL) + timeout + 1;
-            while ( *retjsonp == 0 && time(NULL) <= expiration )
-                usleep(15000);
-            portable_mutex_unlock(&ep->mutex);
-            if ( *retjsonp == 0 || jobj(*retjsonp,"error") != 0 )
-            {
-                if ( ++ep->numerrors >= LP_ELECTRUM_MAXERRORS )
-                {
-                    // electrum_kickstart(ep); seems to hurt more than help
-                }
-            } else if ( ep->numerrors > 0 )
-                ep->numerrors--;
+            if ( electrum_submit_one(symbol,ep,retjsonp,method,params,timeout) != 0 && jobj(*retjsonp,"error") == 0 )
+                return(*retjsonp);
             if ( ep->prev == 0 )
             {
                 if ( *retjsonp == 0 )
@@ -534,6 +782,10 @@ cJSON *electrum_submit(char *symbol,struct electrum_info *ep,cJSON **retjsonp,ch
                 }
                 return(*retjsonp);
             }
+            if ( *retjsonp != 0 )
+                free_json(*retjsonp), *retjsonp = 0;
+            ep->prev->lastfailover = (uint32_t)time(NULL);
+            printf("%s %s failover from %s:%u to %s:%u\n",symbol,method,ep->ipaddr,ep->port,ep->prev->ipaddr,ep->prev->port);
         } //else printf("couldnt find electrum server for (%s %s) or no retjsonp.%p\n",method,params,retjsonp);
         ep = ep->prev;
         //if ( ep != 0 )
@@ -542,6 +794,115 @@ cJSON *electrum_submit(char *symbol,struct electrum_info *ep,cJSON **retjsonp,ch
     return(0);
 }
 
+cJSON *electrum_submit(char *symbol,struct electrum_info *ep,cJSON **retjsonp,char *method,char *params,int32_t timeout)
+{
+    if ( retjsonp == 0 )
+        return(0);
+    return(LP_electrum_request(symbol,ep,retjsonp,method,params,timeout));
+}
+
+// asks every server and only returns an answer that a strict majority of all the configured servers agree on,
+// so servers that are down or silent cant shrink the quorum
+cJSON *electrum_quorum(char *symbol,struct electrum_info *ep,cJSON **retjsonp,char *method,char *params,int32_t timeout)
+{
+    struct electrum_info *eps[64]; char *strs[64],*errstr = 0; cJSON *retjson; int32_t i,j,n=0,numservers=0,numresponses=0,count,bestcount=0,besti=-1;
+    if ( ep == 0 )
+        ep = electrum_server(symbol,0);
+    for (; ep!=0 && n<sizeof(eps)/sizeof(*eps); ep=ep->prev)
+    {
+        numservers++;
+        if ( ep->sock < 0 )
+            continue;
+        eps[n] = ep;
+        strs[n] = 0;
+        retjson = 0;
+        if ( electrum_submit_one(symbol,ep,&retjson,method,params,timeout) != 0 )
+        {
+            if ( jobj(retjson,"error") == 0 )
+                strs[n] = jprint(retjson,0), numresponses++;
+            else if ( errstr == 0 )
+                errstr = jprint(retjson,0);
+            free_json(retjson);
+        }
+        n++;
+    }
+    for (i=0; i<n; i++)
+    {
+        if ( strs[i] == 0 )
+            continue;
+        for (count=j=0; j<n; j++)
+            if ( strs[j] != 0 && strcmp(strs[i],strs[j]) == 0 )
+                count++;
+        if ( count > bestcount )
+            bestcount = count, besti = i;
+    }
+    if ( besti >= 0 && bestcount*2 > numservers )
+    {
+        *retjsonp = cJSON_Parse(strs[besti]);
+        for (i=0; i<n; i++)
+            if ( strs[i] != 0 && strcmp(strs[i],strs[besti]) != 0 )
+            {
+                eps[i]->numdisagree++;
+                printf("%s %s disagrees with quorum for %s\n",symbol,eps[i]->ipaddr,method);
+            }
+    }
+    else if ( numresponses == 0 && errstr != 0 )
+        *retjsonp = cJSON_Parse(errstr);
+    else
+    {
+        printf("%s %s no quorum %d of %d responses from %d servers\n",symbol,method,bestcount,numresponses,numservers);
+        *retjsonp = cJSON_Parse("{\"error\":\"no quorum\"}");
+    }
+    for (i=0; i<n; i++)
+        if ( strs[i] != 0 )
+            free(strs[i]);
+    if ( errstr != 0 )
+        free(errstr);
+    return(*retjsonp);
+}
+
+// with more than one server, the raw transaction must be returned identically by a majority of them
+int32_t LP_electrum_txquorum(struct iguana_info *coin,bits256 txid)
+{
+    char params[128],str[65]; cJSON *retjson = 0; int32_t retval = 0;
+    if ( coin->electrum == 0 || ((struct electrum_info *)coin->electrum)->prev == 0 )
+        return(1);
+    sprintf(params,"[\"%s\"]",bits256_str(str,txid));
+    if ( electrum_quorum(coin->symbol,coin->electrum,&retjson,"blockchain.transaction.get",params,ELECTRUM_TIMEOUT) != 0 )
+    {
+        retval = (jobj(retjson,"error") == 0);
+        free_json(retjson);
+    }
+    return(retval);
+}
+
+cJSON *LP_electrum_health(struct iguana_info *coin)
+{
+    struct electrum_info *ep; cJSON *retjson,*array,*item;
+    retjson = cJSON_CreateObject();
+    jaddstr(retjson,"coin",coin->symbol);
+    array = cJSON_CreateArray();
+    for (ep=coin->electrum; ep!=0; ep=ep->prev)
+    {
+        item = cJSON_CreateObject();
+        jaddstr(item,"ipaddr",ep->ipaddr);
+        jaddnum(item,"port",ep->port);
+        jaddstr(item,"status",ep->sock >= 0 ? "connected" : "disconnected");
+        jaddnum(item,"lasttime",ep->lasttime);
+        jaddnum(item,"requests",ep->numrequests);
+        jaddnum(item,"timeouts",ep->numtimeouts);
+        jaddnum(item,"errors",ep->numerrs);
+        jaddnum(item,"disagree",ep->numdisagree);
+        jaddnum(item,"avelatency_ms",(int64_t)ep->avelatency);
+        if ( ep->lastfailover != 0 )
+            jaddnum(item,"lastfailover",ep->lastfailover);
+        jaddi(array,item);
+    }
+    jadd(retjson,"servers",array);
+    jaddstr(retjson,"result","success");
+    return(retjson);
+}
+
 cJSON *electrum_noargs(char *symbol,struct electrum_info *ep,cJSON **retjsonp,char *method,int32_t timeout)
 {
     cJSON *retjson;
@@ -623,9 +984,20 @@ cJSON *electrum_address_subscribe(char *symbol,struct electrum_info *ep,cJSON **
     return(retjson);
 }
 
+// cashaddr goes to the server in its legacy form, segwit addresses as the reversed sha256 of their output script
 cJSON *electrum_scripthash_cmd(char *symbol,uint8_t taddr,struct electrum_info *ep,cJSON **retjsonp,char *cmd,char *coinaddr)
 {
-    uint8_t addrtype,rmd160[20]; char btcaddr[64],cmdbuf[128]; //char scripthash[51],rmdstr[41],;
+    uint8_t addrtype,rmd160[20],script[64]; char btcaddr[65],cmdbuf[128]; int32_t i,scriptlen; bits256 hash; //char scripthash[51],rmdstr[41],;
+    if ( LP_addrformat(symbol,coinaddr) == LP_ADDRFORMAT_BECH32 )
+    {
+        if ( (scriptlen= LP_address_script(script,symbol,taddr,0,coinaddr)) <= 0 )
+            return(0);
+        vcalc_sha256(0,hash.bytes,script,scriptlen);
+        for (i=0; i<32; i++)
+            sprintf(&btcaddr[i*2],"%02x",hash.bytes[31-i]);
+        sprintf(cmdbuf,"blockchain.scripthash.%s",cmd);
+        return(electrum_strarg(symbol,ep,retjsonp,cmdbuf,btcaddr,ELECTRUM_TIMEOUT));
+    }
     bitcoin_addr2rmd160(symbol,taddr,&addrtype,rmd160,coinaddr);
     bitcoin_address("BTC",btcaddr,0,addrtype,rmd160,20);
     //init_hexbytes_noT(rmdstr,rmd160,20);
@@ -640,7 +1012,7 @@ cJSON *electrum_address_gethistory(char *symbol,struct electrum_info *ep,cJSON *
     char str[65]; struct LP_transaction *tx; cJSON *retjson,*txobj,*item; int32_t i,n,height; bits256 txid; struct iguana_info *coin = LP_coinfind(symbol);
     if ( coin == 0 )
         return(0);
-    if ( strcmp(symbol,"BCH") == 0 )
+    if ( LP_addrformat(symbol,addr) != LP_ADDRFORMAT_BASE58 )
         retjson = electrum_scripthash_cmd(symbol,coin->taddr,ep,retjsonp,"get_history",addr);
     else retjson = electrum_strarg(symbol,ep,retjsonp,"blockchain.address.get_history",addr,ELECTRUM_TIMEOUT);
     //printf("history.(%s)\n",jprint(retjson,0));
@@ -690,7 +1062,7 @@ cJSON *electrum_address_getmempool(char *symbol,struct electrum_info *ep,cJSON *
     cJSON *retjson; struct iguana_info *coin = LP_coinfind(symbol);
     if ( coin == 0 )
         return(0);
-    if ( strcmp(symbol,"BCH") == 0 )
+    if ( LP_addrformat(symbol,addr) != LP_ADDRFORMAT_BASE58 )
         retjson = electrum_scripthash_cmd(symbol,coin->taddr,ep,retjsonp,"get_mempool",addr);
     else retjson = electrum_strarg(symbol,ep,retjsonp,"blockchain.address.get_mempool",addr,ELECTRUM_TIMEOUT);
     //printf("MEMPOOL.(%s)\n",jprint(retjson,0));
@@ -724,7 +1096,7 @@ cJSON *electrum_address_listunspent(char *symbol,struct electrum_info *ep,cJSON
     //usecache = 0; // disable unspents cache
     if ( usecache == 0 || electrumflag > 1 )
     {
-        if ( strcmp(symbol,"BCH") == 0 )
+        if ( LP_addrformat(symbol,addr) != LP_ADDRFORMAT_BASE58 )
             retjson = electrum_scripthash_cmd(symbol,coin->taddr,ep,retjsonp,"listunspent",addr);
         else retjson = electrum_strarg(symbol,ep,retjsonp,"blockchain.address.listunspent",addr,ELECTRUM_TIMEOUT);
         if ( retjson != 0 )
@@ -768,13 +1140,23 @@ cJSON *electrum_address_listunspent(char *symbol,struct electrum_info *ep,cJSON
 
 cJSON *electrum_address_getbalance(char *symbol,struct electrum_info *ep,cJSON **retjsonp,char *addr)
 {
-    if ( strcmp(symbol,"BCH") == 0 )
+    if ( LP_addrformat(symbol,addr) != LP_ADDRFORMAT_BASE58 )
         return(electrum_scripthash_cmd(symbol,0,ep,retjsonp,"get_balance",addr));
     else return(electrum_strarg(symbol,ep,retjsonp,"blockchain.address.get_balance",addr,ELECTRUM_TIMEOUT));
 }
 
 cJSON *electrum_addpeer(char *symbol,struct electrum_info *ep,cJSON **retjsonp,char *endpoint) { return(electrum_strarg(symbol,ep,retjsonp,"server.add_peer",endpoint,ELECTRUM_TIMEOUT)); }
-cJSON *electrum_sendrawtransaction(char *symbol,struct electrum_info *ep,cJSON **retjsonp,char *rawtx) { return(electrum_strarg(symbol,ep,retjsonp,"blockchain.transaction.broadcast",rawtx,ELECTRUM_TIMEOUT)); }
+cJSON *electrum_sendrawtransaction(char *symbol,struct electrum_info *ep,cJSON **retjsonp,char *rawtx)
+{
+    char *params; cJSON *retjson;
+    if ( retjsonp == 0 )
+        retjsonp = &retjson;
+    params = malloc(strlen(rawtx) + 8);
+    sprintf(params,"[\"%s\"]",rawtx);
+    electrum_quorum(symbol,ep,retjsonp,"blockchain.transaction.broadcast",params,ELECTRUM_TIMEOUT);
+    free(params);
+    return(*retjsonp);
+}
 
 cJSON *electrum_estimatefee(char *symbol,struct electrum_info *ep,cJSON **retjsonp,int32_t numblocks)
 {
@@ -1004,7 +1386,7 @@ struct electrum_info *LP_electrum_info(int32_t *alreadyp,char *symbol,char *ipad
     portable_mutex_unlock(&LP_electrummutex);
     if ( ep == 0 )
     {
-        if ( (sock= LP_socket(0,ipaddr,port)) < 0 )
+        if ( (sock= LP_coinsocket(symbol,ipaddr,port)) < 0 )
         {
             printf("error connecting to %s:%u\n",ipaddr,port);
             return(0);
@@ -1404,6 +1786,8 @@ void LP_electrum_txhistory_loop(void *_coin)
         DL_SORT(coin->tx_history, ptr);
         portable_mutex_unlock(&coin->tx_history_mutex);
         free_json(history);
+        if ( coin->txhistory_synced == 0 )
+            coin->txhistory_synced = (uint32_t)time(NULL);
         sleep(10);
     }
 }
@@ -260,6 +260,156 @@ bits256 LP_swapstats_txid(cJSON *argjson,char *name,bits256 oldtxid)
     } else return(oldtxid);
 }
 
+// finished swaps seen on the network, one json line each in DB/swapstats.jsonl for stats_swaps. pubkeys are stored as sha256(pubkey)
+struct LP_swaprecord
+{
+    UT_hash_handle hh;
+    uint64_t aliceid,basesatoshis,relsatoshis;
+    uint32_t started,finished;
+    double price;
+    bits256 makerhash,takerhash;
+    char base[16],rel[16];
+} *LP_swaprecords;
+int32_t LP_swaprecords_loaded;
+
+cJSON *LP_swaprecord_json(struct LP_swaprecord *rp)
+{
+    cJSON *item = cJSON_CreateObject();
+    jadd64bits(item,"aliceid",rp->aliceid);
+    jaddnum(item,"started",rp->started);
+    jaddnum(item,"finished",rp->finished);
+    jaddnum(item,"duration",rp->finished > rp->started ? rp->finished - rp->started : 0);
+    jaddstr(item,"base",rp->base);
+    jaddstr(item,"rel",rp->rel);
+    jaddnum(item,"basevol",dstr(rp->basesatoshis));
+    jaddnum(item,"relvol",dstr(rp->relsatoshis));
+    jaddnum(item,"price",rp->price);
+    jaddbits256(item,"maker",rp->makerhash);
+    jaddbits256(item,"taker",rp->takerhash);
+    return(item);
+}
+
+struct LP_swaprecord *_LP_swaprecord_create(uint64_t aliceid)
+{
+    struct LP_swaprecord *rp;
+    HASH_FIND(hh,LP_swaprecords,&aliceid,sizeof(aliceid),rp);
+    if ( rp != 0 )
+        return(0);
+    rp = calloc(1,sizeof(*rp));
+    rp->aliceid = aliceid;
+    HASH_ADD(hh,LP_swaprecords,aliceid,sizeof(rp->aliceid),rp);
+    return(rp);
+}
+
+void _LP_swaprecords_load()
+{
+    FILE *fp; char fname[512],line[1024]; cJSON *item; struct LP_swaprecord *rp;
+    LP_swaprecords_loaded = 1;
+    sprintf(fname,"%s/swapstats.jsonl",GLOBAL_DBDIR), OS_compatible_path(fname);
+    if ( (fp= fopen(fname,"rb")) == 0 )
+        return;
+    while ( fgets(line,sizeof(line),fp) > 0 )
+    {
+        if ( (item= cJSON_Parse(line)) != 0 )
+        {
+            if ( jstr(item,"base") != 0 && jstr(item,"rel") != 0 && (rp= _LP_swaprecord_create(j64bits(item,"aliceid"))) != 0 )
+            {
+                rp->started = juint(item,"started");
+                rp->finished = juint(item,"finished");
+                safecopy(rp->base,jstr(item,"base"),sizeof(rp->base));
+                safecopy(rp->rel,jstr(item,"rel"),sizeof(rp->rel));
+                rp->basesatoshis = jdouble(item,"basevol") * SATOSHIDEN + 0.0000000049;
+                rp->relsatoshis = jdouble(item,"relvol") * SATOSHIDEN + 0.0000000049;
+                rp->price = jdouble(item,"price");
+                rp->makerhash = jbits256(item,"maker");
+                rp->takerhash = jbits256(item,"taker");
+            }
+            free_json(item);
+        }
+    }
+    fclose(fp);
+}
+
+void LP_swaprecord_add(struct LP_swapstats *sp)
+{
+    FILE *fp; char fname[512],*str; struct LP_swaprecord *rp;
+    if ( sp->finished == 0 || sp->aliceid == 0 )
+        return;
+    portable_mutex_lock(&LP_statslogmutex);
+    if ( LP_swaprecords_loaded == 0 )
+        _LP_swaprecords_load();
+    if ( (rp= _LP_swaprecord_create(sp->aliceid)) != 0 )
+    {
+        rp->started = sp->Q.timestamp;
+        rp->finished = sp->finished;
+        safecopy(rp->base,sp->Q.srccoin,sizeof(rp->base));
+        safecopy(rp->rel,sp->Q.destcoin,sizeof(rp->rel));
+        rp->basesatoshis = sp->Q.satoshis;
+        rp->relsatoshis = sp->Q.destsatoshis;
+        rp->price = sp->qprice;
+        vcalc_sha256(0,rp->makerhash.bytes,sp->Q.srchash.bytes,sizeof(sp->Q.srchash));
+        vcalc_sha256(0,rp->takerhash.bytes,sp->Q.desthash.bytes,sizeof(sp->Q.desthash));
+        sprintf(fname,"%s/swapstats.jsonl",GLOBAL_DBDIR), OS_compatible_path(fname);
+        if ( (fp= fopen(fname,"ab")) != 0 )
+        {
+            str = jprint(LP_swaprecord_json(rp),1);
+            fprintf(fp,"%s\n",str);
+            free(str);
+            fclose(fp);
+        }
+    }
+    portable_mutex_unlock(&LP_statslogmutex);
+}
+
+// {"method":"stats_swaps","from":<unixtime>,"to":<unixtime>,"base":"KMD","rel":"BTC","limit":1000}, either coin order matches the pair
+char *LP_stats_swaps(uint32_t from,uint32_t to,char *base,char *rel,int32_t limit)
+{
+    cJSON *retjson,*array,*pairs,*item; struct LP_swaprecord *rp,*tmp; char pairstr[64]; int32_t n = 0; uint64_t totalbase = 0,totalrel = 0;
+    if ( to == 0 )
+        to = (uint32_t)time(NULL);
+    if ( limit <= 0 )
+        limit = 1000;
+    retjson = cJSON_CreateObject();
+    array = cJSON_CreateArray();
+    pairs = cJSON_CreateObject();
+    portable_mutex_lock(&LP_statslogmutex);
+    if ( LP_swaprecords_loaded == 0 )
+        _LP_swaprecords_load();
+    HASH_ITER(hh,LP_swaprecords,rp,tmp)
+    {
+        if ( rp->finished < from || rp->finished > to )
+            continue;
+        if ( base != 0 && base[0] != 0 && strcmp(base,rp->base) != 0 && strcmp(base,rp->rel) != 0 )
+            continue;
+        if ( rel != 0 && rel[0] != 0 && strcmp(rel,rp->base) != 0 && strcmp(rel,rp->rel) != 0 )
+            continue;
+        sprintf(pairstr,"%s/%s",rp->base,rp->rel);
+        if ( (item= jobj(pairs,pairstr)) == 0 )
+        {
+            item = cJSON_CreateObject();
+            jaddnum(item,"numswaps",0);
+            jaddnum(item,"basevol",0);
+            jaddnum(item,"relvol",0);
+            jadd(pairs,pairstr,item);
+        }
+        cJSON_ReplaceItemInObject(item,"numswaps",cJSON_CreateNumber(jint(item,"numswaps") + 1));
+        cJSON_ReplaceItemInObject(item,"basevol",cJSON_CreateNumber(jdouble(item,"basevol") + dstr(rp->basesatoshis)));
+        cJSON_ReplaceItemInObject(item,"relvol",cJSON_CreateNumber(jdouble(item,"relvol") + dstr(rp->relsatoshis)));
+        totalbase += rp->basesatoshis, totalrel += rp->relsatoshis;
+        if ( n++ < limit )
+            jaddi(array,LP_swaprecord_json(rp));
+    }
+    portable_mutex_unlock(&LP_statslogmutex);
+    jaddstr(retjson,"result","success");
+    jaddnum(retjson,"from",from);
+    jaddnum(retjson,"to",to);
+    jaddnum(retjson,"numswaps",n);
+    jaddnum(retjson,"returned",n < limit ? n : limit);
+    jadd(retjson,"pairs",pairs);
+    jadd(retjson,"swaps",array);
+    return(jprint(retjson,1));
+}
+
 int32_t LP_swapstats_update(struct LP_swapstats *sp,struct LP_quoteinfo *qp,cJSON *lineobj)
 {
     char *statusstr,*base,*rel,gui[64]; uint32_t requestid,quoteid; uint64_t satoshis,destsatoshis;
@@ -285,9 +435,11 @@ int32_t LP_swapstats_update(struct LP_swapstats *sp,struct LP_quoteinfo *qp,cJSO
             {
                 if ( (sp->finished= juint(lineobj,"timestamp")) == 0 )
                     sp->finished = (uint32_t)time(NULL);
+                LP_swaprecord_add(sp);
             }
             if ( sp->finished == 0 && time(NULL) > sp->Q.timestamp+LP_atomic_locktime(base,rel)*2 )
                 sp->expired = (uint32_t)time(NULL);
+            LP_swapstats_count(sp);
             return(0);
         }
         else
@@ -392,6 +544,26 @@ int32_t LP_swap_finished(struct LP_swapstats *sp,int32_t dPoWflag)
     return(0);
 }
 
+// the maker's running swaps_completed/swaps_failed, kept in step with this swap's outcome: finished counts as completed,
+// unfinished past twice the locktime as failed. sp->counted is what it was counted as, so an expired swap that shows up
+// finished later moves over instead of being counted twice
+void LP_swapstats_count(struct LP_swapstats *sp)
+{
+    struct LP_pubkey_info *pubp; uint8_t outcome;
+    outcome = sp->finished != 0 ? 2 : (sp->expired != 0 ? 1 : 0);
+    if ( outcome == sp->counted || bits256_nonz(sp->Q.srchash) == 0 || (pubp= LP_pubkeyadd(sp->Q.srchash)) == 0 )
+        return;
+    if ( sp->counted == 2 )
+        pubp->swapscompleted--;
+    else if ( sp->counted == 1 )
+        pubp->swapsfailed--;
+    if ( outcome == 2 )
+        pubp->swapscompleted++;
+    else if ( outcome == 1 )
+        pubp->swapsfailed++;
+    sp->counted = outcome;
+}
+
 struct LP_swapstats *LP_swapstats_create(uint64_t aliceid,int32_t RTflag,struct LP_quoteinfo *qp,double qprice,int32_t methodind)
 {
     struct LP_pubswap *ptr; struct iguana_info *alice,*bob; struct LP_pubkey_info *pubp; char *base,*rel; struct LP_swapstats *sp = 0;
@@ -414,6 +586,7 @@ struct LP_swapstats *LP_swapstats_create(uint64_t aliceid,int32_t RTflag,struct
         }
         strcpy(sp->bobgui,"nogui");
         strcpy(sp->alicegui,"nogui");
+        LP_swapstats_count(sp);
         if ( LP_swap_finished(sp,1) == 0 ) //sp->finished == 0 && sp->expired == 0 )
         {
             if ( (pubp= LP_pubkeyadd(qp->srchash)) != 0 )
@@ -613,6 +786,8 @@ char *LP_swapstatus_recv(cJSON *argjson)
             sp->methodind = methodind;
             sp->finished = juint(argjson,"finished");
             sp->expired = juint(argjson,"expired");
+            LP_swapstats_count(sp);
+            LP_swaprecord_add(sp);
             txid = jbits256(argjson,"bobdeposit");
             if ( bits256_nonz(txid) != 0 && bits256_nonz(sp->bobdeposit) == 0 )
             {
@@ -700,7 +875,7 @@ int32_t LP_stats_dispiter(cJSON *array,struct LP_swapstats *sp,uint32_t starttim
 {
     int32_t dispflag,retval = 0;
     if ( sp->finished == 0 && sp->expired == 0 && time(NULL) > sp->Q.timestamp+LP_atomic_locktime(sp->Q.srccoin,sp->Q.destcoin)*2 )
-        sp->expired = (uint32_t)time(NULL);
+        sp->expired = (uint32_t)time(NULL), LP_swapstats_count(sp);
     if ( LP_swap_finished(sp,1) > 0 )
         retval = 1;
     dispflag = 0;
@@ -805,6 +980,7 @@ cJSON *LP_statslog_disp(uint32_t starttime,uint32_t endtime,char *refgui,bits256
     jaddnum(retjson,"uniques",LP_aliceids);
     jaddnum(retjson,"tradestatus",LP_tradestatuses);
     jaddnum(retjson,"unknown",LP_unknowns);
+    jadd(retjson,"timings",LP_metrics_timings_json());
     return(retjson);
 }
 
@@ -106,13 +106,242 @@
  */
 #define TX_WAIT_TIMEOUT 1800 // hard to increase this without hitting protocol limits (2/4 hrs)
 
+uint32_t LP_dexfee_div = INSTANTDEX_INSURANCEDIV;
+char LP_dexfee_rmd160[41] = INSTANTDEX_RMD160,LP_dexfee_ethaddr[43] = INSTANTDEX_ETHADDR;
+bits256 LP_feeschedule_hash; double LP_dexfee_tolerance = LP_DEXFEE_TOLERANCE;
+
+void LP_feeschedule_calc()
+{
+    uint8_t buf[sizeof(LP_dexfee_div) + 20 + sizeof(LP_dexfee_ethaddr)]; int32_t i,len = 0;
+    memset(buf,0,sizeof(buf));
+    len += iguana_rwnum(1,&buf[len],sizeof(LP_dexfee_div),&LP_dexfee_div);
+    decode_hex(&buf[len],20,LP_dexfee_rmd160), len += 20;
+    for (i=0; LP_dexfee_ethaddr[i]!=0; i++)
+        buf[len + i] = tolower(LP_dexfee_ethaddr[i]);
+    vcalc_sha256(0,LP_feeschedule_hash.bytes,buf,sizeof(buf));
+}
+
+// the fee schedule belongs to the network, a netid specific entry in "dexfees" overrides a plain "dexfee" object
+int32_t LP_feeschedule_set(cJSON *argjson,uint16_t netid)
+{
+    cJSON *array,*item,*fee = 0; int32_t i,n; char *rmd160,*ethaddr; uint32_t div; char str[65];
+    if ( (array= jarray(&n,argjson,"dexfees")) != 0 )
+    {
+        for (i=0; i<n; i++)
+        {
+            item = jitem(array,i);
+            if ( juint(item,"netid") == netid )
+            {
+                fee = item;
+                break;
+            }
+        }
+    }
+    if ( fee == 0 )
+        fee = jobj(argjson,"dexfee");
+    if ( fee != 0 )
+    {
+        if ( (div= juint(fee,"div")) != 0 )
+        {
+            if ( div < 10 )
+            {
+                printf("dexfee div.%u too small, ignore fee schedule\n",div);
+                return(-1);
+            }
+            LP_dexfee_div = div;
+        }
+        if ( (rmd160= jstr(fee,"rmd160")) != 0 )
+        {
+            if ( strlen(rmd160) != 40 || is_hexstr(rmd160,0) != 40 )
+            {
+                printf("invalid dexfee rmd160 (%s)\n",rmd160);
+                return(-1);
+            }
+            strcpy(LP_dexfee_rmd160,rmd160);
+        }
+        if ( (ethaddr= jstr(fee,"ethaddr")) != 0 )
+        {
+            if ( strlen(ethaddr) != 42 || is_hexstr(ethaddr+2,0) != 40 )
+            {
+                printf("invalid dexfee ethaddr (%s)\n",ethaddr);
+                return(-1);
+            }
+            strcpy(LP_dexfee_ethaddr,ethaddr);
+        }
+    }
+    // the tolerance is a local policy on what the counterparty may round to, so it stays out of the schedule hash
+    if ( jobj(argjson,"dexfee_tolerance") != 0 )
+    {
+        LP_dexfee_tolerance = jdouble(argjson,"dexfee_tolerance");
+        if ( LP_dexfee_tolerance < 0. || LP_dexfee_tolerance > LP_DEXFEE_MAXTOLERANCE )
+        {
+            printf("dexfee_tolerance %.4f outside [0, %.2f], using %.4f\n",LP_dexfee_tolerance,LP_DEXFEE_MAXTOLERANCE,LP_DEXFEE_TOLERANCE);
+            LP_dexfee_tolerance = LP_DEXFEE_TOLERANCE;
+        }
+    }
+    LP_feeschedule_calc();
+    printf("netid.%u dexfee 1/%u to %s eth.%s schedule %s tolerance %.4f\n",netid,LP_dexfee_div,LP_dexfee_rmd160,LP_dexfee_ethaddr,bits256_str(str,LP_feeschedule_hash),LP_dexfee_tolerance);
+    return(0);
+}
+
+int32_t LP_swapstep_timeout = LP_SWAPSTEP_TIMEOUT,LP_pubkeys_timeout = LP_PUBKEYS_TIMEOUT,LP_txwait_timeout = TX_WAIT_TIMEOUT,LP_watchdog_maxstate;
+
+void LP_swaptimeouts_set(cJSON *argjson)
+{
+    if ( jobj(argjson,"swapstep_timeout") != 0 && (LP_swapstep_timeout= jint(argjson,"swapstep_timeout")) < LP_MIN_SWAPSTEP_TIMEOUT )
+        LP_swapstep_timeout = LP_MIN_SWAPSTEP_TIMEOUT;
+    if ( jobj(argjson,"pubkeys_timeout") != 0 && (LP_pubkeys_timeout= jint(argjson,"pubkeys_timeout")) < LP_MIN_PUBKEYS_TIMEOUT )
+        LP_pubkeys_timeout = LP_MIN_PUBKEYS_TIMEOUT;
+    if ( jobj(argjson,"txwait_timeout") != 0 && (LP_txwait_timeout= jint(argjson,"txwait_timeout")) < LP_MIN_TXWAIT_TIMEOUT )
+        LP_txwait_timeout = LP_MIN_TXWAIT_TIMEOUT;
+    if ( jobj(argjson,"watchdog_maxstate") != 0 && (LP_watchdog_maxstate= jint(argjson,"watchdog_maxstate")) != 0 && LP_watchdog_maxstate < LP_MIN_SWAPSTEP_TIMEOUT )
+        LP_watchdog_maxstate = LP_MIN_SWAPSTEP_TIMEOUT;
+    printf("swap timeouts: step.%d pubkeys.%d txwait.%d watchdog.%d\n",LP_swapstep_timeout,LP_pubkeys_timeout,LP_txwait_timeout,LP_watchdog_maxstate);
+}
+
+int32_t LP_swapsteptimeout(struct basilisk_swap *swap)
+{
+    return(swap->swapsteptimeout != 0 ? swap->swapsteptimeout : LP_swapstep_timeout);
+}
+
+// the longer of the two sides, capped at 4x the shorter. only depends on the pair of values so maker and taker end up
+// with the same timeout whichever side is computing it
+uint32_t LP_timeout_agree(int32_t mine,uint32_t other,int32_t minval)
+{
+    uint32_t lo,hi;
+    lo = (uint32_t)mine < other ? (uint32_t)mine : other;
+    hi = (uint32_t)mine < other ? other : (uint32_t)mine;
+    if ( lo < minval )
+        lo = minval;
+    if ( hi > 4*lo )
+        hi = 4*lo;
+    if ( hi < minval )
+        hi = minval;
+    return(hi);
+}
+
+// claiming an htlc output costs more than an average tx, so estimate at the claim size and the current rate
+int64_t LP_claimfee(struct iguana_info *coin,int64_t txfee)
+{
+    int64_t claimfee;
+    if ( coin == 0 || coin->etomic[0] != 0 )
+        return(txfee);
+    if ( (claimfee= LP_txfeecalc(coin,0,LP_CLAIMTX_SIZE)) > txfee )
+        return(claimfee);
+    return(txfee);
+}
+
+// what is left after the claim has to be worth spending again
+int64_t LP_claimmargin(struct iguana_info *coin,int64_t claimfee)
+{
+    int64_t margin = LP_min_tradevol(coin);
+    if ( LP_CLAIMMARGIN_MULT * claimfee > margin )
+        margin = LP_CLAIMMARGIN_MULT * claimfee;
+    return(margin);
+}
+
+// both sides use the larger claim fee and margin, unless the other side asks for more than LP_CLAIMFEE_MAXMULT times ours
+int32_t LP_claimfees_agree(struct basilisk_swap *swap,int64_t *otherfees,int64_t *othermargins)
+{
+    int32_t i;
+    for (i=0; i<2; i++)
+    {
+        swap->claimfees[i] = (otherfees[i] > swap->myclaimfees[i]) ? otherfees[i] : swap->myclaimfees[i];
+        swap->claimmargins[i] = (othermargins[i] > swap->myclaimmargins[i]) ? othermargins[i] : swap->myclaimmargins[i];
+        if ( swap->claimfees[i] > LP_CLAIMFEE_MAXMULT*swap->myclaimfees[i] || swap->claimmargins[i] > LP_CLAIMFEE_MAXMULT*swap->myclaimmargins[i] )
+        {
+            printf("%s claimfee %.8f margin %.8f vs ours %.8f %.8f too far apart\n",i == 0 ? swap->I.alicestr : swap->I.bobstr,dstr(otherfees[i]),dstr(othermargins[i]),dstr(swap->myclaimfees[i]),dstr(swap->myclaimmargins[i]));
+            return(-1);
+        }
+    }
+    swap->I.Aclaimfee = swap->claimfees[0];
+    swap->I.Bclaimfee = swap->claimfees[1];
+    return(0);
+}
+
+struct LP_pairlocktime { char base[16],rel[16]; uint32_t locktime; } LP_pairlocktimes[LP_MAXPAIRLOCKTIMES];
+int32_t LP_numpairlocktimes;
+
+uint32_t LP_pairlocktime_find(char *base,char *rel)
+{
+    int32_t i;
+    for (i=0; i<LP_numpairlocktimes; i++)
+    {
+        if ( (strcmp(LP_pairlocktimes[i].base,base) == 0 && strcmp(LP_pairlocktimes[i].rel,rel) == 0) || (strcmp(LP_pairlocktimes[i].base,rel) == 0 && strcmp(LP_pairlocktimes[i].rel,base) == 0) )
+            return(LP_pairlocktimes[i].locktime);
+    }
+    return(0);
+}
+
 uint32_t LP_atomic_locktime(char *base,char *rel)
 {
-    if ( strcmp(base,"BTC") == 0 && strcmp(rel,"BTC") == 0 )
-        return(INSTANTDEX_LOCKTIME * 10);
-    else if ( LP_is_slowcoin(base) > 0 || LP_is_slowcoin(rel) > 0 )
-        return(INSTANTDEX_LOCKTIME * 4);
-    else return(INSTANTDEX_LOCKTIME);
+    struct iguana_info *basecoin,*relcoin; uint32_t locktime = 0;
+    if ( (locktime= LP_pairlocktime_find(base,rel)) == 0 )
+    {
+        if ( (basecoin= LP_coinfind(base)) != 0 && basecoin->locktime > locktime )
+            locktime = basecoin->locktime;
+        if ( (relcoin= LP_coinfind(rel)) != 0 && relcoin->locktime > locktime )
+            locktime = relcoin->locktime;
+    }
+    if ( locktime == 0 )
+    {
+        if ( strcmp(base,"BTC") == 0 && strcmp(rel,"BTC") == 0 )
+            locktime = INSTANTDEX_LOCKTIME * 10;
+        else if ( LP_is_slowcoin(base) > 0 || LP_is_slowcoin(rel) > 0 )
+            locktime = INSTANTDEX_LOCKTIME * 4;
+        else locktime = INSTANTDEX_LOCKTIME;
+    }
+    if ( locktime < LP_MIN_LOCKTIME )
+        locktime = LP_MIN_LOCKTIME;
+    return(locktime);
+}
+
+char *LP_setlocktime(char *base,char *rel,char *coin,uint32_t locktime)
+{
+    struct iguana_info *ptr; int32_t i; cJSON *retjson;
+    if ( locktime != 0 && locktime < LP_MIN_LOCKTIME )
+        return(clonestr("{\"error\":\"locktime below protocol minimum\"}"));
+    retjson = cJSON_CreateObject();
+    if ( coin != 0 && coin[0] != 0 )
+    {
+        if ( (ptr= LP_coinsearch(coin)) == 0 )
+        {
+            free_json(retjson);
+            return(clonestr("{\"error\":\"cant find coin\"}"));
+        }
+        ptr->locktime = locktime;
+        jaddstr(retjson,"coin",coin);
+    }
+    else if ( base != 0 && base[0] != 0 && rel != 0 && rel[0] != 0 && strlen(base) < 16 && strlen(rel) < 16 )
+    {
+        for (i=0; i<LP_numpairlocktimes; i++)
+            if ( (strcmp(LP_pairlocktimes[i].base,base) == 0 && strcmp(LP_pairlocktimes[i].rel,rel) == 0) || (strcmp(LP_pairlocktimes[i].base,rel) == 0 && strcmp(LP_pairlocktimes[i].rel,base) == 0) )
+                break;
+        if ( i == LP_numpairlocktimes )
+        {
+            if ( LP_numpairlocktimes >= LP_MAXPAIRLOCKTIMES )
+            {
+                free_json(retjson);
+                return(clonestr("{\"error\":\"too many pair locktimes\"}"));
+            }
+            strcpy(LP_pairlocktimes[i].base,base);
+            strcpy(LP_pairlocktimes[i].rel,rel);
+            LP_numpairlocktimes++;
+        }
+        LP_pairlocktimes[i].locktime = locktime;
+        jaddstr(retjson,"base",base);
+        jaddstr(retjson,"rel",rel);
+    }
+    else
+    {
+        free_json(retjson);
+        return(clonestr("{\"error\":\"need coin or base and rel\"}"));
+    }
+    jaddstr(retjson,"result","success");
+    jaddnum(retjson,"locktime",locktime);
+    if ( base != 0 && base[0] != 0 && rel != 0 && rel[0] != 0 )
+        jaddnum(retjson,"effective",LP_atomic_locktime(base,rel));
+    return(jprint(retjson,1));
 }
 
 void basilisk_rawtx_purge(struct basilisk_rawtx *rawtx)
@@ -146,6 +375,7 @@ void basilisk_swap_finished(struct basilisk_swap *swap)
             LP_availableset(swap->myfee.utxotxid,swap->myfee.utxovout);
         }
     }
+    LP_utxolocks_release(swap->I.req.requestid,swap->I.req.quoteid);
     // save to permanent storage
     basilisk_rawtx_purge(&swap->bobdeposit);
     basilisk_rawtx_purge(&swap->bobpayment);
@@ -193,11 +423,61 @@ uint32_t basilisk_requestid(struct basilisk_request *rp)
     return(calc_crc32(0,(void *)&R,sizeof(R)));
 }
 
+// requestid/quoteid, version byte, 5 confirm bytes, pubkey33, deck, 5 timing uint32s, fee schedule hash, claimfees/margins,
+// 5 flag bytes, both amounts in satoshis and as decimal strings, the alice and bob swap contracts, then the ordermatched
+// identity and the signature over everything before it
+#define LP_PUBKEYS_PUB33OFFSET (sizeof(uint32_t)*2 + 1 + 5)
+#define LP_PUBKEYS_IDENTITYOFFSET (sizeof(((struct basilisk_swap *)0)->otherdeck)+LP_PUBKEYS_PUB33OFFSET+33+sizeof(uint32_t)*5+32+sizeof(uint64_t)*6+5+LP_AMOUNTSTRLEN*2+20*2)
+#define LP_PUBKEYS_DATALEN (LP_PUBKEYS_IDENTITYOFFSET+32+65)
+
+// a contract goes over the wire as its 20 bytes, all zero when that side of the swap is not an EVM coin
+int32_t LP_pubkeys_contract(int32_t rwflag,uint8_t *serialized,char *contract)
+{
+    int32_t i;
+    if ( rwflag != 0 )
+    {
+        memset(serialized,0,20);
+        if ( strlen(contract) == 42 )
+            decode_hex(serialized,20,contract+2);
+    }
+    else
+    {
+        contract[0] = 0;
+        for (i=0; i<20; i++)
+            if ( serialized[i] != 0 )
+                break;
+        if ( i < 20 )
+        {
+            strcpy(contract,"0x");
+            init_hexbytes_noT(contract+2,serialized,20);
+        }
+    }
+    return(20);
+}
+
+// an amount goes over the wire as its decimal string zero padded to LP_AMOUNTSTRLEN bytes
+int32_t LP_pubkeys_amount(int32_t rwflag,uint8_t *serialized,char *amountstr)
+{
+    if ( rwflag != 0 )
+    {
+        memset(serialized,0,LP_AMOUNTSTRLEN);
+        safecopy((char *)serialized,amountstr,LP_AMOUNTSTRLEN);
+    }
+    else
+    {
+        memcpy(amountstr,serialized,LP_AMOUNTSTRLEN);
+        amountstr[LP_AMOUNTSTRLEN-1] = 0;
+    }
+    return(LP_AMOUNTSTRLEN);
+}
+
 int32_t LP_pubkeys_data(struct basilisk_swap *swap,uint8_t *data,int32_t maxlen)
 {
-    int32_t i,datalen = 0;
+    int32_t i,steptimeout,datalen = 0; bits256 sighash;
+    steptimeout = LP_tuning_steptimeout(swap->I.bobstr,swap->I.alicestr,LP_swapstep_timeout);
     datalen += iguana_rwnum(1,&data[datalen],sizeof(swap->I.req.requestid),&swap->I.req.requestid);
     datalen += iguana_rwnum(1,&data[datalen],sizeof(swap->I.req.quoteid),&swap->I.req.quoteid);
+    data[datalen++] = LP_PUBKEYS_VERSION;
     data[datalen++] = swap->I.aliceconfirms;
     data[datalen++] = swap->I.bobconfirms;
     data[datalen++] = swap->I.alicemaxconfirms;
@@ -207,14 +487,177 @@ int32_t LP_pubkeys_data(struct basilisk_swap *swap,uint8_t *data,int32_t maxlen)
         data[datalen++] = swap->persistent_pubkey33[i];
     for (i=0; i<sizeof(swap->deck)/sizeof(swap->deck[0][0]); i++)
         datalen += iguana_rwnum(1,&data[datalen],sizeof(swap->deck[i>>1][i&1]),&swap->deck[i>>1][i&1]);
+    datalen += iguana_rwnum(1,&data[datalen],sizeof(swap->I.started),&swap->I.started);
+    datalen += iguana_rwnum(1,&data[datalen],sizeof(swap->I.putduration),&swap->I.putduration);
+    datalen += iguana_rwnum(1,&data[datalen],sizeof(swap->I.callduration),&swap->I.callduration);
+    datalen += iguana_rwnum(1,&data[datalen],sizeof(steptimeout),&steptimeout);
+    datalen += iguana_rwnum(1,&data[datalen],sizeof(LP_txwait_timeout),&LP_txwait_timeout);
+    for (i=0; i<32; i++)
+        data[datalen++] = LP_feeschedule_hash.bytes[i];
+    for (i=0; i<2; i++)
+    {
+        datalen += iguana_rwnum(1,&data[datalen],sizeof(swap->myclaimfees[i]),&swap->myclaimfees[i]);
+        datalen += iguana_rwnum(1,&data[datalen],sizeof(swap->myclaimmargins[i]),&swap->myclaimmargins[i]);
+    }
+    data[datalen++] = swap->I.uniformspends;
+    data[datalen++] = swap->I.burnfee;
+    data[datalen++] = swap->I.csvrefunds;
+    data[datalen++] = swap->I.makerfirst;
+    data[datalen++] = swap->I.feeconfirm;
+    datalen += iguana_rwnum(1,&data[datalen],sizeof(swap->I.alicesatoshis),&swap->I.alicesatoshis);
+    datalen += iguana_rwnum(1,&data[datalen],sizeof(swap->I.bobsatoshis),&swap->I.bobsatoshis);
+    datalen += LP_pubkeys_amount(1,&data[datalen],swap->I.aliceamount);
+    datalen += LP_pubkeys_amount(1,&data[datalen],swap->I.bobamount);
+    datalen += LP_pubkeys_contract(1,&data[datalen],swap->I.alicecontract);
+    datalen += LP_pubkeys_contract(1,&data[datalen],swap->I.bobcontract);
+    // bind the persistent pubkey to the ordermatched identity and sign the whole payload with it
+    for (i=0; i<32; i++)
+        data[datalen++] = (swap->I.iambob != 0 ? swap->I.req.srchash : swap->I.req.desthash).bytes[i];
+    vcalc_sha256(0,sighash.bytes,data,datalen);
+    if ( bitcoin_sign(swap->ctx,"pubkeys",&data[datalen],sighash,swap->persistent_privkey,1) != 65 )
+    {
+        printf("error signing pubkeys payload\n");
+        return(-1);
+    }
+    if ( (datalen += 65) != LP_PUBKEYS_DATALEN )
+    {
+        printf("pubkeys payload %d != %d\n",datalen,(int32_t)LP_PUBKEYS_DATALEN);
+        return(-1);
+    }
     //printf("send >>>>>>>>> r.%u q.%u datalen.%d\n",swap->I.req.requestid,swap->I.req.quoteid,datalen);
     return(datalen);
 }
 
+int32_t LP_pubkeys_identity(struct basilisk_swap *swap,uint8_t *data,int32_t datalen)
+{
+    int32_t i,offset = (int32_t)LP_PUBKEYS_IDENTITYOFFSET; bits256 otherhash,sighash; uint8_t *other33,pub33[33]; struct LP_pubkey_info *pubp; char str[65];
+    if ( datalen != LP_PUBKEYS_DATALEN )
+        return(-1);
+    other33 = &data[LP_PUBKEYS_PUB33OFFSET];
+    for (i=0; i<32; i++)
+        otherhash.bytes[i] = data[offset + i];
+    if ( bits256_cmp(otherhash,swap->I.iambob != 0 ? swap->I.req.desthash : swap->I.req.srchash) != 0 )
+    {
+        printf("pubkeys from %s, but ordermatched with another pubkey\n",bits256_str(str,otherhash));
+        return(-1);
+    }
+    vcalc_sha256(0,sighash.bytes,data,offset+32);
+    memset(pub33,0,sizeof(pub33));
+    if ( bitcoin_recoververify(swap->ctx,"pubkeys",&data[offset+32],sighash,pub33,0) != 0 || memcmp(pub33,other33,33) != 0 )
+    {
+        printf("pubkeys payload not signed by its persistent pubkey\n");
+        return(-1);
+    }
+    if ( (pubp= LP_pubkeyfind(otherhash)) != 0 && pubp->pubsecp[0] != 0 && memcmp(pubp->pubsecp,other33,33) != 0 )
+    {
+        printf("persistent pubkey doesnt match known pubsecp for %s\n",bits256_str(str,otherhash));
+        return(-1);
+    }
+    return(0);
+}
+
+// a pubkeys message checked on its own: the right size and signed by the persistent pubkey it carries, which makes it
+// the sender's commitment to requestid/quoteid and both amounts
+int32_t LP_pubkeys_signer(void *ctx,uint8_t *pub33,uint32_t *requestidp,uint32_t *quoteidp,int64_t *alicesatoshisp,int64_t *bobsatoshisp,uint8_t *data,int32_t datalen)
+{
+    int32_t offset = (int32_t)LP_PUBKEYS_IDENTITYOFFSET,len = 0; bits256 sighash;
+    if ( datalen != LP_PUBKEYS_DATALEN || data[sizeof(uint32_t)*2] != LP_PUBKEYS_VERSION )
+        return(-1);
+    len += iguana_rwnum(0,&data[len],sizeof(*requestidp),requestidp);
+    len += iguana_rwnum(0,&data[len],sizeof(*quoteidp),quoteidp);
+    len = offset - 20*2 - LP_AMOUNTSTRLEN*2 - sizeof(int64_t)*2;
+    len += iguana_rwnum(0,&data[len],sizeof(*alicesatoshisp),alicesatoshisp);
+    len += iguana_rwnum(0,&data[len],sizeof(*bobsatoshisp),bobsatoshisp);
+    vcalc_sha256(0,sighash.bytes,data,offset+32);
+    memset(pub33,0,33);
+    if ( bitcoin_recoververify(ctx,"pubkeys",&data[offset+32],sighash,pub33,0) != 0 || memcmp(pub33,&data[LP_PUBKEYS_PUB33OFFSET],33) != 0 )
+        return(-1);
+    return(0);
+}
+
+// the counterparty's verified pubkeys message goes into the completion receipt as what it signed up for
+void LP_pubkeys_save(struct basilisk_swap *swap,uint8_t *data,int32_t datalen)
+{
+    char fname[512],*hexstr; FILE *fp;
+    sprintf(fname,"%s/SWAPS/%u-%u.pubkeys",GLOBAL_DBDIR,swap->I.req.requestid,swap->I.req.quoteid), OS_compatible_path(fname);
+    if ( (fp= fopen(fname,"wb")) != 0 )
+    {
+        hexstr = malloc(datalen*2 + 1);
+        init_hexbytes_noT(hexstr,data,datalen);
+        fprintf(fp,"%s\n",hexstr);
+        fclose(fp);
+        free(hexstr);
+    }
+}
+
+// bobpayment (and alice's side of the trade) locks until started + putduration, bobdeposit must outlast it by at least as much again
+int32_t LP_pubkeys_locktimes(struct basilisk_swap *swap,uint32_t otherstarted,uint32_t otherput,uint32_t othercall)
+{
+    int32_t diff;
+    diff = (int32_t)((otherstarted + otherput) - (swap->I.started + swap->I.putduration));
+    if ( diff > LP_LOCKTIME_TOLERANCE || diff < -LP_LOCKTIME_TOLERANCE )
+    {
+        printf("payment locktime %u vs %u outside tolerance %d\n",otherstarted + otherput,swap->I.started + swap->I.putduration,LP_LOCKTIME_TOLERANCE);
+        swap->locktimeerr = 1;
+        return(-1);
+    }
+    if ( otherput + othercall < 2*otherput || swap->I.putduration + swap->I.callduration < 2*swap->I.putduration )
+    {
+        printf("deposit locktime durations (%u %u) vs (%u %u) less than twice the payment locktime\n",otherput,othercall,swap->I.putduration,swap->I.callduration);
+        swap->locktimeerr = 2;
+        return(-1);
+    }
+    return(0);
+}
+
+// each side proposes the contract its own payments go to, the other side only takes one it would use or still spends from
+int32_t LP_pubkeys_contracts(struct basilisk_swap *swap,char *otheralice,char *otherbob)
+{
+#ifndef NOTETOMIC
+    char *contract,*symbol; int32_t bobflag = (swap->I.iambob == 0);
+    if ( (bobflag != 0 ? swap->I.bobtomic : swap->I.alicetomic)[0] == 0 )
+        return(0);
+    contract = bobflag != 0 ? otherbob : otheralice;
+    symbol = bobflag != 0 ? swap->I.bobstr : swap->I.alicestr;
+    if ( contract[0] == 0 || LP_etomic_contractknown(symbol,bobflag,contract) == 0 )
+    {
+        printf("counterparty %s contract (%s) is not a known %s swap contract\n",bobflag != 0 ? "bob" : "alice",contract,symbol);
+        return(-1);
+    }
+    if ( bobflag != 0 )
+        safecopy(swap->I.bobcontract,contract,sizeof(swap->I.bobcontract));
+    else safecopy(swap->I.alicecontract,contract,sizeof(swap->I.alicecontract));
+#endif
+    return(0);
+}
+
+// the decimal amounts are what gets compared, satoshis are only the utxo side of them. an etomic side carries the 1 coin
+// placeholder in satoshis, so its string is the only real amount there is
+int32_t LP_pubkeys_amounts(struct basilisk_swap *swap,char *otheraliceamount,char *otherbobamount,int64_t otheralicesatoshis,int64_t otherbobsatoshis)
+{
+    if ( (swap->I.alicetomic[0] == 0 && LP_amount_satoshis(otheraliceamount) != otheralicesatoshis) || (swap->I.bobtomic[0] == 0 && LP_amount_satoshis(otherbobamount) != otherbobsatoshis) )
+    {
+        printf("counterparty amounts (%s %s) dont match its satoshis %.8f %.8f\n",otheraliceamount,otherbobamount,dstr(otheralicesatoshis),dstr(otherbobsatoshis));
+        return(-1);
+    }
+    if ( LP_amount_agree("alice",swap->I.aliceamount,otheraliceamount,LP_dexfee_tolerance) < 0 || LP_amount_agree("bob",swap->I.bobamount,otherbobamount,LP_dexfee_tolerance) < 0 )
+        return(-1);
+    // a payment goes by its payer's amount, so both sides build and check the exact same etomic value
+    if ( swap->I.iambob != 0 )
+        LP_amount_canonical(swap->I.aliceamount,otheraliceamount);
+    else LP_amount_canonical(swap->I.bobamount,otherbobamount);
+    return(0);
+}
+
 int32_t LP_pubkeys_verify(struct basilisk_swap *swap,uint8_t *data,int32_t datalen)
 {
-    uint32_t requestid,quoteid; int32_t i,nonz=0,alicemaxconfirms,bobmaxconfirms,aliceconfirms,bobconfirms,len = 0; uint8_t other33[33];
-    if ( datalen == sizeof(swap->otherdeck)+38+sizeof(uint32_t)*2 )
+    uint32_t requestid,quoteid,otherstarted,otherput,othercall,otherstep,othertxwait; int64_t otherclaimfees[2],otherclaimmargins[2],otheralicesatoshis,otherbobsatoshis; char otheralicecontract[64],otherbobcontract[64],otheraliceamount[LP_AMOUNTSTRLEN],otherbobamount[LP_AMOUNTSTRLEN]; int32_t i,nonz=0,alicemaxconfirms,bobmaxconfirms,aliceconfirms,bobconfirms,len = 0; uint8_t other33[33];
+    if ( datalen > sizeof(uint32_t)*2 && data[sizeof(uint32_t)*2] != LP_PUBKEYS_VERSION )
+    {
+        printf("pubkeys version %d, this node speaks version %d\n",data[sizeof(uint32_t)*2],LP_PUBKEYS_VERSION);
+        return(-1);
+    }
+    if ( datalen == LP_PUBKEYS_DATALEN )
     {
         len += iguana_rwnum(0,&data[len],sizeof(requestid),&requestid);
         len += iguana_rwnum(0,&data[len],sizeof(quoteid),&quoteid);
@@ -223,6 +666,12 @@ int32_t LP_pubkeys_verify(struct basilisk_swap *swap,uint8_t *data,int32_t datal
             printf("SWAP requestid.%u quoteid.%u mismatch received r.%u q.%u\n",swap->I.req.requestid,swap->I.req.quoteid,requestid,quoteid);
             return(-1);
         }
+        if ( LP_pubkeys_identity(swap,data,datalen) < 0 )
+        {
+            swap->identityerr = (uint32_t)time(NULL);
+            return(-1);
+        }
+        len++; // version, checked above
         aliceconfirms = data[len++];
         bobconfirms = data[len++];
         alicemaxconfirms = data[len++];
@@ -260,9 +709,68 @@ int32_t LP_pubkeys_verify(struct basilisk_swap *swap,uint8_t *data,int32_t datal
             memcpy(swap->persistent_other33,other33,33);
         for (i=0; i<sizeof(swap->otherdeck)/sizeof(swap->otherdeck[0][0]); i++)
             len += iguana_rwnum(0,&data[len],sizeof(swap->otherdeck[i>>1][i&1]),&swap->otherdeck[i>>1][i&1]);
+        len += iguana_rwnum(0,&data[len],sizeof(otherstarted),&otherstarted);
+        len += iguana_rwnum(0,&data[len],sizeof(otherput),&otherput);
+        len += iguana_rwnum(0,&data[len],sizeof(othercall),&othercall);
+        len += iguana_rwnum(0,&data[len],sizeof(otherstep),&otherstep);
+        len += iguana_rwnum(0,&data[len],sizeof(othertxwait),&othertxwait);
+        if ( memcmp(&data[len],LP_feeschedule_hash.bytes,32) != 0 )
+        {
+            printf("counterparty uses a different dexfee schedule\n");
+            swap->feeerr = (uint32_t)time(NULL);
+            return(-1);
+        }
+        len += 32;
+        for (i=0; i<2; i++)
+        {
+            len += iguana_rwnum(0,&data[len],sizeof(otherclaimfees[i]),&otherclaimfees[i]);
+            len += iguana_rwnum(0,&data[len],sizeof(otherclaimmargins[i]),&otherclaimmargins[i]);
+        }
+        if ( LP_claimfees_agree(swap,otherclaimfees,otherclaimmargins) < 0 )
+        {
+            swap->feeerr = (uint32_t)time(NULL);
+            return(-1);
+        }
+        // bobpayment script shape has to be agreed on, so uniform spends only when both sides configured it
+        if ( data[len++] == 0 )
+            swap->I.uniformspends = 0;
+        // same for a burned dexfee, the maker only accepts the OP_RETURN form if it allows burning on that coin too
+        if ( data[len++] == 0 )
+            swap->I.burnfee = 0;
+        // relative locktime refunds change both maker scripts, so again only when both sides have them on the bobcoin
+        if ( data[len++] == 0 )
+            swap->I.csvrefunds = 0;
+        // which payment goes first decides every script and both state machines, either side can keep the usual order
+        if ( data[len++] == 0 )
+            swap->I.makerfirst = 0;
+        // the maker tells up front that it waits for our dexfee to confirm, so the taker waits that much longer for it
+        if ( swap->I.iambob == 0 )
+            swap->I.feeconfirm = data[len++];
+        else len++;
+        len += iguana_rwnum(0,&data[len],sizeof(otheralicesatoshis),&otheralicesatoshis);
+        len += iguana_rwnum(0,&data[len],sizeof(otherbobsatoshis),&otherbobsatoshis);
+        len += LP_pubkeys_amount(0,&data[len],otheraliceamount);
+        len += LP_pubkeys_amount(0,&data[len],otherbobamount);
+        len += LP_pubkeys_contract(0,&data[len],otheralicecontract);
+        len += LP_pubkeys_contract(0,&data[len],otherbobcontract);
+        if ( LP_pubkeys_contracts(swap,otheralicecontract,otherbobcontract) < 0 )
+            return(-1);
+        if ( LP_pubkeys_amounts(swap,otheraliceamount,otherbobamount,otheralicesatoshis,otherbobsatoshis) < 0 )
+        {
+            swap->feeerr = (uint32_t)time(NULL);
+            return(-1);
+        }
+        // the counterparty computed its dexfee from its own amounts, so that is what its fee gets checked against
+        swap->otherfee.I.amount = LP_DEXFEE(swap->I.iambob != 0 ? otheralicesatoshis : otherbobsatoshis);
+        swap->swapsteptimeout = LP_timeout_agree(LP_tuning_steptimeout(swap->I.bobstr,swap->I.alicestr,LP_swapstep_timeout),otherstep,LP_MIN_SWAPSTEP_TIMEOUT);
+        swap->txwaittimeout = LP_timeout_agree(LP_txwait_timeout,othertxwait,LP_MIN_TXWAIT_TIMEOUT);
+        LP_swaplog(swap,"timeouts",swap->swapsteptimeout > LP_swapstep_timeout || swap->txwaittimeout > LP_txwait_timeout ? "extended by counterparty" : "local");
+        if ( LP_pubkeys_locktimes(swap,otherstarted,otherput,othercall) < 0 )
+            return(-1);
+        LP_pubkeys_save(swap,data,datalen);
         return(0);
     }
-    printf("pubkeys verify size mismatch %d != %d\n",datalen,(int32_t)(sizeof(swap->otherdeck)+38+sizeof(uint32_t)*2));
+    printf("pubkeys verify size mismatch %d != %d\n",datalen,(int32_t)LP_PUBKEYS_DATALEN);
     return(-1);
 }
 
@@ -473,89 +981,680 @@ int32_t LP_mostprivs_verify(struct basilisk_swap *swap,uint8_t *data,int32_t dat
     return(errs);
 }
 
-int32_t LP_waitfor(int32_t pairsock,struct basilisk_swap *swap,int32_t timeout,int32_t (*verify)(struct basilisk_swap *swap,uint8_t *data,int32_t datalen))
+// lite mode only counts confirmations once the merkle proof checks out against a second server's header
+int32_t LP_swap_numconfirms(char *symbol,char *coinaddr,bits256 txid)
 {
-    struct nn_pollfd pfd; void *data; int32_t datalen,retval = -1; uint32_t expiration = (uint32_t)time(NULL) + timeout;
-    while ( time(NULL) < expiration )
+    struct iguana_info *coin; struct LP_transaction *tx; int32_t n,ht; char str[65];
+    n = LP_numconfirms(symbol,coinaddr,txid,0,1);
+    if ( n > 0 && (coin= LP_coinfind(symbol)) != 0 && coin->electrum != 0 )
     {
-        memset(&pfd,0,sizeof(pfd));
-        pfd.fd = pairsock;
-        pfd.events = NN_POLLIN;
-        if ( nn_poll(&pfd,1,1) > 0 )
+        if ( (tx= LP_transactionfind(coin,txid)) != 0 && tx->SPV > 0 )
+            return(n);
+        if ( LP_electrum_txquorum(coin,txid) == 0 )
         {
-            //printf("start wait\n");
-            if ( (datalen= nn_recv(pairsock,&data,NN_MSG,0)) >= 0 )
-            {
-                //printf("wait for got.%d\n",datalen);
-                retval = (*verify)(swap,data,datalen);
-                swap->received = (uint32_t)time(NULL);
-                nn_freemsg(data);
-                //printf("retval.%d\n",retval);
-                return(retval);
-            } // else printf("error nn_recv\n");
+            printf("%s %s numconfs.%d electrum servers disagree\n",symbol,bits256_str(str,txid),n);
+            return(0);
+        }
+        if ( (ht= LP_txheight(coin,txid)) <= 0 || LP_SPV_crosscheck(coin,txid,ht) <= 0 )
+        {
+            printf("%s %s numconfs.%d not SPV verified yet\n",symbol,bits256_str(str,txid),n);
+            return(0);
         }
     }
-    printf("waitfor timedout aliceid.%llu requestid.%u quoteid.%u\n",(long long)swap->aliceid,swap->I.req.requestid,swap->I.req.quoteid);
-    return(retval);
+    return(n);
 }
 
-int32_t swap_nn_send(int32_t sock,uint8_t *data,int32_t datalen,uint32_t flags,int32_t timeout)
+// remember where a payment was counted as confirmed, so a reorg that later drops it is noticed before it gets spent
+void LP_swap_confirmedat(struct basilisk_swap *swap,char *symbol,bits256 txid)
 {
-    struct nn_pollfd pfd; int32_t i;
-    for (i=0; i<timeout*1000; i++)
+    struct iguana_info *coin; cJSON *txobj; char str[65];
+    if ( (coin= LP_coinfind(symbol)) == 0 || (swap->Apaymentheight= LP_txheight(coin,txid)) <= 0 )
+        return;
+    if ( coin->electrum == 0 && (txobj= LP_gettx("LP_swap_confirmedat",symbol,txid,1)) != 0 )
     {
-        memset(&pfd,0,sizeof(pfd));
-        pfd.fd = sock;
-        pfd.events = NN_POLLOUT;
-        if ( nn_poll(&pfd,1,1) > 0 )
-            return(nn_send(sock,data,datalen,flags));
-        usleep(1000);
+        swap->Apaymentblock = jbits256(txobj,"blockhash");
+        free_json(txobj);
     }
-    return(-1);
+    printf("%s %s confirmed at ht.%d %s\n",symbol,bits256_str(str,txid),swap->Apaymentheight,bits256_nonz(swap->Apaymentblock) != 0 ? "with blockhash" : "");
 }
 
-int32_t LP_waitsend(char *statename,int32_t timeout,int32_t pairsock,struct basilisk_swap *swap,uint8_t *data,int32_t maxlen,int32_t (*verify)(struct basilisk_swap *swap,uint8_t *data,int32_t datalen),int32_t (*datagen)(struct basilisk_swap *swap,uint8_t *data,int32_t maxlen))
+// 1 while the payment is still confirmed (possibly re-mined in another block), 0 once a reorg dropped it back out of the chain
+int32_t LP_swap_reorgcheck(char *symbol,char *coinaddr,bits256 txid,int32_t height,bits256 blockhash)
 {
-    int32_t datalen,sendlen,retval = -1;
-    //printf("waitsend.%s timeout.%d\n",statename,timeout);
-    if ( LP_waitfor(pairsock,swap,timeout,verify) == 0 )
+    struct iguana_info *coin; cJSON *txobj; bits256 nowhash; int32_t ht,confs = 0; char str[65];
+    if ( (coin= LP_coinfind(symbol)) == 0 )
+        return(1);
+    if ( coin->electrum == 0 )
     {
-        //printf("waited for %s\n",statename);
-        if ( (datalen= (*datagen)(swap,data,maxlen)) > 0 )
+        memset(nowhash.bytes,0,sizeof(nowhash));
+        if ( (txobj= LP_gettx("LP_swap_reorgcheck",symbol,txid,1)) != 0 )
         {
-            if ( (sendlen= swap_nn_send(pairsock,data,datalen,0,timeout)) == datalen )
-            {
-                //printf("sent.%d after waitfor.%s\n",sendlen,statename);
-                retval = 0;
-            } else printf("send %s error\n",statename);
-        } else printf("%s datagen no data\n",statename);
-    } else printf("didnt get valid data after %d\n",timeout);
-    return(retval);
+            nowhash = jbits256(txobj,"blockhash");
+            confs = jint(txobj,"confirmations");
+            free_json(txobj);
+        }
+        if ( bits256_nonz(nowhash) == 0 || confs <= 0 )
+            return(0);
+        if ( bits256_nonz(blockhash) != 0 && bits256_cmp(nowhash,blockhash) != 0 )
+            printf("%s %s was reorged out of ht.%d and reconfirmed with %d confs\n",symbol,bits256_str(str,txid),height,confs);
+        return(1);
+    }
+    if ( (ht= LP_txheight(coin,txid)) <= 0 || LP_swap_numconfirms(symbol,coinaddr,txid) <= 0 )
+        return(0);
+    if ( ht != height )
+        printf("%s %s was reorged out of ht.%d and reconfirmed at ht.%d\n",symbol,bits256_str(str,txid),height,ht);
+    return(1);
 }
 
-int32_t LP_sendwait(char *statename,int32_t timeout,int32_t pairsock,struct basilisk_swap *swap,uint8_t *data,int32_t maxlen,int32_t (*verify)(struct basilisk_swap *swap,uint8_t *data,int32_t datalen),int32_t (*datagen)(struct basilisk_swap *swap,uint8_t *data,int32_t maxlen))
+// electrum coins wake up on a new header from blockchain.headers.subscribe, else poll with exponential backoff
+void LP_confirmwait(char *symbol,int32_t *delayp)
 {
-    int32_t datalen,sendlen,retval = -1;
-    //printf("sendwait.%s\n",statename);
-    if ( (datalen= (*datagen)(swap,data,maxlen)) > 0 )
+    struct iguana_info *coin; int32_t i,height,pollmin;
+    if ( (coin= LP_coinfind(symbol)) == 0 || coin->electrum == 0 )
     {
-        //printf("generated %d for %s, timeout.%d\n",datalen,statename,timeout);
-        if ( (sendlen= swap_nn_send(pairsock,data,datalen,0,timeout)) == datalen )
-        {
-            //printf("sendwait.%s sent %d\n",statename,sendlen);
-            if ( LP_waitfor(pairsock,swap,timeout,verify) == 0 )
-            {
-                //printf("waited! sendwait.%s sent %d\n",statename,sendlen);
-                retval = 0;
-            } else printf("didnt get %s\n",statename);
-        } else printf("send %s error\n",statename);
-    } else printf("no datagen for %s\n",statename);
-    return(retval);
+        sleep(LP_tuning_pollinterval(symbol,10));
+        if ( coin != 0 )
+            LP_tuning_height(symbol,coin->height);
+        return;
+    }
+    pollmin = LP_tuning_pollinterval(symbol,LP_CONFIRMPOLL_MIN);
+    if ( *delayp < pollmin )
+        *delayp = pollmin;
+    height = coin->height;
+    LP_tuning_height(symbol,height);
+    for (i=0; i<*delayp; i++)
+    {
+        if ( coin->height != height )
+            break;
+        sleep(1);
+    }
+    if ( coin->height != height )
+    {
+        LP_tuning_height(symbol,coin->height);
+        *delayp = pollmin;
+    }
+    else if ( (*delayp *= 2) > LP_CONFIRMPOLL_MAX )
+        *delayp = LP_CONFIRMPOLL_MAX;
 }
 
-void LP_swapsfp_update(uint32_t requestid,uint32_t quoteid)
+uint32_t LP_feebump_delay(struct iguana_info *coin)
 {
-    static FILE *swapsfp;
+    return(coin->feebumpdelay != 0 ? coin->feebumpdelay : LP_FEEBUMP_DELAY);
+}
+
+// our payment still at zero confs a full feebump delay after its last broadcast: RBF it when the coin opts in, otherwise CPFP off its change
+int32_t LP_swap_feebump(struct basilisk_swap *swap,struct basilisk_rawtx *rawtx,int32_t numconfs)
+{
+    struct iguana_info *coin; int64_t bumpfee; bits256 origtxid,cpfptxid; char *signedtx,changeaddr[64],str[65],str2[65],msg[256]; int32_t retval = -1;
+    if ( numconfs != 0 || rawtx->senttime == 0 || rawtx->numbumps >= LP_FEEBUMP_MAX || (coin= LP_coinfind(rawtx->symbol)) == 0 || coin->etomic[0] != 0 )
+        return(0);
+    if ( LP_now() < rawtx->senttime + LP_feebump_delay(coin) )
+        return(0);
+    bumpfee = (coin->txfee > LP_MIN_TXFEE ? coin->txfee : LP_MIN_TXFEE) << (rawtx->numbumps + 1);
+    rawtx->numbumps++;
+    origtxid = rawtx->I.signedtxid;
+    bitcoin_address(coin->symbol,changeaddr,coin->taddr,coin->pubtype,swap->changermd160,20);
+    if ( coin->rbf != 0 && basilisk_rawtx_rbf(rawtx,changeaddr,bumpfee) == 0 )
+    {
+        basilisk_dontforget_update(swap,rawtx);
+        sprintf(msg,"rbf %s -> %s fee +%.8f",bits256_str(str,origtxid),bits256_str(str2,rawtx->I.signedtxid),dstr(bumpfee));
+        retval = 0;
+    }
+    else if ( (signedtx= basilisk_rawtx_cpfp(&cpfptxid,rawtx,changeaddr,bumpfee)) != 0 )
+    {
+        if ( bits256_cmp(LP_broadcast("cpfp",rawtx->symbol,signedtx,cpfptxid),cpfptxid) == 0 )
+        {
+            sprintf(msg,"cpfp %s child %s fee %.8f",bits256_str(str,origtxid),bits256_str(str2,cpfptxid),dstr(bumpfee));
+            retval = 0;
+        }
+        free(signedtx);
+    }
+    rawtx->senttime = LP_now(); // the next attempt waits another full delay, whether or not this one went out
+    if ( retval == 0 )
+        LP_swaplog(swap,rawtx->name,msg);
+    else printf("%s feebump.%d %s failed\n",rawtx->name,rawtx->numbumps,bits256_str(str,origtxid));
+    return(retval);
+}
+
+// the other side may have RBF'd its payment: once the copy we were sent is gone, find the tx that spent its first input
+// and adopt it if it pays the same amount to the same p2sh
+int32_t LP_swap_replaced(struct basilisk_swap *swap,struct basilisk_rawtx *rawtx,int32_t numconfs)
+{
+    struct iguana_info *coin; cJSON *txobj,*vins,*array; bits256 zero,prevtxid,spendtxid; int32_t numvins,prevvout=0,spendvini,retval = 0; char spentaddr[64],destaddr[64],str[65],str2[65],msg[256];
+    if ( numconfs > 0 || (coin= LP_coinfind(rawtx->symbol)) == 0 || coin->rbf == 0 || rawtx->I.destaddr[0] == 0 || rawtx->I.datalen <= 0 )
+        return(0);
+    if ( rawtx->senttime == 0 )
+        rawtx->senttime = LP_now();
+    if ( LP_now() < rawtx->senttime + LP_feebump_delay(coin) )
+        return(0);
+    if ( LP_swap_numconfirms(rawtx->symbol,rawtx->I.destaddr,rawtx->I.signedtxid) >= 0 )
+        return(0);
+    memset(zero.bytes,0,sizeof(zero));
+    memset(prevtxid.bytes,0,sizeof(prevtxid));
+    if ( (txobj= LP_transaction_fromdata(coin,rawtx->I.signedtxid,rawtx->txbytes,rawtx->I.datalen)) != 0 )
+    {
+        if ( (vins= jarray(&numvins,txobj,"vin")) != 0 && numvins > 0 )
+        {
+            prevtxid = jbits256(jitem(vins,0),"txid");
+            prevvout = jint(jitem(vins,0),"vout");
+        }
+        free_json(txobj);
+    }
+    if ( bits256_nonz(prevtxid) == 0 )
+        return(0);
+    spentaddr[0] = 0;
+    LP_txvalue(spentaddr,rawtx->symbol,prevtxid,prevvout);
+    if ( coin->electrum != 0 )
+    {
+        if ( spentaddr[0] != 0 && (array= electrum_address_gethistory(rawtx->symbol,coin->electrum,&array,spentaddr,zero)) != 0 )
+            free_json(array);
+    } else LP_mempoolscan(rawtx->symbol,zero);
+    if ( LP_spendsearch(destaddr,&spendtxid,&spendvini,rawtx->symbol,prevtxid,prevvout) < 0 || bits256_nonz(spendtxid) == 0 || bits256_cmp(spendtxid,rawtx->I.signedtxid) == 0 )
+        return(0);
+    destaddr[0] = 0;
+    if ( LP_txvalue(destaddr,rawtx->symbol,spendtxid,0) == rawtx->I.amount && strcmp(destaddr,rawtx->I.destaddr) == 0 )
+    {
+        sprintf(msg,"replaced %s -> %s",bits256_str(str,rawtx->I.signedtxid),bits256_str(str2,spendtxid));
+        rawtx->I.signedtxid = rawtx->I.actualtxid = spendtxid;
+        basilisk_dontforget_update(swap,rawtx);
+        LP_swaplog(swap,rawtx->name,msg);
+        retval = 1;
+    }
+    return(retval);
+}
+
+// expected seconds for numconfs blocks, coins.json "blocktime" wins over the BTC/slowcoin guesses
+int32_t LP_confirm_timeout(char *symbol,int32_t numconfs)
+{
+    struct iguana_info *coin; int32_t blocktime = 60;
+    if ( (coin= LP_coinfind(symbol)) != 0 && coin->blocktime > 0 )
+        blocktime = coin->blocktime;
+    else if ( strcmp(symbol,"BTC") == 0 )
+        blocktime = 600;
+    else if ( LP_is_slowcoin(symbol) != 0 )
+        blocktime = 240;
+    return(LP_tuning_waittimeout(symbol,LP_CONFIRM_TIMEOUT_FACTOR * (numconfs + 1) * blocktime));
+}
+
+// every confirmation goes to the swap log, which is also what the watchdog and swap status see, and out to IPC listeners
+void LP_confirm_progress(struct basilisk_swap *swap,char *symbol,struct basilisk_rawtx *rawtx,int32_t numconfs,int32_t target)
+{
+    char msg[128],str[65],*msgstr; cJSON *retjson;
+    sprintf(msg,"%s %d/%d confirmations",bits256_str(str,rawtx->I.signedtxid),numconfs,target);
+    LP_swaplog(swap,rawtx->name,msg);
+    if ( IPC_ENDPOINT >= 0 )
+    {
+        retjson = cJSON_CreateObject();
+        jaddstr(retjson,"method","confirmations");
+        jaddstr(retjson,"uuid",swap->uuidstr);
+        jaddnum(retjson,"requestid",swap->I.req.requestid);
+        jaddnum(retjson,"quoteid",swap->I.req.quoteid);
+        jaddstr(retjson,"tx",rawtx->name);
+        jaddstr(retjson,"coin",symbol);
+        jaddbits256(retjson,"txid",rawtx->I.signedtxid);
+        jaddnum(retjson,"confirmations",numconfs);
+        jaddnum(retjson,"required",target);
+        jaddnum(retjson,"timestamp_ms",LP_utcmillis());
+        msgstr = jprint(retjson,1);
+        LP_queuecommand(0,msgstr,IPC_ENDPOINT,-1,0);
+        free(msgstr);
+    }
+}
+
+// the time the other side's tx gets for target confirmations, never less than a feebump delay
+int32_t LP_swap_confirmwait(char *symbol,int32_t target)
+{
+    int32_t timeout; struct iguana_info *coin;
+    timeout = LP_confirm_timeout(symbol,target);
+    if ( (coin= LP_coinfind(symbol)) != 0 && timeout < LP_feebump_delay(coin) )
+        timeout = LP_feebump_delay(coin);
+    return(timeout);
+}
+
+// waits until rawtx has target confirmations. onpoll runs every round (fee bumps, replacement checks), onconfirm for each new
+// confirmation. reserved keeps our next payment's utxo out of the orderbook meanwhile. -1 on watchdog, or for the other side's
+// tx once target blocks took LP_CONFIRM_TIMEOUT_FACTOR times longer than they should, never less than a feebump delay so
+// they get to bump it. our own tx (onpoll LP_swap_feebump) is already out, giving up on it would only stop the fee bumps
+int32_t LP_swap_waitconfirms(struct basilisk_swap *swap,char *symbol,struct basilisk_rawtx *rawtx,int32_t target,struct basilisk_rawtx *reserved,int32_t (*onpoll)(struct basilisk_swap *swap,struct basilisk_rawtx *rawtx,int32_t numconfs),void (*onconfirm)(struct basilisk_swap *swap,char *symbol,struct basilisk_rawtx *rawtx,int32_t numconfs,int32_t target))
+{
+    int32_t n,lastn = -1,confdelay = 0,timeout; uint32_t deadline = 0; char str[65];
+    timeout = LP_swap_confirmwait(symbol,target);
+    if ( onpoll != LP_swap_feebump )
+        deadline = LP_now() + timeout;
+    while ( (n= LP_swap_numconfirms(symbol,rawtx->I.destaddr,rawtx->I.signedtxid)) < target )
+    {
+        if ( reserved != 0 )
+        {
+            LP_swap_critical = (uint32_t)time(NULL);
+            LP_unavailableset(reserved->utxotxid,reserved->utxovout,(uint32_t)time(NULL)+60,swap->I.otherhash);
+        }
+        if ( n != lastn )
+        {
+            printf("%d wait for %s %s numconfs.%d %s %s\n",n,rawtx->name,rawtx->I.destaddr,target,symbol,bits256_str(str,rawtx->I.signedtxid));
+            if ( onconfirm != 0 && n > 0 && n > lastn )
+                (*onconfirm)(swap,symbol,rawtx,n,target);
+            lastn = n;
+        }
+        if ( onpoll != 0 )
+            (*onpoll)(swap,rawtx,n);
+        LP_confirmwait(symbol,&confdelay);
+        if ( swap->watchdog != 0 )
+            return(-1);
+        if ( deadline != 0 && LP_now() > deadline )
+        {
+            printf("%s %s still at %d/%d confirmations after %ds\n",rawtx->name,bits256_str(str,rawtx->I.signedtxid),n,target,timeout);
+            swap->timedout = LP_now();
+            return(-1);
+        }
+    }
+    if ( onconfirm != 0 && target > 0 )
+        (*onconfirm)(swap,symbol,rawtx,n,target);
+    return(n);
+}
+
+// a zero conf payment is only taken from the mempool if it cant be replaced by fee, pays at least the going fee and spends
+// only confirmed outputs, an unconfirmed parent could itself be replaced or dropped and take the payment with it
+int32_t LP_zeroconf_check(char *symbol,struct basilisk_rawtx *rawtx,char *reason)
+{
+    struct iguana_info *coin; cJSON *txobj,*vins,*vouts,*vin; int64_t inputs=0,outputs=0,value; uint64_t minfee; int32_t i,numvins,numvouts,retval = -1;
+    if ( (coin= LP_coinfind(symbol)) == 0 || rawtx->I.datalen <= 0 )
+    {
+        strcpy(reason,"no txbytes");
+        return(-1);
+    }
+    if ( LP_swap_numconfirms(symbol,rawtx->I.destaddr,rawtx->I.signedtxid) < 0 )
+    {
+        strcpy(reason,"not in mempool");
+        return(-1);
+    }
+    if ( (txobj= LP_transaction_fromdata(coin,rawtx->I.signedtxid,rawtx->txbytes,rawtx->I.datalen)) == 0 )
+    {
+        strcpy(reason,"cant parse tx");
+        return(-1);
+    }
+    vins = jarray(&numvins,txobj,"vin");
+    vouts = jarray(&numvouts,txobj,"vout");
+    for (i=0; i<numvins; i++)
+    {
+        vin = jitem(vins,i);
+        if ( juint(vin,"sequence") < 0xfffffffe )
+        {
+            sprintf(reason,"vin.%d signals rbf",i);
+            break;
+        }
+        if ( (value= LP_outpoint_amount(symbol,jbits256(vin,"txid"),jint(vin,"vout"))) <= 0 )
+        {
+            sprintf(reason,"vin.%d unknown amount",i);
+            break;
+        }
+        if ( LP_txheight(coin,jbits256(vin,"txid")) <= 0 )
+        {
+            sprintf(reason,"vin.%d spends an unconfirmed output",i);
+            break;
+        }
+        inputs += value;
+    }
+    if ( i == numvins && numvins > 0 )
+    {
+        for (i=0; i<numvouts; i++)
+            outputs += LP_value_extract(jitem(vouts,i),0,rawtx->I.signedtxid);
+        minfee = LP_txfeecalc(coin,0,rawtx->I.datalen);
+        if ( inputs - outputs < (int64_t)minfee )
+            sprintf(reason,"txfee %.8f below %.8f",dstr(inputs - outputs),dstr(minfee));
+        else retval = 0;
+    }
+    free_json(txobj);
+    return(retval);
+}
+
+// target 0 on a coin that granted zero conf for this swap means mempool acceptance, a payment that fails
+// LP_zeroconf_check falls back to waiting for a confirmation
+int32_t LP_swap_waitpayment(struct basilisk_swap *swap,char *symbol,struct basilisk_rawtx *rawtx,int32_t target,int32_t bobflag,struct basilisk_rawtx *reserved,int32_t (*onpoll)(struct basilisk_swap *swap,struct basilisk_rawtx *rawtx,int32_t numconfs),void (*onconfirm)(struct basilisk_swap *swap,char *symbol,struct basilisk_rawtx *rawtx,int32_t numconfs,int32_t target))
+{
+    int32_t n; char reason[128],str[65];
+    if ( (n= LP_swap_waitconfirms(swap,symbol,rawtx,target,reserved,onpoll,onconfirm)) < 0 || target != 0 || swap->zeroconfvol[bobflag != 0] == 0 )
+        return(n);
+    if ( swap->I.otheristrusted != 0 && swap->I.otherstrust != 0 )
+        return(n);
+    if ( n > 0 || LP_zeroconf_check(symbol,rawtx,reason) == 0 )
+        return(n);
+    printf("zeroconf %s %s %s rejected: %s, waiting for a confirmation\n",symbol,rawtx->name,bits256_str(str,rawtx->I.signedtxid),reason);
+    return(LP_swap_waitconfirms(swap,symbol,rawtx,1,reserved,onpoll,onconfirm));
+}
+
+// a taker without enough reputation could match, let us deposit and vanish with an unconfirmed dexfee that never
+// makes it into a block. for them the deposit waits until the fee has a confirmation, at most LP_swap_confirmwait which
+// the taker adds to its own wait since the pubkeys message told it so
+int32_t LP_swap_feeconfirm(struct basilisk_swap *swap,char *symbol)
+{
+    char str[65];
+    if ( swap->I.feeconfirm == 0 || swap->otherfee.I.ethTxid[0] != 0 )
+        return(0);
+    LP_swaplog(swap,"FeeConfirmWait",bits256_str(str,swap->otherfee.I.signedtxid));
+    if ( LP_swap_waitconfirms(swap,symbol,&swap->otherfee,1,&swap->bobdeposit,0,LP_confirm_progress) < 0 )
+        return(-1);
+    return(0);
+}
+
+// both payments, net of the agreed claim fees, must clear the negotiated margin before anything is broadcast
+int32_t LP_swap_dustcheck(struct basilisk_swap *swap)
+{
+    int64_t alicemin,bobmin;
+    if ( (alicemin= LP_min_tradevol(LP_coinfind(swap->I.alicestr))) < swap->claimmargins[0] )
+        alicemin = swap->claimmargins[0];
+    if ( (bobmin= LP_min_tradevol(LP_coinfind(swap->I.bobstr))) < swap->claimmargins[1] )
+        bobmin = swap->claimmargins[1];
+    if ( swap->I.alicesatoshis - swap->I.Aclaimfee < alicemin )
+    {
+        printf("alice %s %.8f after claimfee %.8f below margin %.8f\n",swap->I.alicestr,dstr(swap->I.alicesatoshis - swap->I.Aclaimfee),dstr(swap->I.Aclaimfee),dstr(alicemin));
+        return(-1);
+    }
+    if ( swap->I.bobsatoshis - swap->I.Bclaimfee < bobmin )
+    {
+        printf("bob %s %.8f after claimfee %.8f below margin %.8f\n",swap->I.bobstr,dstr(swap->I.bobsatoshis - swap->I.Bclaimfee),dstr(swap->I.Bclaimfee),dstr(bobmin));
+        return(-1);
+    }
+    return(0);
+}
+
+double LP_swap_elapsed(struct basilisk_swap *swap)
+{
+    if ( swap == 0 || swap->startmillis == 0. )
+        return(0.);
+    return(LP_monotonic_millis() - swap->startmillis);
+}
+
+// the phase a state ends, -1 for states like Mempool* or Maintenance* that stay in the current one
+int32_t LP_swap_phase(char *statename)
+{
+    char *fees[] = { "myfee", "otherfee" },*payments[] = { "bobdeposit", "alicepayment", "bobpayment" };
+    char *spends[] = { "alicespend", "bobspend", "aliceclaim", "bobrefund", "bobreclaim", "alicereclaim" }; int32_t i;
+    for (i=0; i<sizeof(fees)/sizeof(*fees); i++)
+        if ( strcmp(statename,fees[i]) == 0 )
+            return(1);
+    for (i=0; i<sizeof(payments)/sizeof(*payments); i++)
+        if ( strcmp(statename,payments[i]) == 0 )
+            return(2);
+    for (i=0; i<sizeof(spends)/sizeof(*spends); i++)
+        if ( strcmp(statename,spends[i]) == 0 )
+            return(3);
+    return(-1);
+}
+
+// time to reach a state is charged to its phase, everything before the first fee state is negotiation
+void LP_swap_phaseadd(struct basilisk_swap *swap,char *statename,double millis)
+{
+    int32_t phase;
+    if ( (phase= LP_swap_phase(statename)) > swap->phase )
+        swap->phase = phase;
+    swap->phasemillis[phase >= 0 ? phase : swap->phase] += millis;
+}
+
+// final timings go into the persisted swap record and the aggregates shown by metrics and statsdisp
+void LP_swap_timings(struct basilisk_swap *swap,int32_t failed)
+{
+    if ( swap->startmillis == 0. )
+        return;
+    LP_metrics_timings(swap->phasemillis,swap->phase,failed);
+    basilisk_dontforget_update(swap,0);
+}
+
+void LP_swaplog(struct basilisk_swap *swap,char *statename,char *msg)
+{
+    FILE *fp; char fname[512],tstr[64]; uint64_t millis; double now,elapsed;
+    if ( swap == 0 )
+        return;
+    if ( swap->startmillis != 0. )
+    {
+        now = LP_monotonic_millis();
+        elapsed = now - (swap->laststatemillis != 0. ? swap->laststatemillis : swap->startmillis);
+        LP_metrics_state(statename,elapsed);
+        LP_swap_phaseadd(swap,statename,elapsed);
+        swap->laststatemillis = now;
+    }
+    safecopy(swap->statename,statename,sizeof(swap->statename));
+    swap->statetime = LP_now();
+    if ( swap->uuidstr[0] == 0 )
+        return;
+    LP_log_swapstatus(swap,statename,msg);
+    sprintf(fname,"%s/SWAPS/%s.log",GLOBAL_DBDIR,swap->uuidstr), OS_compatible_path(fname);
+    if ( (fp= fopen(fname,"a")) != 0 )
+    {
+        millis = LP_utcmillis();
+        fprintf(fp,"%s.%03d +%.3f %s %u-%u %s: %s\n",utc_str(tstr,(uint32_t)(millis / 1000)),(int32_t)(millis % 1000),LP_swap_elapsed(swap)/1000.,swap->I.iambob != 0 ? "bob" : "alice",swap->I.req.requestid,swap->I.req.quoteid,statename,msg);
+        fclose(fp);
+    }
+}
+
+// one json line per swap message next to the swap log, for support bundles
+void LP_swaptrace(struct basilisk_swap *swap,char *dir,char *msgname,char *channel,uint8_t *data,int32_t datalen,int32_t retval)
+{
+    FILE *fp; char fname[512],*str; cJSON *item;
+    if ( swap == 0 || swap->uuidstr[0] == 0 )
+        return;
+    sprintf(fname,"%s/SWAPS/%s.trace",GLOBAL_DBDIR,swap->uuidstr), OS_compatible_path(fname);
+    if ( (fp= fopen(fname,"a")) != 0 )
+    {
+        item = cJSON_CreateObject();
+        jaddnum(item,"timestamp_ms",LP_utcmillis());
+        jaddnum(item,"elapsed_ms",(int64_t)LP_swap_elapsed(swap));
+        jaddstr(item,"dir",dir);
+        jaddstr(item,"msg",msgname);
+        if ( channel != 0 )
+            jaddstr(item,"channel",channel);
+        jaddnum(item,"size",datalen);
+        jaddnum(item,"crc",datalen > 0 ? calc_crc32(0,data,datalen) : 0);
+        jaddnum(item,"result",retval);
+        str = jprint(item,1);
+        fprintf(fp,"%s\n",str);
+        free(str);
+        fclose(fp);
+    }
+}
+
+char *LP_verifyname(int32_t (*verify)(struct basilisk_swap *swap,uint8_t *data,int32_t datalen))
+{
+    if ( verify == LP_pubkeys_verify )
+        return("pubkeys");
+    else if ( verify == LP_choosei_verify )
+        return("choosei");
+    else if ( verify == LP_mostprivs_verify )
+        return("mostprivs");
+    else if ( verify == LP_verify_otherfee )
+        return("otherfee");
+    else if ( verify == LP_verify_bobdeposit )
+        return("bobdeposit");
+    else if ( verify == LP_verify_alicepayment )
+        return("alicepayment");
+    else if ( verify == LP_verify_bobpayment )
+        return("bobpayment");
+    else if ( verify == LP_verify_alicespend )
+        return("alicespend");
+    return("unknown");
+}
+
+int32_t LP_swaplog_uuidvalid(char *uuidstr)
+{
+    int32_t len;
+    if ( uuidstr == 0 || (len= (int32_t)strlen(uuidstr)) == 0 || len > 64 || is_hexstr(uuidstr,0) != len )
+        return(0);
+    return(1);
+}
+
+char *LP_swaplog_tail(char *uuidstr,int32_t numlines)
+{
+    FILE *fp; char fname[512],line[1024],**lines; int32_t i,n = 0; cJSON *retjson,*array;
+    if ( LP_swaplog_uuidvalid(uuidstr) == 0 )
+        return(clonestr("{\"error\":\"invalid uuid\"}"));
+    if ( numlines <= 0 )
+        numlines = 50;
+    else if ( numlines > 1000 )
+        numlines = 1000;
+    sprintf(fname,"%s/SWAPS/%s.log",GLOBAL_DBDIR,uuidstr), OS_compatible_path(fname);
+    if ( (fp= fopen(fname,"rb")) == 0 )
+        return(clonestr("{\"error\":\"no log for uuid\"}"));
+    lines = calloc(numlines,sizeof(*lines));
+    while ( fgets(line,sizeof(line),fp) != 0 )
+    {
+        line[strcspn(line,"\r\n")] = 0;
+        if ( lines[n % numlines] != 0 )
+            free(lines[n % numlines]);
+        lines[n % numlines] = clonestr(line);
+        n++;
+    }
+    fclose(fp);
+    array = cJSON_CreateArray();
+    for (i=(n > numlines ? n-numlines : 0); i<n; i++)
+        jaddistr(array,lines[i % numlines]);
+    for (i=0; i<numlines; i++)
+        if ( lines[i] != 0 )
+            free(lines[i]);
+    free(lines);
+    retjson = cJSON_CreateObject();
+    jaddstr(retjson,"result","success");
+    jaddstr(retjson,"uuid",uuidstr);
+    jaddnum(retjson,"total",n);
+    jadd(retjson,"log",array);
+    return(jprint(retjson,1));
+}
+
+int32_t LP_waitfor(int32_t pairsock,struct basilisk_swap *swap,int32_t timeout,int32_t (*verify)(struct basilisk_swap *swap,uint8_t *data,int32_t datalen))
+{
+    struct nn_pollfd pfd; void *data; uint8_t *space,*opened; int32_t datalen,openlen,channel,retval = -1; uint32_t expiration = LP_now() + timeout;
+    swap->timedout = 0;
+    while ( LP_now() < expiration && swap->aborted == 0 )
+    {
+        data = 0;
+        channel = 0;
+        memset(&pfd,0,sizeof(pfd));
+        pfd.fd = pairsock;
+        pfd.events = NN_POLLIN;
+        if ( nn_poll(&pfd,1,1) > 0 )
+        {
+            //printf("start wait\n");
+            if ( (datalen= nn_recv(pairsock,&data,NN_MSG,0)) < 0 )
+                data = 0;
+        }
+        if ( data == 0 && (data= LP_swapmsg_next(swap,&datalen,&channel)) == 0 )
+            continue;
+        if ( LP_swapmsg_dup(swap,data,datalen) != 0 ) // the other channel was faster
+        {
+            swap->duplicates++;
+            if ( channel == 0 )
+                nn_freemsg(data);
+            else free(data);
+            continue;
+        }
+        //printf("wait for got.%d\n",datalen);
+        space = malloc(datalen);
+        if ( (opened= LP_swapopen(swap,&openlen,space,data,datalen)) == 0 )
+        {
+            printf("%s message over %s failed authentication aliceid.%llu requestid.%u quoteid.%u\n",LP_verifyname(verify),LP_swapchannels[channel],(long long)swap->aliceid,swap->I.req.requestid,swap->I.req.quoteid);
+            LP_swaptrace(swap,"recv",LP_verifyname(verify),LP_swapchannels[channel],data,datalen,-1);
+            if ( channel != 0 ) // cmdchannel copies were authenticated when queued, this is a stale or replayed one
+            {
+                free(space);
+                free(data);
+                continue;
+            }
+            swap->macerr = (uint32_t)time(NULL);
+        }
+        else
+        {
+            swap->delivered[channel]++;
+            retval = (*verify)(swap,opened,openlen);
+            // only a message from the counterparty whose signed pubkeys bound its identity counts against that pubkey
+            if ( retval < 0 && swap->persistent_other33[0] != 0 )
+                LP_pubkey_validationfail(LP_pubkeyfind(swap->I.otherhash),LP_verifyname(verify));
+            swap->received = (uint32_t)time(NULL);
+            LP_swaptrace(swap,"recv",LP_verifyname(verify),LP_swapchannels[channel],opened,openlen,retval);
+        }
+        free(space);
+        if ( channel == 0 )
+            nn_freemsg(data);
+        else free(data);
+        //printf("retval.%d\n",retval);
+        return(retval);
+    }
+    if ( swap->aborted == 0 )
+        swap->timedout = LP_now();
+    printf("waitfor timedout aliceid.%llu requestid.%u quoteid.%u\n",(long long)swap->aliceid,swap->I.req.requestid,swap->I.req.quoteid);
+    return(retval);
+}
+
+int32_t swap_nn_send(struct basilisk_swap *swap,int32_t sock,uint8_t *data,int32_t datalen,int32_t timeout)
+{
+    struct nn_pollfd pfd; int32_t i;
+    for (i=0; i<timeout*1000; i++)
+    {
+        memset(&pfd,0,sizeof(pfd));
+        pfd.fd = sock;
+        pfd.events = NN_POLLOUT;
+        if ( nn_poll(&pfd,1,1) > 0 )
+            return(LP_swapsealsend(swap,sock,data,datalen));
+        usleep(1000);
+    }
+    return(LP_swapsealsend(swap,-1,data,datalen)); // pair never got writable, the relay is all there is
+}
+
+// a wait on a counterparty tx, timed so the tuning history learns how long the coin usually takes
+int32_t LP_waitfortx(struct basilisk_swap *swap,char *symbol,int32_t timeout,int32_t (*verify)(struct basilisk_swap *swap,uint8_t *data,int32_t datalen))
+{
+    uint32_t started = LP_now(); int32_t retval;
+    retval = LP_waitfor(swap->N.pair,swap,timeout,verify);
+    if ( swap->aborted == 0 && swap->watchdog == 0 )
+        LP_tuning_waited(symbol,LP_now() - started,retval < 0 && swap->timedout != 0);
+    return(retval);
+}
+
+int32_t LP_waitsend(char *statename,int32_t timeout,int32_t pairsock,struct basilisk_swap *swap,uint8_t *data,int32_t maxlen,int32_t (*verify)(struct basilisk_swap *swap,uint8_t *data,int32_t datalen),int32_t (*datagen)(struct basilisk_swap *swap,uint8_t *data,int32_t maxlen))
+{
+    int32_t datalen,sendlen,retval = -1;
+    //printf("waitsend.%s timeout.%d\n",statename,timeout);
+    if ( LP_waitfor(pairsock,swap,timeout,verify) == 0 )
+    {
+        //printf("waited for %s\n",statename);
+        if ( (datalen= (*datagen)(swap,data,maxlen)) > 0 )
+        {
+            sendlen = swap_nn_send(swap,pairsock,data,datalen,timeout);
+            LP_swaptrace(swap,"send",statename,0,data,datalen,sendlen);
+            if ( sendlen == datalen )
+            {
+                //printf("sent.%d after waitfor.%s\n",sendlen,statename);
+                retval = 0;
+            } else printf("send %s error\n",statename);
+        } else printf("%s datagen no data\n",statename);
+    } else printf("didnt get valid data after %d\n",timeout);
+    LP_swaplog(swap,statename,retval == 0 ? "completed" : "failed");
+    return(retval);
+}
+
+int32_t LP_sendwait(char *statename,int32_t timeout,int32_t pairsock,struct basilisk_swap *swap,uint8_t *data,int32_t maxlen,int32_t (*verify)(struct basilisk_swap *swap,uint8_t *data,int32_t datalen),int32_t (*datagen)(struct basilisk_swap *swap,uint8_t *data,int32_t maxlen))
+{
+    int32_t datalen,sendlen,retval = -1;
+    //printf("sendwait.%s\n",statename);
+    if ( (datalen= (*datagen)(swap,data,maxlen)) > 0 )
+    {
+        //printf("generated %d for %s, timeout.%d\n",datalen,statename,timeout);
+        sendlen = swap_nn_send(swap,pairsock,data,datalen,timeout);
+        LP_swaptrace(swap,"send",statename,0,data,datalen,sendlen);
+        if ( sendlen == datalen )
+        {
+            //printf("sendwait.%s sent %d\n",statename,sendlen);
+            if ( LP_waitfor(pairsock,swap,timeout,verify) == 0 )
+            {
+                //printf("waited! sendwait.%s sent %d\n",statename,sendlen);
+                retval = 0;
+            } else printf("didnt get %s\n",statename);
+        } else printf("send %s error\n",statename);
+    } else printf("no datagen for %s\n",statename);
+    LP_swaplog(swap,statename,retval == 0 ? "completed" : "failed");
+    return(retval);
+}
+
+void LP_swapsfp_update(uint32_t requestid,uint32_t quoteid)
+{
+    static FILE *swapsfp;
     portable_mutex_lock(&LP_listmutex);
     if ( swapsfp == 0 )
     {
@@ -586,6 +1685,42 @@ struct basilisk_rawtx *LP_swapdata_rawtx(struct basilisk_swap *swap,uint8_t *dat
     return(0);
 }
 
+// a received deposit or payment must pay exactly the P2SH of the script we build ourselves from the negotiated locktime,
+// pubkeys and secret hashes, and the redeemscript sent along must be that script. the right amount to another script
+// would leave us holding a claim or refund path that doesnt exist
+int32_t LP_swap_scriptcheck(struct basilisk_swap *swap,struct iguana_info *coin,struct basilisk_rawtx *rawtx,uint8_t *outscript,int32_t outlen)
+{
+    uint8_t redeemscript[512],spendscript[128],rmd160[20]; char msigaddr[64],*expectstr,*gotstr,msg[2048]; int32_t redeemlen = 0,spendlen = 0,secretstart; uint32_t locktime;
+    if ( rawtx == &swap->bobdeposit || rawtx == &swap->bobpayment )
+        spendlen = basilisk_bobscript(rmd160,redeemscript,&redeemlen,spendscript,0,&locktime,&secretstart,&swap->I,rawtx == &swap->bobdeposit);
+    else if ( rawtx == &swap->alicepayment )
+        spendlen = basilisk_alicescript(coin->symbol,redeemscript,&redeemlen,spendscript,0,msigaddr,coin->taddr,coin->p2shtype,swap->I.pubAm,swap->I.pubBn);
+    else return(0);
+    if ( redeemlen <= 0 || spendlen <= 0 )
+    {
+        printf("%s cant rebuild expected redeemscript\n",rawtx->name);
+        return(-1);
+    }
+    if ( redeemlen == rawtx->I.redeemlen && memcmp(redeemscript,rawtx->redeemscript,redeemlen) == 0 && spendlen == outlen && memcmp(spendscript,outscript,outlen) == 0 )
+        return(0);
+    expectstr = malloc(redeemlen*2 + 1);
+    init_hexbytes_noT(expectstr,redeemscript,redeemlen);
+    gotstr = malloc(rawtx->I.redeemlen*2 + 1);
+    init_hexbytes_noT(gotstr,rawtx->redeemscript,rawtx->I.redeemlen);
+    if ( spendlen == outlen && memcmp(spendscript,outscript,outlen) == 0 )
+        snprintf(msg,sizeof(msg),"%s redeemscript mismatch, expected %s got %s",rawtx->name,expectstr,gotstr);
+    else
+    {
+        bitcoin_address(coin->symbol,msigaddr,coin->taddr,coin->p2shtype,redeemscript,redeemlen);
+        snprintf(msg,sizeof(msg),"%s pays the wrong script, expected p2sh %s of %s",rawtx->name,msigaddr,expectstr);
+    }
+    free(expectstr);
+    free(gotstr);
+    LP_swaplog(swap,"ScriptMismatch",msg);
+    printf("%s\n",msg);
+    return(-1);
+}
+
 int32_t LP_rawtx_spendscript(struct basilisk_swap *swap,int32_t height,struct basilisk_rawtx *rawtx,int32_t v,uint8_t *recvbuf,int32_t recvlen,int32_t suppress_pubkeys)
 {
     bits256 otherhash,myhash,txid; int64_t txfee,val; int32_t i,offset=0,datalen=0,retval=-1,hexlen,n; uint8_t *data; cJSON *txobj,*skey,*vouts,*vout; char *hexstr,bobstr[65],alicestr[65],redeemaddr[64],checkaddr[64]; uint32_t quoteid,msgbits; struct iguana_info *coin;
@@ -673,8 +1808,11 @@ int32_t LP_rawtx_spendscript(struct basilisk_swap *swap,int32_t height,struct ba
                     txfee = swap->I.Atxfee;
                 else txfee = LP_MIN_TXFEE;
             }
-            if ( rawtx->I.amount > 2*txfee)
-                val = rawtx->I.amount-2*txfee;
+            val = rawtx->I.amount;
+            if ( rawtx == &swap->otherfee )
+                val -= (int64_t)(LP_dexfee_tolerance * val);
+            if ( val > 2*txfee)
+                val -= 2*txfee;
             else val = 1;
             if ( j64bits(vout,"satoshis") >= val && (skey= jobj(vout,"scriptPubKey")) != 0 && (hexstr= jstr(skey,"hex")) != 0 )
             {
@@ -684,6 +1822,11 @@ int32_t LP_rawtx_spendscript(struct basilisk_swap *swap,int32_t height,struct ba
                     rawtx->I.spendlen = hexlen;
                     //if ( swap != 0 )
                     //    basilisk_txlog(swap->myinfoptr,swap,rawtx,-1); // bobdeposit, bobpayment or alicepayment
+                    if ( LP_swap_scriptcheck(swap,coin,rawtx,rawtx->spendscript,hexlen) < 0 )
+                    {
+                        free_json(txobj);
+                        return(-1);
+                    }
                     retval = 0;
                     if ( rawtx == &swap->otherfee )
                     {
@@ -698,15 +1841,96 @@ int32_t LP_rawtx_spendscript(struct basilisk_swap *swap,int32_t height,struct ba
     return(retval);
 }
 
-uint32_t LP_swapdata_rawtxsend(int32_t pairsock,struct basilisk_swap *swap,uint32_t msgbits,uint8_t *data,int32_t maxlen,struct basilisk_rawtx *rawtx,uint32_t nextbits,int32_t suppress_swapsend)
+// ask the node if it would take a funding tx before it goes out, and fix what a fee bump or fresh inputs can fix
+// spends and refunds are presigned against the other side, a rejection there is only logged and LP_remember retries
+int32_t LP_swap_precheck(struct basilisk_swap *swap,struct basilisk_rawtx *rawtx)
+{
+    struct iguana_info *coin; char *signedtx,reason[256],coinaddr[64],msg[512]; int64_t txfee; int32_t iter,regen,retval = -1;
+    if ( (coin= LP_coinfind(rawtx->symbol)) == 0 || rawtx->I.datalen <= 0 )
+        return(-1);
+    for (iter=0; iter<4; iter++)
+    {
+        signedtx = malloc(rawtx->I.datalen*2 + 1);
+        init_hexbytes_noT(signedtx,rawtx->txbytes,rawtx->I.datalen);
+        retval = LP_testmempoolaccept(rawtx->symbol,signedtx,reason,sizeof(reason));
+        free(signedtx);
+        if ( retval != 0 || strstr(reason,"already") != 0 )
+            return(retval < 0 ? -1 : 1);
+        snprintf(msg,sizeof(msg),"%s rejected by %s node: %s",rawtx->name,rawtx->symbol,reason);
+        LP_swaplog(swap,"MempoolReject",msg);
+        printf("%s\n",msg);
+        if ( rawtx != &swap->myfee && rawtx != &swap->bobdeposit && rawtx != &swap->bobpayment && rawtx != &swap->alicepayment )
+            return(0);
+        txfee = (rawtx == &swap->alicepayment) ? swap->I.Atxfee : coin->txfee;
+        if ( txfee < LP_MIN_TXFEE )
+            txfee = LP_MIN_TXFEE;
+        regen = 0;
+        if ( strstr(reason,"fee") != 0 )
+            txfee <<= (iter + 1), regen = 1;
+        else if ( strstr(reason,"missing-inputs") != 0 || strstr(reason,"conflict") != 0 || strstr(reason,"spent") != 0 )
+        {
+            memset(rawtx->utxotxid.bytes,0,sizeof(rawtx->utxotxid));
+            rawtx->utxovout = 0;
+            regen = 1;
+        }
+        if ( regen == 0 )
+            return(0);
+        bitcoin_address(coin->symbol,coinaddr,coin->taddr,coin->pubtype,swap->changermd160,20);
+        if ( basilisk_rawtx_gen(swap->ctx,rawtx->name,swap->I.started,swap->persistent_pubkey33,swap->I.iambob,1,rawtx,rawtx->I.locktime,rawtx->spendscript,rawtx->I.spendlen,txfee,1,0,swap->persistent_privkey,swap->changermd160,coinaddr) < 0 )
+        {
+            LP_swaplog(swap,"MempoolReject","regenerate failed");
+            return(0);
+        }
+        if ( rawtx == &swap->bobdeposit )
+        {
+            swap->bobrefund.utxovout = 0;
+            swap->bobrefund.utxotxid = swap->bobdeposit.I.signedtxid;
+            basilisk_bobdeposit_refund(swap,swap->I.putduration);
+        }
+        snprintf(msg,sizeof(msg),"%s regenerated txfee %.8f",rawtx->name,dstr(txfee));
+        LP_swaplog(swap,"MempoolRetry",msg);
+    }
+    return(0);
+}
+
+// nothing is locked yet before myfee, bobdeposit and alicepayment go out, so those wait out a coin's maintenance.
+// later sends dont, holding bobpayment or a spend back could cost the deposit or the funds. the wait stops once half of
+// putduration is gone, the rest of the swap still needs the other half before plocktime, and the send is dropped
+int32_t LP_swap_maintenancewait(struct basilisk_swap *swap,struct basilisk_rawtx *rawtx)
+{
+    struct iguana_info *coin; uint32_t deadline;
+    if ( rawtx != &swap->myfee && rawtx != &swap->bobdeposit && rawtx != &swap->alicepayment )
+        return(0);
+    if ( (coin= LP_coinfind(rawtx->symbol)) == 0 || coin->maintenance == 0 )
+        return(0);
+    deadline = swap->I.started + swap->I.putduration/2;
+    LP_swaplog(swap,"MaintenancePause",rawtx->name);
+    while ( coin->maintenance != 0 && swap->aborted == 0 && swap->watchdog == 0 && LP_now() < deadline )
+        sleep(10);
+    if ( coin->maintenance != 0 )
+    {
+        printf("%s still in maintenance, %s not sent with the refund window closing\n",rawtx->symbol,rawtx->name);
+        LP_swaplog(swap,"MaintenanceTimeout",rawtx->name);
+        return(-1);
+    }
+    LP_swaplog(swap,"MaintenanceResume",rawtx->name);
+    return(0);
+}
+
+uint32_t _LP_swapdata_rawtxsend(int32_t pairsock,struct basilisk_swap *swap,uint32_t msgbits,uint8_t *data,int32_t maxlen,struct basilisk_rawtx *rawtx,uint32_t nextbits,int32_t suppress_swapsend)
 {
     uint8_t sendbuf[32768]; int32_t sendlen,retval = -1;
     if ( LP_swapdata_rawtx(swap,data,maxlen,rawtx) != 0 )
     {
         if ( bits256_nonz(rawtx->I.signedtxid) != 0 && bits256_nonz(rawtx->I.actualtxid) == 0 )
         {
+            if ( LP_swap_maintenancewait(swap,rawtx) < 0 )
+                return(0);
+            LP_swap_precheck(swap,rawtx);
             basilisk_dontforget_update(swap,rawtx);
             rawtx->I.actualtxid = LP_broadcast_tx(rawtx->name,rawtx->symbol,rawtx->txbytes,rawtx->I.datalen);
+            rawtx->senttime = LP_now();
+            LP_swapchange_add(rawtx->symbol,rawtx->I.actualtxid,swap->I.req.requestid,swap->I.req.quoteid,swap->I.started+swap->I.putduration+swap->I.callduration);
             if ( bits256_cmp(rawtx->I.actualtxid,rawtx->I.signedtxid) != 0 )
             {
                 char str[65],str2[65];
@@ -760,12 +1984,15 @@ uint32_t LP_swapdata_rawtxsend(int32_t pairsock,struct basilisk_swap *swap,uint3
                 //printf("sendlen.%d datalen.%d redeemlen.%d\n",sendlen,rawtx->datalen,rawtx->redeemlen);
                 if ( suppress_swapsend == 0 )
                 {
+                    char str[65];
                     retval = LP_swapsend(pairsock,swap,msgbits,sendbuf,sendlen,nextbits,rawtx->I.crcs);
-                    if ( LP_waitmempool(rawtx->symbol,rawtx->I.destaddr,rawtx->I.signedtxid,0,LP_SWAPSTEP_TIMEOUT*10) < 0 )
+                    LP_swaptrace(swap,"send",rawtx->name,0,sendbuf,sendlen,sendlen);
+                    if ( LP_waitmempool(rawtx->symbol,rawtx->I.destaddr,rawtx->I.signedtxid,0,LP_swapsteptimeout(swap)*10) < 0 )
                     {
-                        char str[65]; printf("failed to find %s %s %s in the mempool?\n",rawtx->name,rawtx->I.destaddr,bits256_str(str,rawtx->I.actualtxid));
+                        printf("failed to find %s %s %s in the mempool?\n",rawtx->name,rawtx->I.destaddr,bits256_str(str,rawtx->I.actualtxid));
                         retval = -1;
                     }
+                    LP_swaplog(swap,rawtx->name,retval == -1 ? "not in mempool" : bits256_str(str,rawtx->I.actualtxid));
                     return(retval);
                 }
                 else
@@ -775,202 +2002,1173 @@ uint32_t LP_swapdata_rawtxsend(int32_t pairsock,struct basilisk_swap *swap,uint3
                 }
             }
         }
-        return(nextbits);
-    } //else if ( swap->I.iambob == 0 )
-        printf("error from basilisk_swapdata_rawtx.%s %p len.%d\n",rawtx->name,rawtx->txbytes,rawtx->I.datalen);
-    return(0);
+        return(nextbits);
+    } //else if ( swap->I.iambob == 0 )
+        printf("error from basilisk_swapdata_rawtx.%s %p len.%d\n",rawtx->name,rawtx->txbytes,rawtx->I.datalen);
+    return(0);
+}
+
+uint32_t LP_swapwait(uint32_t expiration,uint32_t requestid,uint32_t quoteid,int32_t duration,int32_t sleeptime)
+{
+    char *retstr; uint32_t finished = 0; cJSON *retjson=0;
+    if ( sleeptime != 0 )
+    {
+        printf("wait %d:%d for SWAP.(r%u/q%u) to complete\n",duration,sleeptime,requestid,quoteid);
+        sleep(sleeptime/3);
+    }
+    while ( expiration == 0 || LP_now() < expiration )
+    {
+        if ( (retstr= basilisk_swapentry(0,requestid,quoteid,1)) != 0 )
+        {
+            if ( (retjson= cJSON_Parse(retstr)) != 0 )
+            {
+                if ( jstr(retjson,"status") != 0 && strcmp(jstr(retjson,"status"),"finished") == 0 )
+                {
+                    finished = LP_now();
+                    free(retstr), retstr = 0;
+                    break;
+                }
+                else if ( expiration != 0 && LP_now() > expiration )
+                    printf("NOT FINISHED.(%s)\n",jprint(retjson,0));
+                free_json(retjson), retjson = 0;
+            }
+            free(retstr);
+        }
+        if ( sleeptime != 0 )
+            sleep(sleeptime);
+        if ( duration < 0 )
+            break;
+    }
+    if ( retjson != 0 )
+    {
+        free_json(retjson);
+        if ( (retstr= basilisk_swapentry(0,requestid,quoteid,1)) != 0 )
+        {
+            printf("\n>>>>>>>>>>>>>>>>>>>>>>>>>\nSWAP completed! %u-%u %s\n",requestid,quoteid,retstr);
+            free(retstr);
+        }
+        return(finished);
+    }
+    else
+    {
+        if ( expiration != 0 && LP_now() > expiration )
+            printf("\nSWAP did not complete! %u-%u %s\n",requestid,quoteid,jprint(retjson,0));
+        if ( duration > 0 )
+            LP_pendswap_add(expiration,requestid,quoteid);
+        return(0);
+    }
+}
+
+int32_t LP_calc_waittimeout(struct basilisk_swap *swap,char *symbol)
+{
+    struct iguana_info *coin; int32_t waittimeout = swap->txwaittimeout != 0 ? swap->txwaittimeout : LP_txwait_timeout;
+    if ( (coin= LP_coinfind(symbol)) != 0 && coin->blocktime > 60 )
+        waittimeout = (waittimeout * coin->blocktime) / 60;
+    else if ( strcmp(symbol,"BTC") == 0 )
+        waittimeout *= 8;
+    else if ( LP_is_slowcoin(symbol) != 0 )
+        waittimeout *= 4;
+    return(LP_tuning_waittimeout(symbol,waittimeout));
+}
+
+struct LP_swapmsg
+{
+    struct LP_swapmsg *next,*prev;
+    int32_t channel,len;
+    uint8_t data[];
+};
+
+struct LP_swapabort
+{
+    struct LP_swapabort *next,*prev;
+    struct basilisk_swap *swap;
+    struct LP_swapmsg *msgs;
+    char uuidstr[65];
+    bits256 otherhash;
+    uint32_t committed,makerpaid;
+} *LP_swapaborts;
+
+void LP_swapabort_register(struct basilisk_swap *swap)
+{
+    struct LP_swapabort *sp = calloc(1,sizeof(*sp));
+    sp->swap = swap;
+    sp->otherhash = swap->I.iambob != 0 ? swap->I.req.desthash : swap->I.req.srchash;
+    safecopy(sp->uuidstr,swap->uuidstr,sizeof(sp->uuidstr));
+    portable_mutex_lock(&LP_swapabortmutex);
+    DL_APPEND(LP_swapaborts,sp);
+    portable_mutex_unlock(&LP_swapabortmutex);
+}
+
+void LP_swapabort_unregister(struct basilisk_swap *swap)
+{
+    struct LP_swapabort *sp,*tmp; struct LP_swapmsg *mp,*mtmp;
+    portable_mutex_lock(&LP_swapabortmutex);
+    DL_FOREACH_SAFE(LP_swapaborts,sp,tmp)
+    {
+        if ( sp->swap == swap )
+        {
+            DL_DELETE(LP_swapaborts,sp);
+            DL_FOREACH_SAFE(sp->msgs,mp,mtmp)
+            {
+                DL_DELETE(sp->msgs,mp);
+                free(mp);
+            }
+            free(sp);
+        }
+    }
+    portable_mutex_unlock(&LP_swapabortmutex);
+}
+
+// once the first funds are about to be committed on chain the swap can no longer be aborted
+int32_t LP_swapabort_commit(struct basilisk_swap *swap)
+{
+    struct LP_swapabort *sp; int32_t retval = -1;
+    portable_mutex_lock(&LP_swapabortmutex);
+    DL_FOREACH(LP_swapaborts,sp)
+    {
+        if ( sp->swap == swap )
+        {
+            if ( swap->aborted == 0 )
+            {
+                sp->committed = (uint32_t)time(NULL);
+                retval = 0;
+            }
+            break;
+        }
+    }
+    portable_mutex_unlock(&LP_swapabortmutex);
+    return(retval);
+}
+
+int32_t LP_swapabort_set(char *uuidstr,bits256 otherhash)
+{
+    struct LP_swapabort *sp; int32_t retval = -1;
+    portable_mutex_lock(&LP_swapabortmutex);
+    DL_FOREACH(LP_swapaborts,sp)
+    {
+        if ( strcmp(sp->uuidstr,uuidstr) == 0 )
+        {
+            if ( bits256_nonz(otherhash) != 0 && bits256_cmp(otherhash,sp->otherhash) != 0 )
+                retval = -2;
+            else if ( sp->committed != 0 )
+                retval = -3;
+            else
+            {
+                if ( sp->swap->aborted == 0 )
+                    sp->swap->aborted = (uint32_t)time(NULL);
+                retval = 0;
+            }
+            break;
+        }
+    }
+    portable_mutex_unlock(&LP_swapabortmutex);
+    return(retval);
+}
+
+// swap messages can also go to the counterparty over its cmdchannel, so a dropped pair socket during the long payment
+// waits doesnt kill the swap. never as a broadcast, the payload is the sealed box the pair socket carries and only the
+// counterparty needs it. LP_waitfor takes whichever copy comes first and counts the rest as duplicates.
+// "swap_relay":0 at startup turns this off
+#define LP_SWAPRELAY_MAXSIZE 16384
+#define LP_SWAPRELAY_MAXQUEUED 16
+
+int32_t LP_swaprelay = 1;
+
+int32_t LP_swaprelay_send(struct basilisk_swap *swap,uint8_t *sealed,int32_t sealedlen)
+{
+    struct LP_pubkey_info *pubp; cJSON *reqjson; char *hexstr,*msg; bits256 otherhash; int32_t sentbytes;
+    if ( LP_swaprelay == 0 || sealedlen > LP_SWAPRELAY_MAXSIZE || swap->uuidstr[0] == 0 )
+        return(-1);
+    otherhash = LP_swap_otherpub25519(swap);
+    if ( (pubp= LP_pubkeyfind(otherhash)) == 0 || pubp->pairsock < 0 )
+        return(-1);
+    hexstr = malloc(sealedlen*2 + 1);
+    init_hexbytes_noT(hexstr,sealed,sealedlen);
+    reqjson = cJSON_CreateObject();
+    jaddstr(reqjson,"method","swapmsg");
+    jaddstr(reqjson,"uuid",swap->uuidstr);
+    jaddbits256(reqjson,"dest",otherhash);
+    jaddnum(reqjson,"timestamp",(uint32_t)time(NULL));
+    jaddstr(reqjson,"data",hexstr);
+    free(hexstr);
+    msg = jprint(reqjson,1);
+    sentbytes = nn_send(pubp->pairsock,msg,(int32_t)strlen(msg)+1,0);
+    free(msg);
+    return(sentbytes);
+}
+
+char *LP_swapmsg_recv(cJSON *argjson)
+{
+    struct LP_swapabort *sp; struct LP_swapmsg *mp; char *uuidstr,*hexstr; uint8_t *data; int32_t len,n;
+    if ( bits256_cmp(jbits256(argjson,"dest"),G.LP_mypub25519) != 0 )
+        return(clonestr("{\"result\":\"success\"}"));
+    if ( (uuidstr= jstr(argjson,"uuid")) == 0 || (hexstr= jstr(argjson,"data")) == 0 || (len= (int32_t)strlen(hexstr)) == 0 || (len & 1) != 0 || len/2 > LP_SWAPRELAY_MAXSIZE || is_hexstr(hexstr,0) != len )
+        return(clonestr("{\"error\":\"missing fields in swapmsg\"}"));
+    len >>= 1;
+    data = malloc(len);
+    decode_hex(data,len,hexstr);
+    portable_mutex_lock(&LP_swapabortmutex);
+    DL_FOREACH(LP_swapaborts,sp)
+    {
+        // only a box the counterparty sealed for this swap gets queued, anything else could fill the queue
+        if ( strcmp(sp->uuidstr,uuidstr) == 0 && LP_swapsealed_valid(sp->swap,data,len) != 0 )
+        {
+            DL_COUNT(sp->msgs,mp,n);
+            if ( n >= LP_SWAPRELAY_MAXQUEUED )
+                break;
+            mp = calloc(1,sizeof(*mp) + len);
+            mp->len = len;
+            mp->channel = 1;
+            memcpy(mp->data,data,len);
+            DL_APPEND(sp->msgs,mp);
+            break;
+        }
+    }
+    portable_mutex_unlock(&LP_swapabortmutex);
+    free(data);
+    return(clonestr("{\"result\":\"success\"}"));
+}
+
+// next relayed message for swap as a malloced copy of the sealed bytes
+uint8_t *LP_swapmsg_next(struct basilisk_swap *swap,int32_t *datalenp,int32_t *channelp)
+{
+    struct LP_swapabort *sp; struct LP_swapmsg *mp = 0; uint8_t *data = 0;
+    portable_mutex_lock(&LP_swapabortmutex);
+    DL_FOREACH(LP_swapaborts,sp)
+    {
+        if ( sp->swap == swap )
+        {
+            if ( (mp= sp->msgs) != 0 )
+                DL_DELETE(sp->msgs,mp);
+            break;
+        }
+    }
+    portable_mutex_unlock(&LP_swapabortmutex);
+    if ( mp != 0 )
+    {
+        data = malloc(mp->len);
+        memcpy(data,mp->data,mp->len);
+        *datalenp = mp->len;
+        *channelp = mp->channel;
+        free(mp);
+    }
+    return(data);
+}
+
+// 1 if the same sealed bytes already came in on another channel
+int32_t LP_swapmsg_dup(struct basilisk_swap *swap,uint8_t *data,int32_t datalen)
+{
+    bits256 hash; int32_t i;
+    vcalc_sha256(0,hash.bytes,data,datalen);
+    for (i=0; i<LP_SWAPMSG_HASHES; i++)
+        if ( bits256_cmp(hash,swap->msghashes[i]) == 0 )
+            return(1);
+    swap->msghashes[swap->msghashind++ % LP_SWAPMSG_HASHES] = hash;
+    return(0);
+}
+
+// swaps still running that use symbol on either side
+int32_t LP_swapabort_coin(char *symbol)
+{
+    struct LP_swapabort *sp; int32_t n = 0;
+    portable_mutex_lock(&LP_swapabortmutex);
+    DL_FOREACH(LP_swapaborts,sp)
+    {
+        if ( strcmp(sp->swap->I.bobstr,symbol) == 0 || strcmp(sp->swap->I.alicestr,symbol) == 0 )
+            n++;
+    }
+    portable_mutex_unlock(&LP_swapabortmutex);
+    return(n);
+}
+
+void LP_swapabort_makerpaid(struct basilisk_swap *swap)
+{
+    struct LP_swapabort *sp;
+    portable_mutex_lock(&LP_swapabortmutex);
+    DL_FOREACH(LP_swapaborts,sp)
+    {
+        if ( sp->swap == swap )
+        {
+            sp->makerpaid = (uint32_t)time(NULL);
+            break;
+        }
+    }
+    portable_mutex_unlock(&LP_swapabortmutex);
+}
+
+// graceful stop: new ordermatches are refused, uncommitted swaps are aborted and committed ones run on until none is
+// between signing a tx and persisting its broadcast. every other state is already on disk and LP_remember resumes it,
+// so the swaps still open are written to DB/SHUTDOWN and put on the pending list at the next start
+#define LP_SHUTDOWN_DEADLINE 600
+
+uint32_t LP_shutdown_started,LP_shutdown_deadline,LP_shutdown_final;
+
+int32_t LP_swap_sendbegin(struct basilisk_swap *swap)
+{
+    int32_t retval = 0;
+    portable_mutex_lock(&LP_swapabortmutex);
+    if ( LP_shutdown_final != 0 )
+        swap->shutdown = (uint32_t)time(NULL), retval = -1;
+    else swap->sending = (uint32_t)time(NULL);
+    portable_mutex_unlock(&LP_swapabortmutex);
+    return(retval);
+}
+
+uint32_t LP_swapdata_rawtxsend(int32_t pairsock,struct basilisk_swap *swap,uint32_t msgbits,uint8_t *data,int32_t maxlen,struct basilisk_rawtx *rawtx,uint32_t nextbits,int32_t suppress_swapsend)
+{
+    uint32_t retval;
+    if ( LP_swap_sendbegin(swap) < 0 )
+    {
+        printf("shutting down, %s not sent, swap resumes on restart\n",rawtx->name);
+        return(0);
+    }
+    retval = _LP_swapdata_rawtxsend(pairsock,swap,msgbits,data,maxlen,rawtx,nextbits,suppress_swapsend);
+    swap->sending = 0;
+    return(retval);
+}
+
+// the active swaps, *unsafep counts the ones in the middle of a send. finalflag stops any new send once none is
+cJSON *LP_shutdown_swaps(int32_t *unsafep,int32_t finalflag)
+{
+    struct LP_swapabort *sp; struct basilisk_swap *swap; cJSON *array,*item;
+    *unsafep = 0;
+    array = cJSON_CreateArray();
+    portable_mutex_lock(&LP_swapabortmutex);
+    DL_FOREACH(LP_swapaborts,sp)
+    {
+        swap = sp->swap;
+        item = cJSON_CreateObject();
+        jaddstr(item,"uuid",sp->uuidstr);
+        jaddnum(item,"requestid",swap->I.req.requestid);
+        jaddnum(item,"quoteid",swap->I.req.quoteid);
+        jaddstr(item,"role",swap->I.iambob != 0 ? "maker" : "taker");
+        jaddstr(item,"state",swap->statename);
+        jaddnum(item,"committed",sp->committed != 0);
+        if ( swap->sending != 0 )
+        {
+            jaddstr(item,"status","sending");
+            (*unsafep)++;
+        } else jaddstr(item,"status",sp->committed != 0 ? "resume" : "aborting");
+        jaddi(array,item);
+    }
+    if ( finalflag != 0 && *unsafep == 0 )
+        LP_shutdown_final = (uint32_t)time(NULL);
+    portable_mutex_unlock(&LP_swapabortmutex);
+    return(array);
+}
+
+void LP_shutdown_loop(void *ctx)
+{
+    struct LP_swapabort *sp; char uuids[64][65],fname[512],*str; int32_t i,n,unsafe; cJSON *array,*retjson; FILE *fp;
+    while ( 1 )
+    {
+        n = 0;
+        portable_mutex_lock(&LP_swapabortmutex);
+        DL_FOREACH(LP_swapaborts,sp)
+            if ( sp->committed == 0 && sp->swap->aborted == 0 && n < sizeof(uuids)/sizeof(*uuids) )
+                safecopy(uuids[n++],sp->uuidstr,sizeof(uuids[0]));
+        portable_mutex_unlock(&LP_swapabortmutex);
+        for (i=0; i<n; i++)
+            if ( (str= LP_abort_swap(uuids[i])) != 0 )
+                free(str);
+        array = LP_shutdown_swaps(&unsafe,1);
+        if ( LP_shutdown_final != 0 || time(NULL) >= LP_shutdown_deadline )
+            break;
+        free_json(array);
+        sleep(1);
+    }
+    retjson = cJSON_CreateObject();
+    jaddnum(retjson,"started",LP_shutdown_started);
+    jaddnum(retjson,"stopped",(uint32_t)time(NULL));
+    jaddnum(retjson,"deadline_hit",LP_shutdown_final == 0);
+    jadd(retjson,"swaps",array);
+    str = jprint(retjson,1);
+    sprintf(fname,"%s/SHUTDOWN",GLOBAL_DBDIR), OS_compatible_path(fname);
+    if ( (fp= fopen(fname,"wb")) != 0 )
+    {
+        fprintf(fp,"%s\n",str);
+        fclose(fp);
+    }
+    printf("shutdown: %s\n",str);
+    free(str);
+    free_json(retjson);
+    LP_STOP_RECEIVED = 1;
+}
+
+char *LP_shutdown(void *ctx,int32_t deadline)
+{
+    cJSON *retjson; int32_t unsafe;
+    if ( LP_shutdown_started == 0 )
+    {
+        if ( deadline <= 0 )
+            deadline = LP_SHUTDOWN_DEADLINE;
+        LP_shutdown_started = (uint32_t)time(NULL);
+        LP_shutdown_deadline = LP_shutdown_started + deadline;
+        if ( OS_thread_create(malloc(sizeof(pthread_t)),NULL,(void *)LP_shutdown_loop,ctx) != 0 )
+        {
+            LP_shutdown_started = 0;
+            return(clonestr("{\"error\":\"couldnt launch shutdown loop\"}"));
+        }
+        printf("graceful shutdown started, deadline %ds\n",deadline);
+    }
+    retjson = cJSON_CreateObject();
+    jaddstr(retjson,"result","success");
+    jaddstr(retjson,"status",LP_shutdown_final != 0 ? "stopping" : "waiting_swaps");
+    jaddnum(retjson,"deadline",LP_shutdown_deadline);
+    jadd(retjson,"swaps",LP_shutdown_swaps(&unsafe,0));
+    jaddnum(retjson,"unsafe",unsafe);
+    return(jprint(retjson,1));
+}
+
+// swaps left open by the last graceful shutdown go straight back on the pending list
+void LP_shutdown_resume()
+{
+    char fname[512],errfname[512],*fstr; long fsize; cJSON *retjson,*array,*item; int32_t i,n,num = 0;
+    sprintf(fname,"%s/SHUTDOWN",GLOBAL_DBDIR), OS_compatible_path(fname);
+    if ( (fstr= OS_filestr(&fsize,fname)) == 0 )
+        return;
+    if ( (retjson= cJSON_Parse(fstr)) != 0 )
+    {
+        if ( (array= jarray(&n,retjson,"swaps")) != 0 )
+        {
+            for (i=0; i<n; i++)
+            {
+                item = jitem(array,i);
+                if ( jint(item,"committed") != 0 && juint(item,"requestid") != 0 )
+                {
+                    LP_pendswap_add(0,juint(item,"requestid"),juint(item,"quoteid"));
+                    sprintf(errfname,"%s/SWAPS/%u-%u.error",GLOBAL_DBDIR,juint(item,"requestid"),juint(item,"quoteid")), OS_compatible_path(errfname);
+                    OS_removefile(errfname,0); // the "resumable" marker of a send the stop held back
+                    num++;
+                }
+            }
+        }
+        free_json(retjson);
+    }
+    free(fstr);
+    OS_removefile(fname,0);
+    printf("resuming %d swaps left open by the last shutdown\n",num);
+}
+
+// nonzero once the maker side of an active swap has started sending its payment
+int32_t LP_swap_makerpaid(char *uuidstr)
+{
+    struct LP_swapabort *sp; int32_t retval = 0;
+    if ( uuidstr == 0 || uuidstr[0] == 0 )
+        return(0);
+    portable_mutex_lock(&LP_swapabortmutex);
+    DL_FOREACH(LP_swapaborts,sp)
+    {
+        if ( strcmp(sp->uuidstr,uuidstr) == 0 )
+        {
+            retval = (sp->makerpaid != 0);
+            break;
+        }
+    }
+    portable_mutex_unlock(&LP_swapabortmutex);
+    return(retval);
 }
 
-uint32_t LP_swapwait(uint32_t expiration,uint32_t requestid,uint32_t quoteid,int32_t duration,int32_t sleeptime)
+// negotiation states get a few step timeouts, once funds are committed no state may run past the payment locktime,
+// after that only the refund paths in LP_remember are safe. watchdog_maxstate caps both
+int32_t LP_swap_watchdog_limit(struct basilisk_swap *swap,uint32_t committed)
 {
-    char *retstr; uint32_t finished = 0; cJSON *retjson=0;
-    if ( sleeptime != 0 )
+    int32_t limit,locklimit;
+    if ( committed == 0 )
+        limit = 4 * LP_swapsteptimeout(swap);
+    else
     {
-        printf("wait %d:%d for SWAP.(r%u/q%u) to complete\n",duration,sleeptime,requestid,quoteid);
-        sleep(sleeptime/3);
+        locklimit = (int32_t)(swap->I.started + swap->I.putduration) - (int32_t)swap->statetime;
+        limit = locklimit > LP_swapsteptimeout(swap) ? locklimit : LP_swapsteptimeout(swap);
     }
-    while ( expiration == 0 || time(NULL) < expiration )
+    if ( LP_watchdog_maxstate > 0 && limit > LP_watchdog_maxstate )
+        limit = LP_watchdog_maxstate;
+    return(limit);
+}
+
+void LP_swap_watchdog()
+{
+    struct LP_swapabort *sp; struct basilisk_swap *swap; uint32_t now = LP_now(); int32_t limit; char msg[128];
+    portable_mutex_lock(&LP_swapabortmutex);
+    DL_FOREACH(LP_swapaborts,sp)
     {
-        if ( (retstr= basilisk_swapentry(0,requestid,quoteid,1)) != 0 )
+        swap = sp->swap;
+        if ( swap->watchdog != 0 || swap->statetime == 0 )
+            continue;
+        limit = LP_swap_watchdog_limit(swap,sp->committed);
+        if ( now > swap->statetime + limit )
         {
-            if ( (retjson= cJSON_Parse(retstr)) != 0 )
-            {
-                if ( jstr(retjson,"status") != 0 && strcmp(jstr(retjson,"status"),"finished") == 0 )
-                {
-                    finished = (uint32_t)time(NULL);
-                    free(retstr), retstr = 0;
-                    break;
-                }
-                else if ( expiration != 0 && time(NULL) > expiration )
-                    printf("NOT FINISHED.(%s)\n",jprint(retjson,0));
-                free_json(retjson), retjson = 0;
-            }
-            free(retstr);
+            snprintf(msg,sizeof(msg),"stuck in %s for %us, limit %ds",swap->statename,now - swap->statetime,limit);
+            printf("swap watchdog %s: %s\n",swap->uuidstr,msg);
+            swap->watchdog = now;
+            if ( swap->aborted == 0 )
+                swap->aborted = now;
+            LP_swaplog(swap,"WatchdogAbort",msg);
         }
-        if ( sleeptime != 0 )
-            sleep(sleeptime);
-        if ( duration < 0 )
+    }
+    portable_mutex_unlock(&LP_swapabortmutex);
+}
+
+char *LP_abort_swap(char *uuidstr)
+{
+    struct LP_swapabort *sp; bits256 otherhash; uint32_t timestamp; char pubsecpstr[67]; int32_t retval; cJSON *reqjson;
+    if ( uuidstr == 0 || uuidstr[0] == 0 )
+        return(clonestr("{\"error\":\"missing uuid\"}"));
+    memset(otherhash.bytes,0,sizeof(otherhash));
+    portable_mutex_lock(&LP_swapabortmutex);
+    DL_FOREACH(LP_swapaborts,sp)
+    {
+        if ( strcmp(sp->uuidstr,uuidstr) == 0 )
+        {
+            otherhash = sp->otherhash;
             break;
+        }
     }
-    if ( retjson != 0 )
+    portable_mutex_unlock(&LP_swapabortmutex);
+    if ( (retval= LP_swapabort_set(uuidstr,otherhash)) == -1 )
+        return(clonestr("{\"error\":\"no active swap with that uuid\"}"));
+    else if ( retval == -3 )
+        return(clonestr("{\"error\":\"swap already committed funds, cant abort\"}"));
+    reqjson = cJSON_CreateObject();
+    jaddstr(reqjson,"method","swap_aborted");
+    jaddstr(reqjson,"uuid",uuidstr);
+    jaddbits256(reqjson,"pubkey",G.LP_mypub25519);
+    timestamp = (uint32_t)time(NULL);
+    jaddnum(reqjson,"timestamp",timestamp);
+    init_hexbytes_noT(pubsecpstr,G.LP_pubsecp,33);
+    jaddstr(reqjson,"pubsecp",pubsecpstr);
+    LP_swapabort_sigadd(reqjson,timestamp,G.LP_privkey,G.LP_pubsecp,G.LP_mypub25519,uuidstr);
+    LP_reserved_msg(1,"","",otherhash,jprint(reqjson,1));
+    printf("aborted swap uuid.%s\n",uuidstr);
+    return(clonestr("{\"result\":\"success\",\"status\":\"swap aborted\"}"));
+}
+
+struct LP_swaperrinfo { int32_t err; char *kind,*side; } LP_swaperrs[] =
+{
+    { -1000, "NegotiationFailed", 0 }, { -1001, "NegotiationFailed", 0 }, { -1002, "NegotiationFailed", 0 },
+    { -1003, "PaymentCreationFailed", "taker" }, { -1004, "TakerFeeBroadcastFailed", "taker" },
+    { -1005, "PaymentValidationFailed", "maker" }, { -1006, "PaymentBroadcastFailed", "taker" },
+    { -1007, "PaymentValidationFailed", "maker" }, { -1008, "Aborted", 0 }, { -1009, "IdentityMismatch", "maker" },
+    { -1010, "LocktimeMismatch", "maker" }, { -1011, "LocktimeMismatch", "maker" }, { -1012, "FeeScheduleMismatch", "maker" },
+    { -1013, "AmountBelowDust", 0 }, { -1015, "WatchdogTimeout", 0 }, { -1016, "ConfirmationTimeout", 0 },
+    { -2000, "NegotiationFailed", 0 }, { -2001, "NegotiationFailed", 0 }, { -2002, "NegotiationFailed", 0 },
+    { -2003, "PaymentCreationFailed", "maker" }, { -2004, "InvalidTakerFee", "taker" },
+    { -2005, "PaymentBroadcastFailed", "maker" }, { -2006, "PaymentValidationFailed", "taker" },
+    { -2007, "PaymentCreationFailed", "maker" }, { -2008, "PaymentBroadcastFailed", "maker" }, { -2009, "Aborted", 0 },
+    { -2010, "IdentityMismatch", "taker" }, { -2011, "LocktimeMismatch", "taker" }, { -2012, "LocktimeMismatch", "taker" },
+    { -2013, "FeeScheduleMismatch", "taker" }, { -2014, "AmountBelowDust", 0 }, { -2016, "TakerPaymentReorged", "taker" },
+    { -2017, "WatchdogTimeout", 0 }, { -2018, "ConfirmationTimeout", 0 }, { -2019, "TakerFeeUnconfirmed", "taker" },
+    { -3000, "CoinNotFound", "maker" }, { -3001, "SwapInitFailed", "maker" }, { -3002, "SwapInitFailed", "maker" },
+    { -3003, "PairBindFailed", "maker" }, { -3004, "PrivkeyNotFound", "maker" }, { -3005, "StalePrice", "maker" },
+    { -3006, "ExposureLimit", "taker" }, { -3007, "QueueExpired", "maker" }, { -3008, "Declined", 0 },
+    { -3009, "VolumeExceeded", "taker" }, { -3010, "Maintenance", "maker" }, { -3011, "InsufficientFunds", "maker" },
+    { -4000, "NegotiationFailed", "maker" }, { -4002, "SwapInitFailed", "taker" }, { -4003, "CoinNotFound", "taker" },
+    { -4004, "SwapInitFailed", "taker" }, { -4005, "PairBindFailed", "taker" }, { -4006, "SwapInitFailed", "taker" },
+    { -4007, "PairBindFailed", "taker" }, { -4008, "PrivkeyNotFound", "taker" }, { -4009, "Declined", 0 },
+    { -6000, "RefundBroadcastFailed", "maker" }, { -6001, "RefundBroadcastFailed", "taker" },
+    { -5000, "InsufficientGas", "maker" }, { -5001, "InsufficientGas", "taker" },
+};
+
+char *LP_swaperr_kind(int32_t err,char *category,char **sidep)
+{
+    int32_t i;
+    if ( sidep != 0 )
+        *sidep = 0;
+    for (i=0; i<sizeof(LP_swaperrs)/sizeof(*LP_swaperrs); i++)
     {
-        free_json(retjson);
-        if ( (retstr= basilisk_swapentry(0,requestid,quoteid,1)) != 0 )
+        if ( LP_swaperrs[i].err == err )
         {
-            printf("\n>>>>>>>>>>>>>>>>>>>>>>>>>\nSWAP completed! %u-%u %s\n",requestid,quoteid,retstr);
-            free(retstr);
+            if ( sidep != 0 )
+                *sidep = LP_swaperrs[i].side;
+            if ( category != 0 && strcmp(category,"shutdown") == 0 )
+                return("Shutdown");
+            else if ( category != 0 && strcmp(category,"timeout") == 0 && strcmp(LP_swaperrs[i].kind,"NegotiationFailed") == 0 )
+                return("NegotiationTimeout");
+            return(LP_swaperrs[i].kind);
         }
-        return(finished);
     }
-    else
+    return("Unknown");
+}
+
+char *LP_swaperr_category(struct basilisk_swap *swap,int32_t err)
+{
+    if ( swap->shutdown != 0 )
+        return("shutdown");
+    else if ( swap->watchdog != 0 )
+        return("watchdog");
+    else if ( swap->aborted != 0 || err == -2009 || err == -1008 )
+        return("aborted");
+    else if ( err == -2005 || err == -2008 || err == -1003 || err == -1004 || err == -1006 )
+        return("broadcast");
+    else if ( swap->timedout != 0 )
+        return("timeout");
+    else return("validation");
+}
+
+struct LP_countdown
+{
+    struct LP_countdown *next,*prev;
+    char uuidstr[65];
+    uint32_t requestid,quoteid,plocktime,dlocktime,lastsent;
+    int32_t iambob,makerfirst;
+} *LP_countdowns;
+
+void LP_countdown_add(struct basilisk_swap *swap)
+{
+    struct LP_countdown *cp = calloc(1,sizeof(*cp));
+    safecopy(cp->uuidstr,swap->uuidstr,sizeof(cp->uuidstr));
+    cp->requestid = swap->I.req.requestid;
+    cp->quoteid = swap->I.req.quoteid;
+    cp->iambob = swap->I.iambob;
+    cp->makerfirst = swap->I.makerfirst;
+    cp->plocktime = swap->I.started + swap->I.putduration;
+    cp->dlocktime = swap->I.started + swap->I.putduration + swap->I.callduration;
+    portable_mutex_lock(&LP_countdownmutex);
+    DL_APPEND(LP_countdowns,cp);
+    portable_mutex_unlock(&LP_countdownmutex);
+}
+
+void LP_countdown_remove(uint32_t requestid,uint32_t quoteid)
+{
+    struct LP_countdown *cp,*tmp;
+    portable_mutex_lock(&LP_countdownmutex);
+    DL_FOREACH_SAFE(LP_countdowns,cp,tmp)
     {
-        if ( expiration != 0 && time(NULL) > expiration )
-            printf("\nSWAP did not complete! %u-%u %s\n",requestid,quoteid,jprint(retjson,0));
-        if ( duration > 0 )
-            LP_pendswap_add(expiration,requestid,quoteid);
-        return(0);
+        if ( cp->requestid == requestid && cp->quoteid == quoteid )
+        {
+            DL_DELETE(LP_countdowns,cp);
+            free(cp);
+        }
     }
+    portable_mutex_unlock(&LP_countdownmutex);
 }
 
-int32_t LP_calc_waittimeout(char *symbol)
+void LP_countdown_timer(cJSON *array,char *event,char *desc,uint32_t locktime,uint32_t now,char *msg,int32_t maxlen)
 {
-    int32_t waittimeout = TX_WAIT_TIMEOUT;
-    if ( strcmp(symbol,"BTC") == 0 )
-        waittimeout *= 8;
-    else if ( LP_is_slowcoin(symbol) != 0 )
-        waittimeout *= 4;
-    return(waittimeout);
+    cJSON *item = cJSON_CreateObject();
+    jaddstr(item,"event",event);
+    jaddnum(item,"locktime",locktime);
+    jaddnum(item,"remaining",locktime > now ? locktime - now : 0);
+    jaddi(array,item);
+    if ( msg[0] == 0 )
+    {
+        if ( locktime > now )
+            snprintf(msg,maxlen,"%s in %u min",desc,(locktime - now + 59) / 60);
+        else snprintf(msg,maxlen,"%s now",desc);
+    }
 }
 
-void LP_bobloop(void *_swap)
+// pushes refund/reclaim timers for every swap that still has funds under a locktime
+void LP_countdowns_send()
 {
-    uint8_t *data; char bobstr[65],alicestr[65]; int32_t bobwaittimeout,alicewaittimeout,maxlen,m,n,err=0; uint32_t expiration; struct basilisk_swap *swap = _swap;
-    G.LP_pendingswaps++;
-    //printf("start swap iambob\n");
-    LP_etomicsymbol(bobstr,swap->I.bobtomic,swap->I.bobstr);
-    LP_etomicsymbol(alicestr,swap->I.alicetomic,swap->I.alicestr);
-    maxlen = 1024*1024 + sizeof(*swap);
-    data = malloc(maxlen);
-    expiration = (uint32_t)time(NULL) + LP_SWAPSTEP_TIMEOUT;
-    bobwaittimeout = LP_calc_waittimeout(bobstr);
-    alicewaittimeout = LP_calc_waittimeout(alicestr);
-#ifndef NOTETOMIC
-    if (swap->I.bobtomic[0] != 0 || swap->I.alicetomic[0] != 0) {
-        int error = 0;
-        uint64_t eth_balance = getEthBalance(swap->I.etomicsrc, &error);
-        if (eth_balance < 500000) {
-            err = -5000, printf("Bob ETH balance too low, aborting swap!\n");
+    struct LP_countdown *cp; cJSON *retjson,*array; uint32_t now = LP_now(); char msg[128],*str;
+    if ( IPC_ENDPOINT < 0 )
+        return;
+    portable_mutex_lock(&LP_countdownmutex);
+    DL_FOREACH(LP_countdowns,cp)
+    {
+        if ( now < cp->lastsent+LP_COUNTDOWN_INTERVAL )
+            continue;
+        cp->lastsent = now;
+        msg[0] = 0;
+        array = cJSON_CreateArray();
+        if ( cp->makerfirst != 0 ) // no deposit, bobpayment has the long lock and alicepayment the short one
+        {
+            if ( cp->iambob != 0 )
+                LP_countdown_timer(array,"bobreclaim","bobpayment reclaimable",cp->dlocktime,now,msg,sizeof(msg));
+            else LP_countdown_timer(array,"alicereclaim","alicepayment reclaimable",cp->plocktime,now,msg,sizeof(msg));
         }
+        else if ( cp->iambob != 0 )
+        {
+            if ( now < cp->plocktime )
+                LP_countdown_timer(array,"bobreclaim","bobpayment reclaimable",cp->plocktime,now,msg,sizeof(msg));
+            LP_countdown_timer(array,"bobrefund","bobdeposit refundable",cp->dlocktime,now,msg,sizeof(msg));
+        } else LP_countdown_timer(array,"aliceclaim","bobdeposit claimable",cp->dlocktime,now,msg,sizeof(msg));
+        retjson = cJSON_CreateObject();
+        jaddstr(retjson,"method","countdown");
+        jaddstr(retjson,"uuid",cp->uuidstr);
+        jaddnum(retjson,"requestid",cp->requestid);
+        jaddnum(retjson,"quoteid",cp->quoteid);
+        jaddnum(retjson,"iambob",cp->iambob);
+        jaddstr(retjson,"status",msg);
+        jadd(retjson,"timers",array);
+        str = jprint(retjson,1);
+        LP_queuecommand(0,str,IPC_ENDPOINT,-1,0);
+        free(str);
     }
-#endif
+    portable_mutex_unlock(&LP_countdownmutex);
+}
 
-    if ( swap != 0 && err == 0)
+int32_t LP_makerswaps,LP_max_concurrent_swaps;
+
+char *LP_makercapacity_check(struct LP_quoteinfo *qp)
+{
+    struct iguana_info *coin,*othercoin; char *reason = 0;
+    portable_mutex_lock(&LP_inflightmutex);
+    if ( LP_shutdown_started != 0 || ((coin= LP_coinfind(qp->srccoin)) != 0 && coin->maintenance != 0) || ((othercoin= LP_coinfind(qp->destcoin)) != 0 && othercoin->maintenance != 0) )
+        reason = "maintenance";
+    else if ( LP_max_concurrent_swaps > 0 && LP_makerswaps >= LP_max_concurrent_swaps )
+        reason = "max_concurrent_swaps";
+    else if ( (coin= LP_coinfind(qp->srccoin)) != 0 && coin->maxinflight != 0 && coin->inflight + qp->satoshis > coin->maxinflight )
+        reason = "max_volume_in_flight";
+    portable_mutex_unlock(&LP_inflightmutex);
+    return(reason);
+}
+
+void LP_makerswap_add(struct basilisk_swap *swap,int32_t dir)
+{
+    struct iguana_info *coin;
+    portable_mutex_lock(&LP_inflightmutex);
+    LP_makerswaps += dir;
+    if ( (coin= LP_coinfind(swap->I.req.src)) != 0 )
     {
-        if ( LP_waitsend("pubkeys",120,swap->N.pair,swap,data,maxlen,LP_pubkeys_verify,LP_pubkeys_data) < 0 )
-            err = -2000, printf("error waitsend pubkeys\n");
-        else if ( LP_waitsend("choosei",LP_SWAPSTEP_TIMEOUT,swap->N.pair,swap,data,maxlen,LP_choosei_verify,LP_choosei_data) < 0 )
-            err = -2001, printf("error waitsend choosei\n");
-        else if ( LP_waitsend("mostprivs",LP_SWAPSTEP_TIMEOUT,swap->N.pair,swap,data,maxlen,LP_mostprivs_verify,LP_mostprivs_data) < 0 )
-            err = -2002, printf("error waitsend mostprivs\n");
-        else if ( basilisk_bobscripts_set(swap,1,1) < 0 )
-            err = -2003, printf("error bobscripts deposit\n");
-        else
+        if ( dir > 0 )
+            coin->inflight += swap->I.req.srcamount;
+        else if ( coin->inflight > swap->I.req.srcamount )
+            coin->inflight -= swap->I.req.srcamount;
+        else coin->inflight = 0;
+    }
+    portable_mutex_unlock(&LP_inflightmutex);
+}
+
+// a coin with zeroconf_max_volume lets its side of the swap go with 0 confirms while the coins zero conf volume stays
+// under it. the other side asking for more confirms still wins in LP_pubkeys_verify
+void LP_zeroconf_reserve(struct basilisk_swap *swap,struct iguana_info *coin,int32_t bobflag)
+{
+    int32_t *confp = bobflag != 0 ? &swap->I.bobconfirms : &swap->I.aliceconfirms; int64_t satoshis = bobflag != 0 ? swap->I.bobsatoshis : swap->I.alicesatoshis;
+    if ( coin == 0 || coin->zeroconfmax == 0 || *confp == 0 || coin->etomic[0] != 0 || satoshis <= 0 )
+        return;
+    portable_mutex_lock(&LP_inflightmutex);
+    if ( coin->zeroconfvol + satoshis <= coin->zeroconfmax )
+    {
+        coin->zeroconfvol += satoshis;
+        swap->zeroconfvol[bobflag != 0] = satoshis;
+        *confp = 0;
+    }
+    portable_mutex_unlock(&LP_inflightmutex);
+}
+
+void LP_zeroconf_release(struct basilisk_swap *swap)
+{
+    struct iguana_info *coin; int32_t i;
+    portable_mutex_lock(&LP_inflightmutex);
+    for (i=0; i<2; i++)
+    {
+        if ( swap->zeroconfvol[i] != 0 && (coin= LP_coinfind(i == 0 ? swap->I.alicestr : swap->I.bobstr)) != 0 )
         {
+            if ( coin->zeroconfvol > swap->zeroconfvol[i] )
+                coin->zeroconfvol -= swap->zeroconfvol[i];
+            else coin->zeroconfvol = 0;
+        }
+        swap->zeroconfvol[i] = 0;
+    }
+    portable_mutex_unlock(&LP_inflightmutex);
+}
+
+void LP_swapfailed(struct basilisk_swap *swap,int32_t err)
+{
+    char errstr[64],*category;
+    category = LP_swaperr_category(swap,err);
+    sprintf(errstr,"error %d %s",err,category);
+    LP_swaplog(swap,"finished",errstr);
+    LP_swap_timings(swap,1);
+    if ( swap->shutdown != 0 ) // held back by a graceful stop after committing, LP_shutdown_resume picks it up again
+    {
+        LP_swap_errorsave(swap->I.req.requestid,swap->I.req.quoteid,err,category);
+        return;
+    }
+    LP_metrics_swap(swap->I.bobstr,swap->I.alicestr,"failed");
+    LP_tuning_swapdone(swap->I.bobstr,swap->I.alicestr,err,LP_swaperr_kind(err,category,0));
+    LP_myorder_match(swap,"failed");
+    LP_multihop_match(swap,"failed");
+    LP_swap_errorsave(swap->I.req.requestid,swap->I.req.quoteid,err,category);
+    LP_failedmsg_category(swap->I.req.requestid,swap->I.req.quoteid,err,swap->uuidstr,category);
+}
+
+// the maker and taker swaps as explicit states, in the order LP_bobloop and LP_aliceloop run them. the makerfirst (MF)
+// states are the order where bobpayment goes out first: no deposit, bobpayment locked for putduration + callduration on
+// bob's secret, alicepayment follows with only putduration, bob claims it revealing privBn and that lets alice claim bobpayment.
+// the switches over these have no default, so a state added here and not handled in LP_maker_next/LP_maker_step or the
+// taker ones is a -Wswitch error in the build
+enum LP_makerstate
+{
+    LP_MAKER_PUBKEYS, LP_MAKER_DUSTCHECK, LP_MAKER_CHOOSEI, LP_MAKER_MOSTPRIVS, LP_MAKER_DEPOSITSCRIPTS, LP_MAKER_MF_SCRIPTS,
+    LP_MAKER_WAITFEE, LP_MAKER_FEECONFIRM, LP_MAKER_COMMIT, LP_MAKER_SENDDEPOSIT, LP_MAKER_DEPOSITCONFIRM, LP_MAKER_WAITALICEPAYMENT,
+    LP_MAKER_PAYMENTSCRIPTS, LP_MAKER_ALICECONFIRM, LP_MAKER_SENDPAYMENT, LP_MAKER_MF_SENDPAYMENT, LP_MAKER_MF_PAYMENTCONFIRM,
+    LP_MAKER_MF_WAITALICEPAYMENT, LP_MAKER_MF_ALICECONFIRM, LP_MAKER_DONE, LP_MAKER_FAILED
+};
+
+enum LP_takerstate
+{
+    LP_TAKER_PUBKEYS, LP_TAKER_DUSTCHECK, LP_TAKER_CHOOSEI, LP_TAKER_MOSTPRIVS, LP_TAKER_COMMIT, LP_TAKER_ALICETXS, LP_TAKER_SENDFEE,
+    LP_TAKER_WAITDEPOSIT, LP_TAKER_DEPOSITCONFIRM, LP_TAKER_SENDPAYMENT, LP_TAKER_PAYMENTCONFIRM, LP_TAKER_WAITBOBPAYMENT,
+    LP_TAKER_BOBCONFIRM, LP_TAKER_MF_WAITBOBPAYMENT, LP_TAKER_MF_BOBCONFIRM, LP_TAKER_MF_SENDPAYMENT, LP_TAKER_MF_PAYMENTCONFIRM,
+    LP_TAKER_DONE, LP_TAKER_FAILED
+};
+
+struct LP_swaploop { uint8_t *data; int32_t maxlen,bobwaittimeout,alicewaittimeout; char bobstr[65],alicestr[65]; };
+
+// the state after a step, only from the step's error and the negotiated flags. LP_MAKER_DONE and LP_MAKER_FAILED end the swap
+enum LP_makerstate LP_maker_next(enum LP_makerstate state,int32_t err,int32_t makerfirst,int32_t internal)
+{
+    if ( err < 0 )
+        return(LP_MAKER_FAILED);
+    switch ( state )
+    {
+        case LP_MAKER_PUBKEYS: return(LP_MAKER_DUSTCHECK);
+        case LP_MAKER_DUSTCHECK: return(LP_MAKER_CHOOSEI);
+        case LP_MAKER_CHOOSEI: return(LP_MAKER_MOSTPRIVS);
+        case LP_MAKER_MOSTPRIVS: return(makerfirst != 0 ? LP_MAKER_MF_SCRIPTS : LP_MAKER_DEPOSITSCRIPTS);
+        case LP_MAKER_DEPOSITSCRIPTS: case LP_MAKER_MF_SCRIPTS: return(internal != 0 ? LP_MAKER_COMMIT : LP_MAKER_WAITFEE);
+        case LP_MAKER_WAITFEE: return(LP_MAKER_FEECONFIRM);
+        case LP_MAKER_FEECONFIRM: return(LP_MAKER_COMMIT);
+        case LP_MAKER_COMMIT: return(makerfirst != 0 ? LP_MAKER_MF_SENDPAYMENT : LP_MAKER_SENDDEPOSIT);
+        case LP_MAKER_SENDDEPOSIT: return(LP_MAKER_DEPOSITCONFIRM);
+        case LP_MAKER_DEPOSITCONFIRM: return(LP_MAKER_WAITALICEPAYMENT);
+        case LP_MAKER_WAITALICEPAYMENT: return(LP_MAKER_PAYMENTSCRIPTS);
+        case LP_MAKER_PAYMENTSCRIPTS: return(LP_MAKER_ALICECONFIRM);
+        case LP_MAKER_ALICECONFIRM: return(LP_MAKER_SENDPAYMENT);
+        case LP_MAKER_SENDPAYMENT: return(LP_MAKER_DONE);
+        case LP_MAKER_MF_SENDPAYMENT: return(LP_MAKER_MF_PAYMENTCONFIRM);
+        case LP_MAKER_MF_PAYMENTCONFIRM: return(LP_MAKER_MF_WAITALICEPAYMENT);
+        case LP_MAKER_MF_WAITALICEPAYMENT: return(LP_MAKER_MF_ALICECONFIRM);
+        case LP_MAKER_MF_ALICECONFIRM: return(LP_MAKER_DONE);
+        case LP_MAKER_DONE: case LP_MAKER_FAILED: return(state);
+    }
+    return(LP_MAKER_FAILED);
+}
+
+enum LP_takerstate LP_taker_next(enum LP_takerstate state,int32_t err,int32_t makerfirst,int32_t internal)
+{
+    if ( err < 0 )
+        return(LP_TAKER_FAILED);
+    switch ( state )
+    {
+        case LP_TAKER_PUBKEYS: return(LP_TAKER_DUSTCHECK);
+        case LP_TAKER_DUSTCHECK: return(LP_TAKER_CHOOSEI);
+        case LP_TAKER_CHOOSEI: return(LP_TAKER_MOSTPRIVS);
+        case LP_TAKER_MOSTPRIVS: return(LP_TAKER_COMMIT);
+        case LP_TAKER_COMMIT: return(LP_TAKER_ALICETXS);
+        case LP_TAKER_ALICETXS:
+            if ( internal == 0 )
+                return(LP_TAKER_SENDFEE);
+            return(makerfirst != 0 ? LP_TAKER_MF_WAITBOBPAYMENT : LP_TAKER_WAITDEPOSIT);
+        case LP_TAKER_SENDFEE: return(makerfirst != 0 ? LP_TAKER_MF_WAITBOBPAYMENT : LP_TAKER_WAITDEPOSIT);
+        case LP_TAKER_WAITDEPOSIT: return(LP_TAKER_DEPOSITCONFIRM);
+        case LP_TAKER_DEPOSITCONFIRM: return(LP_TAKER_SENDPAYMENT);
+        case LP_TAKER_SENDPAYMENT: return(LP_TAKER_PAYMENTCONFIRM);
+        case LP_TAKER_PAYMENTCONFIRM: return(LP_TAKER_WAITBOBPAYMENT);
+        case LP_TAKER_WAITBOBPAYMENT: return(LP_TAKER_BOBCONFIRM);
+        case LP_TAKER_BOBCONFIRM: return(LP_TAKER_DONE);
+        case LP_TAKER_MF_WAITBOBPAYMENT: return(LP_TAKER_MF_BOBCONFIRM);
+        case LP_TAKER_MF_BOBCONFIRM: return(LP_TAKER_MF_SENDPAYMENT);
+        case LP_TAKER_MF_SENDPAYMENT: return(LP_TAKER_MF_PAYMENTCONFIRM);
+        case LP_TAKER_MF_PAYMENTCONFIRM: return(LP_TAKER_DONE);
+        case LP_TAKER_DONE: case LP_TAKER_FAILED: return(state);
+    }
+    return(LP_TAKER_FAILED);
+}
+
+void LP_swaploop_timeouts(struct basilisk_swap *swap,struct LP_swaploop *lp)
+{
+    lp->bobwaittimeout = LP_calc_waittimeout(swap,lp->bobstr);
+    lp->alicewaittimeout = LP_calc_waittimeout(swap,lp->alicestr);
+    LP_swap_critical = (uint32_t)time(NULL);
+}
+
+void LP_swaploop_reclaimprep(struct basilisk_swap *swap)
+{
+    swap->bobreclaim.utxovout = 0;
+    swap->bobreclaim.utxotxid = swap->bobpayment.I.signedtxid;
+    basilisk_bobpayment_reclaim(swap,swap->I.callduration);
+}
+
+// the waiting, sending and persisting of one maker state, 0 or the swap's error code
+int32_t LP_maker_step(struct basilisk_swap *swap,enum LP_makerstate state,struct LP_swaploop *lp)
+{
+    int32_t err = 0;
+    switch ( state )
+    {
+        case LP_MAKER_PUBKEYS:
+            if ( LP_waitsend("pubkeys",LP_pubkeys_timeout,swap->N.pair,swap,lp->data,lp->maxlen,LP_pubkeys_verify,LP_pubkeys_data) < 0 )
+                err = swap->identityerr != 0 ? -2010 : (swap->locktimeerr != 0 ? -2010 - swap->locktimeerr : (swap->feeerr != 0 ? -2013 : -2000)), printf("error waitsend pubkeys\n");
+            break;
+        case LP_MAKER_DUSTCHECK:
+            if ( LP_swap_dustcheck(swap) < 0 )
+                err = -2014, printf("swap amount below dust\n");
+            break;
+        case LP_MAKER_CHOOSEI:
+            if ( LP_waitsend("choosei",LP_swapsteptimeout(swap),swap->N.pair,swap,lp->data,lp->maxlen,LP_choosei_verify,LP_choosei_data) < 0 )
+                err = -2001, printf("error waitsend choosei\n");
+            break;
+        case LP_MAKER_MOSTPRIVS:
+            if ( LP_waitsend("mostprivs",LP_swapsteptimeout(swap),swap->N.pair,swap,lp->data,lp->maxlen,LP_mostprivs_verify,LP_mostprivs_data) < 0 )
+                err = -2002, printf("error waitsend mostprivs\n");
+            break;
+        case LP_MAKER_DEPOSITSCRIPTS:
+            if ( basilisk_bobscripts_set(swap,1,1) < 0 )
+            {
+                err = -2003, printf("error bobscripts deposit\n");
+                break;
+            }
             swap->bobrefund.utxovout = 0;
             swap->bobrefund.utxotxid = swap->bobdeposit.I.signedtxid;
             basilisk_bobdeposit_refund(swap,swap->I.putduration);
-            //printf("depositlen.%d\n",swap->bobdeposit.I.datalen);
-            //LP_swapsfp_update(&swap->I.req);
-            LP_swap_critical = (uint32_t)time(NULL);
+            LP_swaploop_timeouts(swap,lp);
             LP_unavailableset(swap->bobdeposit.utxotxid,swap->bobdeposit.utxovout,(uint32_t)time(NULL)+60,swap->I.otherhash);
-            if ( LP_waitfor(swap->N.pair,swap,bobwaittimeout,LP_verify_otherfee) < 0 )
+            break;
+        case LP_MAKER_MF_SCRIPTS:
+            if ( basilisk_bobscripts_set(swap,0,1) < 0 || swap->bobpayment.I.datalen == 0 )
             {
+                err = -2007, printf("error bobscripts payment\n");
+                break;
+            }
+            LP_swaploop_timeouts(swap,lp);
+            LP_unavailableset(swap->bobpayment.utxotxid,swap->bobpayment.utxovout,(uint32_t)time(NULL)+60,swap->I.otherhash);
+            break;
+        case LP_MAKER_WAITFEE:
+            if ( LP_waitfortx(swap,lp->alicestr,lp->bobwaittimeout,LP_verify_otherfee) < 0 )
                 err = -2004, printf("error waiting for alicefee\n");
+            break;
+        case LP_MAKER_FEECONFIRM:
+            if ( LP_swap_feeconfirm(swap,lp->alicestr) < 0 )
+                err = -2019, printf("alicefee didnt confirm\n");
+            break;
+        case LP_MAKER_COMMIT:
+            if ( LP_swapabort_commit(swap) < 0 )
+                err = -2009, printf("swap aborted before %s\n",swap->I.makerfirst != 0 ? "bobpayment" : "bobdeposit");
+            break;
+        case LP_MAKER_SENDDEPOSIT:
+            if ( LP_swapdata_rawtxsend(swap->N.pair,swap,0x200,lp->data,lp->maxlen,&swap->bobdeposit,0x100,0) == 0 )
+            {
+                err = -2005, printf("error sending bobdeposit\n");
+                break;
+            }
+            LP_countdown_add(swap); // bobdeposit is out, from here on there is something to refund
+            LP_unavailableset(swap->bobpayment.utxotxid,swap->bobpayment.utxovout,(uint32_t)time(NULL)+60,swap->I.otherhash);
+            break;
+        case LP_MAKER_DEPOSITCONFIRM:
+            if ( LP_swap_waitconfirms(swap,lp->bobstr,&swap->bobdeposit,swap->I.bobconfirms,&swap->bobpayment,LP_swap_feebump,LP_confirm_progress) < 0 )
+                err = swap->watchdog != 0 ? -2017 : -2018;
+            break;
+        case LP_MAKER_WAITALICEPAYMENT: case LP_MAKER_MF_WAITALICEPAYMENT:
+            printf("wait for alicepayment\n");
+            if ( LP_waitfortx(swap,lp->alicestr,lp->bobwaittimeout + lp->alicewaittimeout,LP_verify_alicepayment) < 0 )
+                err = -2006, printf("error waiting for alicepayment\n");
+            break;
+        case LP_MAKER_PAYMENTSCRIPTS:
+            LP_swap_critical = (uint32_t)time(NULL);
+            if ( basilisk_bobscripts_set(swap,0,1) < 0 )
+            {
+                err = -2007, printf("error bobscripts payment\n");
+                break;
             }
-            if ( err == 0 )
+            LP_unavailableset(swap->bobpayment.utxotxid,swap->bobpayment.utxovout,(uint32_t)time(NULL)+60,swap->I.otherhash);
+            break;
+        case LP_MAKER_ALICECONFIRM: case LP_MAKER_MF_ALICECONFIRM:
+            // sync with alice, before our bobpayment its utxo stays reserved
+            if ( LP_swap_waitpayment(swap,lp->alicestr,&swap->alicepayment,swap->I.aliceconfirms,0,state == LP_MAKER_ALICECONFIRM ? &swap->bobpayment : 0,LP_swap_replaced,LP_confirm_progress) < 0 )
             {
-                if ( LP_swapdata_rawtxsend(swap->N.pair,swap,0x200,data,maxlen,&swap->bobdeposit,0x100,0) == 0 )
-                {
-                    err = -2005, printf("error sending bobdeposit\n");
-                }
+                err = swap->watchdog != 0 ? -2017 : -2018;
+                if ( state == LP_MAKER_ALICECONFIRM ) // bobpayment is signed by now, its reclaim is kept
+                    LP_swaploop_reclaimprep(swap);
             }
-            if (err == 0) {
-                LP_unavailableset(swap->bobpayment.utxotxid,swap->bobpayment.utxovout,(uint32_t)time(NULL)+60,swap->I.otherhash);
-                m = swap->I.bobconfirms;
-                while ((n = LP_numconfirms(bobstr, swap->bobdeposit.I.destaddr, swap->bobdeposit.I.signedtxid, 0, 1)) < m) {
-                    LP_swap_critical = (uint32_t) time(NULL);
-                    LP_unavailableset(swap->bobpayment.utxotxid, swap->bobpayment.utxovout, (uint32_t) time(NULL) + 60, swap->I.otherhash);
-                    char str[65];
-                    printf("%d wait for bobdeposit %s numconfs.%d %s %s\n", n, swap->bobdeposit.I.destaddr, m, bobstr, bits256_str(str, swap->bobdeposit.I.signedtxid));
-                    sleep(10);
-                }
+            else if ( swap->I.aliceconfirms > 0 )
+                LP_swap_confirmedat(swap,lp->alicestr,swap->alicepayment.I.signedtxid);
+            LP_swap_critical = (uint32_t)time(NULL);
+            break;
+        case LP_MAKER_SENDPAYMENT: case LP_MAKER_MF_SENDPAYMENT:
+            LP_swapabort_makerpaid(swap);
+            if ( LP_swapdata_rawtxsend(swap->N.pair,swap,0x8000,lp->data,lp->maxlen,&swap->bobpayment,0x4000,0) == 0 )
+            {
+                err = -2008, printf("error sending bobpayment\n");
+                if ( state == LP_MAKER_MF_SENDPAYMENT )
+                    break;
+            }
+            else if ( state == LP_MAKER_MF_SENDPAYMENT )
+                LP_countdown_add(swap); // bobpayment is out
+            // after the deposit the reclaim is prepared even for a send that reported an error, it might still have gone out
+            LP_swaploop_reclaimprep(swap);
+            break;
+        case LP_MAKER_MF_PAYMENTCONFIRM:
+            if ( LP_swap_waitconfirms(swap,lp->bobstr,&swap->bobpayment,swap->I.bobconfirms,0,LP_swap_feebump,LP_confirm_progress) < 0 )
+                err = swap->watchdog != 0 ? -2017 : -2018;
+            break;
+        case LP_MAKER_DONE: case LP_MAKER_FAILED:
+            break;
+    }
+    return(err);
+}
 
-                printf("wait for alicepayment\n");
-                if (LP_waitfor(swap->N.pair, swap, bobwaittimeout + alicewaittimeout, LP_verify_alicepayment) < 0) {
-                    err = -2006, printf("error waiting for alicepayment\n");
-                }
+int32_t LP_taker_step(struct basilisk_swap *swap,enum LP_takerstate state,struct LP_swaploop *lp)
+{
+    int32_t n,err = 0; char str[65];
+    switch ( state )
+    {
+        case LP_TAKER_PUBKEYS:
+            if ( LP_sendwait("pubkeys",LP_pubkeys_timeout,swap->N.pair,swap,lp->data,lp->maxlen,LP_pubkeys_verify,LP_pubkeys_data) < 0 )
+                err = swap->identityerr != 0 ? -1009 : (swap->locktimeerr != 0 ? -1009 - swap->locktimeerr : (swap->feeerr != 0 ? -1012 : -1000)), printf("error LP_sendwait pubkeys\n");
+            break;
+        case LP_TAKER_DUSTCHECK:
+            if ( LP_swap_dustcheck(swap) < 0 )
+                err = -1013, printf("swap amount below dust\n");
+            break;
+        case LP_TAKER_CHOOSEI:
+            if ( LP_sendwait("choosei",LP_swapsteptimeout(swap),swap->N.pair,swap,lp->data,lp->maxlen,LP_choosei_verify,LP_choosei_data) < 0 )
+                err = -1001, printf("error LP_sendwait choosei\n");
+            break;
+        case LP_TAKER_MOSTPRIVS:
+            if ( LP_sendwait("mostprivs",LP_swapsteptimeout(swap),swap->N.pair,swap,lp->data,lp->maxlen,LP_mostprivs_verify,LP_mostprivs_data) < 0 )
+                err = -1002, printf("error LP_sendwait mostprivs\n");
+            break;
+        case LP_TAKER_COMMIT:
+            if ( LP_swapabort_commit(swap) < 0 ) // basilisk_alicetxs broadcasts alicefee
+                err = -1008, printf("swap aborted before alicefee\n");
+            break;
+        case LP_TAKER_ALICETXS:
+            if ( basilisk_alicetxs(swap->N.pair,swap,lp->data,lp->maxlen) != 0 )
+            {
+                err = -1003, printf("basilisk_alicetxs error\n");
+                break;
             }
-            if (err == 0)
+            LP_swaploop_timeouts(swap,lp);
+            break;
+        case LP_TAKER_SENDFEE:
+            if ( LP_swapdata_rawtxsend(swap->N.pair,swap,0x80,lp->data,lp->maxlen,&swap->myfee,0x40,0) == 0 )
+                err = -1004, printf("error sending alicefee\n");
+            break;
+        case LP_TAKER_WAITDEPOSIT: case LP_TAKER_MF_WAITBOBPAYMENT:
+            // the maker may wait for our dexfee to confirm first, it said so in its pubkeys message
+            if ( LP_waitfortx(swap,lp->bobstr,lp->bobwaittimeout + (swap->I.feeconfirm != 0 ? LP_swap_confirmwait(lp->alicestr,1) : 0),state == LP_TAKER_WAITDEPOSIT ? LP_verify_bobdeposit : LP_verify_bobpayment) < 0 )
             {
-                LP_swap_critical = (uint32_t)time(NULL);
-                if ( basilisk_bobscripts_set(swap,0,1) < 0 )
-                    err = -2007, printf("error bobscripts payment\n");
-                else
-                {
-                    m = swap->I.aliceconfirms;
-                    LP_unavailableset(swap->bobpayment.utxotxid,swap->bobpayment.utxovout,(uint32_t)time(NULL)+60,swap->I.otherhash);
-                    while ( (n= LP_numconfirms(alicestr,swap->alicepayment.I.destaddr,swap->alicepayment.I.signedtxid,0,1)) < m ) // sync with alice
-                    {
-                        LP_unavailableset(swap->bobpayment.utxotxid,swap->bobpayment.utxovout,(uint32_t)time(NULL)+60,swap->I.otherhash);
-                        LP_swap_critical = (uint32_t)time(NULL);
-                        char str[65];printf("%d wait for alicepayment %s numconfs.%d %s %s\n",n,swap->alicepayment.I.destaddr,m,alicestr,bits256_str(str,swap->alicepayment.I.signedtxid));
-                        sleep(10);
-                    }
-                    LP_swap_critical = (uint32_t)time(NULL);
-                    if ( LP_swapdata_rawtxsend(swap->N.pair,swap,0x8000,data,maxlen,&swap->bobpayment,0x4000,0) == 0 ) {
-                        err = -2008, printf("error sending bobpayment\n");
-                    }
-                    //if ( LP_waitfor(swap->N.pair,swap,10,LP_verify_alicespend) < 0 )
-                    //    printf("error waiting for alicespend\n");
-                    //swap->sentflag = 1;
-                    swap->bobreclaim.utxovout = 0;
-                    swap->bobreclaim.utxotxid = swap->bobpayment.I.signedtxid;
-                    basilisk_bobpayment_reclaim(swap,swap->I.callduration);
-                    if ( swap->N.pair >= 0 )
-                        nn_close(swap->N.pair), swap->N.pair = -1;
-                }
+                err = state == LP_TAKER_WAITDEPOSIT ? -1005 : -1007, printf("error waiting for %s\n",state == LP_TAKER_WAITDEPOSIT ? "bobdeposit" : "bobpayment");
+                break;
+            }
+            LP_unavailableset(swap->alicepayment.utxotxid,swap->alicepayment.utxovout,(uint32_t)time(NULL)+60,swap->I.otherhash);
+            break;
+        case LP_TAKER_DEPOSITCONFIRM: case LP_TAKER_MF_BOBCONFIRM:
+            if ( LP_swap_waitpayment(swap,lp->bobstr,state == LP_TAKER_DEPOSITCONFIRM ? &swap->bobdeposit : &swap->bobpayment,swap->I.bobconfirms,1,&swap->alicepayment,LP_swap_replaced,LP_confirm_progress) < 0 )
+                err = swap->watchdog != 0 ? -1015 : -1016;
+            break;
+        case LP_TAKER_SENDPAYMENT: case LP_TAKER_MF_SENDPAYMENT:
+            if ( LP_swapdata_rawtxsend(swap->N.pair,swap,0x1000,lp->data,lp->maxlen,&swap->alicepayment,0x800,0) == 0 )
+            {
+                err = -1006, printf("error sending alicepayment\n");
+                break;
             }
+            LP_countdown_add(swap); // alicepayment is out
+            break;
+        case LP_TAKER_PAYMENTCONFIRM: case LP_TAKER_MF_PAYMENTCONFIRM:
+            if ( LP_swap_waitconfirms(swap,lp->alicestr,&swap->alicepayment,swap->I.aliceconfirms,0,LP_swap_feebump,LP_confirm_progress) < 0 )
+                err = swap->watchdog != 0 ? -1015 : -1016;
+            if ( state == LP_TAKER_MF_PAYMENTCONFIRM )
+                LP_swap_endcritical = (uint32_t)time(NULL);
+            else LP_swap_critical = (uint32_t)time(NULL);
+            break;
+        case LP_TAKER_WAITBOBPAYMENT:
+            if ( LP_waitfortx(swap,lp->bobstr,lp->bobwaittimeout,LP_verify_bobpayment) < 0 )
+                err = -1007, printf("error waiting for bobpayment\n");
+            break;
+        case LP_TAKER_BOBCONFIRM:
+            LP_swap_endcritical = (uint32_t)time(NULL);
+            if ( (n= LP_swap_waitpayment(swap,lp->bobstr,&swap->bobpayment,swap->I.bobconfirms,1,0,0,LP_confirm_progress)) < 0 )
+                err = swap->watchdog != 0 ? -1015 : -1016;
+            printf("%d waited for bobpayment %s numconfs.%d %s %s\n",n,swap->bobpayment.I.destaddr,swap->I.bobconfirms,lp->bobstr,bits256_str(str,swap->bobpayment.I.signedtxid));
+            break;
+        case LP_TAKER_DONE: case LP_TAKER_FAILED:
+            break;
+    }
+    return(err);
+}
+
+void LP_bobloop(void *_swap)
+{
+    struct LP_swaploop lp; enum LP_makerstate state; int32_t err=0; struct basilisk_swap *swap = _swap;
+    LP_swaplogtag_set(swap);
+    G.LP_pendingswaps++;
+    //printf("start swap iambob\n");
+    memset(&lp,0,sizeof(lp));
+    LP_etomicsymbol(lp.bobstr,swap->I.bobtomic,swap->I.bobstr);
+    LP_etomicsymbol(lp.alicestr,swap->I.alicetomic,swap->I.alicestr);
+    lp.maxlen = 1024*1024 + sizeof(*swap);
+    lp.data = malloc(lp.maxlen);
+#ifndef NOTETOMIC
+    if (swap->I.bobtomic[0] != 0 || swap->I.alicetomic[0] != 0) {
+        int error = 0;
+        LP_etomic_select(swap->I.bobtomic[0] != 0 ? swap->I.bobstr : swap->I.alicestr);
+        uint64_t eth_balance = getEthBalance(swap->I.etomicsrc, &error);
+        if (eth_balance < 500000) {
+            err = -5000, printf("Bob ETH balance too low, aborting swap!\n");
         }
+    }
+#endif
+
+    if ( swap != 0 && err == 0)
+    {
+        LP_swapabort_register(swap);
+        LP_swaplog(swap,"start",swap->I.bobstr);
+        LP_metrics_swap(swap->I.bobstr,swap->I.alicestr,"started");
+        LP_myorder_match(swap,"started");
+        // makerfirst is only settled by the pubkeys exchange, so the flags are read again for every transition
+        for (state=LP_MAKER_PUBKEYS; state != LP_MAKER_DONE && state != LP_MAKER_FAILED; state=LP_maker_next(state,err,swap->I.makerfirst,swap->I.internal))
+            err = LP_maker_step(swap,state,&lp);
+        if ( state == LP_MAKER_DONE && swap->N.pair >= 0 )
+            nn_close(swap->N.pair), swap->N.pair = -1;
     } else printf("swap timed out\n");
+    if ( swap->macerr != 0 && err < 0 )
+        err = -2015;
+    if ( swap->watchdog != 0 && err < 0 )
+        err = -2017;
+    else if ( swap->aborted != 0 && err < 0 && err > -2009 )
+        err = -2009;
+    LP_swapabort_unregister(swap);
+    LP_zeroconf_release(swap);
+    LP_hd_swapaddress_done(LP_coinfind(swap->I.iambob != 0 ? swap->I.alicestr : swap->I.bobstr),swap->claimpath,err == 0);
     LP_swap_endcritical = (uint32_t)time(NULL);
+    // a timeout, an unauthenticated message or an abort is no proof the taker held back its fee, an invalid fee is
+    if ( err == -2004 && swap->I.internal == 0 && swap->timedout == 0 && swap->macerr == 0 && swap->aborted == 0 )
+        LP_pubkey_nofee(swap->I.otherhash);
+    if ( swap->I.internal == 0 && (err == 0 || err == -2006) )
+        LP_reputation_update(swap->I.otherhash,err == 0);
     if ( err < 0 )
-        LP_failedmsg(swap->I.req.requestid,swap->I.req.quoteid,err,swap->uuidstr);
+        LP_swapfailed(swap,err);
+    else
+    {
+        LP_swaplog(swap,"finished","success");
+        LP_swap_timings(swap,0);
+        LP_metrics_swap(swap->I.bobstr,swap->I.alicestr,"completed");
+        LP_tuning_swapdone(swap->I.bobstr,swap->I.alicestr,0,0);
+        LP_mmbot_swapdone(swap->I.bobstr,swap->I.alicestr);
+        LP_myorder_match(swap,"completed");
+    }
+    LP_makerswap_add(swap,-1);
     if ( swap->I.aliceconfirms > 0 )
         sleep(13);
     LP_pendswap_add(swap->I.expiration,swap->I.req.requestid,swap->I.req.quoteid);
     //swap->I.finished = LP_swapwait(swap->I.expiration,swap->I.req.requestid,swap->I.req.quoteid,LP_atomic_locktime(swap->I.bobstr,swap->I.alicestr)*3,swap->I.aliceconfirms == 0 ? 3 : 30);
     basilisk_swap_finished(swap);
+    LP_swaplogtag_set(0);
     free(swap);
-    free(data);
+    free(lp.data);
     G.LP_pendingswaps--;
 }
 
 void LP_aliceloop(void *_swap)
 {
-    uint8_t *data; char bobstr[65],alicestr[65]; int32_t bobwaittimeout,alicewaittimeout,maxlen,n,m,err=0; uint32_t expiration; struct basilisk_swap *swap = _swap;
+    struct LP_swaploop lp; enum LP_takerstate state; int32_t err=0; struct basilisk_swap *swap = _swap;
+    LP_swaplogtag_set(swap);
     LP_alicequery_clear();
     G.LP_pendingswaps++;
-    LP_etomicsymbol(bobstr,swap->I.bobtomic,swap->I.bobstr);
-    LP_etomicsymbol(alicestr,swap->I.alicetomic,swap->I.alicestr);
-    maxlen = 1024*1024 + sizeof(*swap);
-    data = malloc(maxlen);
-    expiration = (uint32_t)time(NULL) + LP_SWAPSTEP_TIMEOUT;
-    bobwaittimeout = LP_calc_waittimeout(bobstr);
-    alicewaittimeout = LP_calc_waittimeout(alicestr);
+    memset(&lp,0,sizeof(lp));
+    LP_etomicsymbol(lp.bobstr,swap->I.bobtomic,swap->I.bobstr);
+    LP_etomicsymbol(lp.alicestr,swap->I.alicetomic,swap->I.alicestr);
+    lp.maxlen = 1024*1024 + sizeof(*swap);
+    lp.data = malloc(lp.maxlen);
 
 #ifndef NOTETOMIC
     if (swap->I.bobtomic[0] != 0 || swap->I.alicetomic[0] != 0) {
         int error = 0;
+        LP_etomic_select(swap->I.alicetomic[0] != 0 ? swap->I.alicestr : swap->I.bobstr);
         uint64_t eth_balance = getEthBalance(swap->I.etomicdest, &error);
         if (eth_balance < 500000) {
             err = -5001, printf("Alice ETH balance too low, aborting swap!\n");
@@ -981,74 +3179,44 @@ void LP_aliceloop(void *_swap)
     if ( swap != 0 && err == 0)
     {
         printf("start swap iamalice pair.%d\n",swap->N.pair);
-        if ( LP_sendwait("pubkeys",120,swap->N.pair,swap,data,maxlen,LP_pubkeys_verify,LP_pubkeys_data) < 0 )
-            err = -1000, printf("error LP_sendwait pubkeys\n");
-        else if ( LP_sendwait("choosei",LP_SWAPSTEP_TIMEOUT,swap->N.pair,swap,data,maxlen,LP_choosei_verify,LP_choosei_data) < 0 )
-            err = -1001, printf("error LP_sendwait choosei\n");
-        else if ( LP_sendwait("mostprivs",LP_SWAPSTEP_TIMEOUT,swap->N.pair,swap,data,maxlen,LP_mostprivs_verify,LP_mostprivs_data) < 0 )
-            err = -1002, printf("error LP_sendwait mostprivs\n");
-        else if ( basilisk_alicetxs(swap->N.pair,swap,data,maxlen) != 0 )
-            err = -1003, printf("basilisk_alicetxs error\n");
-        else
-        {
-            //LP_swapsfp_update(&swap->I.req);
-            LP_swap_critical = (uint32_t)time(NULL);
-            if ( LP_swapdata_rawtxsend(swap->N.pair,swap,0x80,data,maxlen,&swap->myfee,0x40,0) == 0 )
-                err = -1004, printf("error sending alicefee\n");
-            else if ( LP_waitfor(swap->N.pair,swap,bobwaittimeout,LP_verify_bobdeposit) < 0 )
-                err = -1005, printf("error waiting for bobdeposit\n");
-            else
-            {
-                m = swap->I.bobconfirms;
-                LP_unavailableset(swap->alicepayment.utxotxid,swap->alicepayment.utxovout,(uint32_t)time(NULL)+60,swap->I.otherhash);
-                while ( (n= LP_numconfirms(bobstr,swap->bobdeposit.I.destaddr,swap->bobdeposit.I.signedtxid,0,1)) < m )
-                {
-                    LP_swap_critical = (uint32_t)time(NULL);
-                    LP_unavailableset(swap->alicepayment.utxotxid,swap->alicepayment.utxovout,(uint32_t)time(NULL)+60,swap->I.otherhash);
-                    char str[65];printf("%d wait for bobdeposit %s numconfs.%d %s %s\n",n,swap->bobdeposit.I.destaddr,m,bobstr,bits256_str(str,swap->bobdeposit.I.signedtxid));
-                    sleep(10);
-                }
-                if ( LP_swapdata_rawtxsend(swap->N.pair,swap,0x1000,data,maxlen,&swap->alicepayment,0x800,0) == 0 )
-                    err = -1006, printf("error sending alicepayment\n");
-                else
-                {
-                    m = swap->I.aliceconfirms;
-                    while ( (n= LP_numconfirms(alicestr,swap->alicepayment.I.destaddr,swap->alicepayment.I.signedtxid,0,1)) < m )
-                    {
-                        LP_swap_critical = (uint32_t)time(NULL);
-                        char str[65];printf("%d wait for alicepayment %s numconfs.%d %s %s\n",n,swap->alicepayment.I.destaddr,m,alicestr,bits256_str(str,swap->alicepayment.I.signedtxid));
-                        sleep(10);
-                    }
-                    //swap->sentflag = 1;
-                    LP_swap_critical = (uint32_t)time(NULL);
-                    if ( LP_waitfor(swap->N.pair,swap,bobwaittimeout,LP_verify_bobpayment) < 0 )
-                        err = -1007, printf("error waiting for bobpayment\n");
-                    else
-                    {
-                        LP_swap_endcritical = (uint32_t)time(NULL);
-                        while ( (n= LP_numconfirms(bobstr,swap->bobpayment.I.destaddr,swap->bobpayment.I.signedtxid,0,1)) < swap->I.bobconfirms )
-                        {
-                            char str[65];printf("%d wait for bobpayment %s numconfs.%d %s %s\n",n,swap->bobpayment.I.destaddr,swap->I.bobconfirms,bobstr,bits256_str(str,swap->bobpayment.I.signedtxid));
-                            sleep(10);
-                        }
-                        char str[65];printf("%d waited for bobpayment %s numconfs.%d %s %s\n",n,swap->bobpayment.I.destaddr,swap->I.bobconfirms,bobstr,bits256_str(str,swap->bobpayment.I.signedtxid));
-                        if ( swap->N.pair >= 0 )
-                            nn_close(swap->N.pair), swap->N.pair = -1;
-                    }
-                }
-            }
-        }
+        LP_swapabort_register(swap);
+        LP_swaplog(swap,"start",swap->I.alicestr);
+        LP_metrics_swap(swap->I.bobstr,swap->I.alicestr,"started");
+        LP_multihop_match(swap,"started");
+        for (state=LP_TAKER_PUBKEYS; state != LP_TAKER_DONE && state != LP_TAKER_FAILED; state=LP_taker_next(state,err,swap->I.makerfirst,swap->I.internal))
+            err = LP_taker_step(swap,state,&lp);
+        if ( state == LP_TAKER_DONE && swap->N.pair >= 0 )
+            nn_close(swap->N.pair), swap->N.pair = -1;
     }
+    if ( swap->macerr != 0 && err < 0 )
+        err = -1014;
+    if ( swap->watchdog != 0 && err < 0 )
+        err = -1015;
+    else if ( swap->aborted != 0 && err < 0 && err > -1008 )
+        err = -1008;
+    LP_swapabort_unregister(swap);
+    LP_zeroconf_release(swap);
+    LP_hd_swapaddress_done(LP_coinfind(swap->I.iambob != 0 ? swap->I.alicestr : swap->I.bobstr),swap->claimpath,err == 0);
     LP_swap_endcritical = (uint32_t)time(NULL);
     if ( err < 0 )
-        LP_failedmsg(swap->I.req.requestid,swap->I.req.quoteid,err,swap->uuidstr);
+        LP_swapfailed(swap,err);
+    else
+    {
+        LP_swaplog(swap,"finished","success");
+        LP_swap_timings(swap,0);
+        LP_metrics_swap(swap->I.bobstr,swap->I.alicestr,"completed");
+        LP_tuning_swapdone(swap->I.bobstr,swap->I.alicestr,0,0);
+        LP_mmbot_swapdone(swap->I.bobstr,swap->I.alicestr);
+        LP_multihop_match(swap,"completed");
+    }
     if ( swap->I.bobconfirms > 0 )
         sleep(13);
     LP_pendswap_add(swap->I.expiration,swap->I.req.requestid,swap->I.req.quoteid);
     //swap->I.finished = LP_swapwait(swap->I.expiration,swap->I.req.requestid,swap->I.req.quoteid,LP_atomic_locktime(swap->I.bobstr,swap->I.alicestr)*3,swap->I.aliceconfirms == 0 ? 3 : 30);
     basilisk_swap_finished(swap);
+    LP_swaplogtag_set(0);
     free(swap);
-    free(data);
+    free(lp.data);
     G.LP_pendingswaps--;
 }
 
@@ -1137,11 +3305,13 @@ void basilisk_rawtx_setparms(char *name,uint32_t quoteid,struct basilisk_rawtx *
     rawtx->I.vouttype = vouttype; // 0 -> fee, 1 -> std, 2 -> 2of2, 3 -> bobpayment, 4 -> bobdeposit
     if ( rawtx->I.vouttype == 0 )
     {
-        if ( strcmp(coin->symbol,"BTC") == 0 && (quoteid % 10) == 0 )
+        if ( strcmp(coin->symbol,"BTC") == 0 && (quoteid % 10) == 0 && strcmp(LP_dexfee_rmd160,INSTANTDEX_RMD160) == 0 )
             decode_hex(rawtx->I.rmd160,20,TIERNOLAN_RMD160);
-        else decode_hex(rawtx->I.rmd160,20,INSTANTDEX_RMD160);
+        else decode_hex(rawtx->I.rmd160,20,LP_dexfee_rmd160);
         bitcoin_address(coin->symbol,rawtx->I.destaddr,coin->taddr,coin->pubtype,rawtx->I.rmd160,20);
     }
+    if ( pubkey33 != 0 && coin->signerurl[0] != 0 )
+        pubkey33 = coin->signerpub33;
     if ( pubkey33 != 0 )
     {
         memcpy(rawtx->I.pubkey33,pubkey33,33);
@@ -1195,7 +3365,31 @@ struct basilisk_swap *bitcoin_swapinit(bits256 privkey,uint8_t *pubkey33,bits256
         free(swap);
         return(0);
     }
-    swap->I.putduration = swap->I.callduration = LP_atomic_locktime(bobstr,alicestr);
+    swap->myclaimfees[0] = LP_claimfee(alicecoin,swap->I.Atxfee);
+    swap->myclaimfees[1] = LP_claimfee(bobcoin,swap->I.Btxfee);
+    swap->myclaimmargins[0] = LP_claimmargin(alicecoin,swap->myclaimfees[0]);
+    swap->myclaimmargins[1] = LP_claimmargin(bobcoin,swap->myclaimfees[1]);
+    swap->I.uniformspends = bobcoin->uniformspends;
+    swap->I.csvrefunds = (bobcoin->csvrefunds != 0 && bobcoin->etomic[0] == 0);
+    swap->I.burnfee = (alicecoin->burnfee != 0 && alicecoin->etomic[0] == 0);
+#ifndef NOTETOMIC
+    if ( swap->I.alicetomic[0] != 0 )
+        safecopy(swap->I.alicecontract,LP_etomic_contract(swap->I.alicestr,0),sizeof(swap->I.alicecontract));
+    if ( swap->I.bobtomic[0] != 0 )
+        safecopy(swap->I.bobcontract,LP_etomic_contract(swap->I.bobstr,1),sizeof(swap->I.bobcontract));
+#endif
+    // maker pays first when the maker offers it on the pair and the taker asked for it, the etomic contracts keep the usual order
+    if ( (swap->I.makerfirst= ((qp->caps & qp->reqcaps & LP_CAP_MAKERFIRST) != 0 && bobcoin->etomic[0] == 0 && alicecoin->etomic[0] == 0)) != 0 )
+        swap->I.csvrefunds = 0;
+    if ( (swap->I.putduration= qp->locktime) == 0 )
+        swap->I.putduration = LP_atomic_locktime(bobstr,alicestr);
+    if ( swap->I.putduration < LP_MIN_LOCKTIME )
+    {
+        printf("bitcoin_swapinit locktime %u below minimum %d rejected\n",swap->I.putduration,LP_MIN_LOCKTIME);
+        free(swap);
+        return(0);
+    }
+    swap->I.callduration = swap->I.putduration;
     if ( optionduration < 0 )
         swap->I.putduration -= optionduration;
     else if ( optionduration > 0 )
@@ -1212,6 +3406,9 @@ struct basilisk_swap *bitcoin_swapinit(bits256 privkey,uint8_t *pubkey33,bits256
         free(swap);
         return(0);
     }
+    // the quote amounts net of txfee, with whatever decimals past satoshis the coins have
+    LP_amount_align(swap->I.bobamount,qp->srcamountstr,swap->I.bobsatoshis);
+    LP_amount_align(swap->I.aliceamount,qp->destamountstr,swap->I.alicesatoshis);
 #ifndef NOTETOMIC
     if (strcmp(alicestr, "ETOMIC") == 0) {
         swap->I.alicerealsat = swap->I.alicesatoshis;
@@ -1227,6 +3424,7 @@ struct basilisk_swap *bitcoin_swapinit(bits256 privkey,uint8_t *pubkey33,bits256
     if ( (swap->I.aliceinsurance= (swap->I.alicesatoshis / INSTANTDEX_INSURANCEDIV)) < LP_MIN_TXFEE )
         swap->I.aliceinsurance = LP_MIN_TXFEE;
     swap->I.started = qp->timestamp;//(uint32_t)time(NULL);
+    swap->startmillis = LP_monotonic_millis();
     swap->I.expiration = swap->I.req.timestamp + swap->I.putduration + swap->I.callduration;
     OS_randombytes((uint8_t *)&swap->I.choosei,sizeof(swap->I.choosei));
     if ( swap->I.choosei < 0 )
@@ -1247,6 +3445,7 @@ struct basilisk_swap *bitcoin_swapinit(bits256 privkey,uint8_t *pubkey33,bits256
     {
         swap->I.iambob = 1;
         swap->I.otherhash = swap->I.req.srchash;
+        swap->I.feeconfirm = LP_reputation_feeconfirm(swap->I.otherhash);
         swap->I.bobistrusted = 1;
         if ( dynamictrust == 0 && LP_pubkey_istrusted(swap->I.req.desthash) != 0 )
             dynamictrust = 1;
@@ -1277,6 +3476,8 @@ struct basilisk_swap *bitcoin_swapinit(bits256 privkey,uint8_t *pubkey33,bits256
         swap->I.bobconfirms = bobcoin->userconfirms;
     if ( alicecoin->userconfirms > 0 )
         swap->I.aliceconfirms = alicecoin->userconfirms;
+    swap->I.bobconfirms += LP_tuning_confmargin(bobstr);
+    swap->I.aliceconfirms += LP_tuning_confmargin(alicestr);
     if ( (swap->I.bobmaxconfirms= bobcoin->maxconfirms) == 0 )
         swap->I.bobmaxconfirms = BASILISK_DEFAULT_MAXCONFIRMS;
     if ( (swap->I.alicemaxconfirms= alicecoin->maxconfirms) == 0 )
@@ -1304,6 +3505,8 @@ struct basilisk_swap *bitcoin_swapinit(bits256 privkey,uint8_t *pubkey33,bits256
         swap->I.bobconfirms *= !swap->I.bobistrusted;
         swap->I.aliceconfirms *= !swap->I.aliceistrusted;
     }
+    LP_zeroconf_reserve(swap,bobcoin,1);
+    LP_zeroconf_reserve(swap,alicecoin,0);
     printf(">>>>>>>>>> jumblrflag.%d <<<<<<<<< r.%u q.%u, %.8f bobconfs.%d, %.8f aliceconfs.%d taddr.%d %d\n",jumblrflag,swap->I.req.requestid,swap->I.req.quoteid,dstr(swap->I.bobsatoshis),swap->I.bobconfirms,dstr(swap->I.alicesatoshis),swap->I.aliceconfirms,bobcoin->taddr,alicecoin->taddr);
     if ( swap->I.etomicsrc[0] != 0 || swap->I.etomicdest[0] != 0 )
         printf("etomic src (%s %s) dest (%s %s)\n",swap->I.bobtomic,swap->I.etomicsrc,swap->I.alicetomic,swap->I.etomicdest);
@@ -1321,21 +3524,24 @@ struct basilisk_swap *bitcoin_swapinit(bits256 privkey,uint8_t *pubkey33,bits256
     }
     swap->myfee.I.locktime = swap->I.started + 1;
     swap->otherfee.I.locktime = swap->I.started + 1;
+    if ( LP_hd_swapaddress(swap->I.iambob != 0 ? alicecoin : bobcoin,swap->claimpub33,swap->claimpath) == 0 )
+        printf("claim to fresh address %s\n",swap->claimpath);
+    else memcpy(swap->claimpub33,pubkey33,33);
     basilisk_rawtx_setparms("bobdeposit",swap->I.req.quoteid,&swap->bobdeposit,bobcoin,swap->I.bobconfirms,0,LP_DEPOSITSATOSHIS(swap->I.bobsatoshis) + 2*bobcoin->txfee,4,0,jumblrflag);
     basilisk_rawtx_setparms("bobrefund",swap->I.req.quoteid,&swap->bobrefund,bobcoin,1,4,LP_DEPOSITSATOSHIS(swap->I.bobsatoshis),1,bobpub33,jumblrflag);
     swap->bobrefund.I.suppress_pubkeys = 1;
-    basilisk_rawtx_setparms("aliceclaim",swap->I.req.quoteid,&swap->aliceclaim,bobcoin,1,4,LP_DEPOSITSATOSHIS(swap->I.bobsatoshis),1,alicepub33,jumblrflag);
+    basilisk_rawtx_setparms("aliceclaim",swap->I.req.quoteid,&swap->aliceclaim,bobcoin,1,4,LP_DEPOSITSATOSHIS(swap->I.bobsatoshis),1,alicepub33 != 0 ? swap->claimpub33 : 0,jumblrflag);
     swap->aliceclaim.I.suppress_pubkeys = 1;
     swap->aliceclaim.I.locktime = swap->I.started + swap->I.putduration+swap->I.callduration + 1;
     
     basilisk_rawtx_setparms("bobpayment",swap->I.req.quoteid,&swap->bobpayment,bobcoin,swap->I.bobconfirms,0,swap->I.bobsatoshis + 2*bobcoin->txfee,3,0,jumblrflag);
-    basilisk_rawtx_setparms("alicespend",swap->I.req.quoteid,&swap->alicespend,bobcoin,swap->I.bobconfirms,3,swap->I.bobsatoshis,1,alicepub33,jumblrflag);
+    basilisk_rawtx_setparms("alicespend",swap->I.req.quoteid,&swap->alicespend,bobcoin,swap->I.bobconfirms,3,swap->I.bobsatoshis,1,alicepub33 != 0 ? swap->claimpub33 : 0,jumblrflag);
     swap->alicespend.I.suppress_pubkeys = 1;
     basilisk_rawtx_setparms("bobreclaim",swap->I.req.quoteid,&swap->bobreclaim,bobcoin,swap->I.bobconfirms,3,swap->I.bobsatoshis,1,bobpub33,jumblrflag);
     swap->bobreclaim.I.suppress_pubkeys = 1;
     swap->bobreclaim.I.locktime = swap->I.started + swap->I.putduration + 1;
     basilisk_rawtx_setparms("alicepayment",swap->I.req.quoteid,&swap->alicepayment,alicecoin,swap->I.aliceconfirms,0,swap->I.alicesatoshis + 2*alicecoin->txfee,2,0,jumblrflag);
-    basilisk_rawtx_setparms("bobspend",swap->I.req.quoteid,&swap->bobspend,alicecoin,swap->I.aliceconfirms,2,swap->I.alicesatoshis,1,bobpub33,jumblrflag);
+    basilisk_rawtx_setparms("bobspend",swap->I.req.quoteid,&swap->bobspend,alicecoin,swap->I.aliceconfirms,2,swap->I.alicesatoshis,1,bobpub33 != 0 ? swap->claimpub33 : 0,jumblrflag);
     swap->bobspend.I.suppress_pubkeys = 1;
     basilisk_rawtx_setparms("alicereclaim",swap->I.req.quoteid,&swap->alicereclaim,alicecoin,swap->I.aliceconfirms,2,swap->I.alicesatoshis,1,alicepub33,jumblrflag);
     swap->alicereclaim.I.suppress_pubkeys = 1;
@@ -1378,6 +3584,8 @@ struct basilisk_swap *LP_swapinit(int32_t iambob,int32_t optionduration,bits256
         ctx = bitcoin_ctx();
     swap = calloc(1,sizeof(*swap));
     memcpy(swap->uuidstr,qp->uuidstr,sizeof(swap->uuidstr));
+    if ( (swap->I.internal= qp->internal) != 0 )
+        dynamictrust = 1; // own instances, mutual trust drops the required confirms
     swap->aliceid = LP_aliceid_calc(qp->desttxid,qp->destvout,qp->feetxid,qp->feevout);
     swap->I.req.quoteid = rp->quoteid;
     swap->ctx = ctx;
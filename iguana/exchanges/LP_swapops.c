@@ -0,0 +1,170 @@
+
+/******************************************************************************
+ * Copyright © 2014-2018 The SuperNET Developers.                             *
+ *                                                                            *
+ * See the AUTHORS, DEVELOPER-AGREEMENT and LICENSE files at                  *
+ * the top-level directory of this distribution for the individual copyright  *
+ * holder information and the developer policies on copyright and licensing.  *
+ *                                                                            *
+ * Unless otherwise agreed in a custom licensing agreement, no part of the    *
+ * SuperNET software, including this file may be copied, modified, propagated *
+ * or distributed except according to the terms contained in the LICENSE file *
+ *                                                                            *
+ * Removal or modification of this copyright notice is prohibited.            *
+ *                                                                            *
+ ******************************************************************************/
+//
+//  LP_swapops.c
+//  marketmaker
+//
+//  script and scriptSig builders for the swap payments. everything here works only on its args struct: no coin lookups,
+//  no swap state, no network, so the same inputs always give the same bytes and a script can be checked in isolation.
+//  the basilisk_ wrappers in LP_transaction.c fill the args from the swap and call in
+//
+
+struct LP_makerpayment_args // bobdeposit (depositflag) or bobpayment
+{
+    bits256 refundpub,claimpub; // refund path after locktime, claim path with the secret
+    uint8_t refundtype,claimtype,depositsecret160[20],claimsecret160[20];
+    uint32_t locktime,csvsequence; int32_t depositflag,uniform; // nonzero csvsequence replaces the absolute locktime
+};
+
+struct LP_takerpayment_args { bits256 pubAm,pubBn; }; // alicepayment is a 2of2 of alice's and bob's secret keys
+
+struct LP_spend_args { bits256 privkey; }; // the else path, reveals privkey
+
+struct LP_refund_args { bits256 privkey,filler; int32_t uniform; }; // the if path, privkey only for the bobdeposit after locktime
+
+// BIP68 time based relative lock, rounded up to the next 512 second unit
+uint32_t LP_swapops_csvsequence(uint32_t duration)
+{
+    uint32_t units = (duration >> 9) + ((duration & 511) != 0); // duration + 511 would wrap for the largest durations
+    if ( units > 0xffff )
+        units = 0xffff;
+    else if ( units == 0 )
+        units = 1;
+    return(LP_CSV_TYPEFLAG | units);
+}
+
+void LP_swapops_makerargs(struct LP_makerpayment_args *args,int32_t depositflag,int32_t uniformflag,uint32_t locktime,uint32_t csvduration,bits256 pubA0,bits256 pubB0,bits256 pubB1,bits256 privAm,bits256 privBn,uint8_t *secretAm,uint8_t *secretBn)
+{
+    bits256 privkey;
+    memset(args,0,sizeof(*args));
+    args->depositflag = depositflag;
+    args->uniform = (depositflag == 0 && uniformflag != 0);
+    args->locktime = locktime;
+    if ( csvduration != 0 )
+        args->csvsequence = LP_swapops_csvsequence(csvduration);
+    if ( depositflag != 0 )
+    {
+        args->refundtype = 0x02, args->refundpub = pubA0;
+        args->claimtype = 0x03, args->claimpub = pubB0;
+        memcpy(args->depositsecret160,secretAm,20);
+        memcpy(args->claimsecret160,secretBn,20);
+        privkey = privBn;
+    }
+    else
+    {
+        args->refundtype = 0x03, args->refundpub = pubB1;
+        args->claimtype = 0x02, args->claimpub = pubA0;
+        memcpy(args->claimsecret160,secretAm,20);
+        privkey = privAm;
+    }
+    if ( bits256_nonz(privkey) != 0 ) // the secret itself is known, hash it rather than trust the stored hash
+        calc_rmd160_sha256(args->claimsecret160,privkey.bytes,sizeof(privkey));
+}
+
+// maker pays first: both payments are claimed with bob's secret. bobpayment (takerflag 0) refunds to pubB1 and is claimed
+// by pubA0, alicepayment (takerflag 1) refunds to pubA0 and is claimed by pubB0. the secret is revealed byte reversed like
+// privAm in alicespend, so it hashes to the secretBn that alice already has
+void LP_swapops_makerfirstargs(struct LP_makerpayment_args *args,int32_t takerflag,uint32_t locktime,bits256 pubA0,bits256 pubB0,bits256 pubB1,bits256 privBn,uint8_t *secretBn)
+{
+    memset(args,0,sizeof(*args));
+    args->locktime = locktime;
+    if ( takerflag != 0 )
+    {
+        args->refundtype = 0x02, args->refundpub = pubA0;
+        args->claimtype = 0x03, args->claimpub = pubB0;
+    }
+    else
+    {
+        args->refundtype = 0x03, args->refundpub = pubB1;
+        args->claimtype = 0x02, args->claimpub = pubA0;
+    }
+    if ( bits256_nonz(privBn) != 0 )
+        revcalc_rmd160_sha256(args->claimsecret160,privBn);
+    else memcpy(args->claimsecret160,secretBn,20);
+}
+
+int32_t LP_swapops_makerscript(uint8_t *redeemscript,int32_t *secretstartp,struct LP_makerpayment_args *args)
+{
+    int32_t i,n = 0; uint8_t refund33[33],claim33[33];
+    if ( bits256_nonz(args->refundpub) == 0 || bits256_nonz(args->claimpub) == 0 )
+        return(-1);
+    for (i=0; i<20; i++)
+        if ( args->claimsecret160[i] != 0 )
+            break;
+    if ( i == 20 )
+        return(-1);
+    refund33[0] = args->refundtype, memcpy(refund33+1,args->refundpub.bytes,32);
+    claim33[0] = args->claimtype, memcpy(claim33+1,args->claimpub.bytes,32);
+    redeemscript[n++] = SCRIPT_OP_IF;
+    if ( args->csvsequence != 0 )
+        n = bitcoin_checksequenceverify(redeemscript,n,args->csvsequence);
+    else n = bitcoin_checklocktimeverify(redeemscript,n,args->locktime);
+    if ( args->depositflag != 0 )
+        n = bitcoin_secret160verify(redeemscript,n,args->depositsecret160);
+    else if ( args->uniform != 0 )
+        redeemscript[n++] = SCRIPT_OP_DROP; // reclaim pushes a 32 byte filler so it is the same size as the secret revealing spend
+    n = bitcoin_pubkeyspend(redeemscript,n,refund33);
+    redeemscript[n++] = SCRIPT_OP_ELSE;
+    if ( secretstartp != 0 )
+        *secretstartp = n + 2;
+    n = bitcoin_secret160verify(redeemscript,n,args->claimsecret160);
+    n = bitcoin_pubkeyspend(redeemscript,n,claim33);
+    redeemscript[n++] = SCRIPT_OP_ENDIF;
+    return(n);
+}
+
+int32_t LP_swapops_takerscript(uint8_t *redeemscript,int32_t *redeemlenp,uint8_t p2sh160[20],struct LP_takerpayment_args *args)
+{
+    struct vin_info V;
+    memset(&V,0,sizeof(V));
+    memcpy(&V.signers[0].pubkey[1],args->pubAm.bytes,sizeof(args->pubAm)), V.signers[0].pubkey[0] = 0x02;
+    memcpy(&V.signers[1].pubkey[1],args->pubBn.bytes,sizeof(args->pubBn)), V.signers[1].pubkey[0] = 0x03;
+    V.M = V.N = 2;
+    *redeemlenp = bitcoin_MofNspendscript(p2sh160,redeemscript,0,&V);
+    return(*redeemlenp);
+}
+
+int32_t LP_swapops_spenddata(uint8_t *userdata,struct LP_spend_args *args)
+{
+    int32_t i,len = 0;
+    if ( bits256_nonz(args->privkey) != 0 )
+    {
+        userdata[len++] = sizeof(args->privkey);
+        for (i=0; i<sizeof(args->privkey); i++)
+            userdata[len++] = args->privkey.bytes[i];
+    }
+    userdata[len++] = 0;
+    return(len);
+}
+
+int32_t LP_swapops_refunddata(uint8_t *userdata,struct LP_refund_args *args)
+{
+    int32_t i,len = 0;
+    if ( bits256_nonz(args->privkey) != 0 )
+    {
+        userdata[len++] = sizeof(args->privkey);
+        for (i=0; i<sizeof(args->privkey); i++)
+            userdata[len++] = args->privkey.bytes[i];
+    }
+    else if ( args->uniform != 0 )
+    {
+        userdata[len++] = sizeof(args->filler);
+        for (i=0; i<sizeof(args->filler); i++)
+            userdata[len++] = args->filler.bytes[i];
+    }
+    userdata[len++] = 0x51;
+    return(len);
+}
@@ -0,0 +1,402 @@
+
+/******************************************************************************
+ * Copyright © 2014-2018 The SuperNET Developers.                             *
+ *                                                                            *
+ * See the AUTHORS, DEVELOPER-AGREEMENT and LICENSE files at                  *
+ * the top-level directory of this distribution for the individual copyright  *
+ * holder information and the developer policies on copyright and licensing.  *
+ *                                                                            *
+ * Unless otherwise agreed in a custom licensing agreement, no part of the    *
+ * SuperNET software, including this file may be copied, modified, propagated *
+ * or distributed except according to the terms contained in the LICENSE file *
+ *                                                                            *
+ * Removal or modification of this copyright notice is prohibited.            *
+ *                                                                            *
+ ******************************************************************************/
+//
+//  LP_tokens.c
+//  marketmaker
+//
+//  runtime registry of contract based (ERC20) assets, persisted so they come back after a restart
+
+cJSON *LP_tokens,*LP_evmchains;
+
+int32_t LP_token_addrvalid(char *addr)
+{
+    if ( addr == 0 || strlen(addr) != 42 || addr[0] != '0' || (addr[1] != 'x' && addr[1] != 'X') || is_hexstr(addr+2,0) != 40 )
+        return(0);
+#ifndef NOTETOMIC
+    if ( isValidAddress(addr) == 0 )
+        return(0);
+#endif
+    return(1);
+}
+
+void LP_tokens_save()
+{
+    char fname[512],*str; FILE *fp;
+    sprintf(fname,"%s/tokens.json",GLOBAL_DBDIR), OS_compatible_path(fname);
+    if ( LP_tokens != 0 && (fp= fopen(fname,"wb")) != 0 )
+    {
+        str = jprint(LP_tokens,0);
+        fwrite(str,1,strlen(str),fp);
+        fclose(fp);
+        free(str);
+    }
+}
+
+void LP_evmchains_save()
+{
+    char fname[512],*str; FILE *fp;
+    sprintf(fname,"%s/evmchains.json",GLOBAL_DBDIR), OS_compatible_path(fname);
+    if ( LP_evmchains != 0 && (fp= fopen(fname,"wb")) != 0 )
+    {
+        str = jprint(LP_evmchains,0);
+        fwrite(str,1,strlen(str),fp);
+        fclose(fp);
+        free(str);
+    }
+}
+
+void LP_evmchains_load()
+{
+    char fname[512],*fstr; long fsize; cJSON *array;
+    sprintf(fname,"%s/evmchains.json",GLOBAL_DBDIR), OS_compatible_path(fname);
+    if ( (fstr= OS_filestr(&fsize,fname)) != 0 )
+    {
+        if ( (array= cJSON_Parse(fstr)) != 0 )
+        {
+            if ( is_cJSON_Array(array) != 0 )
+            {
+                portable_mutex_lock(&LP_tokenmutex);
+                if ( LP_evmchains != 0 )
+                    free_json(LP_evmchains);
+                LP_evmchains = array;
+                portable_mutex_unlock(&LP_tokenmutex);
+                printf("loaded %d evm chains from %s\n",cJSON_GetArraySize(array),fname);
+            } else free_json(array);
+        }
+        free(fstr);
+    }
+}
+
+int32_t LP_evmchain_contractvalid(char *addr)
+{
+    cJSON *item; char *contract; int32_t i,n,retval = 0;
+    portable_mutex_lock(&LP_tokenmutex);
+    if ( LP_evmchains != 0 && (n= cJSON_GetArraySize(LP_evmchains)) > 0 )
+    {
+        for (i=0; i<n && retval == 0; i++)
+        {
+            item = jitem(LP_evmchains,i);
+            if ( ((contract= jstr(item,"alicecontract")) != 0 && strcasecmp(contract,addr) == 0) || ((contract= jstr(item,"bobcontract")) != 0 && strcasecmp(contract,addr) == 0) )
+                retval = 1;
+        }
+    }
+    portable_mutex_unlock(&LP_tokenmutex);
+    return(retval);
+}
+
+void LP_evmchain_register(uint64_t chainid,char *url,char *type,char *contract)
+{
+    cJSON *item = 0; int32_t i,n; char field[64];
+    portable_mutex_lock(&LP_tokenmutex);
+    if ( LP_evmchains == 0 )
+        LP_evmchains = cJSON_CreateArray();
+    n = cJSON_GetArraySize(LP_evmchains);
+    for (i=0; i<n; i++)
+    {
+        item = jitem(LP_evmchains,i);
+        if ( j64bits(item,"chainid") == chainid )
+            break;
+    }
+    if ( i == n )
+    {
+        item = cJSON_CreateObject();
+        jadd64bits(item,"chainid",chainid);
+        jaddi(LP_evmchains,item);
+    }
+    jdelete(item,"url");
+    jaddstr(item,"url",url);
+    sprintf(field,"%scontract",type);
+    jdelete(item,field);
+    jaddstr(item,field,contract);
+    LP_evmchains_save();
+    portable_mutex_unlock(&LP_tokenmutex);
+}
+
+// a deploy waits for the contract creation tx for up to 10 minutes, so it runs in its own thread. the rpc returns a jobid
+// that deploy_swap_contract_status polls
+struct LP_deployjob
+{
+    struct LP_deployjob *next,*prev;
+    uint32_t jobid,started,finished;
+    uint64_t chainid; int64_t gas; bits256 privkey;
+    char *url,*type,*bytecode,*canonical;
+    cJSON *result;
+} *LP_deployjobs;
+uint32_t LP_deployjobid;
+
+#ifndef NOTETOMIC
+void LP_deployjob_loop(void *arg)
+{
+    struct LP_deployjob *job = arg; cJSON *retjson; char *contract;
+    retjson = LP_etomic_deploy(job->url,job->chainid,job->canonical,job->bytecode,job->gas,job->privkey);
+    memset(job->privkey.bytes,0,sizeof(job->privkey));
+    if ( jstr(retjson,"error") == 0 && (contract= jstr(retjson,"contract")) != 0 )
+        LP_evmchain_register(job->chainid,job->url,job->type,contract);
+    jadd64bits(retjson,"chainid",job->chainid);
+    jaddstr(retjson,"type",job->type);
+    portable_mutex_lock(&LP_tokenmutex);
+    job->result = retjson;
+    job->finished = (uint32_t)time(NULL);
+    portable_mutex_unlock(&LP_tokenmutex);
+}
+#endif
+
+char *LP_deploy_swap_contract_status(uint32_t jobid)
+{
+    struct LP_deployjob *job; cJSON *retjson = 0;
+    portable_mutex_lock(&LP_tokenmutex);
+    DL_FOREACH(LP_deployjobs,job)
+    {
+        if ( job->jobid != jobid )
+            continue;
+        retjson = cJSON_CreateObject();
+        jaddnum(retjson,"jobid",job->jobid);
+        jaddnum(retjson,"started",job->started);
+        if ( job->finished == 0 )
+            jaddstr(retjson,"status","pending");
+        else
+        {
+            jaddstr(retjson,"status","finished");
+            jaddnum(retjson,"finished",job->finished);
+            jadd(retjson,"deploy",cJSON_Duplicate(job->result,1));
+        }
+        break;
+    }
+    portable_mutex_unlock(&LP_tokenmutex);
+    if ( retjson == 0 )
+        return(clonestr("{\"error\":\"unknown jobid\"}"));
+    return(jprint(retjson,1));
+}
+
+char *LP_deploy_swap_contract(cJSON *argjson)
+{
+#ifndef NOTETOMIC
+    struct iguana_info *ethcoin; struct LP_deployjob *job; char *url,*type,*bytecode,*canonical; uint64_t chainid; int64_t gas; cJSON *retjson;
+    url = jstr(argjson,"url");
+    type = jstr(argjson,"type");
+    bytecode = jstr(argjson,"bytecode");
+    chainid = j64bits(argjson,"chainid");
+    if ( (gas= j64bits(argjson,"gas")) == 0 )
+        gas = 3000000;
+    if ( url == 0 || url[0] == 0 || chainid == 0 )
+        return(clonestr("{\"error\":\"need url and chainid\"}"));
+    else if ( type == 0 || (strcmp(type,"alice") != 0 && strcmp(type,"bob") != 0) )
+        return(clonestr("{\"error\":\"type must be alice or bob\"}"));
+    else if ( bytecode == 0 || strlen(bytecode) < 4 || bytecode[0] != '0' || bytecode[1] != 'x' || is_hexstr(bytecode+2,0) != strlen(bytecode+2) )
+        return(clonestr("{\"error\":\"bytecode must be 0x prefixed hex\"}"));
+    else if ( (ethcoin= LP_coinfind("ETH")) == 0 || ethcoin->smartaddr[0] == 0 )
+        return(clonestr("{\"error\":\"ETH must be configured to deploy swap contracts\"}"));
+    canonical = strcmp(type,"alice") == 0 ? ETOMIC_ALICECONTRACT : ETOMIC_BOBCONTRACT;
+    job = calloc(1,sizeof(*job));
+    job->url = clonestr(url);
+    job->type = clonestr(type);
+    job->bytecode = clonestr(bytecode);
+    job->canonical = canonical;
+    job->chainid = chainid;
+    job->gas = gas;
+    job->privkey = LP_privkey(ethcoin->symbol,ethcoin->smartaddr,ethcoin->taddr);
+    job->started = (uint32_t)time(NULL);
+    portable_mutex_lock(&LP_tokenmutex);
+    job->jobid = ++LP_deployjobid;
+    DL_APPEND(LP_deployjobs,job);
+    portable_mutex_unlock(&LP_tokenmutex);
+    if ( OS_thread_create(malloc(sizeof(pthread_t)),NULL,(void *)LP_deployjob_loop,(void *)job) != 0 )
+    {
+        portable_mutex_lock(&LP_tokenmutex);
+        job->result = cJSON_CreateObject();
+        jaddstr(job->result,"error","couldnt start deploy thread");
+        job->finished = (uint32_t)time(NULL);
+        portable_mutex_unlock(&LP_tokenmutex);
+        return(clonestr("{\"error\":\"couldnt start deploy thread\"}"));
+    }
+    retjson = cJSON_CreateObject();
+    jaddstr(retjson,"result","started");
+    jaddnum(retjson,"jobid",job->jobid);
+    jadd64bits(retjson,"chainid",chainid);
+    jaddstr(retjson,"type",type);
+    return(jprint(retjson,1));
+#else
+    return(clonestr("{\"error\":\"etomic support not compiled in\"}"));
+#endif
+}
+
+cJSON *LP_token_json(struct iguana_info *coin,char *swapcontract)
+{
+    cJSON *item = cJSON_CreateObject();
+    jaddstr(item,"coin",coin->symbol);
+    jaddstr(item,"etomic",coin->etomic);
+    jaddnum(item,"decimals",coin->decimals);
+    if ( swapcontract != 0 && swapcontract[0] != 0 )
+        jaddstr(item,"swap_contract",swapcontract);
+    if ( coin->alicecontract[0] != 0 )
+        jaddstr(item,"alice_contract",coin->alicecontract);
+    if ( coin->bobcontract[0] != 0 )
+        jaddstr(item,"bob_contract",coin->bobcontract);
+    if ( coin->fallback_alicecontract[0] != 0 )
+        jaddstr(item,"fallback_alice_contract",coin->fallback_alicecontract);
+    if ( coin->fallback_bobcontract[0] != 0 )
+        jaddstr(item,"fallback_bob_contract",coin->fallback_bobcontract);
+    return(item);
+}
+
+void LP_token_register(struct iguana_info *coin,char *swapcontract)
+{
+    cJSON *item; int32_t i,n;
+    portable_mutex_lock(&LP_tokenmutex);
+    if ( LP_tokens == 0 )
+        LP_tokens = cJSON_CreateArray();
+    n = cJSON_GetArraySize(LP_tokens);
+    for (i=0; i<n; i++)
+    {
+        item = jitem(LP_tokens,i);
+        if ( jstr(item,"coin") != 0 && strcmp(jstr(item,"coin"),coin->symbol) == 0 )
+        {
+            cJSON_DeleteItemFromArray(LP_tokens,i);
+            break;
+        }
+    }
+    jaddi(LP_tokens,LP_token_json(coin,swapcontract));
+    LP_tokens_save();
+    portable_mutex_unlock(&LP_tokenmutex);
+}
+
+int32_t LP_token_contractsvalid(cJSON *argjson)
+{
+    char *fields[] = { "alice_contract", "bob_contract", "fallback_alice_contract", "fallback_bob_contract" }; int32_t i;
+    for (i=0; i<sizeof(fields)/sizeof(*fields); i++)
+        if ( jobj(argjson,fields[i]) != 0 && LP_token_addrvalid(jstr(argjson,fields[i])) == 0 )
+            return(0);
+    return(1);
+}
+
+struct iguana_info *LP_token_create(cJSON *argjson,char **errstrp)
+{
+    struct iguana_info *coin,*ethcoin; char *symbol,*etomic,*swapcontract; cJSON *item; int32_t decimals;
+    *errstrp = 0;
+    symbol = jstr(argjson,"coin");
+    etomic = jstr(argjson,"etomic");
+    swapcontract = jstr(argjson,"swap_contract");
+    decimals = jint(argjson,"decimals");
+    if ( symbol == 0 || symbol[0] == 0 || strlen(symbol) >= 16 )
+        *errstrp = "invalid coin symbol";
+    else if ( LP_token_addrvalid(etomic) == 0 )
+        *errstrp = "invalid etomic contract address";
+    else if ( decimals < 0 || decimals > 18 )
+        *errstrp = "decimals must be between 0 and 18, 0 queries the contract";
+    else if ( (ethcoin= LP_coinfind("ETH")) == 0 || ethcoin->etomic[0] == 0 || ethcoin->smartaddr[0] == 0 )
+        *errstrp = "ETH must be configured to use ERC20 tokens";
+#ifndef NOTETOMIC
+    else if ( swapcontract != 0 && swapcontract[0] != 0 && compareAddresses(swapcontract,ETOMIC_ALICECONTRACT) == 0 && compareAddresses(swapcontract,ETOMIC_BOBCONTRACT) == 0 && LP_evmchain_contractvalid(swapcontract) == 0 )
+        *errstrp = "swap_contract is not one of the supported swap contracts";
+#endif
+    else if ( LP_token_contractsvalid(argjson) == 0 )
+        *errstrp = "alice_contract, bob_contract and their fallbacks must be contract addresses";
+    if ( *errstrp != 0 )
+        return(0);
+    if ( (coin= LP_coinfind(symbol)) != 0 )
+    {
+        if ( coin->etomic[0] == 0 || strcmp(coin->etomic,etomic) != 0 )
+        {
+            *errstrp = "coin already exists with a different contract";
+            return(0);
+        }
+        LP_coin_evmconfig(coin,argjson);
+        return(coin);
+    }
+    item = cJSON_CreateObject();
+    jaddstr(item,"coin",symbol);
+    jaddstr(item,"etomic",etomic);
+    jaddnum(item,"rpcport",80);
+    jaddnum(item,"decimals",decimals);
+    LP_coincreate(item);
+    free_json(item);
+    if ( (coin= LP_coinfind(symbol)) == 0 )
+    {
+        *errstrp = "couldnt create token";
+        return(0);
+    }
+    safecopy(coin->etomic,etomic,sizeof(coin->etomic));
+    strcpy(coin->smartaddr,ethcoin->smartaddr);
+    memcpy(coin->pubkey33,ethcoin->pubkey33,sizeof(coin->pubkey33));
+    LP_coin_evmconfig(coin,argjson);
+    if ( coin->txfee == 0 )
+        coin->txfee = LP_MIN_TXFEE;
+    coin->inactive = (uint32_t)time(NULL);
+    LP_priceinfoadd(symbol);
+    return(coin);
+}
+
+int32_t LP_token_activate(struct iguana_info *coin)
+{
+#ifndef NOTETOMIC
+    LP_etomic_select(coin->symbol);
+    if ( coin->decimals == 0 && (coin->decimals= getErc20DecimalsZeroOnError(coin->etomic)) == 0 )
+        return(-1);
+    if ( coin->inactive != 0 && OS_thread_create(malloc(sizeof(pthread_t)),NULL,(void *)LP_etomic_txhistory_loop,(void *)coin) != 0 )
+    {
+        printf("error launching LP_etomic_txhistory_loop %s\n",coin->symbol);
+        return(-1);
+    }
+#endif
+    coin->inactive = 0;
+    return(0);
+}
+
+char *LP_enable_token(cJSON *argjson)
+{
+    struct iguana_info *coin; char *errstr; cJSON *retjson;
+    if ( (coin= LP_token_create(argjson,&errstr)) == 0 )
+    {
+        retjson = cJSON_CreateObject();
+        jaddstr(retjson,"error",errstr != 0 ? errstr : "couldnt create token");
+        return(jprint(retjson,1));
+    }
+    if ( LP_token_activate(coin) < 0 )
+        return(clonestr("{\"error\":\"Could not get token decimals or token has zero decimals which is not supported!\"}"));
+    LP_token_register(coin,jstr(argjson,"swap_contract"));
+    retjson = LP_coinjson(coin,0);
+    jaddstr(retjson,"result","success");
+    return(jprint(retjson,1));
+}
+
+void LP_tokens_load()
+{
+    char fname[512],*fstr,*errstr; long fsize; cJSON *array,*item; int32_t i,n = 0; struct iguana_info *coin;
+    LP_evmchains_load();
+    sprintf(fname,"%s/tokens.json",GLOBAL_DBDIR), OS_compatible_path(fname);
+    if ( (fstr= OS_filestr(&fsize,fname)) != 0 )
+    {
+        if ( (array= cJSON_Parse(fstr)) != 0 )
+        {
+            if ( is_cJSON_Array(array) != 0 && (n= cJSON_GetArraySize(array)) > 0 )
+            {
+                for (i=0; i<n; i++)
+                {
+                    item = jitem(array,i);
+                    if ( (coin= LP_token_create(item,&errstr)) == 0 )
+                        printf("skip token.(%s) %s\n",jprint(item,0),errstr != 0 ? errstr : "");
+                }
+            }
+            portable_mutex_lock(&LP_tokenmutex);
+            if ( LP_tokens != 0 )
+                free_json(LP_tokens);
+            LP_tokens = array;
+            portable_mutex_unlock(&LP_tokenmutex);
+            printf("loaded %d tokens from %s\n",n,fname);
+        }
+        free(fstr);
+    }
+}
@@ -290,7 +290,7 @@ struct LP_tradebot_trade *LP_tradebot_pending(struct LP_tradebot *bot,cJSON *pen
 double LP_orderbook_maxrel(char *base,char *rel,double maxprice)
 {
     char *retstr; int32_t i,numasks; cJSON *retjson,*asks,*item; double maxvol,maxrel = 0.;
-    if ( (retstr= LP_orderbook(base,rel,0)) != 0 )
+    if ( (retstr= LP_orderbook(base,rel,0,0.)) != 0 )
     {
         //printf("maxprice %.8f %s/%s\n",maxprice,base,rel);
         if ( (retjson= cJSON_Parse(retstr)) != 0 )
@@ -338,7 +338,7 @@ void LP_tradebot_timeslice(void *ctx,struct LP_tradebot *bot)
                     {
                         if ( remaining < 0.001 )
                             break;
-                        if ( (retstr= LP_autobuy(ctx,0,LP_myipaddr,LP_mypubsock,bot->base,bot->rel,bot->maxprice,remaining/i,0,0,G.gui,0,destpubkey,tradeid,0,0,0)) != 0 )
+                        if ( (retstr= LP_autobuy(ctx,0,LP_myipaddr,LP_mypubsock,bot->base,bot->rel,bot->maxprice,remaining/i,0,0,0,G.gui,0,destpubkey,tradeid,0,0,0,0,0)) != 0 )
                         {
                             if ( (retjson2= cJSON_Parse(retstr)) != 0 )
                             {
@@ -482,7 +482,7 @@ char *LP_tradebot_buy(int32_t dispdir,char *base,char *rel,double maxprice,doubl
         }
         free_json(array);
     }*/
-    if ( (retstr= LP_orderbook(base,rel,0)) != 0 )
+    if ( (retstr= LP_orderbook(base,rel,0,0.)) != 0 )
         free(retstr);
     txfee = LP_txfeecalc(relcoin,0,0);
     txfees = 10 * txfee;
@@ -35,6 +35,8 @@ int32_t LP_privkeyadd(bits256 privkey,uint8_t rmd160[20])
     tmpkey = LP_privkeyfind(rmd160);
     if ( bits256_nonz(tmpkey) != 0 )
         return(-bits256_cmp(privkey,tmpkey));
+    if ( G.LP_numprivkeys >= sizeof(G.LP_privkeys)/sizeof(*G.LP_privkeys) )
+        return(-1);
     G.LP_privkeys[G.LP_numprivkeys].privkey = privkey;
     memcpy(G.LP_privkeys[G.LP_numprivkeys].rmd160,rmd160,20);
     //int32_t i; for (i=0; i<20; i++)
@@ -422,7 +424,7 @@ int32_t iguana_vininfo_create(char *symbol,uint8_t taddr,uint8_t pubtype,uint8_t
 
 int32_t bitcoin_verifyvins(void *ctx,char *symbol,uint8_t taddr,uint8_t pubtype,uint8_t p2shtype,uint8_t isPoS,int32_t height,bits256 *signedtxidp,char **signedtx,struct iguana_msgtx *msgtx,uint8_t *serialized,int32_t maxlen,struct vin_info *V,uint32_t sighash,int32_t signtx,int32_t suppress_pubkeys,int32_t zcash)
 {
-    bits256 sigtxid; int64_t spendamount; uint8_t *sig,*script; struct vin_info *vp; char vpnstr[64]; int32_t scriptlen,complete=0,j,vini=0,flag=0,siglen,numvouts,numsigs;
+    bits256 sigtxid; int64_t spendamount; uint8_t *sig,*script; struct vin_info *vp; char vpnstr[64]; int32_t scriptlen,complete=0,j,vini=0,flag=0,siglen,newlen,numvouts,numsigs;
     numvouts = msgtx->tx_out;
     vpnstr[0] = 0;
     *signedtx = 0;
@@ -450,11 +452,9 @@ int32_t bitcoin_verifyvins(void *ctx,char *symbol,uint8_t taddr,uint8_t pubtype,
             {
                 sig = vp->signers[j].sig;
                 siglen = vp->signers[j].siglen;
-                if ( signtx != 0 && bits256_nonz(vp->signers[j].privkey) != 0 )
+                if ( signtx != 0 && (newlen= LP_signer_sign(ctx,symbol,sig,vp->signers[j].pubkey,sigtxid,vp->signers[j].privkey,script,scriptlen)) > 0 )
                 {
-                    siglen = bitcoin_sign(ctx,symbol,sig,sigtxid,vp->signers[j].privkey,0);
-                    //if ( (plen= bitcoin_pubkeylen(vp->signers[j].pubkey)) <= 0 )
-                    bitcoin_pubkey33(ctx,vp->signers[j].pubkey,vp->signers[j].privkey);
+                    siglen = newlen;
                     sig[siglen++] = sighash;
                     vp->signers[j].siglen = siglen;
                     /*char str[65]; printf("SIGTXID.(%s) ",bits256_str(str,sigtxid));
@@ -769,7 +769,7 @@ void test_validate(struct iguana_info *coin,char *signedtx)
 
 char *basilisk_swap_bobtxspend(bits256 *signedtxidp,uint64_t txfee,char *name,char *symbol,uint8_t wiftaddr,uint8_t taddr,uint8_t pubtype,uint8_t p2shtype,uint8_t isPoS,uint8_t wiftype,void *ctx,bits256 privkey,bits256 *privkey2p,uint8_t *redeemscript,int32_t redeemlen,uint8_t *userdata,int32_t userdatalen,bits256 utxotxid,int32_t utxovout,char *destaddr,uint8_t *pubkey33,int32_t finalseqid,uint32_t expiration,int64_t *destamountp,uint64_t satoshis,char *changeaddr,char *vinaddr,int32_t suppress_pubkeys,int32_t zcash)
 {
-    char *rawtxbytes=0,*signedtx=0,tmpaddr[64],hexstr[999],wifstr[128],_destaddr[64]; uint8_t spendscript[512],addrtype,rmd160[20]; cJSON *txobj,*vins,*obj,*vouts,*item,*privkeys; int32_t completed,spendlen,n,ignore_cltverr=1; struct vin_info V[8]; uint32_t timestamp,locktime = 0,sequenceid = 0xffffffff * finalseqid; bits256 txid; uint64_t value=0,change = 0; struct iguana_msgtx msgtx; struct iguana_info *coin;
+    char *rawtxbytes=0,*signedtx=0,tmpaddr[64],hexstr[999],wifstr[128],_destaddr[64]; uint8_t spendscript[512],addrtype,rmd160[20]; cJSON *txobj,*vins,*obj,*vouts,*item,*privkeys; int32_t completed,spendlen,n,ignore_cltverr=1; struct vin_info V[8]; uint32_t timestamp,txversion,csvsequence,locktime = 0,sequenceid = 0xffffffff * finalseqid; bits256 txid; uint64_t value=0,change = 0; struct iguana_msgtx msgtx; struct iguana_info *coin;
     LP_mark_spent(symbol,utxotxid,utxovout);
     *destamountp = 0;
     memset(signedtxidp,0,sizeof(*signedtxidp));
@@ -868,7 +868,15 @@ char *basilisk_swap_bobtxspend(bits256 *signedtxidp,uint64_t txfee,char *name,ch
     V[0].ignore_cltverr = ignore_cltverr;
     if ( redeemlen != 0 )
         memcpy(V[0].p2shscript,redeemscript,redeemlen), V[0].p2shlen = redeemlen;
-    txobj = bitcoin_txcreate(symbol,isPoS,locktime,coin->txversion,timestamp);
+    txversion = coin->txversion;
+    if ( finalseqid == 0 && (csvsequence= basilisk_csvscript(redeemscript,redeemlen)) != 0 ) // BIP68 needs a version 2 tx and the lock in the vin sequence
+    {
+        locktime = 0;
+        sequenceid = csvsequence;
+        if ( txversion < 2 )
+            txversion = 2;
+    }
+    txobj = bitcoin_txcreate(symbol,isPoS,locktime,txversion,timestamp);
     vins = cJSON_CreateArray();
     item = cJSON_CreateObject();
     if ( userdata != 0 && userdatalen > 0 )
@@ -1187,7 +1195,9 @@ int32_t LP_vins_select(void *ctx,struct iguana_info *coin,int64_t *totalp,int64_
             if ( LP_validSPV(coin->symbol,coin->smartaddr,up->U.txid,up->U.vout) < 0 )
                 continue;
         }
-        if ( bits256_cmp(utxotxid,up->U.txid) != 0 && LP_allocated(up->U.txid,up->U.vout) != 0 )
+        if ( bits256_cmp(utxotxid,up->U.txid) != 0 && (LP_allocated(up->U.txid,up->U.vout) != 0 || LP_utxolocked(coin->symbol,up->U.txid,up->U.vout) != 0) )
+            continue;
+        if ( LP_swapchange_blocked(coin,up->U.txid) != 0 )
             continue;
         up->spendheight = 1;
         total += up->U.value;
@@ -1234,7 +1244,7 @@ int32_t LP_vins_select(void *ctx,struct iguana_info *coin,int64_t *totalp,int64_
 char *LP_createrawtransaction(cJSON **txobjp,int32_t *numvinsp,struct iguana_info *coin,struct vin_info *V,int32_t max,bits256 privkey,cJSON *outputs,cJSON *vins,cJSON *privkeys,int64_t txfee,bits256 utxotxid,int32_t utxovout,int32_t onevin,uint32_t locktime,char *opretstr,char *passphrase)
 {
     static void *ctx;
-    cJSON *txobj,*item; uint8_t addrtype,rmd160[20],data[8192+64],script[8192],spendscript[256]; char *coinaddr,*rawtxbytes,*scriptstr; bits256 txid; uint32_t crc32,timestamp; int64_t change=0,adjust=0,total,value,amount = 0; int32_t origspendlen=0,i,offset,len,dustcombine,scriptlen,spendlen,suppress_pubkeys,ignore_cltverr,numvouts=0,numvins=0,numutxos=0; struct LP_address_utxo *utxos[LP_MAXVINS*256]; struct LP_address *ap;
+    cJSON *txobj,*item; uint8_t data[8192+64],script[8192],spendscript[256]; char *coinaddr,*rawtxbytes,*scriptstr; bits256 txid; uint32_t crc32,timestamp; int64_t change=0,adjust=0,total,value,amount = 0; int32_t origspendlen=0,i,offset,len,dustcombine,scriptlen,spendlen,suppress_pubkeys,ignore_cltverr,numvouts=0,numvins=0,numutxos=0; struct LP_address_utxo *utxos[LP_MAXVINS*256]; struct LP_address *ap;
     if ( ctx == 0 )
         ctx = bitcoin_ctx();
     *numvinsp = 0;
@@ -1331,6 +1341,11 @@ char *LP_createrawtransaction(cJSON **txobjp,int32_t *numvinsp,struct iguana_inf
         return(0);
     }
     change = (total - amount);
+    if ( coin->rbf != 0 ) // BIP125 opt-in so a stuck payment can be replaced with a higher fee
+    {
+        for (i=0; i<numvins; i++)
+            jaddnum(jitem(vins,i),"sequence",LP_RBF_SEQUENCE);
+    }
     timestamp = (uint32_t)time(NULL);
     if ( locktime == 0 && strcmp("KMD",coin->symbol) == 0 )
         locktime = timestamp - 777;
@@ -1366,10 +1381,12 @@ char *LP_createrawtransaction(cJSON **txobjp,int32_t *numvinsp,struct iguana_inf
             }
             else
             {
-                bitcoin_addr2rmd160(coin->symbol,coin->taddr,&addrtype,rmd160,coinaddr);
-                if ( addrtype == coin->pubtype )
-                    spendlen = bitcoin_standardspend(spendscript,0,rmd160);
-                else spendlen = bitcoin_p2shspend(spendscript,0,rmd160);
+                if ( (spendlen= LP_address_script(spendscript,coin->symbol,coin->taddr,coin->pubtype,coinaddr)) <= 0 )
+                {
+                    printf("LP_createrawtransaction: cant make script for %s\n",coinaddr);
+                    free_json(txobj);
+                    return(0);
+                }
                 if ( i == numvouts-1 && strcmp(coinaddr,coin->smartaddr) == 0 && change != 0 )
                 {
                     //printf("combine last vout %.8f with change %.8f\n",dstr(value+adjust),dstr(change));
@@ -1548,7 +1565,7 @@ char *LP_opreturndecrypt(void *ctx,char *symbol,bits256 utxotxid,char *passphras
 char *LP_createblasttransaction(uint64_t *changep,int32_t *changeoutp,cJSON **txobjp,cJSON **vinsp,struct vin_info *V,struct iguana_info *coin,bits256 utxotxid,int32_t utxovout,uint64_t utxovalue,bits256 privkey,cJSON *outputs,int64_t txfee)
 {
     static void *ctx;
-    cJSON *txobj,*item,*vins; uint8_t addrtype,rmd160[20],pubkey33[33],tmptype,data[8192+64],script[8192],spendscript[256]; char *coinaddr,*rawtxbytes,*scriptstr,spendscriptstr[128],blastaddr[64],wifstr[64]; bits256 txid; uint32_t locktime,crc32,timestamp; int64_t change=0,adjust=0,total,value,amount = 0; int32_t i,offset,len,scriptlen,spendlen,suppress_pubkeys,ignore_cltverr,numvouts=0;
+    cJSON *txobj,*item,*vins; uint8_t rmd160[20],pubkey33[33],tmptype,data[8192+64],script[8192],spendscript[256]; char *coinaddr,*rawtxbytes,*scriptstr,spendscriptstr[128],blastaddr[64],wifstr[64]; bits256 txid; uint32_t locktime,crc32,timestamp; int64_t change=0,adjust=0,total,value,amount = 0; int32_t i,offset,len,scriptlen,spendlen,suppress_pubkeys,ignore_cltverr,numvouts=0;
     if ( ctx == 0 )
         ctx = bitcoin_ctx();
     *txobjp = *vinsp = 0;
@@ -1633,10 +1650,12 @@ char *LP_createblasttransaction(uint64_t *changep,int32_t *changeoutp,cJSON **tx
             }
             else
             {
-                bitcoin_addr2rmd160(coin->symbol,coin->taddr,&addrtype,rmd160,coinaddr);
-                if ( addrtype == coin->pubtype )
-                    spendlen = bitcoin_standardspend(spendscript,0,rmd160);
-                else spendlen = bitcoin_p2shspend(spendscript,0,rmd160);
+                if ( (spendlen= LP_address_script(spendscript,coin->symbol,coin->taddr,coin->pubtype,coinaddr)) <= 0 )
+                {
+                    fprintf(stderr,"LP_createblasttransaction: cant make script for %s\n",coinaddr);
+                    free_json(txobj);
+                    return(0);
+                }
                 if ( i == numvouts-1 && strcmp(coinaddr,coin->smartaddr) == 0 && change != 0 )
                 {
                     value += change;
@@ -1894,7 +1913,7 @@ char *LP_withdraw(struct iguana_info *coin,cJSON *argjson)
     utxovout = jint(argjson,"utxovout");
     locktime = juint(argjson,"locktime");
     txfee = juint(argjson,"txfee");
-    autofee = (strcmp(coin->symbol,"BTC") == 0);
+    autofee = (strcmp(LP_chainsymbol(coin->symbol),"BTC") == 0);
     //printf("LP_withdraw: %s/v%d %s\n",bits256_str(str,utxotxid),utxovout,jprint(outputs,0));
     if ( txfee == 0 )
     {
@@ -1948,7 +1967,7 @@ char *LP_withdraw(struct iguana_info *coin,cJSON *argjson)
             if ( signedtx == 0 )
                 break;
             datalen = (int32_t)strlen(signedtx) / 2;
-            if ( autofee != 0 && iter == 0 && strcmp(coin->symbol,"BTC") == 0 )
+            if ( autofee != 0 && iter == 0 && strcmp(LP_chainsymbol(coin->symbol),"BTC") == 0 )
             {
                 txfee = newtxfee = LP_txfeecalc(coin,0,datalen);
                 printf("txfee %.8f -> newtxfee %.8f, numvins.%d datalen.%d\n",dstr(txfee),dstr(newtxfee),numvins,datalen);
@@ -2012,6 +2031,70 @@ char *LP_withdraw(struct iguana_info *coin,cJSON *argjson)
     return(jprint(retjson,1));
 }
 
+cJSON *LP_my_balance(struct iguana_info *coin)
+{
+    cJSON *retjson;
+    retjson = LP_address_balance(coin,coin->smartaddr,1);
+    jaddnum(retjson,"locked_by_swaps",dstr(coin->inflight));
+    return(retjson);
+}
+
+// {"coin","to","amount"} or {"coin","to","max":1}, the fee comes out of the change or, for max, out of the amount
+char *LP_withdraw_to(struct iguana_info *coin,cJSON *argjson)
+{
+    char *to,*retstr; cJSON *withdrawjson,*outputs,*item,*retjson; int64_t amount,balance,locked=0,txfee; bits256 zero;
+    if ( (to= jstr(argjson,"to")) == 0 || to[0] == 0 )
+        return(clonestr("{\"error\":\"withdraw needs to or outputs\"}"));
+    if ( coin->etomic[0] != 0 )
+    {
+#ifndef NOTETOMIC
+        if ( jint(argjson,"max") != 0 )
+            return(clonestr("{\"error\":\"max not supported for ETH/ERC20\"}"));
+        return(LP_eth_withdraw(coin,argjson));
+#else
+        return(clonestr("{\"error\":\"built without ETH/ERC20 support\"}"));
+#endif
+    }
+    if ( bitcoin_validaddress(coin->symbol,coin->taddr,coin->pubtype,coin->p2shtype,to) < 0 )
+        return(clonestr("{\"error\":\"invalid to address\"}"));
+    if ( (txfee= juint(argjson,"txfee")) == 0 )
+        txfee = LP_txfeecalc(coin,0,0);
+    if ( jint(argjson,"max") != 0 )
+    {
+        // only what LP_vins_select will pick: inputs locked by a swap or reserved for an ordermatch stay where they are
+        memset(zero.bytes,0,sizeof(zero));
+        balance = LP_spendable_balance(coin,coin->smartaddr,0,0,zero,&locked);
+        if ( (amount= balance - txfee) <= 0 )
+            return(clonestr("{\"error\":\"balance doesnt cover txfee\"}"));
+    }
+    else if ( (amount= jdouble(argjson,"amount") * SATOSHIDEN) <= 0 )
+        return(clonestr("{\"error\":\"withdraw needs amount or max\"}"));
+    withdrawjson = cJSON_CreateObject();
+    outputs = cJSON_CreateArray();
+    item = cJSON_CreateObject();
+    jaddnum(item,to,dstr(amount));
+    jaddi(outputs,item);
+    jadd(withdrawjson,"outputs",outputs);
+    jaddstr(withdrawjson,"coin",coin->symbol);
+    jaddnum(withdrawjson,"txfee",txfee);
+    jaddnum(withdrawjson,"broadcast",jint(argjson,"broadcast"));
+    retstr = LP_withdraw(coin,withdrawjson);
+    free_json(withdrawjson);
+    if ( retstr != 0 && (retjson= cJSON_Parse(retstr)) != 0 )
+    {
+        free(retstr);
+        jaddstr(retjson,"from",coin->smartaddr);
+        jaddstr(retjson,"to",to);
+        jaddnum(retjson,"amount",dstr(amount));
+        jaddnum(retjson,"fee",dstr(txfee));
+        jaddnum(retjson,"total",dstr(amount + txfee));
+        if ( locked != 0 )
+            jaddnum(retjson,"locked",dstr(locked));
+        retstr = jprint(retjson,1);
+    }
+    return(retstr);
+}
+
 char *LP_autosplit(struct iguana_info *coin)
 {
     char *retstr; cJSON *argjson,*withdrawjson,*outputs,*item; int64_t total,balance,txfee;
@@ -2227,6 +2310,7 @@ char *LP_eth_withdraw(struct iguana_info *coin,cJSON *argjson)
     if (broadcast == 1) {
         privkey = LP_privkey(coin->symbol, coin->smartaddr, coin->taddr);
         uint8arrayToHex(privkey_str, privkey.bytes, 32);
+        LP_etomic_select(coin->symbol);
         if (strcmp(coin->symbol, "ETH") == 0) {
             tx_id = sendEth(dest_addr, amount_str, privkey_str, 0, gas, gas_price, 0);
         } else {
@@ -2267,6 +2351,8 @@ int32_t basilisk_rawtx_gen(void *ctx,char *str,uint32_t swapstarted,uint8_t *pub
         if ( (coin= LP_coinfind("ETOMIC")) == 0 )
             return(-1);
     }
+    if ( coin->signerurl[0] != 0 )
+        vinaddr = coin->smartaddr;
     if ( strcmp(coin->smartaddr,vinaddr) != 0 )
     {
         printf("???????????????????????? basilisk_rawtx_gen mismatched %s %s vinaddr.%s != (%s)\n",rawtx->symbol,coin->symbol,vinaddr,coin->smartaddr);
@@ -2280,6 +2366,12 @@ int32_t basilisk_rawtx_gen(void *ctx,char *str,uint32_t swapstarted,uint8_t *pub
     outputs = cJSON_CreateArray();
     item = cJSON_CreateObject();
     jaddnum(item,rawtx->I.destaddr,dstr(rawtx->I.amount));
+    if ( rawtx->I.vouttype == 0 && scriptlen > 0 && script[0] == SCRIPT_OP_RETURN )
+    {
+        char scriptstr[256];
+        init_hexbytes_noT(scriptstr,script,scriptlen);
+        jaddstr(item,"script",scriptstr);
+    }
     jaddi(outputs,item);
     jadd(argjson,"outputs",outputs);
     //printf("call LP_withdraw.(%s)\n",jprint(argjson,0));
@@ -2293,6 +2385,9 @@ int32_t basilisk_rawtx_gen(void *ctx,char *str,uint32_t swapstarted,uint8_t *pub
                 decode_hex(rawtx->txbytes,rawtx->I.datalen,hexstr);
                 rawtx->I.completed = 1;
                 rawtx->I.signedtxid = jbits256(retjson,"txid");
+                if ( rawtx->vins != 0 )
+                    free_json(rawtx->vins);
+                rawtx->vins = jduplicate(jobj(jobj(retjson,"tx"),"vin"));
                 retval = 0;
             } else printf("rawtx withdraw error? (%s)\n",jprint(argjson,0));
             free_json(retjson);
@@ -2303,6 +2398,164 @@ int32_t basilisk_rawtx_gen(void *ctx,char *str,uint32_t swapstarted,uint8_t *pub
     return(retval);
 }
 
+// changeaddr is the swap's change address on entry and the address the change output pays on return. any output back
+// to one of our own addresses other than the payment's counts, the change can be on an HD or segwit address
+int32_t basilisk_rawtx_change(struct iguana_info *coin,struct basilisk_rawtx *rawtx,char *changeaddr,int64_t *changep,uint32_t *locktimep)
+{
+    cJSON *txobj,*vouts,*item; uint8_t extraspace[32768]; char coinaddr[64]; bits256 txid; struct iguana_msgtx msgtx; int32_t i,n,changevout = -1;
+    *changep = 0;
+    if ( (txobj= bitcoin_data2json(coin->symbol,coin->taddr,coin->pubtype,coin->p2shtype,coin->isPoS,coin->longestchain,&txid,&msgtx,extraspace,sizeof(extraspace),rawtx->txbytes,rawtx->I.datalen,0,0,coin->zcash)) != 0 )
+    {
+        if ( locktimep != 0 )
+            *locktimep = juint(txobj,"locktime");
+        if ( (vouts= jarray(&n,txobj,"vout")) != 0 )
+        {
+            for (i=0; i<n; i++)
+            {
+                item = jitem(vouts,i);
+                if ( LP_destaddr(coinaddr,item) < 0 || strcmp(coinaddr,rawtx->I.destaddr) == 0 )
+                    continue;
+                if ( strcmp(coinaddr,changeaddr) == 0 || strcmp(coinaddr,coin->smartaddr) == 0 || bits256_nonz(LP_swap_privkey(coin,coinaddr)) != 0 )
+                {
+                    *changep = LP_value_extract(item,0,txid);
+                    strcpy(changeaddr,coinaddr);
+                    changevout = i;
+                    break;
+                }
+            }
+        }
+        free_json(txobj);
+    }
+    return(changevout);
+}
+
+// the key for the address a vin spends from, found from its p2pkh or p2wpkh scriptPubKey
+bits256 LP_vin_privkey(struct iguana_info *coin,cJSON *vin)
+{
+    uint8_t spk[64],rmd160[20]; char coinaddr[64],*hexstr; cJSON *sobj; int32_t len; bits256 privkey;
+    memset(privkey.bytes,0,sizeof(privkey));
+    if ( (sobj= jobj(vin,"scriptPubKey")) == 0 || (hexstr= jstr(sobj,"hex")) == 0 || (len= is_hexstr(hexstr,0) >> 1) > sizeof(spk) )
+        return(privkey);
+    decode_hex(spk,len,hexstr);
+    if ( len == 25 && spk[0] == 0x76 && spk[1] == 0xa9 && spk[2] == 20 )
+        memcpy(rmd160,&spk[3],20);
+    else if ( len == 22 && spk[0] == 0 && spk[1] == 20 )
+        memcpy(rmd160,&spk[2],20);
+    else return(privkey);
+    bitcoin_address(coin->symbol,coinaddr,coin->taddr,coin->pubtype,rmd160,20);
+    return(LP_swap_privkey(coin,coinaddr));
+}
+
+// same vins and outputs, the extra fee comes out of the change. only commits to rawtx once the replacement is accepted
+int32_t basilisk_rawtx_rbf(struct basilisk_rawtx *rawtx,char *changeaddr,int64_t bumpfee)
+{
+    static void *ctx;
+    struct iguana_info *coin; struct vin_info *V; char *rawtxbytes,*signedtx=0,*hexstr,wifstr[128],str[65]; cJSON *txobj,*origobj,*vins,*vouts,*item,*sobj,*privkeys; uint8_t script[IGUANA_MAXSCRIPTSIZE],extraspace[32768]; bits256 privkey,txid,origtxid,signedtxid; struct iguana_msgtx msgtx; int64_t change,value; uint32_t locktime = 0; int32_t i,len,numvins,numvouts,changevout,scriptlen,completed,signable,retval = -1;
+    if ( ctx == 0 )
+        ctx = bitcoin_ctx();
+    if ( (coin= LP_coinfind(rawtx->symbol)) == 0 || coin->rbf == 0 || coin->etomic[0] != 0 || rawtx->vins == 0 || rawtx->I.datalen <= 0 )
+        return(-1);
+    if ( (numvins= cJSON_GetArraySize(rawtx->vins)) <= 0 || numvins > LP_MAXVINS )
+        return(-1);
+    if ( (changevout= basilisk_rawtx_change(coin,rawtx,changeaddr,&change,&locktime)) < 0 || change - bumpfee < LP_MIN_TXFEE*6 )
+    {
+        printf("%s rbf: change %.8f cant cover bump %.8f\n",rawtx->name,dstr(change),dstr(bumpfee));
+        return(-1);
+    }
+    memset(&msgtx,0,sizeof(msgtx));
+    if ( (origobj= bitcoin_data2json(coin->symbol,coin->taddr,coin->pubtype,coin->p2shtype,coin->isPoS,coin->longestchain,&origtxid,&msgtx,extraspace,sizeof(extraspace),rawtx->txbytes,rawtx->I.datalen,0,0,coin->zcash)) == 0 )
+        return(-1);
+    if ( (vouts= jarray(&numvouts,origobj,"vout")) == 0 || changevout >= numvouts )
+    {
+        free_json(origobj);
+        return(-1);
+    }
+    V = calloc(numvins,sizeof(*V));
+    vins = jduplicate(rawtx->vins);
+    privkeys = cJSON_CreateArray();
+    for (i=0; i<numvins; i++)
+    {
+        privkey = LP_vin_privkey(coin,jitem(vins,i));
+        if ( bits256_nonz(privkey) == 0 )
+        {
+            printf("%s rbf: no key for vin.%d\n",rawtx->name,i);
+            break;
+        }
+        bitcoin_priv2wif(coin->symbol,coin->wiftaddr,wifstr,privkey,coin->wiftype);
+        jdelete(jitem(vins,i),"sequence");
+        jaddnum(jitem(vins,i),"sequence",LP_RBF_SEQUENCE);
+        V[i].N = V[i].M = 1;
+        V[i].signers[0].privkey = privkey;
+        bitcoin_pubkey33(ctx,V[i].signers[0].pubkey,privkey);
+        V[i].suppress_pubkeys = jint(jitem(vins,i),"suppress");
+        jaddistr(privkeys,wifstr);
+    }
+    txobj = bitcoin_txcreate(coin->symbol,coin->isPoS,locktime,coin->txversion,(uint32_t)time(NULL));
+    jdelete(txobj,"vin");
+    jadd(txobj,"vin",jduplicate(vins));
+    if ( (signable= (i == numvins)) != 0 )
+    {
+        // every output of the original is kept as it was, script and all, so an OP_RETURN or a second payment isnt dropped
+        for (i=0; i<numvouts; i++)
+        {
+            item = jitem(vouts,i);
+            if ( (sobj= jobj(item,"scriptPubKey")) == 0 || (hexstr= jstr(sobj,"hex")) == 0 || (scriptlen= is_hexstr(hexstr,0) >> 1) > sizeof(script) )
+                break;
+            decode_hex(script,scriptlen,hexstr);
+            value = LP_value_extract(item,0,origtxid);
+            txobj = bitcoin_txoutput(txobj,script,scriptlen,i == changevout ? change - bumpfee : value);
+        }
+        signable = (i == numvouts);
+    }
+    free_json(origobj);
+    if ( signable != 0 && (rawtxbytes= bitcoin_json2hex(coin->symbol,coin->isPoS,&txid,txobj,V)) != 0 )
+    {
+        memset(&msgtx,0,sizeof(msgtx));
+        memset(signedtxid.bytes,0,sizeof(signedtxid));
+        if ( (completed= iguana_signrawtransaction(ctx,coin->symbol,coin->wiftaddr,coin->taddr,coin->pubtype,coin->p2shtype,coin->isPoS,coin->longestchain,&msgtx,&signedtx,&signedtxid,V,numvins,rawtxbytes,vins,privkeys,coin->zcash)) <= 0 || signedtx == 0 || (len= (int32_t)strlen(signedtx) >> 1) > sizeof(rawtx->txbytes) )
+            printf("%s rbf: couldnt sign replacement %s\n",rawtx->name,bits256_str(str,signedtxid));
+        else if ( bits256_cmp(LP_broadcast(rawtx->name,coin->symbol,signedtx,signedtxid),signedtxid) != 0 )
+            printf("%s rbf: replacement %s rejected\n",rawtx->name,bits256_str(str,signedtxid));
+        else
+        {
+            rawtx->I.datalen = len;
+            decode_hex(rawtx->txbytes,len,signedtx);
+            rawtx->I.signedtxid = rawtx->I.actualtxid = signedtxid;
+            rawtx->I.change = change - bumpfee;
+            free_json(rawtx->vins);
+            rawtx->vins = vins, vins = 0;
+            retval = 0;
+        }
+        if ( signedtx != 0 )
+            free(signedtx);
+        free(rawtxbytes);
+    }
+    if ( vins != 0 )
+        free_json(vins);
+    free_json(privkeys);
+    free_json(txobj);
+    free(V);
+    return(retval);
+}
+
+// child pays for parent: sweep the unconfirmed payment's change back to ourselves with a fee covering both
+char *basilisk_rawtx_cpfp(bits256 *signedtxidp,struct basilisk_rawtx *rawtx,char *changeaddr,int64_t bumpfee)
+{
+    static void *ctx;
+    struct iguana_info *coin; int64_t change,destamount; int32_t changevout;
+    if ( ctx == 0 )
+        ctx = bitcoin_ctx();
+    memset(signedtxidp,0,sizeof(*signedtxidp));
+    if ( (coin= LP_coinfind(rawtx->symbol)) == 0 || coin->etomic[0] != 0 || rawtx->I.datalen <= 0 )
+        return(0);
+    if ( (changevout= basilisk_rawtx_change(coin,rawtx,changeaddr,&change,0)) < 0 || change - bumpfee < LP_MIN_TXFEE*6 )
+    {
+        printf("%s cpfp: change %.8f cant cover bump %.8f\n",rawtx->name,dstr(change),dstr(bumpfee));
+        return(0);
+    }
+    return(basilisk_swap_bobtxspend(signedtxidp,bumpfee,"cpfp",coin->symbol,coin->wiftaddr,coin->taddr,coin->pubtype,coin->p2shtype,coin->isPoS,coin->wiftype,ctx,LP_swap_privkey(coin,changeaddr),0,0,0,0,0,rawtx->I.signedtxid,changevout,changeaddr,G.LP_pubsecp,1,0,&destamount,0,changeaddr,changeaddr,0,coin->zcash));
+}
+
 int32_t basilisk_rawtx_sign(char *symbol,uint8_t wiftaddr,uint8_t taddr,uint8_t pubtype,uint8_t p2shtype,uint8_t isPoS,uint8_t wiftype,struct basilisk_swap *swap,struct basilisk_rawtx *dest,struct basilisk_rawtx *rawtx,bits256 privkey,bits256 *privkey2,uint8_t *userdata,int32_t userdatalen,int32_t ignore_cltverr,uint8_t *changermd160,char *vinaddr,int32_t zcash)
 {
     char *signedtx,*changeaddr = 0,_changeaddr[64]; int64_t txfee,newtxfee=0,destamount; uint32_t timestamp,locktime=0,sequenceid = 0xffffffff; int32_t iter,retval = -1; double estimatedrate;
@@ -2343,17 +2596,11 @@ int32_t basilisk_rawtx_sign(char *symbol,uint8_t wiftaddr,uint8_t taddr,uint8_t
 
 int32_t basilisk_alicescript(char *symbol,uint8_t *redeemscript,int32_t *redeemlenp,uint8_t *script,int32_t n,char *msigaddr,uint8_t taddr,uint8_t altps2h,bits256 pubAm,bits256 pubBn)
 {
-    uint8_t p2sh160[20]; struct vin_info V;
-    memset(&V,0,sizeof(V));
-    memcpy(&V.signers[0].pubkey[1],pubAm.bytes,sizeof(pubAm)), V.signers[0].pubkey[0] = 0x02;
-    memcpy(&V.signers[1].pubkey[1],pubBn.bytes,sizeof(pubBn)), V.signers[1].pubkey[0] = 0x03;
-    V.M = V.N = 2;
-    *redeemlenp = bitcoin_MofNspendscript(p2sh160,redeemscript,n,&V);
+    uint8_t p2sh160[20]; struct LP_takerpayment_args args;
+    args.pubAm = pubAm, args.pubBn = pubBn;
+    LP_swapops_takerscript(redeemscript,redeemlenp,p2sh160,&args);
     bitcoin_address(symbol,msigaddr,taddr,altps2h,p2sh160,sizeof(p2sh160));
     n = bitcoin_p2shspend(script,0,p2sh160);
-    //for (i=0; i<*redeemlenp; i++)
-    //    printf("%02x",redeemscript[i]);
-    //printf(" <- redeemscript alicetx\n");
     return(n);
 }
 
@@ -2523,77 +2770,47 @@ bits256 LP_swap_spendtxid(char *symbol,char *destaddr,bits256 utxotxid,int32_t u
     //char str[65]; printf("swap %s spendtxid.(%s)\n",symbol,bits256_str(str,utxotxid));
 }
 
-int32_t basilisk_swap_bobredeemscript(int32_t depositflag,int32_t *secretstartp,uint8_t *redeemscript,uint32_t locktime,bits256 pubA0,bits256 pubB0,bits256 pubB1,bits256 privAm,bits256 privBn,uint8_t *secretAm,uint8_t *secretAm256,uint8_t *secretBn,uint8_t *secretBn256)
+int32_t basilisk_swap_bobredeemscript(int32_t depositflag,int32_t uniformflag,int32_t *secretstartp,uint8_t *redeemscript,uint32_t locktime,uint32_t csvduration,bits256 pubA0,bits256 pubB0,bits256 pubB1,bits256 privAm,bits256 privBn,uint8_t *secretAm,uint8_t *secretAm256,uint8_t *secretBn,uint8_t *secretBn256)
+{
+    struct LP_makerpayment_args args;
+    LP_swapops_makerargs(&args,depositflag,uniformflag,locktime,csvduration,pubA0,pubB0,pubB1,privAm,privBn,secretAm,secretBn);
+    return(LP_swapops_makerscript(redeemscript,secretstartp,&args));
+}
+
+int32_t basilisk_swap_makerfirstscript(int32_t takerflag,int32_t *secretstartp,uint8_t *redeemscript,uint32_t locktime,bits256 pubA0,bits256 pubB0,bits256 pubB1,bits256 privBn,uint8_t *secretBn)
 {
-    int32_t i,n=0; bits256 cltvpub,destpub,privkey; uint8_t pubkeyA[33],pubkeyB[33],secret160[20],secret256[32];
-    if ( depositflag != 0 )
+    struct LP_makerpayment_args args;
+    LP_swapops_makerfirstargs(&args,takerflag,locktime,pubA0,pubB0,pubB1,privBn,secretBn);
+    return(LP_swapops_makerscript(redeemscript,secretstartp,&args));
+}
+
+// maker pays first: bobpayment goes out first so it takes the deposit's putduration + callduration, alicepayment gets putduration
+uint32_t basilisk_makerfirst_locktime(struct basilisk_swapinfo *swap,int32_t takerflag)
+{
+    if ( takerflag != 0 )
+        return(swap->started + swap->putduration);
+    else return(swap->started + swap->putduration + swap->callduration);
+}
+
+int32_t basilisk_bobscript(uint8_t *rmd160,uint8_t *redeemscript,int32_t *redeemlenp,uint8_t *script,int32_t n,uint32_t *locktimep,int32_t *secretstartp,struct basilisk_swapinfo *swap,int32_t depositflag)
+{
+    uint32_t csvduration = 0;
+    if ( depositflag == 0 && swap->makerfirst != 0 )
     {
-        pubkeyA[0] = 0x02, cltvpub = pubA0;
-        pubkeyB[0] = 0x03, destpub = pubB0;
-        privkey = privBn;
-        memcpy(secret160,secretBn,20);
-        memcpy(secret256,secretBn256,32);
+        *locktimep = basilisk_makerfirst_locktime(swap,0);
+        *redeemlenp = n = basilisk_swap_makerfirstscript(0,secretstartp,redeemscript,*locktimep,swap->pubA0,swap->pubB0,swap->pubB1,swap->privBn,swap->secretBn);
     }
     else
     {
-        pubkeyA[0] = 0x03, cltvpub = pubB1;
-        pubkeyB[0] = 0x02, destpub = pubA0;
-        privkey = privAm;
-        memcpy(secret160,secretAm,20);
-        memcpy(secret256,secretAm256,32);
+        if ( depositflag != 0 )
+            *locktimep = swap->started + swap->putduration + swap->callduration;
+        else *locktimep = swap->started + swap->putduration;
+        // the bobpayment counts putduration from its own confirmation. the bobdeposit keeps its absolute locktime, a
+        // relative one would start from the deposit confirming and could unlock before the payment's refund does
+        if ( swap->csvrefunds != 0 && depositflag == 0 )
+            csvduration = swap->putduration;
+        *redeemlenp = n = basilisk_swap_bobredeemscript(depositflag,swap->uniformspends,secretstartp,redeemscript,*locktimep,csvduration,swap->pubA0,swap->pubB0,swap->pubB1,swap->privAm,swap->privBn,swap->secretAm,swap->secretAm256,swap->secretBn,swap->secretBn256);
     }
-    if ( bits256_nonz(cltvpub) == 0 || bits256_nonz(destpub) == 0 )
-        return(-1);
-    for (i=0; i<20; i++)
-        if ( secret160[i] != 0 )
-            break;
-    if ( i == 20 )
-        return(-1);
-    memcpy(pubkeyA+1,cltvpub.bytes,sizeof(cltvpub));
-    memcpy(pubkeyB+1,destpub.bytes,sizeof(destpub));
-    redeemscript[n++] = SCRIPT_OP_IF;
-    n = bitcoin_checklocktimeverify(redeemscript,n,locktime);
-    if ( depositflag != 0 )
-    {
-        //for (i=0; i<20; i++)
-        //    printf("%02x",secretAm[i]);
-        //printf(" <- secretAm depositflag.%d nonz.%d\n",depositflag,bits256_nonz(privkey));
-        n = bitcoin_secret160verify(redeemscript,n,secretAm);
-    }
-    n = bitcoin_pubkeyspend(redeemscript,n,pubkeyA);
-    redeemscript[n++] = SCRIPT_OP_ELSE;
-    if ( secretstartp != 0 )
-        *secretstartp = n + 2;
-    if ( bits256_nonz(privkey) != 0 )
-    {
-        uint8_t bufA[20],bufB[20];
-        revcalc_rmd160_sha256(bufA,privkey);
-        calc_rmd160_sha256(bufB,privkey.bytes,sizeof(privkey));
-        /*if ( memcmp(bufA,secret160,sizeof(bufA)) == 0 )
-         printf("MATCHES BUFA\n");
-         else if ( memcmp(bufB,secret160,sizeof(bufB)) == 0 )
-         printf("MATCHES BUFB\n");
-         else printf("secret160 matches neither\n");
-         for (i=0; i<20; i++)
-         printf("%02x",bufA[i]);
-         printf(" <- revcalc\n");
-         for (i=0; i<20; i++)
-         printf("%02x",bufB[i]);
-         printf(" <- calc\n");*/
-        memcpy(secret160,bufB,20);
-    }
-    n = bitcoin_secret160verify(redeemscript,n,secret160);
-    n = bitcoin_pubkeyspend(redeemscript,n,pubkeyB);
-    redeemscript[n++] = SCRIPT_OP_ENDIF;
-    return(n);
-}
-
-int32_t basilisk_bobscript(uint8_t *rmd160,uint8_t *redeemscript,int32_t *redeemlenp,uint8_t *script,int32_t n,uint32_t *locktimep,int32_t *secretstartp,struct basilisk_swapinfo *swap,int32_t depositflag)
-{
-    if ( depositflag != 0 )
-        *locktimep = swap->started + swap->putduration + swap->callduration;
-    else *locktimep = swap->started + swap->putduration;
-    *redeemlenp = n = basilisk_swap_bobredeemscript(depositflag,secretstartp,redeemscript,*locktimep,swap->pubA0,swap->pubB0,swap->pubB1,swap->privAm,swap->privBn,swap->secretAm,swap->secretAm256,swap->secretBn,swap->secretBn256);
     if ( n > 0 )
     {
         calc_rmd160_sha256(rmd160,redeemscript,n);
@@ -2605,17 +2822,45 @@ int32_t basilisk_bobscript(uint8_t *rmd160,uint8_t *redeemscript,int32_t *redeem
     return(n);
 }
 
+// OP_IF <sequence> OP_CSV OP_DROP ... is a maker script negotiated with csv refunds, returns the sequence its refund needs
+uint32_t basilisk_csvscript(uint8_t *redeemscript,int32_t redeemlen)
+{
+    if ( redeemscript != 0 && redeemlen > 7 && redeemscript[0] == SCRIPT_OP_IF && redeemscript[1] == 3 && redeemscript[5] == SCRIPT_OP_CHECKSEQUENCEVERIFY && redeemscript[6] == SCRIPT_OP_DROP )
+        return(redeemscript[2] | ((uint32_t)redeemscript[3] << 8) | ((uint32_t)redeemscript[4] << 16));
+    return(0);
+}
+
+// OP_IF <locktime> OP_CLTV OP_DROP ..., returns the absolute locktime of the refund path
+uint32_t basilisk_cltvscript(uint8_t *redeemscript,int32_t redeemlen)
+{
+    if ( redeemscript != 0 && redeemlen > 8 && redeemscript[0] == SCRIPT_OP_IF && redeemscript[1] == 4 && redeemscript[6] == SCRIPT_OP_CHECKLOCKTIMEVERIFY && redeemscript[7] == SCRIPT_OP_DROP )
+        return(redeemscript[2] | ((uint32_t)redeemscript[3] << 8) | ((uint32_t)redeemscript[4] << 16) | ((uint32_t)redeemscript[5] << 24));
+    return(0);
+}
+
+// OP_IF <locktime> OP_CLTV OP_DROP OP_DROP ... is the bobpayment script negotiated with uniform spends
+int32_t basilisk_uniformscript(uint8_t *redeemscript,int32_t redeemlen)
+{
+    if ( redeemscript != 0 && redeemlen > 9 && redeemscript[0] == SCRIPT_OP_IF && redeemscript[1] == 4 && redeemscript[6] == SCRIPT_OP_CHECKLOCKTIMEVERIFY && redeemscript[7] == SCRIPT_OP_DROP && redeemscript[8] == SCRIPT_OP_DROP )
+        return(1);
+    else if ( basilisk_csvscript(redeemscript,redeemlen) != 0 && redeemlen > 8 && redeemscript[7] == SCRIPT_OP_DROP )
+        return(1);
+    return(0);
+}
+
 int32_t basilisk_swapuserdata(uint8_t *userdata,bits256 privkey,int32_t ifpath,bits256 signpriv,uint8_t *redeemscript,int32_t redeemlen)
 {
-    int32_t i,len = 0;
-    if ( bits256_nonz(privkey) != 0 )
+    struct LP_refund_args R; struct LP_spend_args S;
+    if ( ifpath == 0 ) // ifpath == 1 -> if path, 0 -> else path
     {
-        userdata[len++] = sizeof(privkey);
-        for (i=0; i<sizeof(privkey); i++)
-            userdata[len++] = privkey.bytes[i];
+        S.privkey = privkey;
+        return(LP_swapops_spenddata(userdata,&S));
     }
-    userdata[len++] = 0x51 * ifpath; // ifpath == 1 -> if path, 0 -> else path
-    return(len);
+    memset(&R,0,sizeof(R));
+    R.privkey = privkey;
+    if ( (R.uniform= basilisk_uniformscript(redeemscript,redeemlen)) != 0 && bits256_nonz(privkey) == 0 )
+        OS_randombytes(R.filler.bytes,sizeof(R.filler));
+    return(LP_swapops_refunddata(userdata,&R));
 }
 
 /*Bob paytx:
@@ -2745,6 +2990,7 @@ int32_t basilisk_bobscripts_set(struct basilisk_swap *swap,int32_t depositflag,i
                         return(-1);
                     }
                     LP_unspents_mark(coin->symbol,swap->bobpayment.vins);
+                    LP_utxolocks_add(coin->symbol,swap->bobpayment.vins,swap->I.req.requestid,swap->I.req.quoteid,swap->I.started+swap->I.putduration+swap->I.callduration);
                     //printf("bobscripts set completed\n");
                     return(0);
                 }
@@ -2778,6 +3024,7 @@ int32_t basilisk_bobscripts_set(struct basilisk_swap *swap,int32_t depositflag,i
                         return(-1);
                     }
                     LP_unspents_mark(coin->symbol,swap->bobdeposit.vins);
+                    LP_utxolocks_add(coin->symbol,swap->bobdeposit.vins,swap->I.req.requestid,swap->I.req.quoteid,swap->I.started+swap->I.putduration+swap->I.callduration);
                     //printf("bobscripts set completed\n");
                     return(0);
                 }
@@ -2787,10 +3034,58 @@ int32_t basilisk_bobscripts_set(struct basilisk_swap *swap,int32_t depositflag,i
     return(0);
 }
 
+// burned dexfee: the whole fee sits in an unspendable OP_RETURN output tagged with the swap ids
+int32_t LP_burnscript(uint8_t *script,struct basilisk_swap *swap)
+{
+    int32_t len = 0;
+    script[len++] = SCRIPT_OP_RETURN;
+    script[len++] = 12;
+    memcpy(&script[len],"burn",4), len += 4;
+    len += iguana_rwnum(1,&script[len],sizeof(swap->I.req.requestid),&swap->I.req.requestid);
+    len += iguana_rwnum(1,&script[len],sizeof(swap->I.req.quoteid),&swap->I.req.quoteid);
+    return(len);
+}
+
+// the counterparty sent a maker first payment, it has to be the script we would have made. its locktime can be off by as
+// much as the started times were allowed to differ in LP_pubkeys_locktimes
+int32_t basilisk_makerfirst_scriptcheck(struct basilisk_swap *swap,struct basilisk_rawtx *rawtx,int32_t takerflag)
+{
+    uint8_t redeemscript[1024]; int32_t diff,redeemlen; uint32_t locktime;
+    if ( (locktime= basilisk_cltvscript(rawtx->redeemscript,rawtx->I.redeemlen)) == 0 )
+    {
+        printf("%s is not a maker first script\n",rawtx->name);
+        return(-1);
+    }
+    diff = (int32_t)(locktime - basilisk_makerfirst_locktime(&swap->I,takerflag));
+    if ( diff > LP_LOCKTIME_TOLERANCE || diff < -LP_LOCKTIME_TOLERANCE )
+    {
+        printf("%s locktime %u vs %u outside tolerance %d\n",rawtx->name,locktime,basilisk_makerfirst_locktime(&swap->I,takerflag),LP_LOCKTIME_TOLERANCE);
+        return(-1);
+    }
+    redeemlen = basilisk_swap_makerfirstscript(takerflag,0,redeemscript,locktime,swap->I.pubA0,swap->I.pubB0,swap->I.pubB1,swap->I.privBn,swap->I.secretBn);
+    if ( redeemlen <= 0 || redeemlen != rawtx->I.redeemlen || memcmp(redeemscript,rawtx->redeemscript,redeemlen) != 0 )
+    {
+        printf("%s redeemscript doesnt match the negotiated maker first script\n",rawtx->name);
+        return(-1);
+    }
+    return(0);
+}
+
 void basilisk_alicepayment(struct basilisk_swap *swap,struct iguana_info *coin,struct basilisk_rawtx *alicepayment,bits256 pubAm,bits256 pubBn)
 {
-    char coinaddr[64];
-    alicepayment->I.spendlen = basilisk_alicescript(coin->symbol,alicepayment->redeemscript,&alicepayment->I.redeemlen,alicepayment->spendscript,0,alicepayment->I.destaddr,coin->taddr,coin->p2shtype,pubAm,pubBn);
+    char coinaddr[64]; int32_t redeemlen;
+    if ( swap->I.makerfirst != 0 )
+    {
+        alicepayment->I.spendlen = 0;
+        if ( (redeemlen= basilisk_swap_makerfirstscript(1,0,alicepayment->redeemscript,basilisk_makerfirst_locktime(&swap->I,1),swap->I.pubA0,swap->I.pubB0,swap->I.pubB1,swap->I.privBn,swap->I.secretBn)) > 0 )
+        {
+            alicepayment->I.redeemlen = redeemlen;
+            calc_rmd160_sha256(alicepayment->I.rmd160,alicepayment->redeemscript,redeemlen);
+            alicepayment->I.spendlen = bitcoin_p2shspend(alicepayment->spendscript,0,alicepayment->I.rmd160);
+            bitcoin_address(coin->symbol,alicepayment->I.destaddr,coin->taddr,coin->p2shtype,alicepayment->I.rmd160,20);
+        }
+    }
+    else alicepayment->I.spendlen = basilisk_alicescript(coin->symbol,alicepayment->redeemscript,&alicepayment->I.redeemlen,alicepayment->spendscript,0,alicepayment->I.destaddr,coin->taddr,coin->p2shtype,pubAm,pubBn);
     bitcoin_address(coin->symbol,coinaddr,coin->taddr,coin->pubtype,swap->changermd160,20);
     //printf("%s suppress.%d fee.%d\n",coinaddr,alicepayment->I.suppress_pubkeys,swap->myfee.I.suppress_pubkeys);
     basilisk_rawtx_gen(swap->ctx,"alicepayment",swap->I.started,swap->persistent_pubkey33,0,1,alicepayment,alicepayment->I.locktime,alicepayment->spendscript,alicepayment->I.spendlen,swap->I.Atxfee,1,0,swap->persistent_privkey,swap->changermd160,coinaddr);
@@ -2816,18 +3111,24 @@ int32_t basilisk_alicetxs(int32_t pairsock,struct basilisk_swap *swap,uint8_t *d
             //    printf("%02x",swap->alicepayment.txbytes[i]);
             //printf(" ALICE PAYMENT created.(%s)\n",swap->alicepayment.I.destaddr);
             LP_unspents_mark(coin->symbol,swap->alicepayment.vins);
+            LP_utxolocks_add(coin->symbol,swap->alicepayment.vins,swap->I.req.requestid,swap->I.req.quoteid,swap->I.started+swap->I.putduration+swap->I.callduration);
             //LP_importaddress(coin->symbol,swap->alicepayment.I.destaddr);
             //basilisk_txlog(swap,&swap->alicepayment,-1);
         }
+        if ( swap->I.internal != 0 )
+            return(swap->alicepayment.I.datalen != 0 && swap->alicepayment.I.spendlen > 0 ? 0 : -1);
         if ( swap->myfee.I.datalen == 0 )
         {
             //printf("%s generate fee %.8f from.%s\n",coin->symbol,dstr(strcmp(coin->symbol,"BTC") == 0 ? LP_MIN_TXFEE : coin->txfee),coin->smartaddr);
             bitcoin_address(coin->symbol,coinaddr,coin->taddr,coin->pubtype,swap->changermd160,20);
+            if ( swap->I.burnfee != 0 )
+                swap->myfee.I.spendlen = LP_burnscript(swap->myfee.spendscript,swap);
             if ( basilisk_rawtx_gen(swap->ctx,"myfee",swap->I.started,swap->persistent_pubkey33,swap->I.iambob,1,&swap->myfee,swap->myfee.I.locktime,swap->myfee.spendscript,swap->myfee.I.spendlen,strcmp(coin->symbol,"BTC") == 0 ? LP_MIN_TXFEE : coin->txfee,1,0,swap->persistent_privkey,swap->changermd160,coinaddr) == 0 )
             {
                 //printf("rawtxsend %s %.8f\n",coin->symbol,dstr(strcmp(coin->symbol,"BTC") == 0 ? LP_MIN_TXFEE : coin->txfee));
                 swap->I.statebits |= LP_swapdata_rawtxsend(pairsock,swap,0x80,data,maxlen,&swap->myfee,0x40,0);
                 LP_unspents_mark(swap->I.iambob!=0?coin->symbol:coin->symbol,swap->myfee.vins);
+                LP_utxolocks_add(coin->symbol,swap->myfee.vins,swap->I.req.requestid,swap->I.req.quoteid,swap->I.started+swap->I.putduration+swap->I.callduration);
                 //basilisk_txlog(swap,&swap->myfee,-1);
                 //int32_t i; for (i=0; i<swap->myfee.I.datalen; i++)
                 //    printf("%02x",swap->myfee.txbytes[i]);
@@ -2851,7 +3152,7 @@ int32_t basilisk_alicetxs(int32_t pairsock,struct basilisk_swap *swap,uint8_t *d
 
 int32_t LP_verify_otherfee(struct basilisk_swap *swap,uint8_t *data,int32_t datalen)
 {
-    int32_t diff; char bobstr[65],bobtomic[128],alicestr[65],alicetomic[128]; struct iguana_info *coin;
+    int32_t diff,burnlen; uint8_t burnscript[64]; char bobstr[65],bobtomic[128],alicestr[65],alicetomic[128]; struct iguana_info *coin;
     LP_etomicsymbol(bobstr,bobtomic,swap->I.bobstr);
     LP_etomicsymbol(alicestr,alicetomic,swap->I.alicestr);
     if ( (coin= LP_coinfind(swap->I.iambob != 0 ? alicestr : bobstr)) != 0 )
@@ -2859,7 +3160,8 @@ int32_t LP_verify_otherfee(struct basilisk_swap *swap,uint8_t *data,int32_t data
         if ( LP_rawtx_spendscript(swap,coin->longestchain,&swap->otherfee,0,data,datalen,0) == 0 )
         {
             //printf("otherfee amount %.8f -> %s vs %s locktime %u vs %u\n",dstr(swap->otherfee.I.amount),swap->otherfee.p2shaddr,swap->otherfee.I.destaddr,swap->otherfee.I.locktime,swap->I.started+1);
-            if ( strcmp(swap->otherfee.I.destaddr,swap->otherfee.p2shaddr) == 0 )
+            burnlen = LP_burnscript(burnscript,swap);
+            if ( strcmp(swap->otherfee.I.destaddr,swap->otherfee.p2shaddr) == 0 || (swap->I.burnfee != 0 && swap->otherfee.I.spendlen == burnlen && memcmp(swap->otherfee.spendscript,burnscript,burnlen) == 0) )
             {
                 diff = swap->otherfee.I.locktime - (swap->I.started+1);
                 if ( diff < 0 )
@@ -2871,7 +3173,7 @@ int32_t LP_verify_otherfee(struct basilisk_swap *swap,uint8_t *data,int32_t data
                 else printf("locktime mismatch in otherfee, reject %u vs %u\n",swap->otherfee.I.locktime,swap->I.started+1);
 #ifndef NOTETOMIC
                 if (swap->otherfee.I.ethTxid[0] != 0 && LP_etomic_is_empty_tx_id(swap->otherfee.I.ethTxid) == 0) {
-                    if (LP_etomic_wait_for_confirmation(swap->otherfee.I.ethTxid) < 0 || LP_etomic_verify_alice_fee(swap) == 0) {
+                    if (LP_etomic_wait_for_confirmation(swap->I.alicestr,swap->otherfee.I.ethTxid) < 0 || LP_etomic_verify_alice_fee(swap) == 0) {
                         return(-1);
                     }
                 }
@@ -2941,8 +3243,8 @@ int32_t LP_verify_bobdeposit(struct basilisk_swap *swap,uint8_t *data,int32_t da
             //printf(" <- bobdeposit redeem %d %s suppress.%d\n",i,swap->bobdeposit.I.destaddr,swap->aliceclaim.I.suppress_pubkeys);
             memcpy(swap->aliceclaim.redeemscript,swap->bobdeposit.redeemscript,swap->bobdeposit.I.redeemlen);
             swap->aliceclaim.I.redeemlen = swap->bobdeposit.I.redeemlen;
-            memcpy(swap->aliceclaim.I.pubkey33,swap->persistent_pubkey33,33);
-            bitcoin_address(coin->symbol,swap->aliceclaim.I.destaddr,coin->taddr,coin->pubtype,swap->persistent_pubkey33,33);
+            memcpy(swap->aliceclaim.I.pubkey33,swap->claimpub33,33);
+            bitcoin_address(coin->symbol,swap->aliceclaim.I.destaddr,coin->taddr,coin->pubtype,swap->claimpub33,33);
             retval = 0;
             if ( (retval= basilisk_rawtx_sign(coin->symbol,coin->wiftaddr,coin->taddr,coin->pubtype,coin->p2shtype,coin->isPoS,coin->wiftype,swap,&swap->aliceclaim,&swap->bobdeposit,swap->I.myprivs[0],0,userdata,len,1,swap->changermd160,swap->bobdeposit.I.destaddr,coin->zcash)) == 0 )
             {
@@ -2955,7 +3257,7 @@ int32_t LP_verify_bobdeposit(struct basilisk_swap *swap,uint8_t *data,int32_t da
                 //basilisk_txlog(swap,&swap->aliceclaim,swap->I.putduration+swap->I.callduration);
 #ifndef NOTETOMIC
                 if (swap->bobdeposit.I.ethTxid[0] != 0 && LP_etomic_is_empty_tx_id(swap->bobdeposit.I.ethTxid) == 0) {
-                    if (LP_etomic_wait_for_confirmation(swap->bobdeposit.I.ethTxid) < 0 || LP_etomic_verify_bob_deposit(swap, swap->bobdeposit.I.ethTxid) == 0) {
+                    if (LP_etomic_wait_for_confirmation(swap->I.bobstr,swap->bobdeposit.I.ethTxid) < 0 || LP_etomic_verify_bob_deposit(swap, swap->bobdeposit.I.ethTxid) == 0) {
                         return(-1);
                     }
                 }
@@ -2976,6 +3278,8 @@ int32_t LP_verify_alicepayment(struct basilisk_swap *swap,uint8_t *data,int32_t
     {
         if ( LP_rawtx_spendscript(swap,coin->longestchain,&swap->alicepayment,0,data,datalen,0) == 0 )
         {
+            if ( swap->I.makerfirst != 0 && basilisk_makerfirst_scriptcheck(swap,&swap->alicepayment,1) < 0 )
+                return(-1);
             swap->bobspend.utxovout = 0;
             swap->bobspend.utxotxid = swap->alicepayment.I.signedtxid = LP_broadcast_tx(swap->alicepayment.name,coin->symbol,swap->alicepayment.txbytes,swap->alicepayment.I.datalen);
             bitcoin_address(coin->symbol,swap->alicepayment.p2shaddr,coin->taddr,coin->p2shtype,swap->alicepayment.redeemscript,swap->alicepayment.I.redeemlen);
@@ -3018,6 +3322,8 @@ int32_t LP_verify_bobpayment(struct basilisk_swap *swap,uint8_t *data,int32_t da
         memset(revAm.bytes,0,sizeof(revAm));
         if ( LP_rawtx_spendscript(swap,coin->longestchain,&swap->bobpayment,0,data,datalen,0) == 0 )
         {
+            if ( swap->I.makerfirst != 0 && basilisk_makerfirst_scriptcheck(swap,&swap->bobpayment,0) < 0 )
+                return(-1);
             swap->alicespend.utxovout = 0;
             swap->alicespend.utxotxid = swap->bobpayment.I.signedtxid = LP_broadcast_tx(swap->bobpayment.name,coin->symbol,swap->bobpayment.txbytes,swap->bobpayment.I.datalen);
             if ( bits256_nonz(swap->bobpayment.I.signedtxid) != 0 )
@@ -3039,12 +3345,14 @@ int32_t LP_verify_bobpayment(struct basilisk_swap *swap,uint8_t *data,int32_t da
             memcpy(swap->I.userdata_alicespend,userdata,len);
             swap->I.userdata_alicespendlen = len;
             retval = 0;
-            memcpy(swap->alicespend.I.pubkey33,swap->persistent_pubkey33,33);
-            bitcoin_address(coin->symbol,swap->alicespend.I.destaddr,coin->taddr,coin->pubtype,swap->persistent_pubkey33,33);
+            memcpy(swap->alicespend.I.pubkey33,swap->claimpub33,33);
+            bitcoin_address(coin->symbol,swap->alicespend.I.destaddr,coin->taddr,coin->pubtype,swap->claimpub33,33);
             //char str[65],str2[65]; printf("bobpaid privAm.(%s) myprivs[0].(%s)\n",bits256_str(str,swap->I.privAm),bits256_str(str2,swap->I.myprivs[0]));
+            if ( swap->I.makerfirst != 0 ) // claiming needs privBn, basilisk_remember makes alicespend once bobspend reveals it
+                return(LP_waitmempool(coin->symbol,swap->bobpayment.I.destaddr,swap->bobpayment.I.signedtxid,0,60));
 #ifndef NOTETOMIC
             if (swap->bobpayment.I.ethTxid[0] != 0 && LP_etomic_is_empty_tx_id(swap->bobpayment.I.ethTxid) == 0) {
-                if (LP_etomic_wait_for_confirmation(swap->bobpayment.I.ethTxid) < 0 || LP_etomic_verify_bob_payment(swap, swap->bobpayment.I.ethTxid) == 0) {
+                if (LP_etomic_wait_for_confirmation(swap->I.bobstr,swap->bobpayment.I.ethTxid) < 0 || LP_etomic_verify_bob_payment(swap, swap->bobpayment.I.ethTxid) == 0) {
                     return(-1);
                 }
             }
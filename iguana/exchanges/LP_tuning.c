@@ -0,0 +1,321 @@
+
+/******************************************************************************
+ * Copyright © 2014-2018 The SuperNET Developers.                             *
+ *                                                                            *
+ * See the AUTHORS, DEVELOPER-AGREEMENT and LICENSE files at                  *
+ * the top-level directory of this distribution for the individual copyright  *
+ * holder information and the developer policies on copyright and licensing.  *
+ *                                                                            *
+ * Unless otherwise agreed in a custom licensing agreement, no part of the    *
+ * SuperNET software, including this file may be copied, modified, propagated *
+ * or distributed except according to the terms contained in the LICENSE file *
+ *                                                                            *
+ * Removal or modification of this copyright notice is prohibited.            *
+ *                                                                            *
+ ******************************************************************************/
+//
+//  LP_tuning.c
+//  marketmaker
+//
+//  per coin swap history kept in DB/tuning.json: observed block interval, how long counterparty txs took to show up,
+//  timeouts and reorgs. confirmation polling, tx wait timeouts, the advertised step timeout and extra confirmations
+//  follow from it once there are enough samples. "autotune":0 at startup keeps recording but uses the static values
+//
+
+#define LP_MAXTUNING 64
+#define LP_TUNING_MINSAMPLES 5
+#define LP_TUNING_DECAY 20 // clean swaps that cancel one recorded timeout or extra confirmation
+#define LP_TUNING_MAXPAD 4
+#define LP_TUNING_FLUSHSECS 60 // confirmation waits only mark the table dirty, LP_swapsloop writes it out at most this often
+
+struct LP_cointuning { char symbol[16]; double blocksecs,waitsecs,waitmax; uint32_t lastheight,lastheighttime,numblocks,numwaits,timeouts,steptimeouts,reorgs,completed,failed,clean; int32_t confmargin; } LP_cointunings[LP_MAXTUNING];
+int32_t LP_numcointunings,LP_autotune = 1,LP_tuningdirty; uint32_t LP_tuningsaved;
+portable_mutex_t LP_tuningmutex;
+
+struct LP_cointuning *_LP_cointuning(char *symbol,int32_t createflag)
+{
+    int32_t i; struct LP_cointuning *tp;
+    if ( symbol == 0 || symbol[0] == 0 || strlen(symbol) >= sizeof(tp->symbol) )
+        return(0);
+    for (i=0; i<LP_numcointunings; i++)
+        if ( strcmp(LP_cointunings[i].symbol,symbol) == 0 )
+            return(&LP_cointunings[i]);
+    if ( createflag == 0 || LP_numcointunings >= LP_MAXTUNING )
+        return(0);
+    tp = &LP_cointunings[LP_numcointunings++];
+    memset(tp,0,sizeof(*tp));
+    strcpy(tp->symbol,symbol);
+    return(tp);
+}
+
+cJSON *LP_cointuning_json(struct LP_cointuning *tp)
+{
+    cJSON *item = cJSON_CreateObject();
+    jaddstr(item,"coin",tp->symbol);
+    jaddnum(item,"blocksecs",tp->blocksecs);
+    jaddnum(item,"numblocks",tp->numblocks);
+    jaddnum(item,"waitsecs",tp->waitsecs);
+    jaddnum(item,"waitmax",tp->waitmax);
+    jaddnum(item,"numwaits",tp->numwaits);
+    jaddnum(item,"timeouts",tp->timeouts);
+    jaddnum(item,"steptimeouts",tp->steptimeouts);
+    jaddnum(item,"reorgs",tp->reorgs);
+    jaddnum(item,"confmargin",tp->confmargin);
+    jaddnum(item,"completed",tp->completed);
+    jaddnum(item,"failed",tp->failed);
+    jaddnum(item,"clean",tp->clean);
+    return(item);
+}
+
+void _LP_tuning_save()
+{
+    FILE *fp; char fname[512],tmpname[512],*str; int32_t i; cJSON *array = cJSON_CreateArray();
+    for (i=0; i<LP_numcointunings; i++)
+        jaddi(array,LP_cointuning_json(&LP_cointunings[i]));
+    sprintf(fname,"%s/tuning.json",GLOBAL_DBDIR), OS_compatible_path(fname);
+    sprintf(tmpname,"%s.tmp",fname);
+    if ( (fp= fopen(tmpname,"wb")) != 0 )
+    {
+        str = jprint(array,0);
+        fprintf(fp,"%s\n",str);
+        free(str);
+        fclose(fp);
+        if ( OS_renamefile(tmpname,fname) < 0 )
+            printf("tuning: cant rename %s\n",tmpname);
+    }
+    free_json(array);
+    LP_tuningdirty = 0;
+    LP_tuningsaved = (uint32_t)time(NULL);
+}
+
+void LP_tuning_flush()
+{
+    portable_mutex_lock(&LP_tuningmutex);
+    if ( LP_tuningdirty != 0 && time(NULL) >= LP_tuningsaved + LP_TUNING_FLUSHSECS )
+        _LP_tuning_save();
+    portable_mutex_unlock(&LP_tuningmutex);
+}
+
+void LP_tuning_init(cJSON *argjson)
+{
+    char fname[512],*fstr; long fsize; cJSON *array,*item; int32_t i,n; struct LP_cointuning *tp;
+    if ( jobj(argjson,"autotune") != 0 )
+        LP_autotune = jint(argjson,"autotune");
+    sprintf(fname,"%s/tuning.json",GLOBAL_DBDIR), OS_compatible_path(fname);
+    if ( (fstr= OS_filestr(&fsize,fname)) == 0 )
+        return;
+    if ( (array= cJSON_Parse(fstr)) != 0 )
+    {
+        n = cJSON_GetArraySize(array);
+        for (i=0; i<n; i++)
+        {
+            item = jitem(array,i);
+            if ( (tp= _LP_cointuning(jstr(item,"coin"),1)) == 0 )
+                continue;
+            tp->blocksecs = jdouble(item,"blocksecs");
+            tp->numblocks = juint(item,"numblocks");
+            tp->waitsecs = jdouble(item,"waitsecs");
+            tp->waitmax = jdouble(item,"waitmax");
+            tp->numwaits = juint(item,"numwaits");
+            tp->timeouts = juint(item,"timeouts");
+            tp->steptimeouts = juint(item,"steptimeouts");
+            tp->reorgs = juint(item,"reorgs");
+            if ( (tp->confmargin= jint(item,"confmargin")) < 0 )
+                tp->confmargin = 0;
+            else if ( tp->confmargin > BASILISK_DEFAULT_MAXCONFIRMS )
+                tp->confmargin = BASILISK_DEFAULT_MAXCONFIRMS;
+            tp->completed = juint(item,"completed");
+            tp->failed = juint(item,"failed");
+            tp->clean = juint(item,"clean");
+        }
+        free_json(array);
+    }
+    free(fstr);
+    printf("autotune.%d loaded swap history for %d coins\n",LP_autotune,LP_numcointunings);
+}
+
+// called whenever a swap thread sees the chain height, a jump of k blocks over dt seconds is one sample of dt/k
+void LP_tuning_height(char *symbol,int32_t height)
+{
+    struct LP_cointuning *tp; uint32_t now = (uint32_t)time(NULL); double sample;
+    if ( height <= 0 )
+        return;
+    portable_mutex_lock(&LP_tuningmutex);
+    if ( (tp= _LP_cointuning(symbol,1)) != 0 && height != tp->lastheight )
+    {
+        if ( tp->lastheight != 0 && height > tp->lastheight && tp->lastheighttime != 0 && now < tp->lastheighttime + 3600 )
+        {
+            sample = (double)(now - tp->lastheighttime) / (height - tp->lastheight);
+            tp->blocksecs = tp->numblocks == 0 ? sample : (tp->blocksecs * 0.9 + sample * 0.1);
+            tp->numblocks++;
+        }
+        tp->lastheight = height;
+        tp->lastheighttime = now;
+    }
+    portable_mutex_unlock(&LP_tuningmutex);
+}
+
+// how long the counterparty took to get a tx to us, or that it never did
+void LP_tuning_waited(char *symbol,int32_t secs,int32_t timedout)
+{
+    struct LP_cointuning *tp;
+    portable_mutex_lock(&LP_tuningmutex);
+    if ( (tp= _LP_cointuning(symbol,1)) != 0 )
+    {
+        if ( timedout != 0 )
+            tp->timeouts++;
+        else
+        {
+            tp->waitsecs = tp->numwaits == 0 ? secs : (tp->waitsecs * 0.8 + secs * 0.2);
+            tp->waitmax *= 0.95;
+            if ( secs > tp->waitmax )
+                tp->waitmax = secs;
+            tp->numwaits++;
+        }
+        LP_tuningdirty = 1;
+    }
+    portable_mutex_unlock(&LP_tuningmutex);
+}
+
+void _LP_tuning_outcome(char *symbol,int32_t err,char *kind,int32_t takerpayment)
+{
+    struct LP_cointuning *tp;
+    if ( (tp= _LP_cointuning(symbol,1)) == 0 )
+        return;
+    if ( err == 0 )
+    {
+        tp->completed++;
+        if ( ++tp->clean >= LP_TUNING_DECAY )
+        {
+            tp->clean = 0;
+            if ( tp->timeouts > 0 )
+                tp->timeouts--;
+            if ( tp->steptimeouts > 0 )
+                tp->steptimeouts--;
+            if ( tp->confmargin > 0 )
+                tp->confmargin--;
+        }
+        return;
+    }
+    tp->failed++;
+    tp->clean = 0;
+    if ( kind != 0 && strcmp(kind,"NegotiationTimeout") == 0 )
+        tp->steptimeouts++;
+    else if ( kind != 0 && strcmp(kind,"TakerPaymentReorged") == 0 && takerpayment != 0 )
+    {
+        tp->reorgs++;
+        if ( tp->confmargin < BASILISK_DEFAULT_MAXCONFIRMS )
+            tp->confmargin++;
+    }
+}
+
+void LP_tuning_swapdone(char *bobstr,char *alicestr,int32_t err,char *kind)
+{
+    portable_mutex_lock(&LP_tuningmutex);
+    _LP_tuning_outcome(bobstr,err,kind,0);
+    _LP_tuning_outcome(alicestr,err,kind,1);
+    _LP_tuning_save();
+    portable_mutex_unlock(&LP_tuningmutex);
+}
+
+int32_t LP_tuning_pollinterval(char *symbol,int32_t defval)
+{
+    struct LP_cointuning *tp; int32_t interval = defval;
+    if ( LP_autotune == 0 )
+        return(defval);
+    portable_mutex_lock(&LP_tuningmutex);
+    if ( (tp= _LP_cointuning(symbol,0)) != 0 && tp->numblocks >= LP_TUNING_MINSAMPLES )
+    {
+        if ( (interval= tp->blocksecs / 6) < LP_CONFIRMPOLL_MIN )
+            interval = LP_CONFIRMPOLL_MIN;
+        else if ( interval > LP_CONFIRMPOLL_MAX )
+            interval = LP_CONFIRMPOLL_MAX;
+    }
+    portable_mutex_unlock(&LP_tuningmutex);
+    return(interval);
+}
+
+// each recent timeout pads the wait by a quarter, with no timeouts and enough samples it shrinks towards twice the slowest seen
+int32_t LP_tuning_waittimeout(char *symbol,int32_t waittimeout)
+{
+    struct LP_cointuning *tp; int32_t tuned = waittimeout;
+    if ( LP_autotune == 0 )
+        return(waittimeout);
+    portable_mutex_lock(&LP_tuningmutex);
+    if ( (tp= _LP_cointuning(symbol,0)) != 0 )
+    {
+        if ( tp->timeouts > 0 )
+            tuned = waittimeout + (waittimeout * MIN(tp->timeouts,LP_TUNING_MAXPAD)) / 4;
+        else if ( tp->numwaits >= LP_TUNING_MINSAMPLES && 2*tp->waitmax < waittimeout )
+        {
+            if ( (tuned= 2*tp->waitmax) < waittimeout/2 )
+                tuned = waittimeout/2;
+        }
+    }
+    portable_mutex_unlock(&LP_tuningmutex);
+    return(tuned);
+}
+
+// the step timeout is agreed with the counterparty, so it only ever grows from the configured one
+int32_t LP_tuning_steptimeout(char *bobstr,char *alicestr,int32_t steptimeout)
+{
+    struct LP_cointuning *tp; uint32_t n = 0;
+    if ( LP_autotune == 0 )
+        return(steptimeout);
+    portable_mutex_lock(&LP_tuningmutex);
+    if ( (tp= _LP_cointuning(bobstr,0)) != 0 )
+        n = tp->steptimeouts;
+    if ( (tp= _LP_cointuning(alicestr,0)) != 0 && tp->steptimeouts > n )
+        n = tp->steptimeouts;
+    portable_mutex_unlock(&LP_tuningmutex);
+    return(steptimeout + (steptimeout * MIN(n,LP_TUNING_MAXPAD)) / 4);
+}
+
+int32_t LP_tuning_confmargin(char *symbol)
+{
+    struct LP_cointuning *tp; int32_t margin = 0;
+    if ( LP_autotune == 0 )
+        return(0);
+    portable_mutex_lock(&LP_tuningmutex);
+    if ( (tp= _LP_cointuning(symbol,0)) != 0 )
+        margin = tp->confmargin;
+    portable_mutex_unlock(&LP_tuningmutex);
+    if ( margin < 0 )
+        margin = 0;
+    else if ( margin > BASILISK_DEFAULT_MAXCONFIRMS )
+        margin = BASILISK_DEFAULT_MAXCONFIRMS;
+    return(margin);
+}
+
+char *LP_tuning(char *symbol,int32_t resetflag)
+{
+    cJSON *retjson,*array,*item; int32_t i; struct LP_cointuning *tp; char coin[16];
+    retjson = cJSON_CreateObject();
+    array = cJSON_CreateArray();
+    portable_mutex_lock(&LP_tuningmutex);
+    for (i=0; i<LP_numcointunings; i++)
+    {
+        tp = &LP_cointunings[i];
+        if ( symbol != 0 && symbol[0] != 0 && strcmp(symbol,tp->symbol) != 0 )
+            continue;
+        if ( resetflag != 0 )
+        {
+            strcpy(coin,tp->symbol);
+            memset(tp,0,sizeof(*tp));
+            strcpy(tp->symbol,coin);
+        }
+        item = LP_cointuning_json(tp);
+        portable_mutex_unlock(&LP_tuningmutex);
+        jaddnum(item,"pollinterval",LP_tuning_pollinterval(tp->symbol,LP_CONFIRMPOLL_MIN));
+        jaddnum(item,"txwait_timeout",LP_tuning_waittimeout(tp->symbol,LP_txwait_timeout));
+        portable_mutex_lock(&LP_tuningmutex);
+        jaddi(array,item);
+    }
+    if ( resetflag != 0 )
+        _LP_tuning_save();
+    portable_mutex_unlock(&LP_tuningmutex);
+    jaddstr(retjson,"result","success");
+    jaddnum(retjson,"autotune",LP_autotune);
+    jadd(retjson,"coins",array);
+    return(jprint(retjson,1));
+}
@@ -162,6 +162,21 @@ uint32_t LP_allocated(bits256 txid,int32_t vout)
     return(duration);
 }
 
+// reserved for anyone but otherpub
+uint32_t LP_allocated_other(bits256 txid,int32_t vout,bits256 otherpub)
+{
+    struct LP_inuse_info *lp; uint32_t now,duration = 0;
+    now = (uint32_t)time(NULL);
+    portable_mutex_lock(&LP_inusemutex);
+    if ( (lp= _LP_inuse_find(txid,vout)) != 0 && (bits256_nonz(otherpub) == 0 || bits256_cmp(lp->otherpub,otherpub) != 0) )
+    {
+        if ( lp->expiration != 0 && now < lp->expiration )
+            duration = (lp->expiration - now);
+    }
+    portable_mutex_unlock(&LP_inusemutex);
+    return(duration);
+}
+
 void LP_unavailableset(bits256 txid,int32_t vout,uint32_t expiration,bits256 otherpub)
 {
     portable_mutex_lock(&LP_inusemutex);
@@ -176,6 +191,247 @@ void LP_availableset(bits256 txid,int32_t vout)
     portable_mutex_unlock(&LP_inusemutex);
 }
 
+struct LP_utxolock { bits256 txid; char symbol[16]; int32_t vout; uint32_t requestid,quoteid,expiration; } LP_utxolocks[LP_MAXUTXOLOCKS];
+int32_t LP_numutxolocks;
+
+cJSON *LP_utxolock_json(struct LP_utxolock *lp)
+{
+    cJSON *item = cJSON_CreateObject();
+    jaddstr(item,"coin",lp->symbol);
+    jaddbits256(item,"txid",lp->txid);
+    jaddnum(item,"vout",lp->vout);
+    jaddnum(item,"requestid",lp->requestid);
+    jaddnum(item,"quoteid",lp->quoteid);
+    jaddnum(item,"expiration",lp->expiration);
+    return(item);
+}
+
+cJSON *_LP_utxolocks_json()
+{
+    int32_t i; cJSON *array = cJSON_CreateArray();
+    for (i=0; i<LP_numutxolocks; i++)
+        jaddi(array,LP_utxolock_json(&LP_utxolocks[i]));
+    return(array);
+}
+
+// the table as written before schema version 2: raw struct LP_utxolock records
+cJSON *LP_utxolocks_legacy(uint8_t *data,int32_t datalen)
+{
+    struct LP_utxolock L; int32_t i; cJSON *array = cJSON_CreateArray();
+    for (i=0; i+sizeof(L)<=datalen; i+=sizeof(L))
+    {
+        memcpy(&L,&data[i],sizeof(L));
+        L.symbol[sizeof(L.symbol)-1] = 0;
+        jaddi(array,LP_utxolock_json(&L));
+    }
+    return(array);
+}
+
+// DB/utxolocks is {"version","locks"} with the locks as the utxolocks rpc shows them. it goes through a tmp file so a
+// crash mid-write leaves the previous table, not an empty one that lets the next swap spend inputs still in flight
+int32_t LP_utxolocks_write(cJSON *array)
+{
+    FILE *fp; char fname[512],tmpname[512],*str; cJSON *tableobj; int32_t n,retval = -1;
+    sprintf(fname,"%s/utxolocks",GLOBAL_DBDIR), OS_compatible_path(fname);
+    sprintf(tmpname,"%s.tmp",fname);
+    tableobj = cJSON_CreateObject();
+    jaddnum(tableobj,"version",LP_SCHEMA_VERSION);
+    jadd(tableobj,"locks",array);
+    str = jprint(tableobj,1);
+    if ( (fp= fopen(tmpname,"wb")) != 0 )
+    {
+        n = fprintf(fp,"%s\n",str);
+        if ( fclose(fp) == 0 && n > 0 )
+            retval = OS_renamefile(tmpname,fname) < 0 ? -1 : 0;
+    }
+    if ( retval < 0 )
+        printf("cant write %s, previous utxo locks left in place\n",fname);
+    free(str);
+    return(retval);
+}
+
+void _LP_utxolocks_save()
+{
+    LP_utxolocks_write(_LP_utxolocks_json());
+}
+
+// unexpired locks from array that arent in the table yet, the number added
+int32_t _LP_utxolocks_merge(cJSON *array)
+{
+    struct LP_utxolock L; cJSON *item; char *symbol; int32_t i,j,n = 0; uint32_t now = (uint32_t)time(NULL);
+    for (i=0; i<cJSON_GetArraySize(array) && LP_numutxolocks<LP_MAXUTXOLOCKS; i++)
+    {
+        item = jitem(array,i);
+        memset(&L,0,sizeof(L));
+        if ( (symbol= jstr(item,"coin")) == 0 || (L.expiration= juint(item,"expiration")) <= now )
+            continue;
+        safecopy(L.symbol,symbol,sizeof(L.symbol));
+        L.txid = jbits256(item,"txid");
+        L.vout = jint(item,"vout");
+        L.requestid = juint(item,"requestid");
+        L.quoteid = juint(item,"quoteid");
+        for (j=0; j<LP_numutxolocks; j++)
+            if ( LP_utxolocks[j].vout == L.vout && bits256_cmp(LP_utxolocks[j].txid,L.txid) == 0 && strcmp(LP_utxolocks[j].symbol,L.symbol) == 0 )
+                break;
+        if ( j == LP_numutxolocks )
+            LP_utxolocks[LP_numutxolocks++] = L, n++;
+    }
+    return(n);
+}
+
+// schema 2: the binary table becomes json
+int32_t LP_utxolocks_migrate()
+{
+    char fname[512],*fstr; long fsize; cJSON *tableobj; int32_t retval = 0;
+    sprintf(fname,"%s/utxolocks",GLOBAL_DBDIR), OS_compatible_path(fname);
+    if ( (fstr= OS_filestr(&fsize,fname)) == 0 )
+        return(0);
+    if ( (tableobj= cJSON_Parse(fstr)) != 0 )
+        free_json(tableobj);
+    else if ( LP_utxolocks_write(LP_utxolocks_legacy((uint8_t *)fstr,(int32_t)fsize - 1)) < 0 )
+        retval = -1;
+    free(fstr);
+    return(retval);
+}
+
+void _LP_utxolock_delete(int32_t i)
+{
+    if ( i < --LP_numutxolocks )
+        LP_utxolocks[i] = LP_utxolocks[LP_numutxolocks];
+    memset(&LP_utxolocks[LP_numutxolocks],0,sizeof(*LP_utxolocks));
+}
+
+void LP_utxolocks_load()
+{
+    char fname[512],*fstr; long fsize; cJSON *tableobj;
+    sprintf(fname,"%s/utxolocks",GLOBAL_DBDIR), OS_compatible_path(fname);
+    portable_mutex_lock(&LP_utxolockmutex);
+    if ( (fstr= OS_filestr(&fsize,fname)) != 0 )
+    {
+        if ( (tableobj= cJSON_Parse(fstr)) != 0 )
+        {
+            _LP_utxolocks_merge(jarray(0,tableobj,"locks"));
+            free_json(tableobj);
+            _LP_utxolocks_save(); // drops the expired ones
+        } else printf("%s is not a utxo lock table, left as is\n",fname);
+        free(fstr);
+    }
+    portable_mutex_unlock(&LP_utxolockmutex);
+    printf("loaded %d swap utxo locks\n",LP_numutxolocks);
+}
+
+// locked by a swap other than requestid/quoteid
+int32_t LP_utxolocked_other(char *symbol,bits256 txid,int32_t vout,uint32_t requestid,uint32_t quoteid)
+{
+    int32_t i,retval = 0; uint32_t now = (uint32_t)time(NULL);
+    portable_mutex_lock(&LP_utxolockmutex);
+    for (i=0; i<LP_numutxolocks; i++)
+    {
+        if ( LP_utxolocks[i].vout == vout && bits256_cmp(LP_utxolocks[i].txid,txid) == 0 && strcmp(LP_utxolocks[i].symbol,symbol) == 0 )
+        {
+            if ( LP_utxolocks[i].expiration > now && (LP_utxolocks[i].requestid != requestid || LP_utxolocks[i].quoteid != quoteid) )
+                retval = 1;
+            break;
+        }
+    }
+    portable_mutex_unlock(&LP_utxolockmutex);
+    return(retval);
+}
+
+int32_t LP_utxolocked(char *symbol,bits256 txid,int32_t vout)
+{
+    return(LP_utxolocked_other(symbol,txid,vout,0,0));
+}
+
+void LP_utxolocks_add(char *symbol,cJSON *vins,uint32_t requestid,uint32_t quoteid,uint32_t expiration)
+{
+    int32_t i,j,n; cJSON *item; bits256 txid; int32_t vout;
+    if ( vins == 0 || (n= cJSON_GetArraySize(vins)) <= 0 )
+        return;
+    portable_mutex_lock(&LP_utxolockmutex);
+    for (i=0; i<n; i++)
+    {
+        item = jitem(vins,i);
+        txid = jbits256(item,"txid");
+        vout = jint(item,"vout");
+        if ( bits256_nonz(txid) == 0 )
+            continue;
+        for (j=0; j<LP_numutxolocks; j++)
+            if ( LP_utxolocks[j].vout == vout && bits256_cmp(LP_utxolocks[j].txid,txid) == 0 && strcmp(LP_utxolocks[j].symbol,symbol) == 0 )
+                break;
+        if ( j == LP_numutxolocks )
+        {
+            if ( LP_numutxolocks >= LP_MAXUTXOLOCKS )
+            {
+                printf("LP_utxolocks_add overflow, cant lock %s/v%d\n",symbol,vout);
+                break;
+            }
+            LP_numutxolocks++;
+        }
+        memset(&LP_utxolocks[j],0,sizeof(LP_utxolocks[j]));
+        LP_utxolocks[j].txid = txid;
+        LP_utxolocks[j].vout = vout;
+        safecopy(LP_utxolocks[j].symbol,symbol,sizeof(LP_utxolocks[j].symbol));
+        LP_utxolocks[j].requestid = requestid;
+        LP_utxolocks[j].quoteid = quoteid;
+        LP_utxolocks[j].expiration = expiration;
+    }
+    _LP_utxolocks_save();
+    portable_mutex_unlock(&LP_utxolockmutex);
+}
+
+// unexpired locks from a swap backup that we dont have yet, the backup's version says which table format it carries
+int32_t LP_utxolocks_merge(uint8_t *data,int32_t datalen,uint32_t version)
+{
+    char *str; cJSON *tableobj = 0,*array; int32_t n = 0;
+    if ( version < 2 )
+        array = LP_utxolocks_legacy(data,datalen);
+    else
+    {
+        str = calloc(1,datalen + 1);
+        memcpy(str,data,datalen);
+        if ( (tableobj= cJSON_Parse(str)) != 0 )
+            array = jarray(0,tableobj,"locks");
+        else array = 0;
+        free(str);
+    }
+    if ( array != 0 )
+    {
+        portable_mutex_lock(&LP_utxolockmutex);
+        if ( (n= _LP_utxolocks_merge(array)) > 0 )
+            _LP_utxolocks_save();
+        portable_mutex_unlock(&LP_utxolockmutex);
+    }
+    if ( tableobj != 0 )
+        free_json(tableobj);
+    else if ( array != 0 )
+        free_json(array);
+    return(n);
+}
+
+void LP_utxolocks_release(uint32_t requestid,uint32_t quoteid)
+{
+    int32_t i,n = 0;
+    portable_mutex_lock(&LP_utxolockmutex);
+    for (i=LP_numutxolocks-1; i>=0; i--)
+    {
+        if ( LP_utxolocks[i].requestid == requestid && LP_utxolocks[i].quoteid == quoteid )
+            _LP_utxolock_delete(i), n++;
+    }
+    if ( n > 0 )
+        _LP_utxolocks_save();
+    portable_mutex_unlock(&LP_utxolockmutex);
+}
+
+cJSON *LP_utxolocks_json()
+{
+    cJSON *array;
+    portable_mutex_lock(&LP_utxolockmutex);
+    array = _LP_utxolocks_json();
+    portable_mutex_unlock(&LP_utxolockmutex);
+    return(array);
+}
+
 int32_t LP_maxvalue(uint64_t *values,int32_t n)
 {
     int32_t i,maxi = -1; uint64_t maxval = 0;
@@ -355,6 +611,56 @@ int32_t LP_address_minmax(int32_t iambob,uint64_t *medianp,uint64_t *minp,uint64
     return(m/2);
 }
 
+// listunspent total, less outpoints already committed to another swap (utxolocks) or reserved by a pending ordermatch.
+// what requestid/quoteid locked itself or reserved for otherpub is what this swap will spend, so it stays in the balance
+int64_t LP_spendable_balance(struct iguana_info *coin,char *coinaddr,uint32_t requestid,uint32_t quoteid,bits256 otherpub,int64_t *lockedp)
+{
+    cJSON *array,*item; bits256 txid,zero; int64_t value,balance = 0; int32_t i,n,vout,height;
+    *lockedp = 0;
+    memset(zero.bytes,0,sizeof(zero));
+    if ( (array= LP_listunspent(coin->symbol,coinaddr,zero,zero)) != 0 )
+    {
+        if ( (n= cJSON_GetArraySize(array)) > 0 )
+        {
+            for (i=0; i<n; i++)
+            {
+                item = jitem(array,i);
+                value = LP_listunspent_parseitem(coin,&txid,&vout,&height,item);
+                if ( LP_utxolocked_other(coin->symbol,txid,vout,requestid,quoteid) != 0 || LP_allocated_other(txid,vout,otherpub) != 0 )
+                    *lockedp += value;
+                else balance += value;
+            }
+        }
+        free_json(array);
+    }
+    return(balance);
+}
+
+// what this side of a swap of satoshis spends: bob funds deposit and payment, alice the dexfee and her payment, each plus the miner fees
+int64_t LP_swap_fundsneeded(struct iguana_info *coin,int64_t satoshis,int32_t iambob)
+{
+    int64_t txfee;
+    if ( (txfee= coin->txfee) < 10000 )
+        txfee = 10000;
+    if ( iambob != 0 )
+        return(LP_DEPOSITSATOSHIS(satoshis) + satoshis + 6*txfee);
+    else return(satoshis + LP_DEXFEE(satoshis) + 4*txfee);
+}
+
+char *LP_swap_fundscheck(struct iguana_info *coin,int64_t satoshis,int32_t iambob,uint32_t requestid,uint32_t quoteid,bits256 otherpub,int64_t *neededp,int64_t *availablep,int64_t *lockedp)
+{
+    *neededp = LP_swap_fundsneeded(coin,satoshis,iambob);
+    *availablep = *lockedp = 0;
+    if ( coin->etomic[0] != 0 ) // token and ETH balances are checked by the etomic calls themselves
+        return(0);
+    if ( (*availablep= LP_spendable_balance(coin,coin->smartaddr,requestid,quoteid,otherpub,lockedp)) < *neededp )
+    {
+        printf("%s needs %.8f for the swap, only %.8f spendable (%.8f locked by other swaps)\n",coin->symbol,dstr(*neededp),dstr(*availablep),dstr(*lockedp));
+        return("insufficient funds");
+    }
+    return(0);
+}
+
 int32_t LP_address_utxo_ptrs(struct iguana_info *coin,int32_t iambob,struct LP_address_utxo **utxos,int32_t max,struct LP_address *ap,char *coinaddr)
 {
     struct LP_address_utxo *up,*tmp; struct LP_transaction *tx; cJSON *txout,*sobj; int32_t i,n = 0;
@@ -1023,6 +1329,68 @@ int32_t LP_txheight(struct iguana_info *coin,bits256 txid)
     return(height);
 }
 
+// swap funding txs still in the mempool. their change must not fund another swap, a reorg or eviction of the parent
+// would take that unrelated swap down with it. allow_unconfirmed_swapchange turns the rule off
+struct LP_swapchange { bits256 txid; char symbol[16]; uint32_t requestid,quoteid,expiration; } LP_swapchanges[LP_MAXSWAPCHANGES];
+int32_t LP_numswapchanges,LP_swapchange_allow;
+
+void LP_swapchange_add(char *symbol,bits256 txid,uint32_t requestid,uint32_t quoteid,uint32_t expiration)
+{
+    int32_t i; uint32_t now = (uint32_t)time(NULL);
+    if ( LP_swapchange_allow != 0 || bits256_nonz(txid) == 0 )
+        return;
+    portable_mutex_lock(&LP_utxolockmutex);
+    for (i=LP_numswapchanges-1; i>=0; i--)
+        if ( LP_swapchanges[i].expiration < now || (bits256_cmp(LP_swapchanges[i].txid,txid) == 0 && strcmp(LP_swapchanges[i].symbol,symbol) == 0) )
+            LP_swapchanges[i] = LP_swapchanges[--LP_numswapchanges];
+    if ( LP_numswapchanges < LP_MAXSWAPCHANGES )
+    {
+        memset(&LP_swapchanges[LP_numswapchanges],0,sizeof(*LP_swapchanges));
+        LP_swapchanges[LP_numswapchanges].txid = txid;
+        safecopy(LP_swapchanges[LP_numswapchanges].symbol,symbol,sizeof(LP_swapchanges[LP_numswapchanges].symbol));
+        LP_swapchanges[LP_numswapchanges].requestid = requestid;
+        LP_swapchanges[LP_numswapchanges].quoteid = quoteid;
+        LP_swapchanges[LP_numswapchanges].expiration = expiration;
+        LP_numswapchanges++;
+    } else printf("LP_swapchange_add overflow, %s change not tracked\n",symbol);
+    portable_mutex_unlock(&LP_utxolockmutex);
+}
+
+// only applies to inputs picked by a swap thread, withdraw and the other wallet paths can spend the change as before
+int32_t LP_swapchange_blocked(struct iguana_info *coin,bits256 txid)
+{
+    struct basilisk_swap *swap = LP_threadswap; int32_t i,found = 0; char str[65];
+    if ( LP_swapchange_allow != 0 || swap == 0 || LP_numswapchanges == 0 )
+        return(0);
+    portable_mutex_lock(&LP_utxolockmutex);
+    for (i=0; i<LP_numswapchanges; i++)
+    {
+        if ( bits256_cmp(LP_swapchanges[i].txid,txid) == 0 && strcmp(LP_swapchanges[i].symbol,coin->symbol) == 0 )
+        {
+            if ( LP_swapchanges[i].requestid != swap->I.req.requestid || LP_swapchanges[i].quoteid != swap->I.req.quoteid )
+                found = 1;
+            break;
+        }
+    }
+    portable_mutex_unlock(&LP_utxolockmutex);
+    if ( found == 0 )
+        return(0);
+    if ( LP_txheight(coin,txid) > 0 )
+    {
+        portable_mutex_lock(&LP_utxolockmutex);
+        for (i=0; i<LP_numswapchanges; i++)
+            if ( bits256_cmp(LP_swapchanges[i].txid,txid) == 0 && strcmp(LP_swapchanges[i].symbol,coin->symbol) == 0 )
+            {
+                LP_swapchanges[i] = LP_swapchanges[--LP_numswapchanges];
+                break;
+            }
+        portable_mutex_unlock(&LP_utxolockmutex);
+        return(0);
+    }
+    printf("skip %s %s, unconfirmed change from another swap\n",coin->symbol,bits256_str(str,txid));
+    return(1);
+}
+
 int32_t LP_numconfirms(char *symbol,char *coinaddr,bits256 txid,int32_t vout,int32_t mempool)
 {
     struct iguana_info *coin; bits256 zero; int32_t ht,notarized,numconfirms = 100;
@@ -2,6 +2,21 @@
 #include <curl/curl.h>
 
 pthread_mutex_t sendTxMutex = PTHREAD_MUTEX_INITIALIZER;
+#ifdef _MSC_VER
+static __declspec(thread) char *ethRpcUrl;
+#else
+static __thread char *ethRpcUrl;
+#endif
+extern char *(*bitcoind_proxyfunc)(char *debugstr,char *url);
+
+static void etomic_setproxy(CURL *curl,char *url)
+{
+    char *proxy = 0;
+    if (bitcoind_proxyfunc != 0)
+        proxy = (*bitcoind_proxyfunc)("ETH", url);
+    if (proxy != 0 && proxy[0] != 0)
+        curl_easy_setopt(curl, CURLOPT_PROXY, proxy);
+}
 
 struct string {
     char *ptr;
@@ -71,6 +86,7 @@ char *send_post_json_request(char *request, char *url)
         curl_easy_setopt(curl, CURLOPT_WRITEFUNCTION, writefunc);
         curl_easy_setopt(curl, CURLOPT_WRITEDATA, &s);
         curl_easy_setopt(curl, CURLOPT_URL, url);
+        etomic_setproxy(curl, url);
         curl_easy_setopt(curl, CURLOPT_POSTFIELDS, request);
         /* Perform the request, res will get the return code */
         res = curl_easy_perform(curl);
@@ -104,6 +120,7 @@ char *send_get_json_request(char *url)
         curl_easy_setopt(curl, CURLOPT_WRITEFUNCTION, writefunc);
         curl_easy_setopt(curl, CURLOPT_WRITEDATA, &s);
         curl_easy_setopt(curl, CURLOPT_URL, url);
+        etomic_setproxy(curl, url);
         /* Perform the request, res will get the return code */
         res = curl_easy_perform(curl);
         /* Check for errors */
@@ -122,7 +139,7 @@ char *send_get_json_request(char *url)
     }
 }
 
-cJSON *sendRpcRequest(char *method, cJSON *params)
+cJSON *sendRpcRequestUrl(char *url, char *method, cJSON *params)
 {
     char* string;
     cJSON *request = cJSON_CreateObject();
@@ -133,7 +150,7 @@ cJSON *sendRpcRequest(char *method, cJSON *params)
     }
     cJSON_AddNumberToObject(request, "id", 1);
     string = cJSON_PrintUnformatted(request);
-    char* requestResult = send_post_json_request(string, ETOMIC_URL);
+    char* requestResult = send_post_json_request(string, url);
     free(string);
     cJSON_Delete(request);
     cJSON *result = parseEthRpcResponse(requestResult);
@@ -141,6 +158,25 @@ cJSON *sendRpcRequest(char *method, cJSON *params)
     return result;
 }
 
+// node the calls made from this thread go to, the caller selects the one of the coin it is working on
+void selectEthRpcUrl(char *url)
+{
+    ethRpcUrl = url;
+}
+
+char *getEthRpcUrl()
+{
+    if (ethRpcUrl == NULL || ethRpcUrl[0] == 0) {
+        return ETOMIC_URL;
+    }
+    return ethRpcUrl;
+}
+
+cJSON *sendRpcRequest(char *method, cJSON *params)
+{
+    return sendRpcRequestUrl(getEthRpcUrl(), method, params);
+}
+
 char* sendRawTxWaitConfirm(char* rawTx)
 {
     cJSON *params = cJSON_CreateArray();
@@ -184,6 +220,100 @@ char* sendRawTx(char* rawTx)
     return txId;
 }
 
+char* sendRawTxUrl(char *url, char* rawTx)
+{
+    cJSON *params = cJSON_CreateArray();
+    cJSON_AddItemToArray(params, cJSON_CreateString(rawTx));
+    cJSON *resultJson = sendRpcRequestUrl(url, "eth_sendRawTransaction", params);
+    cJSON_Delete(params);
+    char *txId = NULL;
+    if (resultJson != NULL && is_cJSON_String(resultJson) && resultJson->valuestring != NULL) {
+        char* tmp = resultJson->valuestring;
+        txId = (char *) malloc(strlen(tmp) + 1);
+        strcpy(txId, tmp);
+    }
+    cJSON_Delete(resultJson);
+    return txId;
+}
+
+int64_t getNonceUrl(char *url, char* address)
+{
+    cJSON *params = cJSON_CreateArray();
+    cJSON_AddItemToArray(params, cJSON_CreateString(address));
+    cJSON_AddItemToArray(params, cJSON_CreateString("pending"));
+    int64_t nonce = -1;
+    cJSON *nonceJson = sendRpcRequestUrl(url, "eth_getTransactionCount", params);
+    cJSON_Delete(params);
+    if (nonceJson != NULL && is_cJSON_String(nonceJson) && nonceJson->valuestring != NULL) {
+        nonce = (int64_t) strtol(nonceJson->valuestring, NULL, 0);
+    }
+    cJSON_Delete(nonceJson);
+    return nonce;
+}
+
+uint64_t getChainIdUrl(char *url)
+{
+    uint64_t result = 0;
+    cJSON *params = cJSON_CreateArray();
+    cJSON *chainIdJson = sendRpcRequestUrl(url, "eth_chainId", params);
+    cJSON_Delete(params);
+    if (chainIdJson != NULL && is_cJSON_String(chainIdJson) && chainIdJson->valuestring != NULL) {
+        result = (uint64_t) strtoull(chainIdJson->valuestring, NULL, 0);
+    }
+    cJSON_Delete(chainIdJson);
+    return result;
+}
+
+uint64_t getGasPriceUrl(char *url)
+{
+    uint64_t result = 0;
+    cJSON *params = cJSON_CreateArray();
+    cJSON *gasPriceJson = sendRpcRequestUrl(url, "eth_gasPrice", params);
+    cJSON_Delete(params);
+    if (gasPriceJson != NULL && is_cJSON_String(gasPriceJson) && gasPriceJson->valuestring != NULL) {
+        result = (uint64_t) strtoull(gasPriceJson->valuestring, NULL, 0);
+    }
+    cJSON_Delete(gasPriceJson);
+    return result;
+}
+
+char *getCodeUrl(char *url, char *address)
+{
+    cJSON *params = cJSON_CreateArray();
+    cJSON_AddItemToArray(params, cJSON_CreateString(address));
+    cJSON_AddItemToArray(params, cJSON_CreateString("latest"));
+    cJSON *codeJson = sendRpcRequestUrl(url, "eth_getCode", params);
+    cJSON_Delete(params);
+    char *code = NULL;
+    if (codeJson != NULL && is_cJSON_String(codeJson) && codeJson->valuestring != NULL) {
+        code = (char *) malloc(strlen(codeJson->valuestring) + 1);
+        strcpy(code, codeJson->valuestring);
+    }
+    cJSON_Delete(codeJson);
+    return code;
+}
+
+char *getContractAddressUrl(char *url, char *txId)
+{
+    cJSON *params = cJSON_CreateArray();
+    cJSON_AddItemToArray(params, cJSON_CreateString(txId));
+    cJSON *receiptJson = sendRpcRequestUrl(url, "eth_getTransactionReceipt", params);
+    cJSON_Delete(params);
+    char *address = NULL;
+    cJSON *addressJson = receiptJson != NULL ? cJSON_GetObjectItem(receiptJson, "contractAddress") : NULL;
+    cJSON *statusJson = receiptJson != NULL ? cJSON_GetObjectItem(receiptJson, "status") : NULL;
+    if (addressJson != NULL && is_cJSON_String(addressJson) && addressJson->valuestring != NULL) {
+        if (statusJson != NULL && is_cJSON_String(statusJson) && strcmp(statusJson->valuestring, "0x1") != 0) {
+            printf("contract creation tx %s receipt status failed\n", txId);
+        } else {
+            address = (char *) malloc(strlen(addressJson->valuestring) + 1);
+            strcpy(address, addressJson->valuestring);
+        }
+    }
+    cJSON_Delete(receiptJson);
+    return address;
+}
+
 int64_t getNonce(char* address)
 {
     // we should lock this mutex and unlock it only when transaction was already sent or failed.
@@ -336,6 +466,7 @@ uint64_t getGasPriceFromStation(uint8_t defaultOnErr)
         curl_easy_setopt(curl, CURLOPT_WRITEFUNCTION, writefunc);
         curl_easy_setopt(curl, CURLOPT_WRITEDATA, &s);
         curl_easy_setopt(curl, CURLOPT_URL, "https://ethgasstation.info/json/ethgasAPI.json");
+        etomic_setproxy(curl, "https://ethgasstation.info/json/ethgasAPI.json");
         /* Perform the request, res will get the return code */
         res = curl_easy_perform(curl);
         uint64_t result;
@@ -40,7 +40,16 @@ typedef struct
     uint8_t exists;
 } EthTxData;
 
+cJSON *sendRpcRequestUrl(char *url, char *method, cJSON *params);
+void selectEthRpcUrl(char *url);
+char *getEthRpcUrl();
 char *sendRawTx(char *rawTx);
+char *sendRawTxUrl(char *url, char *rawTx);
+int64_t getNonceUrl(char *url, char *address);
+uint64_t getChainIdUrl(char *url);
+uint64_t getGasPriceUrl(char *url);
+char *getCodeUrl(char *url, char *address);
+char *getContractAddressUrl(char *url, char *txId);
 char *sendRawTxWaitConfirm(char *rawTx);
 char *ethCall(char *to, const char *data);
 uint64_t estimateGas(char *from, char *to, const char *data);
@@ -5,6 +5,7 @@
 #include "etomiccurl.h"
 #include <iostream>
 #include <regex>
+#include <limits>
 #include <cpp-ethereum/libethcore/Common.h>
 #include <cpp-ethereum/libethcore/CommonJS.h>
 #include <cpp-ethereum/libethcore/TransactionBase.h>
@@ -534,6 +535,27 @@ char* aliceSpendsBobPayment(AliceSpendsBobPaymentInput input, BasicTxData txData
     return result;
 }
 
+char *deployContract(char *url, char *bytecode, char *privKey, int64_t gas, uint64_t gasPriceWei)
+{
+    char *from = privKey2Addr(privKey);
+    int64_t nonce = getNonceUrl(url, from);
+    free(from);
+    if (nonce < 0) {
+        return NULL;
+    }
+    TransactionSkeleton tx;
+    tx.creation = true;
+    tx.value = 0;
+    tx.gas = gas;
+    tx.gasPrice = gasPriceWei;
+    tx.nonce = nonce;
+    tx.data = jsToBytes(bytecode);
+    char *rawTx = signTx(tx, privKey);
+    char *result = sendRawTxUrl(url, rawTx);
+    free(rawTx);
+    return result;
+}
+
 char* privKey2Addr(char* privKey)
 {
     Secret secretKey(privKey);
@@ -678,7 +700,13 @@ uint64_t weiToSatoshi(char *wei, uint8_t decimals)
     if (decimals < 18) {
         satoshi = satoshi * boost::multiprecision::pow(u256(10), 18 - decimals);
     }
-    return static_cast<uint64_t>(satoshi / boost::multiprecision::pow(u256(10), 10));
+    satoshi /= boost::multiprecision::pow(u256(10), 10);
+    // a plain cast keeps only the low 64 bits, so a huge value could come back as any amount
+    if (satoshi > u256(std::numeric_limits<uint64_t>::max())) {
+        printf("weiToSatoshi %s with %d decimals doesnt fit in 64 bit satoshis\n", wei, decimals);
+        return 0;
+    }
+    return static_cast<uint64_t>(satoshi);
 }
 
 char *sendEth(char *to, char *amount, char *privKey, uint8_t waitConfirm, int64_t gas, int64_t gasPrice, uint8_t defaultGasOnErr)
@@ -817,37 +845,37 @@ uint8_t verifyAliceErc20FeeData(char *tokenAddress, char *to, char *amount, char
     return 1;
 }
 
-uint8_t alicePaymentStatus(char *paymentId)
+uint8_t alicePaymentStatus(char *contract, char *paymentId)
 {
     char buffer[100];
     memset(buffer, 0, sizeof(buffer));
     strcpy(buffer, "0x81cd872a");
     strcat(buffer, paymentId);
-    char *hexStatus = ethCall(ETOMIC_ALICECONTRACT, buffer);
+    char *hexStatus = ethCall(contract, buffer);
     auto status = (uint8_t) strtol(hexStatus + 66, NULL, 0);
     free(hexStatus);
     return status;
 }
 
-uint8_t bobDepositStatus(char *depositId)
+uint8_t bobDepositStatus(char *contract, char *depositId)
 {
     char buffer[100];
     memset(buffer, 0, sizeof(buffer));
     strcpy(buffer, "0x3d4dff7b");
     strcat(buffer, depositId);
-    char *hexStatus = ethCall(ETOMIC_BOBCONTRACT, buffer);
+    char *hexStatus = ethCall(contract, buffer);
     auto status = (uint8_t) strtol(hexStatus + 130, NULL, 0);
     free(hexStatus);
     return status;
 }
 
-uint8_t bobPaymentStatus(char *paymentId)
+uint8_t bobPaymentStatus(char *contract, char *paymentId)
 {
     char buffer[100];
     memset(buffer, 0, sizeof(buffer));
     strcpy(buffer, "0x0716326d");
     strcat(buffer, paymentId);
-    char *hexStatus = ethCall(ETOMIC_BOBCONTRACT, buffer);
+    char *hexStatus = ethCall(contract, buffer);
     auto status = (uint8_t) strtol(hexStatus + 130, NULL, 0);
     free(hexStatus);
     return status;
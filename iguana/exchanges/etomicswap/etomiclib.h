@@ -168,6 +168,9 @@ uint8_t verifyBobErc20PaymentData(BobSendsErc20PaymentInput input, char *data);
 char* bobReclaimsBobPayment(BobReclaimsBobPaymentInput input, BasicTxData txData);
 char* aliceSpendsBobPayment(AliceSpendsBobPaymentInput input, BasicTxData txData);
 
+// deploys contract creation bytecode to the node at url, returns tx id
+char *deployContract(char *url, char *bytecode, char *privKey, int64_t gas, uint64_t gasPriceWei);
+
 char* privKey2Addr(char* privKey);
 char* pubKey2Addr(char* pubKey);
 char* getPubKeyFromPriv(char* privKey);
@@ -201,9 +204,9 @@ char *sendErc20(
 
 uint8_t verifyAliceErc20FeeData(char* tokenAddress, char *to, char *amount, char *data, uint8_t decimals);
 
-uint8_t alicePaymentStatus(char *paymentId);
-uint8_t bobDepositStatus(char *depositId);
-uint8_t bobPaymentStatus(char *paymentId);
+uint8_t alicePaymentStatus(char *contract, char *paymentId);
+uint8_t bobDepositStatus(char *contract, char *depositId);
+uint8_t bobPaymentStatus(char *contract, char *paymentId);
 
 uint64_t estimate_erc20_gas(
         char *tokenAddress,
@@ -365,6 +365,7 @@ int main(int argc, const char * argv[])
         printf("couldnt write to (%s)\n",dirname);
         exit(0);
     }
+    sprintf(dirname,"%s/EXPORT",GLOBAL_DBDIR), OS_ensure_directory(dirname);
 #ifdef FROM_JS
     argc = 2;
     retjson = cJSON_Parse("{\"client\":1,\"passphrase\":\"test\"}");
@@ -32,6 +32,7 @@
 #include "DEXstats.h"
 char *stats_JSON(void *ctx,int32_t fastflag,char *myipaddr,int32_t mypubsock,cJSON *argjson,char *remoteaddr,uint16_t port);
 void LP_queuecommand(char **retstrp,char *buf,int32_t responsesock,int32_t stats_JSONonly,uint32_t queueid);
+char *LP_metrics_prometheus();
 extern uint32_t DOCKERFLAG;
 extern char LP_myipaddr[];
 
@@ -367,6 +368,12 @@ char *stats_rpcparse(char *retbuf,int32_t bufsize,int32_t *jsonflagp,int32_t *po
             return(clonestr("{\"error\":\"cant find index7779\"}"));
         else return(filestr);
     }
+    else if ( strcmp(&url[i],"/metrics") == 0 && strcmp(urlmethod,"GET") == 0 )
+    {
+        *jsonflagp = 1;
+        strcpy(filetype,"txt");
+        return(LP_metrics_prometheus());
+    }
     /*else if ( (filestr= OS_filestr(&filesize,furl)) != 0 ) allows arbitrary file access!
     {
         *jsonflagp = 1;
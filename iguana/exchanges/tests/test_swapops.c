@@ -0,0 +1,164 @@
+/******************************************************************************
+ * Copyright © 2014-2018 The SuperNET Developers.                             *
+ *                                                                            *
+ * See the AUTHORS, DEVELOPER-AGREEMENT and LICENSE files at                  *
+ * the top-level directory of this distribution for the individual copyright  *
+ * holder information and the developer policies on copyright and licensing.  *
+ *                                                                            *
+ * Unless otherwise agreed in a custom licensing agreement, no part of the    *
+ * SuperNET software, including this file may be copied, modified, propagated *
+ * or distributed except according to the terms contained in the LICENSE file *
+ *                                                                            *
+ * Removal or modification of this copyright notice is prohibited.            *
+ *                                                                            *
+ ******************************************************************************/
+//
+//  test_swapops.c
+//  marketmaker
+//
+//  builds every LP_swapops script with fixed keys and parses it back with the basilisk_ script checks that the swap and
+//  LP_remember use on the other side. the whole marketmaker is compiled in with its main renamed, so this is the same code
+//
+
+#define main marketmaker_main
+#include "../mm.c"
+#undef main
+
+int32_t Num_tests,Num_failed;
+
+#define TEST_CHECK(cond,name) do { Num_tests++; if ( !(cond) ) { Num_failed++; printf("FAIL %s: %s (%s:%d)\n",name,#cond,__FILE__,__LINE__); } } while ( 0 )
+
+bits256 test_bits256(uint8_t seed)
+{
+    bits256 x; int32_t i;
+    for (i=0; i<32; i++)
+        x.bytes[i] = seed + i;
+    return(x);
+}
+
+struct test_makercase
+{
+    char *name; int32_t depositflag,uniformflag,csvduration,makerfirst,takerflag;
+    uint32_t cltv,csv; int32_t uniform;
+};
+
+struct test_makercase Test_makercases[] =
+{
+    { "deposit",            1, 0, 0,    0, 0, 1, 0, 0 },
+    { "deposit uniform",    1, 1, 0,    0, 0, 1, 0, 0 }, // uniform spends only ever apply to the bobpayment
+    { "deposit csv",        1, 0, 3600, 0, 0, 0, 1, 0 },
+    { "deposit uniform csv",1, 1, 3600, 0, 0, 0, 1, 0 },
+    { "payment",            0, 0, 0,    0, 0, 1, 0, 0 },
+    { "payment uniform",    0, 1, 0,    0, 0, 1, 0, 1 },
+    { "payment csv",        0, 0, 3600, 0, 0, 0, 1, 0 },
+    { "payment uniform csv",0, 1, 3600, 0, 0, 0, 1, 1 },
+    { "makerfirst bob",     0, 0, 0,    1, 0, 1, 0, 0 },
+    { "makerfirst alice",   0, 0, 0,    1, 1, 1, 0, 0 },
+};
+
+void test_makerscripts(void)
+{
+    struct LP_makerpayment_args args; uint8_t redeemscript[512],secretAm[20],secretBn[20],*claim33; bits256 pubA0,pubB0,pubB1,zero;
+    int32_t i,j,n; uint32_t locktime = 1500000000,csvduration; struct test_makercase *tc;
+    pubA0 = test_bits256(1), pubB0 = test_bits256(2), pubB1 = test_bits256(3);
+    memset(zero.bytes,0,sizeof(zero));
+    memset(secretAm,0xa1,sizeof(secretAm)), memset(secretBn,0xb2,sizeof(secretBn));
+    for (i=0; i<sizeof(Test_makercases)/sizeof(*Test_makercases); i++)
+    {
+        tc = &Test_makercases[i];
+        csvduration = tc->csvduration;
+        if ( tc->makerfirst != 0 )
+            LP_swapops_makerfirstargs(&args,tc->takerflag,locktime,pubA0,pubB0,pubB1,zero,secretBn);
+        else LP_swapops_makerargs(&args,tc->depositflag,tc->uniformflag,locktime,csvduration,pubA0,pubB0,pubB1,zero,zero,secretAm,secretBn);
+        n = LP_swapops_makerscript(redeemscript,0,&args);
+        TEST_CHECK(n > 0 && n < sizeof(redeemscript),tc->name);
+        if ( n <= 0 )
+            continue;
+        TEST_CHECK(redeemscript[0] == SCRIPT_OP_IF && redeemscript[n-1] == SCRIPT_OP_ENDIF,tc->name);
+        TEST_CHECK((basilisk_cltvscript(redeemscript,n) == locktime) == tc->cltv,tc->name);
+        if ( tc->csv != 0 )
+            TEST_CHECK(basilisk_csvscript(redeemscript,n) == LP_swapops_csvsequence(csvduration),tc->name);
+        else TEST_CHECK(basilisk_csvscript(redeemscript,n) == 0,tc->name);
+        TEST_CHECK(basilisk_uniformscript(redeemscript,n) == tc->uniform,tc->name);
+        // the claim path hashes the secret the other side holds, and the deposit refund needs alice's as well
+        for (j=0; j+20<=n; j++)
+            if ( memcmp(&redeemscript[j],tc->depositflag != 0 || tc->makerfirst != 0 ? secretBn : secretAm,20) == 0 )
+                break;
+        TEST_CHECK(j+20 <= n,tc->name);
+        if ( tc->depositflag != 0 )
+        {
+            for (j=0; j+20<=n; j++)
+                if ( memcmp(&redeemscript[j],secretAm,20) == 0 )
+                    break;
+            TEST_CHECK(j+20 <= n,tc->name);
+        }
+        // OP_ELSE <claim secret160verify> <33 byte claim pubkey> OP_CHECKSIG OP_ENDIF
+        claim33 = &redeemscript[n - 35];
+        TEST_CHECK(claim33[-1] == 33 && claim33[33] == SCRIPT_OP_CHECKSIG,tc->name);
+        if ( tc->makerfirst != 0 && tc->takerflag != 0 )
+            TEST_CHECK(claim33[0] == 0x03 && memcmp(claim33+1,pubB0.bytes,32) == 0,tc->name);
+        else if ( tc->depositflag != 0 )
+            TEST_CHECK(claim33[0] == 0x03 && memcmp(claim33+1,pubB0.bytes,32) == 0,tc->name);
+        else TEST_CHECK(claim33[0] == 0x02 && memcmp(claim33+1,pubA0.bytes,32) == 0,tc->name);
+    }
+    // a missing pubkey or secret hash must not give a script anyone could spend
+    LP_swapops_makerargs(&args,0,0,locktime,0,zero,pubB0,pubB1,zero,zero,secretAm,secretBn);
+    TEST_CHECK(LP_swapops_makerscript(redeemscript,0,&args) < 0,"zero claimpub");
+    memset(secretAm,0,sizeof(secretAm));
+    LP_swapops_makerargs(&args,0,0,locktime,0,pubA0,pubB0,pubB1,zero,zero,secretAm,secretBn);
+    TEST_CHECK(LP_swapops_makerscript(redeemscript,0,&args) < 0,"zero claimsecret160");
+}
+
+void test_csvsequence(void)
+{
+    TEST_CHECK(LP_swapops_csvsequence(0) == (LP_CSV_TYPEFLAG | 1),"csv zero");
+    TEST_CHECK(LP_swapops_csvsequence(512) == (LP_CSV_TYPEFLAG | 1),"csv one unit");
+    TEST_CHECK(LP_swapops_csvsequence(513) == (LP_CSV_TYPEFLAG | 2),"csv rounds up");
+    TEST_CHECK(LP_swapops_csvsequence(0xffffffff) == (LP_CSV_TYPEFLAG | 0xffff),"csv clamps");
+}
+
+void test_takerscript(void)
+{
+    struct LP_takerpayment_args args; uint8_t redeemscript[512],p2sh160[20],rmd160[20]; int32_t n,redeemlen = 0;
+    args.pubAm = test_bits256(4), args.pubBn = test_bits256(5);
+    n = LP_swapops_takerscript(redeemscript,&redeemlen,p2sh160,&args);
+    TEST_CHECK(n == redeemlen && n == 1 + 2*34 + 2,"takerscript len");
+    TEST_CHECK(redeemscript[0] == SCRIPT_OP_2 && redeemscript[n-2] == SCRIPT_OP_2 && redeemscript[n-1] == SCRIPT_OP_CHECKMULTISIG,"takerscript 2of2");
+    TEST_CHECK(redeemscript[2] == 0x02 && memcmp(&redeemscript[3],args.pubAm.bytes,32) == 0,"takerscript pubAm");
+    TEST_CHECK(redeemscript[36] == 0x03 && memcmp(&redeemscript[37],args.pubBn.bytes,32) == 0,"takerscript pubBn");
+    TEST_CHECK(basilisk_cltvscript(redeemscript,n) == 0 && basilisk_csvscript(redeemscript,n) == 0 && basilisk_uniformscript(redeemscript,n) == 0,"takerscript no locks");
+    calc_rmd160_sha256(rmd160,redeemscript,n);
+    TEST_CHECK(memcmp(rmd160,p2sh160,20) == 0,"takerscript p2sh");
+}
+
+void test_spenddata(void)
+{
+    struct LP_spend_args spend; struct LP_refund_args refund; uint8_t userdata[128]; int32_t len;
+    spend.privkey = test_bits256(6);
+    len = LP_swapops_spenddata(userdata,&spend);
+    TEST_CHECK(len == 34 && userdata[0] == 32 && memcmp(&userdata[1],spend.privkey.bytes,32) == 0 && userdata[33] == 0,"spenddata secret");
+    memset(spend.privkey.bytes,0,sizeof(spend.privkey));
+    len = LP_swapops_spenddata(userdata,&spend);
+    TEST_CHECK(len == 1 && userdata[0] == 0,"spenddata nosecret");
+    memset(&refund,0,sizeof(refund));
+    refund.privkey = test_bits256(7);
+    len = LP_swapops_refunddata(userdata,&refund);
+    TEST_CHECK(len == 34 && userdata[0] == 32 && memcmp(&userdata[1],refund.privkey.bytes,32) == 0 && userdata[33] == 0x51,"refunddata deposit");
+    memset(&refund,0,sizeof(refund));
+    refund.uniform = 1, refund.filler = test_bits256(8);
+    len = LP_swapops_refunddata(userdata,&refund);
+    TEST_CHECK(len == 34 && userdata[0] == 32 && memcmp(&userdata[1],refund.filler.bytes,32) == 0 && userdata[33] == 0x51,"refunddata uniform");
+    memset(&refund,0,sizeof(refund));
+    len = LP_swapops_refunddata(userdata,&refund);
+    TEST_CHECK(len == 1 && userdata[0] == 0x51,"refunddata payment");
+}
+
+int main(int argc,const char *argv[])
+{
+    test_csvsequence();
+    test_makerscripts();
+    test_takerscript();
+    test_spenddata();
+    printf("%d of %d swapops checks failed\n",Num_failed,Num_tests);
+    return(Num_failed != 0);
+}
@@ -0,0 +1,99 @@
+/******************************************************************************
+ * Copyright © 2014-2018 The SuperNET Developers.                             *
+ *                                                                            *
+ * See the AUTHORS, DEVELOPER-AGREEMENT and LICENSE files at                  *
+ * the top-level directory of this distribution for the individual copyright  *
+ * holder information and the developer policies on copyright and licensing.  *
+ *                                                                            *
+ * Unless otherwise agreed in a custom licensing agreement, no part of the    *
+ * SuperNET software, including this file may be copied, modified, propagated *
+ * or distributed except according to the terms contained in the LICENSE file *
+ *                                                                            *
+ * Removal or modification of this copyright notice is prohibited.            *
+ *                                                                            *
+ ******************************************************************************/
+//
+//  test_swapstates.c
+//  marketmaker
+//
+//  walks LP_maker_next/LP_taker_next from the pubkeys state for every makerfirst/internal combination and checks the
+//  exact path to DONE, then that an error in any state on the path fails the swap and that DONE and FAILED stay put
+//
+
+#define main marketmaker_main
+#include "../mm.c"
+#undef main
+
+int32_t Num_tests,Num_failed;
+
+#define TEST_CHECK(cond,name) do { Num_tests++; if ( !(cond) ) { Num_failed++; printf("FAIL %s: %s (%s:%d)\n",name,#cond,__FILE__,__LINE__); } } while ( 0 )
+
+#define TEST_MAXSTATES 32
+
+struct test_makerpath { char *name; int32_t makerfirst,internal; enum LP_makerstate path[TEST_MAXSTATES]; };
+
+struct test_takerpath { char *name; int32_t makerfirst,internal; enum LP_takerstate path[TEST_MAXSTATES]; };
+
+struct test_makerpath Test_makerpaths[] =
+{
+    { "maker", 0, 0, { LP_MAKER_PUBKEYS, LP_MAKER_DUSTCHECK, LP_MAKER_CHOOSEI, LP_MAKER_MOSTPRIVS, LP_MAKER_DEPOSITSCRIPTS, LP_MAKER_WAITFEE, LP_MAKER_FEECONFIRM, LP_MAKER_COMMIT, LP_MAKER_SENDDEPOSIT, LP_MAKER_DEPOSITCONFIRM, LP_MAKER_WAITALICEPAYMENT, LP_MAKER_PAYMENTSCRIPTS, LP_MAKER_ALICECONFIRM, LP_MAKER_SENDPAYMENT, LP_MAKER_DONE } },
+    { "maker internal", 0, 1, { LP_MAKER_PUBKEYS, LP_MAKER_DUSTCHECK, LP_MAKER_CHOOSEI, LP_MAKER_MOSTPRIVS, LP_MAKER_DEPOSITSCRIPTS, LP_MAKER_COMMIT, LP_MAKER_SENDDEPOSIT, LP_MAKER_DEPOSITCONFIRM, LP_MAKER_WAITALICEPAYMENT, LP_MAKER_PAYMENTSCRIPTS, LP_MAKER_ALICECONFIRM, LP_MAKER_SENDPAYMENT, LP_MAKER_DONE } },
+    { "maker makerfirst", 1, 0, { LP_MAKER_PUBKEYS, LP_MAKER_DUSTCHECK, LP_MAKER_CHOOSEI, LP_MAKER_MOSTPRIVS, LP_MAKER_MF_SCRIPTS, LP_MAKER_WAITFEE, LP_MAKER_FEECONFIRM, LP_MAKER_COMMIT, LP_MAKER_MF_SENDPAYMENT, LP_MAKER_MF_PAYMENTCONFIRM, LP_MAKER_MF_WAITALICEPAYMENT, LP_MAKER_MF_ALICECONFIRM, LP_MAKER_DONE } },
+    { "maker makerfirst internal", 1, 1, { LP_MAKER_PUBKEYS, LP_MAKER_DUSTCHECK, LP_MAKER_CHOOSEI, LP_MAKER_MOSTPRIVS, LP_MAKER_MF_SCRIPTS, LP_MAKER_COMMIT, LP_MAKER_MF_SENDPAYMENT, LP_MAKER_MF_PAYMENTCONFIRM, LP_MAKER_MF_WAITALICEPAYMENT, LP_MAKER_MF_ALICECONFIRM, LP_MAKER_DONE } },
+};
+
+struct test_takerpath Test_takerpaths[] =
+{
+    { "taker", 0, 0, { LP_TAKER_PUBKEYS, LP_TAKER_DUSTCHECK, LP_TAKER_CHOOSEI, LP_TAKER_MOSTPRIVS, LP_TAKER_COMMIT, LP_TAKER_ALICETXS, LP_TAKER_SENDFEE, LP_TAKER_WAITDEPOSIT, LP_TAKER_DEPOSITCONFIRM, LP_TAKER_SENDPAYMENT, LP_TAKER_PAYMENTCONFIRM, LP_TAKER_WAITBOBPAYMENT, LP_TAKER_BOBCONFIRM, LP_TAKER_DONE } },
+    { "taker internal", 0, 1, { LP_TAKER_PUBKEYS, LP_TAKER_DUSTCHECK, LP_TAKER_CHOOSEI, LP_TAKER_MOSTPRIVS, LP_TAKER_COMMIT, LP_TAKER_ALICETXS, LP_TAKER_WAITDEPOSIT, LP_TAKER_DEPOSITCONFIRM, LP_TAKER_SENDPAYMENT, LP_TAKER_PAYMENTCONFIRM, LP_TAKER_WAITBOBPAYMENT, LP_TAKER_BOBCONFIRM, LP_TAKER_DONE } },
+    { "taker makerfirst", 1, 0, { LP_TAKER_PUBKEYS, LP_TAKER_DUSTCHECK, LP_TAKER_CHOOSEI, LP_TAKER_MOSTPRIVS, LP_TAKER_COMMIT, LP_TAKER_ALICETXS, LP_TAKER_SENDFEE, LP_TAKER_MF_WAITBOBPAYMENT, LP_TAKER_MF_BOBCONFIRM, LP_TAKER_MF_SENDPAYMENT, LP_TAKER_MF_PAYMENTCONFIRM, LP_TAKER_DONE } },
+    { "taker makerfirst internal", 1, 1, { LP_TAKER_PUBKEYS, LP_TAKER_DUSTCHECK, LP_TAKER_CHOOSEI, LP_TAKER_MOSTPRIVS, LP_TAKER_COMMIT, LP_TAKER_ALICETXS, LP_TAKER_MF_WAITBOBPAYMENT, LP_TAKER_MF_BOBCONFIRM, LP_TAKER_MF_SENDPAYMENT, LP_TAKER_MF_PAYMENTCONFIRM, LP_TAKER_DONE } },
+};
+
+void test_makerpaths(void)
+{
+    struct test_makerpath *tp; int32_t i,j; enum LP_makerstate state;
+    for (i=0; i<sizeof(Test_makerpaths)/sizeof(*Test_makerpaths); i++)
+    {
+        tp = &Test_makerpaths[i];
+        for (j=0; tp->path[j]!=LP_MAKER_DONE; j++)
+        {
+            state = LP_maker_next(tp->path[j],0,tp->makerfirst,tp->internal);
+            TEST_CHECK(state == tp->path[j+1],tp->name);
+            state = LP_maker_next(tp->path[j],-1,tp->makerfirst,tp->internal);
+            TEST_CHECK(state == LP_MAKER_FAILED,tp->name);
+            if ( tp->internal != 0 )
+                TEST_CHECK(tp->path[j] != LP_MAKER_WAITFEE && tp->path[j] != LP_MAKER_FEECONFIRM,tp->name);
+        }
+        TEST_CHECK(LP_maker_next(LP_MAKER_DONE,0,tp->makerfirst,tp->internal) == LP_MAKER_DONE,tp->name);
+        TEST_CHECK(LP_maker_next(LP_MAKER_FAILED,0,tp->makerfirst,tp->internal) == LP_MAKER_FAILED,tp->name);
+    }
+}
+
+void test_takerpaths(void)
+{
+    struct test_takerpath *tp; int32_t i,j; enum LP_takerstate state;
+    for (i=0; i<sizeof(Test_takerpaths)/sizeof(*Test_takerpaths); i++)
+    {
+        tp = &Test_takerpaths[i];
+        for (j=0; tp->path[j]!=LP_TAKER_DONE; j++)
+        {
+            state = LP_taker_next(tp->path[j],0,tp->makerfirst,tp->internal);
+            TEST_CHECK(state == tp->path[j+1],tp->name);
+            state = LP_taker_next(tp->path[j],-1,tp->makerfirst,tp->internal);
+            TEST_CHECK(state == LP_TAKER_FAILED,tp->name);
+            if ( tp->internal != 0 )
+                TEST_CHECK(tp->path[j] != LP_TAKER_SENDFEE,tp->name);
+        }
+        TEST_CHECK(LP_taker_next(LP_TAKER_DONE,0,tp->makerfirst,tp->internal) == LP_TAKER_DONE,tp->name);
+        TEST_CHECK(LP_taker_next(LP_TAKER_FAILED,0,tp->makerfirst,tp->internal) == LP_TAKER_FAILED,tp->name);
+    }
+}
+
+int main(int argc,const char *argv[])
+{
+    test_makerpaths();
+    test_takerpaths();
+    printf("%d of %d swap state checks failed\n",Num_failed,Num_tests);
+    return(Num_failed != 0);
+}
@@ -201,30 +201,134 @@ int bech32_convert_bits(uint8_t *out,int32_t *outlen,int outbits,const uint8_t *
     return 1;
 }
 
-/*int segwit_addr_encode(char *output, const char *hrp, int witver, const uint8_t *witprog, size_t witprog_len) {
-    uint8_t data[65];
-    size_t datalen = 0;
-    if (witver > 16) return 0;
-    if (witver == 0 && witprog_len != 20 && witprog_len != 32) return 0;
-    if (witprog_len < 2 || witprog_len > 40) return 0;
+// BIP173 bech32 for segwit addresses, the bech32_ functions above are the cashaddr variant
+uint32_t segwit_polymod_step(uint32_t pre)
+{
+    uint8_t b = pre >> 25;
+    return ((pre & 0x1FFFFFF) << 5) ^
+        (-((b >> 0) & 1) & 0x3b6a57b2UL) ^
+        (-((b >> 1) & 1) & 0x26508e6dUL) ^
+        (-((b >> 2) & 1) & 0x1ea119faUL) ^
+        (-((b >> 3) & 1) & 0x3d4233ddUL) ^
+        (-((b >> 4) & 1) & 0x2a1462b3UL);
+}
+
+int segwit_bech32_encode(char *output,const char *hrp,const uint8_t *data,int32_t data_len)
+{
+    uint32_t chk = 1; int32_t i;
+    for (i=0; hrp[i]!=0; i++)
+    {
+        if ( hrp[i] < 33 || hrp[i] > 126 || (hrp[i] >= 'A' && hrp[i] <= 'Z') )
+            return 0;
+        chk = segwit_polymod_step(chk) ^ (hrp[i] >> 5);
+    }
+    if ( i + 7 + data_len > 90 )
+        return 0;
+    chk = segwit_polymod_step(chk);
+    while ( *hrp != 0 )
+    {
+        chk = segwit_polymod_step(chk) ^ (*hrp & 0x1f);
+        *(output++) = *(hrp++);
+    }
+    *(output++) = '1';
+    for (i=0; i<data_len; i++)
+    {
+        if ( *data >> 5 )
+            return 0;
+        chk = segwit_polymod_step(chk) ^ (*data);
+        *(output++) = charset[*(data++)];
+    }
+    for (i=0; i<6; i++)
+        chk = segwit_polymod_step(chk);
+    chk ^= 1;
+    for (i=0; i<6; i++)
+        *(output++) = charset[(chk >> ((5 - i) * 5)) & 0x1f];
+    *output = 0;
+    return 1;
+}
+
+int segwit_bech32_decode(char *hrp,uint8_t *data,int32_t *data_len,const char *input)
+{
+    uint32_t chk = 1; int32_t i,v,hrp_len,input_len = (int32_t)strlen(input),have_lower = 0,have_upper = 0;
+    if ( input_len < 8 || input_len > 90 )
+        return 0;
+    *data_len = 0;
+    while ( *data_len < input_len && input[(input_len - 1) - *data_len] != '1' )
+        ++(*data_len);
+    hrp_len = input_len - (1 + *data_len);
+    if ( hrp_len < 1 || *data_len < 6 )
+        return 0;
+    *(data_len) -= 6;
+    for (i=0; i<hrp_len; i++)
+    {
+        int ch = input[i];
+        if ( ch < 33 || ch > 126 )
+            return 0;
+        if ( ch >= 'a' && ch <= 'z' )
+            have_lower = 1;
+        else if ( ch >= 'A' && ch <= 'Z' )
+        {
+            have_upper = 1;
+            ch = (ch - 'A') + 'a';
+        }
+        hrp[i] = ch;
+        chk = segwit_polymod_step(chk) ^ (ch >> 5);
+    }
+    hrp[i] = 0;
+    chk = segwit_polymod_step(chk);
+    for (i=0; i<hrp_len; i++)
+        chk = segwit_polymod_step(chk) ^ (hrp[i] & 0x1f);
+    for (++i; i<input_len; i++)
+    {
+        v = (input[i] & 0x80) ? -1 : charset_rev[(int)input[i]];
+        if ( input[i] >= 'a' && input[i] <= 'z' )
+            have_lower = 1;
+        else if ( input[i] >= 'A' && input[i] <= 'Z' )
+            have_upper = 1;
+        if ( v == -1 )
+            return 0;
+        chk = segwit_polymod_step(chk) ^ v;
+        if ( i + 6 < input_len )
+            data[i - (1 + hrp_len)] = v;
+    }
+    if ( have_lower && have_upper )
+        return 0;
+    return chk == 1;
+}
+
+int segwit_addr_encode(char *output,const char *hrp,int witver,const uint8_t *witprog,int32_t witprog_len)
+{
+    uint8_t data[65]; int32_t datalen = 0;
+    if ( witver > 16 )
+        return 0;
+    if ( witver == 0 && witprog_len != 20 && witprog_len != 32 )
+        return 0;
+    if ( witprog_len < 2 || witprog_len > 40 )
+        return 0;
     data[0] = witver;
-    convert_bits(data + 1, &datalen, 5, witprog, witprog_len, 8, 1);
+    bech32_convert_bits(data + 1,&datalen,5,witprog,witprog_len,8,1);
     ++datalen;
-    return bech32_encode(output, hrp, data, datalen);
+    return segwit_bech32_encode(output,hrp,data,datalen);
 }
 
-int segwit_addr_decode(int* witver, uint8_t* witdata, size_t* witdata_len, const char* hrp, const char* addr) {
-    uint8_t data[84];
-    char hrp_actual[84];
-    size_t data_len;
-    if (!bech32_decode(hrp_actual, data, &data_len, addr)) return 0;
-    if (data_len == 0 || data_len > 65) return 0;
-    if (strncmp(hrp, hrp_actual, 84) != 0) return 0;
-    if (data[0] > 16) return 0;
+int segwit_addr_decode(int *witver,uint8_t *witdata,int32_t *witdata_len,const char *hrp,const char *addr)
+{
+    uint8_t data[84]; char hrp_actual[84]; int32_t data_len;
+    if ( segwit_bech32_decode(hrp_actual,data,&data_len,addr) == 0 )
+        return 0;
+    if ( data_len == 0 || data_len > 65 )
+        return 0;
+    if ( strncmp(hrp,hrp_actual,84) != 0 )
+        return 0;
+    if ( data[0] > 16 )
+        return 0;
     *witdata_len = 0;
-    if (!convert_bits(witdata, witdata_len, 8, data + 1, data_len - 1, 5, 0)) return 0;
-    if (*witdata_len < 2 || *witdata_len > 40) return 0;
-    if (data[0] == 0 && *witdata_len != 20 && *witdata_len != 32) return 0;
+    if ( bech32_convert_bits(witdata,witdata_len,8,data + 1,data_len - 1,5,0) == 0 )
+        return 0;
+    if ( *witdata_len < 2 || *witdata_len > 40 )
+        return 0;
+    if ( data[0] == 0 && *witdata_len != 20 && *witdata_len != 32 )
+        return 0;
     *witver = data[0];
     return 1;
-}*/
+}